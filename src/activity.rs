@@ -0,0 +1,232 @@
+//! Keeping track of when the user is at the computer, for the get_uptime_and_idle tool. A thread
+//! checks how long it's been since the last key press or mouse movement, and remembers the
+//! stretches of time the user was active, so they can ask how long they've been working today.
+
+use chrono::{DateTime, Local, TimeDelta};
+use std::{
+    sync::{Mutex, OnceLock},
+    thread,
+    time::Duration,
+};
+use sysinfo::System;
+use tracing::warn;
+
+/// How often the idle time is checked.
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+/// Being idle for this long counts as a break.
+const BREAK_AFTER: Duration = Duration::from_secs(5 * 60);
+
+/// A stretch of time the user was at the computer, from their first input to their last.
+struct Period {
+    start: DateTime<Local>,
+    end: DateTime<Local>,
+}
+
+static PERIODS: Mutex<Vec<Period>> = Mutex::new(Vec::new());
+/// When tracking started, since time before that isn't counted.
+static TRACKING_SINCE: OnceLock<DateTime<Local>> = OnceLock::new();
+
+/// How long it's been since the user last pressed a key or moved the mouse.
+pub fn idle_time() -> Result<Duration, anyhow::Error> {
+    #[cfg(target_os = "linux")]
+    return x11::idle_time();
+
+    #[cfg(windows)]
+    return windows::idle_time();
+
+    #[cfg(target_os = "macos")]
+    return macos::idle_time();
+
+    #[allow(unreachable_code)]
+    Err(anyhow::anyhow!("Idle time can't be read on this system"))
+}
+
+/// Starts remembering when the user is active. Does nothing if it's already started, or if idle
+/// time can't be read on this system.
+pub fn start_tracking() {
+    if TRACKING_SINCE.get().is_some() {
+        return;
+    }
+    if let Err(err) = idle_time() {
+        warn!("Not tracking activity: {:#}", err);
+        return;
+    }
+    if TRACKING_SINCE.set(Local::now()).is_ok() {
+        thread::spawn(|| loop {
+            if let Ok(idle) = idle_time() {
+                record(idle);
+            }
+            thread::sleep(POLL_INTERVAL);
+        });
+    }
+}
+
+fn record(idle: Duration) {
+    let now = Local::now();
+    let last_input = now - TimeDelta::from_std(idle).unwrap_or_default();
+    let break_after = TimeDelta::from_std(BREAK_AFTER).unwrap();
+    let mut periods = PERIODS.lock().unwrap();
+    match periods.last_mut() {
+        Some(period) if last_input - period.end < break_after => {
+            period.end = period.end.max(last_input)
+        }
+        _ => periods.push(Period {
+            start: last_input,
+            end: last_input,
+        }),
+    }
+    // Only today and yesterday are ever asked about.
+    let two_days_ago = now - TimeDelta::days(2);
+    periods.retain(|period| period.end > two_days_ago);
+}
+
+/// The start of today.
+fn midnight() -> DateTime<Local> {
+    Local::now()
+        .date_naive()
+        .and_hms_opt(0, 0, 0)
+        .unwrap()
+        .and_local_timezone(Local)
+        .earliest()
+        .unwrap_or_else(Local::now)
+}
+
+fn format_duration(duration: TimeDelta) -> String {
+    let minutes = duration.num_minutes().max(0);
+    match (minutes / 60, minutes % 60) {
+        (0, minutes) => format!("{} minutes", minutes),
+        (hours, 0) => format!("{} hours", hours),
+        (hours, minutes) => format!("{} hours {} minutes", hours, minutes),
+    }
+}
+
+/// Describes how long the computer has been on, how long the user has been idle, and how long
+/// they've been at the computer today.
+pub fn describe() -> String {
+    let mut info = String::new();
+    let uptime = TimeDelta::seconds(System::uptime() as i64);
+    let booted = Local::now() - uptime;
+    info.push_str(&format!(
+        "The computer has been on for {}, since {}.\n",
+        format_duration(uptime),
+        booted.format("%A %-I:%M %p")
+    ));
+
+    let idle = match idle_time() {
+        Ok(idle) => idle,
+        Err(err) => {
+            info.push_str(&format!(
+                "How long the user has been idle is unknown: {:#}\n",
+                err
+            ));
+            return info;
+        }
+    };
+    let idle_delta = TimeDelta::from_std(idle).unwrap_or_default();
+    info.push_str(&format!(
+        "The user last used the keyboard or mouse {} ago.\n",
+        humantime::format_duration(Duration::from_secs(idle.as_secs()))
+    ));
+
+    record(idle);
+    let periods = PERIODS.lock().unwrap();
+    let midnight = midnight();
+    let today: Vec<(DateTime<Local>, DateTime<Local>)> = periods
+        .iter()
+        .filter(|period| period.end > midnight)
+        .map(|period| (period.start.max(midnight), period.end))
+        .collect();
+    let Some(&(first, _)) = today.first() else {
+        info.push_str("The user hasn't been at the computer today.\n");
+        return info;
+    };
+    let active: TimeDelta = today.iter().map(|(start, end)| *end - *start).sum();
+    info.push_str(&format!(
+        "Today the user has been at the computer for {} in total, starting at {}, with {} breaks of at least {} minutes.",
+        format_duration(active),
+        first.format("%-I:%M %p"),
+        today.len() - 1,
+        BREAK_AFTER.as_secs() / 60
+    ));
+    if idle < BREAK_AFTER {
+        let (start, _) = today.last().unwrap();
+        info.push_str(&format!(
+            " They've been working for {} without a break.",
+            format_duration(Local::now() - idle_delta - *start)
+        ));
+    }
+    info.push('\n');
+    if let Some(&tracking_since) = TRACKING_SINCE.get().filter(|since| **since > midnight) {
+        info.push_str(&format!(
+            "Time before {}, when the assistant started, isn't counted.\n",
+            tracking_since.format("%-I:%M %p")
+        ));
+    }
+    info
+}
+
+#[cfg(target_os = "linux")]
+mod x11 {
+    use anyhow::Context;
+    use std::time::Duration;
+    use x11rb::{connection::Connection, protocol::screensaver};
+
+    /// Asks the X server's screen saver extension, which counts time since the last input.
+    pub fn idle_time() -> Result<Duration, anyhow::Error> {
+        let (conn, screen_num) =
+            x11rb::connect(None).context("Failed to connect to the X server")?;
+        let root = conn.setup().roots[screen_num].root;
+        let info = screensaver::query_info(&conn, root)?
+            .reply()
+            .context("The X server doesn't support the screen saver extension")?;
+        Ok(Duration::from_millis(info.ms_since_user_input.into()))
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use anyhow::bail;
+    use std::time::Duration;
+    use windows_sys::Win32::{
+        System::SystemInformation::GetTickCount,
+        UI::Input::KeyboardAndMouse::{GetLastInputInfo, LASTINPUTINFO},
+    };
+
+    pub fn idle_time() -> Result<Duration, anyhow::Error> {
+        let mut info = LASTINPUTINFO {
+            cbSize: std::mem::size_of::<LASTINPUTINFO>() as u32,
+            dwTime: 0,
+        };
+        if unsafe { GetLastInputInfo(&mut info) } == 0 {
+            bail!("Failed to get the time of the last input");
+        }
+        // Both are milliseconds since boot, which wrap around every 49 days.
+        let idle = unsafe { GetTickCount() }.wrapping_sub(info.dwTime);
+        Ok(Duration::from_millis(idle.into()))
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod macos {
+    use anyhow::{bail, Context};
+    use std::{process::Command, time::Duration};
+
+    /// Reads `HIDIdleTime`, in nanoseconds, from the IOKit registry.
+    pub fn idle_time() -> Result<Duration, anyhow::Error> {
+        let output = Command::new("ioreg")
+            .args(["-c", "IOHIDSystem"])
+            .output()
+            .context("Failed to run ioreg")?;
+        if !output.status.success() {
+            bail!("ioreg failed");
+        }
+        let nanoseconds: u64 = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .find_map(|line| {
+                let (_, value) = line.split_once("\"HIDIdleTime\" = ")?;
+                value.trim().parse().ok()
+            })
+            .context("ioreg didn't give the idle time")?;
+        Ok(Duration::from_nanos(nanoseconds))
+    }
+}
@@ -0,0 +1,437 @@
+//! Finding installed applications by name and launching them.
+//! Linux reads the .desktop files of the XDG data dirs, Windows reads the Start Menu shortcuts and the
+//! registry's App Paths, and macOS looks in the Applications folders.
+
+use anyhow::{bail, Context};
+use std::{
+    path::PathBuf,
+    process::{Command, Stdio},
+    thread,
+};
+
+/// An installed application.
+#[derive(Debug, Clone)]
+pub struct App {
+    pub name: String,
+    /// Other words the app can be found by, like "web browser".
+    keywords: Vec<String>,
+    launch: Launch,
+}
+
+#[derive(Debug, Clone)]
+enum Launch {
+    /// Runs a shell command line.
+    #[allow(dead_code)]
+    Shell(String),
+    /// Opens a file, like a shortcut or an app bundle, the way the file browser would.
+    #[allow(dead_code)]
+    Open(PathBuf),
+}
+
+impl App {
+    fn launch(&self) -> Result<(), anyhow::Error> {
+        match &self.launch {
+            Launch::Shell(command_line) => {
+                let mut child = Command::new("sh")
+                    .args(["-c", command_line])
+                    .stdin(Stdio::null())
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .spawn()
+                    .with_context(|| format!("Failed to start {}", self.name))?;
+                // Waited on so it doesn't linger as a zombie once it exits.
+                thread::spawn(move || child.wait());
+                Ok(())
+            }
+            Launch::Open(path) => {
+                open::that_detached(path).with_context(|| format!("Failed to open {}", self.name))
+            }
+        }
+    }
+}
+
+/// Every application that can be launched, as far as can be told.
+pub fn installed() -> Vec<App> {
+    #[cfg(target_os = "linux")]
+    return linux::installed();
+
+    #[cfg(windows)]
+    return windows::installed();
+
+    #[cfg(target_os = "macos")]
+    return macos::installed();
+
+    #[allow(unreachable_code)]
+    Vec::new()
+}
+
+/// The score of a name that has what was asked for somewhere in it.
+pub const CONTAINS_SCORE: u32 = 60;
+/// How many of the closest names are suggested when nothing matches.
+const CLOSEST_COUNT: usize = 3;
+
+/// How well `name` matches what was asked for, which should be lowercase.
+/// Higher is better, and None isn't a match.
+pub fn score(name: &str, query: &str) -> Option<u32> {
+    let name = name.to_lowercase();
+    if name == query {
+        return Some(100);
+    }
+    if name.starts_with(query) {
+        return Some(80);
+    }
+    if name.split_whitespace().any(|word| word.starts_with(query)) {
+        return Some(70);
+    }
+    if name.contains(query) {
+        return Some(CONTAINS_SCORE);
+    }
+    if query.split_whitespace().all(|word| name.contains(word)) {
+        return Some(50);
+    }
+    // The first letter of each word, like "vsc" for "Visual Studio Code".
+    let initials: String = name
+        .split_whitespace()
+        .filter_map(|word| word.chars().next())
+        .collect();
+    let is_initials = initials.chars().count() > 1 && initials == query.replace(' ', "");
+    is_initials.then_some(40)
+}
+
+/// Up to a few of `names` that are spelled most like `query`, closest first, to suggest when
+/// nothing matches.
+pub fn closest<'a>(names: impl IntoIterator<Item = &'a str>, query: &str) -> Vec<&'a str> {
+    let query = query.trim().to_lowercase();
+    let mut names: Vec<(f64, &str)> = names
+        .into_iter()
+        .map(|name| (strsim::jaro_winkler(&name.to_lowercase(), &query), name))
+        .collect();
+    names.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+    let mut closest: Vec<&str> = Vec::new();
+    for (_, name) in names {
+        if !closest.contains(&name) {
+            closest.push(name);
+        }
+        if closest.len() == CLOSEST_COUNT {
+            break;
+        }
+    }
+    closest
+}
+
+/// Finds the installed application that best matches `query`, out of `apps`.
+fn find<'a>(apps: &'a [App], query: &str) -> Option<&'a App> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return None;
+    }
+    apps.iter()
+        .filter_map(|app| {
+            let by_name = score(&app.name, &query);
+            // Matching a keyword counts for less than matching the name.
+            let by_keyword = app
+                .keywords
+                .iter()
+                .filter_map(|keyword| score(keyword, &query))
+                .max()
+                .map(|score| score / 2);
+            let score = by_name.max(by_keyword)?;
+            Some((score, app))
+        })
+        // The shortest name wins a tie, so "Firefox" beats "Firefox Profile Manager".
+        .max_by(|(a_score, a), (b_score, b)| {
+            a_score.cmp(b_score).then(b.name.len().cmp(&a.name.len()))
+        })
+        .map(|(_, app)| app)
+}
+
+/// Launches the installed application that best matches `query`, and returns its name.
+pub fn open(query: &str) -> Result<String, anyhow::Error> {
+    let apps = installed();
+    let Some(app) = find(&apps, query) else {
+        let closest = closest(apps.iter().map(|app| app.name.as_str()), query);
+        if closest.is_empty() {
+            bail!("No installed application matching \"{}\" was found", query);
+        }
+        bail!(
+            "No installed application matching \"{}\" was found. The closest are: {}",
+            query,
+            closest.join(", ")
+        );
+    };
+    app.launch()?;
+    Ok(app.name.clone())
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::{collections::HashSet, env, fs, path::PathBuf};
+
+    use super::{App, Launch};
+
+    /// The folders applications are in, most important first.
+    fn application_dirs() -> Vec<PathBuf> {
+        let mut data_dirs: Vec<PathBuf> = Vec::new();
+        data_dirs.extend(dirs::data_dir());
+        match env::var_os("XDG_DATA_DIRS").filter(|dirs| !dirs.is_empty()) {
+            Some(dirs) => data_dirs.extend(env::split_paths(&dirs)),
+            None => data_dirs.extend(["/usr/local/share", "/usr/share"].map(PathBuf::from)),
+        }
+        // Flatpak apps, in case XDG_DATA_DIRS wasn't set up to include them.
+        data_dirs.extend(dirs::data_dir().map(|dir| dir.join("flatpak/exports/share")));
+        data_dirs.push(PathBuf::from("/var/lib/flatpak/exports/share"));
+        data_dirs
+            .into_iter()
+            .map(|dir| dir.join("applications"))
+            .collect()
+    }
+
+    pub fn installed() -> Vec<App> {
+        let mut apps = Vec::new();
+        // The same desktop file in a more important folder hides the others.
+        let mut seen = HashSet::new();
+        for dir in application_dirs() {
+            let mut pending = vec![dir.clone()];
+            while let Some(current) = pending.pop() {
+                let Ok(entries) = fs::read_dir(&current) else {
+                    continue;
+                };
+                for entry in entries.flatten() {
+                    let path = entry.path();
+                    if path.is_dir() {
+                        pending.push(path);
+                        continue;
+                    }
+                    if path
+                        .extension()
+                        .is_none_or(|extension| extension != "desktop")
+                    {
+                        continue;
+                    }
+                    // Desktop file IDs use dashes for subfolders.
+                    let id = path
+                        .strip_prefix(&dir)
+                        .unwrap_or(&path)
+                        .to_string_lossy()
+                        .replace('/', "-");
+                    if !seen.insert(id) {
+                        continue;
+                    }
+                    if let Some(app) = fs::read_to_string(&path)
+                        .ok()
+                        .and_then(|text| parse_desktop_file(&text))
+                    {
+                        apps.push(app);
+                    }
+                }
+            }
+        }
+        apps
+    }
+
+    /// Reads the app a desktop file describes, if it's one that's shown in menus.
+    fn parse_desktop_file(text: &str) -> Option<App> {
+        let mut in_entry = false;
+        let mut name = None;
+        let mut exec = None;
+        let mut keywords = Vec::new();
+        for line in text.lines().map(str::trim) {
+            if line.starts_with('[') {
+                in_entry = line == "[Desktop Entry]";
+                continue;
+            }
+            if !in_entry {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            match (key.trim(), value.trim()) {
+                ("Type", kind) if kind != "Application" => return None,
+                ("NoDisplay" | "Hidden", "true") => return None,
+                // Terminal apps would need a terminal to be picked to run in.
+                ("Terminal", "true") => return None,
+                ("Name", value) => name = Some(value.to_string()),
+                ("Exec", value) => exec = Some(value.to_string()),
+                ("GenericName", value) => keywords.push(value.to_string()),
+                ("Keywords", value) => keywords.extend(
+                    value
+                        .split(';')
+                        .filter(|keyword| !keyword.is_empty())
+                        .map(str::to_string),
+                ),
+                _ => {}
+            }
+        }
+        Some(App {
+            name: name?,
+            keywords,
+            launch: Launch::Shell(strip_field_codes(&exec?)),
+        })
+    }
+
+    /// Removes the placeholders for files and URLs from an Exec line, since none are passed.
+    fn strip_field_codes(exec: &str) -> String {
+        let mut command_line = String::new();
+        let mut chars = exec.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                command_line.push(c);
+                continue;
+            }
+            if let Some('%') = chars.next() {
+                command_line.push('%');
+            }
+        }
+        command_line.trim().to_string()
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use std::{env, fs, path::PathBuf, process::Command};
+
+    use super::{App, Launch};
+
+    const APP_PATHS_KEY: &str = r"HKLM\SOFTWARE\Microsoft\Windows\CurrentVersion\App Paths";
+
+    pub fn installed() -> Vec<App> {
+        let mut apps = Vec::new();
+        let start_menus = ["APPDATA", "PROGRAMDATA"].into_iter().filter_map(|var| {
+            Some(PathBuf::from(env::var_os(var)?).join(r"Microsoft\Windows\Start Menu\Programs"))
+        });
+        for start_menu in start_menus {
+            let mut pending = vec![start_menu];
+            while let Some(current) = pending.pop() {
+                let Ok(entries) = fs::read_dir(&current) else {
+                    continue;
+                };
+                for entry in entries.flatten() {
+                    let path = entry.path();
+                    if path.is_dir() {
+                        pending.push(path);
+                        continue;
+                    }
+                    if path.extension().is_none_or(|extension| extension != "lnk") {
+                        continue;
+                    }
+                    let Some(name) = path
+                        .file_stem()
+                        .map(|stem| stem.to_string_lossy().into_owned())
+                    else {
+                        continue;
+                    };
+                    // Start Menu folders are full of these next to the apps themselves.
+                    let lowercase = name.to_lowercase();
+                    if lowercase.contains("uninstall") || lowercase.contains("readme") {
+                        continue;
+                    }
+                    apps.push(App {
+                        name,
+                        keywords: Vec::new(),
+                        launch: Launch::Open(path),
+                    });
+                }
+            }
+        }
+        apps.extend(app_paths());
+        apps
+    }
+
+    /// The programs registered in App Paths, which Windows can start by their file name alone.
+    fn app_paths() -> Vec<App> {
+        let Ok(output) = Command::new("reg").args(["query", APP_PATHS_KEY]).output() else {
+            return Vec::new();
+        };
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| {
+                let file_name = line.trim().rsplit('\\').next()?;
+                let name = file_name
+                    .strip_suffix(".exe")
+                    .or(file_name.strip_suffix(".EXE"))?;
+                Some(App {
+                    name: name.to_string(),
+                    keywords: Vec::new(),
+                    launch: Launch::Open(PathBuf::from(file_name)),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod macos {
+    use std::{fs, path::PathBuf};
+
+    use super::{App, Launch};
+
+    pub fn installed() -> Vec<App> {
+        let mut dirs = vec![
+            PathBuf::from("/Applications"),
+            PathBuf::from("/Applications/Utilities"),
+            PathBuf::from("/System/Applications"),
+            PathBuf::from("/System/Applications/Utilities"),
+        ];
+        dirs.extend(dirs::home_dir().map(|home| home.join("Applications")));
+
+        dirs.into_iter()
+            .filter_map(|dir| fs::read_dir(dir).ok())
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|extension| extension == "app"))
+            .filter_map(|path| {
+                Some(App {
+                    name: path.file_stem()?.to_string_lossy().into_owned(),
+                    keywords: Vec::new(),
+                    launch: Launch::Shell(format!(
+                        "open -a '{}'",
+                        path.display().to_string().replace('\'', r"'\''")
+                    )),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app(name: &str) -> App {
+        App {
+            name: name.to_string(),
+            keywords: Vec::new(),
+            launch: Launch::Open(PathBuf::from(name)),
+        }
+    }
+
+    #[test]
+    fn only_close_names_match() {
+        assert_eq!(score("Firefox", "firefox"), Some(100));
+        assert_eq!(score("Visual Studio Code", "studio"), Some(70));
+        assert_eq!(score("Visual Studio Code", "vsc"), Some(40));
+        // Letters scattered through a name aren't a match.
+        assert_eq!(score("Thunderbird", "tb"), None);
+        assert_eq!(score("LibreOffice Calc", "lo"), None);
+
+        let apps = [
+            app("Firefox"),
+            app("Firefox Profile Manager"),
+            app("Thunderbird"),
+        ];
+        assert_eq!(find(&apps, "firefox").unwrap().name, "Firefox");
+        assert!(find(&apps, "fx").is_none());
+        assert!(find(&apps, "  ").is_none());
+    }
+
+    #[test]
+    fn closest_names_are_suggested() {
+        let names = ["Firefox", "Thunderbird", "Files", "Firefox"];
+        assert_eq!(
+            closest(names, "firefix"),
+            ["Firefox", "Files", "Thunderbird"]
+        );
+    }
+}
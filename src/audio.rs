@@ -0,0 +1,1230 @@
+//! One shared audio output for every sound the assistant plays.
+//!
+//! Sounds are played through sinks on named channels. Each channel has its own volume,
+//! and ducking lowers every channel except speech. While a channel preempts others,
+//! such as an alarm ringing, the channels it preempts are paused until it's done.
+//! Each channel can play on its own output device, and the choice is remembered across restarts.
+//! When output devices are added, removed, or the default device changes, outputs are reopened
+//! and sounds still playing carry on from where they were on the right device, converted to its
+//! sample rate and channel count. With no output device at all, such as in a headless session,
+//! sounds are played to nowhere until a device can be opened.
+//!
+//! Setting `QUICK_ASSISTANT_AUDIO=mock` replaces every sink with a mock that plays sounds
+//! instantly, so code that plays audio can run without sound hardware. Tests always use the mock.
+
+use anyhow::Context;
+use cpal::traits::{DeviceTrait, HostTrait};
+use rodio::{
+    cpal::FromSample,
+    source::{ChannelVolume, UniformSourceIterator},
+    OutputStream, OutputStreamHandle, Sample, Sink, Source,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    fs, io,
+    path::PathBuf,
+    sync::{Arc, Condvar, LazyLock, Mutex, MutexGuard, Once, Weak},
+    thread,
+    time::{Duration, Instant},
+};
+use tracing::{debug, info, warn};
+
+use crate::CACHE_DIR;
+
+// How long to wait before trying to open an output device again after failing to open any.
+const NULL_OUTPUT_RETRY_INTERVAL: Duration = Duration::from_secs(10);
+
+// How many frames of a sound are handed to its output at a time. What an output has been handed
+// but not played yet is lost when the sound moves to another device, so this is kept short.
+const RESUMABLE_CHUNK_FRAMES: usize = 1024;
+
+// How often the output devices are checked for changes on platforms without device change events.
+#[cfg(not(any(target_os = "linux", windows)))]
+const DEVICE_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+// The output device picked for each channel. Channels left out play on the default device.
+static OUTPUT_DEVICES_PATH: LazyLock<PathBuf> =
+    LazyLock::new(|| CACHE_DIR.join("output_devices.toml"));
+
+/// A queue of sounds playing on a device. Sinks come from `new_sink`.
+pub trait AudioSink: Send + Sync {
+    /// Queues a sound. `append` takes any source.
+    fn append_boxed(&self, source: Box<dyn Source<Item = f32> + Send>);
+    fn play(&self);
+    fn pause(&self);
+    fn is_paused(&self) -> bool;
+    /// Stops and throws away every queued sound.
+    fn stop(&self);
+    /// Returns true once every queued sound has played.
+    fn empty(&self) -> bool;
+    /// Returns the number of queued sounds, including the one playing.
+    fn len(&self) -> usize;
+    fn set_volume(&self, volume: f32);
+    /// Blocks until every queued sound has played or the sink is stopped.
+    fn sleep_until_end(&self);
+}
+
+impl dyn AudioSink {
+    /// Queues a sound.
+    pub fn append<S>(&self, source: S)
+    where
+        S: Source + Send + 'static,
+        S::Item: Sample,
+        f32: FromSample<S::Item>,
+    {
+        self.append_boxed(Box::new(source.convert_samples()));
+    }
+}
+
+/// The sample rate and channel count an output device plays at.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct OutputFormat {
+    channels: u16,
+    sample_rate: u32,
+}
+
+/// A sound that can carry on from where it got to on another output device.
+struct Resumable {
+    source: Box<dyn Source<Item = f32> + Send>,
+    // Bumped when the sound moves, which ends the part of it still queued on the old device.
+    epoch: u64,
+}
+
+/// The sounds queued on a sink that haven't finished yet.
+#[derive(Default)]
+struct Sounds {
+    queued: Mutex<Vec<Arc<Mutex<Resumable>>>>,
+    // Notified whenever a sound finishes or is stopped.
+    ended: Condvar,
+}
+
+impl Sounds {
+    fn finish(&self, sound: &Arc<Mutex<Resumable>>) {
+        self.queued
+            .lock()
+            .unwrap()
+            .retain(|queued| !Arc::ptr_eq(queued, sound));
+        self.ended.notify_all();
+    }
+}
+
+/// Plays a `Resumable` sound on one sink. The sound is read a chunk at a time once it starts
+/// playing, so if the sink's device goes away, the rest of it is still there to be played on
+/// another one.
+struct ResumableSource {
+    sound: Arc<Mutex<Resumable>>,
+    sounds: Arc<Sounds>,
+    epoch: u64,
+    started: bool,
+    chunk: std::collections::VecDeque<f32>,
+    channels: u16,
+    sample_rate: u32,
+}
+
+impl Resumable {
+    /// How many samples the next chunk has. A chunk never spans a change of the sound's format.
+    fn chunk_len(&self) -> usize {
+        let chunk_len = RESUMABLE_CHUNK_FRAMES * self.source.channels().max(1) as usize;
+        match self.source.current_frame_len() {
+            Some(frame_len) if frame_len > 0 => frame_len.min(chunk_len),
+            _ => chunk_len,
+        }
+    }
+}
+
+impl ResumableSource {
+    fn new(sound: Arc<Mutex<Resumable>>, sounds: Arc<Sounds>) -> Self {
+        let (epoch, channels, sample_rate) = {
+            let sound = sound.lock().unwrap();
+            (
+                sound.epoch,
+                sound.source.channels(),
+                sound.source.sample_rate(),
+            )
+        };
+        Self {
+            sound,
+            sounds,
+            epoch,
+            started: false,
+            chunk: Default::default(),
+            channels,
+            sample_rate,
+        }
+    }
+
+    fn read_chunk(&mut self) {
+        let mut sound = self.sound.lock().unwrap();
+        // The sound has moved to another sink.
+        if sound.epoch != self.epoch {
+            return;
+        }
+        self.channels = sound.source.channels();
+        self.sample_rate = sound.source.sample_rate();
+        let len = sound.chunk_len();
+        self.chunk.extend(sound.source.by_ref().take(len));
+        if self.chunk.is_empty() {
+            drop(sound);
+            self.sounds.finish(&self.sound);
+        }
+    }
+}
+
+impl Iterator for ResumableSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if !self.started {
+            self.started = true;
+            self.read_chunk();
+        }
+        let sample = self.chunk.pop_front()?;
+        // The next chunk is read right away, so its format is known before it's played.
+        if self.chunk.is_empty() {
+            self.read_chunk();
+        }
+        Some(sample)
+    }
+}
+
+impl Source for ResumableSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        match self.started {
+            true => Some(self.chunk.len()),
+            false => Some(self.sound.lock().unwrap().chunk_len()),
+        }
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// A sink on an output device. When outputs are reopened, its sounds move to a sink on the
+/// channel's device, from where they'd got to.
+struct DeviceSink {
+    channel: Channel,
+    // Locked before `sounds.queued`, so a sound is never queued on a sink that's being replaced.
+    sink: Mutex<(Sink, Option<OutputFormat>)>,
+    sounds: Arc<Sounds>,
+}
+
+impl DeviceSink {
+    fn new(channel: Channel, sink: Sink, format: Option<OutputFormat>) -> Self {
+        Self {
+            channel,
+            sink: Mutex::new((sink, format)),
+            sounds: Arc::default(),
+        }
+    }
+
+    /// Queues `sound` on `sink`, converted to the device's format when it's known.
+    fn queue(&self, sink: &Sink, format: Option<OutputFormat>, sound: Arc<Mutex<Resumable>>) {
+        let source = ResumableSource::new(sound, self.sounds.clone());
+        match format {
+            Some(format) => sink.append(UniformSourceIterator::<_, f32>::new(
+                source,
+                format.channels,
+                format.sample_rate,
+            )),
+            None => sink.append(source),
+        }
+    }
+
+    /// Carries on playing every unfinished sound on `sink` instead.
+    fn move_to(&self, sink: Sink, format: Option<OutputFormat>) {
+        let mut current = self.sink.lock().unwrap();
+        sink.set_volume(current.0.volume());
+        let paused = current.0.is_paused();
+        sink.pause();
+        for sound in self.sounds.queued.lock().unwrap().iter() {
+            sound.lock().unwrap().epoch += 1;
+            self.queue(&sink, format, sound.clone());
+        }
+        if !paused {
+            sink.play();
+        }
+        // Whatever's left on the old sink is stopped as it's dropped.
+        *current = (sink, format);
+    }
+}
+
+impl AudioSink for DeviceSink {
+    fn append_boxed(&self, source: Box<dyn Source<Item = f32> + Send>) {
+        let sink = self.sink.lock().unwrap();
+        let sound = Arc::new(Mutex::new(Resumable { source, epoch: 0 }));
+        self.sounds.queued.lock().unwrap().push(sound.clone());
+        self.queue(&sink.0, sink.1, sound);
+    }
+
+    fn play(&self) {
+        self.sink.lock().unwrap().0.play();
+    }
+
+    fn pause(&self) {
+        self.sink.lock().unwrap().0.pause();
+    }
+
+    fn is_paused(&self) -> bool {
+        self.sink.lock().unwrap().0.is_paused()
+    }
+
+    fn stop(&self) {
+        let sink = self.sink.lock().unwrap();
+        sink.0.stop();
+        self.sounds.queued.lock().unwrap().clear();
+        self.sounds.ended.notify_all();
+    }
+
+    fn empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn len(&self) -> usize {
+        self.sounds.queued.lock().unwrap().len()
+    }
+
+    fn set_volume(&self, volume: f32) {
+        self.sink.lock().unwrap().0.set_volume(volume);
+    }
+
+    fn sleep_until_end(&self) {
+        let queued = self.sounds.queued.lock().unwrap();
+        let _queued = self
+            .sounds
+            .ended
+            .wait_while(queued, |queued| !queued.is_empty())
+            .unwrap();
+    }
+}
+
+/// A sink that plays every sound as soon as it's queued, without a device. Sounds are still read
+/// to the end, so anything that happens as a sound plays still happens. Sounds queued while it's
+/// paused wait until it plays.
+#[derive(Default)]
+pub struct MockSink {
+    state: Mutex<MockState>,
+    // Notified whenever the queued sounds are played or stopped.
+    ended: Condvar,
+}
+
+#[derive(Default)]
+struct MockState {
+    paused: bool,
+    queued: VecDeque<Box<dyn Source<Item = f32> + Send>>,
+}
+
+impl MockSink {
+    fn play_queued(&self, mut state: MutexGuard<MockState>) {
+        while !state.paused {
+            let Some(source) = state.queued.pop_front() else {
+                break;
+            };
+            source.for_each(drop);
+        }
+        self.ended.notify_all();
+    }
+}
+
+impl AudioSink for MockSink {
+    fn append_boxed(&self, source: Box<dyn Source<Item = f32> + Send>) {
+        let mut state = self.state.lock().unwrap();
+        state.queued.push_back(source);
+        self.play_queued(state);
+    }
+
+    fn play(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.paused = false;
+        self.play_queued(state);
+    }
+
+    fn pause(&self) {
+        self.state.lock().unwrap().paused = true;
+    }
+
+    fn is_paused(&self) -> bool {
+        self.state.lock().unwrap().paused
+    }
+
+    fn stop(&self) {
+        self.state.lock().unwrap().queued.clear();
+        self.ended.notify_all();
+    }
+
+    fn empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn len(&self) -> usize {
+        self.state.lock().unwrap().queued.len()
+    }
+
+    fn set_volume(&self, _volume: f32) {}
+
+    fn sleep_until_end(&self) {
+        let state = self.state.lock().unwrap();
+        let _state = self
+            .ended
+            .wait_while(state, |state| !state.queued.is_empty())
+            .unwrap();
+    }
+}
+
+/// Held by tests that preempt channels or depend on them not being preempted, since preemptions
+/// are shared by every test.
+#[cfg(test)]
+pub static TEST_LOCK: Mutex<()> = Mutex::new(());
+
+// Whether sinks are mocks instead of playing on a device.
+static USE_MOCK_SINKS: LazyLock<bool> = LazyLock::new(|| {
+    cfg!(test) || std::env::var("QUICK_ASSISTANT_AUDIO").is_ok_and(|audio| audio == "mock")
+});
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Channel {
+    /// The AI voice.
+    Speech,
+    /// Alarms and other sounds the user has to notice.
+    Alerts,
+    /// Short sound effects, such as the recording sounds and the thinking tick.
+    Ui,
+}
+
+impl Channel {
+    const ALL: [Channel; 3] = [Channel::Speech, Channel::Alerts, Channel::Ui];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    /// The channels paused while this one is preempting.
+    fn preempts(self) -> &'static [Channel] {
+        match self {
+            // An alarm and the AI voice talking over each other are hard to follow.
+            Channel::Alerts => &[Channel::Speech],
+            Channel::Speech | Channel::Ui => &[],
+        }
+    }
+}
+
+/// An open output device. It's closed when this is dropped.
+struct Output {
+    handle: OutputStreamHandle,
+    format: OutputFormat,
+    // Disconnecting this tells the thread holding the output stream to close it.
+    _close_tx: flume::Sender<()>,
+}
+
+struct AudioState {
+    // Each output device is opened the first time a sink needs it. None is the default device.
+    outputs: HashMap<Option<String>, Output>,
+    // The sinks on output devices, to move to the right device when outputs are reopened.
+    device_sinks: Vec<Weak<DeviceSink>>,
+    devices: [Option<String>; Channel::ALL.len()],
+    volumes: [f32; Channel::ALL.len()],
+    // How loud ducked channels play, while audio is ducked.
+    ducked_volume: Option<f32>,
+    sinks: Vec<(Channel, Weak<dyn AudioSink>)>,
+    // How many preemptions each channel has going.
+    preemptions: [u32; Channel::ALL.len()],
+    // Sinks paused by a preemption, to be played again when it ends.
+    held: Vec<(Channel, Weak<dyn AudioSink>)>,
+    // When no output device could be opened, so sounds are being dropped.
+    null_output_since: Option<Instant>,
+}
+
+impl AudioState {
+    fn sink_volume(&self, channel: Channel) -> f32 {
+        let volume = self.volumes[channel.index()];
+        // A preempting channel isn't ducked, since what it would be ducked for is paused.
+        let preempting = self.preemptions[channel.index()] > 0;
+        match self.ducked_volume {
+            Some(ducked_volume) if channel != Channel::Speech && !preempting => {
+                volume * ducked_volume
+            }
+            _ => volume,
+        }
+    }
+
+    fn is_preempted(&self, channel: Channel) -> bool {
+        Channel::ALL.iter().any(|preempting| {
+            self.preemptions[preempting.index()] > 0 && preempting.preempts().contains(&channel)
+        })
+    }
+
+    fn is_held(&self, sink: &Arc<dyn AudioSink>) -> bool {
+        self.held
+            .iter()
+            .any(|(_, held)| std::ptr::addr_eq(held.as_ptr(), Arc::as_ptr(sink)))
+    }
+
+    fn channel_of(&self, sink: &Arc<dyn AudioSink>) -> Option<Channel> {
+        self.sinks
+            .iter()
+            .find(|(_, registered)| std::ptr::addr_eq(registered.as_ptr(), Arc::as_ptr(sink)))
+            .map(|(channel, _)| *channel)
+    }
+
+    fn update_sinks(&mut self) {
+        let volumes = Channel::ALL.map(|channel| self.sink_volume(channel));
+        let preempted = Channel::ALL.map(|channel| self.is_preempted(channel));
+
+        let mut newly_held = Vec::new();
+        self.sinks.retain(|(channel, weak)| match weak.upgrade() {
+            Some(sink) => {
+                sink.set_volume(volumes[channel.index()]);
+                if preempted[channel.index()] && !sink.is_paused() {
+                    sink.pause();
+                    newly_held.push((*channel, weak.clone()));
+                }
+                true
+            }
+            None => false,
+        });
+        self.held.extend(newly_held);
+
+        self.held.retain(|(channel, weak)| {
+            if preempted[channel.index()] {
+                return true;
+            }
+            if let Some(sink) = weak.upgrade() {
+                sink.play();
+            }
+            false
+        });
+    }
+}
+
+static AUDIO_STATE: LazyLock<Mutex<AudioState>> = LazyLock::new(|| {
+    Mutex::new(AudioState {
+        outputs: HashMap::new(),
+        device_sinks: Vec::new(),
+        devices: load_output_devices(),
+        volumes: [1.0; Channel::ALL.len()],
+        ducked_volume: None,
+        sinks: Vec::new(),
+        preemptions: [0; Channel::ALL.len()],
+        held: Vec::new(),
+        null_output_since: None,
+    })
+});
+
+/// Creates a sink on `channel`. Its volume is managed by the audio service, so sounds that
+/// need their own volume should be amplified instead of setting the sink's volume.
+/// If no output device can be opened, the sink plays to nowhere.
+/// Sounds can have any sample rate and channel count. They're converted to the format of whichever
+/// device the sink ends up on, so switching devices doesn't change their pitch or speed.
+pub fn new_sink(channel: Channel) -> Arc<dyn AudioSink> {
+    let mut state = AUDIO_STATE.lock().unwrap();
+    let sink: Arc<dyn AudioSink> = if *USE_MOCK_SINKS {
+        Arc::new(MockSink::default())
+    } else {
+        let (sink, format) = output_sink(&mut state, channel);
+        let sink = Arc::new(DeviceSink::new(channel, sink, format));
+        state.device_sinks.push(Arc::downgrade(&sink));
+        sink
+    };
+    sink.set_volume(state.sink_volume(channel));
+    state.sinks.push((channel, Arc::downgrade(&sink)));
+    sink
+}
+
+/// Makes a sink on the channel's output device, or a null sink if no device can be opened.
+fn output_sink(state: &mut AudioState, channel: Channel) -> (Sink, Option<OutputFormat>) {
+    let retry_output = state
+        .null_output_since
+        .is_none_or(|since| since.elapsed() >= NULL_OUTPUT_RETRY_INTERVAL);
+    match retry_output.then(|| device_sink(state, channel)) {
+        Some(Ok((sink, format))) => {
+            if state.null_output_since.take().is_some() {
+                info!("An audio output device is available again");
+            }
+            (sink, Some(format))
+        }
+        Some(Err(err)) => {
+            if state.null_output_since.is_none() {
+                warn!(
+                    "{:?}. Sounds won't be heard until an output device is available.",
+                    err
+                );
+            }
+            state.null_output_since = Some(Instant::now());
+            (null_sink(), None)
+        }
+        None => (null_sink(), None),
+    }
+}
+
+fn device_sink(
+    state: &mut AudioState,
+    channel: Channel,
+) -> Result<(Sink, OutputFormat), anyhow::Error> {
+    let device = state.devices[channel.index()].clone();
+    let (handle, format) = match output(state, device.clone()) {
+        Ok(handle) => handle,
+        // The device may have been unplugged since it was picked.
+        Err(err) if device.is_some() => {
+            warn!(
+                "{:?}. Playing {:?} audio on the default device.",
+                err, channel
+            );
+            output(state, None)?
+        }
+        Err(err) => return Err(err),
+    };
+    let sink = Sink::try_new(&handle).context("Failed to create audio sink")?;
+    Ok((sink, format))
+}
+
+/// A sink that isn't connected to any device. Its sounds are thrown away as fast as they'd play,
+/// so anything waiting for them to end waits as long as it would with a device.
+fn null_sink() -> Sink {
+    // How much audio is thrown away at a time.
+    const CHUNK: Duration = Duration::from_millis(50);
+
+    let (sink, mut queue) = Sink::new_idle();
+    thread::spawn(move || loop {
+        let samples_per_chunk =
+            (queue.sample_rate() as f32 * queue.channels() as f32 * CHUNK.as_secs_f32()) as usize;
+        // Ends once the sink is dropped and everything queued on it is done.
+        if queue.by_ref().take(samples_per_chunk.max(1)).count() == 0 {
+            return;
+        }
+        thread::sleep(CHUNK);
+    });
+    sink
+}
+
+/// Places a sound between the left (-1.0) and right (1.0) speakers. 0.0 is centered.
+/// The sound is mixed down to mono first, and the side it's moved toward keeps its full volume.
+pub fn pan<S>(source: S, position: f32) -> ChannelVolume<S>
+where
+    S: Source,
+    S::Item: Sample,
+{
+    let position = position.clamp(-1.0, 1.0);
+    // The channels are summed when mixed down, so they're scaled back to the sound's own loudness.
+    let mixdown = 1.0 / source.channels().max(1) as f32;
+    ChannelVolume::new(
+        source,
+        vec![
+            (1.0 - position).min(1.0) * mixdown,
+            (1.0 + position).min(1.0) * mixdown,
+        ],
+    )
+}
+
+/// Sets the volume of a channel, where 1.0 is the original volume.
+pub fn set_channel_volume(channel: Channel, volume: f32) {
+    let mut state = AUDIO_STATE.lock().unwrap();
+    state.volumes[channel.index()] = volume;
+    state.update_sinks();
+}
+
+pub fn channel_volume(channel: Channel) -> f32 {
+    AUDIO_STATE.lock().unwrap().volumes[channel.index()]
+}
+
+/// Lowers every channel except speech to `ducked_volume`, or restores them with `None`.
+pub fn set_ducked(ducked_volume: Option<f32>) {
+    let mut state = AUDIO_STATE.lock().unwrap();
+    state.ducked_volume = ducked_volume;
+    state.update_sinks();
+}
+
+/// Plays `channel` on the output device named `device`, or on the default device if it's None.
+/// Sounds already playing stay on the device they started on.
+pub fn set_output_device(channel: Channel, device: Option<String>) -> Result<(), anyhow::Error> {
+    if let Some(name) = &device {
+        let names = output_device_names()?;
+        if !names.contains(name) {
+            anyhow::bail!(
+                "No output device is named \"{}\". The output devices are: {}",
+                name,
+                names.join(", ")
+            );
+        }
+    }
+
+    let mut state = AUDIO_STATE.lock().unwrap();
+    state.devices[channel.index()] = device;
+    save_output_devices(&state.devices)
+}
+
+/// Returns the output device each channel plays on. None is the default device.
+pub fn output_devices() -> Vec<(Channel, Option<String>)> {
+    let state = AUDIO_STATE.lock().unwrap();
+    Channel::ALL
+        .iter()
+        .map(|channel| (*channel, state.devices[channel.index()].clone()))
+        .collect()
+}
+
+pub fn output_device_names() -> Result<Vec<String>, anyhow::Error> {
+    let devices = cpal::default_host()
+        .output_devices()
+        .context("Failed to get list of output devices")?;
+    Ok(devices.filter_map(|device| device.name().ok()).collect())
+}
+
+/// Plays a sink from the audio service. If its channel is preempted, it starts playing when the preemption ends.
+pub fn play(sink: &Arc<dyn AudioSink>) {
+    let mut state = AUDIO_STATE.lock().unwrap();
+    match state.channel_of(sink) {
+        Some(channel) if state.is_preempted(channel) => {
+            if !state.is_held(sink) {
+                state.held.push((channel, Arc::downgrade(sink)));
+            }
+        }
+        _ => sink.play(),
+    }
+}
+
+/// Pauses a sink from the audio service, so it stays paused when a preemption ends.
+pub fn pause(sink: &Arc<dyn AudioSink>) {
+    let mut state = AUDIO_STATE.lock().unwrap();
+    state
+        .held
+        .retain(|(_, held)| !std::ptr::addr_eq(held.as_ptr(), Arc::as_ptr(sink)));
+    sink.pause();
+}
+
+/// Pauses the channels `channel` preempts until the returned guard is dropped.
+pub fn preempt(channel: Channel) -> Preemption {
+    let mut state = AUDIO_STATE.lock().unwrap();
+    state.preemptions[channel.index()] += 1;
+    state.update_sinks();
+    Preemption { channel }
+}
+
+/// Ends a preemption started by `preempt` when dropped.
+pub struct Preemption {
+    channel: Channel,
+}
+
+impl Drop for Preemption {
+    fn drop(&mut self) {
+        let mut state = AUDIO_STATE.lock().unwrap();
+        state.preemptions[self.channel.index()] -= 1;
+        state.update_sinks();
+    }
+}
+
+fn output(
+    state: &mut AudioState,
+    device: Option<String>,
+) -> Result<(OutputStreamHandle, OutputFormat), anyhow::Error> {
+    WATCH_DEVICES.call_once(watch_devices);
+    if let Some(output) = state.outputs.get(&device) {
+        return Ok((output.handle.clone(), output.format));
+    }
+    let output = open_output(device.clone())?;
+    let opened = (output.handle.clone(), output.format);
+    state.outputs.insert(device, output);
+    Ok(opened)
+}
+
+/// Opens an output device on a thread that keeps it open, since an output stream
+/// can't be moved between threads.
+fn open_output(device: Option<String>) -> Result<Output, anyhow::Error> {
+    let (handle_tx, handle_rx) = flume::bounded(1);
+    let (close_tx, close_rx) = flume::bounded::<()>(0);
+    let thread_device = device.clone();
+    thread::spawn(move || match open_stream(thread_device.as_deref()) {
+        Ok((_stream, handle, format)) => {
+            if handle_tx.send(Ok((handle, format))).is_err() {
+                return;
+            }
+            // Returns once the output is dropped. Sounds still playing on it end early.
+            let _ = close_rx.recv();
+            debug!(
+                "Closed audio output {}",
+                thread_device.as_deref().unwrap_or("default")
+            );
+        }
+        Err(err) => {
+            let _ = handle_tx.send(Err(err));
+        }
+    });
+
+    let (handle, format) = handle_rx.recv().context("Audio output thread exited")??;
+    info!(
+        "Opened audio output {} at {} Hz with {} channels",
+        device.as_deref().unwrap_or("default"),
+        format.sample_rate,
+        format.channels
+    );
+    Ok(Output {
+        handle,
+        format,
+        _close_tx: close_tx,
+    })
+}
+
+static WATCH_DEVICES: Once = Once::new();
+
+/// Closes every open output, so each one is opened again on whatever device it should use now.
+/// Sounds still playing move to their channel's device and carry on from where they were.
+fn reopen_outputs() {
+    let mut state = AUDIO_STATE.lock().unwrap();
+    if !state.outputs.is_empty() {
+        info!("Audio devices changed. Reopening audio outputs.");
+        state.outputs.clear();
+    }
+    // A new device may be what was missing.
+    if state.null_output_since.is_some() {
+        state.null_output_since = Some(Instant::now() - NULL_OUTPUT_RETRY_INTERVAL);
+    }
+
+    let mut device_sinks = std::mem::take(&mut state.device_sinks);
+    device_sinks.retain(|sink| sink.strong_count() > 0);
+    // Even idle sinks move, since anything queued on a closed output would never play.
+    for sink in device_sinks.iter().filter_map(Weak::upgrade) {
+        let (new_sink, format) = output_sink(&mut state, sink.channel);
+        sink.move_to(new_sink, format);
+    }
+    state.device_sinks = device_sinks;
+}
+
+/// Starts a thread that reopens the outputs whenever the system's output devices change.
+/// Uses PulseAudio/PipeWire's event stream, so nothing is polled.
+#[cfg(target_os = "linux")]
+fn watch_devices() {
+    use std::io::BufRead;
+
+    thread::spawn(|| {
+        let mut child = match std::process::Command::new("pactl")
+            .arg("subscribe")
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::null())
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(err) => {
+                debug!(
+                    "pactl unavailable. Not watching for audio device changes: {}",
+                    err
+                );
+                return;
+            }
+        };
+        let Some(stdout) = child.stdout.take() else {
+            return;
+        };
+
+        // Events look like "Event 'new' on sink #57". A change on the server can be a new default device.
+        for line in io::BufReader::new(stdout).lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            let devices_changed = line.contains("'change' on server")
+                || ((line.contains("'new'") || line.contains("'remove'"))
+                    && line.contains(" on sink #"));
+            if devices_changed {
+                debug!("{}", line);
+                reopen_outputs();
+            }
+        }
+        let _ = child.wait();
+    });
+}
+
+/// Starts a thread that reopens the outputs whenever the system's output devices change.
+/// Windows tells registered clients when devices come and go or the default changes, so nothing is
+/// polled.
+#[cfg(windows)]
+fn watch_devices() {
+    thread::spawn(|| {
+        let (changed_tx, changed_rx) = flume::unbounded();
+        if let Err(err) = wasapi::notify_device_changes(changed_tx) {
+            warn!("Not watching for audio device changes: {:#}", err);
+            return;
+        }
+        while changed_rx.recv().is_ok() {
+            // A device change comes with several notifications, so they're handled together.
+            thread::sleep(Duration::from_millis(100));
+            changed_rx.drain().for_each(drop);
+            reopen_outputs();
+        }
+    });
+}
+
+/// Registers for the device change notifications of Windows' audio API, which are sent on its own
+/// threads. They aren't acted on there, since Windows expects them to return straight away.
+#[cfg(windows)]
+mod wasapi {
+    use anyhow::bail;
+    use std::{ffi::c_void, ptr};
+    use windows_sys::{
+        core::{GUID, HRESULT, PCWSTR},
+        Win32::{
+            Foundation::{E_NOINTERFACE, S_OK},
+            Media::Audio::{eRender, EDataFlow, ERole},
+            System::Com::{CoCreateInstance, CoInitializeEx, CLSCTX_ALL, COINIT_MULTITHREADED},
+        },
+    };
+
+    const CLSID_MM_DEVICE_ENUMERATOR: GUID =
+        GUID::from_u128(0xbcde0395_e52f_467c_8e3d_c4579291692e);
+    const IID_IUNKNOWN: GUID = GUID::from_u128(0x00000000_0000_0000_c000_000000000046);
+    const IID_IMM_DEVICE_ENUMERATOR: GUID = GUID::from_u128(0xa95664d2_9614_4f35_a746_de8db63617e6);
+    const IID_IMM_NOTIFICATION_CLIENT: GUID =
+        GUID::from_u128(0x7991eec9_7e89_4d85_8390_6c703cec60c0);
+
+    // windows-sys only has pointers for COM interfaces, so their layouts are written out here.
+    // Windows reads them, not Rust, so most of their fields look unused.
+    #[allow(dead_code)]
+    #[repr(C)]
+    struct DeviceEnumeratorVtbl {
+        query_interface: usize,
+        add_ref: usize,
+        release: usize,
+        enum_audio_endpoints: usize,
+        get_default_audio_endpoint: usize,
+        get_device: usize,
+        register_endpoint_notification_callback:
+            unsafe extern "system" fn(*mut c_void, *mut c_void) -> HRESULT,
+    }
+
+    #[allow(dead_code)]
+    #[repr(C)]
+    struct PropertyKey {
+        fmtid: GUID,
+        pid: u32,
+    }
+
+    #[allow(dead_code)]
+    #[repr(C)]
+    struct NotificationClientVtbl {
+        query_interface:
+            unsafe extern "system" fn(*mut c_void, *const GUID, *mut *mut c_void) -> HRESULT,
+        add_ref: unsafe extern "system" fn(*mut c_void) -> u32,
+        release: unsafe extern "system" fn(*mut c_void) -> u32,
+        on_device_state_changed: unsafe extern "system" fn(*mut c_void, PCWSTR, u32) -> HRESULT,
+        on_device_added: unsafe extern "system" fn(*mut c_void, PCWSTR) -> HRESULT,
+        on_device_removed: unsafe extern "system" fn(*mut c_void, PCWSTR) -> HRESULT,
+        on_default_device_changed:
+            unsafe extern "system" fn(*mut c_void, EDataFlow, ERole, PCWSTR) -> HRESULT,
+        on_property_value_changed:
+            unsafe extern "system" fn(*mut c_void, PCWSTR, PropertyKey) -> HRESULT,
+    }
+
+    /// An IMMNotificationClient that tells `changed_tx` about output device changes. It's never
+    /// freed, since Windows can call it until the process exits.
+    #[allow(dead_code)]
+    #[repr(C)]
+    struct NotificationClient {
+        vtbl: &'static NotificationClientVtbl,
+        changed_tx: flume::Sender<()>,
+    }
+
+    static NOTIFICATION_CLIENT_VTBL: NotificationClientVtbl = NotificationClientVtbl {
+        query_interface,
+        add_ref,
+        release,
+        on_device_state_changed,
+        on_device_added,
+        on_device_removed,
+        on_default_device_changed,
+        on_property_value_changed,
+    };
+
+    unsafe fn changed(this: *mut c_void) -> HRESULT {
+        let client = &*(this as *const NotificationClient);
+        let _ = client.changed_tx.send(());
+        S_OK
+    }
+
+    unsafe extern "system" fn query_interface(
+        this: *mut c_void,
+        iid: *const GUID,
+        object: *mut *mut c_void,
+    ) -> HRESULT {
+        let iid = &*iid;
+        let known = [IID_IUNKNOWN, IID_IMM_NOTIFICATION_CLIENT]
+            .iter()
+            .any(|known| {
+                known.data1 == iid.data1
+                    && known.data2 == iid.data2
+                    && known.data3 == iid.data3
+                    && known.data4 == iid.data4
+            });
+        if !known {
+            *object = ptr::null_mut();
+            return E_NOINTERFACE;
+        }
+        *object = this;
+        S_OK
+    }
+
+    // The client lives forever, so there's no reference count to keep.
+    unsafe extern "system" fn add_ref(_this: *mut c_void) -> u32 {
+        1
+    }
+
+    unsafe extern "system" fn release(_this: *mut c_void) -> u32 {
+        1
+    }
+
+    unsafe extern "system" fn on_device_state_changed(
+        this: *mut c_void,
+        _device_id: PCWSTR,
+        _new_state: u32,
+    ) -> HRESULT {
+        changed(this)
+    }
+
+    // Added and removed devices also change state, which is what's acted on.
+    unsafe extern "system" fn on_device_added(_this: *mut c_void, _device_id: PCWSTR) -> HRESULT {
+        S_OK
+    }
+
+    unsafe extern "system" fn on_device_removed(_this: *mut c_void, _device_id: PCWSTR) -> HRESULT {
+        S_OK
+    }
+
+    unsafe extern "system" fn on_default_device_changed(
+        this: *mut c_void,
+        flow: EDataFlow,
+        _role: ERole,
+        _device_id: PCWSTR,
+    ) -> HRESULT {
+        match flow == eRender {
+            true => changed(this),
+            false => S_OK,
+        }
+    }
+
+    unsafe extern "system" fn on_property_value_changed(
+        _this: *mut c_void,
+        _device_id: PCWSTR,
+        _key: PropertyKey,
+    ) -> HRESULT {
+        S_OK
+    }
+
+    /// Sends to `changed_tx` whenever an output device is added, removed, or becomes the default.
+    /// The calling thread joins the multithreaded COM apartment.
+    pub fn notify_device_changes(changed_tx: flume::Sender<()>) -> Result<(), anyhow::Error> {
+        unsafe {
+            let result = CoInitializeEx(ptr::null(), COINIT_MULTITHREADED as u32);
+            if result < 0 {
+                bail!("Failed to initialize COM: {:#x}", result);
+            }
+            let mut enumerator: *mut c_void = ptr::null_mut();
+            let result = CoCreateInstance(
+                &CLSID_MM_DEVICE_ENUMERATOR,
+                ptr::null_mut(),
+                CLSCTX_ALL,
+                &IID_IMM_DEVICE_ENUMERATOR,
+                &mut enumerator,
+            );
+            if result < 0 {
+                bail!(
+                    "Failed to create the audio device enumerator: {:#x}",
+                    result
+                );
+            }
+            // The enumerator is kept too, since releasing it would unregister the client.
+            let client = Box::leak(Box::new(NotificationClient {
+                vtbl: &NOTIFICATION_CLIENT_VTBL,
+                changed_tx,
+            }));
+            let vtbl = &**(enumerator as *const *const DeviceEnumeratorVtbl);
+            let result = (vtbl.register_endpoint_notification_callback)(
+                enumerator,
+                client as *mut NotificationClient as *mut c_void,
+            );
+            if result < 0 {
+                bail!("Failed to register for audio device changes: {:#x}", result);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Starts a thread that reopens the outputs whenever the system's output devices change.
+/// cpal has no device change events on macOS, so as a fallback, the default device and the list
+/// of devices are polled.
+#[cfg(not(any(target_os = "linux", windows)))]
+fn watch_devices() {
+    fn output_devices() -> (Option<String>, Vec<String>) {
+        let host = cpal::default_host();
+        let default = host
+            .default_output_device()
+            .and_then(|device| device.name().ok());
+        let mut devices: Vec<String> = host
+            .output_devices()
+            .map(|devices| devices.filter_map(|device| device.name().ok()).collect())
+            .unwrap_or_default();
+        devices.sort();
+        (default, devices)
+    }
+
+    thread::spawn(|| {
+        let mut last = output_devices();
+        loop {
+            thread::sleep(DEVICE_POLL_INTERVAL);
+            let devices = output_devices();
+            if devices != last {
+                debug!(
+                    "Audio devices changed. Default output: {}",
+                    devices.0.as_deref().unwrap_or("none")
+                );
+                last = devices;
+                reopen_outputs();
+            }
+        }
+    });
+}
+
+fn open_stream(
+    device: Option<&str>,
+) -> Result<(OutputStream, OutputStreamHandle, OutputFormat), anyhow::Error> {
+    let host = cpal::default_host();
+    let device = match device {
+        Some(name) => host
+            .output_devices()
+            .context("Failed to get list of output devices")?
+            .find(|device| device.name().is_ok_and(|device_name| device_name == name))
+            .with_context(|| format!("Output device \"{}\" not found", name))?,
+        None => host
+            .default_output_device()
+            .context("Failed to open the default audio output")?,
+    };
+    let name = device.name().unwrap_or_else(|_| "default".to_string());
+    let config = device
+        .default_output_config()
+        .with_context(|| format!("Failed to get the format of output device \"{}\"", name))?;
+    let format = OutputFormat {
+        channels: config.channels(),
+        sample_rate: config.sample_rate().0,
+    };
+    let (stream, handle) = OutputStream::try_from_device_config(&device, config)
+        .with_context(|| format!("Failed to open output device \"{}\"", name))?;
+    Ok((stream, handle, format))
+}
+
+fn load_output_devices() -> [Option<String>; Channel::ALL.len()] {
+    let mut devices: [Option<String>; Channel::ALL.len()] = Default::default();
+    let saved: BTreeMap<Channel, String> = match fs::read_to_string(&*OUTPUT_DEVICES_PATH) {
+        Ok(text) => match toml::from_str(&text) {
+            Ok(saved) => saved,
+            Err(err) => {
+                warn!("Failed to parse {}: {}", OUTPUT_DEVICES_PATH.display(), err);
+                return devices;
+            }
+        },
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return devices,
+        Err(err) => {
+            warn!("Failed to read {}: {}", OUTPUT_DEVICES_PATH.display(), err);
+            return devices;
+        }
+    };
+    for (channel, device) in saved {
+        devices[channel.index()] = Some(device);
+    }
+    devices
+}
+
+fn save_output_devices(devices: &[Option<String>]) -> Result<(), anyhow::Error> {
+    let saved: BTreeMap<Channel, String> = Channel::ALL
+        .iter()
+        .filter_map(|channel| Some((*channel, devices[channel.index()].clone()?)))
+        .collect();
+    fs::create_dir_all(CACHE_DIR.as_path())?;
+    fs::write(&*OUTPUT_DEVICES_PATH, toml::to_string(&saved)?)
+        .with_context(|| format!("Failed to write {}", OUTPUT_DEVICES_PATH.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rodio::buffer::SamplesBuffer;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    fn resumable(source: impl Source<Item = f32> + Send + 'static) -> Arc<Mutex<Resumable>> {
+        Arc::new(Mutex::new(Resumable {
+            source: Box::new(source),
+            epoch: 0,
+        }))
+    }
+
+    #[test]
+    fn a_moved_sound_carries_on_from_where_it_was() {
+        let samples: Vec<f32> = (0..5000).map(|i| i as f32).collect();
+        let sound = resumable(SamplesBuffer::new(1, 8000, samples));
+        let sounds = Arc::new(Sounds::default());
+        sounds.queued.lock().unwrap().push(sound.clone());
+
+        let mut old = ResumableSource::new(sound.clone(), sounds.clone());
+        let played: Vec<f32> = old.by_ref().take(100).collect();
+        assert_eq!(played[99], 99.0);
+
+        // What the old device was handed but didn't play is lost, and the old source ends.
+        sound.lock().unwrap().epoch += 1;
+        let new = ResumableSource::new(sound.clone(), sounds.clone());
+        assert_eq!(old.count(), RESUMABLE_CHUNK_FRAMES - 100);
+        let rest: Vec<f32> = new.collect();
+        assert_eq!(rest.first(), Some(&(RESUMABLE_CHUNK_FRAMES as f32)));
+        assert_eq!(rest.last(), Some(&4999.0));
+        assert!(sounds.queued.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn a_moved_sound_is_converted_to_the_new_devices_format() {
+        let sound = resumable(SamplesBuffer::new(2, 24000, vec![0.5; 48000]));
+        let sounds = Arc::new(Sounds::default());
+        let source = ResumableSource::new(sound, sounds);
+        assert_eq!((source.channels(), source.sample_rate()), (2, 24000));
+        let converted = UniformSourceIterator::<_, f32>::new(source, 1, 48000);
+        // A second of stereo at 24 kHz is a second of mono at 48 kHz, give or take the last
+        // few samples the converter can't interpolate.
+        assert!((47_900..=48_000).contains(&converted.count()));
+    }
+
+    #[test]
+    fn a_preempted_channel_waits_for_the_preemption_to_end() {
+        let _preemptions = TEST_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let played = Arc::new(AtomicBool::new(false));
+        let callback_played = played.clone();
+        let sink = new_sink(Channel::Speech);
+        let preemption = preempt(Channel::Alerts);
+        play(&sink);
+        sink.append(rodio::source::EmptyCallback::<f32>::new(Box::new(
+            move || callback_played.store(true, Ordering::Relaxed),
+        )));
+        assert!(sink.is_paused());
+        assert!(!played.load(Ordering::Relaxed));
+        assert_eq!(sink.len(), 1);
+
+        drop(preemption);
+        assert!(!sink.is_paused());
+        assert!(played.load(Ordering::Relaxed));
+        assert!(sink.empty());
+    }
+
+    #[test]
+    fn sounds_start_when_theyre_played() {
+        let started = Arc::new(AtomicBool::new(false));
+        let callback_started = started.clone();
+        let sound = resumable(rodio::source::EmptyCallback::<f32>::new(Box::new(
+            move || callback_started.store(true, Ordering::Relaxed),
+        )));
+        let mut source = ResumableSource::new(sound, Arc::new(Sounds::default()));
+        assert!(!started.load(Ordering::Relaxed));
+        assert_eq!(source.next(), None);
+        assert!(started.load(Ordering::Relaxed));
+    }
+}
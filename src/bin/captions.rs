@@ -0,0 +1,235 @@
+//! A frameless, always-on-top overlay with live captions of the running assistant:
+//! what it heard you say, and what it's saying back. Handy when you can't listen, like in a meeting.
+
+slint::slint! {
+    export component CaptionsWindow inherits Window {
+        no-frame: true;
+        always-on-top: true;
+        background: transparent;
+        width: 900px;
+        height: 220px;
+        title: "quick-assistant captions";
+
+        in property <string> heard;
+        in property <string> spoken;
+        in property <length> caption-font-size: 24px;
+
+        VerticalLayout {
+            alignment: end;
+            padding: 12px;
+            spacing: 6px;
+            if heard != "": Rectangle {
+                background: #000000b0;
+                border-radius: 8px;
+                HorizontalLayout {
+                    padding: 8px;
+                    Text {
+                        text: "You: " + heard;
+                        color: #9fd89f;
+                        font-size: caption-font-size;
+                        wrap: word-wrap;
+                    }
+                }
+            }
+            if spoken != "": Rectangle {
+                background: #000000b0;
+                border-radius: 8px;
+                HorizontalLayout {
+                    padding: 8px;
+                    Text {
+                        text: spoken;
+                        color: #ffffff;
+                        font-size: caption-font-size;
+                        wrap: word-wrap;
+                    }
+                }
+            }
+        }
+    }
+}
+
+// The overlay follows the assistant over the same local socket that the UI uses.
+#[allow(dead_code)]
+#[path = "../conversation.rs"]
+mod conversation;
+#[allow(dead_code)]
+#[path = "../instance.rs"]
+mod instance;
+#[path = "../paths.rs"]
+mod paths;
+
+use clap::Parser;
+use conversation::ConversationEvent;
+use slint::ComponentHandle;
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How long captions stay up after the conversation goes quiet.
+const LINGER: Duration = Duration::from_secs(6);
+/// How long to wait before trying to reach the assistant again.
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(2);
+/// The most characters of a response shown at once when it isn't being spoken.
+const MAX_RESPONSE_CHARS: usize = 200;
+
+#[derive(Parser, Debug)]
+#[command(version)]
+struct Opt {
+    /// Where to put the overlay's left edge, in pixels from the left of the screen.
+    #[arg(long)]
+    x: Option<i32>,
+
+    /// Where to put the overlay's top edge, in pixels from the top of the screen.
+    #[arg(long)]
+    y: Option<i32>,
+
+    /// The size of the caption text, in pixels.
+    #[arg(long, default_value_t = 24.0)]
+    font_size: f32,
+}
+
+/// What the captions show.
+#[derive(Default)]
+struct Captions {
+    /// What the assistant last heard the user say.
+    heard: String,
+    /// The sentence the AI voice is speaking.
+    speaking: Option<String>,
+    /// The AI's response so far, shown when it isn't being spoken, such as when the voice is muted.
+    response: String,
+    response_spoken: bool,
+}
+
+impl Captions {
+    fn update(&mut self, event: ConversationEvent) {
+        match event {
+            ConversationEvent::User { text } => {
+                self.heard = text;
+                self.response.clear();
+                self.response_spoken = false;
+            }
+            ConversationEvent::Token { text } => self.response.push_str(&text),
+            ConversationEvent::SpeechStarted { text } => {
+                self.speaking = Some(text);
+                self.response_spoken = true;
+            }
+            // Only clear the caption if a newer sentence hasn't replaced it.
+            ConversationEvent::SpeechFinished { text } if self.speaking.as_ref() != Some(&text) => {
+            }
+            ConversationEvent::SpeechFinished { .. } | ConversationEvent::SpeechStopped => {
+                self.speaking = None;
+            }
+            ConversationEvent::FunctionCall { .. }
+            | ConversationEvent::FunctionResult { .. }
+            | ConversationEvent::ResponseDone
+            | ConversationEvent::ListeningStarted
+            | ConversationEvent::ListeningLevel { .. }
+            | ConversationEvent::ListeningStopped
+            | ConversationEvent::AlarmStarted
+            | ConversationEvent::AlarmStopped => {}
+        }
+    }
+
+    /// Clears everything that isn't being spoken right now.
+    fn clear_quiet(&mut self) {
+        if self.speaking.is_none() {
+            self.heard.clear();
+            self.response.clear();
+        }
+    }
+
+    fn spoken(&self) -> String {
+        match &self.speaking {
+            Some(sentence) => sentence.clone(),
+            None if self.response_spoken => String::new(),
+            None => tail(self.response.trim(), MAX_RESPONSE_CHARS),
+        }
+    }
+}
+
+/// The end of `text`, at most `max_chars` long, starting at a word.
+fn tail(text: &str, max_chars: usize) -> String {
+    let char_count = text.chars().count();
+    if char_count <= max_chars {
+        return text.to_string();
+    }
+    let end: String = text.chars().skip(char_count - max_chars).collect();
+    match end.split_once(' ') {
+        Some((_, rest)) => format!("…{}", rest),
+        None => end,
+    }
+}
+
+fn show(window: &slint::Weak<CaptionsWindow>, captions: &Captions) {
+    let heard = captions.heard.clone();
+    let spoken = captions.spoken();
+    let _ = window.upgrade_in_event_loop(move |window| {
+        window.set_heard(heard.into());
+        window.set_spoken(spoken.into());
+    });
+}
+
+/// Keeps the captions up to date, reconnecting whenever the assistant restarts.
+fn follow_conversation(window: slint::Weak<CaptionsWindow>) {
+    loop {
+        let events = match instance::subscribe() {
+            Ok(events) => events,
+            Err(_) => {
+                thread::sleep(RECONNECT_INTERVAL);
+                continue;
+            }
+        };
+
+        // Events are read on their own thread, so captions can be cleared while waiting for the next one.
+        let (events_tx, events_rx) = flume::unbounded();
+        thread::spawn(move || {
+            for event in events {
+                if events_tx.send(event).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut captions = Captions::default();
+        let mut clear_at = None;
+        loop {
+            let event = match clear_at {
+                Some(clear_at) => events_rx.recv_deadline(clear_at),
+                None => events_rx.recv().map_err(Into::into),
+            };
+            match event {
+                Ok(event) => {
+                    captions.update(event);
+                    clear_at = Some(Instant::now() + LINGER);
+                }
+                Err(flume::RecvTimeoutError::Timeout) => {
+                    captions.clear_quiet();
+                    clear_at = None;
+                }
+                Err(flume::RecvTimeoutError::Disconnected) => break,
+            }
+            show(&window, &captions);
+        }
+
+        show(&window, &Captions::default());
+        thread::sleep(RECONNECT_INTERVAL);
+    }
+}
+
+fn main() {
+    let opt = Opt::parse();
+
+    let window = CaptionsWindow::new().unwrap();
+    window.set_caption_font_size(opt.font_size);
+    if let (Some(x), Some(y)) = (opt.x, opt.y) {
+        window
+            .window()
+            .set_position(slint::PhysicalPosition::new(x, y));
+    }
+
+    let window_weak = window.as_weak();
+    thread::spawn(move || follow_conversation(window_weak));
+
+    window.run().unwrap();
+}
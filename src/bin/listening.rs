@@ -0,0 +1,135 @@
+//! A small floating microphone indicator that shows up while the running assistant is recording,
+//! with a live level bar, so you know it's hearing you before you start talking.
+
+slint::slint! {
+    export component ListeningWindow inherits Window {
+        no-frame: true;
+        always-on-top: true;
+        background: transparent;
+        width: 220px;
+        height: 48px;
+        title: "quick-assistant listening";
+
+        // How full the level bar is, from 0 to 1.
+        in property <float> level;
+
+        Rectangle {
+            background: #000000c0;
+            border-radius: 24px;
+            HorizontalLayout {
+                padding: 12px;
+                spacing: 10px;
+                alignment: start;
+                Rectangle {
+                    width: 24px;
+                    height: 24px;
+                    border-radius: 12px;
+                    background: #e04848;
+                }
+                Rectangle {
+                    width: 150px;
+                    height: 24px;
+                    border-radius: 4px;
+                    background: #ffffff30;
+                    Rectangle {
+                        x: 0;
+                        width: parent.width * level;
+                        height: parent.height;
+                        border-radius: 4px;
+                        background: level > 0.9 ? #e0a048 : #9fd89f;
+                    }
+                }
+            }
+        }
+    }
+}
+
+// The indicator follows the assistant over the same local socket that the UI uses.
+#[allow(dead_code)]
+#[path = "../conversation.rs"]
+mod conversation;
+#[allow(dead_code)]
+#[path = "../instance.rs"]
+mod instance;
+#[path = "../paths.rs"]
+mod paths;
+
+use clap::Parser;
+use conversation::ConversationEvent;
+use slint::ComponentHandle;
+use std::{thread, time::Duration};
+
+/// How long to wait before trying to reach the assistant again.
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(2);
+/// The quietest level, in dB, that the bar shows.
+const METER_FLOOR_DB: f32 = -60.0;
+
+#[derive(Parser, Debug)]
+#[command(version)]
+struct Opt {
+    /// Where to put the indicator's left edge, in pixels from the left of the screen.
+    #[arg(long)]
+    x: Option<i32>,
+
+    /// Where to put the indicator's top edge, in pixels from the top of the screen.
+    #[arg(long)]
+    y: Option<i32>,
+}
+
+/// How full the level bar is for a microphone level, on a dB scale so quiet speech still moves it.
+fn bar_fill(level: f32) -> f32 {
+    let db = 20.0 * level.max(f32::MIN_POSITIVE).log10();
+    (1.0 - db / METER_FLOOR_DB).clamp(0.0, 1.0)
+}
+
+fn set_listening(window: &slint::Weak<ListeningWindow>, listening: bool) {
+    let _ = window.upgrade_in_event_loop(move |window| {
+        window.set_level(0.0);
+        let result = match listening {
+            true => window.show(),
+            false => window.hide(),
+        };
+        if let Err(err) = result {
+            eprintln!("Failed to show the listening indicator: {}", err);
+        }
+    });
+}
+
+/// Shows the indicator while the assistant records, reconnecting whenever the assistant restarts.
+fn follow_conversation(window: slint::Weak<ListeningWindow>) {
+    loop {
+        if let Ok(events) = instance::subscribe() {
+            for event in events {
+                match event {
+                    ConversationEvent::ListeningStarted => set_listening(&window, true),
+                    ConversationEvent::ListeningStopped => set_listening(&window, false),
+                    ConversationEvent::ListeningLevel { level } => {
+                        let fill = bar_fill(level);
+                        let _ = window.upgrade_in_event_loop(move |window| window.set_level(fill));
+                    }
+                    _ => {}
+                }
+            }
+            // The assistant exited, maybe while recording.
+            set_listening(&window, false);
+        }
+        thread::sleep(RECONNECT_INTERVAL);
+    }
+}
+
+fn main() {
+    let opt = Opt::parse();
+
+    let window = ListeningWindow::new().unwrap();
+    if let (Some(x), Some(y)) = (opt.x, opt.y) {
+        window
+            .window()
+            .set_position(slint::PhysicalPosition::new(x, y));
+    }
+
+    let window_weak = window.as_weak();
+    thread::spawn(move || follow_conversation(window_weak));
+
+    // The window is only shown while listening, so the event loop has to outlive it being hidden.
+    slint::run_event_loop_until_quit().unwrap();
+}
@@ -1,9 +1,26 @@
+//! A text chat window for the running assistant. Typed messages are answered like spoken ones,
+//! and the conversation shows what's said by voice too.
+
 slint::slint! {
     import { AboutSlint, Button, VerticalBox } from "std-widgets.slint";
 
     import { Button, GroupBox, SpinBox, ComboBox, CheckBox, LineEdit, TabWidget, VerticalBox, HorizontalBox,
-        Slider, ProgressIndicator, SpinBox, Switch, Spinner, GridBox, StandardButton, TextEdit, ScrollView} from "std-widgets.slint";
+        Slider, ProgressIndicator, SpinBox, Switch, Spinner, GridBox, StandardButton, TextEdit, ScrollView, ListView} from "std-widgets.slint";
+
+
+    export struct ChatMessage {
+        // "user", "assistant", "function", or "error".
+        role: string,
+        text: string,
+    }
 
+    export struct SessionItem {
+        // The session's name, which is when it started.
+        name: string,
+        title: string,
+        // The first thing the user said.
+        preview: string,
+    }
 
     export component MainWindow inherits Window {
         width: 1280px;
@@ -12,73 +29,425 @@ slint::slint! {
 
         callback handle_message;
         callback disable_send_if_empty_message;
-        in-out property <string> message_history <=> message_history_text.text;
+        callback search_sessions;
+        callback open_session(string);
+        callback load_session();
+        callback show_live_chat();
+        callback copy_text(string);
+        callback dismiss_alarm();
+        in property <[ChatMessage]> messages;
+        in property <[ChatMessage]> session_messages;
+        in property <[SessionItem]> sessions;
+        // The saved session being looked at, or empty while showing the live chat.
+        in property <string> viewed_session;
+        in property <string> viewed_session_title;
+        in property <string> status;
+        in property <bool> alarm_ringing;
+        in-out property <string> search <=> search_lineedit.text;
         in-out property <string> message <=> message_lineedit.text;
         in property <bool> send_button_enabled <=> send_button.enabled;
 
-        VerticalLayout {
-            width: 66%;
-            padding-bottom: 100px;
-            // TextEdit {
-
-            // }
-            Rectangle {
-                border-color: darkslategrey;
-                // border-width: 1px;
-                border-radius: 10px;
-                // drop-shadow-color: blue;
-                // ScrollView {
-                    HorizontalLayout {
-                        padding: 10px;
-                        alignment: start;
+        public function scroll_to_bottom() {
+            message_list.viewport-y = min(0px, message_list.visible-height - message_list.viewport-height);
+        }
+
+        HorizontalLayout {
+            padding: 10px;
+            spacing: 10px;
+
+            VerticalLayout {
+                width: 280px;
+                spacing: 6px;
+                Text {
+                    text: "Conversations";
+                    font-size: 16px;
+                    font-weight: 700;
+                }
+                search_lineedit := LineEdit {
+                    placeholder-text: "Search";
+                    edited => {
+                        search_sessions();
+                    }
+                }
+                Button {
+                    text: "Back to live chat";
+                    enabled: viewed_session != "";
+                    clicked => {
+                        show_live_chat();
+                    }
+                }
+                ListView {
+                    for session in sessions: Rectangle {
+                        border-radius: 8px;
+                        background: session.name == viewed_session ? #2b5278
+                            : session_touch.has-hover ? #303030
+                            : transparent;
+                        session_touch := TouchArea {
+                            clicked => {
+                                open_session(session.name);
+                            }
+                        }
                         VerticalLayout {
-                            alignment: end;
-                            message_history_text := Text {
-                                wrap: word-wrap;
+                            padding: 8px;
+                            Text {
+                                text: session.title;
+                                font-weight: 700;
+                            }
+                            Text {
+                                text: session.preview;
+                                color: #a0a0a0;
+                                overflow: elide;
                             }
                         }
                     }
-
-                // }
+                }
             }
-            Rectangle {
-                border-color: darkslategray;
-                border-width: 1px;
-                border-radius: 15px;
-                // drop-shadow-color: blue;
-                height: 50px;
-                // width: 100px;
-                // height: 100px;
-
-                HorizontalBox {
-                    message_lineedit := LineEdit {
-                        accepted => {
-                            handle_message();
+
+            VerticalLayout {
+                padding-bottom: 100px;
+                spacing: 6px;
+                if alarm_ringing: Rectangle {
+                    border-radius: 10px;
+                    background: #5c4a20;
+                    HorizontalBox {
+                        Text {
+                            text: "An alarm is ringing";
+                            vertical-alignment: center;
+                            font-weight: 700;
                         }
-                        // width: 80%;
-                        edited => {disable_send_if_empty_message()}
+                        Button {
+                            text: "Dismiss";
+                            clicked => {
+                                dismiss_alarm();
+                            }
+                        }
+                    }
+                }
+                if viewed_session != "": HorizontalBox {
+                    Text {
+                        text: viewed_session_title;
+                        vertical-alignment: center;
+                        font-weight: 700;
                     }
-                    send_button := Button {
-                        text: "Send";
-                        // width: 50px;
-                        primary: true;
+                    Button {
+                        text: "Load into conversation";
                         clicked => {
-                            handle_message();
+                            load_session();
+                        }
+                    }
+                }
+                Rectangle {
+                    border-color: darkslategrey;
+                    // border-width: 1px;
+                    border-radius: 10px;
+                    message_list := ListView {
+                        for message in viewed_session == "" ? messages : session_messages: HorizontalLayout {
+                            padding: 6px;
+                            alignment: message.role == "user" ? end : start;
+                            Rectangle {
+                                max-width: message_list.visible-width * 0.8;
+                                border-radius: 10px;
+                                background: message.role == "user" ? #2b5278
+                                    : message.role == "assistant" ? #303030
+                                    : message.role == "error" ? #5c2020
+                                    : transparent;
+                                VerticalLayout {
+                                    padding: 10px;
+                                    spacing: 4px;
+                                    Text {
+                                        text: message.text;
+                                        wrap: word-wrap;
+                                        color: message.role == "function" ? #a0a0a0 : #ffffff;
+                                        font-italic: message.role == "function";
+                                    }
+                                    if message.role == "user" || message.role == "assistant": HorizontalLayout {
+                                        alignment: end;
+                                        Text {
+                                            text: "Copy";
+                                            font-size: 11px;
+                                            color: copy_touch.has-hover ? #ffffff : #a0a0a0;
+                                            copy_touch := TouchArea {
+                                                clicked => {
+                                                    copy_text(message.text);
+                                                }
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+                Text {
+                    text: status;
+                    color: #a0a0a0;
+                }
+                Rectangle {
+                    border-color: darkslategray;
+                    border-width: 1px;
+                    border-radius: 15px;
+                    // drop-shadow-color: blue;
+                    height: 50px;
+                    // width: 100px;
+                    // height: 100px;
+
+                    HorizontalBox {
+                        message_lineedit := LineEdit {
+                            accepted => {
+                                handle_message();
+                            }
+                            // width: 80%;
+                            edited => {disable_send_if_empty_message()}
+                        }
+                        send_button := Button {
+                            text: "Send";
+                            // width: 50px;
+                            primary: true;
+                            clicked => {
+                                handle_message();
+                            }
+                            enabled: false;
                         }
-                        enabled: false;
                     }
                 }
             }
         }
     }
 }
-fn main() {
-    println!("Hello World!");
 
+// The UI talks to the assistant over the same local socket that later launches use.
+#[allow(dead_code)]
+#[path = "../conversation.rs"]
+mod conversation;
+#[allow(dead_code)]
+#[path = "../instance.rs"]
+mod instance;
+// Saved conversations are read straight from disk, so they can be browsed while the assistant isn't running.
+#[allow(dead_code)]
+#[path = "../sessions.rs"]
+mod sessions;
+#[path = "../paths.rs"]
+mod paths;
+
+use clipboard::{ClipboardContext, ClipboardProvider};
+use conversation::ConversationEvent;
+use sessions::{Entry, EntryMessage};
+use slint::{Model, ModelRc, VecModel};
+use std::{rc::Rc, thread, time::Duration};
+
+/// How long to wait before trying to reach the assistant again.
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(2);
+
+/// A change to the messages shown in the window.
+enum ChatUpdate {
+    Push(ChatMessage),
+    /// Continues the AI's response as more of it arrives.
+    AppendToLast(String),
+}
+
+fn chat_message(role: &str, text: impl Into<slint::SharedString>) -> ChatMessage {
+    ChatMessage {
+        role: role.into(),
+        text: text.into(),
+    }
+}
+
+fn apply(main_window: &slint::Weak<MainWindow>, update: ChatUpdate) {
+    let _ = main_window.upgrade_in_event_loop(move |main_window| {
+        let messages = main_window.get_messages();
+        let Some(messages) = messages.as_any().downcast_ref::<VecModel<ChatMessage>>() else {
+            return;
+        };
+        match update {
+            ChatUpdate::Push(message) => messages.push(message),
+            ChatUpdate::AppendToLast(text) => {
+                let last = messages.row_count().saturating_sub(1);
+                if let Some(mut message) = messages.row_data(last) {
+                    message.text = format!("{}{}", message.text, text).into();
+                    messages.set_row_data(last, message);
+                }
+            }
+        }
+        main_window.invoke_scroll_to_bottom();
+    });
+}
+
+fn session_title(entries: &[Entry], name: &str) -> String {
+    match entries.first() {
+        Some(entry) => entry.time.format("%B %-d, %Y at %H:%M").to_string(),
+        None => name.to_string(),
+    }
+}
+
+/// Whether anything said in a session contains `query`, ignoring case.
+fn session_matches(entries: &[Entry], query: &str) -> bool {
+    let query = query.to_lowercase();
+    entries.iter().any(|entry| match &entry.message {
+        EntryMessage::User { text } | EntryMessage::Assistant { text } => {
+            text.to_lowercase().contains(&query)
+        }
+        _ => false,
+    })
+}
+
+/// The saved sessions matching a search, newest first.
+fn session_items(query: &str) -> Vec<SessionItem> {
+    let sessions = sessions::list().unwrap_or_default();
+    sessions
+        .iter()
+        .rev()
+        .filter_map(|session| {
+            let entries = sessions::load(session).ok()?;
+            if !query.trim().is_empty() && !session_matches(&entries, query.trim()) {
+                return None;
+            }
+            let name = sessions::name_of(session);
+            let preview = entries
+                .iter()
+                .find_map(|entry| match &entry.message {
+                    EntryMessage::User { text } => Some(text.lines().next().unwrap_or_default()),
+                    _ => None,
+                })
+                .unwrap_or_default();
+            Some(SessionItem {
+                title: session_title(&entries, &name).into(),
+                preview: preview.into(),
+                name: name.into(),
+            })
+        })
+        .collect()
+}
+
+/// Reloads the sessions list, keeping the current search.
+fn refresh_sessions(main_window: &slint::Weak<MainWindow>) {
+    let _ = main_window.upgrade_in_event_loop(|main_window| {
+        let query = main_window.get_search().to_string();
+        let main_window_weak = main_window.as_weak();
+        // Searching reads every session, so it's kept off the UI thread.
+        thread::spawn(move || {
+            let items = session_items(&query);
+            let _ = main_window_weak.upgrade_in_event_loop(move |main_window| {
+                main_window.set_sessions(ModelRc::from(Rc::new(VecModel::from(items))));
+            });
+        });
+    });
+}
+
+/// The messages of a saved session, shown the same way as the live chat.
+fn session_messages(entries: Vec<Entry>) -> Vec<ChatMessage> {
+    entries
+        .into_iter()
+        .filter_map(|entry| match entry.message {
+            EntryMessage::User { text } => Some(chat_message("user", text)),
+            EntryMessage::Assistant { text } => Some(chat_message("assistant", text)),
+            EntryMessage::ToolCall { name, arguments } => Some(chat_message(
+                "function",
+                format!("Called {} {}", name, arguments),
+            )),
+            EntryMessage::ToolResult { .. } => None,
+        })
+        .collect()
+}
+
+fn set_status(main_window: &slint::Weak<MainWindow>, status: String) {
+    let _ = main_window.upgrade_in_event_loop(move |main_window| {
+        main_window.set_status(status.into());
+    });
+}
+
+fn set_alarm_ringing(main_window: &slint::Weak<MainWindow>, ringing: bool) {
+    let _ = main_window.upgrade_in_event_loop(move |main_window| {
+        main_window.set_alarm_ringing(ringing);
+    });
+}
+
+fn copy_to_clipboard(text: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let mut clipboard: ClipboardContext = ClipboardProvider::new()?;
+    clipboard.set_contents(text.to_string())
+}
+
+/// Shows the assistant's conversation as it happens, reconnecting whenever the assistant restarts.
+fn follow_conversation(main_window: slint::Weak<MainWindow>) {
+    let mut shown_error = false;
+    loop {
+        match instance::subscribe() {
+            Ok(events) => {
+                shown_error = false;
+                // Whether the AI is partway through a response that tokens should be added to.
+                let mut responding = false;
+                for event in events {
+                    let update = match event {
+                        ConversationEvent::User { text } => {
+                            responding = false;
+                            ChatUpdate::Push(chat_message("user", text))
+                        }
+                        ConversationEvent::Token { text } if responding => {
+                            ChatUpdate::AppendToLast(text)
+                        }
+                        ConversationEvent::Token { text } => {
+                            responding = true;
+                            ChatUpdate::Push(chat_message("assistant", text))
+                        }
+                        ConversationEvent::FunctionCall { name, arguments } => {
+                            responding = false;
+                            ChatUpdate::Push(chat_message(
+                                "function",
+                                format!("Called {} {}", name, arguments),
+                            ))
+                        }
+                        ConversationEvent::ResponseDone => {
+                            responding = false;
+                            // The response was just saved, maybe to a brand new session.
+                            refresh_sessions(&main_window);
+                            continue;
+                        }
+                        ConversationEvent::AlarmStarted => {
+                            set_alarm_ringing(&main_window, true);
+                            continue;
+                        }
+                        ConversationEvent::AlarmStopped => {
+                            set_alarm_ringing(&main_window, false);
+                            continue;
+                        }
+                        // Tool results are for the AI, and what its voice is saying is already shown as text.
+                        ConversationEvent::FunctionResult { .. }
+                        | ConversationEvent::SpeechStarted { .. }
+                        | ConversationEvent::SpeechFinished { .. }
+                        | ConversationEvent::SpeechStopped
+                        | ConversationEvent::ListeningStarted
+                        | ConversationEvent::ListeningLevel { .. }
+                        | ConversationEvent::ListeningStopped => continue,
+                    };
+                    apply(&main_window, update);
+                }
+                set_alarm_ringing(&main_window, false);
+                apply(
+                    &main_window,
+                    ChatUpdate::Push(chat_message("error", "The assistant stopped running.")),
+                );
+            }
+            Err(err) if !shown_error => {
+                shown_error = true;
+                apply(
+                    &main_window,
+                    ChatUpdate::Push(chat_message(
+                        "error",
+                        format!("{:#}. Start it by running quick-assistant.", err),
+                    )),
+                );
+            }
+            Err(_) => {}
+        }
+        thread::sleep(RECONNECT_INTERVAL);
+    }
+}
+
+fn main() {
     // let config = slint_build::CompilerConfiguration::new().with_style("hello".to_string());
     // slint_build::compile_with_config();
 
     let main_window = MainWindow::new().unwrap();
+    main_window.set_messages(ModelRc::from(Rc::new(VecModel::<ChatMessage>::default())));
 
     let main_window_weak = main_window.as_weak();
     main_window.on_handle_message(move || {
@@ -90,12 +459,22 @@ fn main() {
             return;
         }
 
-        let mut current_message_history: String = main_window.get_message_history().into();
-        current_message_history.push_str(&format!("\n{}", message));
-
-        main_window.set_message_history(current_message_history.into());
         main_window.set_message("".into());
         main_window.set_send_button_enabled(false);
+
+        // The message is shown once the assistant hears it, like anything said by voice.
+        let main_window_weak = main_window_weak.clone();
+        thread::spawn(move || {
+            if let Err(err) = instance::send_message(&message) {
+                apply(
+                    &main_window_weak,
+                    ChatUpdate::Push(chat_message(
+                        "error",
+                        format!("Failed to send message: {:#}", err),
+                    )),
+                );
+            }
+        });
     });
 
     let main_window_weak = main_window.as_weak();
@@ -105,5 +484,84 @@ fn main() {
         main_window.set_send_button_enabled(!message.trim().is_empty());
     });
 
+    let main_window_weak = main_window.as_weak();
+    main_window.on_search_sessions(move || refresh_sessions(&main_window_weak));
+
+    let main_window_weak = main_window.as_weak();
+    main_window.on_open_session(move |name| {
+        let main_window = main_window_weak.unwrap();
+        let entries = match sessions::find(Some(&name)).and_then(|session| sessions::load(&session))
+        {
+            Ok(entries) => entries,
+            Err(err) => {
+                main_window.set_status(format!("{:#}", err).into());
+                return;
+            }
+        };
+        main_window.set_viewed_session_title(session_title(&entries, &name).into());
+        main_window.set_session_messages(ModelRc::from(Rc::new(VecModel::from(session_messages(
+            entries,
+        )))));
+        main_window.set_viewed_session(name);
+        main_window.set_status("".into());
+    });
+
+    let main_window_weak = main_window.as_weak();
+    main_window.on_show_live_chat(move || {
+        let main_window = main_window_weak.unwrap();
+        main_window.set_viewed_session("".into());
+        main_window.set_status("".into());
+        main_window.invoke_scroll_to_bottom();
+    });
+
+    let main_window_weak = main_window.as_weak();
+    main_window.on_load_session(move || {
+        let main_window = main_window_weak.unwrap();
+        let name = main_window.get_viewed_session().to_string();
+        let main_window_weak = main_window_weak.clone();
+        thread::spawn(move || match instance::load_session(&name) {
+            Ok(()) => {
+                let _ = main_window_weak.upgrade_in_event_loop(|main_window| {
+                    main_window.set_viewed_session("".into());
+                    main_window.set_status(
+                        "Loaded the conversation. The assistant can pick up where it left off."
+                            .into(),
+                    );
+                    main_window.invoke_scroll_to_bottom();
+                });
+            }
+            Err(err) => set_status(
+                &main_window_weak,
+                format!("Failed to load the conversation: {:#}", err),
+            ),
+        });
+    });
+
+    let main_window_weak = main_window.as_weak();
+    main_window.on_copy_text(move |text| {
+        let status = match copy_to_clipboard(&text) {
+            Ok(()) => "Copied to the clipboard.".to_string(),
+            Err(err) => format!("Failed to copy: {}", err),
+        };
+        main_window_weak.unwrap().set_status(status.into());
+    });
+
+    let main_window_weak = main_window.as_weak();
+    main_window.on_dismiss_alarm(move || {
+        let main_window_weak = main_window_weak.clone();
+        thread::spawn(move || {
+            if let Err(err) = instance::send(instance::InstanceCommand::DismissAlarm) {
+                set_status(
+                    &main_window_weak,
+                    format!("Failed to dismiss the alarm: {:#}", err),
+                );
+            }
+        });
+    });
+
+    let main_window_weak = main_window.as_weak();
+    thread::spawn(move || follow_conversation(main_window_weak));
+    refresh_sessions(&main_window.as_weak());
+
     main_window.run().unwrap();
 }
@@ -0,0 +1,218 @@
+//! Listing and connecting paired Bluetooth devices for the list_bluetooth_devices and
+//! connect_bluetooth_device tools. Linux uses bluetoothctl and macOS uses blueutil.
+//! Once headphones or a speaker connects, speech is moved over to it.
+#![cfg_attr(not(any(target_os = "linux", target_os = "macos")), allow(dead_code))]
+
+use anyhow::{bail, Context};
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::audio::{self, Channel};
+
+/// How long to wait for a newly connected device to show up as an output device.
+const OUTPUT_DEVICE_WAIT: Duration = Duration::from_secs(6);
+
+/// A paired Bluetooth device.
+pub struct Device {
+    pub name: String,
+    pub address: String,
+    pub connected: bool,
+}
+
+/// The devices paired with this computer.
+pub fn list() -> Result<Vec<Device>, anyhow::Error> {
+    #[cfg(target_os = "linux")]
+    return linux::list();
+
+    #[cfg(target_os = "macos")]
+    return macos::list();
+
+    #[allow(unreachable_code)]
+    Err(anyhow::anyhow!(
+        "Bluetooth devices can't be managed on this system"
+    ))
+}
+
+fn connect_address(address: &str) -> Result<(), anyhow::Error> {
+    #[cfg(target_os = "linux")]
+    return linux::connect(address);
+
+    #[cfg(target_os = "macos")]
+    return macos::connect(address);
+
+    #[allow(unreachable_code)]
+    Err(anyhow::anyhow!(
+        "Bluetooth devices can't be managed on this system"
+    ))
+}
+
+/// Describes the paired devices for the AI, one per line.
+pub fn describe(devices: &[Device]) -> String {
+    if devices.is_empty() {
+        return "No Bluetooth devices are paired with this computer.".to_string();
+    }
+    let mut info = "=== Paired Bluetooth devices ===\n".to_string();
+    for device in devices {
+        info.push_str(&format!(
+            "- {} ({}){}\n",
+            device.name,
+            device.address,
+            if device.connected { ", connected" } else { "" }
+        ));
+    }
+    info
+}
+
+/// Finds the paired device whose name or address matches `name`.
+fn find(mut devices: Vec<Device>, name: &str) -> Result<Device, anyhow::Error> {
+    let query = name.trim().to_lowercase();
+    if query.is_empty() {
+        bail!("No device name was given");
+    }
+    if let Some(index) = devices.iter().position(|device| {
+        device.name.to_lowercase() == query || device.address.to_lowercase() == query
+    }) {
+        return Ok(devices.swap_remove(index));
+    }
+    let mut matches: Vec<Device> = devices
+        .into_iter()
+        .filter(|device| device.name.to_lowercase().contains(&query))
+        .collect();
+    match matches.len() {
+        0 => bail!("No paired Bluetooth device is named \"{}\"", name),
+        1 => Ok(matches.remove(0)),
+        _ => bail!(
+            "Several paired devices match \"{}\": {}. Ask the user which one they mean.",
+            name,
+            matches
+                .iter()
+                .map(|device| device.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+/// Connects the paired device named `name`, then plays every channel on it if it's an output
+/// device. Returns what happened, for the AI.
+pub fn connect(name: &str) -> Result<String, anyhow::Error> {
+    let device = find(list()?, name)?;
+    if !device.connected {
+        connect_address(&device.address)
+            .with_context(|| format!("Failed to connect {}", device.name))?;
+    }
+    let mut info = match device.connected {
+        true => format!("{} was already connected.", device.name),
+        false => format!("Connected {}.", device.name),
+    };
+    match follow_output(&device.name) {
+        Some(output) => info.push_str(&format!(" Speech now plays on {}.", output)),
+        None => info.push_str(
+            " It isn't an output device this app can see, so speech plays where it did before.",
+        ),
+    }
+    Ok(info)
+}
+
+/// Waits for an output device named like the Bluetooth device to show up, and plays every
+/// channel on it. Returns the output device's name if one showed up.
+fn follow_output(name: &str) -> Option<String> {
+    let name = name.to_lowercase();
+    let start = Instant::now();
+    loop {
+        let output = audio::output_device_names()
+            .unwrap_or_default()
+            .into_iter()
+            .find(|output| output.to_lowercase().contains(&name));
+        if let Some(output) = output {
+            for channel in [Channel::Speech, Channel::Alerts, Channel::Ui] {
+                if let Err(err) = audio::set_output_device(channel, Some(output.clone())) {
+                    tracing::warn!("Failed to play {:?} on {}: {:#}", channel, output, err);
+                    return None;
+                }
+            }
+            return Some(output);
+        }
+        if start.elapsed() >= OUTPUT_DEVICE_WAIT {
+            return None;
+        }
+        thread::sleep(Duration::from_millis(500));
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use super::Device;
+    use crate::system_command::output;
+
+    /// Reads lines like "Device AA:BB:CC:DD:EE:FF Name".
+    fn parse_devices(text: &str) -> Vec<(String, String)> {
+        text.lines()
+            .filter_map(|line| {
+                let rest = line.trim().strip_prefix("Device ")?;
+                let (address, name) = rest.split_once(' ').unwrap_or((rest, rest));
+                Some((address.to_string(), name.trim().to_string()))
+            })
+            .collect()
+    }
+
+    pub fn list() -> Result<Vec<Device>, anyhow::Error> {
+        // Older versions of bluetoothctl only have paired-devices.
+        let paired = output("bluetoothctl", &["devices", "Paired"])
+            .or_else(|_| output("bluetoothctl", &["paired-devices"]))?;
+        parse_devices(&paired)
+            .into_iter()
+            .map(|(address, name)| {
+                let info = output("bluetoothctl", &["info", &address])?;
+                Ok(Device {
+                    connected: info.lines().any(|line| line.trim() == "Connected: yes"),
+                    name,
+                    address,
+                })
+            })
+            .collect()
+    }
+
+    pub fn connect(address: &str) -> Result<(), anyhow::Error> {
+        let printed = output("bluetoothctl", &["connect", address])?;
+        // bluetoothctl exits successfully even when connecting fails.
+        if printed.contains("Failed to connect") {
+            let reason = printed
+                .lines()
+                .find(|line| line.contains("Failed to connect"))
+                .unwrap_or_default();
+            anyhow::bail!("{}. Is the device on and nearby?", reason.trim());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod macos {
+    use super::Device;
+    use crate::system_command::{output, run};
+
+    pub fn list() -> Result<Vec<Device>, anyhow::Error> {
+        let paired = output("blueutil", &["--paired", "--format", "json"])?;
+        let devices: Vec<serde_json::Value> = serde_json::from_str(&paired)?;
+        Ok(devices
+            .into_iter()
+            .filter_map(|device| {
+                Some(Device {
+                    name: device["name"]
+                        .as_str()
+                        .unwrap_or("Unnamed device")
+                        .to_string(),
+                    address: device["address"].as_str()?.to_string(),
+                    connected: device["connected"].as_bool().unwrap_or(false),
+                })
+            })
+            .collect())
+    }
+
+    pub fn connect(address: &str) -> Result<(), anyhow::Error> {
+        run("blueutil", &["--connect", address])
+    }
+}
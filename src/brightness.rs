@@ -0,0 +1,250 @@
+//! Reading and changing the screen's brightness, as a percentage.
+//! Linux uses the kernel's backlight interface, falling back to `brightnessctl` where that isn't
+//! writable. Windows uses WMI for built-in screens and DDC/CI for external monitors.
+
+use anyhow::{anyhow, bail};
+
+/// The screen's brightness, from 0 to 100.
+pub fn get() -> Result<u32, anyhow::Error> {
+    #[cfg(target_os = "linux")]
+    return linux::get();
+
+    #[cfg(windows)]
+    return windows::get();
+
+    #[allow(unreachable_code)]
+    Err(anyhow!(
+        "Reading the screen brightness isn't supported on this system"
+    ))
+}
+
+/// Sets the screen's brightness, from 0 to 100.
+pub fn set(percent: u32) -> Result<(), anyhow::Error> {
+    if percent > 100 {
+        bail!("Brightness must be between 0 and 100");
+    }
+
+    #[cfg(target_os = "linux")]
+    return linux::set(percent);
+
+    #[cfg(windows)]
+    return windows::set(percent);
+
+    #[allow(unreachable_code)]
+    Err(anyhow!(
+        "Changing the screen brightness isn't supported on this system"
+    ))
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use anyhow::{bail, Context};
+    use std::{fs, io, path::PathBuf, process::Command};
+
+    const BACKLIGHT_DIR: &str = "/sys/class/backlight";
+
+    /// The first backlight the kernel knows about, usually the laptop's built-in screen.
+    fn backlight() -> Option<PathBuf> {
+        let mut backlights: Vec<PathBuf> = fs::read_dir(BACKLIGHT_DIR)
+            .ok()?
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .collect();
+        backlights.sort();
+        backlights.into_iter().next()
+    }
+
+    fn read_number(path: PathBuf) -> Result<u32, anyhow::Error> {
+        let text = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        text.trim()
+            .parse()
+            .with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    pub fn get() -> Result<u32, anyhow::Error> {
+        if let Some(backlight) = backlight() {
+            let brightness = read_number(backlight.join("brightness"))?;
+            let max = read_number(backlight.join("max_brightness"))?.max(1);
+            return Ok((brightness * 100 + max / 2) / max);
+        }
+        // The machine readable output looks like "intel_backlight,backlight,120000,50%,240000".
+        let output = brightnessctl(&["-m", "info"])?;
+        output
+            .split(',')
+            .find_map(|field| field.strip_suffix('%')?.parse().ok())
+            .context("Failed to read the brightness from brightnessctl")
+    }
+
+    pub fn set(percent: u32) -> Result<(), anyhow::Error> {
+        if let Some(backlight) = backlight() {
+            let max = read_number(backlight.join("max_brightness"))?;
+            let path = backlight.join("brightness");
+            match fs::write(&path, (max * percent / 100).to_string()) {
+                Ok(()) => return Ok(()),
+                // Writing the backlight usually needs root or a udev rule, which brightnessctl comes with.
+                Err(err) if err.kind() == io::ErrorKind::PermissionDenied => {}
+                Err(err) => {
+                    return Err(err).with_context(|| format!("Failed to write {}", path.display()))
+                }
+            }
+        }
+        brightnessctl(&["set", &format!("{}%", percent)])?;
+        Ok(())
+    }
+
+    fn brightnessctl(args: &[&str]) -> Result<String, anyhow::Error> {
+        let output = match Command::new("brightnessctl").args(args).output() {
+            Ok(output) => output,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => bail!(
+                "No writable screen backlight was found. Install brightnessctl to change the brightness."
+            ),
+            Err(err) => return Err(err).context("Failed to run brightnessctl"),
+        };
+        if !output.status.success() {
+            bail!(
+                "brightnessctl failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use anyhow::{bail, Context};
+    use std::{process::Command, ptr};
+    use windows_sys::Win32::{
+        Devices::Display::{
+            DestroyPhysicalMonitors, GetMonitorBrightness, GetNumberOfPhysicalMonitorsFromHMONITOR,
+            GetPhysicalMonitorsFromHMONITOR, SetMonitorBrightness, PHYSICAL_MONITOR,
+        },
+        Foundation::{BOOL, LPARAM, RECT, TRUE},
+        Graphics::Gdi::{EnumDisplayMonitors, HDC, HMONITOR},
+    };
+
+    /// Runs a PowerShell command, for WMI, which only built-in screens support.
+    fn powershell(command: &str) -> Result<String, anyhow::Error> {
+        let output = Command::new("powershell")
+            .args(["-NoProfile", "-NonInteractive", "-Command", command])
+            .output()
+            .context("Failed to run PowerShell")?;
+        if !output.status.success() {
+            bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    fn wmi_get() -> Option<u32> {
+        powershell("(Get-CimInstance -Namespace root/WMI -ClassName WmiMonitorBrightness -ErrorAction Stop | Select-Object -First 1).CurrentBrightness")
+            .ok()?
+            .parse()
+            .ok()
+    }
+
+    fn wmi_set(percent: u32) -> bool {
+        powershell(&format!(
+            "Get-CimInstance -Namespace root/WMI -ClassName WmiMonitorBrightnessMethods -ErrorAction Stop | Invoke-CimMethod -MethodName WmiSetBrightness -Arguments @{{Timeout = 1; Brightness = {}}} -ErrorAction Stop",
+            percent
+        ))
+        .is_ok()
+    }
+
+    unsafe extern "system" fn collect_monitor(
+        monitor: HMONITOR,
+        _: HDC,
+        _: *mut RECT,
+        monitors: LPARAM,
+    ) -> BOOL {
+        (*(monitors as *mut Vec<HMONITOR>)).push(monitor);
+        TRUE
+    }
+
+    /// The monitors that can be controlled over DDC/CI, which is most external monitors.
+    /// They're destroyed when the returned value is dropped.
+    struct PhysicalMonitors(Vec<PHYSICAL_MONITOR>);
+
+    impl PhysicalMonitors {
+        fn new() -> Self {
+            let mut handles: Vec<HMONITOR> = Vec::new();
+            // SAFETY: The callback only pushes onto `handles`, which outlives the call.
+            unsafe {
+                EnumDisplayMonitors(
+                    0,
+                    ptr::null(),
+                    Some(collect_monitor),
+                    &mut handles as *mut Vec<HMONITOR> as LPARAM,
+                );
+            }
+
+            let mut monitors = Vec::new();
+            for handle in handles {
+                let mut count = 0;
+                // SAFETY: `physical` has room for the number of monitors Windows says there are.
+                unsafe {
+                    if GetNumberOfPhysicalMonitorsFromHMONITOR(handle, &mut count) == 0
+                        || count == 0
+                    {
+                        continue;
+                    }
+                    let mut physical: Vec<PHYSICAL_MONITOR> =
+                        vec![std::mem::zeroed(); count as usize];
+                    if GetPhysicalMonitorsFromHMONITOR(handle, count, physical.as_mut_ptr()) != 0 {
+                        monitors.extend(physical);
+                    }
+                }
+            }
+            Self(monitors)
+        }
+
+        /// The minimum, current and maximum brightness of each monitor that supports DDC/CI.
+        fn brightness(&self) -> impl Iterator<Item = (isize, u32, u32, u32)> + '_ {
+            self.0.iter().filter_map(|monitor| {
+                let (mut min, mut current, mut max) = (0, 0, 0);
+                // SAFETY: The handle is valid until the monitors are destroyed.
+                let ok = unsafe {
+                    GetMonitorBrightness(monitor.hPhysicalMonitor, &mut min, &mut current, &mut max)
+                };
+                (ok != 0 && max > min).then_some((monitor.hPhysicalMonitor, min, current, max))
+            })
+        }
+    }
+
+    impl Drop for PhysicalMonitors {
+        fn drop(&mut self) {
+            if !self.0.is_empty() {
+                // SAFETY: The monitors came from GetPhysicalMonitorsFromHMONITOR and are destroyed once.
+                unsafe {
+                    DestroyPhysicalMonitors(self.0.len() as u32, self.0.as_ptr());
+                }
+            }
+        }
+    }
+
+    pub fn get() -> Result<u32, anyhow::Error> {
+        if let Some(percent) = wmi_get() {
+            return Ok(percent);
+        }
+        let monitors = PhysicalMonitors::new();
+        let brightness = monitors.brightness().next();
+        match brightness {
+            Some((_, min, current, max)) => Ok(current.saturating_sub(min) * 100 / (max - min)),
+            None => bail!("No screen that supports changing its brightness was found"),
+        }
+    }
+
+    /// Sets every screen that supports it, so built-in and external screens stay alike.
+    pub fn set(percent: u32) -> Result<(), anyhow::Error> {
+        let mut changed = wmi_set(percent);
+        let monitors = PhysicalMonitors::new();
+        for (handle, min, _, max) in monitors.brightness() {
+            // SAFETY: The handle is valid until the monitors are destroyed.
+            changed |=
+                unsafe { SetMonitorBrightness(handle, min + (max - min) * percent / 100) } != 0;
+        }
+        if !changed {
+            bail!("No screen that supports changing its brightness was found");
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,473 @@
+//! Working out arithmetic exactly for the calculate tool, since the AI's mental math is often wrong.
+//! Supports + - * / % ^, factorials, brackets, constants like pi, and common functions like sqrt.
+
+use anyhow::{anyhow, bail};
+use std::f64::consts;
+
+/// How deeply brackets, signs and powers can nest, so something like "((((…" gives an error
+/// instead of overflowing the stack.
+const MAX_DEPTH: usize = 200;
+
+/// Works out `expression`, like "2 * (3 + 4)^2" or "sqrt(2) / 2".
+pub fn evaluate(expression: &str) -> Result<f64, anyhow::Error> {
+    let tokens = tokenize(expression)?;
+    if tokens.is_empty() {
+        bail!("No expression was given");
+    }
+    let mut parser = Parser {
+        tokens,
+        position: 0,
+        depth: 0,
+    };
+    let value = parser.expression()?;
+    if let Some(token) = parser.peek() {
+        bail!("Unexpected {:?}", token);
+    }
+    if value.is_nan() {
+        bail!("The result isn't a number");
+    }
+    Ok(value)
+}
+
+/// Writes a number the way a person would say it, without float noise like 0.30000000000000004.
+pub fn format_number(value: f64) -> String {
+    if value.is_infinite() {
+        return if value > 0.0 { "infinity" } else { "-infinity" }.to_string();
+    }
+    if value != 0.0 && (value.abs() >= 1e15 || value.abs() < 1e-6) {
+        return format!("{:e}", value);
+    }
+    let rounded = format!("{:.10}", value);
+    let trimmed = rounded.trim_end_matches('0').trim_end_matches('.');
+    match trimmed {
+        "-0" => "0".to_string(),
+        trimmed => trimmed.to_string(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Name(String),
+    Operator(char),
+    Open,
+    Close,
+    Comma,
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, anyhow::Error> {
+    let mut tokens = Vec::new();
+    let mut chars = expression.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '0'..='9' | '.' => {
+                let mut number = String::new();
+                while let Some(&c) = chars.peek() {
+                    // Digits, a decimal point, and an exponent like 1e-3.
+                    let is_exponent_sign = matches!(c, '+' | '-')
+                        && number.ends_with(['e', 'E'])
+                        && number.starts_with(|c: char| c.is_ascii_digit() || c == '.');
+                    if c.is_ascii_digit()
+                        || c == '.'
+                        || c == '_'
+                        || (matches!(c, 'e' | 'E') && !number.contains(['e', 'E']))
+                        || is_exponent_sign
+                    {
+                        if c != '_' {
+                            number.push(c);
+                        }
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                // "2e" is 2 times e, not an unfinished exponent.
+                if number.ends_with(['e', 'E']) {
+                    number.pop();
+                    tokens.push(Token::Number(parse_number(&number)?));
+                    tokens.push(Token::Name("e".to_string()));
+                } else {
+                    tokens.push(Token::Number(parse_number(&number)?));
+                }
+            }
+            c if c.is_alphabetic() => {
+                let mut name = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_alphanumeric() || c == '_' {
+                        name.push(c);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                tokens.push(Token::Name(name.to_lowercase()));
+            }
+            '+' | '-' | '*' | '/' | '%' | '^' | '!' => {
+                chars.next();
+                // ** is the same as ^.
+                if c == '*' && chars.peek() == Some(&'*') {
+                    chars.next();
+                    tokens.push(Token::Operator('^'));
+                } else {
+                    tokens.push(Token::Operator(c));
+                }
+            }
+            '×' | '·' => {
+                chars.next();
+                tokens.push(Token::Operator('*'));
+            }
+            '÷' => {
+                chars.next();
+                tokens.push(Token::Operator('/'));
+            }
+            '−' => {
+                chars.next();
+                tokens.push(Token::Operator('-'));
+            }
+            '(' | '[' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' | ']' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            ',' => {
+                chars.next();
+                tokens.push(Token::Comma);
+            }
+            c => bail!("Unexpected character '{}'", c),
+        }
+    }
+    Ok(tokens)
+}
+
+fn parse_number(number: &str) -> Result<f64, anyhow::Error> {
+    number
+        .parse()
+        .map_err(|_| anyhow!("\"{}\" isn't a number", number))
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Runs `parse` one level deeper, failing once the expression is nested too deeply.
+    fn nested(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<f64, anyhow::Error>,
+    ) -> Result<f64, anyhow::Error> {
+        if self.depth >= MAX_DEPTH {
+            bail!("The expression is nested too deeply");
+        }
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    /// Addition and subtraction.
+    fn expression(&mut self) -> Result<f64, anyhow::Error> {
+        let mut value = self.term()?;
+        loop {
+            if self.eat(&Token::Operator('+')) {
+                value += self.term()?;
+            } else if self.eat(&Token::Operator('-')) {
+                value -= self.term()?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    /// Multiplication, division and remainders, including "2pi" and "3(4 + 5)".
+    fn term(&mut self) -> Result<f64, anyhow::Error> {
+        let mut value = self.unary()?;
+        loop {
+            if self.eat(&Token::Operator('*')) {
+                value *= self.unary()?;
+            } else if self.eat(&Token::Operator('/')) {
+                let divisor = self.unary()?;
+                if divisor == 0.0 {
+                    bail!("Can't divide by zero");
+                }
+                value /= divisor;
+            } else if self.eat(&Token::Operator('%')) {
+                let divisor = self.unary()?;
+                if divisor == 0.0 {
+                    bail!("Can't divide by zero");
+                }
+                value %= divisor;
+            } else if matches!(self.peek(), Some(Token::Open | Token::Name(_))) {
+                value *= self.unary()?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    /// Signs, which bind looser than powers, so -2^2 is -4.
+    fn unary(&mut self) -> Result<f64, anyhow::Error> {
+        self.nested(|parser| {
+            if parser.eat(&Token::Operator('-')) {
+                return Ok(-parser.unary()?);
+            }
+            if parser.eat(&Token::Operator('+')) {
+                return parser.unary();
+            }
+            parser.power()
+        })
+    }
+
+    /// Powers, which group from the right, so 2^3^2 is 2^9.
+    fn power(&mut self) -> Result<f64, anyhow::Error> {
+        self.nested(|parser| {
+            let base = parser.factorial()?;
+            if parser.eat(&Token::Operator('^')) {
+                return Ok(base.powf(parser.unary()?));
+            }
+            Ok(base)
+        })
+    }
+
+    fn factorial(&mut self) -> Result<f64, anyhow::Error> {
+        let mut value = self.primary()?;
+        while self.eat(&Token::Operator('!')) {
+            if value < 0.0 || value.fract() != 0.0 {
+                bail!("Factorials are only for whole numbers that aren't negative");
+            }
+            if value > 170.0 {
+                bail!("{}! is too big to work out", value);
+            }
+            value = (1..=value as u64).map(|n| n as f64).product();
+        }
+        Ok(value)
+    }
+
+    fn primary(&mut self) -> Result<f64, anyhow::Error> {
+        match self.next() {
+            Some(Token::Number(number)) => Ok(number),
+            Some(Token::Open) => {
+                let value = self.expression()?;
+                if !self.eat(&Token::Close) {
+                    bail!("A bracket isn't closed");
+                }
+                Ok(value)
+            }
+            Some(Token::Name(name)) => {
+                if self.eat(&Token::Open) {
+                    let mut args = vec![self.expression()?];
+                    while self.eat(&Token::Comma) {
+                        args.push(self.expression()?);
+                    }
+                    if !self.eat(&Token::Close) {
+                        bail!("A bracket isn't closed");
+                    }
+                    return call(&name, &args);
+                }
+                match name.as_str() {
+                    "pi" | "π" => Ok(consts::PI),
+                    "e" => Ok(consts::E),
+                    "tau" | "τ" => Ok(consts::TAU),
+                    "phi" | "φ" => Ok((1.0 + 5f64.sqrt()) / 2.0),
+                    "inf" | "infinity" => Ok(f64::INFINITY),
+                    // Functions of one number can leave out the brackets, like "sqrt 2".
+                    _ if is_function(&name) => {
+                        let arg = self.power()?;
+                        call(&name, &[arg])
+                    }
+                    _ => bail!("Unknown name \"{}\"", name),
+                }
+            }
+            Some(token) => bail!("Unexpected {:?}", token),
+            None => bail!("The expression ends too soon"),
+        }
+    }
+}
+
+fn is_function(name: &str) -> bool {
+    matches!(
+        name,
+        "sqrt"
+            | "cbrt"
+            | "abs"
+            | "ln"
+            | "log"
+            | "log2"
+            | "log10"
+            | "exp"
+            | "sin"
+            | "cos"
+            | "tan"
+            | "asin"
+            | "acos"
+            | "atan"
+            | "sinh"
+            | "cosh"
+            | "tanh"
+            | "floor"
+            | "ceil"
+            | "round"
+            | "trunc"
+            | "sign"
+            | "rad"
+            | "deg"
+    )
+}
+
+fn call(name: &str, args: &[f64]) -> Result<f64, anyhow::Error> {
+    let one = || match args {
+        [x] => Ok(*x),
+        _ => Err(anyhow!("{} takes one number", name)),
+    };
+    Ok(match name {
+        "sqrt" => {
+            let x = one()?;
+            if x < 0.0 {
+                bail!("Can't take the square root of a negative number");
+            }
+            x.sqrt()
+        }
+        "cbrt" => one()?.cbrt(),
+        "abs" => one()?.abs(),
+        "ln" => one()?.ln(),
+        // log(x) is base 10, and log(x, base) is any base.
+        "log" => match args {
+            [x] => x.log10(),
+            [x, base] => x.log(*base),
+            _ => bail!("log takes a number and an optional base"),
+        },
+        "log2" => one()?.log2(),
+        "log10" => one()?.log10(),
+        "exp" => one()?.exp(),
+        "sin" => one()?.sin(),
+        "cos" => one()?.cos(),
+        "tan" => one()?.tan(),
+        "asin" => one()?.asin(),
+        "acos" => one()?.acos(),
+        "atan" => match args {
+            [x] => x.atan(),
+            [y, x] => y.atan2(*x),
+            _ => bail!("atan takes one or two numbers"),
+        },
+        "sinh" => one()?.sinh(),
+        "cosh" => one()?.cosh(),
+        "tanh" => one()?.tanh(),
+        "floor" => one()?.floor(),
+        "ceil" => one()?.ceil(),
+        "round" => match args {
+            [x] => x.round(),
+            [x, places] => {
+                let scale = 10f64.powi(*places as i32);
+                (x * scale).round() / scale
+            }
+            _ => bail!("round takes a number and an optional number of decimal places"),
+        },
+        "trunc" => one()?.trunc(),
+        "sign" => one()?.signum(),
+        // Converts degrees to radians and back, like sin(rad(30)).
+        "rad" => one()?.to_radians(),
+        "deg" => one()?.to_degrees(),
+        "min" if !args.is_empty() => args.iter().copied().fold(f64::INFINITY, f64::min),
+        "max" if !args.is_empty() => args.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+        _ => bail!("Unknown function \"{}\"", name),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(expression: &str, expected: f64) {
+        let value = evaluate(expression).unwrap();
+        assert!(
+            (value - expected).abs() < 1e-9,
+            "{} gave {}, not {}",
+            expression,
+            value,
+            expected
+        );
+    }
+
+    #[test]
+    fn operators_follow_precedence() {
+        check("2 + 3 * 4", 14.0);
+        check("(2 + 3) * 4", 20.0);
+        check("10 - 4 - 3", 3.0);
+        check("12 / 4 / 3", 1.0);
+        check("7 % 4 * 2", 6.0);
+        check("2 * 3^2", 18.0);
+        check("3!^2", 36.0);
+        check("2pi", 2.0 * consts::PI);
+        check("3(4 + 5)", 27.0);
+    }
+
+    #[test]
+    fn unary_minus_binds_looser_than_powers() {
+        check("-2^2", -4.0);
+        check("(-2)^2", 4.0);
+        check("2^-1", 0.5);
+        check("--3", 3.0);
+        check("4 - -2", 6.0);
+        check("-sqrt 4", -2.0);
+    }
+
+    #[test]
+    fn powers_group_from_the_right() {
+        check("2^3^2", 512.0);
+        check("(2^3)^2", 64.0);
+        check("2**3**2", 512.0);
+    }
+
+    #[test]
+    fn dividing_by_zero_is_an_error() {
+        assert!(evaluate("1 / 0").is_err());
+        assert!(evaluate("5 % (2 - 2)").is_err());
+        assert!(evaluate("sqrt(-1)").is_err());
+    }
+
+    #[test]
+    fn deep_nesting_is_an_error_instead_of_a_stack_overflow() {
+        check(&format!("{}1{}", "(".repeat(50), ")".repeat(50)), 1.0);
+        for expression in [
+            format!("{}1{}", "(".repeat(100_000), ")".repeat(100_000)),
+            format!("{}1", "-".repeat(100_000)),
+            format!("{}2", "sqrt ".repeat(100_000)),
+            format!("2{}", "^2".repeat(100_000)),
+        ] {
+            assert!(evaluate(&expression).is_err());
+        }
+    }
+
+    #[test]
+    fn numbers_are_written_without_float_noise() {
+        assert_eq!(format_number(0.1 + 0.2), "0.3");
+        assert_eq!(format_number(-0.0), "0");
+        assert_eq!(format_number(f64::INFINITY), "infinity");
+    }
+}
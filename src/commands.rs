@@ -0,0 +1,236 @@
+//! Running the commands set up in the config file for the run_command tool.
+//! The AI can only run these, and only fill in the placeholders in their arguments, like `{path}`.
+//! Each filled in argument is passed to the program as it is, without a shell, so it can't run
+//! anything else.
+
+use anyhow::{bail, Context};
+use regex::Regex;
+use std::{
+    collections::HashMap,
+    io::Read,
+    process::{Child, Command, Stdio},
+    sync::{LazyLock, OnceLock},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::config::CommandConfig;
+
+/// How long a command can run before it's stopped, unless the config file says otherwise.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+/// How long to wait for the rest of a command's output once it's finished, in case something it
+/// started is still holding its stdout or stderr open.
+const OUTPUT_GRACE: Duration = Duration::from_secs(2);
+/// The most characters of each of stdout and stderr given to the AI.
+const MAX_OUTPUT_CHARS: usize = 4_000;
+
+static COMMANDS: OnceLock<HashMap<String, CommandConfig>> = OnceLock::new();
+
+static PLACEHOLDER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{([A-Za-z0-9_-]+)\}").unwrap());
+
+/// Sets the commands that can be run, from the config file.
+pub fn configure(commands: HashMap<String, CommandConfig>) {
+    let _ = COMMANDS.set(commands);
+}
+
+fn commands() -> &'static HashMap<String, CommandConfig> {
+    COMMANDS.get_or_init(HashMap::new)
+}
+
+/// The names of the placeholders in a command's arguments, in the order they first appear.
+fn placeholders(command: &CommandConfig) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for arg in &command.args {
+        for capture in PLACEHOLDER.captures_iter(arg) {
+            if !names.iter().any(|name| name == &capture[1]) {
+                names.push(capture[1].to_string());
+            }
+        }
+    }
+    names
+}
+
+/// Describes the commands that can be run, for the run_command tool's description.
+pub fn describe() -> String {
+    let mut names: Vec<&String> = commands().keys().collect();
+    if names.is_empty() {
+        return "No commands are set up in the config file, so none can be run.".to_string();
+    }
+    names.sort();
+    let mut info = "The commands are:".to_string();
+    for name in names {
+        let command = &commands()[name];
+        info.push_str(&format!(" {}", name));
+        let placeholders = placeholders(command);
+        if !placeholders.is_empty() {
+            info.push_str(&format!(" (args: {})", placeholders.join(", ")));
+        }
+        match &command.description {
+            Some(description) => info.push_str(&format!(": {};", description)),
+            None => info.push(';'),
+        }
+    }
+    info.pop();
+    info.push('.');
+    info
+}
+
+/// Runs the command named `name`, with its placeholders filled in from `values`.
+/// Returns its exit status and what it printed, cut short if there was a lot.
+pub fn run(
+    name: &str,
+    values: &serde_json::Map<String, serde_json::Value>,
+) -> Result<String, anyhow::Error> {
+    let Some(command) = commands().get(name) else {
+        bail!("There's no command named \"{}\". {}", name, describe());
+    };
+
+    let placeholders = placeholders(command);
+    let mut filled: HashMap<&str, String> = HashMap::new();
+    for placeholder in &placeholders {
+        let value = match values.get(placeholder) {
+            Some(serde_json::Value::String(value)) => value.clone(),
+            Some(serde_json::Value::Number(value)) => value.to_string(),
+            Some(serde_json::Value::Bool(value)) => value.to_string(),
+            Some(_) => bail!("The {} arg must be a string", placeholder),
+            None => bail!("The {} arg is missing", placeholder),
+        };
+        // Otherwise a value could add an option the command wasn't set up with.
+        if value.starts_with('-') {
+            bail!("The {} arg can't start with a dash", placeholder);
+        }
+        filled.insert(placeholder, value);
+    }
+    if let Some(extra) = values.keys().find(|key| !placeholders.contains(key)) {
+        bail!("The {} command has no {} arg", name, extra);
+    }
+    let args: Vec<String> = command
+        .args
+        .iter()
+        .map(|arg| {
+            let arg = PLACEHOLDER
+                .replace_all(arg, |capture: &regex::Captures| filled[&capture[1]].clone())
+                .to_string();
+            // There's no shell to expand `~`, so it's done here.
+            match arg.strip_prefix("~/") {
+                Some(rest) => dirs::home_dir()
+                    .unwrap_or_default()
+                    .join(rest)
+                    .to_string_lossy()
+                    .to_string(),
+                None => arg,
+            }
+        })
+        .collect();
+
+    let mut child = Command::new(&command.program);
+    child
+        .args(&args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    // In its own process group, so anything it starts is stopped with it.
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut child, 0);
+    let mut child = child
+        .spawn()
+        .with_context(|| format!("Failed to start {}", command.program))?;
+    // Both pipes are read as the command runs, so it can't get stuck with a full pipe.
+    let stdout = read_in_background(child.stdout.take());
+    let stderr = read_in_background(child.stderr.take());
+
+    let timeout = command
+        .timeout_seconds
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_TIMEOUT);
+    let start = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break Some(status);
+        }
+        if start.elapsed() >= timeout {
+            kill(&mut child);
+            break None;
+        }
+        thread::sleep(Duration::from_millis(50));
+    };
+    let output_deadline = Instant::now() + OUTPUT_GRACE;
+    let stdout = stdout.recv_deadline(output_deadline).unwrap_or_default();
+    let stderr = stderr.recv_deadline(output_deadline).unwrap_or_default();
+
+    let mut info = match status {
+        Some(status) => match status.code() {
+            Some(code) => format!("{} exited with code {}.\n", name, code),
+            None => format!("{} was stopped by a signal.\n", name),
+        },
+        None => format!(
+            "{} was stopped after running for {} seconds.\n",
+            name,
+            timeout.as_secs()
+        ),
+    };
+    for (label, output) in [("stdout", stdout), ("stderr", stderr)] {
+        let output = output.trim();
+        if !output.is_empty() {
+            info.push_str(&format!(
+                "=== {} ===\n{}\n",
+                label,
+                crate::truncate(output, MAX_OUTPUT_CHARS)
+            ));
+        }
+    }
+    Ok(info)
+}
+
+/// Stops a command that ran for too long, along with anything it started.
+fn kill(child: &mut Child) {
+    #[cfg(unix)]
+    // SAFETY: killpg only sends a signal. The group is the child's own, from process_group(0).
+    unsafe {
+        libc::killpg(child.id() as libc::pid_t, libc::SIGKILL);
+    }
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// Reads all of `pipe` on another thread. The output is sent once the pipe closes.
+fn read_in_background<R: Read + Send + 'static>(pipe: Option<R>) -> flume::Receiver<String> {
+    let (tx, rx) = flume::bounded(1);
+    thread::spawn(move || {
+        let mut bytes = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut bytes);
+        }
+        let _ = tx.send(String::from_utf8_lossy(&bytes).to_string());
+    });
+    rx
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_command_that_runs_too_long_is_stopped_with_what_it_started() {
+        configure(HashMap::from([(
+            "hang".to_string(),
+            CommandConfig {
+                program: "sh".to_string(),
+                // The background sleep holds stdout open after sh is killed, unless it's killed too.
+                args: vec![
+                    "-c".to_string(),
+                    "echo started; sleep 60 & sleep 60".to_string(),
+                ],
+                description: None,
+                timeout_seconds: Some(1),
+            },
+        )]));
+
+        let start = Instant::now();
+        let info = run("hang", &serde_json::Map::new()).unwrap();
+        assert!(start.elapsed() < Duration::from_secs(10));
+        assert!(info.contains("was stopped after running for 1 seconds"));
+        assert!(info.contains("started"));
+    }
+}
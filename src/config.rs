@@ -0,0 +1,406 @@
+use anyhow::Context;
+use clap::{parser::ValueSource, ArgMatches, ValueEnum};
+use serde::Deserialize;
+use std::{collections::HashMap, fs, io, path::PathBuf, sync::LazyLock};
+use tracing::warn;
+
+use crate::{easy_rdev_key::PTTKey, options::Opt, VoiceEnum};
+
+pub static CONFIG_PATH: LazyLock<PathBuf> = LazyLock::new(|| {
+    dirs::config_dir()
+        .unwrap()
+        .join("quick-assistant")
+        .join("config.toml")
+});
+
+/// Settings read from the config file. Anything left out keeps its default.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Config {
+    /// The audio device to record from, like `--device`.
+    pub device: Option<String>,
+    /// The push-to-talk key, like `--ptt-key`.
+    pub ptt_key: Option<String>,
+    /// The push-to-talk key as a keycode, like `--special-ptt-key`.
+    pub special_ptt_key: Option<u32>,
+    /// The voice the AI speaks with, like `--ai-voice`.
+    pub ai_voice: Option<String>,
+    pub sounds: SoundOverrides,
+    pub terminal: TerminalConfig,
+    pub spotify: SpotifyConfig,
+    pub files: FilesConfig,
+    pub open: OpenConfig,
+    pub clipboard: ClipboardConfig,
+    pub screenshots: ScreenshotsConfig,
+    pub email: EmailConfig,
+    pub notes: NotesConfig,
+    pub finance: FinanceConfig,
+    pub news: NewsConfig,
+    pub git: GitConfig,
+    pub location: LocationConfig,
+    pub monitoring: MonitoringConfig,
+    pub songs: SongsConfig,
+    pub morning: MorningConfig,
+    pub routines: HashMap<String, RoutineConfig>,
+    /// The people message_contact can write to, by name.
+    pub contacts: HashMap<String, ContactConfig>,
+    /// Commands the run_command tool can run, by name.
+    pub commands: HashMap<String, CommandConfig>,
+    /// Named sets of settings, like `[profile.work]`, chosen with `--profile` or the switch_profile tool.
+    pub profile: HashMap<String, Profile>,
+}
+
+/// Settings a profile changes. Anything left out keeps its value from the command line.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Profile {
+    pub model: Option<String>,
+    pub voice: Option<String>,
+    pub ptt_key: Option<String>,
+    /// The names of the tools the AI can use. Every tool is available when this is left out.
+    pub tools: Option<Vec<String>>,
+}
+
+/// Sound files that replace the built-in sounds. An empty path turns a sound off.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct SoundOverrides {
+    pub failed: Option<PathBuf>,
+    pub alarm: Option<PathBuf>,
+    pub recording_started: Option<PathBuf>,
+    pub recording_stopped: Option<PathBuf>,
+    pub thinking: Option<PathBuf>,
+    pub function_invoked: Option<PathBuf>,
+}
+
+/// How the conversation is printed to the terminal.
+#[derive(Debug, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct TerminalConfig {
+    /// Colors are names like "green" or "bright blue", or hex like "#00ff00".
+    pub user_color: String,
+    pub ai_color: String,
+    pub error_color: String,
+    /// The color of code in the AI's responses.
+    pub code_color: String,
+    /// Print the time before each message.
+    pub timestamps: bool,
+    /// Wrap text at this many columns. 0 turns wrapping off. Defaults to the terminal's width, where it's known.
+    pub wrap_width: Option<usize>,
+    /// Show the AI's markdown as formatting, like bold text and bullet points, instead of as symbols.
+    pub markdown: bool,
+}
+
+impl Default for TerminalConfig {
+    fn default() -> Self {
+        Self {
+            user_color: "#00ff00".to_string(),
+            ai_color: "#0000ff".to_string(),
+            error_color: "#ff0000".to_string(),
+            code_color: "yellow".to_string(),
+            timestamps: false,
+            wrap_width: None,
+            markdown: true,
+        }
+    }
+}
+
+/// Lets the AI play music on the user's Spotify account.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct SpotifyConfig {
+    /// The client ID of an app made at https://developer.spotify.com/dashboard,
+    /// with http://127.0.0.1:47614/callback as a redirect URI.
+    pub client_id: Option<String>,
+}
+
+/// Where the AI can look for files.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct FilesConfig {
+    /// The folders search_files can search. Defaults to the home folder's documents, downloads,
+    /// desktop, music, pictures and videos.
+    pub allowed_roots: Option<Vec<PathBuf>>,
+}
+
+/// What the AI can open with open_url and open_path.
+#[derive(Debug, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct OpenConfig {
+    /// Sites on these domains, and their subdomains, open without asking, like "github.com".
+    pub allowed_domains: Vec<String>,
+    /// Ask before opening other sites, or paths outside the folders search_files can search.
+    pub confirm_others: bool,
+}
+
+impl Default for OpenConfig {
+    fn default() -> Self {
+        Self {
+            allowed_domains: Vec::new(),
+            confirm_others: true,
+        }
+    }
+}
+
+/// How the AI can use the clipboard.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct ClipboardConfig {
+    /// Have the AI ask before it reads the clipboard, which can hold passwords.
+    pub confirm_reads: bool,
+}
+
+/// Where take_screenshot saves screenshots.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct ScreenshotsConfig {
+    /// Defaults to a Screenshots folder in the pictures folder.
+    pub folder: Option<PathBuf>,
+}
+
+/// The account send_email sends from. The password is kept in the system keyring.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct EmailConfig {
+    /// The SMTP server, like "smtp.gmail.com".
+    pub smtp_host: Option<String>,
+    /// Defaults to 587. Port 465 is also supported.
+    pub smtp_port: Option<u16>,
+    /// The address emails are sent from.
+    pub from: Option<String>,
+    /// The username to log in with. Defaults to the address.
+    pub username: Option<String>,
+}
+
+/// Where append_note keeps the daily notes.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct NotesConfig {
+    /// Defaults to a quick-assistant/notes folder in the documents folder.
+    pub folder: Option<PathBuf>,
+}
+
+/// Where get_stock_quote gets quotes from.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct FinanceConfig {
+    /// A free key from https://www.alphavantage.co. Yahoo Finance is used without one.
+    pub alpha_vantage_key: Option<String>,
+}
+
+/// Where get_news_headlines reads headlines from.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct NewsConfig {
+    /// The URLs of RSS or Atom feeds. Defaults to BBC News and NPR.
+    pub feeds: Option<Vec<String>>,
+}
+
+/// The repos git_status and summarize_recent_commits can check.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct GitConfig {
+    /// Paths to the repos, which are named after their folders.
+    pub repos: Vec<String>,
+}
+
+/// Someone message_contact can write to, like `[contacts."Anna Smith"]`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct ContactConfig {
+    /// With the country code, like "+1 555 123 4567", for WhatsApp and text messages.
+    pub phone: Option<String>,
+    pub email: Option<String>,
+    /// How to message them when the user doesn't say: "whatsapp", "sms" or "email".
+    /// Defaults to a text message if there's a phone number, or email if not.
+    pub via: Option<String>,
+}
+
+/// Where the user lives, used instead of looking up their IP address.
+#[derive(Debug, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct LocationConfig {
+    /// Like "Berlin, Germany". Without coordinates, it's looked up when it's first needed.
+    pub name: Option<String>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    /// The IANA timezone, like "Europe/Berlin".
+    pub timezone: Option<String>,
+    /// Whether the user's location can be looked up from their IP address.
+    pub ip_lookup: bool,
+}
+
+impl Default for LocationConfig {
+    fn default() -> Self {
+        Self {
+            name: None,
+            latitude: None,
+            longitude: None,
+            timezone: None,
+            ip_lookup: true,
+        }
+    }
+}
+
+/// Warnings about the computer running hot or its disks filling up, checked in the background.
+#[derive(Debug, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct MonitoringConfig {
+    pub enabled: bool,
+    /// The hottest the CPU can run, in degrees Celsius, before the user is warned.
+    pub cpu_max_celsius: f32,
+    pub gpu_max_celsius: f32,
+    /// Warn when a fan spins slower than this, like one that has stopped. Off when left out.
+    pub fan_min_rpm: Option<u32>,
+    /// How full a disk can get, in percent, before the user is warned.
+    pub disk_max_percent: f32,
+    /// How long a temperature or fan has to stay past its limit before the user is warned.
+    pub minutes: u64,
+}
+
+impl Default for MonitoringConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            cpu_max_celsius: 90.0,
+            gpu_max_celsius: 85.0,
+            fan_min_rpm: None,
+            disk_max_percent: 90.0,
+            minutes: 5,
+        }
+    }
+}
+
+/// How identify_song listens for songs.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct SongsConfig {
+    /// A key from https://audd.io, which recognizes the songs.
+    pub audd_key: Option<String>,
+    /// An input device that records what the computer is playing, like "Stereo Mix" on Windows.
+    /// On Linux, the default output's monitor is used when this is left out.
+    pub system_audio_device: Option<String>,
+}
+
+/// The briefing given after the wake-up alarm is dismissed.
+#[derive(Debug, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct MorningConfig {
+    pub enabled: bool,
+    /// The tools whose answers make up the briefing, in order.
+    pub steps: Vec<RoutineStep>,
+}
+
+impl Default for MorningConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            steps: vec![
+                RoutineStep {
+                    tool: "get_weather".to_string(),
+                    args: serde_json::Value::Null,
+                },
+                RoutineStep {
+                    tool: "get_upcoming_events".to_string(),
+                    args: serde_json::json!({ "days": 1 }),
+                },
+                RoutineStep {
+                    tool: "get_news_headlines".to_string(),
+                    args: serde_json::json!({ "count": 3 }),
+                },
+            ],
+        }
+    }
+}
+
+/// A routine the user can run by name, like "goodnight".
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RoutineConfig {
+    /// What the routine is for, shown to the AI.
+    pub description: Option<String>,
+    /// The tools the routine calls, in order.
+    pub steps: Vec<RoutineStep>,
+}
+
+/// A tool call in a routine, like `{ tool = "get_news_headlines", args = { count = 3 } }`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RoutineStep {
+    pub tool: String,
+    /// The tool's arguments, as the AI would give them.
+    #[serde(default)]
+    pub args: serde_json::Value,
+}
+
+/// A command the run_command tool can run, like `[commands.disk-usage]`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct CommandConfig {
+    pub program: String,
+    /// The arguments, where placeholders like `{path}` are filled in by the AI.
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// What the command does, so the AI knows when to run it.
+    #[serde(default)]
+    pub description: Option<String>,
+    /// How long the command can run before it's stopped. Defaults to 30 seconds.
+    #[serde(default)]
+    pub timeout_seconds: Option<u64>,
+}
+
+/// Reads the config file. A missing config file isn't an error, and gives the default config.
+pub fn load() -> Result<Config, anyhow::Error> {
+    match fs::read_to_string(&*CONFIG_PATH) {
+        Ok(text) => toml::from_str(&text)
+            .with_context(|| format!("Failed to parse {}", CONFIG_PATH.display())),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+        Err(err) => Err(err).with_context(|| format!("Failed to read {}", CONFIG_PATH.display())),
+    }
+}
+
+/// Changes the config file, keeping any settings the change doesn't touch.
+/// Comments in the file are lost.
+pub fn update(change: impl FnOnce(&mut toml::Table)) -> Result<(), anyhow::Error> {
+    let mut table: toml::Table = match fs::read_to_string(&*CONFIG_PATH) {
+        Ok(text) => text
+            .parse()
+            .with_context(|| format!("Failed to parse {}", CONFIG_PATH.display()))?,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => toml::Table::new(),
+        Err(err) => {
+            return Err(err).with_context(|| format!("Failed to read {}", CONFIG_PATH.display()))
+        }
+    };
+    change(&mut table);
+
+    if let Some(dir) = CONFIG_PATH.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&*CONFIG_PATH, toml::to_string(&table)?)
+        .with_context(|| format!("Failed to write {}", CONFIG_PATH.display()))
+}
+
+/// Applies the config file's settings to every option that wasn't passed on the command line.
+pub fn apply(config: &Config, opt: &mut Opt, matches: &ArgMatches) {
+    let unset = |id: &str| matches.value_source(id) != Some(ValueSource::CommandLine);
+
+    if let Some(device) = config.device.clone().filter(|_| unset("device")) {
+        opt.device = device;
+    }
+    // Either push-to-talk option on the command line replaces both from the config file.
+    if unset("ptt_key") && unset("special_ptt_key") {
+        if let Some(key) = &config.ptt_key {
+            match PTTKey::from_str(key, true) {
+                Ok(key) => opt.ptt_key = Some(key),
+                Err(err) => warn!("Ignoring push-to-talk key from config file: {}", err),
+            }
+        } else if config.special_ptt_key.is_some() {
+            opt.special_ptt_key = config.special_ptt_key;
+        }
+    }
+    if let Some(voice) = config.ai_voice.as_ref().filter(|_| unset("ai_voice")) {
+        match VoiceEnum::from_str(voice, true) {
+            Ok(voice) => opt.ai_voice = Some(voice),
+            Err(err) => warn!("Ignoring voice from config file: {}", err),
+        }
+    }
+}
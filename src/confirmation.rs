@@ -0,0 +1,136 @@
+//! Confirming tool calls that can't be undone, like shutting down or sending an email, with the
+//! user. The first call is held here and gets back a code. The call only goes ahead when it's made
+//! again with that code after the user has said something, so the AI can't confirm for itself,
+//! and neither can anything it reads, like a web page or an email telling it to.
+
+use anyhow::bail;
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        LazyLock, Mutex,
+    },
+    time::{Duration, Instant},
+};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// A held call is forgotten after this long, so an old one can't be confirmed by accident.
+const EXPIRY: Duration = Duration::from_secs(10 * 60);
+/// The argument a held call is confirmed with.
+pub const ARG: &str = "confirmation";
+
+/// The tools that hold their calls until the user confirms them.
+const GUARDED: [&str; 7] = [
+    "shutdown_computer",
+    "send_email",
+    "type_text",
+    "restart_container",
+    "open_url",
+    "open_path",
+    "get_clipboard",
+];
+
+struct Pending {
+    tool: String,
+    args: Value,
+    /// The user's turn when the call was held. It can only be confirmed on a later one.
+    turn: u64,
+    created: Instant,
+}
+
+/// How many times the user has said something to the AI.
+static USER_TURN: AtomicU64 = AtomicU64::new(0);
+static PENDING: LazyLock<Mutex<HashMap<String, Pending>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Notes that the user said something, which is what lets held calls be confirmed.
+pub fn user_turn() {
+    USER_TURN.fetch_add(1, Ordering::SeqCst);
+}
+
+/// Whether `tool` asks the user before doing anything.
+pub fn is_guarded(tool: &str) -> bool {
+    GUARDED.contains(&tool)
+}
+
+/// Holds a call to `tool` until the user confirms it. Returns the code to confirm it with.
+pub fn request(tool: &str, args: &Value) -> String {
+    let mut args = args.clone();
+    if let Some(args) = args.as_object_mut() {
+        args.remove(ARG);
+    }
+    let code = Uuid::new_v4().simple().to_string()[..8].to_string();
+    let mut pending = PENDING.lock().unwrap();
+    pending.retain(|_, pending| pending.created.elapsed() < EXPIRY);
+    pending.insert(
+        code.clone(),
+        Pending {
+            tool: tool.to_string(),
+            args,
+            turn: USER_TURN.load(Ordering::SeqCst),
+            created: Instant::now(),
+        },
+    );
+    info!("Holding a call to {} for confirmation", tool);
+    code
+}
+
+/// Takes the held call a call to `tool` confirms, if it has a confirmation code. Returns the
+/// held call's arguments, which are used instead of the confirming call's, or `None` if the call
+/// has no code and still needs confirming.
+pub fn take(tool: &str, args: &Value) -> Result<Option<Value>, anyhow::Error> {
+    let Some(code) = args[ARG]
+        .as_str()
+        .map(str::trim)
+        .filter(|code| !code.is_empty())
+    else {
+        return Ok(None);
+    };
+    let mut pending = PENDING.lock().unwrap();
+    let Some(held) = pending.get(code) else {
+        bail!("That confirmation code isn't valid, or has expired. Call {} again without it to get a new one.", tool);
+    };
+    if held.tool != tool {
+        bail!("That confirmation code is for {}, not {}", held.tool, tool);
+    }
+    if held.created.elapsed() >= EXPIRY {
+        pending.remove(code);
+        bail!(
+            "That confirmation code has expired. Call {} again without it to get a new one.",
+            tool
+        );
+    }
+    if held.turn >= USER_TURN.load(Ordering::SeqCst) {
+        warn!(
+            "Refused to confirm {} without the user saying anything",
+            tool
+        );
+        bail!("Nothing was done. Only the user can confirm this: ask them, and wait for their answer before calling {} again with the code.", tool);
+    }
+    Ok(pending.remove(code).map(|held| held.args))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn only_a_later_user_turn_confirms() {
+        let code = request("shutdown_computer", &json!({ "restart": true }));
+        let confirming = json!({ "confirmation": code, "restart": false });
+
+        // The AI can't confirm straight away, or with the code for another tool.
+        assert!(take("shutdown_computer", &confirming).is_err());
+        assert!(take("send_email", &confirming).is_err());
+        assert_eq!(take("shutdown_computer", &json!({})).unwrap(), None);
+
+        // Once the user answers, the held arguments are used, and only once.
+        user_turn();
+        let held = take("shutdown_computer", &confirming).unwrap().unwrap();
+        assert_eq!(held, json!({ "restart": true }));
+        assert!(take("shutdown_computer", &confirming).is_err());
+    }
+}
@@ -0,0 +1,144 @@
+//! The contacts from the config file, and the message_contact tool, which opens a message to one
+//! of them already written out. Nothing is sent until the user presses send.
+
+use anyhow::{bail, Context};
+use std::{collections::HashMap, sync::OnceLock};
+
+use crate::config::ContactConfig;
+
+static CONTACTS: OnceLock<HashMap<String, ContactConfig>> = OnceLock::new();
+
+/// Sets the contacts, from the config file.
+pub fn configure(contacts: HashMap<String, ContactConfig>) {
+    let _ = CONTACTS.set(contacts);
+}
+
+fn contacts() -> &'static HashMap<String, ContactConfig> {
+    CONTACTS.get_or_init(HashMap::new)
+}
+
+/// The names of the contacts, sorted.
+pub fn names() -> Vec<String> {
+    let mut names: Vec<String> = contacts().keys().cloned().collect();
+    names.sort();
+    names
+}
+
+/// How a message is sent.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Via {
+    WhatsApp,
+    Sms,
+    Email,
+}
+
+impl Via {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "whatsapp" => Some(Self::WhatsApp),
+            "sms" | "text" | "imessage" => Some(Self::Sms),
+            "email" | "mail" => Some(Self::Email),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::WhatsApp => "WhatsApp",
+            Self::Sms => "a text message",
+            Self::Email => "email",
+        }
+    }
+}
+
+/// Finds the contact named `name`, or whose name starts with it, like "Anna" for "Anna Smith".
+fn find(name: &str) -> Result<(&'static str, &'static ContactConfig), anyhow::Error> {
+    let wanted = name.trim().to_lowercase();
+    if contacts().is_empty() {
+        bail!("There are no contacts in the config file");
+    }
+    if let Some((name, contact)) = contacts()
+        .iter()
+        .find(|(name, _)| name.to_lowercase() == wanted)
+    {
+        return Ok((name, contact));
+    }
+    let matches: Vec<(&String, &ContactConfig)> = contacts()
+        .iter()
+        .filter(|(name, _)| name.to_lowercase().starts_with(&wanted))
+        .collect();
+    match matches[..] {
+        [(name, contact)] => Ok((name, contact)),
+        [] => bail!(
+            "No contact is named \"{}\". The contacts are: {}",
+            name,
+            names().join(", ")
+        ),
+        _ => bail!(
+            "Several contacts match \"{}\": {}. Ask the user which one they mean.",
+            name,
+            matches
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+/// Opens a message to the contact named `name` with `text` written out, in WhatsApp, the
+/// system's messaging app, or the email app. `via` defaults to the contact's own choice, then
+/// whatever they have details for. Returns the contact's full name and how it's being sent.
+pub fn message(name: &str, text: &str, via: Option<Via>) -> Result<(String, Via), anyhow::Error> {
+    let (name, contact) = find(name)?;
+    let via = match via {
+        Some(via) => via,
+        None => match contact.via.as_deref() {
+            Some(via) => Via::from_name(via)
+                .with_context(|| format!("{} has an unknown via, \"{}\"", name, via))?,
+            None if contact.phone.is_some() => Via::Sms,
+            None => Via::Email,
+        },
+    };
+
+    let url = match via {
+        Via::WhatsApp | Via::Sms => {
+            let phone = contact
+                .phone
+                .as_deref()
+                .with_context(|| format!("There's no phone number for {}", name))?
+                .trim();
+            // Numbers are written all sorts of ways, but links only take the digits.
+            let digits: String = phone.chars().filter(char::is_ascii_digit).collect();
+            match via {
+                Via::WhatsApp => format!("https://wa.me/{}?text={}", digits, encode(text)),
+                _ => {
+                    let plus = if phone.starts_with('+') { "+" } else { "" };
+                    format!("sms:{}{}?body={}", plus, digits, encode(text))
+                }
+            }
+        }
+        Via::Email => {
+            let email = contact
+                .email
+                .as_deref()
+                .with_context(|| format!("There's no email address for {}", name))?;
+            format!("mailto:{}?body={}", email.trim(), encode(text))
+        }
+    };
+    open::that_detached(&url).with_context(|| format!("Failed to open {}", via.name()))?;
+    Ok((name.to_string(), via))
+}
+
+/// Percent encodes `text` for a link. Spaces become "%20", since mail and messaging apps show a
+/// "+" as it is.
+fn encode(text: &str) -> String {
+    text.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
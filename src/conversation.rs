@@ -0,0 +1,52 @@
+//! What's said in the conversation as it happens, for frontends other than the terminal.
+
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ConversationEvent {
+    /// The user said or typed something.
+    User { text: String },
+    /// The next part of the AI's response, without speech tags.
+    Token { text: String },
+    /// The AI called a tool.
+    FunctionCall { name: String, arguments: String },
+    /// What a tool returned to the AI.
+    FunctionResult { name: String, content: String },
+    /// The AI finished responding. Its voice may still be speaking.
+    ResponseDone,
+    /// The AI voice started speaking a sentence.
+    SpeechStarted { text: String },
+    /// The AI voice finished speaking a sentence, or skipped it.
+    SpeechFinished { text: String },
+    /// The AI voice was stopped, and everything it had left to say was thrown away.
+    SpeechStopped,
+    /// The microphone started recording the user.
+    ListeningStarted,
+    /// How loud the microphone is while recording, from 0 to 1. Sent several times a second.
+    ListeningLevel { level: f32 },
+    /// The microphone stopped recording.
+    ListeningStopped,
+    /// An alarm started ringing.
+    AlarmStarted,
+    /// Every alarm stopped ringing.
+    AlarmStopped,
+}
+
+static SUBSCRIBERS: Mutex<Vec<flume::Sender<ConversationEvent>>> = Mutex::new(Vec::new());
+
+/// Returns a channel that receives every conversation event from now on.
+pub fn subscribe() -> flume::Receiver<ConversationEvent> {
+    let (tx, rx) = flume::unbounded();
+    SUBSCRIBERS.lock().unwrap().push(tx);
+    rx
+}
+
+/// Sends an event to every subscriber, forgetting subscribers that have hung up.
+pub fn emit(event: ConversationEvent) {
+    SUBSCRIBERS
+        .lock()
+        .unwrap()
+        .retain(|tx| tx.send(event.clone()).is_ok());
+}
@@ -0,0 +1,314 @@
+//! Cooking mode: walking the user through a recipe one step at a time, hands free. A recipe is
+//! read from a web page's recipe data, or given as steps by the AI when the user reads one out or
+//! pastes it. Steps that take a set time, like "simmer for 20 minutes", start a timer when they're
+//! reached.
+
+use anyhow::{bail, Context};
+use chrono::Local;
+use regex::Regex;
+use serde_json::Value;
+use std::{
+    sync::{LazyLock, Mutex},
+    time::Duration,
+};
+
+use crate::timers::{self, AlarmSettings};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+/// The longest a step's timer can be.
+const MAX_TIMER_MINUTES: f64 = 24.0 * 60.0;
+
+static LD_JSON: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?is)<script[^>]*type\s*=\s*["']application/ld\+json["'][^>]*>(.*?)</script>"#)
+        .unwrap()
+});
+static HTML_TAG: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"<[^>]*>").unwrap());
+/// A time in a step, like "20 minutes", "1 hour" or "25-30 mins". The lower time of a range is
+/// used, so the food is checked on in time.
+static STEP_TIME: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)\b(\d+(?:\.\d+)?)(?:\s*(?:-|–|to)\s*\d+(?:\.\d+)?)?\s*(hours?|hrs?|minutes?|mins?)\b",
+    )
+    .unwrap()
+});
+
+/// One step of a recipe.
+pub struct Step {
+    pub text: String,
+    /// How long the step takes, which a timer is set for.
+    pub timer: Option<Duration>,
+}
+
+impl Step {
+    /// A step whose timer is worked out from its text.
+    pub fn from_text(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+            timer: step_time(text),
+        }
+    }
+}
+
+struct Recipe {
+    title: String,
+    ingredients: Vec<String>,
+    steps: Vec<Step>,
+    /// The step being done, or `None` before the first step.
+    current: Option<usize>,
+}
+
+static RECIPE: Mutex<Option<Recipe>> = Mutex::new(None);
+
+fn step_time(text: &str) -> Option<Duration> {
+    let caps = STEP_TIME.captures(text)?;
+    let amount: f64 = caps[1].parse().ok()?;
+    let minutes = match caps[2].to_lowercase().starts_with('h') {
+        true => amount * 60.0,
+        false => amount,
+    };
+    timer_for(minutes)
+}
+
+/// A step's timer for `minutes`, up to a day, or `None` if it isn't more than zero.
+pub fn timer_for(minutes: f64) -> Option<Duration> {
+    (minutes > 0.0).then(|| Duration::from_secs_f64(minutes.min(MAX_TIMER_MINUTES) * 60.0))
+}
+
+/// Turns the HTML bits left in recipe data into plain text.
+fn clean(text: &str) -> String {
+    let text = HTML_TAG.replace_all(text, " ");
+    let text = text
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'");
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Finds the schema.org Recipe in a page's JSON-LD, which can be nested in a list or a graph.
+fn find_recipe(value: &Value) -> Option<&Value> {
+    match value {
+        Value::Array(items) => items.iter().find_map(find_recipe),
+        Value::Object(object) => {
+            let is_recipe = match &object.get("@type") {
+                Some(Value::String(kind)) => kind == "Recipe",
+                Some(Value::Array(kinds)) => kinds.iter().any(|kind| kind == "Recipe"),
+                _ => false,
+            };
+            match is_recipe {
+                true => Some(value),
+                false => object.get("@graph").and_then(find_recipe),
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Collects the steps of `recipeInstructions`, which can be text, a list of text, a list of
+/// HowToSteps, or HowToSections of them.
+fn instructions(value: &Value, steps: &mut Vec<String>) {
+    match value {
+        Value::String(text) => {
+            let text = clean(text);
+            if !text.is_empty() {
+                steps.push(text);
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                instructions(item, steps);
+            }
+        }
+        Value::Object(object) => {
+            if let Some(items) = object.get("itemListElement") {
+                instructions(items, steps);
+            } else if let Some(text) = object.get("text") {
+                instructions(text, steps);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Reads the recipe on the page at `url`.
+fn fetch(url: &str) -> Result<Recipe, anyhow::Error> {
+    let page = reqwest::blocking::Client::new()
+        .get(url)
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .context("Failed to load the page")?
+        .error_for_status()?
+        .text()?;
+    let recipe = LD_JSON
+        .captures_iter(&page)
+        .filter_map(|caps| serde_json::from_str::<Value>(caps[1].trim()).ok())
+        .find_map(|value| find_recipe(&value).cloned())
+        .context(
+            "The page has no recipe data. Read the recipe from the page another way, and give its \
+             steps to load_recipe.",
+        )?;
+
+    let mut steps = Vec::new();
+    match &recipe["recipeInstructions"] {
+        // All the steps in one block of text are split by line, or by sentence.
+        Value::String(text) => {
+            let lines: Vec<&str> = match text.contains('\n') {
+                true => text.lines().collect(),
+                false => text.split(". ").collect(),
+            };
+            for line in lines {
+                instructions(&Value::String(line.to_string()), &mut steps);
+            }
+        }
+        value => instructions(value, &mut steps),
+    }
+    if steps.is_empty() {
+        bail!("The page's recipe has no steps");
+    }
+    let ingredients = match &recipe["recipeIngredient"] {
+        Value::Array(ingredients) => ingredients
+            .iter()
+            .filter_map(Value::as_str)
+            .map(clean)
+            .collect(),
+        _ => Vec::new(),
+    };
+    Ok(Recipe {
+        title: recipe["name"]
+            .as_str()
+            .map(clean)
+            .unwrap_or_else(|| "the recipe".to_string()),
+        ingredients,
+        steps: steps.iter().map(|step| Step::from_text(step)).collect(),
+        current: None,
+    })
+}
+
+/// Loads a recipe from `url`, or from the given steps, replacing any recipe in progress. Returns
+/// a description of it for the AI to read out before starting.
+pub fn load(
+    url: Option<&str>,
+    title: &str,
+    ingredients: Vec<String>,
+    steps: Vec<Step>,
+) -> Result<String, anyhow::Error> {
+    let recipe = match url.map(str::trim).filter(|url| !url.is_empty()) {
+        Some(url) => fetch(url)?,
+        None => {
+            if steps.is_empty() {
+                bail!("Give either a link to the recipe or its steps");
+            }
+            Recipe {
+                title: match title.trim() {
+                    "" => "the recipe".to_string(),
+                    title => title.to_string(),
+                },
+                ingredients,
+                steps,
+                current: None,
+            }
+        }
+    };
+
+    let mut info = format!(
+        "Loaded {}, which has {} steps.\n",
+        recipe.title,
+        recipe.steps.len()
+    );
+    if !recipe.ingredients.is_empty() {
+        info.push_str("Ingredients:\n");
+        for ingredient in &recipe.ingredients {
+            info.push_str(&format!("- {}\n", ingredient));
+        }
+    }
+    info.push_str(
+        "Ask the user if they have everything ready, then call next_step to read them the first step.",
+    );
+    *RECIPE.lock().unwrap() = Some(recipe);
+    Ok(info)
+}
+
+/// Describes the current step, starting its timer if `start_timer` is set and it has one.
+fn describe_step(recipe: &Recipe, start_timer: bool) -> Result<String, anyhow::Error> {
+    let index = recipe.current.unwrap_or_default();
+    let step = &recipe.steps[index];
+    let mut info = format!(
+        "Step {} of {}: {}",
+        index + 1,
+        recipe.steps.len(),
+        step.text
+    );
+    if let Some(timer) = step.timer {
+        let minutes = humantime::format_duration(timer);
+        match start_timer {
+            true => {
+                let description = format!("{}, step {}", recipe.title, index + 1);
+                let goes_off = Local::now()
+                    .checked_add_signed(chrono::Duration::from_std(timer)?)
+                    .context("The step's timer is too long")?;
+                timers::set_timer(description, goes_off, AlarmSettings::default())?;
+                info.push_str(&format!("\nA {} timer was started for this step.", minutes));
+            }
+            false => info.push_str(&format!(
+                "\nThis step's {} timer was started when it was first read.",
+                minutes
+            )),
+        }
+    }
+    if index + 1 == recipe.steps.len() {
+        info.push_str("\nThis is the last step.");
+    }
+    Ok(info)
+}
+
+/// Moves on to the next step of the recipe and describes it.
+pub fn next_step() -> Result<String, anyhow::Error> {
+    let mut guard = RECIPE.lock().unwrap();
+    let Some(recipe) = guard.as_mut() else {
+        bail!("No recipe is loaded. Load one with load_recipe.");
+    };
+    let next = recipe.current.map_or(0, |current| current + 1);
+    if next >= recipe.steps.len() {
+        let title = recipe.title.clone();
+        *guard = None;
+        return Ok(format!(
+            "That was the last step, so {} is done. Cooking mode has ended.",
+            title
+        ));
+    }
+    recipe.current = Some(next);
+    describe_step(recipe, true)
+}
+
+/// Describes the current step again, without starting its timer again.
+pub fn repeat_step() -> Result<String, anyhow::Error> {
+    let guard = RECIPE.lock().unwrap();
+    let Some(recipe) = guard.as_ref() else {
+        bail!("No recipe is loaded. Load one with load_recipe.");
+    };
+    if recipe.current.is_none() {
+        bail!("The recipe hasn't started yet. Call next_step for the first step.");
+    }
+    describe_step(recipe, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn step_timers_are_at_most_a_day() {
+        assert_eq!(
+            step_time("Simmer for 20-25 minutes"),
+            Some(Duration::from_secs(20 * 60))
+        );
+        assert_eq!(timer_for(0.0), None);
+        assert_eq!(timer_for(f64::NAN), None);
+        let day = Some(Duration::from_secs(24 * 60 * 60));
+        assert_eq!(timer_for(1e300), day);
+        assert_eq!(
+            step_time("Leave to rise for 99999999999999999999 hours"),
+            day
+        );
+    }
+}
@@ -0,0 +1,219 @@
+//! Finding audio devices, measuring what a microphone hears, and the `list-devices` subcommand.
+
+use anyhow::{bail, Context};
+use colored::Colorize;
+use cpal::{
+    traits::{DeviceTrait, HostTrait, StreamTrait},
+    FromSample, Sample, SizedSample,
+};
+use rodio::{source::SineWave, Source};
+use std::{
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+use tracing::warn;
+
+use crate::audio::{self, Channel};
+
+const TEST_TONE_FREQUENCY: f32 = 440.0;
+const TEST_TONE_DURATION: Duration = Duration::from_millis(800);
+const TEST_TONE_VOLUME: f32 = 0.3;
+
+/// How long the microphone is measured for each half of the loopback check.
+const LOOPBACK_LISTEN_DURATION: Duration = Duration::from_millis(800);
+/// How much louder the microphone has to get while the tone plays for it to count as heard.
+const LOOPBACK_MIN_RATIO: f32 = 2.0;
+
+/// Finds an input device by name. "default" is the system default device.
+pub fn find_input_device(name: &str) -> Result<cpal::Device, anyhow::Error> {
+    let host = cpal::default_host();
+    let device = if name == "default" {
+        host.default_input_device()
+    } else {
+        host.input_devices()
+            .context("Failed to get list of input devices")?
+            .find(|device| device.name().map(|n| n == name).unwrap_or(false))
+    };
+    device.with_context(|| format!("Failed to find input device '{}'", name))
+}
+
+/// An open microphone that keeps track of how loud it is. It stops listening when dropped.
+pub struct LevelStream {
+    _stream: cpal::Stream,
+    level: Arc<AtomicU32>,
+}
+
+impl LevelStream {
+    pub fn open(device: &cpal::Device) -> Result<Self, anyhow::Error> {
+        let config = device
+            .default_input_config()
+            .context("Failed to get default input config")?;
+        let level = Arc::new(AtomicU32::new(0));
+
+        let stream = match config.sample_format() {
+            cpal::SampleFormat::I16 => build_level_stream::<i16>(device, &config.into(), &level),
+            cpal::SampleFormat::U16 => build_level_stream::<u16>(device, &config.into(), &level),
+            cpal::SampleFormat::I32 => build_level_stream::<i32>(device, &config.into(), &level),
+            cpal::SampleFormat::F32 => build_level_stream::<f32>(device, &config.into(), &level),
+            sample_format => bail!("Unsupported sample format '{sample_format}'"),
+        }
+        .context("Failed to build input stream")?;
+        stream.play().context("Failed to start input stream")?;
+
+        Ok(Self {
+            _stream: stream,
+            level,
+        })
+    }
+
+    /// The RMS loudness of the latest chunk of samples, from 0 to 1.
+    pub fn level(&self) -> f32 {
+        f32::from_bits(self.level.load(Ordering::Relaxed))
+    }
+
+    /// The loudest level heard over `duration`.
+    fn peak_over(&self, duration: Duration) -> f32 {
+        let start = Instant::now();
+        let mut peak: f32 = 0.0;
+        while start.elapsed() < duration {
+            peak = peak.max(self.level());
+            thread::sleep(Duration::from_millis(10));
+        }
+        peak
+    }
+}
+
+fn build_level_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    level: &Arc<AtomicU32>,
+) -> Result<cpal::Stream, cpal::BuildStreamError>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    let level = level.clone();
+    device.build_input_stream(
+        config,
+        move |data: &[T], _: &_| {
+            let sum: f32 = data
+                .iter()
+                .map(|&sample| f32::from_sample(sample).powi(2))
+                .sum();
+            let rms = (sum / data.len().max(1) as f32).sqrt();
+            level.store(rms.to_bits(), Ordering::Relaxed);
+        },
+        |err| warn!("An error occurred on the level stream: {}", err),
+        None,
+    )
+}
+
+/// Plays a short tone on a channel's output and waits for it to finish.
+fn play_test_tone(channel: Channel) {
+    let sink = audio::new_sink(channel);
+    sink.append(
+        SineWave::new(TEST_TONE_FREQUENCY)
+            .take_duration(TEST_TONE_DURATION)
+            .amplify(TEST_TONE_VOLUME),
+    );
+    audio::play(&sink);
+    sink.sleep_until_end();
+}
+
+/// Lists the input and output devices, marking the defaults and the ones in use.
+/// `selected_input` is the input device chosen with `--device` or the config file.
+pub fn list(selected_input: &str, test_tone: bool, loopback: bool) -> Result<(), anyhow::Error> {
+    let host = cpal::default_host();
+    let default_input = host.default_input_device().and_then(|d| d.name().ok());
+    let default_output = host.default_output_device().and_then(|d| d.name().ok());
+    let output_choices = audio::output_devices();
+
+    let marks = |name: &str, is_default: bool, used_by: Vec<String>| {
+        let mut marks = Vec::new();
+        if is_default {
+            marks.push("default".to_string());
+        }
+        if !used_by.is_empty() {
+            marks.push(format!("used for {}", used_by.join(", ")));
+        }
+        match marks.is_empty() {
+            true => format!("  {:?}", name),
+            false => format!("  {:?} {}", name, format!("({})", marks.join("; ")).green()),
+        }
+    };
+
+    println!("{}", "Input devices:".bold());
+    let inputs = host
+        .input_devices()
+        .context("Failed to get list of input devices")?;
+    for name in inputs.filter_map(|device| device.name().ok()) {
+        let is_default = default_input.as_deref() == Some(name.as_str());
+        let in_use = match selected_input {
+            "default" => is_default,
+            selected => selected == name,
+        };
+        let used_by = match in_use {
+            true => vec!["recording".to_string()],
+            false => Vec::new(),
+        };
+        println!("{}", marks(&name, is_default, used_by));
+    }
+
+    println!("{}", "Output devices:".bold());
+    for name in audio::output_device_names()? {
+        let is_default = default_output.as_deref() == Some(name.as_str());
+        let used_by = output_choices
+            .iter()
+            .filter(|(_, device)| match device {
+                Some(device) => *device == name,
+                None => is_default,
+            })
+            .map(|(channel, _)| format!("{:?}", channel).to_lowercase())
+            .collect();
+        println!("{}", marks(&name, is_default, used_by));
+    }
+
+    if test_tone {
+        println!();
+        for (channel, device) in &output_choices {
+            println!(
+                "Playing a test tone on the {} output ({})",
+                format!("{:?}", channel).to_lowercase(),
+                device.as_deref().unwrap_or("default")
+            );
+            play_test_tone(*channel);
+        }
+    }
+
+    if loopback {
+        println!();
+        loopback_check(selected_input)?;
+    }
+    Ok(())
+}
+
+/// Plays a tone on the speech output and checks whether the microphone hears it.
+fn loopback_check(selected_input: &str) -> Result<(), anyhow::Error> {
+    println!("Checking that the microphone can hear the speech output. Stay quiet for a moment...");
+    let microphone = LevelStream::open(&find_input_device(selected_input)?)?;
+
+    let quiet = microphone.peak_over(LOOPBACK_LISTEN_DURATION);
+    let tone_thread = thread::spawn(|| play_test_tone(Channel::Speech));
+    let with_tone = microphone.peak_over(LOOPBACK_LISTEN_DURATION);
+    let _ = tone_thread.join();
+
+    if with_tone > quiet * LOOPBACK_MIN_RATIO && with_tone > 0.0 {
+        println!("{}", "The microphone heard the test tone.".green());
+    } else {
+        println!(
+            "{}",
+            "The microphone didn't hear the test tone. Check that the output is audible and the microphone isn't muted."
+                .yellow()
+        );
+    }
+    Ok(())
+}
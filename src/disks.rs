@@ -0,0 +1,89 @@
+//! How full the computer's disks are, for the get_disk_usage tool and the disk space warnings.
+
+use std::path::PathBuf;
+use sysinfo::Disks;
+
+/// Disk images and snap packages are always full, so they're left out.
+const SKIPPED_FILE_SYSTEMS: [&str; 4] = ["squashfs", "iso9660", "udf", "overlay"];
+
+/// How much of a mounted disk is used.
+pub struct DiskUsage {
+    pub mount_point: PathBuf,
+    /// The device, like "/dev/nvme0n1p2" or "C:".
+    pub name: String,
+    pub file_system: String,
+    pub total: u64,
+    pub available: u64,
+    pub removable: bool,
+}
+
+impl DiskUsage {
+    pub fn used_percent(&self) -> f32 {
+        match self.total {
+            0 => 0.0,
+            total => (total - self.available.min(total)) as f32 / total as f32 * 100.0,
+        }
+    }
+}
+
+/// The disks that files can be saved to, with the most used first.
+pub fn usage() -> Vec<DiskUsage> {
+    let disks = Disks::new_with_refreshed_list();
+    let mut usage: Vec<DiskUsage> = disks
+        .iter()
+        .filter(|disk| disk.total_space() > 0 && !disk.is_read_only())
+        .map(|disk| DiskUsage {
+            mount_point: disk.mount_point().to_path_buf(),
+            name: disk.name().to_string_lossy().to_string(),
+            file_system: disk.file_system().to_string_lossy().to_string(),
+            total: disk.total_space(),
+            available: disk.available_space(),
+            removable: disk.is_removable(),
+        })
+        .filter(|disk| !SKIPPED_FILE_SYSTEMS.contains(&disk.file_system.to_lowercase().as_str()))
+        .collect();
+    // A disk mounted in several places, like with btrfs subvolumes, is only listed once, at its
+    // shortest mount point.
+    usage.sort_by_key(|disk| disk.mount_point.as_os_str().len());
+    let mut seen = Vec::new();
+    usage.retain(|disk| {
+        let key = (disk.name.clone(), disk.total, disk.available);
+        let new = !seen.contains(&key);
+        seen.push(key);
+        new
+    });
+    usage.sort_by(|a, b| b.used_percent().total_cmp(&a.used_percent()));
+    usage
+}
+
+pub fn format_bytes(bytes: u64) -> String {
+    const GB: f64 = 1024.0 * 1024.0 * 1024.0;
+    let gigabytes = bytes as f64 / GB;
+    match gigabytes >= 1024.0 {
+        true => format!("{:.1} TB", gigabytes / 1024.0),
+        false => format!("{:.1} GB", gigabytes),
+    }
+}
+
+/// Describes every disk, one per line, like
+/// "- / (/dev/nvme0n1p2, ext4): 76% used, 120.3 GB free of 500.0 GB".
+pub fn describe() -> String {
+    let usage = usage();
+    if usage.is_empty() {
+        return "No disks could be read.".to_string();
+    }
+    let mut info = String::new();
+    for disk in &usage {
+        info.push_str(&format!(
+            "- {} ({}, {}{}): {:.0}% used, {} free of {}\n",
+            disk.mount_point.display(),
+            disk.name,
+            disk.file_system,
+            if disk.removable { ", removable" } else { "" },
+            disk.used_percent(),
+            format_bytes(disk.available),
+            format_bytes(disk.total)
+        ));
+    }
+    info
+}
@@ -0,0 +1,150 @@
+//! Turning the system's do not disturb mode on and off for the set_do_not_disturb tool.
+//! Each desktop keeps its own switch for this, so the one in use is worked out and set here.
+
+#[cfg(windows)]
+use crate::system_command::run;
+
+/// Turns do not disturb on or off. Returns where it was changed, for the AI.
+pub fn set(on: bool) -> Result<String, anyhow::Error> {
+    #[cfg(target_os = "linux")]
+    return linux::set(on);
+
+    // Windows has no API for Focus Assist, so notification banners are turned off instead, the
+    // same as the switch at the top of the notification settings.
+    #[cfg(windows)]
+    {
+        let value = if on { "0" } else { "1" };
+        run(
+            "reg",
+            &[
+                "add",
+                r"HKCU\Software\Microsoft\Windows\CurrentVersion\PushNotifications",
+                "/v",
+                "ToastEnabled",
+                "/t",
+                "REG_DWORD",
+                "/d",
+                value,
+                "/f",
+            ],
+        )?;
+        return Ok("Windows notification banners".to_string());
+    }
+
+    #[allow(unreachable_code)]
+    Err(anyhow::anyhow!(
+        "Do not disturb can't be changed on this system"
+    ))
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use crate::system_command::run;
+    use anyhow::{bail, Context};
+    use std::{collections::HashMap, env, sync::Mutex};
+    use zbus::{blocking::Connection, zvariant::Value};
+
+    /// KDE's do not disturb lasts while the connection that asked for it is open, so it's kept
+    /// here along with the cookie that ends it.
+    static KDE_INHIBITION: Mutex<Option<(Connection, u32)>> = Mutex::new(None);
+
+    const NOTIFICATIONS: &str = "org.freedesktop.Notifications";
+    const NOTIFICATIONS_PATH: &str = "/org/freedesktop/Notifications";
+
+    pub fn set(on: bool) -> Result<String, anyhow::Error> {
+        let desktop = env::var("XDG_CURRENT_DESKTOP")
+            .unwrap_or_default()
+            .to_lowercase();
+        if desktop.contains("kde") {
+            set_kde(on)?;
+            return Ok("KDE Plasma".to_string());
+        }
+        if desktop.contains("cinnamon") {
+            let value = if on { "false" } else { "true" };
+            run(
+                "gsettings",
+                &[
+                    "set",
+                    "org.cinnamon.desktop.notifications",
+                    "display-notifications",
+                    value,
+                ],
+            )?;
+            return Ok("Cinnamon".to_string());
+        }
+        if desktop.contains("xfce") {
+            let value = if on { "true" } else { "false" };
+            run(
+                "xfconf-query",
+                &["-c", "xfce4-notifyd", "-p", "/do-not-disturb", "-s", value],
+            )?;
+            return Ok("Xfce".to_string());
+        }
+        if desktop.contains("gnome") || desktop.contains("unity") || desktop.contains("budgie") {
+            let value = if on { "false" } else { "true" };
+            run(
+                "gsettings",
+                &[
+                    "set",
+                    "org.gnome.desktop.notifications",
+                    "show-banners",
+                    value,
+                ],
+            )?;
+            return Ok("GNOME".to_string());
+        }
+
+        // Window managers usually leave notifications to dunst or mako.
+        if run(
+            "dunstctl",
+            &["set-paused", if on { "true" } else { "false" }],
+        )
+        .is_ok()
+        {
+            return Ok("dunst".to_string());
+        }
+        let mode = if on { "-a" } else { "-r" };
+        if run("makoctl", &["mode", mode, "do-not-disturb"]).is_ok() {
+            return Ok("mako".to_string());
+        }
+        bail!(
+            "Do not disturb isn't supported on this desktop ({})",
+            match desktop.is_empty() {
+                true => "unknown",
+                false => &desktop,
+            }
+        )
+    }
+
+    fn set_kde(on: bool) -> Result<(), anyhow::Error> {
+        let mut inhibition = KDE_INHIBITION.lock().unwrap();
+        if let Some((connection, cookie)) = inhibition.take() {
+            // Closing the connection would end it too, but only once it's dropped.
+            let _ = connection.call_method(
+                Some(NOTIFICATIONS),
+                NOTIFICATIONS_PATH,
+                Some(NOTIFICATIONS),
+                "UnInhibit",
+                &cookie,
+            );
+        }
+        if !on {
+            return Ok(());
+        }
+
+        let connection = Connection::session().context("Failed to connect to D-Bus")?;
+        let hints: HashMap<&str, Value> = HashMap::new();
+        let reply = connection
+            .call_method(
+                Some(NOTIFICATIONS),
+                NOTIFICATIONS_PATH,
+                Some(NOTIFICATIONS),
+                "Inhibit",
+                &("quick-assistant", "Asked to by the user", hints),
+            )
+            .context("Failed to ask Plasma not to show notifications")?;
+        let cookie: u32 = reply.body().deserialize()?;
+        *inhibition = Some((connection, cookie));
+        Ok(())
+    }
+}
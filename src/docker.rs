@@ -0,0 +1,245 @@
+//! Listing and restarting Docker containers for the list_containers and restart_container tools.
+//! This talks to the Docker Engine API over its socket, or its named pipe on Windows.
+
+use anyhow::{bail, Context};
+use serde::Deserialize;
+use std::{
+    env,
+    io::{Read, Write},
+    time::Duration,
+};
+
+/// Restarting a container waits for it to stop, which can take a while.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+/// How long a container gets to stop by itself before it's killed.
+const STOP_SECONDS: u32 = 10;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Container {
+    names: Vec<String>,
+    image: String,
+    state: String,
+    status: String,
+}
+
+impl Container {
+    /// Docker puts a slash in front of container names.
+    fn name(&self) -> &str {
+        self.names
+            .first()
+            .map(|name| name.trim_start_matches('/'))
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Deserialize)]
+struct ErrorMessage {
+    message: String,
+}
+
+/// Where the Docker socket is, from `DOCKER_HOST` if it's set.
+#[cfg(unix)]
+fn socket_path() -> Result<String, anyhow::Error> {
+    match env::var("DOCKER_HOST") {
+        Ok(host) => match host.strip_prefix("unix://") {
+            Some(path) => Ok(path.to_string()),
+            None => bail!(
+                "DOCKER_HOST is {}, but only unix:// sockets are supported",
+                host
+            ),
+        },
+        Err(_) => Ok("/var/run/docker.sock".to_string()),
+    }
+}
+
+#[cfg(unix)]
+fn connect() -> Result<std::os::unix::net::UnixStream, anyhow::Error> {
+    let path = socket_path()?;
+    let stream = std::os::unix::net::UnixStream::connect(&path).with_context(|| {
+        format!(
+            "Failed to connect to Docker at {}. Is it running, and is this user in the docker group?",
+            path
+        )
+    })?;
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    Ok(stream)
+}
+
+#[cfg(windows)]
+fn connect() -> Result<std::fs::File, anyhow::Error> {
+    let path = match env::var("DOCKER_HOST") {
+        Ok(host) => match host.strip_prefix("npipe://") {
+            Some(path) => path.replace('/', "\\"),
+            None => bail!(
+                "DOCKER_HOST is {}, but only npipe:// pipes are supported",
+                host
+            ),
+        },
+        Err(_) => r"\\.\pipe\docker_engine".to_string(),
+    };
+    std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&path)
+        .with_context(|| format!("Failed to connect to Docker at {}. Is it running?", path))
+}
+
+/// Sends a request to the Docker Engine API and returns the response's status code and body.
+fn request(method: &str, path: &str) -> Result<(u16, String), anyhow::Error> {
+    #[cfg(any(unix, windows))]
+    {
+        let mut stream = connect()?;
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: docker\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            method, path
+        )?;
+        stream.flush()?;
+        let mut response = Vec::new();
+        stream
+            .read_to_end(&mut response)
+            .context("Failed to read Docker's response")?;
+        return parse_response(&response);
+    }
+
+    #[allow(unreachable_code)]
+    Err(anyhow::anyhow!("Docker isn't supported on this system"))
+}
+
+fn parse_response(response: &[u8]) -> Result<(u16, String), anyhow::Error> {
+    let head_end = response
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .context("Docker's response was cut short")?;
+    let head = String::from_utf8_lossy(&response[..head_end]);
+    let body = &response[head_end + 4..];
+    let status: u16 = head
+        .split_whitespace()
+        .nth(1)
+        .and_then(|status| status.parse().ok())
+        .context("Docker's response has no status")?;
+    let chunked = head.lines().any(|line| {
+        line.to_lowercase()
+            .replace(' ', "")
+            .starts_with("transfer-encoding:chunked")
+    });
+    let body = match chunked {
+        true => dechunk(body)?,
+        false => body.to_vec(),
+    };
+    Ok((status, String::from_utf8_lossy(&body).to_string()))
+}
+
+/// Joins the chunks of a chunked response body.
+fn dechunk(mut body: &[u8]) -> Result<Vec<u8>, anyhow::Error> {
+    let mut joined = Vec::new();
+    loop {
+        let line_end = body
+            .windows(2)
+            .position(|window| window == b"\r\n")
+            .context("Bad chunk in Docker's response")?;
+        let size = String::from_utf8_lossy(&body[..line_end]);
+        let size = usize::from_str_radix(size.split(';').next().unwrap_or_default().trim(), 16)?;
+        if size == 0 {
+            return Ok(joined);
+        }
+        let rest = &body[line_end + 2..];
+        let chunk = rest
+            .get(..size)
+            .context("Docker's response was cut short")?;
+        joined.extend_from_slice(chunk);
+        body = rest[size..].strip_prefix(b"\r\n").unwrap_or(&rest[size..]);
+    }
+}
+
+/// Fails with Docker's message if a request failed.
+fn check(status: u16, body: &str) -> Result<(), anyhow::Error> {
+    if status >= 400 {
+        let message = serde_json::from_str::<ErrorMessage>(body)
+            .map(|error| error.message)
+            .unwrap_or_else(|_| body.trim().to_string());
+        bail!("Docker said: {}", message);
+    }
+    Ok(())
+}
+
+fn containers() -> Result<Vec<Container>, anyhow::Error> {
+    let (status, body) = request("GET", "/containers/json?all=true")?;
+    check(status, &body)?;
+    serde_json::from_str(&body).context("Failed to read the list of containers")
+}
+
+/// Describes every container, running or not, one per line.
+pub fn list() -> Result<String, anyhow::Error> {
+    let mut containers = containers()?;
+    if containers.is_empty() {
+        return Ok("There are no Docker containers.".to_string());
+    }
+    // Running containers first.
+    containers
+        .sort_by_key(|container| (container.state != "running", container.name().to_string()));
+    let running = containers
+        .iter()
+        .filter(|container| container.state == "running")
+        .count();
+    let mut info = format!(
+        "=== {} containers, {} running ===\n",
+        containers.len(),
+        running
+    );
+    for container in &containers {
+        info.push_str(&format!(
+            "- {} ({}): {}\n",
+            container.name(),
+            container.image,
+            container.status
+        ));
+    }
+    Ok(info)
+}
+
+/// Finds the name of the container matching `name`, exactly or by part of its name.
+pub fn find(name: &str) -> Result<String, anyhow::Error> {
+    let query = name.trim().to_lowercase();
+    if query.is_empty() {
+        bail!("No container name was given");
+    }
+    let containers = containers()?;
+    if let Some(container) = containers
+        .iter()
+        .find(|container| container.name().to_lowercase() == query)
+    {
+        return Ok(container.name().to_string());
+    }
+    let matches: Vec<&str> = containers
+        .iter()
+        .map(Container::name)
+        .filter(|container| container.to_lowercase().contains(&query))
+        .collect();
+    match matches[..] {
+        [] => bail!("No container is named \"{}\"", name),
+        [container] => Ok(container.to_string()),
+        _ => bail!(
+            "Several containers match \"{}\": {}. Ask the user which one they mean.",
+            name,
+            matches.join(", ")
+        ),
+    }
+}
+
+/// Restarts the container named exactly `name`.
+pub fn restart(name: &str) -> Result<(), anyhow::Error> {
+    // Container names can only have these in them, so nothing needs escaping.
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "_.-".contains(c))
+    {
+        bail!("\"{}\" isn't a container name", name);
+    }
+    let (status, body) = request(
+        "POST",
+        &format!("/containers/{}/restart?t={}", name, STOP_SECONDS),
+    )?;
+    check(status, &body)
+}
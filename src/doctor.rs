@@ -0,0 +1,226 @@
+//! The `doctor` subcommand, which checks for the things the assistant needs from the system
+//! and says how to fix whatever is missing.
+
+use async_openai::Client;
+use colored::Colorize;
+use cpal::traits::{DeviceTrait, HostTrait};
+use std::{env, path::PathBuf, thread, time::Duration};
+
+use crate::{audio, brightness, secrets};
+
+/// How long the key listener has to fail before input access counts as working.
+const LISTEN_GRACE_PERIOD: Duration = Duration::from_secs(1);
+
+enum Status {
+    Ok,
+    /// Something optional is missing.
+    Warning,
+    /// Something the assistant needs is missing.
+    Problem,
+}
+
+#[derive(Default)]
+struct Report {
+    warnings: usize,
+    problems: usize,
+}
+
+impl Report {
+    fn check(&mut self, status: Status, what: &str, fix: &str) {
+        match status {
+            Status::Ok => println!("{} {}", "✓".green(), what),
+            Status::Warning => {
+                self.warnings += 1;
+                println!("{} {}\n    {}", "!".yellow(), what, fix);
+            }
+            Status::Problem => {
+                self.problems += 1;
+                println!("{} {}\n    {}", "✗".red(), what, fix);
+            }
+        }
+    }
+}
+
+/// Runs every check and prints the results. `device` is the chosen input device.
+pub async fn run(device: &str, api_key: Option<&str>) {
+    let mut report = Report::default();
+
+    check_programs(&mut report);
+    check_input_device(&mut report, device);
+    check_output_devices(&mut report);
+    check_api_key(&mut report, api_key).await;
+    check_input_access(&mut report);
+
+    println!();
+    match (report.problems, report.warnings) {
+        (0, 0) => println!("{}", "Everything looks good.".green()),
+        (0, warnings) => println!(
+            "{}",
+            format!(
+                "No problems, but {} optional features are unavailable.",
+                warnings
+            )
+            .yellow()
+        ),
+        (problems, _) => println!(
+            "{}",
+            format!(
+                "Found {} problems that will stop the assistant from working.",
+                problems
+            )
+            .red()
+        ),
+    }
+}
+
+/// Finds a program on the PATH.
+fn find_program(name: &str) -> Option<PathBuf> {
+    let file_name = format!("{}{}", name, env::consts::EXE_SUFFIX);
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|dir| dir.join(&file_name))
+        .find(|path| path.is_file())
+}
+
+fn check_programs(report: &mut Report) {
+    match find_program("ffmpeg") {
+        Some(path) => report.check(
+            Status::Ok,
+            &format!("ffmpeg found at {}", path.display()),
+            "",
+        ),
+        None => report.check(
+            Status::Problem,
+            "ffmpeg not found. Speech can't be transcribed without it.",
+            "Install ffmpeg from https://ffmpeg.org/download.html and add it to your PATH.",
+        ),
+    }
+    match find_program("speedtest-rs") {
+        Some(_) => report.check(Status::Ok, "speedtest-rs found", ""),
+        None => report.check(
+            Status::Warning,
+            "speedtest-rs not found. The speedtest tool won't work.",
+            "Install it with `cargo install speedtest-rs`.",
+        ),
+    }
+    match brightness::get() {
+        Ok(_) => report.check(Status::Ok, "Screen brightness can be changed", ""),
+        Err(err) => report.check(
+            Status::Warning,
+            &format!("The AI can't change screen brightness: {:#}", err),
+            "On Linux, install brightnessctl. External monitors need DDC/CI turned on in their settings.",
+        ),
+    }
+}
+
+fn check_input_device(report: &mut Report, device: &str) {
+    let host = cpal::default_host();
+    let found = if device == "default" {
+        host.default_input_device().is_some()
+    } else {
+        host.input_devices()
+            .map(|mut devices| {
+                devices.any(|d| d.name().map(|name| name == device).unwrap_or(false))
+            })
+            .unwrap_or(false)
+    };
+
+    if found {
+        report.check(Status::Ok, &format!("Microphone '{}' found", device), "");
+    } else {
+        report.check(
+            Status::Problem,
+            &format!("Microphone '{}' not found", device),
+            "Plug in a microphone, or pick another with the setup subcommand or --device. \
+             The list-devices subcommand shows the ones available.",
+        );
+    }
+}
+
+fn check_output_devices(report: &mut Report) {
+    if cpal::default_host().default_output_device().is_none() {
+        report.check(
+            Status::Problem,
+            "No audio output device found",
+            "Plug in speakers or headphones, or check your system's sound settings.",
+        );
+        return;
+    }
+    report.check(Status::Ok, "Audio output device found", "");
+
+    let names = audio::output_device_names().unwrap_or_default();
+    for (channel, device) in audio::output_devices() {
+        if let Some(device) = device.filter(|device| !names.contains(device)) {
+            report.check(
+                Status::Warning,
+                &format!(
+                    "Output device '{}' chosen for {:?} sounds isn't connected. The default device is used instead.",
+                    device, channel
+                ),
+                "Reconnect it, or ask the AI to change the output device.",
+            );
+        }
+    }
+}
+
+async fn check_api_key(report: &mut Report, api_key: Option<&str>) {
+    let api_key = api_key
+        .map(str::to_string)
+        .or_else(|| env::var("OPENAI_API_KEY").ok())
+        .or_else(secrets::load_api_key);
+    let Some(api_key) = api_key else {
+        report.check(
+            Status::Problem,
+            "No OpenAI API key found",
+            "Save one with the set-key subcommand, or set the OPENAI_API_KEY env var.",
+        );
+        return;
+    };
+
+    // Listing models is free, so it's a cheap way to find out if the key works.
+    let client =
+        Client::with_config(async_openai::config::OpenAIConfig::new().with_api_key(api_key));
+    match client.models().list().await {
+        Ok(_) => report.check(Status::Ok, "OpenAI API key works", ""),
+        Err(err) => report.check(
+            Status::Problem,
+            &format!("OpenAI API key was rejected: {}", err),
+            "Check the key at https://platform.openai.com/api-keys and save it again with the set-key subcommand.",
+        ),
+    }
+}
+
+fn check_input_access(report: &mut Report) {
+    if cfg!(target_os = "linux")
+        && env::var_os("WAYLAND_DISPLAY").is_some()
+        && env::var_os("DISPLAY").is_none()
+    {
+        report.check(
+            Status::Problem,
+            "Running under Wayland without XWayland. Key presses can't be read.",
+            "Enable XWayland, or log in with an X11 session.",
+        );
+        return;
+    }
+
+    // Listening only fails straight away if there's no access, and otherwise never returns.
+    let (result_tx, result_rx) = flume::bounded(1);
+    thread::spawn(move || {
+        let result = rdev::listen(|_| {});
+        let _ = result_tx.send(result);
+    });
+    match result_rx.recv_timeout(LISTEN_GRACE_PERIOD) {
+        Ok(Err(err)) => {
+            let fix = if cfg!(target_os = "macos") {
+                "Allow your terminal under System Settings > Privacy & Security > Accessibility and Input Monitoring."
+            } else {
+                "Make sure you're running in a graphical session that allows reading the keyboard."
+            };
+            report.check(
+                Status::Problem,
+                &format!("Can't read key presses: {:?}", err),
+                fix,
+            );
+        }
+        _ => report.check(Status::Ok, "Key presses can be read", ""),
+    }
+}
@@ -0,0 +1,209 @@
+//! Downloading files for the download_file tool. The request is made straight away so a bad link
+//! fails at once, and the file is then saved on a worker thread, reporting how far along it is.
+
+use anyhow::{bail, Context};
+use reqwest::{blocking::Response, header, Url};
+use std::{
+    fs::{self, File},
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use crate::file_search;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+/// How long the server can take to start answering, or to send the next part of the file.
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+/// How often progress is reported.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A download whose response has started to arrive.
+pub struct Download {
+    response: Response,
+    /// Where the file will be saved.
+    pub path: PathBuf,
+    /// The file's size, if the server said.
+    pub size: Option<u64>,
+}
+
+/// Requests `url`, and works out where to save it. `destination` can be a folder, a file path, or
+/// a folder name like "desktop", and defaults to the downloads folder. Files aren't overwritten.
+pub fn start(url: &str, destination: Option<&str>) -> Result<Download, anyhow::Error> {
+    let url = Url::parse(url.trim()).with_context(|| format!("\"{}\" isn't a link", url))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        bail!("Only http and https links can be downloaded");
+    }
+
+    // The blocking client's timeout is for getting the response and for each read of the file,
+    // not the whole download, so a big file can take as long as it needs while it keeps arriving.
+    let response = reqwest::blocking::Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .timeout(READ_TIMEOUT)
+        .build()?
+        .get(url.clone())
+        .send()
+        .with_context(|| {
+            format!(
+                "Failed to connect to {}",
+                url.host_str().unwrap_or_default()
+            )
+        })?;
+    if !response.status().is_success() {
+        bail!("The server answered {}", response.status());
+    }
+
+    let destination = match destination.map(str::trim).filter(|d| !d.is_empty()) {
+        Some(destination) => file_search::resolve_root(destination),
+        None => dirs::download_dir()
+            .or_else(dirs::home_dir)
+            .context("There's no downloads folder")?,
+    };
+    let (folder, name) = match destination.is_dir() {
+        true => (destination, file_name(&response)),
+        false => {
+            let name = destination
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .with_context(|| format!("{} isn't a file name", destination.display()))?;
+            let folder = destination.parent().unwrap_or(Path::new("")).to_path_buf();
+            (folder, name)
+        }
+    };
+    if !folder.is_dir() {
+        bail!("The folder {} doesn't exist", folder.display());
+    }
+    if !file_search::is_allowed(&folder) {
+        bail!(
+            "{} isn't a folder files can be saved in. Only the folders search_files looks in can be.",
+            folder.display()
+        );
+    }
+
+    Ok(Download {
+        size: response.content_length(),
+        path: unused_path(&folder.join(name)),
+        response,
+    })
+}
+
+impl Download {
+    /// Saves the file, calling `progress` every so often with a description like
+    /// "40 MB of 100 MB (40%)". It's written beside its final path and moved there once complete.
+    pub fn save(mut self, mut progress: impl FnMut(&str)) -> Result<PathBuf, anyhow::Error> {
+        let partial = self.path.with_file_name(format!(
+            "{}.part",
+            self.path.file_name().unwrap_or_default().to_string_lossy()
+        ));
+        let result = (|| {
+            let mut file = File::create(&partial)
+                .with_context(|| format!("Failed to create {}", partial.display()))?;
+            let mut buffer = vec![0; 64 * 1024];
+            let mut downloaded: u64 = 0;
+            let mut last_report = Instant::now();
+            loop {
+                let read = self
+                    .response
+                    .read(&mut buffer)
+                    .context("The download was interrupted")?;
+                if read == 0 {
+                    break;
+                }
+                file.write_all(&buffer[..read])
+                    .with_context(|| format!("Failed to write {}", partial.display()))?;
+                downloaded += read as u64;
+                if last_report.elapsed() >= PROGRESS_INTERVAL {
+                    progress(&describe_progress(downloaded, self.size));
+                    last_report = Instant::now();
+                }
+            }
+            if self.size.is_some_and(|size| downloaded < size) {
+                bail!("The download ended early");
+            }
+            file.flush()?;
+            Ok(())
+        })();
+        if let Err(err) = result {
+            let _ = fs::remove_file(&partial);
+            return Err(err);
+        }
+        // Another file may have taken the name while downloading.
+        let path = unused_path(&self.path);
+        fs::rename(&partial, &path)
+            .with_context(|| format!("Failed to move the download to {}", path.display()))?;
+        Ok(path)
+    }
+}
+
+/// The file's name, from the server or else the end of the link.
+fn file_name(response: &Response) -> String {
+    let from_header = response
+        .headers()
+        .get(header::CONTENT_DISPOSITION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| {
+            value
+                .split(';')
+                .filter_map(|part| part.trim().strip_prefix("filename="))
+                .next()
+                .map(|name| name.trim_matches('"').to_string())
+        });
+    let from_url = response
+        .url()
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .map(|name| name.to_string());
+    // Only the last part of a name is used, so it can't point into another folder.
+    [from_header, from_url]
+        .into_iter()
+        .flatten()
+        .filter_map(|name| {
+            Path::new(&name)
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+        })
+        .find(|name| !name.starts_with('.'))
+        .unwrap_or_else(|| "download".to_string())
+}
+
+/// `path`, or `path` with a number added, like "photo (2).jpg", if it's taken.
+fn unused_path(path: &Path) -> PathBuf {
+    if !path.exists() {
+        return path.to_path_buf();
+    }
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = path
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default();
+    (2..)
+        .map(|number| path.with_file_name(format!("{} ({}){}", stem, number, extension)))
+        .find(|path| !path.exists())
+        .unwrap()
+}
+
+pub fn format_bytes(bytes: u64) -> String {
+    const KB: f64 = 1024.0;
+    const MB: f64 = KB * 1024.0;
+    const GB: f64 = MB * 1024.0;
+    let bytes = bytes as f64;
+    if bytes >= GB {
+        format!("{:.1} GB", bytes / GB)
+    } else if bytes >= MB {
+        format!("{:.1} MB", bytes / MB)
+    } else {
+        format!("{:.0} KB", bytes / KB)
+    }
+}
+
+fn describe_progress(downloaded: u64, size: Option<u64>) -> String {
+    match size.filter(|&size| size > 0) {
+        Some(size) => format!(
+            "{} of {} ({}%)",
+            format_bytes(downloaded),
+            format_bytes(size),
+            downloaded * 100 / size
+        ),
+        None => format!("{} so far", format_bytes(downloaded)),
+    }
+}
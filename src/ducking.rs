@@ -0,0 +1,149 @@
+use std::sync::{LazyLock, Mutex};
+use tracing::debug;
+
+use crate::audio;
+
+/// Tracks why audio should be ducked.
+struct DuckState {
+    duck_while_speaking: bool,
+    duck_while_listening: bool,
+    ducked_volume: f32,
+    speaking: bool,
+    listening: bool,
+    ducked: bool,
+    // Other applications' audio streams and their volumes from before they were ducked.
+    saved_app_volumes: Vec<(u64, Vec<u64>)>,
+}
+
+static DUCK_STATE: LazyLock<Mutex<DuckState>> = LazyLock::new(|| {
+    Mutex::new(DuckState {
+        duck_while_speaking: false,
+        duck_while_listening: false,
+        ducked_volume: 1.0,
+        speaking: false,
+        listening: false,
+        ducked: false,
+        saved_app_volumes: Vec::new(),
+    })
+});
+
+/// Sets when audio should be ducked, and how loud ducked audio plays relative to its normal volume.
+pub fn configure(duck_while_speaking: bool, duck_while_listening: bool, ducked_volume: f32) {
+    let mut state = DUCK_STATE.lock().unwrap();
+    state.duck_while_speaking = duck_while_speaking;
+    state.duck_while_listening = duck_while_listening;
+    state.ducked_volume = ducked_volume;
+    update(&mut state);
+}
+
+/// Called when the AI voice starts or stops speaking.
+pub fn set_speaking(speaking: bool) {
+    let mut state = DUCK_STATE.lock().unwrap();
+    state.speaking = speaking;
+    update(&mut state);
+}
+
+/// Called when the push-to-talk key is pressed or released.
+pub fn set_listening(listening: bool) {
+    let mut state = DUCK_STATE.lock().unwrap();
+    state.listening = listening;
+    update(&mut state);
+}
+
+fn update(state: &mut DuckState) {
+    let should_duck = (state.speaking && state.duck_while_speaking)
+        || (state.listening && state.duck_while_listening);
+    if should_duck == state.ducked {
+        return;
+    }
+    state.ducked = should_duck;
+    debug!("Audio ducking {}", if should_duck { "on" } else { "off" });
+
+    // The assistant's own sounds, other than its voice.
+    audio::set_ducked(should_duck.then_some(state.ducked_volume));
+
+    if should_duck {
+        state.saved_app_volumes = duck_other_applications(state.ducked_volume);
+    } else {
+        restore_other_applications(std::mem::take(&mut state.saved_app_volumes));
+    }
+}
+
+/// Lowers the volume of every other application's audio stream through PulseAudio/PipeWire.
+/// Returns each stream's index and per-channel volume from before it was lowered.
+#[cfg(target_os = "linux")]
+fn duck_other_applications(ducked_volume: f32) -> Vec<(u64, Vec<u64>)> {
+    let output = match std::process::Command::new("pactl")
+        .args(["--format=json", "list", "sink-inputs"])
+        .output()
+    {
+        Ok(output) if output.status.success() => output,
+        _ => {
+            debug!("pactl unavailable. Only ducking the assistant's own sounds.");
+            return Vec::new();
+        }
+    };
+
+    let sink_inputs: serde_json::Value = match serde_json::from_slice(&output.stdout) {
+        Ok(json) => json,
+        Err(err) => {
+            debug!("Failed to parse pactl output: {}", err);
+            return Vec::new();
+        }
+    };
+
+    let own_pid = std::process::id().to_string();
+    let mut saved = Vec::new();
+    for sink_input in sink_inputs.as_array().into_iter().flatten() {
+        let pid = sink_input["properties"]["application.process.id"].as_str();
+        if pid == Some(own_pid.as_str()) {
+            continue;
+        }
+        let Some(index) = sink_input["index"].as_u64() else {
+            continue;
+        };
+        let volumes: Vec<u64> = sink_input["volume"]
+            .as_object()
+            .into_iter()
+            .flat_map(|channels| channels.values())
+            .filter_map(|channel| channel["value"].as_u64())
+            .collect();
+        if volumes.is_empty() {
+            continue;
+        }
+
+        let ducked: Vec<u64> = volumes
+            .iter()
+            .map(|&v| (v as f32 * ducked_volume) as u64)
+            .collect();
+        set_sink_input_volume(index, &ducked);
+        saved.push((index, volumes));
+    }
+
+    saved
+}
+
+#[cfg(target_os = "linux")]
+fn restore_other_applications(saved_app_volumes: Vec<(u64, Vec<u64>)>) {
+    for (index, volumes) in saved_app_volumes {
+        set_sink_input_volume(index, &volumes);
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn set_sink_input_volume(index: u64, volumes: &[u64]) {
+    let mut args = vec!["set-sink-input-volume".to_string(), index.to_string()];
+    args.extend(volumes.iter().map(|v| v.to_string()));
+    if let Err(err) = std::process::Command::new("pactl").args(&args).output() {
+        debug!("Failed to set volume of sink input {}: {}", index, err);
+    }
+}
+
+// Other platforms have no mixer integration yet, so only the assistant's own sounds are ducked.
+#[cfg(not(target_os = "linux"))]
+fn duck_other_applications(_ducked_volume: f32) -> Vec<(u64, Vec<u64>)> {
+    Vec::new()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn restore_other_applications(_saved_app_volumes: Vec<(u64, Vec<u64>)>) {}
@@ -0,0 +1,436 @@
+//! Sending plain text emails over SMTP for the send_email tool.
+//! The server and address come from the config file, and the password from the system keyring.
+//! Mail is only ever sent encrypted, either with TLS from the start on port 465, or with STARTTLS.
+
+use anyhow::{bail, Context};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::Local;
+use rustls::{pki_types::ServerName, ClientConfig, ClientConnection, RootCertStore, StreamOwned};
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
+use uuid::Uuid;
+
+use crate::secrets;
+
+/// The port that starts with TLS, rather than upgrading to it with STARTTLS.
+const IMPLICIT_TLS_PORT: u16 = 465;
+const DEFAULT_PORT: u16 = 587;
+const TIMEOUT: Duration = Duration::from_secs(30);
+/// How many bytes of text fit in one encoded word, which can be at most 75 characters long
+/// including its `=?UTF-8?B?` and `?=`. 45 bytes is 60 characters of base64.
+const ENCODED_WORD_BYTES: usize = 45;
+
+struct Account {
+    host: String,
+    port: u16,
+    from: String,
+    username: String,
+}
+
+static ACCOUNT: OnceLock<Account> = OnceLock::new();
+
+/// Sets the account emails are sent from, from the config file. The username defaults to the address.
+pub fn configure(host: String, port: Option<u16>, from: String, username: Option<String>) {
+    let _ = ACCOUNT.set(Account {
+        host,
+        port: port.unwrap_or(DEFAULT_PORT),
+        username: username.unwrap_or_else(|| from.clone()),
+        from,
+    });
+}
+
+/// Whether an account has been set up to send from.
+pub fn is_configured() -> bool {
+    ACCOUNT.get().is_some()
+}
+
+/// Checks that `address` looks like an email address, and can't change the SMTP commands it's put in.
+fn check_address(address: &str) -> Result<(), anyhow::Error> {
+    let valid = address
+        .split_once('@')
+        .is_some_and(|(user, domain)| !user.is_empty() && domain.contains('.'))
+        && !address
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control() || matches!(c, '<' | '>' | ',' | ';'));
+    if !valid {
+        bail!("\"{}\" isn't an email address", address);
+    }
+    Ok(())
+}
+
+/// Splits a list of addresses separated by commas or semicolons.
+pub fn parse_recipients(to: &str) -> Result<Vec<String>, anyhow::Error> {
+    let recipients: Vec<String> = to
+        .split([',', ';'])
+        .map(str::trim)
+        .filter(|address| !address.is_empty())
+        .map(str::to_string)
+        .collect();
+    if recipients.is_empty() {
+        bail!("No one to send the email to was given");
+    }
+    for address in &recipients {
+        check_address(address)?;
+    }
+    Ok(recipients)
+}
+
+/// Sends a plain text email to every address in `to`.
+pub fn send(to: &str, subject: &str, body: &str) -> Result<(), anyhow::Error> {
+    let Some(account) = ACCOUNT.get() else {
+        bail!("Email isn't set up. Add an [email] section to the config file, then run `quick-assistant set-email-password`.");
+    };
+    let Some(password) = secrets::load_email_password() else {
+        bail!("No email password is saved. Run `quick-assistant set-email-password` to save one.");
+    };
+    check_address(&account.from)?;
+    let recipients = parse_recipients(to)?;
+    let message = compose(&account.from, &recipients, subject, body);
+
+    let mut session = Session::connect(account)?;
+    session.authenticate(&account.username, &password)?;
+    session.command(&format!("MAIL FROM:<{}>", account.from), 250)?;
+    for recipient in &recipients {
+        session
+            .command(&format!("RCPT TO:<{}>", recipient), 250)
+            .with_context(|| format!("The mail server refused {}", recipient))?;
+    }
+    session.command("DATA", 354)?;
+    session.command(&format!("{}\r\n.", dot_stuff(&message)), 250)?;
+    // The email has been accepted, so it doesn't matter if saying goodbye fails.
+    let _ = session.command("QUIT", 221);
+    Ok(())
+}
+
+/// Encodes a header value that isn't plain ASCII, so any language can be used in the subject.
+/// Long values are split into several encoded words on folded lines, without splitting a character.
+fn encode_header(value: &str) -> String {
+    if value.is_ascii() {
+        return value.to_string();
+    }
+    let mut words = Vec::new();
+    let mut chunk = String::new();
+    for c in value.chars() {
+        if chunk.len() + c.len_utf8() > ENCODED_WORD_BYTES {
+            words.push(format!("=?UTF-8?B?{}?=", STANDARD.encode(&chunk)));
+            chunk.clear();
+        }
+        chunk.push(c);
+    }
+    if !chunk.is_empty() {
+        words.push(format!("=?UTF-8?B?{}?=", STANDARD.encode(&chunk)));
+    }
+    words.join("\r\n ")
+}
+
+/// Doubles the dot at the start of any line, so no line of the email can be taken as its end.
+fn dot_stuff(message: &str) -> String {
+    message
+        .split("\r\n")
+        .map(|line| match line.starts_with('.') {
+            true => format!(".{}", line),
+            false => line.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\r\n")
+}
+
+/// Writes the email's headers and body, ready to be sent after DATA.
+fn compose(from: &str, to: &[String], subject: &str, body: &str) -> String {
+    // Line breaks in the subject would start new headers.
+    let subject = subject.replace(['\r', '\n'], " ");
+    let domain = from.rsplit('@').next().unwrap_or("localhost");
+    let body = body.replace("\r\n", "\n").replace('\n', "\r\n");
+    // Base64 keeps any text safe to send, and lines from ever being too long.
+    let encoded = STANDARD.encode(body);
+    let lines: Vec<&str> = encoded
+        .as_bytes()
+        .chunks(76)
+        .map(|line| std::str::from_utf8(line).unwrap_or_default())
+        .collect();
+    format!(
+        "From: {}\r\nTo: {}\r\nSubject: {}\r\nDate: {}\r\nMessage-ID: <{}@{}>\r\nMIME-Version: 1.0\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Transfer-Encoding: base64\r\n\r\n{}",
+        from,
+        to.join(", "),
+        encode_header(&subject),
+        Local::now().to_rfc2822(),
+        Uuid::new_v4(),
+        domain,
+        lines.join("\r\n")
+    )
+}
+
+enum Stream {
+    Plain(TcpStream),
+    Tls(Box<StreamOwned<ClientConnection, TcpStream>>),
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Stream::Plain(stream) => stream.read(buf),
+            Stream::Tls(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Stream::Plain(stream) => stream.write(buf),
+            Stream::Tls(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Stream::Plain(stream) => stream.flush(),
+            Stream::Tls(stream) => stream.flush(),
+        }
+    }
+}
+
+/// Wraps `tcp` in TLS, checking the server's certificate against the system's trusted ones.
+fn start_tls(tcp: TcpStream, host: &str) -> Result<Stream, anyhow::Error> {
+    let mut roots = RootCertStore::empty();
+    for cert in rustls_native_certs::load_native_certs().certs {
+        let _ = roots.add(cert);
+    }
+    let config = ClientConfig::builder()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    let name = ServerName::try_from(host.to_string())
+        .with_context(|| format!("\"{}\" isn't a valid server name", host))?;
+    let connection = ClientConnection::new(Arc::new(config), name)?;
+    Ok(Stream::Tls(Box::new(StreamOwned::new(connection, tcp))))
+}
+
+struct Session {
+    stream: BufReader<Stream>,
+    /// What the server said it supports, in answer to EHLO.
+    extensions: Vec<String>,
+}
+
+impl Session {
+    fn connect(account: &Account) -> Result<Self, anyhow::Error> {
+        let address = (account.host.as_str(), account.port)
+            .to_socket_addrs()
+            .with_context(|| format!("Failed to look up {}", account.host))?
+            .next()
+            .with_context(|| format!("Failed to look up {}", account.host))?;
+        let tcp = TcpStream::connect_timeout(&address, TIMEOUT)
+            .with_context(|| format!("Failed to connect to {}", account.host))?;
+        tcp.set_read_timeout(Some(TIMEOUT))?;
+        tcp.set_write_timeout(Some(TIMEOUT))?;
+
+        let implicit_tls = account.port == IMPLICIT_TLS_PORT;
+        let stream = match implicit_tls {
+            true => start_tls(tcp, &account.host)?,
+            false => Stream::Plain(tcp),
+        };
+        let mut session = Self {
+            stream: BufReader::new(stream),
+            extensions: Vec::new(),
+        };
+        session.expect(220)?;
+        session.hello()?;
+
+        if !implicit_tls {
+            if !session.supports("STARTTLS") {
+                bail!(
+                    "{} doesn't support encryption, so the password can't be sent safely",
+                    account.host
+                );
+            }
+            session.command("STARTTLS", 220)?;
+            let Stream::Plain(tcp) = session.stream.into_inner() else {
+                unreachable!("STARTTLS is only used on a plain connection");
+            };
+            session.stream = BufReader::new(start_tls(tcp, &account.host)?);
+            // The server forgets everything from before encryption started.
+            session.hello()?;
+        }
+        Ok(session)
+    }
+
+    fn hello(&mut self) -> Result<(), anyhow::Error> {
+        let lines = self.command("EHLO localhost", 250)?;
+        // The first line is the server greeting us.
+        self.extensions = lines
+            .into_iter()
+            .skip(1)
+            .map(|line| line.to_uppercase())
+            .collect();
+        Ok(())
+    }
+
+    fn supports(&self, extension: &str) -> bool {
+        self.extensions
+            .iter()
+            .any(|line| line.split_whitespace().next() == Some(extension))
+    }
+
+    fn authenticate(&mut self, username: &str, password: &str) -> Result<(), anyhow::Error> {
+        let mechanisms: Vec<&str> = self
+            .extensions
+            .iter()
+            .filter_map(|line| line.strip_prefix("AUTH "))
+            .flat_map(str::split_whitespace)
+            .collect();
+        let result = if mechanisms.contains(&"PLAIN") {
+            let credentials = STANDARD.encode(format!("\0{}\0{}", username, password));
+            self.command(&format!("AUTH PLAIN {}", credentials), 235)
+        } else if mechanisms.contains(&"LOGIN") {
+            self.command("AUTH LOGIN", 334)?;
+            self.command(&STANDARD.encode(username), 334)?;
+            self.command(&STANDARD.encode(password), 235)
+        } else {
+            bail!("The mail server doesn't support logging in with a password");
+        };
+        result
+            .map(|_| ())
+            .context("The mail server didn't accept the username and password")
+    }
+
+    /// Sends a command, and returns the lines of the reply if it has the expected code.
+    fn command(&mut self, command: &str, expected: u16) -> Result<Vec<String>, anyhow::Error> {
+        let stream = self.stream.get_mut();
+        stream.write_all(command.as_bytes())?;
+        stream.write_all(b"\r\n")?;
+        stream.flush()?;
+        self.expect(expected)
+    }
+
+    /// Reads a reply, which can span several lines, and checks its code.
+    fn expect(&mut self, expected: u16) -> Result<Vec<String>, anyhow::Error> {
+        let mut lines = Vec::new();
+        loop {
+            let mut line = String::new();
+            if self.stream.read_line(&mut line)? == 0 {
+                bail!("The mail server closed the connection");
+            }
+            let line = line.trim_end();
+            let code: u16 = line
+                .get(..3)
+                .and_then(|code| code.parse().ok())
+                .with_context(|| format!("The mail server sent a bad reply: {}", line))?;
+            let last = line.as_bytes().get(3) != Some(&b'-');
+            lines.push(line.get(4..).unwrap_or_default().to_string());
+            if last {
+                if code != expected {
+                    bail!("The mail server said: {}", line);
+                }
+                return Ok(lines);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{net::TcpListener, thread};
+
+    #[test]
+    fn long_subjects_are_split_into_short_encoded_words() {
+        let subject = "Über ".repeat(40);
+        let encoded = encode_header(&subject);
+        let mut decoded = String::new();
+        for word in encoded.split("\r\n ") {
+            assert!(word.len() <= 75, "{} is too long", word);
+            let text = word
+                .strip_prefix("=?UTF-8?B?")
+                .and_then(|word| word.strip_suffix("?="))
+                .unwrap();
+            decoded.push_str(&String::from_utf8(STANDARD.decode(text).unwrap()).unwrap());
+        }
+        assert_eq!(decoded, subject);
+        assert_eq!(encode_header("Plain subject"), "Plain subject");
+    }
+
+    #[test]
+    fn lines_starting_with_a_dot_are_stuffed() {
+        assert_eq!(dot_stuff(".\r\nok\r\n..two"), "..\r\nok\r\n...two");
+        let message = compose(
+            "me@example.com",
+            &["you@example.com".to_string()],
+            "Hi",
+            ".\n.hidden\nend",
+        );
+        assert!(!dot_stuff(&message).split("\r\n").any(|line| line == "."));
+    }
+
+    #[test]
+    fn headers_cant_be_added_through_the_subject() {
+        let message = compose(
+            "me@example.com",
+            &["you@example.com".to_string()],
+            "Hi\r\nBcc: someone@example.com",
+            "Hello",
+        );
+        assert!(!message.split("\r\n").any(|line| line.starts_with("Bcc:")));
+        assert!(check_address("a@b.com>\r\nRCPT TO:<c@d.com").is_err());
+    }
+
+    /// Logs in to a pretend server that offers `mechanisms`, and returns what it was sent.
+    fn log_in(mechanisms: &'static str, replies: &'static [&'static str]) -> Vec<String> {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (tcp, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(tcp.try_clone().unwrap());
+            let mut writer = tcp;
+            let mut received = Vec::new();
+            writer.write_all(b"220 ready\r\n").unwrap();
+            let ehlo = format!("250-example.com\r\n250 AUTH {}\r\n", mechanisms);
+            for reply in std::iter::once(ehlo.as_str()).chain(replies.iter().copied()) {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                received.push(line.trim_end().to_string());
+                writer.write_all(reply.as_bytes()).unwrap();
+            }
+            received
+        });
+        let mut session = Session {
+            stream: BufReader::new(Stream::Plain(TcpStream::connect(address).unwrap())),
+            extensions: Vec::new(),
+        };
+        session.expect(220).unwrap();
+        session.hello().unwrap();
+        session.authenticate("me@example.com", "secret").unwrap();
+        server.join().unwrap()
+    }
+
+    #[test]
+    fn logs_in_with_plain() {
+        let received = log_in("LOGIN PLAIN", &["235 ok\r\n"]);
+        assert_eq!(
+            received,
+            [
+                "EHLO localhost".to_string(),
+                format!("AUTH PLAIN {}", STANDARD.encode("\0me@example.com\0secret")),
+            ]
+        );
+    }
+
+    #[test]
+    fn logs_in_with_login() {
+        let received = log_in(
+            "LOGIN",
+            &["334 VXNlcm5hbWU6\r\n", "334 UGFzc3dvcmQ6\r\n", "235 ok\r\n"],
+        );
+        assert_eq!(
+            received,
+            [
+                "EHLO localhost".to_string(),
+                "AUTH LOGIN".to_string(),
+                STANDARD.encode("me@example.com"),
+                STANDARD.encode("secret"),
+            ]
+        );
+    }
+}
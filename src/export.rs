@@ -0,0 +1,146 @@
+//! Writes saved conversations out as Markdown or HTML transcripts, for the `export` subcommand
+//! and the `export_conversation` tool.
+
+use anyhow::Context;
+use chrono::{DateTime, Local};
+use clap::ValueEnum;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::sessions::{self, Entry, EntryMessage};
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default)]
+pub enum Format {
+    #[default]
+    Markdown,
+    Html,
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::from_str(name, true).ok()
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Markdown => "md",
+            Format::Html => "html",
+        }
+    }
+}
+
+/// Where transcripts go when no folder is given.
+pub fn default_folder() -> PathBuf {
+    dirs::document_dir()
+        .or_else(dirs::home_dir)
+        .unwrap()
+        .join("quick-assistant")
+}
+
+/// Writes a session's transcript into `folder`, and returns the path of the transcript.
+pub fn export(session: &Path, format: Format, folder: &Path) -> Result<PathBuf, anyhow::Error> {
+    let entries = sessions::load(session)?;
+    let name = sessions::name_of(session);
+    let title = match entries.first() {
+        Some(entry) => format!(
+            "Conversation on {}",
+            entry.time.format("%B %-d, %Y at %H:%M")
+        ),
+        None => format!("Conversation {}", name),
+    };
+    let transcript = match format {
+        Format::Markdown => markdown(&title, &entries),
+        Format::Html => html(&title, &entries),
+    };
+
+    fs::create_dir_all(folder).with_context(|| format!("Failed to create {}", folder.display()))?;
+    let path = folder.join(format!("conversation_{}.{}", name, format.extension()));
+    fs::write(&path, transcript).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+fn time(time: &DateTime<Local>) -> String {
+    time.format("%H:%M:%S").to_string()
+}
+
+fn markdown(title: &str, entries: &[Entry]) -> String {
+    let mut out = format!("# {}\n", title);
+    for entry in entries {
+        let time = time(&entry.time);
+        out.push('\n');
+        match &entry.message {
+            EntryMessage::User { text } => {
+                out.push_str(&format!("**You** · {}\n\n{}\n", time, text))
+            }
+            EntryMessage::Assistant { text } => {
+                out.push_str(&format!("**Assistant** · {}\n\n{}\n", time, text))
+            }
+            EntryMessage::ToolCall { name, arguments } => out.push_str(&format!(
+                "*Called `{}`* · {}\n\n```json\n{}\n```\n",
+                name, time, arguments
+            )),
+            EntryMessage::ToolResult { name, content } => out.push_str(&format!(
+                "*`{}` returned* · {}\n\n```\n{}\n```\n",
+                name, time, content
+            )),
+        }
+    }
+    out
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+const HTML_STYLE: &str =
+    "body { font-family: sans-serif; max-width: 50em; margin: 2em auto; padding: 0 1em; }
+.entry { margin: 1em 0; padding: 0.6em 0.9em; border-radius: 8px; }
+.user { background: #dcecff; }
+.assistant { background: #f0f0f0; }
+.tool { background: #fff6dc; font-size: 0.9em; }
+.meta { color: #666; font-size: 0.85em; margin-bottom: 0.3em; }
+.text { white-space: pre-wrap; }
+pre { white-space: pre-wrap; margin: 0; }";
+
+fn html(title: &str, entries: &[Entry]) -> String {
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n<style>\n{HTML_STYLE}\n</style>\n</head>\n<body>\n<h1>{title}</h1>\n",
+        title = escape_html(title),
+    );
+    for entry in entries {
+        let (class, who, body) = match &entry.message {
+            EntryMessage::User { text } => ("user", "You".to_string(), text_html(text)),
+            EntryMessage::Assistant { text } => {
+                ("assistant", "Assistant".to_string(), text_html(text))
+            }
+            EntryMessage::ToolCall { name, arguments } => (
+                "tool",
+                format!("Called {}", escape_html(name)),
+                format!("<pre>{}</pre>", escape_html(arguments)),
+            ),
+            EntryMessage::ToolResult { name, content } => (
+                "tool",
+                format!("{} returned", escape_html(name)),
+                format!("<pre>{}</pre>", escape_html(content)),
+            ),
+        };
+        out.push_str(&format!(
+            "<div class=\"entry {}\">\n<div class=\"meta\"><strong>{}</strong> · {}</div>\n{}\n</div>\n",
+            class,
+            who,
+            time(&entry.time),
+            body
+        ));
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+fn text_html(text: &str) -> String {
+    format!("<div class=\"text\">{}</div>", escape_html(text))
+}
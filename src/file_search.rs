@@ -0,0 +1,157 @@
+//! Finding files by name for the search_files tool. Only the configured folders are searched,
+//! so the AI can't go looking through the rest of the system.
+
+use anyhow::{bail, Context};
+use chrono::{DateTime, Local};
+use glob::{MatchOptions, Pattern};
+use std::{
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+use walkdir::WalkDir;
+
+/// The most results returned, however many the AI asks for.
+pub const MAX_RESULTS: usize = 50;
+/// How many files and folders are looked at before giving up, so a search can't run for ages.
+const MAX_VISITED: usize = 200_000;
+
+static ALLOWED_ROOTS: OnceLock<Vec<PathBuf>> = OnceLock::new();
+
+/// Sets the folders that can be searched, from the config file. They can be names like "downloads".
+pub fn configure(allowed_roots: Vec<PathBuf>) {
+    let _ = ALLOWED_ROOTS.set(
+        allowed_roots
+            .iter()
+            .map(|root| resolve_root(&root.to_string_lossy()))
+            .collect(),
+    );
+}
+
+fn allowed_roots() -> &'static [PathBuf] {
+    ALLOWED_ROOTS.get_or_init(|| {
+        [
+            dirs::document_dir(),
+            dirs::download_dir(),
+            dirs::desktop_dir(),
+            dirs::audio_dir(),
+            dirs::picture_dir(),
+            dirs::video_dir(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    })
+}
+
+/// A file that matched a search.
+pub struct Found {
+    pub path: PathBuf,
+    pub size: u64,
+    pub modified: Option<DateTime<Local>>,
+}
+
+/// Turns a folder name like "downloads" into its path. Anything else is taken as a path.
+pub fn resolve_root(root: &str) -> PathBuf {
+    let known = match root.trim().to_lowercase().as_str() {
+        "home" | "~" => dirs::home_dir(),
+        "documents" => dirs::document_dir(),
+        "downloads" => dirs::download_dir(),
+        "desktop" => dirs::desktop_dir(),
+        "music" => dirs::audio_dir(),
+        "pictures" => dirs::picture_dir(),
+        "videos" => dirs::video_dir(),
+        _ => None,
+    };
+    known.unwrap_or_else(|| match root.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir().unwrap_or_default().join(rest),
+        None => PathBuf::from(root),
+    })
+}
+
+/// Whether `path` is inside one of the folders that can be searched.
+pub fn is_allowed(path: &Path) -> bool {
+    let Ok(path) = path.canonicalize() else {
+        return false;
+    };
+    allowed_roots()
+        .iter()
+        .filter_map(|root| root.canonicalize().ok())
+        .any(|root| path.starts_with(root))
+}
+
+/// Finds files whose names match `pattern`, newest first. A pattern without wildcards matches any name
+/// containing it. Searches `root` if it's given, or every allowed folder if not.
+pub fn search(
+    pattern: &str,
+    root: Option<&str>,
+    limit: usize,
+) -> Result<Vec<Found>, anyhow::Error> {
+    let pattern = pattern.trim();
+    if pattern.is_empty() {
+        bail!("No file name pattern was given");
+    }
+    let pattern = match pattern.contains(['*', '?', '[']) {
+        true => pattern.to_string(),
+        false => format!("*{}*", Pattern::escape(pattern)),
+    };
+    let pattern =
+        Pattern::new(&pattern).with_context(|| format!("\"{}\" isn't a valid pattern", pattern))?;
+    let options = MatchOptions {
+        case_sensitive: false,
+        ..Default::default()
+    };
+
+    let roots = match root {
+        Some(root) => {
+            let root = resolve_root(root);
+            if !is_allowed(&root) {
+                bail!(
+                    "{} isn't a folder that can be searched. Allowed folders: {}",
+                    root.display(),
+                    allowed_roots()
+                        .iter()
+                        .map(|root| root.display().to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+            }
+            vec![root]
+        }
+        None => allowed_roots().to_vec(),
+    };
+
+    let mut found = Vec::new();
+    let mut visited = 0;
+    for root in roots {
+        let entries = WalkDir::new(root)
+            .into_iter()
+            // Hidden folders are mostly caches and settings.
+            .filter_entry(|entry| {
+                entry.depth() == 0 || !entry.file_name().to_string_lossy().starts_with('.')
+            })
+            .flatten();
+        for entry in entries {
+            visited += 1;
+            if visited > MAX_VISITED {
+                break;
+            }
+            if !entry.file_type().is_file()
+                || !pattern.matches_with(&entry.file_name().to_string_lossy(), options)
+            {
+                continue;
+            }
+            let metadata = entry.metadata().ok();
+            found.push(Found {
+                path: entry.into_path(),
+                size: metadata.as_ref().map_or(0, |metadata| metadata.len()),
+                modified: metadata
+                    .and_then(|metadata| metadata.modified().ok())
+                    .map(DateTime::<Local>::from),
+            });
+        }
+    }
+
+    found.sort_by_key(|file| std::cmp::Reverse(file.modified));
+    found.truncate(limit.min(MAX_RESULTS));
+    Ok(found)
+}
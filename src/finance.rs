@@ -0,0 +1,220 @@
+//! Exchange rates and stock quotes for the get_exchange_rate and get_stock_quote tools.
+//! Exchange rates are the European Central Bank's, from Frankfurter, which doesn't need an API key.
+//! Quotes come from Alpha Vantage when its API key is in the config file, or Yahoo Finance if not.
+
+use anyhow::{bail, Context};
+use chrono::{DateTime, Local};
+use serde::Deserialize;
+use std::{collections::HashMap, sync::OnceLock, time::Duration};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Yahoo turns away requests that don't look like they're from a browser.
+const USER_AGENT: &str = "Mozilla/5.0 (compatible; quick-assistant)";
+
+static ALPHA_VANTAGE_KEY: OnceLock<String> = OnceLock::new();
+
+/// Sets the Alpha Vantage API key used for stock quotes, from the config file.
+pub fn configure(alpha_vantage_key: String) {
+    let _ = ALPHA_VANTAGE_KEY.set(alpha_vantage_key);
+}
+
+#[derive(Deserialize)]
+struct Rates {
+    amount: f64,
+    base: String,
+    date: String,
+    rates: HashMap<String, f64>,
+}
+
+/// Converts `amount` of one currency to another, like "USD" to "EUR", at the latest rate.
+pub fn exchange_rate(from: &str, to: &str, amount: f64) -> Result<String, anyhow::Error> {
+    let (from, to) = (from.trim().to_uppercase(), to.trim().to_uppercase());
+    if from == to {
+        return Ok(format!("{} {} = {} {}", amount, from, amount, to));
+    }
+    let response = reqwest::blocking::Client::new()
+        .get("https://api.frankfurter.app/latest")
+        .query(&[
+            ("amount", amount.to_string()),
+            ("from", from.clone()),
+            ("to", to.clone()),
+        ])
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .context("Failed to get exchange rates")?;
+    // An unknown currency is a 404 or a 422.
+    if response.status().is_client_error() {
+        bail!(
+            "{} to {} isn't a pair of currencies the European Central Bank publishes rates for. Use three letter codes like USD.",
+            from,
+            to
+        );
+    }
+    let rates: Rates = response.error_for_status()?.json()?;
+    let Some(converted) = rates.rates.get(&to) else {
+        bail!("No exchange rate for {} was found", to);
+    };
+    Ok(format!(
+        "{} {} = {:.4} {} (rate from {})",
+        rates.amount, rates.base, converted, to, rates.date
+    ))
+}
+
+/// A stock's latest price and how it's changed since the last close.
+struct Quote {
+    symbol: String,
+    name: Option<String>,
+    price: f64,
+    previous_close: Option<f64>,
+    currency: Option<String>,
+    /// When the price is from, as it was given.
+    time: Option<String>,
+}
+
+/// Describes the latest quote for a ticker symbol like "AAPL", for the AI.
+pub fn stock_quote(symbol: &str) -> Result<String, anyhow::Error> {
+    let symbol = symbol.trim().to_uppercase();
+    if symbol.is_empty() {
+        bail!("No ticker symbol was given");
+    }
+    let quote = match ALPHA_VANTAGE_KEY.get() {
+        Some(key) => alpha_vantage_quote(&symbol, key)?,
+        None => yahoo_quote(&symbol)?,
+    };
+
+    let mut info = match &quote.name {
+        Some(name) => format!("{} ({})", name, quote.symbol),
+        None => quote.symbol.clone(),
+    };
+    info.push_str(&format!(": {:.2}", quote.price));
+    if let Some(currency) = &quote.currency {
+        info.push_str(&format!(" {}", currency));
+    }
+    if let Some(previous_close) = quote.previous_close.filter(|close| *close != 0.0) {
+        let change = quote.price - previous_close;
+        info.push_str(&format!(
+            ", {:+.2} ({:+.2}%) since the previous close of {:.2}",
+            change,
+            change / previous_close * 100.0,
+            previous_close
+        ));
+    }
+    if let Some(time) = &quote.time {
+        info.push_str(&format!(", as of {}", time));
+    }
+    Ok(info)
+}
+
+#[derive(Deserialize)]
+struct YahooResponse {
+    chart: YahooChart,
+}
+
+#[derive(Deserialize)]
+struct YahooChart {
+    result: Option<Vec<YahooResult>>,
+    error: Option<YahooError>,
+}
+
+#[derive(Deserialize)]
+struct YahooError {
+    description: String,
+}
+
+#[derive(Deserialize)]
+struct YahooResult {
+    meta: YahooMeta,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct YahooMeta {
+    symbol: String,
+    currency: Option<String>,
+    long_name: Option<String>,
+    short_name: Option<String>,
+    regular_market_price: Option<f64>,
+    chart_previous_close: Option<f64>,
+    previous_close: Option<f64>,
+    regular_market_time: Option<i64>,
+}
+
+fn yahoo_quote(symbol: &str) -> Result<Quote, anyhow::Error> {
+    let response: YahooResponse = reqwest::blocking::Client::new()
+        .get(format!(
+            "https://query1.finance.yahoo.com/v8/finance/chart/{}",
+            symbol
+        ))
+        .query(&[("range", "1d"), ("interval", "1d")])
+        .header(reqwest::header::USER_AGENT, USER_AGENT)
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .context("Failed to get the stock quote")?
+        .json()
+        .context("Failed to read the stock quote")?;
+    if let Some(error) = response.chart.error {
+        bail!("{}", error.description);
+    }
+    let Some(meta) = response
+        .chart
+        .result
+        .and_then(|results| results.into_iter().next())
+        .map(|result| result.meta)
+    else {
+        bail!("No stock with the symbol {} was found", symbol);
+    };
+    let Some(price) = meta.regular_market_price else {
+        bail!("{} has no price right now", symbol);
+    };
+    Ok(Quote {
+        symbol: meta.symbol,
+        name: meta.long_name.or(meta.short_name),
+        price,
+        previous_close: meta.previous_close.or(meta.chart_previous_close),
+        currency: meta.currency,
+        time: meta
+            .regular_market_time
+            .and_then(|time| DateTime::from_timestamp(time, 0))
+            .map(|time| {
+                time.with_timezone(&Local)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string()
+            }),
+    })
+}
+
+fn alpha_vantage_quote(symbol: &str, key: &str) -> Result<Quote, anyhow::Error> {
+    let response: HashMap<String, serde_json::Value> = reqwest::blocking::Client::new()
+        .get("https://www.alphavantage.co/query")
+        .query(&[
+            ("function", "GLOBAL_QUOTE"),
+            ("symbol", symbol),
+            ("apikey", key),
+        ])
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .context("Failed to get the stock quote")?
+        .error_for_status()?
+        .json()
+        .context("Failed to read the stock quote")?;
+    // Running out of requests, or a bad key, is a message instead of a quote.
+    for message in ["Note", "Information", "Error Message"] {
+        if let Some(message) = response.get(message).and_then(|message| message.as_str()) {
+            bail!("Alpha Vantage said: {}", message);
+        }
+    }
+    let quote = response
+        .get("Global Quote")
+        .filter(|quote| quote.as_object().is_some_and(|quote| !quote.is_empty()))
+        .with_context(|| format!("No stock with the symbol {} was found", symbol))?;
+    let field = |name: &str| quote[name].as_str().map(str::to_string);
+    let number = |name: &str| field(name).and_then(|value| value.parse::<f64>().ok());
+    Ok(Quote {
+        symbol: field("01. symbol").unwrap_or_else(|| symbol.to_string()),
+        name: None,
+        price: number("05. price").context("The quote has no price")?,
+        previous_close: number("08. previous close"),
+        currency: None,
+        time: field("07. latest trading day"),
+    })
+}
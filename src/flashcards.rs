@@ -0,0 +1,350 @@
+//! Flashcards the user can be quizzed on by voice. Cards are kept in decks in a SQLite database,
+//! and each is scheduled with spaced repetition: the better it's known, the longer until it's due.
+//! During a review session the AI asks each due card's question, the user answers out loud, and
+//! the AI grades the answer with grade_flashcard, which moves on to the next card.
+
+use anyhow::{bail, Context};
+use chrono::{DateTime, TimeDelta, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::{
+    fs,
+    sync::{Mutex, MutexGuard, OnceLock},
+};
+use tracing::info;
+
+use crate::CACHE_DIR;
+
+// Each migration upgrades the database schema by one version. Only ever add to the end of this list.
+const MIGRATIONS: [&str; 1] = ["CREATE TABLE cards (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        deck TEXT NOT NULL,
+        question TEXT NOT NULL,
+        answer TEXT NOT NULL,
+        created TEXT NOT NULL,
+        -- In UTC, so due dates sort properly across daylight saving changes.
+        due TEXT NOT NULL,
+        interval_days REAL NOT NULL DEFAULT 0,
+        ease REAL NOT NULL DEFAULT 2.5,
+        repetitions INTEGER NOT NULL DEFAULT 0,
+        lapses INTEGER NOT NULL DEFAULT 0
+    );"];
+
+/// The most cards asked in one review session.
+const MAX_SESSION_CARDS: u32 = 20;
+/// A forgotten card comes back this soon, so it can be asked again in the same session.
+const RELEARN_MINUTES: i64 = 10;
+const MIN_EASE: f64 = 1.3;
+
+static DB: OnceLock<Mutex<Connection>> = OnceLock::new();
+
+fn db() -> Result<MutexGuard<'static, Connection>, anyhow::Error> {
+    if DB.get().is_none() {
+        let _ = DB.set(Mutex::new(
+            open().context("Failed to open the flashcards database")?,
+        ));
+    }
+    Ok(DB.get().unwrap().lock().unwrap())
+}
+
+fn open() -> Result<Connection, anyhow::Error> {
+    fs::create_dir_all(CACHE_DIR.as_path())?;
+    let mut conn = Connection::open(CACHE_DIR.join("flashcards.db"))?;
+    let version: usize = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration)?;
+        tx.pragma_update(None, "user_version", i + 1)?;
+        tx.commit()?;
+        info!("Migrated the flashcards database to version {}", i + 1);
+    }
+    Ok(conn)
+}
+
+/// How well the user knew a card's answer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Grade {
+    /// They didn't know it.
+    Again,
+    /// They got it, but with difficulty.
+    Hard,
+    Good,
+    /// They knew it straight away.
+    Easy,
+}
+
+impl Grade {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "again" | "wrong" => Some(Self::Again),
+            "hard" => Some(Self::Hard),
+            "good" | "right" => Some(Self::Good),
+            "easy" => Some(Self::Easy),
+            _ => None,
+        }
+    }
+
+    /// The grade on SM-2's scale of 0 to 5.
+    fn quality(self) -> f64 {
+        match self {
+            Self::Again => 0.0,
+            Self::Hard => 3.0,
+            Self::Good => 4.0,
+            Self::Easy => 5.0,
+        }
+    }
+}
+
+struct Card {
+    id: i64,
+    deck: String,
+    question: String,
+    answer: String,
+    interval_days: f64,
+    ease: f64,
+    repetitions: u32,
+}
+
+/// The columns a card is read from.
+const CARD_COLUMNS: &str = "id, deck, question, answer, interval_days, ease, repetitions";
+
+impl Card {
+    fn from_row(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row.get(0)?,
+            deck: row.get(1)?,
+            question: row.get(2)?,
+            answer: row.get(3)?,
+            interval_days: row.get(4)?,
+            ease: row.get(5)?,
+            repetitions: row.get(6)?,
+        })
+    }
+}
+
+/// The review session in progress.
+struct Session {
+    /// Only cards from this deck are asked, or from every deck if it's `None`.
+    deck: Option<String>,
+    /// The card waiting to be graded.
+    current: Option<i64>,
+    asked: u32,
+    correct: u32,
+}
+
+static SESSION: Mutex<Option<Session>> = Mutex::new(None);
+
+/// Decks are compared without case, so "Spanish" and "spanish" are one deck.
+fn deck_name(deck: &str) -> Result<String, anyhow::Error> {
+    let deck = deck.trim();
+    if deck.is_empty() {
+        bail!("No deck was given");
+    }
+    let existing: Option<String> = db()?
+        .query_row(
+            "SELECT deck FROM cards WHERE deck = ?1 COLLATE NOCASE LIMIT 1",
+            [deck],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(existing.unwrap_or_else(|| deck.to_string()))
+}
+
+/// Adds a card to `deck`, which is created if it's new. The card is due straight away.
+/// Returns the deck's name and how many cards it has.
+pub fn add(deck: &str, question: &str, answer: &str) -> Result<(String, usize), anyhow::Error> {
+    let (question, answer) = (question.trim(), answer.trim());
+    if question.is_empty() || answer.is_empty() {
+        bail!("A flashcard needs both a question and an answer");
+    }
+    let deck = deck_name(deck)?;
+    let db = db()?;
+    let now = Utc::now();
+    db.execute(
+        "INSERT INTO cards (deck, question, answer, created, due) VALUES (?1, ?2, ?3, ?4, ?4)",
+        params![deck, question, answer, now],
+    )?;
+    let count: usize = db.query_row(
+        "SELECT COUNT(*) FROM cards WHERE deck = ?1",
+        [&deck],
+        |row| row.get(0),
+    )?;
+    Ok((deck, count))
+}
+
+/// Describes each deck, with how many cards it has and how many are due.
+pub fn describe_decks() -> Result<String, anyhow::Error> {
+    let db = db()?;
+    let mut stmt = db.prepare(
+        "SELECT deck, COUNT(*), SUM(due <= ?1) FROM cards GROUP BY deck ORDER BY deck COLLATE NOCASE",
+    )?;
+    let decks = stmt
+        .query_map([Utc::now()], |row| {
+            Ok(format!(
+                "- {}: {} cards, {} due",
+                row.get::<_, String>(0)?,
+                row.get::<_, usize>(1)?,
+                row.get::<_, usize>(2)?
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    if decks.is_empty() {
+        return Ok("There are no flashcards yet.".to_string());
+    }
+    Ok(decks.join("\n"))
+}
+
+/// The due card that's been due longest, in `deck` or any deck.
+fn next_due(deck: Option<&str>) -> Result<Option<Card>, anyhow::Error> {
+    let db = db()?;
+    let card = db
+        .query_row(
+            &format!(
+                "SELECT {} FROM cards WHERE due <= ?1 AND (?2 IS NULL OR deck = ?2)
+                ORDER BY due LIMIT 1",
+                CARD_COLUMNS
+            ),
+            params![Utc::now(), deck],
+            Card::from_row,
+        )
+        .optional()?;
+    Ok(card)
+}
+
+fn card(id: i64) -> Result<Card, anyhow::Error> {
+    Ok(db()?.query_row(
+        &format!("SELECT {} FROM cards WHERE id = ?1", CARD_COLUMNS),
+        [id],
+        Card::from_row,
+    )?)
+}
+
+/// Tells the AI to ask the card's question, or sums up the session when no cards are left.
+fn ask_next(session: &mut Session) -> Result<String, anyhow::Error> {
+    let next = match session.asked < MAX_SESSION_CARDS {
+        true => next_due(session.deck.as_deref())?,
+        false => None,
+    };
+    let Some(card) = next else {
+        session.current = None;
+        let summary = match session.asked {
+            0 => "No flashcards are due, so there's nothing to review right now.".to_string(),
+            asked => format!(
+                "The review session is over. The user got {} of {} right. Tell them, and \
+                 encourage them.",
+                session.correct, asked
+            ),
+        };
+        return Ok(summary);
+    };
+    session.current = Some(card.id);
+    Ok(format!(
+        "Ask the user this question from the {} deck, and wait for their answer: {}\n\
+         The answer is: {}\n\
+         Don't say the answer until they've answered. Then tell them whether they were right, \
+         saying the answer if they weren't, and call grade_flashcard.",
+        card.deck, card.question, card.answer
+    ))
+}
+
+/// Starts reviewing the due cards in `deck`, or in every deck. Returns what the AI should do next.
+pub fn start_review(deck: Option<&str>) -> Result<String, anyhow::Error> {
+    let deck = match deck.map(str::trim).filter(|deck| !deck.is_empty()) {
+        Some(deck) => {
+            let deck = deck_name(deck)?;
+            let count: usize = db()?.query_row(
+                "SELECT COUNT(*) FROM cards WHERE deck = ?1",
+                [&deck],
+                |row| row.get(0),
+            )?;
+            if count == 0 {
+                bail!(
+                    "There's no deck named \"{}\". The decks are:\n{}",
+                    deck,
+                    describe_decks()?
+                );
+            }
+            Some(deck)
+        }
+        None => None,
+    };
+    let mut session = Session {
+        deck,
+        current: None,
+        asked: 0,
+        correct: 0,
+    };
+    let next = ask_next(&mut session)?;
+    *SESSION.lock().unwrap() = session.current.is_some().then_some(session);
+    Ok(next)
+}
+
+/// Grades the card just asked, schedules when it's next due, and moves on to the next card.
+pub fn grade(grade: Grade) -> Result<String, anyhow::Error> {
+    let mut session_guard = SESSION.lock().unwrap();
+    let Some(session) = session_guard.as_mut() else {
+        bail!("No review session is in progress. Start one with start_review_session.");
+    };
+    let Some(id) = session.current else {
+        bail!("No flashcard is waiting to be graded");
+    };
+    let mut card = card(id)?;
+
+    // SM-2: the ease changes with how well the card was known, and the interval grows by it.
+    let quality = grade.quality();
+    card.ease = (card.ease + 0.1 - (5.0 - quality) * (0.08 + (5.0 - quality) * 0.02)).max(MIN_EASE);
+    let due = match grade {
+        Grade::Again => {
+            card.repetitions = 0;
+            card.interval_days = 0.0;
+            Utc::now() + TimeDelta::minutes(RELEARN_MINUTES)
+        }
+        _ => {
+            card.repetitions += 1;
+            card.interval_days = match card.repetitions {
+                1 => 1.0,
+                2 => 6.0,
+                _ => card.interval_days * card.ease,
+            };
+            if grade == Grade::Easy {
+                card.interval_days *= 1.3;
+            }
+            Utc::now() + TimeDelta::minutes((card.interval_days * 24.0 * 60.0) as i64)
+        }
+    };
+    db()?.execute(
+        "UPDATE cards SET due = ?1, interval_days = ?2, ease = ?3, repetitions = ?4,
+        lapses = lapses + ?5 WHERE id = ?6",
+        params![
+            due,
+            card.interval_days,
+            card.ease,
+            card.repetitions,
+            i32::from(grade == Grade::Again),
+            card.id
+        ],
+    )?;
+
+    session.asked += 1;
+    if grade != Grade::Again {
+        session.correct += 1;
+    }
+    let next = ask_next(session)?;
+    if session.current.is_none() {
+        *session_guard = None;
+    }
+    Ok(format!(
+        "The card is next due {}.\n{}",
+        describe_due(due),
+        next
+    ))
+}
+
+fn describe_due(due: DateTime<Utc>) -> String {
+    let wait = due - Utc::now();
+    match wait.num_days() {
+        0 if wait.num_hours() == 0 => "in a few minutes".to_string(),
+        0 => "later today".to_string(),
+        1 => "tomorrow".to_string(),
+        days => format!("in {} days", days),
+    }
+}
@@ -0,0 +1,74 @@
+//! Working out whether the terminal the assistant runs in is the focused window,
+//! so responses can be surfaced some other way when it isn't.
+
+/// Whether the terminal is the focused window, or `None` if that can't be told on this system.
+pub fn terminal_is_focused() -> Option<bool> {
+    #[cfg(target_os = "linux")]
+    return x11::terminal_is_focused();
+
+    #[cfg(windows)]
+    return windows::terminal_is_focused();
+
+    #[allow(unreachable_code)]
+    None
+}
+
+#[cfg(target_os = "linux")]
+mod x11 {
+    use std::env;
+    use x11rb::{
+        connection::Connection,
+        protocol::xproto::{AtomEnum, ConnectionExt},
+    };
+
+    /// Compares the active window with the one the terminal says it's running in.
+    /// Only terminals that set `WINDOWID`, like xterm, Konsole, kitty and Alacritty, can be told apart.
+    pub fn terminal_is_focused() -> Option<bool> {
+        let terminal: u32 = env::var("WINDOWID").ok()?.parse().ok()?;
+        let (conn, screen_num) = x11rb::connect(None).ok()?;
+        let root = conn.setup().roots.get(screen_num)?.root;
+
+        let active_window_atom = conn
+            .intern_atom(false, b"_NET_ACTIVE_WINDOW")
+            .ok()?
+            .reply()
+            .ok()?
+            .atom;
+        let active = conn
+            .get_property(false, root, active_window_atom, AtomEnum::WINDOW, 0, 1)
+            .ok()?
+            .reply()
+            .ok()?
+            .value32()?
+            .next()?;
+
+        // WINDOWID can be a window inside the terminal's top level window, which is what gets focus.
+        let mut window = terminal;
+        while window != root && window != 0 {
+            if window == active {
+                return Some(true);
+            }
+            window = conn.query_tree(window).ok()?.reply().ok()?.parent;
+        }
+        Some(false)
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use windows_sys::Win32::{
+        System::Console::GetConsoleWindow, UI::WindowsAndMessaging::GetForegroundWindow,
+    };
+
+    /// Compares the foreground window with the console window. Windows Terminal hides the console window
+    /// behind its own, so there the terminal never counts as focused.
+    pub fn terminal_is_focused() -> Option<bool> {
+        // SAFETY: Neither function takes arguments, and both only return a window handle.
+        let console = unsafe { GetConsoleWindow() };
+        if console == 0 {
+            return None;
+        }
+        let foreground = unsafe { GetForegroundWindow() };
+        Some(foreground == console)
+    }
+}
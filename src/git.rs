@@ -0,0 +1,202 @@
+//! Checking on the git repos listed in the config file for the git_status and
+//! summarize_recent_commits tools. Repos are named after their folders and read with the git
+//! command line tool.
+
+use anyhow::{bail, Context};
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+    sync::OnceLock,
+};
+
+/// The most changed files listed by git_status.
+const MAX_FILES: usize = 20;
+/// The most commits summarize_recent_commits lists.
+pub const MAX_COMMITS: usize = 30;
+
+static REPOS: OnceLock<Vec<PathBuf>> = OnceLock::new();
+
+/// Sets the repos that can be checked, from the config file. Paths can start with `~`.
+pub fn configure(repos: Vec<String>) {
+    let repos = repos
+        .into_iter()
+        .map(|repo| match repo.strip_prefix("~/") {
+            Some(rest) => dirs::home_dir().unwrap_or_default().join(rest),
+            None => PathBuf::from(repo),
+        })
+        .collect();
+    let _ = REPOS.set(repos);
+}
+
+fn repos() -> &'static [PathBuf] {
+    REPOS.get_or_init(Vec::new)
+}
+
+fn repo_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+/// The names of the repos that can be checked.
+pub fn names() -> Vec<String> {
+    repos().iter().map(|repo| repo_name(repo)).collect()
+}
+
+/// Names said out loud lose their dashes, so they're compared without them.
+fn simplify(name: &str) -> String {
+    name.to_lowercase()
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .collect()
+}
+
+/// Finds the repo named `name`. With only one repo, an empty name means that one.
+fn find(name: &str) -> Result<&'static Path, anyhow::Error> {
+    let repos = repos();
+    if repos.is_empty() {
+        bail!("No git repos are listed in the config file");
+    }
+    if name.trim().is_empty() && repos.len() == 1 {
+        return Ok(&repos[0]);
+    }
+    let wanted = simplify(name);
+    repos
+        .iter()
+        .find(|repo| simplify(&repo_name(repo)) == wanted)
+        .map(PathBuf::as_path)
+        .with_context(|| {
+            format!(
+                "No repo is named \"{}\". The repos are: {}",
+                name,
+                names().join(", ")
+            )
+        })
+}
+
+/// Runs git in `repo` and returns what it printed.
+fn git(repo: &Path, args: &[&str]) -> Result<String, anyhow::Error> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(args)
+        .output()
+        .context("Failed to run git. Is it installed?")?;
+    if !output.status.success() {
+        bail!(
+            "git {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Describes the repo's branch, how it compares to its upstream, and its uncommitted changes.
+pub fn status(name: &str) -> Result<String, anyhow::Error> {
+    let repo = find(name)?;
+    let output = git(repo, &["status", "--porcelain=v1", "--branch"])?;
+    let mut lines = output.lines();
+
+    // Like "## main...origin/main [ahead 1, behind 2]".
+    let branch_line = lines.next().unwrap_or_default();
+    let branch = branch_line.trim_start_matches("## ");
+    let mut info = format!("=== {} ===\n", repo_name(repo));
+    match branch.split_once("...") {
+        Some((local, upstream)) => {
+            let (upstream, tracking) = match upstream.split_once(" [") {
+                Some((upstream, tracking)) => (upstream, tracking.trim_end_matches(']')),
+                None => (upstream, "up to date"),
+            };
+            info.push_str(&format!(
+                "On branch {}, {} with {}.\n",
+                local, tracking, upstream
+            ));
+        }
+        None => info.push_str(&format!("On branch {}, which has no upstream.\n", branch)),
+    }
+
+    let (mut staged, mut modified, mut untracked, mut conflicted) = (0, 0, 0, 0);
+    let mut files = Vec::new();
+    for line in lines {
+        let Some((code, path)) = line.split_at_checked(2) else {
+            continue;
+        };
+        let path = path.trim();
+        let description = match code {
+            "??" => {
+                untracked += 1;
+                "untracked"
+            }
+            "UU" | "AA" | "DD" | "AU" | "UA" | "DU" | "UD" => {
+                conflicted += 1;
+                "conflicted"
+            }
+            _ => {
+                let (index, worktree) = (&code[..1], &code[1..]);
+                if index != " " {
+                    staged += 1;
+                }
+                if worktree != " " {
+                    modified += 1;
+                }
+                match (index, worktree) {
+                    (_, "D") | ("D", _) => "deleted",
+                    ("A", _) => "added",
+                    ("R", _) => "renamed",
+                    (_, " ") => "staged",
+                    _ => "modified",
+                }
+            }
+        };
+        files.push(format!("- {} ({})", path, description));
+    }
+
+    if files.is_empty() {
+        info.push_str("There are no uncommitted changes.\n");
+        return Ok(info);
+    }
+    info.push_str(&format!(
+        "Uncommitted changes: {} staged, {} modified, {} untracked",
+        staged, modified, untracked
+    ));
+    if conflicted > 0 {
+        info.push_str(&format!(", {} with merge conflicts", conflicted));
+    }
+    info.push_str(".\n");
+    let total = files.len();
+    for file in files.into_iter().take(MAX_FILES) {
+        info.push_str(&format!("{}\n", file));
+    }
+    if total > MAX_FILES {
+        info.push_str(&format!("...and {} more files\n", total - MAX_FILES));
+    }
+    Ok(info)
+}
+
+/// The repo's latest `count` commits on its current branch, newest first.
+pub fn recent_commits(name: &str, count: usize) -> Result<String, anyhow::Error> {
+    let repo = find(name)?;
+    let count = count.clamp(1, MAX_COMMITS).to_string();
+    let output = git(
+        repo,
+        &[
+            "log",
+            "-n",
+            &count,
+            "--pretty=format:%h%x1f%an%x1f%ar%x1f%s",
+        ],
+    )?;
+    if output.trim().is_empty() {
+        bail!("{} has no commits yet", repo_name(repo));
+    }
+
+    let mut info = format!("=== Recent commits in {} ===\n", repo_name(repo));
+    for line in output.lines() {
+        let fields: Vec<&str> = line.split('\x1f').collect();
+        if let [hash, author, when, subject] = fields[..] {
+            info.push_str(&format!("- {} {} ({}, {})\n", hash, subject, author, when));
+        }
+    }
+    Ok(info)
+}
@@ -0,0 +1,745 @@
+//! Reads events from an ICS calendar file or URL.
+
+use anyhow::Context;
+use chrono::{
+    DateTime, Datelike, Days, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc, Weekday,
+};
+use chrono_tz::Tz;
+use std::{collections::HashSet, sync::OnceLock, thread, time::Duration};
+use tracing::{info, warn};
+
+// How often the calendar is read again to look for events to remind the user about.
+const REMINDER_POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+// The most days, weeks, months or years a recurring event is followed through, so a rule that never
+// matches a date can't loop forever. A daily event that started 100 years ago is still followed.
+const MAX_RECURRENCE_PERIODS: u32 = 40_000;
+
+/// The path or URL of the calendar, if one was given.
+static CALENDAR_SOURCE: OnceLock<String> = OnceLock::new();
+
+#[derive(Clone, Debug)]
+pub struct CalendarEvent {
+    pub uid: String,
+    pub summary: String,
+    pub location: Option<String>,
+    pub start: DateTime<Local>,
+    /// Whether the event lasts all day rather than starting at a time.
+    pub all_day: bool,
+}
+
+/// Sets the path or URL of the calendar to read events from.
+pub fn configure(source: String) {
+    let _ = CALENDAR_SOURCE.set(source);
+}
+
+/// Returns the events starting within `within` from now, soonest first.
+pub fn upcoming_events(within: Duration) -> Result<Vec<CalendarEvent>, anyhow::Error> {
+    let Some(source) = CALENDAR_SOURCE.get() else {
+        anyhow::bail!("No calendar is set. Start the assistant with --calendar <path or URL>");
+    };
+
+    let now = Local::now();
+    let end = now + chrono::Duration::from_std(within)?;
+    let today = now.date_naive();
+    // From a day ago, so today's all day events are included.
+    let mut events: Vec<CalendarEvent> = load_events(source, now - chrono::Duration::days(1), end)?
+        .into_iter()
+        .filter(|event| {
+            // All day events are upcoming for the whole day.
+            let started = if event.all_day {
+                event.start.date_naive() < today
+            } else {
+                event.start < now
+            };
+            !started && event.start <= end
+        })
+        .collect();
+    events.sort_by_key(|event| event.start);
+    Ok(events)
+}
+
+/// Starts a thread that sends each event `lead_time` before it starts, so the user can be reminded of it.
+pub fn spawn_reminders(lead_time: Duration) -> flume::Receiver<CalendarEvent> {
+    let (reminder_tx, reminder_rx) = flume::unbounded();
+
+    thread::spawn(move || {
+        // Events already reminded about, by UID and start time, since the start time can change.
+        let mut reminded: HashSet<(String, DateTime<Local>)> = HashSet::new();
+        let mut error_was_logged = false;
+
+        loop {
+            match upcoming_events(lead_time) {
+                Ok(events) => {
+                    error_was_logged = false;
+                    for event in events.into_iter().filter(|event| !event.all_day) {
+                        if reminded.insert((event.uid.clone(), event.start)) {
+                            info!("Reminding about calendar event \"{}\"", event.summary);
+                            if reminder_tx.send(event).is_err() {
+                                return;
+                            }
+                        }
+                    }
+                }
+                Err(e) => {
+                    if !error_was_logged {
+                        warn!("Error reading calendar: {:?}", e);
+                        error_was_logged = true;
+                    }
+                }
+            }
+
+            thread::sleep(REMINDER_POLL_INTERVAL);
+        }
+    });
+
+    reminder_rx
+}
+
+/// Reads the calendar's events. Recurring events are given once for each time they happen between
+/// `from` and `until`.
+fn load_events(
+    source: &str,
+    from: DateTime<Local>,
+    until: DateTime<Local>,
+) -> Result<Vec<CalendarEvent>, anyhow::Error> {
+    let text = if let Some(rest) = source.strip_prefix("webcal://") {
+        fetch(&format!("https://{}", rest))?
+    } else if source.starts_with("http://") || source.starts_with("https://") {
+        fetch(source)?
+    } else {
+        std::fs::read_to_string(source).with_context(|| format!("Failed to read {}", source))?
+    };
+    Ok(parse_events(&text, from, until))
+}
+
+fn fetch(url: &str) -> Result<String, anyhow::Error> {
+    let text = reqwest::blocking::get(url)
+        .with_context(|| format!("Failed to download {}", url))?
+        .error_for_status()?
+        .text()?;
+    Ok(text)
+}
+
+/// The time zone a time in a calendar is in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Zone {
+    /// A date without a time, for all day events. It's taken as midnight local time.
+    AllDay,
+    Utc,
+    /// A time without a time zone, which is the same wall clock time wherever the user is.
+    Floating,
+    Tz(Tz),
+}
+
+/// A date or time from a calendar, in the time zone it was given in.
+#[derive(Clone, Copy, Debug)]
+struct Time {
+    naive: NaiveDateTime,
+    zone: Zone,
+}
+
+impl Time {
+    /// The time `naive` in this time's zone, as local time.
+    /// Wall clock times skipped by a daylight saving change don't exist, so they're `None`.
+    fn at(&self, naive: NaiveDateTime) -> Option<DateTime<Local>> {
+        match self.zone {
+            Zone::AllDay => Local
+                .from_local_datetime(&naive.date().and_time(NaiveTime::MIN))
+                .earliest(),
+            Zone::Utc => Some(Utc.from_utc_datetime(&naive).with_timezone(&Local)),
+            Zone::Floating => Local.from_local_datetime(&naive).earliest(),
+            Zone::Tz(tz) => Some(
+                tz.from_local_datetime(&naive)
+                    .earliest()?
+                    .with_timezone(&Local),
+            ),
+        }
+    }
+
+    fn local(&self) -> Option<DateTime<Local>> {
+        self.at(self.naive)
+    }
+}
+
+/// Finds the time zone named by a TZID. Some calendars put a path before the name, like
+/// "/mozilla.org/20050126_1/America/New_York".
+fn parse_tz(tzid: &str) -> Option<Tz> {
+    let tzid = tzid.trim_matches('"');
+    tzid.parse().ok().or_else(|| {
+        let segments: Vec<&str> = tzid.split('/').collect();
+        (2..=3)
+            .filter_map(|n| {
+                segments
+                    .len()
+                    .checked_sub(n)
+                    .map(|i| segments[i..].join("/"))
+            })
+            .find_map(|name| name.parse().ok())
+    })
+}
+
+/// The value of the parameter `name` of a property, like TZID in "DTSTART;TZID=Europe/Paris".
+fn param<'a>(params: &[&'a str], name: &str) -> Option<&'a str> {
+    params.iter().find_map(|param| {
+        let (key, value) = param.split_once('=')?;
+        key.eq_ignore_ascii_case(name).then_some(value)
+    })
+}
+
+/// Parses a DATE or DATE-TIME value. A TZID that isn't known is treated as local time.
+fn parse_time(value: &str, params: &[&str]) -> Option<Time> {
+    let value = value.trim();
+    let is_date = param(params, "VALUE").is_some_and(|kind| kind.eq_ignore_ascii_case("DATE"))
+        || value.len() == 8;
+    if is_date {
+        let date = NaiveDate::parse_from_str(value, "%Y%m%d").ok()?;
+        return Some(Time {
+            naive: date.and_time(NaiveTime::MIN),
+            zone: Zone::AllDay,
+        });
+    }
+
+    let (value, zone) = match value.strip_suffix('Z') {
+        Some(utc) => (utc, Zone::Utc),
+        None => match param(params, "TZID") {
+            Some(tzid) => match parse_tz(tzid) {
+                Some(tz) => (value, Zone::Tz(tz)),
+                None => {
+                    warn!("Unknown calendar time zone {}, so local time is used", tzid);
+                    (value, Zone::Floating)
+                }
+            },
+            None => (value, Zone::Floating),
+        },
+    };
+    let naive = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+    Some(Time { naive, zone })
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// An RRULE, which says when an event happens again.
+#[derive(Debug)]
+struct Recurrence {
+    frequency: Frequency,
+    interval: u32,
+    count: Option<u32>,
+    until: Option<DateTime<Local>>,
+    /// Days of the week, each with which one in the month it is for monthly events, like -1 for
+    /// the last Friday.
+    by_day: Vec<(Option<i32>, Weekday)>,
+    by_month_day: Vec<i32>,
+}
+
+fn parse_weekday(day: &str) -> Option<Weekday> {
+    Some(match day {
+        "MO" => Weekday::Mon,
+        "TU" => Weekday::Tue,
+        "WE" => Weekday::Wed,
+        "TH" => Weekday::Thu,
+        "FR" => Weekday::Fri,
+        "SA" => Weekday::Sat,
+        "SU" => Weekday::Sun,
+        _ => return None,
+    })
+}
+
+/// Parses an RRULE for an event starting at `start`. Returns `None` for rules that repeat more
+/// often than daily, which calendars rarely use for events.
+fn parse_recurrence(value: &str, start: &Time) -> Option<Recurrence> {
+    let mut recurrence = Recurrence {
+        frequency: Frequency::Daily,
+        interval: 1,
+        count: None,
+        until: None,
+        by_day: Vec::new(),
+        by_month_day: Vec::new(),
+    };
+    let mut frequency = None;
+    for part in value.split(';') {
+        let Some((key, value)) = part.split_once('=') else {
+            continue;
+        };
+        match key.to_ascii_uppercase().as_str() {
+            "FREQ" => {
+                frequency = match value.to_ascii_uppercase().as_str() {
+                    "DAILY" => Some(Frequency::Daily),
+                    "WEEKLY" => Some(Frequency::Weekly),
+                    "MONTHLY" => Some(Frequency::Monthly),
+                    "YEARLY" => Some(Frequency::Yearly),
+                    _ => None,
+                }
+            }
+            "INTERVAL" => recurrence.interval = value.parse().ok().filter(|n| *n > 0)?,
+            "COUNT" => recurrence.count = value.parse().ok(),
+            "UNTIL" => {
+                let until = parse_time(value, &[])?;
+                // An UNTIL date includes the whole day, and a floating one is in the event's zone.
+                recurrence.until = match until.zone {
+                    Zone::AllDay => Local
+                        .from_local_datetime(&until.naive.date().and_hms_opt(23, 59, 59)?)
+                        .latest(),
+                    Zone::Floating => start.at(until.naive),
+                    _ => until.local(),
+                };
+            }
+            "BYDAY" => {
+                for day in value.split(',') {
+                    let day = day.trim().to_ascii_uppercase();
+                    let (ordinal, weekday) = day.split_at(day.len().saturating_sub(2));
+                    let ordinal = match ordinal {
+                        "" => None,
+                        ordinal => Some(
+                            ordinal
+                                .trim_start_matches('+')
+                                .parse()
+                                .ok()
+                                .filter(|n| *n != 0)?,
+                        ),
+                    };
+                    recurrence.by_day.push((ordinal, parse_weekday(weekday)?));
+                }
+            }
+            "BYMONTHDAY" => {
+                for day in value.split(',') {
+                    recurrence.by_month_day.push(day.trim().parse().ok()?);
+                }
+            }
+            _ => {}
+        }
+    }
+    recurrence.frequency = frequency?;
+    Some(recurrence)
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    let (next_year, next_month) = if month == 12 {
+        (year + 1, 1)
+    } else {
+        (year, month + 1)
+    };
+    NaiveDate::from_ymd_opt(next_year, next_month, 1)
+        .and_then(|next| next.pred_opt())
+        .map_or(28, |last| last.day())
+}
+
+impl Recurrence {
+    /// The dates the event could happen on in the `period`th day, week, month or year after the
+    /// one it starts in, in order. Returns `None` once the dates are out of range.
+    fn dates_in_period(&self, first: NaiveDate, period: u32) -> Option<Vec<NaiveDate>> {
+        let step = period.checked_mul(self.interval)?;
+        let mut dates = match self.frequency {
+            Frequency::Daily => vec![first.checked_add_days(Days::new(step.into()))?],
+            Frequency::Weekly => {
+                let week_start = first
+                    .checked_sub_days(Days::new(first.weekday().num_days_from_monday().into()))?
+                    .checked_add_days(Days::new(u64::from(step) * 7))?;
+                let weekdays: Vec<Weekday> = match self.by_day.is_empty() {
+                    true => vec![first.weekday()],
+                    false => self.by_day.iter().map(|(_, weekday)| *weekday).collect(),
+                };
+                weekdays
+                    .into_iter()
+                    .filter_map(|weekday| {
+                        week_start
+                            .checked_add_days(Days::new(weekday.num_days_from_monday().into()))
+                    })
+                    .collect()
+            }
+            Frequency::Monthly => {
+                let month_index = (first.year() * 12 + first.month0() as i32)
+                    .checked_add(i32::try_from(step).ok()?)?;
+                let (year, month) = (
+                    month_index.div_euclid(12),
+                    month_index.rem_euclid(12) as u32 + 1,
+                );
+                let days_in_month = days_in_month(year, month);
+                let date = |day: u32| NaiveDate::from_ymd_opt(year, month, day);
+                if !self.by_day.is_empty() {
+                    let mut dates = Vec::new();
+                    for (ordinal, weekday) in &self.by_day {
+                        let matching: Vec<NaiveDate> = (1..=days_in_month)
+                            .filter_map(date)
+                            .filter(|date| date.weekday() == *weekday)
+                            .collect();
+                        match ordinal {
+                            None => dates.extend(matching),
+                            Some(n) if *n > 0 => dates.extend(matching.get(*n as usize - 1)),
+                            Some(n) => dates.extend(
+                                matching
+                                    .len()
+                                    .checked_sub(n.unsigned_abs() as usize)
+                                    .and_then(|i| matching.get(i)),
+                            ),
+                        }
+                    }
+                    dates
+                } else {
+                    let days = match self.by_month_day.is_empty() {
+                        true => vec![first.day() as i32],
+                        false => self.by_month_day.clone(),
+                    };
+                    // Months without the day, like the 31st, are skipped.
+                    days.into_iter()
+                        .filter_map(|day| match day {
+                            1.. => date(day as u32),
+                            ..=-1 => date((days_in_month as i32 + day + 1).try_into().ok()?),
+                            0 => None,
+                        })
+                        .collect()
+                }
+            }
+            Frequency::Yearly => {
+                let year = first.year().checked_add(i32::try_from(step).ok()?)?;
+                NaiveDate::from_ymd_opt(year, first.month(), first.day())
+                    .into_iter()
+                    .collect()
+            }
+        };
+        dates.sort();
+        dates.dedup();
+        Some(dates)
+    }
+
+    /// The times an event starting at `start` happens between `from` and `until`, leaving out
+    /// `excluded` times.
+    fn occurrences(
+        &self,
+        start: &Time,
+        excluded: &[DateTime<Local>],
+        from: DateTime<Local>,
+        until: DateTime<Local>,
+    ) -> Vec<DateTime<Local>> {
+        let first = start.naive;
+        let mut occurrences = Vec::new();
+        // Excluded times still count towards COUNT.
+        let mut count = 0;
+        for period in 0..MAX_RECURRENCE_PERIODS {
+            let Some(dates) = self.dates_in_period(first.date(), period) else {
+                break;
+            };
+            for date in dates {
+                let naive = date.and_time(first.time());
+                if naive < first {
+                    continue;
+                }
+                let Some(time) = start.at(naive) else {
+                    continue;
+                };
+                let past_until = self.until.is_some_and(|last| time > last);
+                let past_count = self.count.is_some_and(|max| count >= max);
+                if past_until || past_count || time > until {
+                    return occurrences;
+                }
+                count += 1;
+                if time >= from && !excluded.contains(&time) {
+                    occurrences.push(time);
+                }
+            }
+        }
+        occurrences
+    }
+}
+
+/// A VEVENT as it's read.
+#[derive(Default)]
+struct PartialEvent {
+    uid: String,
+    summary: String,
+    location: Option<String>,
+    start: Option<Time>,
+    recurrence: Option<String>,
+    excluded: Vec<DateTime<Local>>,
+    /// Set on an event that replaces one time a recurring event happens.
+    recurrence_id: Option<DateTime<Local>>,
+    cancelled: bool,
+}
+
+/// Joins long lines, which are folded onto lines starting with a space or tab.
+fn unfold(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continued), Some(last)) => last.push_str(continued),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+/// Parses the VEVENTs of an ICS calendar. Recurring events are given once for each time they
+/// happen between `from` and `until`. Other events are given whenever they are.
+fn parse_events(text: &str, from: DateTime<Local>, until: DateTime<Local>) -> Vec<CalendarEvent> {
+    let mut parsed = Vec::new();
+    let mut event: Option<PartialEvent> = None;
+    for line in unfold(text) {
+        let Some((name_and_params, value)) = line.split_once(':') else {
+            continue;
+        };
+        let mut parts = name_and_params.split(';');
+        let name = parts.next().unwrap_or_default().to_ascii_uppercase();
+        let params: Vec<&str> = parts.collect();
+
+        match (name.as_str(), event.as_mut()) {
+            ("BEGIN", _) if value.eq_ignore_ascii_case("VEVENT") => {
+                event = Some(PartialEvent::default());
+            }
+            ("END", Some(_)) if value.eq_ignore_ascii_case("VEVENT") => {
+                parsed.extend(event.take());
+            }
+            ("UID", Some(event)) => event.uid = value.to_string(),
+            ("SUMMARY", Some(event)) => event.summary = unescape(value),
+            ("LOCATION", Some(event)) => {
+                event.location = Some(unescape(value)).filter(|location| !location.is_empty())
+            }
+            ("DTSTART", Some(event)) => event.start = parse_time(value, &params),
+            ("RRULE", Some(event)) => event.recurrence = Some(value.to_string()),
+            ("EXDATE", Some(event)) => event.excluded.extend(
+                value
+                    .split(',')
+                    .filter_map(|value| parse_time(value, &params)?.local()),
+            ),
+            ("RECURRENCE-ID", Some(event)) => {
+                event.recurrence_id = parse_time(value, &params).and_then(|time| time.local())
+            }
+            ("STATUS", Some(event)) => event.cancelled = value.eq_ignore_ascii_case("CANCELLED"),
+            _ => {}
+        }
+    }
+
+    // Times of recurring events that were moved or cancelled, by the recurring event's UID.
+    let replaced: Vec<(String, DateTime<Local>)> = parsed
+        .iter()
+        .filter_map(|event| Some((event.uid.clone(), event.recurrence_id?)))
+        .collect();
+
+    let mut events = Vec::new();
+    for event in parsed {
+        let Some(start) = event.start else {
+            continue;
+        };
+        if event.cancelled {
+            continue;
+        }
+        let recurrence = event
+            .recurrence
+            .as_deref()
+            .filter(|_| event.recurrence_id.is_none())
+            .and_then(|rule| parse_recurrence(rule, &start));
+        let starts = match recurrence {
+            Some(recurrence) => {
+                let mut excluded = event.excluded;
+                excluded.extend(
+                    replaced
+                        .iter()
+                        .filter(|(uid, _)| *uid == event.uid)
+                        .map(|(_, time)| *time),
+                );
+                recurrence.occurrences(&start, &excluded, from, until)
+            }
+            None => start.local().into_iter().collect(),
+        };
+        for occurrence in starts {
+            events.push(CalendarEvent {
+                uid: event.uid.clone(),
+                summary: event.summary.clone(),
+                location: event.location.clone(),
+                start: occurrence,
+                all_day: start.zone == Zone::AllDay,
+            });
+        }
+    }
+    events
+}
+
+fn unescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => unescaped.push('\n'),
+            Some(escaped) => unescaped.push(escaped),
+            None => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(text: &str) -> DateTime<Utc> {
+        NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M")
+            .unwrap()
+            .and_utc()
+    }
+
+    fn calendar(events: &str) -> String {
+        format!("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n{events}END:VCALENDAR\r\n")
+    }
+
+    /// The start times of the events in a calendar between 2024 and 2026, in UTC.
+    fn starts(events: &str) -> Vec<DateTime<Utc>> {
+        let from = utc("2024-01-01 00:00").with_timezone(&Local);
+        let until = utc("2026-01-01 00:00").with_timezone(&Local);
+        let mut starts: Vec<DateTime<Utc>> = parse_events(&calendar(events), from, until)
+            .iter()
+            .map(|event| event.start.with_timezone(&Utc))
+            .collect();
+        starts.sort();
+        starts
+    }
+
+    #[test]
+    fn folded_lines_are_joined() {
+        let text = calendar(
+            "BEGIN:VEVENT\r\nUID:1\r\nSUMMARY:Quarterly plan\r\n ning meeting\r\nDTSTART:20240301T150000Z\r\nEND:VEVENT\r\n",
+        );
+        let events = parse_events(&text, Local::now(), Local::now());
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].summary, "Quarterly planning meeting");
+    }
+
+    #[test]
+    fn text_is_unescaped() {
+        let text = calendar(
+            "BEGIN:VEVENT\r\nUID:1\r\nSUMMARY:Lunch\\, then a walk\\; maybe\r\nLOCATION:Cafe\\nMain St\r\nDTSTART:20240301T150000Z\r\nEND:VEVENT\r\n",
+        );
+        let events = parse_events(&text, Local::now(), Local::now());
+        assert_eq!(events[0].summary, "Lunch, then a walk; maybe");
+        assert_eq!(events[0].location.as_deref(), Some("Cafe\nMain St"));
+    }
+
+    #[test]
+    fn dates_are_all_day_events() {
+        let text = calendar(
+            "BEGIN:VEVENT\r\nUID:1\r\nSUMMARY:Holiday\r\nDTSTART;VALUE=DATE:20240704\r\nEND:VEVENT\r\n",
+        );
+        let events = parse_events(&text, Local::now(), Local::now());
+        assert!(events[0].all_day);
+        assert_eq!(
+            events[0].start.naive_local(),
+            NaiveDate::from_ymd_opt(2024, 7, 4)
+                .unwrap()
+                .and_time(NaiveTime::MIN)
+        );
+    }
+
+    #[test]
+    fn times_are_read_in_their_time_zone() {
+        let starts = starts(
+            "BEGIN:VEVENT\r\nUID:1\r\nDTSTART:20240301T150000Z\r\nEND:VEVENT\r\n\
+             BEGIN:VEVENT\r\nUID:2\r\nDTSTART;TZID=America/New_York:20240301T090000\r\nEND:VEVENT\r\n\
+             BEGIN:VEVENT\r\nUID:3\r\nDTSTART;TZID=\"/example.com/Europe/Paris\":20240701T090000\r\nEND:VEVENT\r\n",
+        );
+        assert_eq!(
+            starts,
+            [
+                utc("2024-03-01 14:00"),
+                utc("2024-03-01 15:00"),
+                utc("2024-07-01 07:00")
+            ]
+        );
+    }
+
+    #[test]
+    fn weekly_events_recur_until_their_count() {
+        // Every other Tuesday and Thursday, six times, without the second Thursday.
+        let starts = starts(
+            "BEGIN:VEVENT\r\nUID:1\r\nDTSTART;TZID=Europe/London:20240102T100000\r\n\
+             RRULE:FREQ=WEEKLY;INTERVAL=2;BYDAY=TU,TH;COUNT=6\r\n\
+             EXDATE;TZID=Europe/London:20240118T100000\r\nEND:VEVENT\r\n",
+        );
+        assert_eq!(
+            starts,
+            [
+                utc("2024-01-02 10:00"),
+                utc("2024-01-04 10:00"),
+                utc("2024-01-16 10:00"),
+                utc("2024-01-30 10:00"),
+                utc("2024-02-01 10:00"),
+            ]
+        );
+    }
+
+    #[test]
+    fn daily_events_recur_until_their_end() {
+        // The clocks go forward on the 31st, and the event stays at 9 in the morning in Berlin.
+        let starts = starts(
+            "BEGIN:VEVENT\r\nUID:1\r\nDTSTART;TZID=Europe/Berlin:20240329T090000\r\n\
+             RRULE:FREQ=DAILY;UNTIL=20240401T070000Z\r\nEND:VEVENT\r\n",
+        );
+        assert_eq!(
+            starts,
+            [
+                utc("2024-03-29 08:00"),
+                utc("2024-03-30 08:00"),
+                utc("2024-03-31 07:00"),
+                utc("2024-04-01 07:00"),
+            ]
+        );
+    }
+
+    #[test]
+    fn monthly_events_recur() {
+        let starts = starts(
+            "BEGIN:VEVENT\r\nUID:1\r\nDTSTART:20240131T120000Z\r\nRRULE:FREQ=MONTHLY;COUNT=3\r\nEND:VEVENT\r\n\
+             BEGIN:VEVENT\r\nUID:2\r\nDTSTART:20240126T180000Z\r\n\
+             RRULE:FREQ=MONTHLY;BYDAY=-1FR;UNTIL=20240331\r\nEND:VEVENT\r\n\
+             BEGIN:VEVENT\r\nUID:3\r\nDTSTART:20240401T100000Z\r\nRRULE:FREQ=MONTHLY;BYDAY=0FR\r\nEND:VEVENT\r\n\
+             BEGIN:VEVENT\r\nUID:4\r\nDTSTART:20240501T100000Z\r\n\
+             RRULE:FREQ=MONTHLY;INTERVAL=2147483647\r\nEND:VEVENT\r\n",
+        );
+        // The 31st is skipped in months without one, and broken rules give just the first date.
+        assert_eq!(
+            starts,
+            [
+                utc("2024-01-26 18:00"),
+                utc("2024-01-31 12:00"),
+                utc("2024-02-23 18:00"),
+                utc("2024-03-29 18:00"),
+                utc("2024-03-31 12:00"),
+                utc("2024-04-01 10:00"),
+                utc("2024-05-01 10:00"),
+                utc("2024-05-31 12:00"),
+            ]
+        );
+    }
+
+    #[test]
+    fn only_occurrences_in_the_window_are_given() {
+        let starts = starts(
+            "BEGIN:VEVENT\r\nUID:1\r\nDTSTART:20001225T080000Z\r\nRRULE:FREQ=YEARLY\r\nEND:VEVENT\r\n",
+        );
+        assert_eq!(
+            starts,
+            [utc("2024-12-25 08:00"), utc("2025-12-25 08:00")]
+        );
+    }
+
+    #[test]
+    fn moved_and_cancelled_occurrences_replace_the_recurring_ones() {
+        let starts = starts(
+            "BEGIN:VEVENT\r\nUID:1\r\nDTSTART:20240101T090000Z\r\nRRULE:FREQ=DAILY;COUNT=3\r\nEND:VEVENT\r\n\
+             BEGIN:VEVENT\r\nUID:1\r\nRECURRENCE-ID:20240102T090000Z\r\nDTSTART:20240102T130000Z\r\nEND:VEVENT\r\n\
+             BEGIN:VEVENT\r\nUID:1\r\nRECURRENCE-ID:20240103T090000Z\r\nDTSTART:20240103T090000Z\r\n\
+             STATUS:CANCELLED\r\nEND:VEVENT\r\n",
+        );
+        assert_eq!(
+            starts,
+            [utc("2024-01-01 09:00"), utc("2024-01-02 13:00")]
+        );
+    }
+}
@@ -0,0 +1,492 @@
+//! Keeps a second copy of the assistant from fighting the first over the microphone and the
+//! push-to-talk key. The running assistant holds a lock on a file in the cache folder, and listens
+//! for requests on a Unix socket, or a named pipe on Windows, that only the user can connect to.
+//! Later launches can hand it a command instead of starting. Other frontends, like the UI, use
+//! the same socket to send typed messages and follow the conversation, and so can scripts and
+//! macro pads like the Stream Deck, by writing a line like "quick-assistant toggle-mute" or
+//! "quick-assistant ask what's the weather" to it.
+
+use anyhow::{bail, Context};
+use clap::ValueEnum;
+use std::{
+    fs::{self, File, TryLockError},
+    io::{BufRead, BufReader, Write},
+    sync::OnceLock,
+    thread,
+    time::Duration,
+};
+use tracing::{info, warn};
+
+use crate::{
+    conversation::{self, ConversationEvent},
+    paths::CACHE_DIR,
+};
+
+const REPLY_TIMEOUT: Duration = Duration::from_secs(2);
+/// How long a subscriber can go without an event before an empty line is written to it, so one
+/// that's gone is noticed and dropped without waiting for the next event.
+const SUBSCRIBER_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+/// Sent ahead of each request, so the assistant only acts on lines that were meant as requests.
+const GREETING: &str = "quick-assistant";
+const OK_REPLY: &str = "ok";
+
+/// Commands another launch can send to the running assistant.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum InstanceCommand {
+    ToggleMute,
+    Mute,
+    Unmute,
+    SkipSentence,
+    TogglePause,
+    RepeatResponse,
+    #[value(alias = "stop-speech")]
+    StopSpeaking,
+    DismissAlarm,
+    TogglePauseListening,
+    PauseListening,
+    ResumeListening,
+    /// Starts listening, like pressing the push to talk key.
+    PttStart,
+    /// Stops listening and sends what was said, like letting go of the push to talk key.
+    PttStop,
+}
+
+/// Something the running assistant is asked to do.
+#[derive(Debug)]
+pub enum InstanceRequest {
+    Command(InstanceCommand),
+    /// A typed message for the AI to answer.
+    Message(String),
+    /// The name of a saved session to give back to the AI, so the conversation can pick up where it left off.
+    LoadSession(String),
+}
+
+/// Locked by the running assistant for as long as it runs.
+static LOCK: OnceLock<File> = OnceLock::new();
+
+/// Locks the lock file. Returns `false` if another assistant has it locked. The operating system
+/// unlocks it when the assistant exits, even if it crashes.
+fn lock() -> Result<bool, anyhow::Error> {
+    fs::create_dir_all(&*CACHE_DIR)?;
+    let path = CACHE_DIR.join("instance.lock");
+    let file = File::options()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    match file.try_lock() {
+        Ok(()) => {
+            let _ = LOCK.set(file);
+            Ok(true)
+        }
+        Err(TryLockError::WouldBlock) => Ok(false),
+        Err(TryLockError::Error(err)) => {
+            Err(err).with_context(|| format!("Failed to lock {}", path.display()))
+        }
+    }
+}
+
+/// Claims being the running assistant. Returns the requests sent by later launches and other
+/// frontends, or `None` if another assistant is already running.
+pub fn claim() -> Result<Option<flume::Receiver<InstanceRequest>>, anyhow::Error> {
+    if !lock()? {
+        return Ok(None);
+    }
+
+    let (requests_tx, requests_rx) = flume::unbounded();
+    // Without the socket the assistant still works, it just can't be controlled from outside.
+    let mut listener = match endpoint::Listener::bind() {
+        Ok(listener) => listener,
+        Err(err) => {
+            warn!(
+                "Failed to listen for requests from other launches: {:?}",
+                err
+            );
+            return Ok(Some(requests_rx));
+        }
+    };
+    thread::spawn(move || loop {
+        let stream = match listener.accept() {
+            Ok(stream) => stream,
+            Err(err) => {
+                warn!("Failed to accept instance connection: {}", err);
+                thread::sleep(Duration::from_secs(1));
+                continue;
+            }
+        };
+        // Subscribers stay connected, so each connection gets its own thread.
+        let requests_tx = requests_tx.clone();
+        thread::spawn(move || {
+            if let Err(err) = handle_connection(&stream, &requests_tx) {
+                warn!("Failed to handle instance request: {:?}", err);
+            }
+            endpoint::finish(&stream);
+        });
+    });
+    Ok(Some(requests_rx))
+}
+
+fn handle_connection(
+    mut stream: &endpoint::Stream,
+    requests_tx: &flume::Sender<InstanceRequest>,
+) -> Result<(), anyhow::Error> {
+    endpoint::set_read_timeout(stream, Some(REPLY_TIMEOUT))?;
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line)?;
+
+    let Some((GREETING, request)) = line.trim().split_once(' ') else {
+        writeln!(stream, "Not a quick-assistant request")?;
+        return Ok(());
+    };
+    match request.split_once(' ') {
+        Some(("message", text)) => {
+            let text: String = serde_json::from_str(text).context("Failed to parse message")?;
+            requests_tx.send(InstanceRequest::Message(text))?;
+            writeln!(stream, "{}", OK_REPLY)?;
+        }
+        // The same as a message, but as plain text, for scripts and macro pads.
+        Some(("ask", text)) if !text.trim().is_empty() => {
+            requests_tx.send(InstanceRequest::Message(text.trim().to_string()))?;
+            writeln!(stream, "{}", OK_REPLY)?;
+        }
+        Some(("load-session", name)) => {
+            let name: String =
+                serde_json::from_str(name).context("Failed to parse session name")?;
+            requests_tx.send(InstanceRequest::LoadSession(name))?;
+            writeln!(stream, "{}", OK_REPLY)?;
+        }
+        None if request == "subscribe" => {
+            let events = conversation::subscribe();
+            writeln!(stream, "{}", OK_REPLY)?;
+            // Ends when the subscriber disconnects and writing fails, which drops `events` so the
+            // conversation stops sending to it.
+            loop {
+                match events.recv_timeout(SUBSCRIBER_CHECK_INTERVAL) {
+                    Ok(event) => writeln!(stream, "{}", serde_json::to_string(&event)?)?,
+                    Err(flume::RecvTimeoutError::Timeout) => writeln!(stream)?,
+                    Err(flume::RecvTimeoutError::Disconnected) => break,
+                }
+            }
+        }
+        _ => match InstanceCommand::from_str(request, true) {
+            Ok(command) => {
+                info!("Received {:?} from another launch", command);
+                requests_tx.send(InstanceRequest::Command(command))?;
+                writeln!(stream, "{}", OK_REPLY)?;
+            }
+            Err(err) => writeln!(stream, "{}", err)?,
+        },
+    }
+    Ok(())
+}
+
+/// Sends a request to the running assistant, and returns the connection once it's accepted.
+fn request(request: &str) -> Result<BufReader<endpoint::Stream>, anyhow::Error> {
+    let mut stream = endpoint::connect().context("quick-assistant isn't running")?;
+    endpoint::set_read_timeout(&stream, Some(REPLY_TIMEOUT))?;
+
+    writeln!(stream, "{} {}", GREETING, request)?;
+    let mut reader = BufReader::new(stream);
+    let mut reply = String::new();
+    reader
+        .read_line(&mut reply)
+        .context("The running assistant didn't reply")?;
+
+    match reply.trim() {
+        OK_REPLY => Ok(reader),
+        "" => bail!("The running assistant didn't reply"),
+        reply => bail!("{}", reply),
+    }
+}
+
+/// Sends a command to the running assistant.
+pub fn send(command: InstanceCommand) -> Result<(), anyhow::Error> {
+    let name = command.to_possible_value().unwrap();
+    request(name.get_name())?;
+    Ok(())
+}
+
+/// Sends a typed message for the running assistant to answer.
+pub fn send_message(text: &str) -> Result<(), anyhow::Error> {
+    request(&format!("message {}", serde_json::to_string(text)?))?;
+    Ok(())
+}
+
+/// Asks the running assistant to pick up an earlier conversation, by the name of its saved session.
+pub fn load_session(name: &str) -> Result<(), anyhow::Error> {
+    request(&format!("load-session {}", serde_json::to_string(name)?))?;
+    Ok(())
+}
+
+/// Follows the running assistant's conversation. The iterator ends when the assistant exits.
+pub fn subscribe() -> Result<impl Iterator<Item = ConversationEvent>, anyhow::Error> {
+    let reader = request("subscribe")?;
+    // Events can be far apart, so only the reply to the request is timed.
+    endpoint::set_read_timeout(reader.get_ref(), None)?;
+    Ok(reader
+        .lines()
+        .map_while(Result::ok)
+        // Empty lines only check that the subscriber is still there.
+        .filter(|line| !line.is_empty())
+        .filter_map(|line| match serde_json::from_str(&line) {
+            Ok(event) => Some(event),
+            Err(err) => {
+                warn!("Ignoring conversation event that failed to parse: {}", err);
+                None
+            }
+        }))
+}
+
+/// The socket the running assistant listens on, which only the user can connect to.
+#[cfg(unix)]
+mod endpoint {
+    use std::{
+        fs::{self, DirBuilder, Permissions},
+        io,
+        os::unix::{
+            fs::{DirBuilderExt, PermissionsExt},
+            net::{UnixListener, UnixStream},
+        },
+        path::PathBuf,
+        time::Duration,
+    };
+
+    use crate::paths::CACHE_DIR;
+
+    pub type Stream = UnixStream;
+
+    /// The socket is in a folder only the user can open, so no one else can reach it.
+    fn socket_path() -> PathBuf {
+        CACHE_DIR.join("ipc").join("instance.sock")
+    }
+
+    pub struct Listener(UnixListener);
+
+    impl Listener {
+        /// Listens on the socket. Only call this while holding the lock.
+        pub fn bind() -> io::Result<Self> {
+            let path = socket_path();
+            let folder = path.parent().unwrap();
+            DirBuilder::new()
+                .recursive(true)
+                .mode(0o700)
+                .create(folder)?;
+            // The folder could have been made with other permissions.
+            fs::set_permissions(folder, Permissions::from_mode(0o700))?;
+            // Only the running assistant holds the lock, so a socket left here is from one that
+            // crashed.
+            let _ = fs::remove_file(&path);
+            let listener = UnixListener::bind(&path)?;
+            fs::set_permissions(&path, Permissions::from_mode(0o600))?;
+            Ok(Self(listener))
+        }
+
+        pub fn accept(&mut self) -> io::Result<Stream> {
+            self.0.accept().map(|(stream, _)| stream)
+        }
+    }
+
+    pub fn connect() -> io::Result<Stream> {
+        UnixStream::connect(socket_path())
+    }
+
+    pub fn set_read_timeout(stream: &Stream, timeout: Option<Duration>) -> io::Result<()> {
+        stream.set_read_timeout(timeout)
+    }
+
+    /// Finishes a connection once its request is handled.
+    pub fn finish(_stream: &Stream) {}
+}
+
+/// The named pipe the running assistant listens on, which only the user can connect to.
+#[cfg(windows)]
+mod endpoint {
+    use std::{
+        fs::{File, OpenOptions},
+        io,
+        os::windows::io::{AsRawHandle, FromRawHandle},
+        ptr,
+        time::Duration,
+    };
+    use windows_sys::Win32::{
+        Foundation::{
+            CloseHandle, LocalFree, ERROR_PIPE_BUSY, ERROR_PIPE_CONNECTED, HANDLE,
+            INVALID_HANDLE_VALUE,
+        },
+        Security::{
+            Authorization::{
+                ConvertSidToStringSidW, ConvertStringSecurityDescriptorToSecurityDescriptorW,
+                SDDL_REVISION_1,
+            },
+            GetTokenInformation, TokenUser, PSECURITY_DESCRIPTOR, SECURITY_ATTRIBUTES, TOKEN_QUERY,
+            TOKEN_USER,
+        },
+        Storage::FileSystem::{
+            FlushFileBuffers, FILE_FLAG_FIRST_PIPE_INSTANCE, PIPE_ACCESS_DUPLEX,
+        },
+        System::{
+            Pipes::{
+                ConnectNamedPipe, CreateNamedPipeW, WaitNamedPipeW, PIPE_READMODE_BYTE,
+                PIPE_REJECT_REMOTE_CLIENTS, PIPE_TYPE_BYTE, PIPE_UNLIMITED_INSTANCES, PIPE_WAIT,
+            },
+            Threading::{GetCurrentProcess, OpenProcessToken},
+        },
+    };
+
+    const BUFFER_SIZE: u32 = 64 * 1024;
+
+    pub type Stream = File;
+
+    /// Pipe names are shared by everyone on the computer, so each user has their own pipe.
+    fn pipe_name() -> String {
+        format!(
+            r"\\.\pipe\quick-assistant-{}",
+            std::env::var("USERNAME").unwrap_or_default()
+        )
+    }
+
+    fn wide(text: &str) -> Vec<u16> {
+        text.encode_utf16().chain(Some(0)).collect()
+    }
+
+    /// The current user's SID, like "S-1-5-21-...".
+    fn user_sid() -> io::Result<String> {
+        unsafe {
+            let mut token: HANDLE = 0;
+            if OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token) == 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let mut size = 0;
+            GetTokenInformation(token, TokenUser, ptr::null_mut(), 0, &mut size);
+            // u64s, so the TOKEN_USER in it is aligned.
+            let mut buffer = vec![0u64; (size as usize).div_ceil(8)];
+            let read = GetTokenInformation(
+                token,
+                TokenUser,
+                buffer.as_mut_ptr().cast(),
+                size,
+                &mut size,
+            );
+            CloseHandle(token);
+            if read == 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let user = &*(buffer.as_ptr() as *const TOKEN_USER);
+            let mut sid = ptr::null_mut();
+            if ConvertSidToStringSidW(user.User.Sid, &mut sid) == 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let length = (0..).take_while(|&i| *sid.add(i) != 0).count();
+            let text = String::from_utf16_lossy(std::slice::from_raw_parts(sid, length));
+            LocalFree(sid.cast());
+            Ok(text)
+        }
+    }
+
+    /// A security descriptor that only lets the current user use the pipe. It's kept for as long
+    /// as the assistant runs.
+    fn user_only_security() -> io::Result<PSECURITY_DESCRIPTOR> {
+        let sddl = wide(&format!("D:P(A;;GA;;;{})", user_sid()?));
+        let mut descriptor = ptr::null_mut();
+        let converted = unsafe {
+            ConvertStringSecurityDescriptorToSecurityDescriptorW(
+                sddl.as_ptr(),
+                SDDL_REVISION_1,
+                &mut descriptor,
+                ptr::null_mut(),
+            )
+        };
+        if converted == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(descriptor)
+    }
+
+    pub struct Listener {
+        name: Vec<u16>,
+        security: PSECURITY_DESCRIPTOR,
+        /// The pipe instance waiting for the next connection.
+        next: HANDLE,
+    }
+
+    // The security descriptor is only read, by the thread that owns the listener.
+    unsafe impl Send for Listener {}
+
+    impl Listener {
+        pub fn bind() -> io::Result<Self> {
+            let mut listener = Self {
+                name: wide(&pipe_name()),
+                security: user_only_security()?,
+                next: INVALID_HANDLE_VALUE,
+            };
+            // Being the first instance makes sure no one else made a pipe with this name first.
+            listener.next = listener.create(FILE_FLAG_FIRST_PIPE_INSTANCE)?;
+            Ok(listener)
+        }
+
+        fn create(&self, flags: u32) -> io::Result<HANDLE> {
+            let attributes = SECURITY_ATTRIBUTES {
+                nLength: std::mem::size_of::<SECURITY_ATTRIBUTES>() as u32,
+                lpSecurityDescriptor: self.security,
+                bInheritHandle: 0,
+            };
+            let handle = unsafe {
+                CreateNamedPipeW(
+                    self.name.as_ptr(),
+                    PIPE_ACCESS_DUPLEX | flags,
+                    PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+                    PIPE_UNLIMITED_INSTANCES,
+                    BUFFER_SIZE,
+                    BUFFER_SIZE,
+                    0,
+                    &attributes,
+                )
+            };
+            match handle {
+                INVALID_HANDLE_VALUE => Err(io::Error::last_os_error()),
+                handle => Ok(handle),
+            }
+        }
+
+        pub fn accept(&mut self) -> io::Result<Stream> {
+            let connected = unsafe { ConnectNamedPipe(self.next, ptr::null_mut()) } != 0
+                || io::Error::last_os_error().raw_os_error() == Some(ERROR_PIPE_CONNECTED as i32);
+            if !connected {
+                let err = io::Error::last_os_error();
+                // A client that left before it was connected spoils the instance, so it's replaced.
+                let next = self.create(0)?;
+                let spoiled = std::mem::replace(&mut self.next, next);
+                unsafe { CloseHandle(spoiled) };
+                return Err(err);
+            }
+            // The next instance is made before this one is handed off, so there's always one
+            // waiting.
+            let next = self.create(0)?;
+            let handle = std::mem::replace(&mut self.next, next);
+            Ok(unsafe { File::from_raw_handle(handle as _) })
+        }
+    }
+
+    pub fn connect() -> io::Result<Stream> {
+        let name = pipe_name();
+        match OpenOptions::new().read(true).write(true).open(&name) {
+            // Every instance is busy, so wait for the next one.
+            Err(err) if err.raw_os_error() == Some(ERROR_PIPE_BUSY as i32) => {
+                unsafe { WaitNamedPipeW(wide(&name).as_ptr(), 2_000) };
+                OpenOptions::new().read(true).write(true).open(&name)
+            }
+            result => result,
+        }
+    }
+
+    /// Pipes can't time out reads. The assistant always replies, so it isn't needed.
+    pub fn set_read_timeout(_stream: &Stream, _timeout: Option<Duration>) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Waits for the client to read the reply, since closing the pipe would throw it away.
+    pub fn finish(stream: &Stream) {
+        unsafe { FlushFileBuffers(stream.as_raw_handle() as HANDLE) };
+    }
+}
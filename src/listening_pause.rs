@@ -0,0 +1,25 @@
+//! Pausing listening, so the assistant can't hear or interrupt, like during a meeting.
+//! While paused the push to talk key is ignored and nothing is said unprompted. What would have
+//! been, like timers going off or downloads finishing, is shown as a desktop notification instead.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::info;
+
+static PAUSED: AtomicBool = AtomicBool::new(false);
+
+pub fn is_paused() -> bool {
+    PAUSED.load(Ordering::SeqCst)
+}
+
+pub fn set_paused(paused: bool) {
+    if PAUSED.swap(paused, Ordering::SeqCst) != paused {
+        info!("Listening {}", if paused { "paused" } else { "resumed" });
+    }
+}
+
+/// Pauses listening, or resumes it if it's paused. Returns whether it's now paused.
+pub fn toggle() -> bool {
+    let paused = !is_paused();
+    set_paused(paused);
+    paused
+}
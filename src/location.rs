@@ -0,0 +1,218 @@
+//! Working out where the user is, for tools like the weather that depend on it.
+//! A home location set in the config file, or with the set_home_location tool, is used instead of
+//! looking up the user's IP address, and IP lookups can be turned off entirely.
+
+use anyhow::{bail, Context};
+use serde::Deserialize;
+use std::{
+    sync::{LazyLock, OnceLock, RwLock},
+    time::Duration,
+};
+
+use crate::config::{self, LocationConfig};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where the user lives, as far as it's known.
+enum Home {
+    Unknown,
+    /// Only the name of the place is known. It's looked up the first time it's needed.
+    Named(String),
+    Located(Location),
+}
+
+static HOME: LazyLock<RwLock<Home>> = LazyLock::new(|| RwLock::new(Home::Unknown));
+static IP_LOOKUP: OnceLock<bool> = OnceLock::new();
+
+/// Sets the home location, and whether IP addresses can be looked up, from the config file.
+pub fn configure(config: &LocationConfig) {
+    let _ = IP_LOOKUP.set(config.ip_lookup);
+    let home = match (&config.name, config.latitude, config.longitude) {
+        (name, Some(latitude), Some(longitude)) => Home::Located(Location {
+            name: name
+                .clone()
+                .unwrap_or_else(|| format!("{:.2}, {:.2}", latitude, longitude)),
+            latitude,
+            longitude,
+            timezone: config.timezone.clone(),
+        }),
+        (Some(name), _, _) => Home::Named(name.clone()),
+        _ => Home::Unknown,
+    };
+    *HOME.write().unwrap() = home;
+}
+
+/// Whether the user's location can be looked up from their IP address.
+pub fn ip_lookup_allowed() -> bool {
+    *IP_LOOKUP.get_or_init(|| true)
+}
+
+/// A place with coordinates.
+#[derive(Debug, Clone)]
+pub struct Location {
+    /// Like "Berlin, Germany".
+    pub name: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    /// The IANA timezone, like "Europe/Berlin", where it's known.
+    pub timezone: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct IpLocation {
+    city: Option<String>,
+    region: Option<String>,
+    country_name: Option<String>,
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+    timezone: Option<String>,
+    #[serde(default)]
+    error: bool,
+    reason: Option<String>,
+}
+
+/// The user's home location, if one is set.
+pub fn home() -> Result<Option<Location>, anyhow::Error> {
+    let name = match &*HOME.read().unwrap() {
+        Home::Unknown => return Ok(None),
+        Home::Located(location) => return Ok(Some(location.clone())),
+        Home::Named(name) => name.clone(),
+    };
+    let location = find(&name).context("Failed to find the home location")?;
+    *HOME.write().unwrap() = Home::Located(location.clone());
+    Ok(Some(location))
+}
+
+/// Where the user is: their home location if it's set, or where their IP address is if not.
+/// Returns whether it came from the home location too.
+pub fn current() -> Result<(Location, bool), anyhow::Error> {
+    if let Some(home) = home()? {
+        return Ok((home, true));
+    }
+    if !ip_lookup_allowed() {
+        bail!("The user turned off looking up their location from their IP address, and hasn't set a home location. Ask where they are, or offer to set their home location.");
+    }
+    Ok((get_location()?, false))
+}
+
+/// Makes `location` the user's home, and saves it to the config file.
+pub fn set_home(location: Location) -> Result<(), anyhow::Error> {
+    config::update(|table| {
+        let section = table
+            .entry("location")
+            .or_insert_with(|| toml::Table::new().into());
+        if let Some(section) = section.as_table_mut() {
+            section.insert("name".into(), location.name.clone().into());
+            section.insert("latitude".into(), location.latitude.into());
+            section.insert("longitude".into(), location.longitude.into());
+            match &location.timezone {
+                Some(timezone) => section.insert("timezone".into(), timezone.clone().into()),
+                None => section.remove("timezone"),
+            };
+        }
+    })?;
+    *HOME.write().unwrap() = Home::Located(location);
+    Ok(())
+}
+
+/// Looks up roughly where the user is from their public IP address.
+pub fn get_location() -> Result<Location, anyhow::Error> {
+    if !ip_lookup_allowed() {
+        bail!("Looking up the location of this IP address is turned off in the config file");
+    }
+    let response: IpLocation = reqwest::blocking::Client::new()
+        .get("https://ipapi.co/json/")
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .context("Failed to look up the location of this IP address")?
+        .error_for_status()?
+        .json()?;
+    if response.error {
+        bail!(
+            "Failed to look up the location of this IP address: {}",
+            response.reason.unwrap_or_default()
+        );
+    }
+    let (Some(latitude), Some(longitude)) = (response.latitude, response.longitude) else {
+        bail!("The location of this IP address isn't known");
+    };
+    let name = [response.city, response.region, response.country_name]
+        .into_iter()
+        .flatten()
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(", ");
+    Ok(Location {
+        name,
+        latitude,
+        longitude,
+        timezone: response.timezone,
+    })
+}
+
+#[derive(Deserialize)]
+struct GeocodingResponse {
+    #[serde(default)]
+    results: Vec<GeocodingResult>,
+}
+
+#[derive(Deserialize)]
+struct GeocodingResult {
+    name: String,
+    admin1: Option<String>,
+    country: Option<String>,
+    latitude: f64,
+    longitude: f64,
+    timezone: Option<String>,
+}
+
+/// Finds a place by name, like "Paris" or "Springfield, Illinois", using Open-Meteo's geocoding.
+pub fn find(place: &str) -> Result<Location, anyhow::Error> {
+    // The geocoder only matches place names, so anything after a comma narrows down the matches instead.
+    let mut parts = place.split(',').map(str::trim);
+    let name = parts.next().unwrap_or_default();
+    let qualifiers: Vec<String> = parts.map(str::to_lowercase).collect();
+
+    let response: GeocodingResponse = reqwest::blocking::Client::new()
+        .get("https://geocoding-api.open-meteo.com/v1/search")
+        .query(&[("name", name), ("count", "10"), ("format", "json")])
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .with_context(|| format!("Failed to look up \"{}\"", place))?
+        .error_for_status()?
+        .json()?;
+
+    let matches_qualifiers = |result: &&GeocodingResult| {
+        qualifiers.iter().all(|qualifier| {
+            [&result.admin1, &result.country]
+                .into_iter()
+                .flatten()
+                .any(|part| part.to_lowercase().starts_with(qualifier.as_str()))
+        })
+    };
+    let Some(result) = response
+        .results
+        .iter()
+        .find(matches_qualifiers)
+        .or(response.results.first())
+    else {
+        bail!("Couldn't find a place called \"{}\"", place);
+    };
+
+    let name = [
+        Some(&result.name),
+        result.admin1.as_ref(),
+        result.country.as_ref(),
+    ]
+    .into_iter()
+    .flatten()
+    .cloned()
+    .collect::<Vec<_>>()
+    .join(", ");
+    Ok(Location {
+        name,
+        latitude: result.latitude,
+        longitude: result.longitude,
+        timezone: result.timezone.clone(),
+    })
+}
@@ -1,4 +1,7 @@
 use anyhow::Context;
+// The chat request still takes tools as functions.
+#[allow(deprecated)]
+use async_openai::types::ChatCompletionFunctions;
 use async_openai::types::{
     ChatCompletionFunctionsArgs, ChatCompletionRequestFunctionMessageArgs, ChatCompletionRequestToolMessageArgs, FinishReason
 };
@@ -16,37 +19,108 @@ use timers::*;
 use tracing_appender::non_blocking::WorkerGuard;
 use tracing_subscriber::filter::FilterFn;
 use tracing_subscriber::Registry;
+mod timer_store;
 mod timers;
+mod translate;
 mod transcribe;
 use chrono::{DateTime, Local};
 use futures::stream::StreamExt; // For `.next()` on FuturesOrdered.
 use std::thread;
 use tempfile::Builder;
 mod record;
+mod routines;
 use async_openai::{
     types::{
         ChatCompletionRequestAssistantMessageArgs, ChatCompletionRequestMessage,
         ChatCompletionRequestSystemMessageArgs, ChatCompletionRequestUserMessageArgs,
-        CreateChatCompletionRequestArgs, Voice,
+        CreateChatCompletionRequestArgs,
     },
     Client,
 };
 use async_std::future;
-use clap::{Parser, Subcommand};
+use clap::{Args, CommandFactory, FromArgMatches, Subcommand};
 use colored::Colorize;
-use cpal::traits::{DeviceTrait, HostTrait};
 use rdev::{listen, Event};
 use record::rec;
+use std::collections::HashMap;
 use std::error::Error;
-use std::sync::LazyLock;
-use std::time::Duration;
+use std::sync::{LazyLock, OnceLock};
+use std::time::{Duration, Instant};
 use uuid::Uuid;
+mod activity;
+mod apps;
+mod audio;
+mod bluetooth;
+mod calculator;
+mod commands;
+mod contacts;
+mod cooking;
+mod brightness;
+mod config;
+mod confirmation;
+mod conversation;
+mod devices;
+mod disks;
+mod do_not_disturb;
+mod docker;
+mod doctor;
+mod download;
+mod ducking;
 mod easy_rdev_key;
+mod email;
+mod export;
+mod file_search;
+mod flashcards;
+mod finance;
+mod git;
+mod focus;
+mod ics;
+mod instance;
+mod listening_pause;
+mod location;
+mod monitoring;
+mod read_aloud;
+mod songs;
 mod speakstream;
+mod spotify;
+mod tasks;
+mod sound_theme;
+mod speech_text;
+mod tick;
+mod time_stretch;
+mod tts_cache;
+mod units;
+mod wikipedia;
+mod window_control;
+mod window_text;
+mod weather;
 use enigo::{Enigo, KeyboardControllable};
+use audio::Channel;
+use conversation::ConversationEvent;
+use paths::CACHE_DIR;
+use sound_theme::{Sound, SoundTheme};
 use speakstream::ss;
+use speech_text::{SpeechTagFilter, SPEECH_TAGS_PROMPT};
 use timers::AudibleTimers;
+mod news;
+mod notes;
+mod notifications;
+mod opener;
 mod options;
+mod paths;
+mod pomodoro;
+mod power;
+mod processes;
+mod profiles;
+mod reminders;
+mod screenshot;
+mod secrets;
+mod sessions;
+mod terminal;
+mod settings;
+mod setup;
+mod shutdown;
+mod system_command;
 use tracing::{debug, error, info, instrument, warn};
 use tracing_appender::rolling::{RollingFileAppender, Rotation};
 
@@ -56,30 +130,134 @@ use crate::speakstream::ss::SpeakStream;
 pub enum SubCommands {
     /// Displays keys as you press them so you can figure out what key to use for push to talk.
     ShowKeyPresses,
-    /// Lists the audio input devices on your system.
-    ListDevices,
+    /// Lists the audio input and output devices on your system, marking the defaults
+    /// and the ones the assistant uses.
+    ListDevices {
+        /// Play a short tone on each output the assistant uses.
+        #[arg(long)]
+        test_tone: bool,
+        /// Play a tone on the speech output and check that the microphone hears it.
+        #[arg(long)]
+        loopback: bool,
+    },
+    /// Walks through choosing your API key, microphone, push-to-talk key and voice,
+    /// and saves them so the assistant starts without any options.
+    Setup,
+    /// Saves your OpenAI API key to the system keyring, so it doesn't need to be passed
+    /// with `--api-key` or kept in a .env file. Asks for the key if it isn't given.
+    SetKey {
+        /// The API key to save. Leaving this out keeps it out of your shell history.
+        api_key: Option<String>,
+    },
+    /// Saves the password of the email account the AI sends emails from to the system keyring.
+    /// The account itself is set in the [email] section of the config file. Asks for the password if it isn't given.
+    SetEmailPassword {
+        /// The password to save. Leaving this out keeps it out of your shell history.
+        /// Services like Gmail need an app password here.
+        password: Option<String>,
+    },
+    /// Logs in to Spotify in your browser, so the AI can play music on your account.
+    /// Needs the client ID of an app made at https://developer.spotify.com/dashboard.
+    SpotifyLogin {
+        /// The client ID of your Spotify app. It's saved to the config file, so it's only needed once.
+        #[arg(long)]
+        client_id: Option<String>,
+    },
+    /// Checks for the programs, devices, API key and permissions the assistant needs,
+    /// and says how to fix anything that's missing.
+    Doctor,
+    /// Sends a command to the assistant that's already running, a question for it to answer, or a saved
+    /// conversation for it to pick up.
+    Send {
+        #[arg(value_enum, required_unless_present_any(["ask", "load_session"]))]
+        command: Option<instance::InstanceCommand>,
+        /// A question for the AI to answer, like it was said with push to talk.
+        #[arg(long, conflicts_with("command"))]
+        ask: Option<String>,
+        /// A saved conversation for the AI to pick up, like "2024-05-01_09-30-00".
+        #[arg(long, conflicts_with_all(["command", "ask"]))]
+        load_session: Option<String>,
+    },
+    /// Asks the assistant one question, prints and speaks the answer, then exits.
+    /// The AI can use all of its tools to answer.
+    Ask(AskArgs),
+    /// Exports a saved conversation as a Markdown or HTML transcript.
+    Export {
+        /// The session to export, like "2024-05-01_09-30-00". Defaults to the latest one.
+        session: Option<String>,
+        #[arg(long, value_enum, default_value_t)]
+        format: export::Format,
+        /// The folder to write the transcript to. Defaults to a quick-assistant folder in your documents.
+        #[arg(long)]
+        output: Option<PathBuf>,
+        /// Open the transcript once it's written.
+        #[arg(long)]
+        open: bool,
+        /// List the saved sessions instead of exporting one.
+        #[arg(long, conflicts_with_all(["session", "open"]))]
+        list: bool,
+    },
+}
+
+#[derive(Args, Debug)]
+pub struct AskArgs {
+    /// The question to ask, like "what's on my timer list".
+    #[arg(required_unless_present("audio"))]
+    question: Option<String>,
+    /// An audio file of the question to transcribe, instead of typing it.
+    #[arg(long, conflicts_with("question"))]
+    audio: Option<PathBuf>,
+    /// Only print the answer, without speaking it. An assistant that's already running answers
+    /// instead, and speaks the answer as it normally would.
+    #[arg(long)]
+    no_speak: bool,
 }
 
 #[derive(clap::ValueEnum, Clone, Debug)]
 pub enum VoiceEnum {
     Alloy,
+    Ash,
+    Coral,
     Echo,
     Fable,
     Onyx,
     Nova,
+    Sage,
     Shimmer,
 }
 
-impl From<VoiceEnum> for Voice {
-    fn from(item: VoiceEnum) -> Self {
-        match item {
-            VoiceEnum::Alloy => Voice::Alloy,
-            VoiceEnum::Echo => Voice::Echo,
-            VoiceEnum::Fable => Voice::Fable,
-            VoiceEnum::Onyx => Voice::Onyx,
-            VoiceEnum::Nova => Voice::Nova,
-            VoiceEnum::Shimmer => Voice::Shimmer,
-        }
+/// Returns the name OpenAI's speech API uses for a voice.
+pub fn voice_to_str(voice: &VoiceEnum) -> &'static str {
+    match voice {
+        VoiceEnum::Alloy => "alloy",
+        VoiceEnum::Ash => "ash",
+        VoiceEnum::Coral => "coral",
+        VoiceEnum::Echo => "echo",
+        VoiceEnum::Fable => "fable",
+        VoiceEnum::Onyx => "onyx",
+        VoiceEnum::Nova => "nova",
+        VoiceEnum::Sage => "sage",
+        VoiceEnum::Shimmer => "shimmer",
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Debug, PartialEq)]
+pub enum TtsModelEnum {
+    #[value(name = "tts-1")]
+    Tts1,
+    #[value(name = "tts-1-hd")]
+    Tts1Hd,
+    /// Supports `--tts-instructions`.
+    #[value(name = "gpt-4o-mini-tts")]
+    Gpt4oMiniTts,
+}
+
+/// Returns the name OpenAI's speech API uses for a text to speech model.
+pub fn tts_model_to_str(model: &TtsModelEnum) -> &'static str {
+    match model {
+        TtsModelEnum::Tts1 => "tts-1",
+        TtsModelEnum::Tts1Hd => "tts-1-hd",
+        TtsModelEnum::Gpt4oMiniTts => "gpt-4o-mini-tts",
     }
 }
 
@@ -109,8 +287,19 @@ fn truncate(s: &str, len: usize) -> String {
     }
 }
 
+/// Has the AI tell the user something they didn't just ask about, like that a download finished.
+/// Nothing is said unprompted while listening is paused, so `summary` and `body` are shown as a
+/// desktop notification instead.
+fn tell_user(llm_messages_tx: &flume::Sender<Message>, fn_name: &str, content: String, summary: &str, body: &str) {
+    if listening_pause::is_paused() {
+        notifications::notify(summary, body);
+        return;
+    }
+    llm_messages_tx.send(Message::Function { fn_name: fn_name.to_string(), content }).unwrap();
+}
+
 fn println_error(err: &str) {
-    println!("{}: {}", "Error".truecolor(255, 0, 0), err);
+    terminal::print_error(err);
     warn!("{}", err);
 }
 
@@ -129,21 +318,107 @@ fn create_temp_file_from_bytes(bytes: &[u8], extension: &str) -> NamedTempFile {
     temp_file
 }
 
-fn set_screen_brightness(brightness: u32) -> Option<()> {
-    if brightness > 100 {
-        println!("Brightness must be between 0 and 100");
-        return None;
+/// The longest an alarm can ring for, wait to ring again or be snoozed for.
+const MAX_ALARM_WAIT: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Reads the optional alarm and countdown announcement arguments of the set timer functions.
+fn alarm_settings_from_args(args: &serde_json::Value) -> AlarmSettings {
+    let mut alarm = AlarmSettings {
+        sound: args["sound"].as_str().filter(|s| !s.is_empty()).map(str::to_string),
+        escalate: args["escalate"].as_bool().unwrap_or(false),
+        ..Default::default()
+    };
+    if let Some(volume) = args["volume"].as_u64() {
+        alarm.volume = volume.min(100) as f32 / 100.0;
+    }
+    if let Some(ring_duration) = parse_duration_arg(&args["ring_for"]) {
+        alarm.ring_duration = ring_duration.min(MAX_ALARM_WAIT);
+    }
+    if let Some(re_ring_interval) = parse_duration_arg(&args["re_ring_every"]) {
+        alarm.re_ring_interval = re_ring_interval.min(MAX_ALARM_WAIT);
+    }
+    if let Some(re_rings) = args["re_rings"].as_u64() {
+        alarm.re_rings = re_rings.min(u32::MAX as u64) as u32;
+    }
+    if let Some(announce_before) = args["announce_before"].as_array() {
+        alarm.announce_before = announce_before.iter().filter_map(parse_duration_arg).collect();
+    }
+    alarm
+}
+
+/// Parses an optional duration argument like "2 minutes". Invalid durations are ignored.
+fn parse_duration_arg(arg: &serde_json::Value) -> Option<Duration> {
+    // humantime doesn't understand "and", as in "1 hour and 30 minutes".
+    humantime::parse_duration(&arg.as_str()?.replace(" and ", " ")).ok()
+}
+
+/// Parses a time for the set timer functions. With a timezone, like "Asia/Tokyo", the time is read
+/// as the clock time there and any UTC offset in it is ignored, so the AI doesn't need to know the offset.
+/// The error is a message for the AI.
+fn parse_time_in_timezone(time_str: &str, timezone: Option<&str>) -> Result<DateTime<Local>, String> {
+    let Some(timezone) = timezone.filter(|timezone| !timezone.is_empty()) else {
+        return time_str
+            .parse::<DateTime<Local>>()
+            .map_err(|err| format!("Please enter valid rfc_3339: {}", err));
+    };
+
+    let tz: chrono_tz::Tz = timezone.parse().map_err(|_| {
+        format!("\"{}\" isn't a known timezone. Use an IANA timezone name like \"Asia/Tokyo\".", timezone)
+    })?;
+    let clock_time = match chrono::DateTime::parse_from_rfc3339(time_str) {
+        Ok(time) => time.naive_local(),
+        Err(_) => ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M"]
+            .iter()
+            .find_map(|format| chrono::NaiveDateTime::parse_from_str(time_str, format).ok())
+            .ok_or_else(|| format!("\"{}\" isn't a valid time. Use a format like \"2024-12-04T09:00:00\".", time_str))?,
+    };
+    match chrono::TimeZone::from_local_datetime(&tz, &clock_time).earliest() {
+        Some(time) => Ok(time.with_timezone(&Local)),
+        None => Err(format!("{} doesn't happen in {}, because of a daylight saving time change.", clock_time, tz.name())),
+    }
+}
+
+/// Parses an optional rfc3339 time argument. The error is a message for the AI.
+fn parse_time_arg(arg: &serde_json::Value) -> Result<Option<DateTime<Local>>, String> {
+    match arg.as_str() {
+        Some(time_str) => time_str
+            .parse::<DateTime<Local>>()
+            .map(Some)
+            .map_err(|err| format!("\"{}\" isn't a valid rfc3339 time: {}", time_str, err)),
+        None => Ok(None),
+    }
+}
+
+/// Describes a calendar event for the AI.
+fn describe_event(event: &ics::CalendarEvent) -> String {
+    let start = if event.all_day {
+        format!("all day on {}", event.start.format("%A %Y-%m-%d"))
+    } else {
+        format!("at {}", event.start.to_rfc3339())
+    };
+    match &event.location {
+        Some(location) => format!("Event: \"{}\" {} at location \"{}\"", event.summary, start, location),
+        None => format!("Event: \"{}\" {}", event.summary, start),
     }
+}
 
-    Command::new("luster")
-        .arg(brightness.to_string())
-        .output()
-        .ok()
-        .map(|_| ())
+/// Describes timers for the AI, like: the "tea" timer (ID 3).
+fn describe_timers(timers: &[Timer]) -> String {
+    timers
+        .iter()
+        .map(|timer| format!("the \"{}\" timer (ID {})", timer.description, timer.id))
+        .collect::<Vec<_>>()
+        .join(", ")
 }
 
-#[instrument]
-fn call_fn(fn_name: &str, fn_args: &str, llm_messages_tx: flume::Sender<Message>) -> Option<String>{
+#[instrument(skip(speak_stream_mutex, audible_timers))]
+fn call_fn(
+    fn_name: &str,
+    fn_args: &str,
+    llm_messages_tx: flume::Sender<Message>,
+    speak_stream_mutex: &Arc<Mutex<SpeakStream>>,
+    audible_timers: &AudibleTimers,
+) -> Option<String> {
     let mut enigo = Enigo::new();
 
     println!("{}{}", "Invoking function: ".purple(), fn_name);
@@ -153,14 +428,27 @@ fn call_fn(fn_name: &str, fn_args: &str, llm_messages_tx: flume::Sender<Message>
         "set_screen_brightness" => {
             info!("Handling set_screen_brightness function call.");
             let args: serde_json::Value = serde_json::from_str(fn_args).unwrap();
-            let brightness = args["brightness"].as_str().unwrap().parse::<u32>().unwrap();
+            // Older conversations pass the brightness as a string.
+            let brightness = match &args["brightness"] {
+                serde_json::Value::String(brightness) => brightness.trim().parse().ok(),
+                brightness => brightness.as_u64(),
+            };
+            let Some(brightness) = brightness else {
+                return Some("Brightness must be a number between 0 and 100.".to_string());
+            };
 
             println!("{}{}", "set_screen_brightness: ".purple(), brightness);
 
-            if set_screen_brightness(brightness).is_some() {
-                Some("Brightness set".to_string())
-            } else {
-                Some("Failed to set brightness".to_string())
+            match brightness::set(brightness.min(u32::MAX as u64) as u32) {
+                Ok(()) => Some(format!("Brightness set to {}%", brightness)),
+                Err(err) => Some(format!("Failed to set brightness: {:#}", err)),
+            }
+        }
+        "get_screen_brightness" => {
+            println!("{}", "get_screen_brightness".purple());
+            match brightness::get() {
+                Ok(brightness) => Some(format!("The screen brightness is {}%", brightness)),
+                Err(err) => Some(format!("Failed to get the screen brightness: {:#}", err)),
             }
         }
         "media_controls" => {
@@ -219,14 +507,159 @@ fn call_fn(fn_name: &str, fn_args: &str, llm_messages_tx: flume::Sender<Message>
 
             println!("{}{}", "opening application: ".purple(), application);
 
-            enigo.key_click(enigo::Key::Meta);
-            std::thread::sleep(std::time::Duration::from_millis(500));
-            enigo.key_sequence(application);
-            std::thread::sleep(std::time::Duration::from_millis(500));
-            enigo.key_click(enigo::Key::Return);
+            match apps::open(application) {
+                Ok(name) => Some(format!("Opened {}.", name)),
+                Err(err) => Some(format!("Failed to open application: {:#}", err)),
+            }
+        }
+        "list_open_windows" => {
+            println!("{}", "list_open_windows".purple());
+            match window_control::list() {
+                Ok(windows) if windows.is_empty() => Some("No windows are open.".to_string()),
+                Ok(windows) => {
+                    let info = windows
+                        .iter()
+                        .map(|window| match &window.app {
+                            Some(app) => format!("\"{}\" ({})", window.title, app),
+                            None => format!("\"{}\"", window.title),
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    Some(format!("Open windows, oldest first:\n{}", info))
+                }
+                Err(err) => Some(format!("Failed to list windows: {:#}", err)),
+            }
+        }
+        "focus_window" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap();
+            let title = args["title"].as_str().unwrap_or_default();
+
+            println!("{}{}", "focus_window: ".purple(), title);
+
+            match window_control::focus(title) {
+                Ok(title) => Some(format!("Switched to \"{}\".", title)),
+                Err(err) => Some(format!("Failed to switch windows: {:#}", err)),
+            }
+        }
+        "minimize_window" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap_or_default();
+            let title = args["title"].as_str();
+
+            println!("{}{}", "minimize_window: ".purple(), title.unwrap_or("focused window"));
+
+            match window_control::minimize(title) {
+                Ok(title) => Some(format!("Minimized \"{}\".", title)),
+                Err(err) => Some(format!("Failed to minimize window: {:#}", err)),
+            }
+        }
+        "close_window" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap_or_default();
+            let title = args["title"].as_str();
+
+            println!("{}{}", "close_window: ".purple(), title.unwrap_or("focused window"));
+
+            match window_control::close(title) {
+                Ok(title) => Some(format!("Asked \"{}\" to close.", title)),
+                Err(err) => Some(format!("Failed to close window: {:#}", err)),
+            }
+        }
+        "search_files" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap();
+            let pattern = args["pattern"].as_str().unwrap_or_default();
+            let root = args["root"].as_str().filter(|root| !root.trim().is_empty());
+            let limit = args["limit"].as_u64().unwrap_or(10) as usize;
+
+            println!("{}{} in {}", "search_files: ".purple(), pattern, root.unwrap_or("all allowed folders"));
+
+            match file_search::search(pattern, root, limit) {
+                Ok(found) if found.is_empty() => Some(format!("No files matching \"{}\" were found.", pattern)),
+                Ok(found) => {
+                    let mut info = format!("=== {} matching files, newest first ===\n", found.len());
+                    for file in found {
+                        let modified = file
+                            .modified
+                            .map(|modified| modified.format("%Y-%m-%d %H:%M").to_string())
+                            .unwrap_or_else(|| "unknown".to_string());
+                        info.push_str(&format!("{} ({} KB, modified {})\n", file.path.display(), file.size.div_ceil(1024), modified));
+                    }
+                    println!("{}", info);
+                    Some(info)
+                }
+                Err(err) => Some(format!("Failed to search files: {:#}", err)),
+            }
+        }
+        "read_file_aloud" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap();
+            let path = args["path"].as_str().unwrap_or_default();
+            let summarize = args["summarize"].as_bool().unwrap_or(false);
+
+            println!("{}{}", "read_file_aloud: ".purple(), path);
 
+            let (path, text) = match read_aloud::load(path) {
+                Ok(file) => file,
+                Err(err) => return Some(format!("Failed to read file: {:#}", err)),
+            };
+            if summarize {
+                let truncated = text.chars().count() > read_aloud::MAX_SUMMARY_CHARS;
+                let text: String = text.chars().take(read_aloud::MAX_SUMMARY_CHARS).collect();
+                return Some(format!(
+                    "=== {}{} ===\n{}\n=== end of file ===\nSummarize this file for the user to listen to. Keep it short and don't use markdown.",
+                    path.display(),
+                    if truncated { " (only the start of the file)" } else { "" },
+                    text
+                ));
+            }
+            let mut speak_stream = speak_stream_mutex.lock().unwrap();
+            if !speak_stream.read_aloud(&text) {
+                return Some("AI speech is muted. Unmute it to hear the file read aloud.".to_string());
+            }
+            // Anything the AI said now would be spoken after the whole file.
             None
         }
+        "open_url" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap();
+            // A confirmed call opens what the user agreed to.
+            let held = match confirmation::take("open_url", &args) {
+                Ok(held) => held,
+                Err(err) => return Some(format!("Failed to open URL: {:#}", err)),
+            };
+            let confirmed = held.is_some();
+            let args = held.unwrap_or(args);
+            let url = args["url"].as_str().unwrap_or_default();
+
+            println!("{}{}", "open_url: ".purple(), url);
+
+            match opener::open_url(url, confirmed) {
+                Ok(opener::Opening::Opened(url)) => Some(format!("Opened {}.", url)),
+                Ok(opener::Opening::NeedsConfirmation(url)) => {
+                    let code = confirmation::request("open_url", &json!({ "url": url }));
+                    Some(format!("{} isn't on the list of sites that open without asking. Ask the user whether to open it, and wait for their answer. Only if they agree, call open_url again with confirmation set to \"{}\".", url, code))
+                }
+                Err(err) => Some(format!("Failed to open URL: {:#}", err)),
+            }
+        }
+        "open_path" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap();
+            // A confirmed call opens what the user agreed to.
+            let held = match confirmation::take("open_path", &args) {
+                Ok(held) => held,
+                Err(err) => return Some(format!("Failed to open path: {:#}", err)),
+            };
+            let confirmed = held.is_some();
+            let args = held.unwrap_or(args);
+            let path = args["path"].as_str().unwrap_or_default();
+
+            println!("{}{}", "open_path: ".purple(), path);
+
+            match opener::open_path(path, confirmed) {
+                Ok(opener::Opening::Opened(path)) => Some(format!("Opened {}.", path)),
+                Ok(opener::Opening::NeedsConfirmation(path)) => {
+                    let code = confirmation::request("open_path", &json!({ "path": path }));
+                    Some(format!("{} is outside the folders that open without asking. Ask the user whether to open it, and wait for their answer. Only if they agree, call open_path again with confirmation set to \"{}\".", path, code))
+                }
+                Err(err) => Some(format!("Failed to open path: {:#}", err)),
+            }
+        }
         "open_logs_folder" => {
             match open::that(&*LOGS_DIR) {
                 Ok(_) => None,
@@ -235,7 +668,165 @@ fn call_fn(fn_name: &str, fn_args: &str, llm_messages_tx: flume::Sender<Message>
         }
         "sysinfo" => Some(get_system_info()),
 
-        "get_system_processes" => Some(get_system_processes()),
+        "summarize_active_window" => {
+            println!("{}", "summarize_active_window".purple());
+
+            let (window, text) = match window_text::read_active() {
+                Ok(window) => window,
+                Err(err) => return Some(format!("Failed to read the focused window: {:#}", err)),
+            };
+            let Some(text) = text else {
+                return Some(format!("The focused window is titled \"{}\", but its text couldn't be read. Summarize what you can tell from the title, and say that's all you could see.", window.title));
+            };
+            let truncated = text.chars().count() > window_text::MAX_TEXT_CHARS;
+            let text: String = text.chars().take(window_text::MAX_TEXT_CHARS).collect();
+            Some(format!(
+                "=== {}{} ===\n{}\n=== end of window ===\nThis is the text of the window the user is looking at, which can include menus and buttons as well as the content. Answer the user's question about it, or summarize the content for the user to listen to. Keep it short and don't use markdown.",
+                window.title,
+                if truncated { " (only the start of the text)" } else { "" },
+                text
+            ))
+        }
+        "list_bluetooth_devices" => {
+            println!("{}", "list_bluetooth_devices".purple());
+
+            match bluetooth::list() {
+                Ok(devices) => Some(bluetooth::describe(&devices)),
+                Err(err) => Some(format!("Failed to list Bluetooth devices: {:#}", err)),
+            }
+        }
+        "connect_bluetooth_device" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap();
+            let name = args["name"].as_str().unwrap_or_default();
+
+            println!("{}{}", "connect_bluetooth_device: ".purple(), name);
+
+            match bluetooth::connect(name) {
+                Ok(info) => Some(info),
+                Err(err) => Some(format!("Failed to connect the Bluetooth device: {:#}", err)),
+            }
+        }
+        "lock_screen" => {
+            println!("{}", "lock_screen".purple());
+            match power::lock_screen() {
+                Ok(()) => Some("Locked the screen.".to_string()),
+                Err(err) => Some(format!("Failed to lock the screen: {:#}", err)),
+            }
+        }
+        "sleep_computer" => {
+            println!("{}", "sleep_computer".purple());
+            match power::sleep() {
+                // The computer is asleep by the time a reply could be spoken.
+                Ok(()) => None,
+                Err(err) => Some(format!("Failed to put the computer to sleep: {:#}", err)),
+            }
+        }
+        "shutdown_computer" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap_or_default();
+            // A confirmed call does what was asked the first time.
+            let held = match confirmation::take("shutdown_computer", &args) {
+                Ok(held) => held,
+                Err(err) => return Some(format!("Failed to shut down the computer: {:#}", err)),
+            };
+            let confirmed = held.is_some();
+            let args = held.unwrap_or(args);
+            let Some(delay_minutes) = u32::try_from(args["delay_minutes"].as_u64().unwrap_or(0)).ok().filter(|minutes| *minutes <= power::MAX_DELAY_MINUTES) else {
+                return Some(format!("Failed to shut down the computer: the delay can be at most {} minutes", power::MAX_DELAY_MINUTES));
+            };
+            let restart = args["restart"].as_bool().unwrap_or(false);
+            let action = if restart { "restart" } else { "shut down" };
+
+            println!("{}{} in {} minutes", "shutdown_computer: ".purple(), action, delay_minutes);
+
+            if !confirmed {
+                let code = confirmation::request("shutdown_computer", &args);
+                return Some(format!("Nothing was done yet. Ask the user to confirm that they want to {} the computer{}, since unsaved work can be lost, and wait for their answer. Only if they agree, call shutdown_computer again with confirmation set to \"{}\".", action, if delay_minutes > 0 { format!(" in {} minutes", delay_minutes) } else { " now".to_string() }, code));
+            }
+            match power::shutdown(delay_minutes, restart) {
+                Ok(()) if delay_minutes > 0 => Some(format!("The computer will {} in {} minutes. cancel_shutdown stops it.", action, delay_minutes)),
+                Ok(()) => None,
+                Err(err) => Some(format!("Failed to {} the computer: {:#}", action, err)),
+            }
+        }
+        "cancel_shutdown" => {
+            println!("{}", "cancel_shutdown".purple());
+            match power::cancel_shutdown() {
+                Ok(()) => Some("Cancelled the scheduled shutdown.".to_string()),
+                Err(err) => Some(format!("Failed to cancel the shutdown: {:#}", err)),
+            }
+        }
+        "set_do_not_disturb" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap();
+            let on = args["on"].as_bool().unwrap_or(true);
+
+            println!(
+                "{}{}",
+                "set_do_not_disturb: ".purple(),
+                if on { "on" } else { "off" }
+            );
+
+            match do_not_disturb::set(on) {
+                Ok(place) => Some(format!(
+                    "Do not disturb is {} ({}).",
+                    if on { "on" } else { "off" },
+                    place
+                )),
+                Err(err) => Some(format!("Failed to change do not disturb: {:#}", err)),
+            }
+        }
+        "top_processes" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap_or_default();
+            let sort_by = args["sort_by"].as_str().unwrap_or("cpu");
+            let count = args["count"].as_u64().unwrap_or(10) as usize;
+
+            println!("{}{} by {}", "top_processes: ".purple(), count, sort_by);
+
+            let Some(sort_by) = processes::SortBy::from_name(sort_by) else {
+                return Some(format!("Unknown sort_by \"{}\". Use \"cpu\" or \"memory\".", sort_by));
+            };
+            Some(processes::top(sort_by, count))
+        }
+        "get_process_details" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap();
+            let name_or_pid = args["name_or_pid"].as_str().unwrap_or_default();
+
+            println!("{}{}", "get_process_details: ".purple(), name_or_pid);
+
+            match processes::details(name_or_pid) {
+                Ok(info) => Some(info),
+                Err(err) => Some(format!("Failed to get process details: {:#}", err)),
+            }
+        }
+
+        "notify_when_process_exits" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap();
+            let name_or_pid = args["name_or_pid"].as_str().unwrap_or_default();
+            let description = args["description"].as_str().filter(|description| !description.trim().is_empty());
+
+            println!("{}{}", "notify_when_process_exits: ".purple(), name_or_pid);
+
+            let watched = match processes::watch(name_or_pid) {
+                Ok(watched) => watched,
+                Err(err) => return Some(format!("Failed to watch the process: {:#}", err)),
+            };
+            let mut names: Vec<&str> = watched.iter().map(|watched| watched.name.as_str()).collect();
+            names.sort();
+            names.dedup();
+            let names = names.join(", ");
+            let what = description.map(str::to_string).unwrap_or_else(|| names.clone());
+
+            let count = watched.len();
+            let thread_fn_name = fn_name.to_string();
+            let thread_what = what.clone();
+            thread::spawn(move || {
+                let task = tasks::start(&format!("Waiting for {} to finish", thread_what));
+                let waited = processes::wait_for_exit(&watched);
+                drop(task);
+                let content = format!("{} ({}) has finished, after {} of waiting. Tell the user, for example \"your render job finished\".", thread_what, names, humantime::format_duration(Duration::from_secs(waited.as_secs())));
+                tell_user(&llm_messages_tx, &thread_fn_name, content, "Finished", &format!("{} has finished.", thread_what));
+            });
+            Some(format!("Watching {} process{}. The user will be told when {} finishes.", count, if count == 1 { "" } else { "es" }, what))
+        }
 
         "kill_processes_with_name" => {
             let args: serde_json::Value = serde_json::from_str(fn_args).unwrap();
@@ -306,34 +897,200 @@ fn call_fn(fn_name: &str, fn_args: &str, llm_messages_tx: flume::Sender<Message>
 
             let thread_fn_name = fn_name.to_string();
             thread::spawn(move || {
-                match speedtest() {
+                let task = tasks::start("Running a speed test");
+                let result = speedtest();
+                drop(task);
+                match result {
                     Ok(answer) => {
-                        llm_messages_tx.send(
-                            Message::Function {
-                                content: format!("Speedtest results: {}", answer),
-                                fn_name: thread_fn_name,
-                            }
-                        ).unwrap();
+                        let content = format!("Speedtest results: {}", answer);
+                        tell_user(&llm_messages_tx, &thread_fn_name, content, "Speed test finished", &answer);
                     },
                     Err(err) => {
-                        llm_messages_tx.send(
-                            Message::Function {
-                                content: format!("Speedtest failed with error: {}", err),
-                                fn_name: thread_fn_name,
-                            }
-                        ).unwrap();
+                        let content = format!("Speedtest failed with error: {}", err);
+                        tell_user(&llm_messages_tx, &thread_fn_name, content, "Speed test failed", &err.to_string());
                     },
                 }
             });
             None
         },
 
+        "download_file" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap();
+            let url = args["url"].as_str().unwrap_or_default();
+            let destination = args["destination"].as_str();
+
+            println!("{}{}", "download_file: ".purple(), url);
+
+            let download = match download::start(url, destination) {
+                Ok(download) => download,
+                Err(err) => return Some(format!("Failed to download the file: {:#}", err)),
+            };
+            let name = download.path.file_name().unwrap_or_default().to_string_lossy().to_string();
+            let mut started = format!("Started downloading {} into {}", name, download.path.parent().unwrap_or(Path::new("")).display());
+            if let Some(size) = download.size {
+                started.push_str(&format!(". It's {}", download::format_bytes(size)));
+            }
+
+            let thread_fn_name = fn_name.to_string();
+            let thread_name = name.clone();
+            thread::spawn(move || {
+                let task = tasks::start(&format!("Downloading {}", thread_name));
+                let result = download.save(|progress| task.set_progress(progress));
+                drop(task);
+                match result {
+                    Ok(path) => {
+                        let content = format!("The download of {} has finished. It was saved to {}. Tell the user.", thread_name, path.display());
+                        tell_user(&llm_messages_tx, &thread_fn_name, content, "Download finished", &format!("{} was saved to {}.", thread_name, path.display()));
+                    }
+                    Err(err) => {
+                        let content = format!("The download of {} failed: {:#}. Tell the user.", thread_name, err);
+                        tell_user(&llm_messages_tx, &thread_fn_name, content, "Download failed", &format!("{}: {:#}", thread_name, err));
+                    }
+                }
+            });
+            Some(format!("{}. The user will be told when it finishes, and its progress can be checked with get_running_tasks.", started))
+        }
+
+        "get_disk_usage" => {
+            println!("{}", "get_disk_usage".purple());
+            Some(disks::describe())
+        }
+
+        "get_temperatures" => {
+            println!("{}", "get_temperatures".purple());
+            Some(monitoring::describe())
+        }
+
+        "get_uptime_and_idle" => {
+            println!("{}", "get_uptime_and_idle".purple());
+            Some(activity::describe())
+        }
+
+        "identify_song" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap_or_default();
+            let source = args["source"].as_str().unwrap_or("system");
+
+            println!("{}{}", "identify_song: ".purple(), source);
+
+            let Some(source) = songs::Source::from_name(source) else {
+                return Some(format!("Failed to identify the song: \"{}\" isn't a source. Use \"system\" or \"microphone\".", source));
+            };
+            if let Err(err) = songs::audd_key() {
+                return Some(format!("Failed to identify the song: {:#}", err));
+            }
+
+            let thread_fn_name = fn_name.to_string();
+            thread::spawn(move || {
+                let task = tasks::start("Listening for a song");
+                let result = songs::identify(source);
+                drop(task);
+                let (content, summary, body) = match result {
+                    Ok(Some(song)) => (format!("The song was identified: {}. Tell the user.", song), "Song identified", song.to_string()),
+                    Ok(None) => ("No song was recognized. It may have been too quiet, or not a released song. Tell the user.".to_string(), "No song recognized", "It may have been too quiet, or not a released song.".to_string()),
+                    Err(err) => (format!("Failed to identify the song: {:#}", err), "Couldn't identify the song", format!("{:#}", err)),
+                };
+                tell_user(&llm_messages_tx, &thread_fn_name, content, summary, &body);
+            });
+            Some(format!("Listening for {} seconds. The user will be told what the song is afterwards.", songs::LISTEN_DURATION.as_secs()))
+        }
+
+        "add_flashcard" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap();
+            let deck = args["deck"].as_str().unwrap_or_default();
+            let question = args["question"].as_str().unwrap_or_default();
+            let answer = args["answer"].as_str().unwrap_or_default();
+
+            println!("{}{} - {}", "add_flashcard: ".purple(), deck, question);
+
+            match flashcards::add(deck, question, answer) {
+                Ok((deck, count)) => Some(format!("Added the flashcard to the {} deck, which now has {} cards.", deck, count)),
+                Err(err) => Some(format!("Failed to add the flashcard: {:#}", err)),
+            }
+        }
+
+        "start_review_session" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap_or_default();
+            let deck = args["deck"].as_str();
+
+            println!("{}{}", "start_review_session: ".purple(), deck.unwrap_or("all decks"));
+
+            match flashcards::start_review(deck) {
+                Ok(next) => Some(next),
+                Err(err) => Some(format!("Failed to start the review session: {:#}", err)),
+            }
+        }
+
+        "grade_flashcard" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap();
+            let grade = args["grade"].as_str().unwrap_or_default();
+
+            println!("{}{}", "grade_flashcard: ".purple(), grade);
+
+            let Some(grade) = flashcards::Grade::from_name(grade) else {
+                return Some(format!("Failed to grade the flashcard: \"{}\" isn't a grade. Use again, hard, good or easy.", grade));
+            };
+            match flashcards::grade(grade) {
+                Ok(next) => Some(next),
+                Err(err) => Some(format!("Failed to grade the flashcard: {:#}", err)),
+            }
+        }
+
+        "list_flashcard_decks" => {
+            println!("{}", "list_flashcard_decks".purple());
+            match flashcards::describe_decks() {
+                Ok(decks) => Some(decks),
+                Err(err) => Some(format!("Failed to list the flashcard decks: {:#}", err)),
+            }
+        }
+
+        "load_recipe" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap_or_default();
+            let url = args["url"].as_str();
+            let title = args["title"].as_str().unwrap_or_default();
+            let ingredients: Vec<String> = args["ingredients"].as_array().into_iter().flatten().filter_map(|ingredient| ingredient.as_str()).map(str::to_string).collect();
+            let steps: Vec<cooking::Step> = args["steps"].as_array().into_iter().flatten().filter_map(|step| {
+                let text = step["text"].as_str()?;
+                Some(match step["timer_minutes"].as_f64() {
+                    Some(minutes) => cooking::Step { text: text.to_string(), timer: cooking::timer_for(minutes) },
+                    None => cooking::Step::from_text(text),
+                })
+            }).collect();
+
+            println!("{}{}", "load_recipe: ".purple(), url.unwrap_or(title));
+
+            match cooking::load(url, title, ingredients, steps) {
+                Ok(info) => Some(info),
+                Err(err) => Some(format!("Failed to load the recipe: {:#}", err)),
+            }
+        }
+
+        "next_step" => {
+            println!("{}", "next_step".purple());
+            match cooking::next_step() {
+                Ok(step) => Some(step),
+                Err(err) => Some(format!("Failed to go to the next step: {:#}", err)),
+            }
+        }
+
+        "repeat_step" => {
+            println!("{}", "repeat_step".purple());
+            match cooking::repeat_step() {
+                Ok(step) => Some(step),
+                Err(err) => Some(format!("Failed to repeat the step: {:#}", err)),
+            }
+        }
+
+        "get_running_tasks" => {
+            println!("{}", "get_running_tasks".purple());
+            Some(tasks::describe())
+        }
+
         "set_timer_at" => {
             let args: serde_json::Value = serde_json::from_str(fn_args).unwrap();
             let time_str = args["time"].as_str().unwrap();
             let description = args["description"].as_str().unwrap_or_default();
-            match time_str.parse::<DateTime<Local>>() {
-                Ok(timestamp) => match set_timer(description.to_string(), timestamp) {
+            match parse_time_in_timezone(time_str, args["timezone"].as_str()) {
+                Ok(timestamp) => match set_timer(description.to_string(), timestamp, alarm_settings_from_args(&args)) {
                     Ok(_) => {
 
                         let success_response_message = {
@@ -361,47 +1118,163 @@ fn call_fn(fn_name: &str, fn_args: &str, llm_messages_tx: flume::Sender<Message>
                 
                     Err(err) => Some(format!("Setting timer failed with error: {}", err)),
                 },
-                Err(err) => Some(format!(
-                    "Setting timer failed. Please enter valid rfc_3339: {}",
-                    err
-                )),
+                Err(err) => Some(format!("Setting timer failed. {}", err)),
             }
         }
 
-        "check_on_timers" => {
-            let timers = get_timers();
-            let mut info = String::from("=== Timers ===\n");
-            for timer in timers {
-                let timer_time = timer.2;
-                let time_diff = timer_time.signed_duration_since(Local::now());
-
-                // Convert to std::time::Duration and handle potential negative durations
-                let duration_std = match time_diff.to_std() {
-                    Ok(dur) => dur,
-                    Err(e) => {
-                        eprintln!("Error converting duration: {}", e);
-                        std::time::Duration::new(0, 0)
+        "set_wake_alarm" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap();
+            let time_str = args["time"].as_str().unwrap_or_default();
+
+            println!("{}{}", "set_wake_alarm: ".purple(), time_str);
+
+            // A time of day, like "07:00", is the next time it comes, so routines can set the alarm.
+            let timestamp = match chrono::NaiveTime::parse_from_str(time_str, "%H:%M") {
+                Ok(time) => {
+                    let today = Local::now().date_naive();
+                    [today, today + chrono::Days::new(1)]
+                        .into_iter()
+                        .filter_map(|day| day.and_time(time).and_local_timezone(Local).earliest())
+                        .find(|timestamp| *timestamp > Local::now())
+                        .ok_or_else(|| format!("{} doesn't happen in the next day, because of a daylight saving time change.", time_str))
+                }
+                Err(_) => parse_time_in_timezone(time_str, args["timezone"].as_str()),
+            };
+            match timestamp {
+                Ok(timestamp) => match set_timer(routines::WAKE_ALARM_DESCRIPTION.to_string(), timestamp, alarm_settings_from_args(&args)) {
+                    Ok(_) => {
+                        let briefing = match routines::morning_steps() {
+                            Some(_) => "A morning briefing will be given once it's dismissed.",
+                            None => "The morning briefing is turned off.",
+                        };
+                        Some(format!("The wake-up alarm is set for {}. {}", timestamp.format("%A %-I:%M %p"), briefing))
                     }
-                };
+                    Err(err) => Some(format!("Setting the wake-up alarm failed with error: {}", err)),
+                },
+                Err(err) => Some(format!("Setting the wake-up alarm failed. {}", err)),
+            }
+        }
+
+        "get_time_in_timezone" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap();
+            // The home timezone when no timezone is given.
+            let home_timezone = location::home().ok().flatten().and_then(|home| home.timezone);
+            let timezone = args["timezone"]
+                .as_str()
+                .filter(|timezone| !timezone.trim().is_empty())
+                .or(home_timezone.as_deref())
+                .unwrap_or_default();
+
+            println!("{}{}", "get_time_in_timezone: ".purple(), timezone);
+
+            match timezone.parse::<chrono_tz::Tz>() {
+                Ok(tz) => {
+                    let time = Local::now().with_timezone(&tz);
+                    Some(format!(
+                        "The time in {} is {} ({}, UTC{}).",
+                        tz.name(),
+                        time.format("%A %Y-%m-%d %H:%M"),
+                        time.format("%Z"),
+                        time.format("%:z"),
+                    ))
+                }
+                Err(_) => Some(format!(
+                    "\"{}\" isn't a known timezone. Use an IANA timezone name like \"Asia/Tokyo\".",
+                    timezone
+                )),
+            }
+        }
 
-                // Truncate the duration to whole seconds
-                let duration_sec = std::time::Duration::new(duration_std.as_secs(), 0);
+        "set_timer_in" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap();
+            let duration_str = args["duration"].as_str().unwrap_or_default();
+            let description = args["description"].as_str().unwrap_or_default();
 
-                // Convert to a human-readable string with second precision
-                let time_diff_str = humantime::format_duration(duration_sec).to_string();
+            // humantime doesn't understand "and", as in "1 hour and 30 minutes".
+            let duration = match humantime::parse_duration(&duration_str.replace(" and ", " ")) {
+                Ok(duration) => duration,
+                Err(err) => {
+                    return Some(format!(
+                        "Setting timer failed. \"{}\" isn't a valid duration: {}. Try something like \"25 minutes\" or \"1h30m\".",
+                        duration_str, err
+                    ))
+                }
+            };
+            let timestamp = match chrono::Duration::from_std(duration) {
+                Ok(time_diff) => match Local::now().checked_add_signed(time_diff) {
+                    Some(timestamp) => timestamp,
+                    None => return Some("Setting timer failed. Duration is too long.".to_string()),
+                },
+                Err(err) => return Some(format!("Setting timer failed. Duration is too long: {}", err)),
+            };
 
-                info.push_str(&format!(
-                    "Timer_ID: \"{}\" Timer_description: \"{}\" goes off at time: \"{}\" which is \"{}\" from now.\n",
-                    timer.0,
-                    timer.1,
-                    timer.2.to_rfc3339(),
-                    time_diff_str,
-                ));
+            println!("{}{}", "set_timer_in: ".purple(), humantime::format_duration(duration));
+
+            match set_timer(description.to_string(), timestamp, alarm_settings_from_args(&args)) {
+                Ok(_) => Some(format!(
+                    "Successfully set timer to go off at: \"{}\" which is \"{}\" from now.",
+                    timestamp.to_rfc3339(),
+                    humantime::format_duration(duration)
+                )),
+                Err(err) => Some(format!("Setting timer failed with error: {}", err)),
             }
+        }
 
-            println!("{}", info);
+        "check_on_timers" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap_or_default();
+            let limit = args["limit"].as_u64().map(|limit| limit as usize);
+            let description_filter = args["description_contains"].as_str().map(str::to_lowercase);
+            let after = match parse_time_arg(&args["after"]) {
+                Ok(after) => after,
+                Err(err) => return Some(err),
+            };
+            let before = match parse_time_arg(&args["before"]) {
+                Ok(before) => before,
+                Err(err) => return Some(err),
+            };
 
-            Some(info)
+            let mut timers: Vec<Timer> = get_timers()
+                .into_iter()
+                .filter(|timer| after.is_none_or(|after| timer.timestamp >= after))
+                .filter(|timer| before.is_none_or(|before| timer.timestamp <= before))
+                .filter(|timer| {
+                    description_filter
+                        .as_ref()
+                        .is_none_or(|filter| timer.description.to_lowercase().contains(filter))
+                })
+                .collect();
+            timers.sort_by_key(|timer| timer.timestamp);
+            let matching = timers.len();
+            timers.truncate(limit.unwrap_or(usize::MAX));
+
+            let timers_json: Vec<serde_json::Value> = timers
+                .iter()
+                .map(|timer| {
+                    // Negative durations, from timers that are about to go off, become zero.
+                    let time_left = timer.timestamp.signed_duration_since(Local::now()).to_std().unwrap_or_default();
+                    println!(
+                        "Timer {}: \"{}\" goes off at {} ({} from now)",
+                        timer.id,
+                        timer.description,
+                        timer.timestamp.format("%Y-%m-%d %H:%M:%S"),
+                        humantime::format_duration(Duration::from_secs(time_left.as_secs())),
+                    );
+                    json!({
+                        "id": timer.id,
+                        "description": timer.description,
+                        "goes_off_at": timer.timestamp.to_rfc3339(),
+                        "time_left": humantime::format_duration(Duration::from_secs(time_left.as_secs())).to_string(),
+                    })
+                })
+                .collect();
+
+            Some(
+                json!({
+                    "matching_timers": matching,
+                    "timers": timers_json,
+                })
+                .to_string(),
+            )
         }
 
         "delete_timer_by_id" => {
@@ -413,19 +1286,559 @@ fn call_fn(fn_name: &str, fn_args: &str, llm_messages_tx: flume::Sender<Message>
                 Err(err) => Some(format!("Failed to delete timer with ID: {}. Error: {}", timer_id, err)),
             }
         }
-      
-        "show_live_log_stream" => match get_currently_active_log_file() {
-            Some(log_file) => match run_get_content_wait_on_file(&log_file) {
-                Ok(_) => Some("Successfully opened log file in powershell".to_string()),
-                Err(err) => Some(format!("Failed to open log file in powershell: {}", err)),
-            },
-            None => Some("No log files found".to_string()),
-        },
 
-        "set_clipboard" => {
-            let args = match serde_json::from_str::<serde_json::Value>(fn_args) {
-                Ok(json) => json,
-                Err(e) => return Some(format!("Failed to parse arguments: {}", e)),
+        "update_timer" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap();
+            let Some(timer_id) = args["timer_id"].as_u64() else {
+                return Some("Updating timer failed. timer_id is required.".to_string());
+            };
+            let new_description = args["new_description"].as_str().map(str::to_string);
+
+            let mut new_time = match args["new_time"].as_str() {
+                Some(time_str) => match time_str.parse::<DateTime<Local>>() {
+                    Ok(timestamp) => Some(timestamp),
+                    Err(err) => {
+                        return Some(format!(
+                            "Updating timer failed. \"{}\" isn't a valid rfc3339 time: {}",
+                            time_str, err
+                        ))
+                    }
+                },
+                None => None,
+            };
+            if let Some(push_back_by) = args["push_back_by"].as_str() {
+                let Some(duration) = parse_duration_arg(&args["push_back_by"]) else {
+                    return Some(format!(
+                        "Updating timer failed. \"{}\" isn't a valid duration. Try something like \"30 minutes\".",
+                        push_back_by
+                    ));
+                };
+                let current_time = match new_time {
+                    Some(time) => time,
+                    None => match get_timers().into_iter().find(|timer| timer.id == timer_id) {
+                        Some(timer) => timer.timestamp,
+                        None => return Some(format!("Updating timer failed. Timer with ID {} not found", timer_id)),
+                    },
+                };
+                new_time = match chrono::Duration::from_std(duration).ok().and_then(|duration| current_time.checked_add_signed(duration)) {
+                    Some(time) => Some(time),
+                    None => return Some(format!("Updating timer failed. \"{}\" is too long to push the timer back by.", push_back_by)),
+                };
+            }
+
+            println!("{}{}", "update_timer: ".purple(), timer_id);
+
+            match update_timer(timer_id, new_time, new_description) {
+                Ok(timer) => Some(format!(
+                    "Successfully updated timer. Timer_ID: \"{}\" Timer_description: \"{}\" goes off at time: \"{}\".",
+                    timer.id,
+                    timer.description,
+                    timer.timestamp.to_rfc3339(),
+                )),
+                Err(err) => Some(format!("Updating timer failed with error: {}", err)),
+            }
+        }
+
+        "get_upcoming_events" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap_or_default();
+            // Up to a year, so the time to look ahead can't overflow.
+            let days = args["days"].as_u64().unwrap_or(7).min(366);
+            let limit = args["limit"].as_u64().unwrap_or(10) as usize;
+
+            println!("{}{} days", "get_upcoming_events: ".purple(), days);
+
+            match ics::upcoming_events(Duration::from_secs(days * 24 * 60 * 60)) {
+                Ok(events) => {
+                    let mut info = format!("=== {} upcoming events ===\n", events.len());
+                    for event in events.iter().take(limit) {
+                        info.push_str(&describe_event(event));
+                        info.push('\n');
+                    }
+                    println!("{}", info);
+                    Some(info)
+                }
+                Err(err) => Some(format!("Failed to get upcoming events: {}", err)),
+            }
+        }
+
+        "play_music" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap_or_default();
+            let query = args["query"].as_str().filter(|query| !query.trim().is_empty());
+            let kind = match args["type"].as_str() {
+                Some(kind) => match spotify::Kind::from_name(kind) {
+                    Some(kind) => kind,
+                    None => return Some(format!("Unknown type \"{}\". Use \"track\", \"album\", \"artist\" or \"playlist\".", kind)),
+                },
+                None => spotify::Kind::Track,
+            };
+
+            println!("{}{}", "play_music: ".purple(), query.unwrap_or("resume"));
+
+            match spotify::play(query, kind) {
+                Ok(info) => Some(info),
+                Err(err) => Some(format!("Failed to play music: {:#}", err)),
+            }
+        }
+
+        "play_playlist" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap();
+            let name = args["name"].as_str().unwrap_or_default();
+
+            println!("{}{}", "play_playlist: ".purple(), name);
+
+            match spotify::play_playlist(name) {
+                Ok(info) => Some(info),
+                Err(err) => Some(format!("Failed to play playlist: {:#}", err)),
+            }
+        }
+
+        "pause_music" => {
+            println!("{}", "pause_music".purple());
+            match spotify::pause() {
+                Ok(()) => Some("Paused Spotify.".to_string()),
+                Err(err) => Some(format!("Failed to pause music: {:#}", err)),
+            }
+        }
+
+        "next_track" => {
+            println!("{}", "next_track".purple());
+            match spotify::next() {
+                Ok(()) => Some("Skipped to the next track on Spotify.".to_string()),
+                Err(err) => Some(format!("Failed to skip track: {:#}", err)),
+            }
+        }
+
+        "current_track" => {
+            println!("{}", "current_track".purple());
+            match spotify::current_track() {
+                Ok(info) => Some(info),
+                Err(err) => Some(format!("Failed to get the current track: {:#}", err)),
+            }
+        }
+
+        "get_location" => {
+            println!("{}", "get_location".purple());
+            match location::current() {
+                Ok((location, is_home)) => Some(format!(
+                    "The user is {} {} (latitude {:.2}, longitude {:.2}{}), going by {}.",
+                    if is_home { "in" } else { "roughly in" },
+                    location.name,
+                    location.latitude,
+                    location.longitude,
+                    location.timezone.as_deref().map(|timezone| format!(", timezone {}", timezone)).unwrap_or_default(),
+                    if is_home { "the home location they set" } else { "their IP address" }
+                )),
+                Err(err) => Some(format!("Failed to get the user's location: {:#}", err)),
+            }
+        }
+
+        "set_home_location" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap_or_default();
+            let place = args["place"].as_str().filter(|place| !place.trim().is_empty());
+            let coordinates = (args["latitude"].as_f64(), args["longitude"].as_f64());
+
+            println!("{}{}", "set_home_location: ".purple(), place.unwrap_or("coordinates"));
+
+            let location = match (place, coordinates) {
+                (name, (Some(latitude), Some(longitude))) => {
+                    if !(-90.0..=90.0).contains(&latitude) || !(-180.0..=180.0).contains(&longitude) {
+                        return Some("The latitude must be between -90 and 90, and the longitude between -180 and 180.".to_string());
+                    }
+                    Ok(location::Location {
+                        name: name.map(str::to_string).unwrap_or_else(|| format!("{:.2}, {:.2}", latitude, longitude)),
+                        latitude,
+                        longitude,
+                        timezone: None,
+                    })
+                }
+                (Some(place), _) => location::find(place),
+                (None, _) => return Some("Give either a place or both a latitude and a longitude.".to_string()),
+            };
+            match location.and_then(|location| {
+                location::set_home(location.clone())?;
+                Ok(location)
+            }) {
+                Ok(location) => Some(format!(
+                    "The home location is now {} (latitude {:.2}, longitude {:.2}), and it's saved in the config file.",
+                    location.name, location.latitude, location.longitude
+                )),
+                Err(err) => Some(format!("Failed to set the home location: {:#}", err)),
+            }
+        }
+
+        "get_weather" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap_or_default();
+            let units = match args["units"].as_str() {
+                Some(units) => match weather::Units::from_name(units) {
+                    Some(units) => units,
+                    None => return Some(format!("Unknown units \"{}\". Use \"metric\" or \"imperial\".", units)),
+                },
+                None => weather::Units::Metric,
+            };
+            let place = args["location"].as_str().filter(|place| !place.trim().is_empty());
+
+            println!("{}{}", "get_weather: ".purple(), place.unwrap_or("current location"));
+
+            let location = match place {
+                Some(place) => location::find(place),
+                None => location::current().map(|(location, _)| location),
+            };
+            match location.and_then(|location| weather::describe(&location, units)) {
+                Ok(info) => {
+                    println!("{}", info);
+                    Some(info)
+                }
+                Err(err) => Some(format!("Failed to get the weather: {:#}", err)),
+            }
+        }
+
+        "get_news_headlines" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap_or_default();
+            let topic = args["topic"].as_str().filter(|topic| !topic.trim().is_empty());
+            let count = args["count"].as_u64().unwrap_or(8) as usize;
+
+            println!("{}{}", "get_news_headlines: ".purple(), topic.unwrap_or("all"));
+
+            match news::headlines(topic, count) {
+                Ok(info) => Some(info),
+                Err(err) => Some(format!("Failed to get the news: {:#}", err)),
+            }
+        }
+        "translate" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap();
+            let text = args["text"].as_str().unwrap_or_default();
+            let target_lang = args["target_lang"].as_str().unwrap_or_default();
+            let speak = args["speak"].as_bool().unwrap_or(false);
+
+            println!("{}{}", "translate: ".purple(), target_lang);
+
+            let translation = match translate::translate(text, target_lang) {
+                Ok(translation) => translation,
+                Err(err) => return Some(format!("Failed to translate: {:#}", err)),
+            };
+            if !speak {
+                return Some(format!("Translation into {}: {}", target_lang, translation));
+            }
+            println!("{}", translation);
+            let mut speak_stream = speak_stream_mutex.lock().unwrap();
+            if !speak_stream.speak_in_language(&translation, target_lang) {
+                return Some(format!("Translation into {}: {}\nAI speech is muted, so it wasn't spoken. Tell the user the translation.", target_lang, translation));
+            }
+            Some(format!("Translation into {}: {}\nThe translation is being spoken aloud in {}. Don't repeat it. Only add something short if it helps, like how formal it is.", target_lang, translation, target_lang))
+        }
+        "wikipedia_summary" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap();
+            let query = args["query"].as_str().unwrap_or_default();
+            let language = args["language"].as_str().unwrap_or("en");
+
+            println!("{}{}", "wikipedia_summary: ".purple(), query);
+
+            match wikipedia::summary(query, language) {
+                Ok(info) => Some(info),
+                Err(err) => Some(format!("Failed to look up Wikipedia: {:#}", err)),
+            }
+        }
+        "list_containers" => {
+            println!("{}", "list_containers".purple());
+
+            match docker::list() {
+                Ok(info) => Some(info),
+                Err(err) => Some(format!("Failed to list the containers: {:#}", err)),
+            }
+        }
+        "restart_container" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap();
+            // A confirmed call restarts the container the user agreed to.
+            let held = match confirmation::take("restart_container", &args) {
+                Ok(held) => held,
+                Err(err) => return Some(format!("Failed to restart the container: {:#}", err)),
+            };
+            let confirmed = held.is_some();
+            let args = held.unwrap_or(args);
+            let name = args["name"].as_str().unwrap_or_default();
+
+            println!("{}{}", "restart_container: ".purple(), name);
+
+            let name = match docker::find(name) {
+                Ok(name) => name,
+                Err(err) => return Some(format!("Failed to restart the container: {:#}", err)),
+            };
+            if !confirmed {
+                let code = confirmation::request("restart_container", &json!({ "name": name }));
+                return Some(format!("Nothing was restarted yet. Ask the user to confirm that they want to restart the {} container, and wait for their answer. Only if they agree, call restart_container again with confirmation set to \"{}\".", name, code));
+            }
+            match docker::restart(&name) {
+                Ok(()) => Some(format!("Restarted the {} container.", name)),
+                Err(err) => Some(format!("Failed to restart the {} container: {:#}", name, err)),
+            }
+        }
+        "git_status" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap_or_default();
+            let repo_name = args["repo_name"].as_str().unwrap_or_default();
+
+            println!("{}{}", "git_status: ".purple(), repo_name);
+
+            match git::status(repo_name) {
+                Ok(info) => Some(info),
+                Err(err) => Some(format!("Failed to get the git status: {:#}", err)),
+            }
+        }
+        "summarize_recent_commits" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap_or_default();
+            let repo_name = args["repo_name"].as_str().unwrap_or_default();
+            let count = args["count"].as_u64().unwrap_or(10) as usize;
+
+            println!("{}{}", "summarize_recent_commits: ".purple(), repo_name);
+
+            match git::recent_commits(repo_name, count) {
+                Ok(info) => Some(info),
+                Err(err) => Some(format!("Failed to get the recent commits: {:#}", err)),
+            }
+        }
+        "get_exchange_rate" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap();
+            let from = args["from"].as_str().unwrap_or_default();
+            let to = args["to"].as_str().unwrap_or_default();
+            let amount = args["amount"].as_f64().unwrap_or(1.0);
+
+            println!("{}{} {} to {}", "get_exchange_rate: ".purple(), amount, from, to);
+
+            match finance::exchange_rate(from, to, amount) {
+                Ok(info) => Some(info),
+                Err(err) => Some(format!("Failed to get the exchange rate: {:#}", err)),
+            }
+        }
+        "get_stock_quote" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap();
+            let symbol = args["symbol"].as_str().unwrap_or_default();
+
+            println!("{}{}", "get_stock_quote: ".purple(), symbol);
+
+            match finance::stock_quote(symbol) {
+                Ok(info) => Some(info),
+                Err(err) => Some(format!("Failed to get the stock quote: {:#}", err)),
+            }
+        }
+
+        "snooze_alarm" => {
+            if !audible_timers.is_alarm_ringing() {
+                return Some("No alarm is ringing.".to_string());
+            }
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap();
+            let timer_id = args["timer_id"].as_u64();
+            let duration_str = args["duration"].as_str().unwrap_or("10 minutes");
+
+            let duration = match humantime::parse_duration(&duration_str.replace(" and ", " ")) {
+                Ok(duration) => duration,
+                Err(err) => {
+                    return Some(format!(
+                        "Snoozing failed. \"{}\" isn't a valid duration: {}. Try something like \"10 minutes\".",
+                        duration_str, err
+                    ))
+                }
+            }
+            .min(MAX_ALARM_WAIT);
+
+            println!("{}{}", "snooze_alarm: ".purple(), humantime::format_duration(duration));
+
+            match audible_timers.snooze(timer_id, duration) {
+                Ok(snoozed) if snoozed.is_empty() => Some("No matching alarm is ringing.".to_string()),
+                Ok(snoozed) => Some(format!(
+                    "Snoozed {} for {}.",
+                    describe_timers(&snoozed),
+                    humantime::format_duration(duration)
+                )),
+                Err(err) => Some(format!("Snoozing failed with error: {}", err)),
+            }
+        }
+
+        "dismiss_alarm" => {
+            if !audible_timers.is_alarm_ringing() {
+                return Some("No alarm is ringing.".to_string());
+            }
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap();
+            let timer_id = args["timer_id"].as_u64();
+
+            println!("{}{:?}", "dismiss_alarm: ".purple(), timer_id);
+
+            let dismissed = audible_timers.dismiss(timer_id);
+            if dismissed.is_empty() {
+                Some("No matching alarm is ringing.".to_string())
+            } else {
+                Some(format!("Dismissed {}.", describe_timers(&dismissed)))
+            }
+        }
+      
+        "add_reminder" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap();
+            let text = args["text"].as_str().unwrap_or_default();
+
+            println!("{}{}", "add_reminder: ".purple(), text);
+
+            match reminders::add_reminder(text) {
+                Ok(id) => Some(format!("Added reminder with ID {}: \"{}\"", id, text)),
+                Err(err) => Some(format!("Adding reminder failed with error: {}", err)),
+            }
+        }
+
+        "list_reminders" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap_or_default();
+            let include_completed = args["include_completed"].as_bool().unwrap_or(false);
+
+            match reminders::list_reminders(include_completed) {
+                Ok(reminders) => {
+                    let mut info = String::from("=== Reminders ===\n");
+                    for reminder in reminders {
+                        info.push_str(&format!(
+                            "Reminder_ID: \"{}\" Text: \"{}\" added: \"{}\"{}\n",
+                            reminder.id,
+                            reminder.text,
+                            reminder.created.to_rfc3339(),
+                            match reminder.completed {
+                                Some(completed) => format!(" completed: \"{}\"", completed.to_rfc3339()),
+                                None => String::new(),
+                            },
+                        ));
+                    }
+                    println!("{}", info);
+                    Some(info)
+                }
+                Err(err) => Some(format!("Listing reminders failed with error: {}", err)),
+            }
+        }
+
+        "complete_reminder" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap();
+            let Some(reminder_id) = args["reminder_id"].as_u64() else {
+                return Some("Completing reminder failed. reminder_id is required.".to_string());
+            };
+
+            println!("{}{}", "complete_reminder: ".purple(), reminder_id);
+
+            match reminders::complete_reminder(reminder_id) {
+                Ok(_) => Some(format!("Marked reminder with ID {} as done.", reminder_id)),
+                Err(err) => Some(format!("Completing reminder failed with error: {}", err)),
+            }
+        }
+
+        "start_stopwatch" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap();
+            let name = args["name"].as_str().unwrap_or("stopwatch");
+
+            println!("{}{}", "start_stopwatch: ".purple(), name);
+
+            match start_stopwatch(name) {
+                Ok(_) => Some(format!("Started the \"{}\" stopwatch.", name)),
+                Err(err) => Some(format!("Starting stopwatch failed with error: {}", err)),
+            }
+        }
+
+        "stop_stopwatch" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap();
+            let name = args["name"].as_str().unwrap_or("stopwatch");
+
+            println!("{}{}", "stop_stopwatch: ".purple(), name);
+
+            match stop_stopwatch(name) {
+                Ok(elapsed) => Some(format!(
+                    "Stopped the \"{}\" stopwatch at {}.",
+                    name,
+                    humantime::format_duration(Duration::from_secs(elapsed.as_secs()))
+                )),
+                Err(err) => Some(format!("Stopping stopwatch failed with error: {}", err)),
+            }
+        }
+
+        "check_stopwatches" => {
+            let stopwatches = get_stopwatches();
+            let mut info = String::from("=== Stopwatches ===\n");
+            for (name, elapsed, running) in stopwatches {
+                info.push_str(&format!(
+                    "Stopwatch: \"{}\" is at \"{}\" and is {}.\n",
+                    name,
+                    humantime::format_duration(Duration::from_secs(elapsed.as_secs())),
+                    if running { "running" } else { "stopped" },
+                ));
+            }
+
+            println!("{}", info);
+
+            Some(info)
+        }
+
+        "reset_stopwatch" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap();
+            let name = args["name"].as_str().unwrap_or("stopwatch");
+
+            println!("{}{}", "reset_stopwatch: ".purple(), name);
+
+            match reset_stopwatch(name) {
+                Ok(_) => Some(format!("Reset the \"{}\" stopwatch.", name)),
+                Err(err) => Some(format!("Resetting stopwatch failed with error: {}", err)),
+            }
+        }
+
+        "start_pomodoro" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap();
+            // Up to a day, so the phase's end time can't overflow.
+            let work_minutes = args["work_minutes"].as_u64().unwrap_or(25).min(24 * 60);
+            let break_minutes = args["break_minutes"].as_u64().unwrap_or(5).min(24 * 60);
+            let cycles = args["cycles"].as_u64().unwrap_or(4).min(u32::MAX as u64) as u32;
+
+            println!(
+                "{}{} min work, {} min break, {} cycles",
+                "start_pomodoro: ".purple(),
+                work_minutes,
+                break_minutes,
+                cycles
+            );
+
+            match pomodoro::start(
+                Duration::from_secs(work_minutes.saturating_mul(60)),
+                Duration::from_secs(break_minutes.saturating_mul(60)),
+                cycles,
+            ) {
+                Ok(_) => pomodoro::status(),
+                Err(err) => Some(format!("Starting pomodoro failed with error: {}", err)),
+            }
+        }
+
+        "pause_pomodoro" => match pomodoro::pause() {
+            Ok(_) => pomodoro::status(),
+            Err(err) => Some(format!("Pausing pomodoro failed with error: {}", err)),
+        },
+
+        "resume_pomodoro" => match pomodoro::resume() {
+            Ok(_) => pomodoro::status(),
+            Err(err) => Some(format!("Resuming pomodoro failed with error: {}", err)),
+        },
+
+        "stop_pomodoro" => {
+            if pomodoro::stop() {
+                Some("Pomodoro stopped.".to_string())
+            } else {
+                Some("No pomodoro is running.".to_string())
+            }
+        }
+
+        "check_pomodoro" => {
+            let status = pomodoro::status().unwrap_or_else(|| "No pomodoro is running.".to_string());
+            println!("{}", status);
+            Some(status)
+        }
+
+        "show_live_log_stream" => match get_currently_active_log_file() {
+            Some(log_file) => match run_get_content_wait_on_file(&log_file) {
+                Ok(_) => Some("Successfully opened log file in powershell".to_string()),
+                Err(err) => Some(format!("Failed to open log file in powershell: {}", err)),
+            },
+            None => Some("No log files found".to_string()),
+        },
+
+        "set_clipboard" => {
+            let args = match serde_json::from_str::<serde_json::Value>(fn_args) {
+                Ok(json) => json,
+                Err(e) => return Some(format!("Failed to parse arguments: {}", e)),
             };
 
             let clipboard_text = match args["clipboard_text"].as_str() {
@@ -438,10 +1851,511 @@ fn call_fn(fn_name: &str, fn_args: &str, llm_messages_tx: flume::Sender<Message>
                 Err(e) => return Some(format!("Failed to initialize clipboard: {}", e)),
             };
 
-            match clipboard.set_contents(clipboard_text.to_string()) {
-                Ok(_) => Some("Clipboard set successfully.".to_string()),
-                Err(e) => Some(format!("Failed to set clipboard contents: {}", e)),
+            match clipboard.set_contents(clipboard_text.to_string()) {
+                Ok(_) => Some("Clipboard set successfully.".to_string()),
+                Err(e) => Some(format!("Failed to set clipboard contents: {}", e)),
+            }
+        }
+
+        "calculate" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap();
+            let expression = args["expression"].as_str().unwrap_or_default();
+
+            println!("{}{}", "calculate: ".purple(), expression);
+
+            match calculator::evaluate(expression) {
+                Ok(value) => Some(format!("{} = {}", expression, calculator::format_number(value))),
+                Err(err) => Some(format!("Failed to calculate {}: {:#}", expression, err)),
+            }
+        }
+        "convert_units" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap();
+            let value = args["value"].as_f64().unwrap_or_default();
+            let from = args["from"].as_str().unwrap_or_default();
+            let to = args["to"].as_str().unwrap_or_default();
+
+            println!("{}{} {} to {}", "convert_units: ".purple(), value, from, to);
+
+            match units::convert(value, from, to) {
+                Ok((result, from, to)) => Some(format!(
+                    "{} {} = {} {}",
+                    calculator::format_number(value),
+                    from,
+                    calculator::format_number(result),
+                    to
+                )),
+                Err(err) => Some(format!("Failed to convert units: {:#}", err)),
+            }
+        }
+
+        "append_note" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap();
+            let text = args["text"].as_str().unwrap_or_default();
+
+            println!("{}{}", "append_note: ".purple(), text);
+
+            match notes::append(text) {
+                Ok(path) => Some(format!("Added the note to {}.", path.display())),
+                Err(err) => Some(format!("Failed to add note: {:#}", err)),
+            }
+        }
+        "read_todays_notes" => {
+            println!("{}", "read_todays_notes".purple());
+
+            let today = Local::now().date_naive();
+            match notes::read(today) {
+                Ok(Some(text)) => Some(format!("=== Today's notes ===\n{}", truncate(&text, 20_000))),
+                Ok(None) => Some("There are no notes for today yet.".to_string()),
+                Err(err) => Some(format!("Failed to read notes: {:#}", err)),
+            }
+        }
+
+        "send_email" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap();
+            // A confirmed call sends the email that was read back to the user.
+            let held = match confirmation::take("send_email", &args) {
+                Ok(held) => held,
+                Err(err) => return Some(format!("Failed to send email: {:#}", err)),
+            };
+            let confirmed = held.is_some();
+            let args = held.unwrap_or(args);
+            let to = args["to"].as_str().unwrap_or_default();
+            let subject = args["subject"].as_str().unwrap_or_default();
+            let body = args["body"].as_str().unwrap_or_default();
+
+            println!("{}{}: {}", "send_email: ".purple(), to, subject);
+
+            if !email::is_configured() {
+                return Some("Email isn't set up. Tell the user to add an [email] section to the config file, then run `quick-assistant set-email-password`.".to_string());
+            }
+            if let Err(err) = email::parse_recipients(to) {
+                return Some(format!("Failed to send email: {:#}", err));
+            }
+            if !confirmed {
+                let code = confirmation::request("send_email", &args);
+                return Some(format!("Nothing was sent yet. Read who it's to, the subject and the email back to the user, ask them to confirm, and wait for their answer. Only if they agree, call send_email again with confirmation set to \"{}\". To change the email, call send_email again without it.", code));
+            }
+            match email::send(to, subject, body) {
+                Ok(()) => Some(format!("Sent the email to {}.", to)),
+                Err(err) => Some(format!("Failed to send email: {:#}", err)),
+            }
+        }
+
+        "take_screenshot" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap_or_default();
+            let region = args["region"].as_str().unwrap_or("screen");
+
+            println!("{}{}", "take_screenshot: ".purple(), region);
+
+            let region = match screenshot::Region::parse(region) {
+                Ok(region) => region,
+                Err(err) => return Some(format!("Failed to take screenshot: {:#}", err)),
+            };
+            match screenshot::take(region) {
+                Ok(screenshot) => match screenshot.clipboard_error {
+                    None => Some(format!("Saved the screenshot to {} and copied it to the clipboard.", screenshot.path.display())),
+                    Some(err) => Some(format!("Saved the screenshot to {}, but couldn't copy it to the clipboard: {:#}", screenshot.path.display(), err)),
+                },
+                Err(err) => Some(format!("Failed to take screenshot: {:#}", err)),
+            }
+        }
+
+        "get_clipboard" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap_or_default();
+
+            println!("{}", "get_clipboard".purple());
+
+            if CONFIRM_CLIPBOARD_READS.get().copied().unwrap_or(false) {
+                match confirmation::take("get_clipboard", &args) {
+                    Ok(Some(_)) => {}
+                    Ok(None) => {
+                        let code = confirmation::request("get_clipboard", &args);
+                        return Some(format!("The user wants to be asked before their clipboard is read. Ask them, and wait for their answer. Only if they agree, call get_clipboard again with confirmation set to \"{}\".", code));
+                    }
+                    Err(err) => return Some(format!("Failed to read the clipboard: {:#}", err)),
+                }
+            }
+
+            let mut clipboard: ClipboardContext = match ClipboardProvider::new() {
+                Ok(c) => c,
+                Err(e) => return Some(format!("Failed to initialize clipboard: {}", e)),
+            };
+
+            match clipboard.get_contents() {
+                Ok(text) if text.trim().is_empty() => Some("The clipboard is empty, or doesn't hold text.".to_string()),
+                Ok(text) => {
+                    let length = text.chars().count();
+                    let mut info = format!("=== Clipboard ({} characters) ===\n{}\n", length, truncate(&text, MAX_CLIPBOARD_CHARS));
+                    if length > MAX_CLIPBOARD_CHARS {
+                        info.push_str(&format!("Only the first {} characters are shown.\n", MAX_CLIPBOARD_CHARS));
+                    }
+                    Some(info)
+                }
+                Err(e) => Some(format!("Failed to get clipboard contents: {}", e)),
+            }
+        }
+
+        "type_text" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap();
+            // A confirmed call types the text the user agreed to.
+            let held = match confirmation::take("type_text", &args) {
+                Ok(held) => held,
+                Err(err) => return Some(format!("Failed to type the text: {:#}", err)),
+            };
+            let confirmed = held.is_some();
+            let args = held.unwrap_or(args);
+            let text = args["text"].as_str().unwrap_or_default();
+
+            println!("{}{}", "type_text: ".purple(), truncate(text, 80));
+
+            if text.is_empty() {
+                return Some("There's no text to type.".to_string());
+            }
+            if text.chars().count() > MAX_TYPED_CHARS {
+                return Some(format!("That text is too long to type. The limit is {} characters. Use set_clipboard instead, so the user can paste it.", MAX_TYPED_CHARS));
+            }
+            if !confirmed {
+                let code = confirmation::request("type_text", &args);
+                return Some(format!("Nothing was typed yet. Tell the user what you're about to type, briefly, ask them to confirm, and wait for their answer. Only if they agree, call type_text again with confirmation set to \"{}\".", code));
+            }
+
+            enigo.key_sequence(text);
+            Some("Typed the text into the focused app.".to_string())
+        }
+
+        "set_ai_volume" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap();
+            let volume = match args["volume"].as_u64() {
+                Some(volume) if volume <= 100 => volume,
+                _ => return Some("Volume must be a number between 0 and 100.".to_string()),
+            };
+
+            println!("{}{}", "set_ai_volume: ".purple(), volume);
+
+            let mut speak_stream = speak_stream_mutex.lock().unwrap();
+            speak_stream.set_volume(volume as f32 / 100.0);
+            drop(speak_stream);
+            settings::update(|saved| saved.ai_volume = Some(volume as u32));
+
+            Some(format!("AI voice volume set to {}%", volume))
+        }
+
+        "skip_sentence" => {
+            speak_stream_mutex.lock().unwrap().skip_sentence();
+            None
+        }
+
+        "pause_speech" => {
+            speak_stream_mutex.lock().unwrap().pause_speech();
+            None
+        }
+
+        "resume_speech" => {
+            speak_stream_mutex.lock().unwrap().resume_speech();
+            None
+        }
+
+        "set_tick" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap();
+            let mut settings = tick::settings();
+
+            if let Some(sound) = args["sound"].as_str() {
+                settings.sound = match sound {
+                    "tick" => tick::TickSound::Tick,
+                    "typing" => tick::TickSound::Typing,
+                    "off" => tick::TickSound::Off,
+                    _ => return Some(format!("Unknown thinking sound: {}", sound)),
+                };
+            }
+            if let Some(volume) = args["volume"].as_u64() {
+                if volume > 100 {
+                    return Some("Volume must be a number between 0 and 100.".to_string());
+                }
+                settings.volume = volume as f32 / 100.0;
+            }
+            if let Some(interval_ms) = args["interval_ms"].as_u64() {
+                settings.interval = Duration::from_millis(interval_ms.max(100));
+            }
+
+            println!("{}{:?}", "set_tick: ".purple(), settings);
+            let result = format!(
+                "Thinking sound set to {:?} at {}% volume, every {}ms",
+                settings.sound,
+                (settings.volume * 100.0).round(),
+                settings.interval.as_millis()
+            );
+            settings::update(|saved| {
+                saved.set_tick_sound(settings.sound);
+                saved.tick_volume = Some((settings.volume * 100.0).round() as u32);
+                saved.tick_interval_ms = Some(settings.interval.as_millis() as u64);
+            });
+            tick::configure(settings);
+            Some(result)
+        }
+
+        "save_last_response_audio" => {
+            println!("{}", "save_last_response_audio".purple());
+            let speak_stream = speak_stream_mutex.lock().unwrap();
+            match speak_stream.save_last_response_audio(&SAVED_AUDIO_DIR) {
+                Ok(path) => Some(format!("Saved the last response's audio to {}", path.display())),
+                Err(err) => Some(format!("Failed to save the last response's audio: {:#}", err)),
+            }
+        }
+
+        "mute_speech" => {
+            println!("{}", "mute_speech".purple());
+            speak_stream_mutex.lock().unwrap().set_muted(true);
+            settings::update(|saved| saved.muted = Some(true));
+            Some("AI speech muted. Responses will only be shown as text.".to_string())
+        }
+
+        "unmute_speech" => {
+            println!("{}", "unmute_speech".purple());
+            speak_stream_mutex.lock().unwrap().set_muted(false);
+            settings::update(|saved| saved.muted = Some(false));
+            Some("AI speech unmuted.".to_string())
+        }
+
+        "pause_listening" => {
+            println!("{}", "pause_listening".purple());
+            listening_pause::set_paused(true);
+            Some("Listening paused. The push to talk key is ignored and nothing is said unprompted until listening is resumed. Timers only show a notification.".to_string())
+        }
+
+        "resume_listening" => {
+            println!("{}", "resume_listening".purple());
+            listening_pause::set_paused(false);
+            Some("Listening resumed.".to_string())
+        }
+
+        "export_conversation" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap();
+            let format = match args["format"].as_str() {
+                Some(format) => match export::Format::from_name(format) {
+                    Some(format) => format,
+                    None => return Some(format!("Unknown format \"{}\". Use \"markdown\" or \"html\".", format)),
+                },
+                None => export::Format::default(),
+            };
+            let folder = match args["folder"].as_str() {
+                Some(folder) => PathBuf::from(folder),
+                None => export::default_folder(),
+            };
+
+            println!("{} {:?}", "export_conversation".purple(), format);
+
+            let Some(session) = sessions::current() else {
+                return Some("Nothing has been said yet, so there's no conversation to export.".to_string());
+            };
+            match export::export(&session, format, &folder) {
+                Ok(path) => {
+                    if let Err(err) = open::that(&path) {
+                        warn!("Failed to open transcript: {}", err);
+                    }
+                    Some(format!("Exported the conversation to {} and opened it.", path.display()))
+                }
+                Err(err) => Some(format!("Failed to export the conversation: {:#}", err)),
+            }
+        }
+
+        "exit_assistant" => {
+            println!("{}", "exit_assistant".purple());
+            shutdown::request();
+            // There's nobody left to hear a reply.
+            None
+        }
+
+        "repeat_last_response" => {
+            let mut speak_stream = speak_stream_mutex.lock().unwrap();
+            if speak_stream.is_muted() {
+                Some("AI speech is muted. Unmute it to hear the last response.".to_string())
+            } else if speak_stream.repeat_last_response() {
+                None
+            } else {
+                Some("There is no previous response to repeat.".to_string())
+            }
+        }
+
+        "set_ai_voice" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap_or_default();
+            let voice = match args["voice"]
+                .as_str()
+                .map(|voice| <VoiceEnum as clap::ValueEnum>::from_str(voice, true))
+            {
+                Some(Ok(voice)) => voice,
+                _ => return Some("Voice must be one of alloy, ash, coral, echo, fable, onyx, nova, sage, or shimmer.".to_string()),
+            };
+
+            println!("{}{}", "set_ai_voice: ".purple(), voice_to_str(&voice));
+
+            speak_stream_mutex.lock().unwrap().set_voice(voice.clone());
+            settings::update(|saved| saved.set_ai_voice(&voice));
+            Some(format!("AI voice set to {}", voice_to_str(&voice)))
+        }
+
+        "set_speech_speed" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap_or_default();
+            let speed = match args["speed"].as_f64() {
+                Some(speed) if (0.5..=100.0).contains(&speed) => speed as f32,
+                _ => return Some("Speed must be a number between 0.5 and 100.".to_string()),
+            };
+
+            println!("{}{}", "set_speech_speed: ".purple(), speed);
+
+            speak_stream_mutex.lock().unwrap().set_speech_speed(speed);
+            settings::update(|saved| saved.speech_speed = Some(speed));
+            Some(format!("AI speech speed set to {}x", speed))
+        }
+
+        "get_ai_volume" => {
+            let speak_stream = speak_stream_mutex.lock().unwrap();
+            let volume = (speak_stream.volume() * 100.0).round();
+            drop(speak_stream);
+
+            Some(format!("AI voice volume is {}%", volume))
+        }
+
+        "message_contact" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap();
+            let name = args["name"].as_str().unwrap_or_default();
+            let text = args["text"].as_str().unwrap_or_default();
+            let via = match args["via"].as_str() {
+                Some(via) => match contacts::Via::from_name(via) {
+                    Some(via) => Some(via),
+                    None => return Some("via must be whatsapp, sms or email.".to_string()),
+                },
+                None => None,
+            };
+
+            println!("{}{}", "message_contact: ".purple(), name);
+
+            match contacts::message(name, text, via) {
+                Ok((name, via)) => Some(format!("Opened {} to {} with the message written out. It hasn't been sent. The user has to press send.", via.name(), name)),
+                Err(err) => Some(format!("Failed to message the contact: {:#}", err)),
+            }
+        }
+        "run_command" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap();
+            let name = args["name"].as_str().unwrap_or_default();
+            let values = args["args"].as_object().cloned().unwrap_or_default();
+
+            println!("{}{}", "run_command: ".purple(), name);
+
+            match commands::run(name, &values) {
+                Ok(info) => Some(info),
+                Err(err) => Some(format!("Failed to run the command: {:#}", err)),
+            }
+        }
+        "run_routine" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap();
+            let name = args["name"].as_str().unwrap_or_default();
+
+            println!("{}{}", "run_routine: ".purple(), name);
+
+            let (name, steps) = match routines::steps(name) {
+                Ok(routine) => routine,
+                Err(err) => return Some(format!("Failed to run the routine: {:#}", err)),
+            };
+            let results = routines::run(&steps, |fn_name, fn_args| {
+                call_routine_step(fn_name, fn_args, llm_messages_tx.clone(), speak_stream_mutex, audible_timers)
+            });
+            Some(format!("Ran the {} routine. Briefly tell the user how it went. Each step's result:\n{}", name, results))
+        }
+        "save_routine" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap();
+            let name = args["name"].as_str().unwrap_or_default();
+            let description = args["description"].as_str();
+            let steps: Vec<config::RoutineStep> = args["steps"].as_array().into_iter().flatten().filter_map(|step| {
+                Some(config::RoutineStep { tool: step["tool"].as_str()?.to_string(), args: step["args"].clone() })
+            }).collect();
+
+            println!("{}{}", "save_routine: ".purple(), name);
+
+            match routines::save(name, description, steps, tool_parameters) {
+                Ok(true) => Some(format!("Replaced the {} routine.", name.trim())),
+                Ok(false) => Some(format!("Saved the {} routine. The user can run it by asking for it by name.", name.trim())),
+                Err(err) => Some(format!("Failed to save the routine: {:#}", err)),
+            }
+        }
+        "switch_profile" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap_or_default();
+            let Some(name) = args["name"].as_str() else {
+                return Some("A profile name is required.".to_string());
+            };
+
+            println!("{}{}", "switch_profile: ".purple(), name);
+
+            let profile = match profiles::switch(name) {
+                Ok(profile) => profile,
+                Err(err) => return Some(format!("Failed to switch profile: {:?}", err)),
+            };
+            if let Some(voice) = profile.voice.clone() {
+                speak_stream_mutex.lock().unwrap().set_voice(voice);
+            }
+
+            let mut changes = Vec::new();
+            if let Some(model) = &profile.model {
+                changes.push(format!("model {}", model));
+            }
+            if let Some(voice) = &profile.voice {
+                changes.push(format!("voice {}", voice_to_str(voice)));
+            }
+            if let Some(ptt_key) = &profile.ptt_key {
+                changes.push(format!("push to talk key {:?}", ptt_key));
+            }
+            if let Some(tools) = &profile.tools {
+                changes.push(format!("tools {}", tools.join(", ")));
+            }
+            if changes.is_empty() {
+                Some(format!("Switched to profile {}.", profile.name))
+            } else {
+                Some(format!(
+                    "Switched to profile {}, which uses {}. The new model and tools apply from the next message.",
+                    profile.name,
+                    changes.join("; ")
+                ))
+            }
+        }
+
+        "set_output_device" => {
+            let args: serde_json::Value = serde_json::from_str(fn_args).unwrap_or_default();
+            // All channels when no channel is given.
+            let channel = match args.get("channel") {
+                Some(channel) => match serde_json::from_value::<Channel>(channel.clone()) {
+                    Ok(channel) => Some(channel),
+                    Err(_) => return Some("Channel must be speech, alerts, or ui.".to_string()),
+                },
+                None => None,
+            };
+            // The default device when no device is given.
+            let device = args["device"]
+                .as_str()
+                .filter(|device| !device.is_empty())
+                .map(str::to_string);
+
+            println!(
+                "{}{}",
+                "set_output_device: ".purple(),
+                device.as_deref().unwrap_or("default")
+            );
+
+            let channels = match channel {
+                Some(channel) => vec![channel],
+                None => vec![Channel::Speech, Channel::Alerts, Channel::Ui],
+            };
+            for channel in channels {
+                if let Err(err) = audio::set_output_device(channel, device.clone()) {
+                    return Some(format!("Failed to set output device: {:?}", err));
+                }
             }
+
+            let routing: Vec<String> = audio::output_devices()
+                .into_iter()
+                .map(|(channel, device)| {
+                    format!("{:?}: {}", channel, device.as_deref().unwrap_or("default device"))
+                })
+                .collect();
+            Some(format!("Output devices set. {}", routing.join(", ")))
         }
 
         _ => {
@@ -488,17 +2402,16 @@ fn set_up_logging(logs_dir: &Path) -> WorkerGuard {
     guard
 }
 
-static LOGS_DIR: LazyLock<PathBuf> = LazyLock::new(|| {
-    dirs::cache_dir()
+static LOGS_DIR: LazyLock<PathBuf> = LazyLock::new(|| CACHE_DIR.join("logs"));
+
+static SAVED_AUDIO_DIR: LazyLock<PathBuf> = LazyLock::new(|| {
+    dirs::audio_dir()
+        .or_else(dirs::home_dir)
         .unwrap()
         .join("quick-assistant")
-        .join("logs")
 });
 
-static CACHE_DIR: LazyLock<PathBuf> =
-    LazyLock::new(|| dirs::cache_dir().unwrap().join("quick-assistant"));
-
-use sysinfo::{Components, Disks, Networks, System};
+use sysinfo::{Components, Networks, System};
 
 fn get_system_info() -> String {
     let mut info = String::new();
@@ -538,10 +2451,7 @@ fn get_system_info() -> String {
 
     // Disks information:
     info.push_str("=> disks:\n");
-    let disks = Disks::new_with_refreshed_list();
-    for disk in &disks {
-        info.push_str(&format!("{:?}\n", disk));
-    }
+    info.push_str(&disks::describe());
 
     // Network interfaces information:
     info.push_str("=> networks:\n");
@@ -565,38 +2475,6 @@ fn get_system_info() -> String {
     info
 }
 
-fn get_system_processes() -> String {
-    let mut info = String::new();
-
-    let mut sys = System::new_all();
-
-    sys.refresh_all();
-
-    // Add "=> processes:" to info
-    info.push_str("=> processes:\n");
-
-
-    // Display processes ID, name, and disk usage:
-    for (pid, process) in sys.processes() {
-        let path_string = match process.exe() {
-            Some(path) => path.to_string_lossy().to_string(),
-            None => "Unknown".to_string(),
-        };
-        info.push_str(&format!(
-            "[{}] {:?} start_time: {:?} runtime: {} status: {} cpu_usage: {} directory: {}\n",
-            pid,
-            process.name(),
-            process.start_time(),
-            process.run_time(),
-            process.status(),
-            process.cpu_usage(),
-            path_string,
-        ));
-    }
-
-    info
-}
-
 /// returns a list of unique process names on the system.
 fn get_process_names() -> Vec<String> {
     let sys = System::new_all();
@@ -695,54 +2573,1982 @@ fn run_get_content_wait_on_file(file_path: &Path) -> Result<String, String> {
     }
 }
 
+/// The most clipboard text get_clipboard gives the AI, so a huge copy doesn't fill the conversation.
+const MAX_CLIPBOARD_CHARS: usize = 20_000;
+
+/// The most text type_text types, so a runaway response can't type for minutes.
+const MAX_TYPED_CHARS: usize = 5_000;
+
+/// Whether the AI has to ask the user before reading the clipboard, from the config file.
+static CONFIRM_CLIPBOARD_READS: OnceLock<bool> = OnceLock::new();
+
+static SOUND_THEME: OnceLock<SoundTheme> = OnceLock::new();
+
+/// Plays one of the sounds from the sound theme, unless it's turned off.
+fn play_sound(sound: Sound) {
+    if let Some(path) = SOUND_THEME.get().and_then(|theme| theme.path(sound)) {
+        PLAY_AUDIO(path, sound.pan());
+    }
+}
+
+/// A global, lazily-initialized closure for sending paths, and where to pan them, into a channel.
+static PLAY_AUDIO: LazyLock<Box<dyn Fn(&Path, f32) + Send + Sync>> = LazyLock::new(|| {
+    // Create a channel for path buffers.
+    let (audio_playing_tx, audio_playing_rx) = flume::unbounded::<(PathBuf, f32)>();
+
+    // Create the audio playing thread
+    // Playing audio has it's own dedicated thread because I wanted to be able to play audio
+    // by passing an audio file path to a function. But the audio playing function needs to
+    // have the sink and stream variable not be dropped after the end of the function.
+    thread::spawn( move || {
+        // The sound playing now, which is cut off when the next one starts.
+        let mut sink: Option<Arc<dyn audio::AudioSink>> = None;
+
+        for (audio_path, pan) in audio_playing_rx.iter() {
+            // Sounds can be user provided files, so failing to play one shouldn't take down this thread.
+            let decoder = match std::fs::File::open(&audio_path)
+                .map_err(anyhow::Error::from)
+                .and_then(|file| Ok(rodio::Decoder::new(BufReader::new(file))?))
+            {
+                Ok(decoder) => decoder,
+                Err(err) => {
+                    warn!("Failed to play {}: {}", audio_path.display(), err);
+                    continue;
+                }
+            };
+            if let Some(sink) = sink.take() {
+                sink.stop();
+            }
+            // Each sound gets a new sink, so it plays on the ui channel's current output device.
+            let new_sink = audio::new_sink(Channel::Ui);
+            new_sink.append(audio::pan(decoder, pan));
+            sink = Some(new_sink);
+        }
+    });
+
+    // Return our closure, capturing the sending side of the channel.
+    Box::new(move |path: &Path, pan: f32| {
+        audio_playing_tx.send((path.to_path_buf(), pan)).unwrap();
+    })
+});
+
+/// How often the microphone level is reported while recording, for overlays that show it.
+const LEVEL_METER_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Asks the assistant that's already running `question`, and prints its answer as it comes.
+/// `events` follows its conversation, and should be subscribed to before asking, so nothing is missed.
+fn ask_running_instance(
+    question: &str,
+    events: impl Iterator<Item = ConversationEvent>,
+) -> Result<(), anyhow::Error> {
+    instance::send_message(question)?;
+    // Anything the assistant was already saying is ignored, until the question shows up.
+    let mut answering = false;
+    for event in events {
+        match event {
+            ConversationEvent::User { text } if text.trim() == question.trim() => answering = true,
+            ConversationEvent::Token { text } if answering => {
+                print!("{}", text);
+                stdout().flush()?;
+            }
+            ConversationEvent::ResponseDone if answering => {
+                println!();
+                return Ok(());
+            }
+            _ => {}
+        }
+    }
+    anyhow::bail!("The assistant exited before it answered")
+}
+
+/// Reports the microphone level to the conversation until the returned sender is dropped.
+fn spawn_level_meter(level: rec::InputLevel) -> flume::Sender<()> {
+    let (stop_tx, stop_rx) = flume::bounded::<()>(0);
+    thread::spawn(move || {
+        while let Err(flume::RecvTimeoutError::Timeout) = stop_rx.recv_timeout(LEVEL_METER_INTERVAL) {
+            conversation::emit(ConversationEvent::ListeningLevel { level: level.get() });
+        }
+    });
+    stop_tx
+}
+
+
+/// The parameters of every tool, by name. Building every schema is slow, so it's only done once.
+#[allow(deprecated)]
+static TOOL_PARAMETERS: LazyLock<HashMap<String, serde_json::Value>> = LazyLock::new(|| {
+    tool_functions()
+        .into_iter()
+        .map(|function| (function.name, function.parameters))
+        .collect()
+});
+
+/// The parameters of the tool named `name`, or `None` if the AI has no such tool.
+fn tool_parameters(name: &str) -> Option<&'static serde_json::Value> {
+    TOOL_PARAMETERS.get(name)
+}
+
+/// Calls a routine's step, unless the active profile doesn't enable its tool.
+fn call_routine_step(
+    fn_name: &str,
+    fn_args: &str,
+    llm_messages_tx: flume::Sender<Message>,
+    speak_stream_mutex: &Arc<Mutex<SpeakStream>>,
+    audible_timers: &AudibleTimers,
+) -> Option<String> {
+    if !profiles::is_enabled(fn_name) {
+        return Some(format!("The active profile doesn't allow {}, so it was skipped.", fn_name));
+    }
+    call_fn(fn_name, fn_args, llm_messages_tx, speak_stream_mutex, audible_timers)
+}
+
+/// Every tool the AI can call.
+#[allow(deprecated)]
+fn tool_functions() -> Vec<ChatCompletionFunctions> {
+    vec![
+        ChatCompletionFunctionsArgs::default()
+            .name("set_screen_brightness")
+            .description("Sets the brightness of the device's screen.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "brightness": {
+                        "type": "integer",
+                        "description": "The brightness of the screen. A number between 0 and 100.",
+                    },
+                },
+                "required": ["brightness"],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("get_screen_brightness")
+            .description("Gets the screen's brightness, from 0 to 100.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {},
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("media_controls")
+            .description("Plays/Pauses/Seeks media.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "media_button": { "type": "string", "enum": ["MediaStop", "MediaNextTrack", "MediaPlayPause", "MediaPrevTrack", "VolumeUp", "VolumeDown", "VolumeMute"] },
+                },
+                "required": ["media_button"],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("open_application")
+            .description("Opens an installed application. The closest match to the name is opened, and its full name is returned.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "application": {
+                        "type": "string",
+                        "description": "The application's name, like \"Firefox\" or \"calculator\".",
+                    },
+                },
+                "required": ["application"],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("list_open_windows")
+            .description("Lists the open windows' titles and applications.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {},
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("focus_window")
+            .description("Switches to an open window, like \"switch to Chrome\". Use open_application instead if it isn't open.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "title": {
+                        "type": "string",
+                        "description": "Part of the window's title or its application's name, like \"chrome\" or \"budget.xlsx\".",
+                    },
+                },
+                "required": ["title"],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("minimize_window")
+            .description("Minimizes a window.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "title": {
+                        "type": "string",
+                        "description": "Optional. Part of the window's title or its application's name. Defaults to the focused window.",
+                    },
+                },
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("close_window")
+            .description("Closes a window. Its application can still ask to save first.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "title": {
+                        "type": "string",
+                        "description": "Optional. Part of the window's title or its application's name. Defaults to the focused window.",
+                    },
+                },
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("search_files")
+            .description("Finds files by name in the user's documents, downloads, desktop, music, pictures and videos, or the folders they've allowed. Hidden folders are skipped.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "pattern": {
+                        "type": "string",
+                        "description": "Part of the file name, like \"invoice\", or a glob like \"*invoice*.pdf\". Case doesn't matter.",
+                    },
+                    "root": {
+                        "type": "string",
+                        "description": "Optional. The folder to search, like \"downloads\", \"documents\" or a path. Defaults to every allowed folder.",
+                    },
+                    "limit": {
+                        "type": "integer",
+                        "description": "Optional. The most files to return, up to 50. Defaults to 10.",
+                    },
+                },
+                "required": ["pattern"],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("read_file_aloud")
+            .description("Reads a text or markdown file aloud to the user, like their notes, or summarizes it for them. Only files in the folders search_files looks in can be read, up to 100 KB.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "The file's full path, like one from search_files. It can start with ~.",
+                    },
+                    "summarize": {
+                        "type": "boolean",
+                        "description": "Optional. Returns the file's text for you to summarize, instead of reading all of it aloud. Defaults to false.",
+                    },
+                },
+                "required": ["path"],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("open_url")
+            .description("Opens a web page in the user's default browser, like \"open github\".")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "url": {
+                        "type": "string",
+                        "description": "The page's address, like \"https://github.com\" or \"youtube.com\".",
+                    },
+                    "confirmation": {
+                        "type": "string",
+                        "description": "Optional. The code this tool gave when it asked the user, once they've agreed. Leave this out the first time.",
+                    },
+                },
+                "required": ["url"],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("open_path")
+            .description("Opens a folder in the file browser, or a document, picture, song or video in its default app, like \"open my downloads folder\". Other files, like programs and scripts, can't be opened this way.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "A folder name like \"downloads\", \"documents\", \"desktop\", \"music\", \"pictures\", \"videos\" or \"home\", or a full path, which can start with ~.",
+                    },
+                    "confirmation": {
+                        "type": "string",
+                        "description": "Optional. The code this tool gave when it asked the user, once they've agreed. Leave this out the first time.",
+                    },
+                },
+                "required": ["path"],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("open_logs_folder")
+            .description("Opens this program's logging folder in the default file browser for the user to see.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {},
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("sysinfo")
+            .description("Returns this system's information.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {},
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("summarize_active_window")
+            .description("Reads the title and text of the window the user is looking at, like an article or document, so it can be summarized or asked about. Use this for questions like \"what is this article about?\"")
+            .parameters(json!({
+                "type": "object",
+                "properties": {},
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("list_bluetooth_devices")
+            .description("Lists the Bluetooth devices paired with this computer and whether each is connected.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {},
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("connect_bluetooth_device")
+            .description("Connects a paired Bluetooth device, like headphones or a speaker. If it's an audio device, speech moves over to it.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "name": {
+                        "type": "string",
+                        "description": "The device's name, or part of it, like \"headphones\" or \"WH-1000XM4\".",
+                    },
+                },
+                "required": ["name"],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("lock_screen")
+            .description("Locks the computer's screen.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {},
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("sleep_computer")
+            .description("Puts the computer to sleep straight away.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {},
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("shutdown_computer")
+            .description("Shuts down or restarts the computer, now or after a delay. The user has to agree to it first.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "delay_minutes": {
+                        "type": "integer",
+                        "description": "Optional. How many minutes to wait first, up to a day (1440). Defaults to 0, which is now.",
+                    },
+                    "restart": {
+                        "type": "boolean",
+                        "description": "Optional. Restart instead of shutting down. Defaults to false.",
+                    },
+                    "confirmation": {
+                        "type": "string",
+                        "description": "Optional. The code this tool gave when it asked the user, once they've agreed. Leave this out the first time.",
+                    },
+                },
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("cancel_shutdown")
+            .description("Cancels a shutdown or restart that was scheduled for later.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {},
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("set_do_not_disturb")
+            .description("Turns the system's do not disturb mode on or off, so notifications stop popping up.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "on": {
+                        "type": "boolean",
+                        "description": "True to turn do not disturb on, false to turn it off.",
+                    },
+                },
+                "required": ["on"],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("top_processes")
+            .description("Returns the processes using the most CPU or memory, with their pid, name, CPU and memory use.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "sort_by": {
+                        "type": "string",
+                        "enum": ["cpu", "memory"],
+                        "description": "Optional. Defaults to cpu.",
+                    },
+                    "count": {
+                        "type": "integer",
+                        "description": "Optional. How many processes to return, up to 25. Defaults to 10.",
+                    },
+                },
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("get_process_details")
+            .description("Returns details about a running process, like when it started, its CPU and memory use, its executable, command line and parent. Describes every process whose name matches, with their totals.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "name_or_pid": {
+                        "type": "string",
+                        "description": "A pid, or part of a process name like \"chrome\".",
+                    },
+                },
+                "required": ["name_or_pid"],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("notify_when_process_exits")
+            .description("Watches a running process, like a render or a build, and tells the user when it finishes. Returns straight away.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "name_or_pid": {
+                        "type": "string",
+                        "description": "A pid, or part of a process name like \"blender\". Every matching process is waited on.",
+                    },
+                    "description": {
+                        "type": "string",
+                        "description": "Optional. What the process is doing, in the user's words, like \"your render job\".",
+                    },
+                },
+                "required": ["name_or_pid"],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("kill_processes_with_name")
+            .description("Kills all processes with a given name. ALWAYS call \"top_processes\" or \"get_process_details\" first to get the name of the process you want to kill.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "process_name": { "type": "string" },
+                },
+                "required": ["process_name"],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("speedtest")
+            .description("Runs an internet speedtest and returns the results.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {},
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("download_file")
+            .description("Downloads a file from a link in the background. Returns straight away, and the user is told when it finishes.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "url": {
+                        "type": "string",
+                        "description": "The http or https link to download.",
+                    },
+                    "destination": {
+                        "type": "string",
+                        "description": "Optional. A folder name like \"desktop\", a folder, or a file path to save as. Defaults to the downloads folder.",
+                    },
+                },
+                "required": ["url"],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("get_disk_usage")
+            .description("Lists each disk with where it's mounted, how full it is, and how much space is free.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {},
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("get_temperatures")
+            .description("Reads the computer's temperature sensors, like the CPU and GPU, and its fan speeds.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {},
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("get_uptime_and_idle")
+            .description("Says how long the computer has been on, how long since the user last used it, and how long they've been at the computer today and since their last break. Use this for questions like \"how long have I been working?\" or to suggest a break.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {},
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("identify_song")
+            .description("Listens to the song that's playing for a few seconds and finds out what it is. Returns straight away, and the answer comes afterwards.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "source": {
+                        "type": "string",
+                        "enum": ["system", "microphone"],
+                        "description": "Optional. \"system\" for a song the computer is playing, or \"microphone\" for one playing in the room. Defaults to system.",
+                    },
+                },
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("add_flashcard")
+            .description("Adds a flashcard to a deck, creating the deck if it's new. The user can be quizzed on it with start_review_session.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "deck": {
+                        "type": "string",
+                        "description": "The deck's name, like \"Spanish\" or \"Biology\".",
+                    },
+                    "question": {
+                        "type": "string",
+                        "description": "The question, worded to be read out loud.",
+                    },
+                    "answer": {
+                        "type": "string",
+                    },
+                },
+                "required": ["deck", "question", "answer"],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("start_review_session")
+            .description("Starts quizzing the user on the flashcards that are due, one at a time. Returns the first question to ask.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "deck": {
+                        "type": "string",
+                        "description": "Optional. The deck to review. Every deck is reviewed when left out.",
+                    },
+                },
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("grade_flashcard")
+            .description("Grades the user's answer to the flashcard just asked in a review session, which decides when it's asked again. Returns the next question to ask.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "grade": {
+                        "type": "string",
+                        "enum": ["again", "hard", "good", "easy"],
+                        "description": "\"again\" if they got it wrong, \"hard\" if they got it with difficulty, \"good\" if they got it, or \"easy\" if they knew it straight away. Answers with the same meaning count as right.",
+                    },
+                },
+                "required": ["grade"],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("list_flashcard_decks")
+            .description("Lists the flashcard decks, with how many cards each has and how many are due for review.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {},
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("load_recipe")
+            .description("Starts cooking mode with a recipe, to walk the user through it one step at a time with next_step. Give either a link to the recipe, or its steps when the user reads or pastes one. Steps that take a set time start a timer when they're reached.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "url": {
+                        "type": "string",
+                        "description": "Optional. A link to the recipe's web page.",
+                    },
+                    "title": {
+                        "type": "string",
+                        "description": "Optional. The recipe's name, when giving its steps.",
+                    },
+                    "ingredients": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Optional. The ingredients with their amounts, when giving its steps.",
+                    },
+                    "steps": {
+                        "type": "array",
+                        "description": "Optional. The recipe's steps in order, when there's no link.",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "text": {
+                                    "type": "string",
+                                    "description": "What to do, worded to be read out loud.",
+                                },
+                                "timer_minutes": {
+                                    "type": "number",
+                                    "description": "Optional. How long to set a timer for when the step is reached, like 20 for \"simmer for 20 minutes\", up to a day. 0 for no timer.",
+                                },
+                            },
+                            "required": ["text"],
+                        },
+                    },
+                },
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("next_step")
+            .description("Moves on to the next step of the recipe in cooking mode, and starts its timer if it has one. Call this when the user says \"next\" or \"done\" while cooking.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {},
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("repeat_step")
+            .description("Gets the current step of the recipe in cooking mode again, for when the user asks what to do or to hear it again.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {},
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("get_running_tasks")
+            .description("Lists the tasks still running in the background, like a speed test, and the ones that recently finished. Use this to answer questions like \"is the speed test done yet?\".")
+            .parameters(json!({
+                "type": "object",
+                "properties": {},
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("set_wake_alarm")
+            .description("Sets the user's wake-up alarm. Once it's dismissed, the user gets a morning briefing with the weather, today's events and the news. Pass the time as rfc3339 datetime string, like \"2024-12-04T07:00:00-08:00\", or as a local time of day, like \"07:00\", for the next time it comes.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "time": { "type": "string" },
+                    "timezone": {
+                        "type": "string",
+                        "description": "Optional. The IANA name of the timezone the time is in, like \"Asia/Tokyo\". Defaults to local time.",
+                    },
+                    "sound": {
+                        "type": "string",
+                        "description": "Optional. The alarm sound: one of \"alarm\", \"failed\", \"recording-started\", \"recording-stopped\", \"thinking\", or \"function-invoked\", or a path to an audio file.",
+                    },
+                    "escalate": {
+                        "type": "boolean",
+                        "description": "Optional. Start the alarm quiet and make it louder the longer it rings.",
+                    },
+                },
+                "required": ["time"],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("set_timer_at")
+            .description("Sets a timer to go off at a specific time. Pass the time as rfc3339 datetime string. Example: \"2024-12-04T00:44:00-08:00\". For a time in another timezone, like \"9am Tokyo time\", pass the clock time there without an offset, like \"2024-12-04T09:00:00\", and its IANA name as the timezone, like \"Asia/Tokyo\". The description field is optional, add descriptions that will tell you what to remind the user to do, if anything, after the timer goes off.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "time": { "type": "string" },
+                    "timezone": {
+                        "type": "string",
+                        "description": "Optional. The IANA name of the timezone the time is in, like \"Asia/Tokyo\". Defaults to local time.",
+                    },
+                    "description": { "type": "string" },
+                    "sound": {
+                        "type": "string",
+                        "description": "Optional. The alarm sound: one of \"alarm\", \"failed\", \"recording-started\", \"recording-stopped\", \"thinking\", or \"function-invoked\", or a path to an audio file.",
+                    },
+                    "volume": {
+                        "type": "integer",
+                        "description": "Optional. How loud the alarm rings, from 0 to 100. Defaults to 100.",
+                    },
+                    "escalate": {
+                        "type": "boolean",
+                        "description": "Optional. Start the alarm quiet and make it louder the longer it rings.",
+                    },
+                    "ring_for": {
+                        "type": "string",
+                        "description": "Optional. How long the alarm rings before stopping by itself, like \"2 minutes\", up to a day. \"0s\" rings until it's stopped. Defaults to 2 minutes.",
+                    },
+                    "re_ring_every": {
+                        "type": "string",
+                        "description": "Optional. How long the alarm waits to ring again after stopping by itself, up to a day. Defaults to 5 minutes.",
+                    },
+                    "re_rings": {
+                        "type": "integer",
+                        "description": "Optional. How many times the alarm rings again after stopping by itself. Defaults to 3.",
+                    },
+                    "announce_before": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Optional. When to announce how long is left before the timer goes off, like [\"5 minutes\", \"1 minute\"]. Only use this if the user asks for it.",
+                    },
+                },
+                "required": ["time"],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("get_time_in_timezone")
+            .description("Gets the current time in a timezone, including its UTC offset and whether it's on daylight saving time.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "timezone": {
+                        "type": "string",
+                        "description": "The IANA timezone name, like \"Asia/Tokyo\" or \"America/New_York\". Leave this out for the timezone of the user's home location.",
+                    },
+                },
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("set_timer_in")
+            .description("Sets a timer to go off after a length of time, such as \"25 minutes\" or \"1h30m\". Prefer this over \"set_timer_at\" whenever the user says how long the timer should be. The description field is optional, add descriptions that will tell you what to remind the user to do, if anything, after the timer goes off.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "duration": { "type": "string" },
+                    "description": { "type": "string" },
+                    "sound": {
+                        "type": "string",
+                        "description": "Optional. The alarm sound: one of \"alarm\", \"failed\", \"recording-started\", \"recording-stopped\", \"thinking\", or \"function-invoked\", or a path to an audio file.",
+                    },
+                    "volume": {
+                        "type": "integer",
+                        "description": "Optional. How loud the alarm rings, from 0 to 100. Defaults to 100.",
+                    },
+                    "escalate": {
+                        "type": "boolean",
+                        "description": "Optional. Start the alarm quiet and make it louder the longer it rings.",
+                    },
+                    "ring_for": {
+                        "type": "string",
+                        "description": "Optional. How long the alarm rings before stopping by itself, like \"2 minutes\", up to a day. \"0s\" rings until it's stopped. Defaults to 2 minutes.",
+                    },
+                    "re_ring_every": {
+                        "type": "string",
+                        "description": "Optional. How long the alarm waits to ring again after stopping by itself, up to a day. Defaults to 5 minutes.",
+                    },
+                    "re_rings": {
+                        "type": "integer",
+                        "description": "Optional. How many times the alarm rings again after stopping by itself. Defaults to 3.",
+                    },
+                    "announce_before": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Optional. When to announce how long is left before the timer goes off, like [\"5 minutes\", \"1 minute\"]. Only use this if the user asks for it.",
+                    },
+                },
+                "required": ["duration"],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("check_on_timers")
+            .description("Lists the timers that are currently set, soonest first, with the time they go off and the duration remaining until they go off. Use the optional filters to only get the timers you need.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "limit": {
+                        "type": "integer",
+                        "description": "Optional. Only list this many of the soonest matching timers.",
+                    },
+                    "after": {
+                        "type": "string",
+                        "description": "Optional. Only list timers going off at or after this rfc3339 datetime.",
+                    },
+                    "before": {
+                        "type": "string",
+                        "description": "Optional. Only list timers going off at or before this rfc3339 datetime.",
+                    },
+                    "description_contains": {
+                        "type": "string",
+                        "description": "Optional. Only list timers whose description contains this text.",
+                    },
+                },
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("delete_timer_by_id")
+            .description("Deletes a timer by it's ID. Pass the ID of the timer you want to delete. To get the ID of a timer, call the \"check_on_timers\" function.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "timer_id": { "type": "integer" },
+                },
+                "required": ["timer_id"],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("update_timer")
+            .description("Changes when a timer goes off, what it's for, or both, keeping its ID. Use this instead of deleting and setting a timer again. To get the ID of a timer, call the \"check_on_timers\" function.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "timer_id": { "type": "integer" },
+                    "new_time": {
+                        "type": "string",
+                        "description": "Optional. The new time as an rfc3339 datetime string.",
+                    },
+                    "push_back_by": {
+                        "type": "string",
+                        "description": "Optional. How much later the timer should go off, like \"30 minutes\".",
+                    },
+                    "new_description": { "type": "string" },
+                },
+                "required": ["timer_id"],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("get_upcoming_events")
+            .description("Gets the upcoming events in the user's calendar, soonest first.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "days": {
+                        "type": "integer",
+                        "description": "Optional. How many days ahead to look, up to 366. Defaults to 7.",
+                    },
+                    "limit": {
+                        "type": "integer",
+                        "description": "Optional. The most events to get. Defaults to 10.",
+                    },
+                },
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("play_music")
+            .description("Plays music on the user's Spotify account, like \"play some jazz\" or \"play Abbey Road\". Leave out query to resume what was playing. Prefer this over media_controls for music.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "query": {
+                        "type": "string",
+                        "description": "Optional. What to search Spotify for, like \"jazz\", \"Bohemian Rhapsody\" or \"Daft Punk\".",
+                    },
+                    "type": {
+                        "type": "string",
+                        "enum": ["track", "album", "artist", "playlist"],
+                        "description": "Optional. What kind of thing to play. Defaults to track. Use playlist for a genre or mood, like \"some jazz\".",
+                    },
+                },
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("play_playlist")
+            .description("Plays a playlist on Spotify by name. The user's own playlists are checked first.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "name": {
+                        "type": "string",
+                        "description": "The playlist's name, like \"Discover Weekly\".",
+                    },
+                },
+                "required": ["name"],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("pause_music")
+            .description("Pauses Spotify.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {},
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("next_track")
+            .description("Skips to the next track on Spotify.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {},
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("current_track")
+            .description("Gets what's playing on Spotify.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {},
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("get_location")
+            .description("Gets where the user is: their home location if they've set one, or roughly where their IP address is.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {},
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("set_home_location")
+            .description("Sets where the user lives, which the weather and other tools use instead of looking up their IP address. It's saved in the config file.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "place": {
+                        "type": "string",
+                        "description": "A city or town, like \"Springfield, Illinois\". With coordinates, this is only used as the name.",
+                    },
+                    "latitude": {
+                        "type": "number",
+                        "description": "Optional. The latitude, for a more exact location.",
+                    },
+                    "longitude": {
+                        "type": "number",
+                        "description": "Optional. The longitude, for a more exact location.",
+                    },
+                },
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("get_weather")
+            .description("Gets the current weather and a forecast for the next few days. Use this to answer any question about the weather instead of guessing.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "location": {
+                        "type": "string",
+                        "description": "Optional. A place name, like \"Paris\" or \"Springfield, Illinois\". Defaults to where the user is.",
+                    },
+                    "units": {
+                        "type": "string",
+                        "enum": ["metric", "imperial"],
+                        "description": "Optional. Defaults to metric. Use imperial for users in the US.",
+                    },
+                },
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("snooze_alarm")
+            .description("Silences a ringing alarm and sets its timer to go off again later. Leave out timer_id to snooze every ringing alarm.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "duration": {
+                        "type": "string",
+                        "description": "How long to snooze for, like \"10 minutes\", up to a day. Defaults to 10 minutes.",
+                    },
+                    "timer_id": { "type": "integer" },
+                },
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("dismiss_alarm")
+            .description("Silences a ringing alarm for good. Leave out timer_id to dismiss every ringing alarm.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "timer_id": { "type": "integer" },
+                },
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("add_reminder")
+            .description("Adds an item to the user's reminder list, like \"email Dave\". Reminders don't ring. They're for things the user will ask about later. Use a timer instead if the user wants to be alerted at a certain time.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "text": { "type": "string" },
+                },
+                "required": ["text"],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("list_reminders")
+            .description("Lists the reminders the user hasn't done yet, oldest first.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "include_completed": {
+                        "type": "boolean",
+                        "description": "Optional. Also list reminders that are already done.",
+                    },
+                },
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("complete_reminder")
+            .description("Marks a reminder as done. To get the ID of a reminder, call the \"list_reminders\" function.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "reminder_id": { "type": "integer" },
+                },
+                "required": ["reminder_id"],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("start_stopwatch")
+            .description("Starts a stopwatch, or resumes it if it was stopped. Give each thing being timed its own name, like \"pasta\". The name defaults to \"stopwatch\".")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "name": { "type": "string" },
+                },
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("stop_stopwatch")
+            .description("Stops a stopwatch and returns how much time it counted.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "name": { "type": "string" },
+                },
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("check_stopwatches")
+            .description("Displays every stopwatch, how much time it has counted, and whether it's running.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {},
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("reset_stopwatch")
+            .description("Removes a stopwatch, so starting it again counts from zero.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "name": { "type": "string" },
+                },
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("start_pomodoro")
+            .description("Starts a pomodoro: work sessions with breaks between them, moving from one to the next automatically. Replaces any running pomodoro. Defaults to 25 minute work sessions, 5 minute breaks, and 4 cycles.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "work_minutes": { "type": "integer" },
+                    "break_minutes": { "type": "integer" },
+                    "cycles": { "type": "integer" },
+                },
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("pause_pomodoro")
+            .description("Pauses the running pomodoro.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {},
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("resume_pomodoro")
+            .description("Resumes a paused pomodoro where it left off.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {},
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("stop_pomodoro")
+            .description("Stops the running pomodoro.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {},
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("check_pomodoro")
+            .description("Says which session the running pomodoro is in and how much time is left.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {},
+                "required": [],
+            }))
+            .build().unwrap(),
+
+
+      ChatCompletionFunctionsArgs::default()
+            .name("show_live_log_stream")
+            .description("Shows live updates of the log file via opening powershell and running 'Get-Content -Path \"path/to/log/file\" -Wait'.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {},
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("type_text")
+            .description("Types text into the app the user has focused, as if they typed it on the keyboard, like a reply you wrote for them. The user has to agree to it first.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "text": {
+                        "type": "string",
+                        "description": "The text to type, as plain text without markdown.",
+                    },
+                    "confirmation": {
+                        "type": "string",
+                        "description": "Optional. The code this tool gave when it asked the user, once they've agreed. Leave this out the first time.",
+                    },
+                },
+                "required": ["text"],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("get_news_headlines")
+            .description("Returns current news headlines, newest first, from the user's news feeds. Use this for anything about the news instead of what you already know.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "topic": {
+                        "type": "string",
+                        "description": "Optional. Only headlines about this, like \"climate\" or \"Formula 1\".",
+                    },
+                    "count": {
+                        "type": "integer",
+                        "description": "Optional. How many headlines to return, up to 15. Defaults to 8.",
+                    },
+                },
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("translate")
+            .description("Translates text into another language. It can also speak the translation in that language, for phrasebook style questions like \"how do I say where is the train station in Italian?\"")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "text": {
+                        "type": "string",
+                        "description": "The text to translate.",
+                    },
+                    "target_lang": {
+                        "type": "string",
+                        "description": "The language to translate into, like \"Italian\".",
+                    },
+                    "speak": {
+                        "type": "boolean",
+                        "description": "Optional. Whether to speak the translation aloud in the target language, so the user can hear how it's pronounced. Defaults to false.",
+                    },
+                },
+                "required": ["text", "target_lang"],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("wikipedia_summary")
+            .description("Returns the summary of the Wikipedia article that best matches a query. Use this to check facts about people, places, things and events.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "query": {
+                        "type": "string",
+                        "description": "What to look up, like \"Ada Lovelace\" or \"speed of sound\".",
+                    },
+                    "language": {
+                        "type": "string",
+                        "description": "Optional. The Wikipedia to use, as a language code like \"de\". Defaults to \"en\".",
+                    },
+                },
+                "required": ["query"],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("list_containers")
+            .description("Lists the Docker containers on this computer, running or not, with their images and how long they've been up.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {},
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("restart_container")
+            .description("Restarts a Docker container. The user has to confirm first.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "name": {
+                        "type": "string",
+                        "description": "The container's name, or part of it.",
+                    },
+                    "confirmation": {
+                        "type": "string",
+                        "description": "Optional. The code this tool gave when it asked the user, once they've agreed. Leave this out the first time.",
+                    },
+                },
+                "required": ["name"],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("git_status")
+            .description(format!("Returns a git repo's branch, whether it's ahead of or behind its upstream, and its uncommitted changes. The repos are: {}", git::names().join(", ")))
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "repo_name": {
+                        "type": "string",
+                        "description": "The name of the repo. Can be left out when there's only one.",
+                    },
+                },
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("summarize_recent_commits")
+            .description(format!("Returns the latest commits on a git repo's current branch, newest first, with their authors and when they were made. Summarize them for the user. The repos are: {}", git::names().join(", ")))
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "repo_name": {
+                        "type": "string",
+                        "description": "The name of the repo. Can be left out when there's only one.",
+                    },
+                    "count": {
+                        "type": "integer",
+                        "description": format!("Optional. How many commits to return, at most {}. Defaults to 10.", git::MAX_COMMITS),
+                    },
+                },
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("get_exchange_rate")
+            .description("Converts an amount of money between currencies at the latest exchange rate, published each working day by the European Central Bank. Cryptocurrencies aren't supported.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "from": {
+                        "type": "string",
+                        "description": "A three letter currency code, like \"USD\".",
+                    },
+                    "to": {
+                        "type": "string",
+                        "description": "A three letter currency code, like \"EUR\".",
+                    },
+                    "amount": {
+                        "type": "number",
+                        "description": "Optional. Defaults to 1.",
+                    },
+                },
+                "required": ["from", "to"],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("get_stock_quote")
+            .description("Returns a stock's latest price and how it's changed since the previous close.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "symbol": {
+                        "type": "string",
+                        "description": "The ticker symbol, like \"AAPL\". Stocks on exchanges outside the US can need a suffix, like \"VOD.L\" for London.",
+                    },
+                },
+                "required": ["symbol"],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("calculate")
+            .description("Works out a math expression exactly. Use this for any arithmetic instead of doing it in your head.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "expression": {
+                        "type": "string",
+                        "description": "Like \"(17.5 * 12) / 3\" or \"sqrt(2)^3\". Supports + - * / % ^ !, brackets, pi, e, and functions like sqrt, abs, ln, log, log(x, base), exp, sin, cos, tan in radians, rad, deg, floor, ceil, round(x, places), min and max.",
+                    },
+                },
+                "required": ["expression"],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("convert_units")
+            .description("Converts a value from one unit to another, like miles to kilometers or Fahrenheit to Celsius. Covers length, mass, volume, area, time, speed, data, energy, power, pressure and temperature. Use get_exchange_rate for currencies.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "value": {
+                        "type": "number",
+                    },
+                    "from": {
+                        "type": "string",
+                        "description": "The unit's symbol or name, like \"mi\", \"pounds\" or \"°F\".",
+                    },
+                    "to": {
+                        "type": "string",
+                        "description": "The unit to convert to, like \"km\", \"kg\" or \"°C\".",
+                    },
+                },
+                "required": ["value", "from", "to"],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("append_note")
+            .description("Adds a note to today's daily notes file, like \"note that the car needs an oil change\".")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "text": {
+                        "type": "string",
+                        "description": "The note, written the way the user would jot it down.",
+                    },
+                },
+                "required": ["text"],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("read_todays_notes")
+            .description("Returns the notes the user has taken today.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {},
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("send_email")
+            .description("Sends a plain text email from the user's email account. The user has to agree to it first.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "to": {
+                        "type": "string",
+                        "description": "The email addresses to send to, separated by commas.",
+                    },
+                    "subject": {
+                        "type": "string",
+                    },
+                    "body": {
+                        "type": "string",
+                        "description": "The email, as plain text without markdown.",
+                    },
+                    "confirmation": {
+                        "type": "string",
+                        "description": "Optional. The code this tool gave when it asked the user, once they've agreed. Leave this out the first time.",
+                    },
+                },
+                "required": ["to", "subject", "body"],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("take_screenshot")
+            .description("Takes a screenshot, saves it as a PNG file in the user's screenshots folder, and copies it to the clipboard. Returns where it was saved. You can't see the screenshot.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "region": {
+                        "type": "string",
+                        "description": "Optional. \"screen\" for everything, \"window\" for the focused window, or \"x,y,width,height\" in pixels. Defaults to \"screen\".",
+                    },
+                },
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("get_clipboard")
+            .description("Returns the text on the clipboard, like for \"summarize what's on my clipboard\".")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "confirmation": {
+                        "type": "string",
+                        "description": "Optional. The code this tool gave when it asked the user, once they've agreed. Leave this out the first time.",
+                    },
+                },
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("set_clipboard")
+            .description("Sets the clipboard to the given text.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "clipboard_text": { "type": "string" },
+                },
+                "required": ["clipboard_text"],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("set_ai_volume")
+            .description("Sets the volume of the AI's voice without changing the system volume.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "volume": {
+                        "type": "integer",
+                        "description": "The volume of the AI's voice. A number between 0 and 100.",
+                    },
+                },
+                "required": ["volume"],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("set_output_device")
+            .description("Sets the audio output device the assistant plays sounds on. Speech, alerts such as alarms, and ui sounds can each play on a different device. The choice is remembered across restarts.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "channel": {
+                        "type": "string",
+                        "enum": ["speech", "alerts", "ui"],
+                        "description": "Which sounds to move. Leave this out to move all of them.",
+                    },
+                    "device": {
+                        "type": "string",
+                        "description": "The name of the output device. Leave this out to use the system's default device.",
+                    },
+                },
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("set_ai_voice")
+            .description("Changes the voice the AI speaks with.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "voice": {
+                        "type": "string",
+                        "enum": ["alloy", "ash", "coral", "echo", "fable", "onyx", "nova", "sage", "shimmer"],
+                    },
+                },
+                "required": ["voice"],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("set_speech_speed")
+            .description("Changes how fast the AI speaks.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "speed": {
+                        "type": "number",
+                        "description": "How fast the AI speaks, where 1.0 is normal speed. Between 0.5 and 100.",
+                    },
+                },
+                "required": ["speed"],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("get_ai_volume")
+            .description("Returns the current volume of the AI's voice as a percentage.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {},
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("skip_sentence")
+            .description("Skips the sentence the AI's voice is currently speaking and moves on to the next one.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {},
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("pause_speech")
+            .description("Pauses the AI's voice. Call \"resume_speech\" to continue where it left off.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {},
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("resume_speech")
+            .description("Resumes the AI's voice after it was paused.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {},
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("repeat_last_response")
+            .description("Speaks your last response out loud again, word for word. Don't say anything else after calling this.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {},
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("mute_speech")
+            .description("Mutes the AI's voice. Responses are only shown as text until \"unmute_speech\" is called.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {},
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("unmute_speech")
+            .description("Unmutes the AI's voice after it was muted.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {},
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("pause_listening")
+            .description("Stops listening, for when the user is in a meeting or doesn't want to be interrupted. The push to talk key is ignored and timers and reminders aren't announced until \"resume_listening\" is called. Timers still show a desktop notification.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {},
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("resume_listening")
+            .description("Starts listening again after \"pause_listening\" was called.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {},
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("export_conversation")
+            .description("Exports this conversation as a transcript with timestamps and tool calls, then opens it. Returns where it was saved.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "format": {
+                        "type": "string",
+                        "enum": ["markdown", "html"],
+                        "description": "Optional. The transcript's format. Defaults to markdown.",
+                    },
+                    "folder": {
+                        "type": "string",
+                        "description": "Optional. The folder to save the transcript in. Defaults to a quick-assistant folder in the user's documents.",
+                    },
+                },
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("exit_assistant")
+            .description("Shuts the assistant down. Call this when the user tells you to exit, quit, or go to sleep.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {},
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("save_last_response_audio")
+            .description("Saves the spoken audio of your last response as an audio file, like a voice memo. Returns the file's path.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {},
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("set_tick")
+            .description("Changes the sound played while you're thinking. Only the given settings are changed.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "sound": {
+                        "type": "string",
+                        "enum": ["tick", "typing", "off"],
+                        "description": "Which thinking sound to play, or off for none.",
+                    },
+                    "volume": {
+                        "type": "integer",
+                        "description": "The volume of the thinking sound, from 0 to 100.",
+                    },
+                    "interval_ms": {
+                        "type": "integer",
+                        "description": "How many milliseconds apart the thinking sounds are played.",
+                    },
+                },
+                "required": [],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("message_contact")
+            .description(format!("Opens WhatsApp, a text message or an email to one of the user's contacts with a message written out, ready for the user to send. The contacts are: {}", contacts::names().join(", ")))
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "name": {
+                        "type": "string",
+                        "description": "The contact's name, or their first name.",
+                    },
+                    "text": {
+                        "type": "string",
+                        "description": "The message, written as the user would write it.",
+                    },
+                    "via": {
+                        "type": "string",
+                        "enum": ["whatsapp", "sms", "email"],
+                        "description": "Optional. How to send it. Leave this out to use the contact's usual way.",
+                    },
+                },
+                "required": ["name", "text"],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("run_command")
+            .description(format!("Runs one of the commands the user set up in the config file and returns what it printed. No other commands can be run. {}", commands::describe()))
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "name": {
+                        "type": "string",
+                        "description": "The name of the command to run.",
+                    },
+                    "args": {
+                        "type": "object",
+                        "description": "The command's args by name, like {\"path\": \"~/Videos\"}. Leave this out for commands without args.",
+                        "additionalProperties": {
+                            "type": "string",
+                        },
+                    },
+                },
+                "required": ["name"],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("run_routine")
+            .description(format!("Runs one of the user's routines, which calls a fixed list of tools in order, like a \"goodnight\" routine that turns off the lights and sets an alarm. {}", routines::describe()))
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "name": {
+                        "type": "string",
+                        "description": "The name of the routine to run.",
+                    },
+                },
+                "required": ["name"],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("save_routine")
+            .description("Saves a routine the user teaches you, like \"when I say goodnight, turn off the lights, turn on do not disturb and set an alarm for 7am\", so it can be run by name with run_routine. Each step is one of your tools with the arguments you'd call it with. Give times in a form that's right whenever the routine runs, like a wake alarm at \"07:00\". Tools that ask the user before doing anything, like sending an email or shutting down, can't be part of a routine. Read the steps back to the user and check them before saving. Saving a routine with the name of an existing one replaces it.")
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "name": {
+                        "type": "string",
+                        "description": "The routine's name, like \"goodnight\".",
+                    },
+                    "description": {
+                        "type": "string",
+                        "description": "Optional. What the routine is for.",
+                    },
+                    "steps": {
+                        "type": "array",
+                        "description": "The tools to call, in order.",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "tool": {
+                                    "type": "string",
+                                    "description": "The name of the tool.",
+                                },
+                                "args": {
+                                    "type": "object",
+                                    "description": "The tool's arguments. Leave this out for tools without any.",
+                                },
+                            },
+                            "required": ["tool"],
+                        },
+                    },
+                },
+                "required": ["name", "steps"],
+            }))
+            .build().unwrap(),
+
+        ChatCompletionFunctionsArgs::default()
+            .name("switch_profile")
+            .description(format!("Switches to a profile from the config file, which can change the language model, the AI's voice, the push to talk key, and which tools are available. The profiles are: {}", profiles::names().join(", ")))
+            .parameters(json!({
+                "type": "object",
+                "properties": {
+                    "name": {
+                        "type": "string",
+                        "description": "The name of the profile to switch to.",
+                    },
+                },
+                "required": ["name"],
+            }))
+            .build().unwrap(),
+    ]
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let log_guard = set_up_logging(&LOGS_DIR);
+    println!("Logs will be stored at: {}", LOGS_DIR.display());
+    info!("Starting up");
 
-static FAILED_TEMP_FILE: LazyLock<NamedTempFile> = LazyLock::new(|| {
-    create_temp_file_from_bytes(include_bytes!("../assets/failed.mp3"), ".mp3")
-});
-           
-
-/// A global, lazily-initialized closure for sending paths into a channel.
-static PLAY_AUDIO: LazyLock<Box<dyn Fn(&Path) + Send + Sync>> = LazyLock::new(|| {
-    // Create a channel for path buffers.
-    let (audio_playing_tx, audio_playing_rx) = flume::unbounded::<PathBuf>();
+    let matches = options::Opt::command().get_matches();
+    let mut opt = options::Opt::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+    let _ = dotenv();
 
-    // Create the audio playing thread
-    // Playing audio has it's own dedicated thread because I wanted to be able to play audio
-    // by passing an audio file path to a function. But the audio playing function needs to
-    // have the sink and stream variable not be dropped after the end of the function.
-    thread::spawn( move || {
-        let (_stream, stream_handle) = rodio::OutputStream::try_default().unwrap();
-        let sink = rodio::Sink::try_new(&stream_handle).unwrap();
+    let mut config = match config::load() {
+        Ok(config) => config,
+        Err(err) => {
+            println_error(&format!("Failed to load config file: {:#}", err));
+            config::Config::default()
+        }
+    };
+    config::apply(&config, &mut opt, &matches);
+    terminal::configure(&config.terminal);
+    if let Some(client_id) = config.spotify.client_id.clone() {
+        spotify::configure(client_id);
+    }
+    if let Some(allowed_roots) = config.files.allowed_roots.clone() {
+        file_search::configure(allowed_roots);
+    }
+    if let Some(folder) = config.screenshots.folder.clone() {
+        screenshot::configure(folder);
+    }
+    if let (Some(host), Some(from)) = (config.email.smtp_host.clone(), config.email.from.clone()) {
+        email::configure(host, config.email.smtp_port, from, config.email.username.clone());
+    }
+    if let Some(folder) = config.notes.folder.clone() {
+        notes::configure(folder);
+    }
+    if let Some(key) = config.finance.alpha_vantage_key.clone() {
+        finance::configure(key);
+    }
+    if let Some(feeds) = config.news.feeds.clone() {
+        news::configure(feeds);
+    }
+    git::configure(config.git.repos.clone());
+    location::configure(&config.location);
+    let _ = CONFIRM_CLIPBOARD_READS.set(config.clipboard.confirm_reads);
+    opener::configure(
+        config.open.allowed_domains.clone(),
+        config.open.confirm_others,
+    );
+    settings::apply(&mut opt, &matches);
+    translate::configure(opt.model.clone());
+    songs::configure(std::mem::take(&mut config.songs), opt.device.clone());
+    if let Err(err) = routines::configure_morning(std::mem::take(&mut config.morning), tool_parameters) {
+        println_error(&format!("{:#}", err));
+    }
+    for err in routines::configure(std::mem::take(&mut config.routines), tool_parameters) {
+        println_error(&format!("{:#}", err));
+    }
 
-        for audio_path in audio_playing_rx.iter() {
-            let file = std::fs::File::open(audio_path).unwrap();
-            sink.stop();
-            sink.append(rodio::Decoder::new(BufReader::new(file)).unwrap());
-            // sink.play();
+    profiles::configure(std::mem::take(&mut config.profile));
+    commands::configure(std::mem::take(&mut config.commands));
+    contacts::configure(std::mem::take(&mut config.contacts));
+    let profile = opt.profile.as_deref().and_then(|name| match profiles::switch(name) {
+        Ok(profile) => {
+            println!("Using profile {}", profile.name);
+            Some(profile)
+        }
+        Err(err) => {
+            println_error(&format!("Failed to use profile: {:?}", err));
+            None
         }
     });
 
-    // Return our closure, capturing the sending side of the channel.
-    Box::new(move |path: &Path| {
-        audio_playing_tx.send(path.to_path_buf()).unwrap();
-    })
-});
-
-
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn Error>> {
-    let _guard = set_up_logging(&LOGS_DIR);
-    println!("Logs will be stored at: {}", LOGS_DIR.display());
-    info!("Starting up");
+    let ai_voice = profile
+        .and_then(|profile| profile.voice)
+        .or(opt.ai_voice.clone())
+        .unwrap_or(VoiceEnum::Echo);
 
-    let opt = options::Opt::parse();
-    let _ = dotenv();
+    // Only gpt-4o-mini-tts can be told how to speak.
+    let tts_instructions = match (&opt.tts_model, &opt.tts_instructions) {
+        (TtsModelEnum::Gpt4oMiniTts, instructions) => instructions.clone(),
+        (_, Some(_)) => {
+            println!("--tts-instructions is only supported by the gpt-4o-mini-tts model and will be ignored.");
+            None
+        }
+        (_, None) => None,
+    };
 
-    let ai_voice: Voice = match opt.ai_voice {
-        Some(voice) => voice.into(),
-        None => Voice::Echo,
+    let mut speak_stream = ss::SpeakStream::new(
+        ai_voice,
+        opt.tts_model.clone(),
+        tts_instructions,
+        opt.speech_speed,
+        opt.ai_volume as f32 / 100.0,
+        opt.speech_buffer_size,
+        opt.max_speech_chars,
+    );
+    speak_stream.set_muted(opt.mute);
+    speak_stream.set_crossfade(Duration::from_millis(opt.speech_crossfade_ms));
+
+    // A single question is answered by the assistant itself, just without push to talk.
+    let ask = match opt
+        .subcommands
+        .take_if(|subcommand| matches!(subcommand, SubCommands::Ask(_)))
+    {
+        Some(SubCommands::Ask(ask)) => {
+            if ask.no_speak {
+                speak_stream.set_muted(true);
+            }
+            Some(ask)
+        }
+        _ => None,
     };
-    let (speak_stream, _stream) = ss::SpeakStream::new(ai_voice, opt.speech_speed);
     let speak_stream_mutex = Arc::new(Mutex::new(speak_stream));
 
     match opt.subcommands {
@@ -762,45 +4568,220 @@ async fn main() -> Result<(), Box<dyn Error>> {
                         println_error(&format!("Failed to listen to key presses: {:?}", error));
                     }
                 }
-                SubCommands::ListDevices => {
-                    let host = cpal::default_host();
-
-                    // Set up the input device and stream with the default input config.
-                    host.default_input_device();
-                    let devices = host
-                        .input_devices()
-                        .context("Failed to get list of input devices")?;
-
-                    for device in devices {
-                        let device_name = match device.name() {
-                            Ok(name) => name,
-                            Err(err) => {
-                                println_error(&format!("Failed to get device name: {:?}", err));
-                                continue;
+                SubCommands::ListDevices { test_tone, loopback } => {
+                    if let Err(err) = devices::list(&opt.device, test_tone, loopback) {
+                        println_error(&format!("Failed to list devices: {:#}", err));
+                    }
+                }
+                SubCommands::Setup => {
+                    if let Err(err) = setup::run(&speak_stream_mutex) {
+                        println_error(&format!("Setup failed: {:#}", err));
+                    }
+                }
+                SubCommands::SetKey { api_key } => {
+                    let api_key = match api_key.or(opt.api_key) {
+                        Some(api_key) => api_key,
+                        None => setup::prompt_hidden("Paste your OpenAI API key:")?,
+                    };
+                    if api_key.is_empty() {
+                        println_error("No API key given.");
+                    } else {
+                        match secrets::save_api_key(&api_key) {
+                            Ok(()) => println!("Saved your API key to the system keyring."),
+                            Err(err) => println_error(&format!("{:#}", err)),
+                        }
+                    }
+                }
+                SubCommands::SetEmailPassword { password } => {
+                    let password = match password {
+                        Some(password) => password,
+                        None => setup::prompt_hidden("Paste the email account's password:")?,
+                    };
+                    if password.is_empty() {
+                        println_error("No password given.");
+                    } else {
+                        match secrets::save_email_password(&password) {
+                            Ok(()) => println!("Saved the email password to the system keyring."),
+                            Err(err) => println_error(&format!("{:#}", err)),
+                        }
+                    }
+                }
+                SubCommands::SpotifyLogin { client_id } => {
+                    let client_id = match client_id {
+                        Some(client_id) => {
+                            let saved = config::update(|table| {
+                                let spotify = table.entry("spotify").or_insert_with(|| toml::Table::new().into());
+                                if let Some(spotify) = spotify.as_table_mut() {
+                                    spotify.insert("client-id".into(), client_id.clone().into());
+                                }
+                            });
+                            if let Err(err) = saved {
+                                println_error(&format!("Failed to save the client ID: {:#}", err));
                             }
-                        };
-                        println!("{:?}", device_name);
+                            client_id
+                        }
+                        None => match config.spotify.client_id.clone() {
+                            Some(client_id) => client_id,
+                            None => {
+                                println_error("No client ID given. Pass --client-id, or add client-id to the [spotify] section of the config file.");
+                                return Ok(());
+                            }
+                        },
+                    };
+                    match spotify::login(&client_id) {
+                        Ok(()) => println!("Logged in to Spotify."),
+                        Err(err) => println_error(&format!("Failed to log in to Spotify: {:#}", err)),
+                    }
+                }
+                SubCommands::Doctor => {
+                    doctor::run(&opt.device, opt.api_key.as_deref()).await;
+                }
+                SubCommands::Send { command, ask, load_session } => {
+                    let sent = match (command, load_session) {
+                        (Some(command), _) => instance::send(command),
+                        (None, Some(name)) => instance::load_session(&name),
+                        // Clap only allows leaving out the command when there's a question or a session.
+                        (None, None) => instance::send_message(&ask.unwrap_or_default()),
+                    };
+                    if let Err(err) = sent {
+                        println_error(&format!("Failed to send command: {:#}", err));
+                    }
+                }
+                SubCommands::Export { session, format, output, open, list } => {
+                    if list {
+                        match sessions::list() {
+                            Ok(sessions) if sessions.is_empty() => println!("No conversations have been saved yet."),
+                            Ok(sessions) => {
+                                for session in sessions {
+                                    println!("{}", sessions::name_of(&session));
+                                }
+                            }
+                            Err(err) => println_error(&format!("Failed to list sessions: {:#}", err)),
+                        }
+                        return Ok(());
+                    }
+
+                    let folder = output.unwrap_or_else(export::default_folder);
+                    match sessions::find(session.as_deref())
+                        .and_then(|session| export::export(&session, format, &folder))
+                    {
+                        Ok(path) => {
+                            println!("Exported the conversation to {}", path.display());
+                            if open {
+                                if let Err(err) = open::that(&path) {
+                                    println_error(&format!("Failed to open transcript: {}", err));
+                                }
+                            }
+                        }
+                        Err(err) => println_error(&format!("Failed to export conversation: {:#}", err)),
                     }
                 }
+                SubCommands::Ask(_) => unreachable!("Questions are answered by the assistant"),
             }
 
             Ok(())
         }
         // Run AI
         None => {
+            // Two assistants would both record and answer every push to talk.
+            // A single question doesn't listen for push to talk, so it can be asked alongside one.
+            let instance_requests_rx = match ask {
+                // Nothing can send to a channel whose sender is already dropped.
+                Some(_) => flume::unbounded().1,
+                None => match instance::claim() {
+                    Ok(Some(instance_requests_rx)) => instance_requests_rx,
+                    Ok(None) => {
+                        println!("quick-assistant is already running. Use the send subcommand to control it, like `quick-assistant send toggle-mute`.");
+                        return Ok(());
+                    }
+                    Err(err) => {
+                        println_error(&format!("Failed to check whether quick-assistant is already running: {:#}", err));
+                        return Ok(());
+                    }
+                },
+            };
+
             // Fail if ai_voice_speed out of range
             if opt.speech_speed < 0.5 || opt.speech_speed > 100.0 {
                 println!("Speech speed must be between 0.5 and 100.0");
                 return Ok(());
             }
 
+            // Fail if speech_buffer_size out of range
+            if opt.speech_buffer_size == 0 {
+                println!("Speech buffer size must be at least 1");
+                return Ok(());
+            }
+
+            // Fail if ai_volume out of range
+            if opt.ai_volume > 100 {
+                println!("AI volume must be between 0 and 100");
+                return Ok(());
+            }
+
+            // Fail if tick_volume out of range
+            if opt.tick_volume > 100 {
+                println!("Tick volume must be between 0 and 100");
+                return Ok(());
+            }
+
+            // Fail if duck_volume out of range
+            if opt.duck_volume > 100 {
+                println!("Duck volume must be between 0 and 100");
+                return Ok(());
+            }
+            ducking::configure(opt.duck, opt.duck_ptt, opt.duck_volume as f32 / 100.0);
+
+            if let Some(captions_file) = opt.captions_file.clone() {
+                let speech_events_rx = speak_stream_mutex.lock().unwrap().subscribe();
+                thread::spawn(move || {
+                    let mut caption = String::new();
+                    for event in speech_events_rx.iter() {
+                        caption = match event {
+                            ss::SpeechEvent::Started(text) => text,
+                            // Only clear the caption if a newer sentence hasn't replaced it.
+                            ss::SpeechEvent::Finished(text) if text != caption => continue,
+                            ss::SpeechEvent::Finished(_) | ss::SpeechEvent::Stopped => String::new(),
+                        };
+                        if let Err(err) = fs::write(&captions_file, &caption) {
+                            warn!("Failed to write captions file: {}", err);
+                        }
+                    }
+                });
+            }
+
+            // Let other frontends, like the captions overlay, show what's being spoken.
+            let speech_events_rx = speak_stream_mutex.lock().unwrap().subscribe();
+            thread::spawn(move || {
+                for event in speech_events_rx.iter() {
+                    conversation::emit(match event {
+                        ss::SpeechEvent::Started(text) => ConversationEvent::SpeechStarted { text },
+                        ss::SpeechEvent::Finished(text) => ConversationEvent::SpeechFinished { text },
+                        ss::SpeechEvent::Stopped => ConversationEvent::SpeechStopped,
+                    });
+                }
+            });
+
+            // Save the conversation, so it can be exported or looked back on later.
+            sessions::start();
+
+            // Answering a single question prints to the terminal the user just typed in.
+            if ask.is_none() {
+                notifications::notify_responses(opt.response_notifications);
+            }
+
             // figure out ptt key
-            let ptt_key = match opt.ptt_key {
-                Some(ptt_key) => ptt_key.into(),
+            let ptt_key = match profiles::active()
+                .and_then(|profile| profile.ptt_key)
+                .or(opt.ptt_key.map(Into::into))
+            {
+                Some(ptt_key) => ptt_key,
                 None => match opt.special_ptt_key {
                     Some(special_ptt_key) => rdev::Key::Unknown(special_ptt_key),
+                    // Key presses aren't listened to while answering a single question.
+                    None if ask.is_some() => rdev::Key::Unknown(0),
                     None => {
-                        println!("No push to talk key specified. Please pass a key using the --ptt-key argument or the --special-ptt-key argument.");
+                        println!("No push to talk key specified. Please run the setup subcommand, or pass a key using the --ptt-key argument or the --special-ptt-key argument.");
                         return Ok(());
                     }
                 },
@@ -808,14 +4789,54 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
             if let Some(api_key) = opt.api_key {
                 env::set_var("OPENAI_API_KEY", api_key);
+            } else if env::var("OPENAI_API_KEY").is_err() {
+                if let Some(api_key) = secrets::load_api_key() {
+                    env::set_var("OPENAI_API_KEY", api_key);
+                }
             }
 
             // Fail if OPENAI_API_KEY is not set
             if env::var("OPENAI_API_KEY").is_err() {
-                println!("OPENAI_API_KEY not set. Please pass your API key as an argument or assign is to the 'OPENAI_API_KEY' env var using terminal or .env file.");
+                println!("OPENAI_API_KEY not set. Please run the setup subcommand, pass your API key as an argument or assign is to the 'OPENAI_API_KEY' env var using terminal or .env file.");
                 return Ok(());
             }
 
+            let question = match &ask {
+                Some(AskArgs { question: Some(question), .. }) => Some(question.clone()),
+                Some(AskArgs { audio: Some(audio), .. }) => {
+                    match transcribe::transcribe(&Client::new(), audio).await {
+                        Ok(transcription) if !transcription.is_empty() => Some(transcription),
+                        Ok(_) => {
+                            println_error("Nothing was said in the audio file.");
+                            return Ok(());
+                        }
+                        Err(err) => {
+                            println_error(&format!("Failed to transcribe audio: {:?}", err));
+                            return Ok(());
+                        }
+                    }
+                }
+                _ => None,
+            };
+
+            // The assistant that's running answers instead, so what the AI does, like setting a
+            // timer, happens where it's kept track of.
+            if let Some(question) = &question {
+                match instance::subscribe() {
+                    Ok(events) => {
+                        if let Err(err) = ask_running_instance(question, events) {
+                            println_error(&format!("Failed to ask the running assistant: {:#}", err));
+                        }
+                        return Ok(());
+                    }
+                    Err(err) => info!("Answering the question here: {:#}", err),
+                }
+            }
+
+            let skip_sentence_key: Option<rdev::Key> = opt.skip_sentence_key.map(Into::into);
+            let pause_speech_key: Option<rdev::Key> = opt.pause_speech_key.map(Into::into);
+            let repeat_response_key: Option<rdev::Key> = opt.repeat_response_key.map(Into::into);
+
             let (key_handler_tx, key_handler_rx): (flume::Sender<Event>, flume::Receiver<Event>) =
                 flume::unbounded();
 
@@ -824,33 +4845,208 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
             let llm_should_stop_mutex = Arc::new(Mutex::new(false));
 
-            let alarm_temp_file = create_temp_file_from_bytes(
-                include_bytes!("../assets/Dreaming of Victory.mp3"),
-                ".mp3",
-            );
-            let (audible_timers, expired_timers_rx) = AudibleTimers::new(alarm_temp_file.path().to_path_buf())
-                .expect("Failed to create audible_timers");
+            let sound_theme = SOUND_THEME.get_or_init(|| SoundTheme::new(&config.sounds));
+            tick::configure(tick::TickSettings {
+                sound: opt.tick_sound,
+                volume: opt.tick_volume as f32 / 100.0,
+                interval: Duration::from_millis(opt.tick_interval_ms),
+                custom_sound: sound_theme.path(Sound::Thinking).map(Path::to_path_buf),
+            });
+
+            let alarm_path = sound_theme
+                .path(Sound::Alarm)
+                .expect("The alarm sound can't be turned off")
+                .to_path_buf();
+            if let Some(calendar) = &opt.calendar {
+                ics::configure(calendar.clone());
+            }
+
+            // Missed timers are left for the next full launch to tell the user about.
+            let missed_timers = match ask {
+                Some(_) => Vec::new(),
+                None => take_missed_timers().unwrap_or_else(|err| {
+                    println_error(&format!("Failed to check for missed timers: {:?}", err));
+                    Vec::new()
+                }),
+            };
+            // Timers set while answering a single question are left to ring in a full launch.
+            let (audible_timers, timer_events_rx) = match ask {
+                Some(_) => AudibleTimers::silent(),
+                None => AudibleTimers::new(alarm_path).expect("Failed to create audible_timers"),
+            };
+            let snooze_key: Option<rdev::Key> = opt.snooze_key.map(Into::into);
+            let dismiss_key: Option<rdev::Key> = opt.dismiss_key.map(Into::into);
+            let pause_listening_key: Option<rdev::Key> = opt.pause_listening_key.map(Into::into);
+            let snooze_duration = Duration::from_secs(opt.snooze_minutes.saturating_mul(60)).min(MAX_ALARM_WAIT);
+
+            // Create alarm state thread
+            // This thread tells other frontends when an alarm starts or stops ringing, so they can show it.
+            let alarm_state_rx = audible_timers.subscribe();
+            thread::spawn(move || {
+                for ringing in alarm_state_rx.iter() {
+                    conversation::emit(match ringing {
+                        true => ConversationEvent::AlarmStarted,
+                        false => ConversationEvent::AlarmStopped,
+                    });
+                }
+            });
+
+            let (llm_messages_tx, llm_messages_rx): (flume::Sender<Message>, flume::Receiver<Message>) = flume::unbounded();
+
+            // Handle requests sent by later launches and other frontends.
+            let thread_llm_should_stop_mutex = llm_should_stop_mutex.clone();
+            let thread_speak_stream_mutex = speak_stream_mutex.clone();
+            let thread_audible_timers = audible_timers.clone();
+            let thread_llm_messages_tx = llm_messages_tx.clone();
+            // Push to talk commands act like the key, so they're handled with the key presses.
+            let thread_key_handler_tx = key_handler_tx.clone();
+            thread::spawn(move || {
+                for request in instance_requests_rx.iter() {
+                    let command = match request {
+                        instance::InstanceRequest::Command(command) => command,
+                        // A typed message interrupts the AI like pressing push to talk does.
+                        instance::InstanceRequest::Message(text) => {
+                            *thread_llm_should_stop_mutex.lock().unwrap() = true;
+                            thread_speak_stream_mutex.lock().unwrap().stop_speech();
+                            thread_llm_messages_tx.send(Message::User { content: text }).unwrap();
+                            continue;
+                        }
+                        instance::InstanceRequest::LoadSession(name) => {
+                            let entries = match sessions::find(Some(&name)).and_then(|session| sessions::load(&session)) {
+                                Ok(entries) => entries,
+                                Err(err) => {
+                                    println_error(&format!("Failed to load conversation: {:#}", err));
+                                    continue;
+                                }
+                            };
+                            *thread_llm_should_stop_mutex.lock().unwrap() = true;
+                            thread_speak_stream_mutex.lock().unwrap().stop_speech();
+                            let content = format!("The user loaded an earlier conversation so you can refer back to it and carry on from it. Briefly confirm that you have it, mentioning what it was about.\n{}", sessions::as_context(&entries));
+                            thread_llm_messages_tx.send(Message::System { content }).unwrap();
+                            continue;
+                        }
+                    };
+                    let mut speak_stream = thread_speak_stream_mutex.lock().unwrap();
+                    match command {
+                        instance::InstanceCommand::ToggleMute
+                        | instance::InstanceCommand::Mute
+                        | instance::InstanceCommand::Unmute => {
+                            let muted = match command {
+                                instance::InstanceCommand::Mute => true,
+                                instance::InstanceCommand::Unmute => false,
+                                _ => !speak_stream.is_muted(),
+                            };
+                            speak_stream.set_muted(muted);
+                            settings::update(|saved| saved.muted = Some(muted));
+                        }
+                        instance::InstanceCommand::SkipSentence => speak_stream.skip_sentence(),
+                        instance::InstanceCommand::TogglePause => {
+                            if speak_stream.is_paused() {
+                                speak_stream.resume_speech();
+                            } else {
+                                speak_stream.pause_speech();
+                            }
+                        }
+                        instance::InstanceCommand::RepeatResponse => {
+                            speak_stream.repeat_last_response();
+                        }
+                        instance::InstanceCommand::StopSpeaking => {
+                            *thread_llm_should_stop_mutex.lock().unwrap() = true;
+                            speak_stream.stop_speech();
+                        }
+                        instance::InstanceCommand::DismissAlarm => {
+                            thread_audible_timers.dismiss(None);
+                        }
+                        instance::InstanceCommand::TogglePauseListening => {
+                            listening_pause::toggle();
+                        }
+                        instance::InstanceCommand::PauseListening => listening_pause::set_paused(true),
+                        instance::InstanceCommand::ResumeListening => listening_pause::set_paused(false),
+                        instance::InstanceCommand::PttStart | instance::InstanceCommand::PttStop => {
+                            let key = profiles::ptt_key(ptt_key);
+                            let event_type = match command {
+                                instance::InstanceCommand::PttStart => rdev::EventType::KeyPress(key),
+                                _ => rdev::EventType::KeyRelease(key),
+                            };
+                            let event = Event { time: std::time::SystemTime::now(), name: None, event_type };
+                            if thread_key_handler_tx.send(event).is_err() {
+                                warn!("Ignoring {:?}, because push to talk has stopped", command);
+                            }
+                        }
+                    }
+                }
+            });
 
             // Create audio recorder thread
             // This thread listens to the push to talk key and records audio when it's pressed.
             // It then sends the path of the recorded audio file to the AI thread.
             let thread_llm_should_stop_mutex = llm_should_stop_mutex.clone();
             let thread_speak_stream_mutex = speak_stream_mutex.clone();
-            thread::spawn(move || {
+            let thread_audible_timers = audible_timers.clone();
+            // Disconnected when shutting down, so a recording in progress is finished properly.
+            let (key_thread_stop_tx, key_thread_stop_rx) = flume::bounded::<()>(0);
+            let key_thread = thread::spawn(move || {
+                let audible_timers = thread_audible_timers;
                 let mut recorder = rec::Recorder::new();
                 let mut recording_start = std::time::SystemTime::now();
                 let mut key_pressed = false;
-                let key_to_check = ptt_key;
+                // The push to talk key is held while listening is paused.
+                let mut ignored_key_pressed = false;
                 let tmp_dir = tempdir().unwrap();
                 let mut voice_tmp_path_option: Option<PathBuf> = None;
+                // Dropped to stop reporting the microphone level.
+                let mut level_meter_stop_tx: Option<flume::Sender<()>> = None;
                
-                for event in key_handler_rx.iter() {
+                while let Some(event) = flume::Selector::new()
+                    .recv(&key_handler_rx, Result::ok)
+                    .recv(&key_thread_stop_rx, |_| None)
+                    .wait()
+                {
+                    // The push to talk key can change when the profile is switched.
+                    let key_to_check = profiles::ptt_key(ptt_key);
                     match event.event_type {
+                        rdev::EventType::KeyPress(key) if Some(key) == skip_sentence_key => {
+                            thread_speak_stream_mutex.lock().unwrap().skip_sentence();
+                        }
+                        rdev::EventType::KeyPress(key) if Some(key) == pause_speech_key => {
+                            let mut speak_stream = thread_speak_stream_mutex.lock().unwrap();
+                            if speak_stream.is_paused() {
+                                speak_stream.resume_speech();
+                            } else {
+                                speak_stream.pause_speech();
+                            }
+                        }
+                        rdev::EventType::KeyPress(key) if Some(key) == repeat_response_key => {
+                            thread_speak_stream_mutex.lock().unwrap().repeat_last_response();
+                        }
+                        rdev::EventType::KeyPress(key) if Some(key) == snooze_key => {
+                            if let Err(err) = audible_timers.snooze(None, snooze_duration) {
+                                println_error(&format!("Failed to snooze alarm: {:?}", err));
+                            }
+                        }
+                        rdev::EventType::KeyPress(key) if Some(key) == dismiss_key => {
+                            audible_timers.dismiss(None);
+                        }
+                        rdev::EventType::KeyPress(key) if Some(key) == pause_listening_key => {
+                            let paused = listening_pause::toggle();
+                            println!("{}", if paused { "Listening paused" } else { "Listening resumed" }.purple());
+                        }
+                        rdev::EventType::KeyPress(key) if key == key_to_check && !key_pressed && listening_pause::is_paused() => {
+                            // Held keys repeat, so this is only said once per press.
+                            let first_press = !ignored_key_pressed;
+                            ignored_key_pressed = true;
+                            if first_press {
+                                println!("{}", "Listening is paused. Resume it to talk to the assistant.".purple());
+                            }
+                        }
                         rdev::EventType::KeyPress(key) => {
                             if key == key_to_check && !key_pressed {
                                 key_pressed = true;
                                 // handle key press
 
+                                ducking::set_listening(true);
+                                tick::set_thinking(false);
+
                                 audible_timers.stop_alarm();
 
                                 // stop the AI voice from speaking
@@ -873,7 +5069,12 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
                                 recording_start = std::time::SystemTime::now();
                                 match recorder.start_recording(&voice_tmp_path, Some(&opt.device)) {
-                                    Ok(_) => info!("Recording started"),
+                                    Ok(_) => {
+                                        info!("Recording started");
+                                        play_sound(Sound::RecordingStarted);
+                                        conversation::emit(ConversationEvent::ListeningStarted);
+                                        level_meter_stop_tx = Some(spawn_level_meter(recorder.level()));
+                                    }
                                     Err(err) => println_error(&format!(
                                         "Failed to start recording: {:?}",
                                         err
@@ -882,10 +5083,18 @@ async fn main() -> Result<(), Box<dyn Error>> {
                             }
                         }
                         rdev::EventType::KeyRelease(key) => {
+                            if key == key_to_check {
+                                ignored_key_pressed = false;
+                            }
                             if key == key_to_check && key_pressed {
                                 key_pressed = false;
                                 // handle key release
 
+                                ducking::set_listening(false);
+                                if level_meter_stop_tx.take().is_some() {
+                                    conversation::emit(ConversationEvent::ListeningStopped);
+                                }
+
                                 // stop any alarms
                                 audible_timers.stop_alarm();
 
@@ -903,7 +5112,10 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
                                 // stop recording
                                 match recorder.stop_recording() {
-                                    Ok(_) => info!("Recording stopped"),
+                                    Ok(_) => {
+                                        info!("Recording stopped");
+                                        play_sound(Sound::RecordingStopped);
+                                    }
                                     Err(err) => {
                                         println_error(&format!(
                                             "Failed to stop recording: {:?}",
@@ -936,16 +5148,117 @@ async fn main() -> Result<(), Box<dyn Error>> {
                         _ => (),
                     }
                 }
+
+                if level_meter_stop_tx.take().is_some() {
+                    conversation::emit(ConversationEvent::ListeningStopped);
+                }
+                if key_pressed {
+                    if let Err(err) = recorder.stop_recording() {
+                        println_error(&format!("Failed to stop recording: {:?}", err));
+                    }
+                }
             });
 
-            let (llm_messages_tx, llm_messages_rx): (flume::Sender<Message>, flume::Receiver<Message>) = flume::unbounded();
+            // Told each time the AI finishes a response, so a single question knows when it's answered.
+            let (response_done_tx, response_done_rx) = flume::bounded(1);
+
+            if let Some(question) = question {
+                llm_messages_tx.send(Message::User { content: question }).unwrap();
+            }
+
+            if !missed_timers.is_empty() {
+                let timer_strings: Vec<String> = missed_timers
+                    .iter()
+                    .map(|timer| {
+                        format!(
+                            "Timer_ID: \"{}\" Timer_description: \"{}\" went off at time: \"{}\"",
+                            timer.id,
+                            timer.description,
+                            timer.timestamp.to_rfc3339(),
+                        )
+                    })
+                    .collect();
+                llm_messages_tx.send(
+                    Message::Function { fn_name: "check_on_timers".to_string(), content: format!("These timers went off while the assistant wasn't running. Briefly tell the user about them, for example \"while I was off, your 3pm timer for the laundry went off\". Their alarms won't ring.\n{}", timer_strings.join("\n"))}
+                ).unwrap();
+            }
 
             // Create timer to llm message thread
-            // This thread listens to the expired timers channel and sends a message to the AI thread
-            // when a timer expires.
+            // This thread listens to the timer events channel and sends a message to the AI thread
+            // when a timer expires or has a countdown announcement.
             let thread_llm_messages_tx = llm_messages_tx.clone();
+            let thread_audible_timers = audible_timers.clone();
+            let thread_speak_stream_mutex = speak_stream_mutex.clone();
+            let timer_notifications = !opt.no_timer_notifications;
             thread::spawn(move || {
-                for timer in expired_timers_rx.iter() {
+                for event in timer_events_rx.iter() {
+                    let timer = match event {
+                        TimerEvent::Expired(timer) => timer,
+                        TimerEvent::Countdown { timer, time_left } => {
+                            if listening_pause::is_paused() {
+                                continue;
+                            }
+                            // Round to the nearest minute, so "4m 59s" is announced as "5m".
+                            let minutes = (time_left.as_secs() + 30) / 60;
+                            let time_left = Duration::from_secs(minutes.max(1) * 60);
+                            thread_llm_messages_tx.send(
+                                Message::Function { fn_name: "check_on_timers".to_string(), content: format!("Briefly tell the user how long is left on this timer, for example \"5 minutes left on your oven timer\".\nTimer_ID: \"{}\" Timer_description: \"{}\" goes off in: \"{}\"", timer.id, timer.description, humantime::format_duration(time_left))}
+                            ).unwrap();
+                            continue;
+                        }
+                    };
+
+                    // While listening is paused, timers go off silently and only show a notification.
+                    let paused = listening_pause::is_paused();
+                    if paused {
+                        thread_audible_timers.dismiss(Some(timer.id));
+                    }
+
+                    if timer_notifications || paused {
+                        notifications::notify_timer_expired(&timer, &thread_audible_timers, snooze_duration);
+                    }
+
+                    let pomodoro_content = pomodoro::on_timer_expired(&timer);
+                    if paused {
+                        continue;
+                    }
+                    if let Some(content) = pomodoro_content {
+                        thread_llm_messages_tx.send(
+                            Message::Function { fn_name: "check_pomodoro".to_string(), content }
+                        ).unwrap();
+                        continue;
+                    }
+
+                    if let Some(steps) = routines::morning_steps().filter(|_| routines::is_wake_alarm(&timer)) {
+                        // An alarm ringing again, or after a snooze, already has its briefing waiting.
+                        if !routines::start_briefing(timer.id) {
+                            continue;
+                        }
+                        thread_llm_messages_tx.send(
+                            Message::Function { fn_name: "set_wake_alarm".to_string(), content: "The user's wake-up alarm has gone off. Only say good morning. A briefing will follow once the alarm is dismissed.".to_string() }
+                        ).unwrap();
+                        // The briefing waits for this alarm to be dismissed, so it isn't talked over.
+                        let routine_llm_messages_tx = thread_llm_messages_tx.clone();
+                        let routine_audible_timers = thread_audible_timers.clone();
+                        let routine_speak_stream_mutex = thread_speak_stream_mutex.clone();
+                        thread::spawn(move || {
+                            let task = tasks::start("Preparing the morning briefing");
+                            let briefing = routines::run(steps, |fn_name, fn_args| {
+                                call_routine_step(fn_name, fn_args, routine_llm_messages_tx.clone(), &routine_speak_stream_mutex, &routine_audible_timers)
+                            });
+                            drop(task);
+                            let dismissed = routine_audible_timers.wait_until_dismissed(timer.id, routines::BRIEFING_WAIT);
+                            routines::end_briefing(timer.id);
+                            if !dismissed {
+                                info!("Skipped the morning briefing, because the wake-up alarm wasn't dismissed");
+                                return;
+                            }
+                            let content = format!("The wake-up alarm has been dismissed. Give the user their morning briefing from the following, as one short spoken summary in this order.\n{}", briefing);
+                            tell_user(&routine_llm_messages_tx, "set_wake_alarm", content, "Good morning", "Your morning briefing wasn't given, because listening is paused.");
+                        });
+                        continue;
+                    }
+
                     let timer_string = &format!(
                         "Timer_ID: \"{}\" Timer_description: \"{}\" goes off at time: \"{}\"",
                         timer.id,
@@ -953,11 +5266,40 @@ async fn main() -> Result<(), Box<dyn Error>> {
                         timer.timestamp.to_rfc3339(),
                     );
                     thread_llm_messages_tx.send(
-                        Message::Function { fn_name: "check_on_timers".to_string(), content: format!("A timer has gone off with the following details. Depending on what the timer is for, alert the user a timer at the given time has gone off, and tell them what it's time for them to do. Or take independent action accordingly.\n{}", timer_string)}
+                        Message::Function { fn_name: "check_on_timers".to_string(), content: format!("A timer has gone off with the following details. Depending on what the timer is for, alert the user a timer at the given time has gone off, and tell them what it's time for them to do. Or take independent action accordingly. The alarm keeps ringing until the user snoozes or dismisses it.\n{}", timer_string)}
                     ).unwrap();
                 }
             });
 
+            // Create calendar reminder to llm message thread
+            // This thread sends a message to the AI thread when a calendar event is about to start.
+            if let Some(reminder_minutes) = opt.calendar_reminder_minutes {
+                let reminder_rx = ics::spawn_reminders(Duration::from_secs(reminder_minutes * 60));
+                let thread_llm_messages_tx = llm_messages_tx.clone();
+                thread::spawn(move || {
+                    for event in reminder_rx.iter() {
+                        let content = format!("A calendar event is starting soon. Remind the user about it.\n{}", describe_event(&event));
+                        let starts = match event.all_day {
+                            true => "Today".to_string(),
+                            false => format!("At {}", event.start.format("%H:%M")),
+                        };
+                        tell_user(&thread_llm_messages_tx, "get_upcoming_events", content, &event.summary, &starts);
+                    }
+                });
+            }
+
+            // Create monitoring alert to llm message thread
+            // This thread sends a message to the AI thread when the computer has been running hot or a disk is nearly full.
+            let alert_rx = monitoring::spawn_alerts(&config.monitoring);
+            let thread_llm_messages_tx = llm_messages_tx.clone();
+            thread::spawn(move || {
+                for alert in alert_rx.iter() {
+                    tell_user(&thread_llm_messages_tx, alert.tool, alert.message, "Computer warning", &alert.notice);
+                }
+            });
+
+            activity::start_tracking();
+
             // Create user audio to text thread
             // This thread listens to the audio recorder thread and transcribes the audio
             // before feeding it to the AI assistant.
@@ -988,7 +5330,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
                                 err
                             ));
 
-                            PLAY_AUDIO(FAILED_TEMP_FILE.path());
+                            play_sound(Sound::Failed);
 
                             continue;
                         }
@@ -998,7 +5340,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
                         Ok(transcription) => transcription,
                         Err(err) => {
                             println_error(&format!("Failed to transcribe audio: {:?}", err));
-                            PLAY_AUDIO(FAILED_TEMP_FILE.path());
+                            play_sound(Sound::Failed);
                             continue;
                         }
                     };
@@ -1017,6 +5359,9 @@ async fn main() -> Result<(), Box<dyn Error>> {
                 }
             });
 
+            // The AI thread takes the timers, but shutting down needs to silence them too.
+            let shutdown_audible_timers = audible_timers.clone();
+
             // Create AI thread
             // This thread receives new llm messages and processes them with the AI.
             let thread_llm_should_stop_mutex = llm_should_stop_mutex.clone();
@@ -1027,7 +5372,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
                 message_history.push(
                     ChatCompletionRequestSystemMessageArgs::default()
-                        .content("You are a desktop voice assistant. The messages you receive from the user are voice transcriptions. Your responses will be spoken out loud by a text to speech engine. You should be helpful but concise. As conversations should be a back and forth. Don't make audio clips that run on for more than 15 seconds. Also don't ask 'if I would like to know more'. If you are told to set a timer, you should always call the \"set_timer_at\" function.")
+                        .content("You are a desktop voice assistant. The messages you receive from the user are voice transcriptions. Your responses will be spoken out loud by a text to speech engine. You should be helpful but concise. As conversations should be a back and forth. Don't make audio clips that run on for more than 15 seconds. Also don't ask 'if I would like to know more'. If you are told to set a timer for a length of time, you should always call the \"set_timer_in\" function. If you are told to set a timer for a time of day, call the \"set_timer_at\" function. ".to_string() + SPEECH_TAGS_PROMPT)
                         .build()
                         .unwrap()
                         .into(),
@@ -1038,6 +5383,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
                     .unwrap();
 
                 for llm_message in llm_messages_rx.iter() {
+                    terminal::set_responding(true);
 
                     // convert message type to ChatCompletionRequestMessage
                     match llm_message {
@@ -1051,6 +5397,8 @@ async fn main() -> Result<(), Box<dyn Error>> {
                             );
                         }
                         Message::User { content } => {
+                            confirmation::user_turn();
+
                             // Add time header to user message
                             let time_header = format!("Local Time: {}", Local::now());
                             let user_message = time_header + "\n" + &content;
@@ -1063,8 +5411,8 @@ async fn main() -> Result<(), Box<dyn Error>> {
                                     .into(),
                             );
 
-                            println!("{}", "You: ".truecolor(0, 255, 0));
-                            println!("{}", content);
+                            conversation::emit(ConversationEvent::User { text: content.clone() });
+                            terminal::print_user(&content);
                             info!("User transcription: \"{}\"", truncate(&content, 20));
                         }
                         Message::Assistant { content } => {
@@ -1097,6 +5445,9 @@ async fn main() -> Result<(), Box<dyn Error>> {
                         }
                     };
 
+                    // Remember the previous response so it can be repeated.
+                    thread_speak_stream_mutex.lock().unwrap().begin_response();
+
                     // Make sure the LLM token generation is allowed to start
                     // It should only be stopped when the LLM is running.
                     // Since it's not running now, it should be allowed to start.
@@ -1105,165 +5456,18 @@ async fn main() -> Result<(), Box<dyn Error>> {
                     drop(llm_should_stop);
 
                     // repeatedly create request until it's answered
-                    let mut displayed_ai_label = false;
+                    let mut response_renderer = terminal::ResponseRenderer::default();
+                    let mut speech_tag_filter = SpeechTagFilter::default();
                     'request: loop {
                         debug!("Entered chat completion request loop");
+                        tick::set_thinking(true);
                         let mut ai_content = String::new();
                         let request = CreateChatCompletionRequestArgs::default()
                             // .model("gpt-3.5-turbo")
-                            .model(&opt.model)
+                            .model(profiles::model(&opt.model))
                             .max_tokens(512u16)
                             .messages(message_history.clone())
-                            .functions([
-                                ChatCompletionFunctionsArgs::default()
-                                .description("Sets the brightness of the device's screen.")
-                                .name("set_screen_brightness")
-                                    .parameters(json!({
-                                        "type": "object",
-                                        "properties": {
-                                            "brightness": {
-                                                "type": "string",
-                                                "description": "The brightness of the screen. A number between 0 and 100.",
-                                            },
-                                        },
-                                        "required": ["brightness"],
-                                    }))
-                                    .build().unwrap(),
-
-                                ChatCompletionFunctionsArgs::default()
-                                    .name("media_controls")
-                                    .description("Plays/Pauses/Seeks media.")
-                                    .parameters(json!({
-                                        "type": "object",
-                                        "properties": {
-                                            "media_button": { "type": "string", "enum": ["MediaStop", "MediaNextTrack", "MediaPlayPause", "MediaPrevTrack", "VolumeUp", "VolumeDown", "VolumeMute"] },
-                                        },
-                                        "required": ["media_button"],
-                                    }))
-                                    .build().unwrap(),
-
-                                ChatCompletionFunctionsArgs::default()
-                                    .name("open_application")
-                                    .description("naively opens an applicatin by pressing the super key to open system search and then types the name of the application and presses enter.")
-                                    .parameters(json!({
-                                        "type": "object",
-                                        "properties": {
-                                            "application": { "type": "string" },
-                                        },
-                                        "required": ["application"],
-                                    }))
-                                    .build().unwrap(),
-
-                                ChatCompletionFunctionsArgs::default()
-                                    .name("open_logs_folder")
-                                    .description("Opens this program's logging folder in the default file browser for the user to see.")
-                                    .parameters(json!({
-                                        "type": "object",
-                                        "properties": {},
-                                        "required": [],
-                                    }))
-                                    .build().unwrap(),
-
-                                ChatCompletionFunctionsArgs::default()
-                                    .name("sysinfo")
-                                    .description("Returns this system's information.")
-                                    .parameters(json!({
-                                        "type": "object",
-                                        "properties": {},
-                                        "required": [],
-                                    }))
-                                    .build().unwrap(),
-
-                                ChatCompletionFunctionsArgs::default()
-                                    .name("get_system_processes")
-                                    .description("Returns this system's processes with their pid, name, start_time, runtime, status, cpu_usage, exe_path and other information.")
-                                    .parameters(json!({
-                                        "type": "object",
-                                        "properties": {},
-                                        "required": [],
-                                    }))
-                                    .build().unwrap(),
-
-                                ChatCompletionFunctionsArgs::default()
-                                    .name("kill_processes_with_name")
-                                    .description("Kills all processes with a given name. ALWAYS call \"get_system_processes\" first to get the name of the process you want to kill.")
-                                    .parameters(json!({
-                                        "type": "object",
-                                        "properties": {
-                                            "process_name": { "type": "string" },
-                                        },
-                                        "required": ["process_name"],
-                                    }))
-                                    .build().unwrap(),
-
-                                ChatCompletionFunctionsArgs::default()
-                                    .name("speedtest")
-                                    .description("Runs an internet speedtest and returns the results.")
-                                    .parameters(json!({
-                                        "type": "object",
-                                        "properties": {},
-                                        "required": [],
-                                    }))
-                                    .build().unwrap(),
-
-                                ChatCompletionFunctionsArgs::default()
-                                    .name("set_timer_at")
-                                    .description("Sets a timer to go off at a specific time. Pass the time as rfc3339 datetime string. Example: \"2024-12-04T00:44:00-08:00\". The description field is optional, add descriptions that will tell you what to remind the user to do, if anything, after the timer goes off.")
-                                    .parameters(json!({
-                                        "type": "object",
-                                        "properties": {
-                                            "time": { "type": "string" },
-                                            "description": { "type": "string" },
-                                        },
-                                        "required": ["time"],
-                                    }))
-                                    .build().unwrap(),
-
-                                ChatCompletionFunctionsArgs::default()
-                                    .name("check_on_timers")
-                                    .description("Displays all timers that are currently set. The time they are set to go off and the duration remaining until they go off.")
-                                    .parameters(json!({
-                                        "type": "object",
-                                        "properties": {},
-                                        "required": [],
-                                    }))
-                                    .build().unwrap(),
-
-                                ChatCompletionFunctionsArgs::default()
-                                    .name("delete_timer_by_id")
-                                    .description("Deletes a timer by it's ID. Pass the ID of the timer you want to delete. To get the ID of a timer, call the \"check_on_timers\" function.")
-                                    .parameters(json!({
-                                        "type": "object",
-                                        "properties": {
-                                            "timer_id": { "type": "integer" },
-                                        },
-                                        "required": ["timer_id"],
-                                    }))
-                                    .build().unwrap(),
-                                    
-                              
-                              ChatCompletionFunctionsArgs::default()
-                                    .name("show_live_log_stream")
-                                    .description("Shows live updates of the log file via opening powershell and running 'Get-Content -Path \"path/to/log/file\" -Wait'.")
-                                    .parameters(json!({
-                                        "type": "object",
-                                        "properties": {},
-                                        "required": [],
-                                    }))
-                                    .build().unwrap(),
-
-                                ChatCompletionFunctionsArgs::default()
-                                    .name("set_clipboard")
-                                    .description("Sets the clipboard to the given text.")
-                                    .parameters(json!({
-                                        "type": "object",
-                                        "properties": {
-                                            "clipboard_text": { "type": "string" },
-                                        },
-                                        "required": ["clipboard_text"],
-                                    }))
-                                    .build().unwrap(),
-                            ])
+                            .functions(profiles::enabled_tools(tool_functions()))
                             .build()
                             .unwrap();
 
@@ -1276,7 +5480,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
                                 Err(err) => {
                                     println_error(&format!("Failed to create stream: {}", err));
 
-                                    PLAY_AUDIO(FAILED_TEMP_FILE.path());
+                                    play_sound(Sound::Failed);
 
                                     break 'request;
                                 }
@@ -1287,7 +5491,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
                                     err
                                 ));
 
-                                PLAY_AUDIO(FAILED_TEMP_FILE.path());
+                                play_sound(Sound::Failed);
 
                                 break 'request;
                             }
@@ -1311,7 +5515,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
                                         err
                                     ));
 
-                                    PLAY_AUDIO(FAILED_TEMP_FILE.path());
+                                    play_sound(Sound::Failed);
 
                                     break 'request;
                                 }
@@ -1333,7 +5537,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
                                         .into(),
                                 );
 
-                                println!();
+                                response_renderer.finish();
 
                                 break 'request;
                             }
@@ -1353,9 +5557,12 @@ async fn main() -> Result<(), Box<dyn Error>> {
                                         }
                                         if let Some(finish_reason) = &chat_choice.finish_reason {
                                             if matches!(finish_reason, FinishReason::FunctionCall) {
-                                                let func_response_option = call_fn(&fn_name, &fn_args, llm_messages_tx.clone());
+                                                play_sound(Sound::FunctionInvoked);
+                                                conversation::emit(ConversationEvent::FunctionCall { name: fn_name.clone(), arguments: fn_args.clone() });
+                                                let func_response_option = call_fn(&fn_name, &fn_args, llm_messages_tx.clone(), &thread_speak_stream_mutex, &audible_timers);
 
                                                 if let Some(func_response) = func_response_option {
+                                                    conversation::emit(ConversationEvent::FunctionResult { name: fn_name.clone(), content: func_response.clone() });
                                                     message_history.push(
                                                         ChatCompletionRequestFunctionMessageArgs::default()
                                                             .name(fn_name.clone())
@@ -1369,12 +5576,10 @@ async fn main() -> Result<(), Box<dyn Error>> {
                                                 }
                                             }
                                         } else if let Some(content) = &chat_choice.delta.content {
-                                            if !displayed_ai_label {
-                                                println!("{}", "AI: ".truecolor(0, 0, 255));
-                                                displayed_ai_label = true;
-                                            }
-
-                                            print!("{}", content);
+                                            tick::set_thinking(false);
+                                            let text = speech_tag_filter.filter(content);
+                                            response_renderer.push(&text);
+                                            conversation::emit(ConversationEvent::Token { text });
                                             ai_content += content;
 
                                             let mut last_non_empty_line_option = None;
@@ -1484,7 +5689,12 @@ async fn main() -> Result<(), Box<dyn Error>> {
                             }
                             stdout().flush().unwrap();
                         }
-                        println!();
+                        let text = speech_tag_filter.flush();
+                        response_renderer.push(&text);
+                        response_renderer.finish();
+                        if !text.is_empty() {
+                            conversation::emit(ConversationEvent::Token { text });
+                        }
 
                         message_history.push(
                             ChatCompletionRequestAssistantMessageArgs::default()
@@ -1496,12 +5706,54 @@ async fn main() -> Result<(), Box<dyn Error>> {
                         break;
                     }
 
+                    tick::set_thinking(false);
+
                     // Tells the ai voice to speak the remaining text in the buffer
                     let mut thread_speak_stream = thread_speak_stream_mutex.lock().unwrap();
                     thread_speak_stream.complete_sentence();
                     drop(thread_speak_stream);
                     debug!("AI token generation complete.");
+                    conversation::emit(ConversationEvent::ResponseDone);
+                    terminal::set_responding(false);
+                    let _ = response_done_tx.try_send(());
+                }
+            });
+
+            if ask.is_some() {
+                let _ = response_done_rx.recv_async().await;
+                while !speak_stream_mutex.lock().unwrap().is_idle() {
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                }
+                return Ok(());
+            }
+
+            // Listening for key presses below never returns, so shutting down happens on its own thread.
+            shutdown::handle_ctrl_c();
+            let thread_llm_should_stop_mutex = llm_should_stop_mutex.clone();
+            let thread_speak_stream_mutex = speak_stream_mutex.clone();
+            thread::spawn(move || {
+                shutdown::wait();
+                info!("Shutting down");
+                println!("Shutting down...");
+
+                *thread_llm_should_stop_mutex.lock().unwrap() = true;
+                thread_speak_stream_mutex.lock().unwrap().stop_speech();
+                shutdown_audible_timers.stop_alarm();
+                tick::set_thinking(false);
+
+                drop(key_thread_stop_tx);
+                let deadline = Instant::now() + Duration::from_secs(2);
+                while !key_thread.is_finished() && Instant::now() < deadline {
+                    thread::sleep(Duration::from_millis(10));
                 }
+
+                // Put other applications' volume back if it was ducked.
+                ducking::set_speaking(false);
+                ducking::set_listening(false);
+
+                // Exiting skips destructors, so the log has to be flushed first.
+                drop(log_guard);
+                std::process::exit(0);
             });
 
             info!("System ready");
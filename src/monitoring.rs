@@ -0,0 +1,330 @@
+//! Watching the computer's temperatures, fans and disks in the background, and warning the user
+//! when one stays past its limit, like a CPU above 90°C for five minutes or a disk over 90% full.
+//! Limits come from the config file.
+
+use std::{
+    collections::HashMap,
+    thread,
+    time::{Duration, Instant},
+};
+use sysinfo::Components;
+use tracing::info;
+
+use crate::{config::MonitoringConfig, disks};
+
+/// How often the sensors are read.
+const POLL_INTERVAL: Duration = Duration::from_secs(15);
+/// How far a temperature must fall below its limit before it can be warned about again, so one
+/// hovering around the limit doesn't keep warning.
+const HYSTERESIS_CELSIUS: f32 = 5.0;
+/// How far a disk must fall below its limit before it can be warned about again.
+const HYSTERESIS_PERCENT: f32 = 2.0;
+
+/// A warning for the user.
+pub struct Alert {
+    /// The tool that tells the AI more.
+    pub tool: &'static str,
+    /// What's wrong, and what the AI should do about it.
+    pub message: String,
+    /// What's wrong, for a desktop notification when nothing can be said.
+    pub notice: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Cpu,
+    Gpu,
+    Other,
+}
+
+/// A temperature sensor's reading.
+struct Temperature {
+    label: String,
+    kind: Kind,
+    celsius: f32,
+}
+
+/// A fan's speed.
+struct Fan {
+    label: String,
+    rpm: u32,
+}
+
+/// Works out what a sensor measures from its label, like "coretemp Package id 0" or
+/// "amdgpu edge".
+fn kind(label: &str) -> Kind {
+    let label = label.to_lowercase();
+    if ["amdgpu", "radeon", "nouveau", "gpu"]
+        .iter()
+        .any(|name| label.contains(name))
+    {
+        Kind::Gpu
+    } else if [
+        "coretemp", "k10temp", "zenpower", "package", "tctl", "tdie", "cpu",
+    ]
+    .iter()
+    .any(|name| label.contains(name))
+    {
+        Kind::Cpu
+    } else {
+        Kind::Other
+    }
+}
+
+fn temperatures(components: &mut Components) -> Vec<Temperature> {
+    components.refresh_list();
+    let mut temperatures: Vec<Temperature> = components
+        .iter()
+        .filter(|component| component.temperature().is_finite())
+        .map(|component| Temperature {
+            label: component.label().to_string(),
+            kind: kind(component.label()),
+            celsius: component.temperature(),
+        })
+        .collect();
+    // NVIDIA's own driver doesn't show up as a sensor.
+    if !temperatures.iter().any(|sensor| sensor.kind == Kind::Gpu) {
+        temperatures.extend(nvidia_temperatures());
+    }
+    temperatures
+}
+
+fn nvidia_temperatures() -> Vec<Temperature> {
+    let Ok(output) = std::process::Command::new("nvidia-smi")
+        .args([
+            "--query-gpu=name,temperature.gpu",
+            "--format=csv,noheader,nounits",
+        ])
+        .output()
+    else {
+        return Vec::new();
+    };
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (name, celsius) = line.rsplit_once(',')?;
+            Some(Temperature {
+                label: name.trim().to_string(),
+                kind: Kind::Gpu,
+                celsius: celsius.trim().parse().ok()?,
+            })
+        })
+        .collect()
+}
+
+/// The fans the system reports, from hwmon on Linux.
+fn fans() -> Vec<Fan> {
+    #[cfg(target_os = "linux")]
+    {
+        let mut fans = Vec::new();
+        let Ok(devices) = std::fs::read_dir("/sys/class/hwmon") else {
+            return fans;
+        };
+        for device in devices.flatten() {
+            let path = device.path();
+            let read = |name: &str| {
+                std::fs::read_to_string(path.join(name))
+                    .map(|text| text.trim().to_string())
+                    .ok()
+            };
+            let device_name = read("name").unwrap_or_default();
+            for number in 1..=16 {
+                let Some(rpm) = read(&format!("fan{}_input", number)) else {
+                    continue;
+                };
+                let Ok(rpm) = rpm.parse() else {
+                    continue;
+                };
+                let label = read(&format!("fan{}_label", number))
+                    .unwrap_or_else(|| format!("fan {}", number));
+                fans.push(Fan {
+                    label: format!("{} {}", device_name, label),
+                    rpm,
+                });
+            }
+        }
+        fans
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    Vec::new()
+}
+
+/// Describes every temperature and fan reading, for the AI.
+pub fn describe() -> String {
+    let temperatures = temperatures(&mut Components::new());
+    let fans = fans();
+    if temperatures.is_empty() && fans.is_empty() {
+        return "No temperature sensors or fans could be read on this computer.".to_string();
+    }
+    let mut info = String::new();
+    for sensor in &temperatures {
+        let kind = match sensor.kind {
+            Kind::Cpu => " (CPU)",
+            Kind::Gpu => " (GPU)",
+            Kind::Other => "",
+        };
+        info.push_str(&format!(
+            "{}{}: {:.0}°C\n",
+            sensor.label, kind, sensor.celsius
+        ));
+    }
+    for fan in &fans {
+        info.push_str(&format!("{}: {} RPM\n", fan.label, fan.rpm));
+    }
+    info
+}
+
+/// How long a reading has been past its limit.
+#[derive(Default)]
+struct Breach {
+    since: Option<Instant>,
+    warned: bool,
+}
+
+impl Breach {
+    /// Updates the breach with whether the reading is past its limit and whether it has recovered,
+    /// and returns true when it's time to warn.
+    fn update(&mut self, past_limit: bool, recovered: bool, sustained: Duration) -> bool {
+        if recovered {
+            *self = Self::default();
+            return false;
+        }
+        if !past_limit {
+            self.since = None;
+            return false;
+        }
+        let since = *self.since.get_or_insert_with(Instant::now);
+        if !self.warned && since.elapsed() >= sustained {
+            self.warned = true;
+            return true;
+        }
+        false
+    }
+}
+
+/// Starts a thread that sends a warning whenever a temperature or fan stays past its limit for
+/// the configured time, or a disk gets too full. Nothing is sent when monitoring is turned off.
+pub fn spawn_alerts(config: &MonitoringConfig) -> flume::Receiver<Alert> {
+    let (alert_tx, alert_rx) = flume::unbounded();
+    if !config.enabled {
+        return alert_rx;
+    }
+    let limits = [
+        (Kind::Cpu, "CPU", config.cpu_max_celsius),
+        (Kind::Gpu, "GPU", config.gpu_max_celsius),
+    ];
+    let fan_min_rpm = config.fan_min_rpm;
+    let disk_max_percent = config.disk_max_percent;
+    let sustained = Duration::from_secs(config.minutes * 60);
+
+    thread::spawn(move || {
+        let mut components = Components::new();
+        let mut breaches: HashMap<String, Breach> = HashMap::new();
+        loop {
+            let temperatures = temperatures(&mut components);
+            for (kind, name, limit) in limits {
+                // The hottest sensor of each kind, like the hottest of a CPU's cores.
+                let Some(hottest) = temperatures
+                    .iter()
+                    .filter(|sensor| sensor.kind == kind)
+                    .map(|sensor| sensor.celsius)
+                    .reduce(f32::max)
+                else {
+                    continue;
+                };
+                let breach = breaches.entry(name.to_string()).or_default();
+                if breach.update(
+                    hottest > limit,
+                    hottest < limit - HYSTERESIS_CELSIUS,
+                    sustained,
+                ) {
+                    info!("{} has been above {}°C", name, limit);
+                    let message = format!(
+                        "The {} has been above {:.0}°C for {}, and is {:.0}°C now. Warn the user, \
+                         and suggest closing demanding programs or checking the computer's cooling.",
+                        name,
+                        limit,
+                        humantime::format_duration(sustained),
+                        hottest
+                    );
+                    let alert = Alert {
+                        tool: "get_temperatures",
+                        message,
+                        notice: format!("The {} is at {:.0}°C.", name, hottest),
+                    };
+                    if alert_tx.send(alert).is_err() {
+                        return;
+                    }
+                }
+            }
+
+            if let Some(min_rpm) = fan_min_rpm {
+                for fan in fans() {
+                    let breach = breaches.entry(fan.label.clone()).or_default();
+                    if breach.update(fan.rpm < min_rpm, fan.rpm >= min_rpm, sustained) {
+                        info!("{} has been below {} RPM", fan.label, min_rpm);
+                        let message = format!(
+                            "The fan \"{}\" has been below {} RPM for {}, and is at {} RPM now. \
+                             Warn the user that it may have stopped.",
+                            fan.label,
+                            min_rpm,
+                            humantime::format_duration(sustained),
+                            fan.rpm
+                        );
+                        let alert = Alert {
+                            tool: "get_temperatures",
+                            message,
+                            notice: format!(
+                                "The fan \"{}\" is at {} RPM, and may have stopped.",
+                                fan.label, fan.rpm
+                            ),
+                        };
+                        if alert_tx.send(alert).is_err() {
+                            return;
+                        }
+                    }
+                }
+            }
+
+            for disk in disks::usage() {
+                let used = disk.used_percent();
+                let breach = breaches
+                    .entry(disk.mount_point.display().to_string())
+                    .or_default();
+                // Unlike temperatures, a full disk doesn't fix itself, so it's warned about at once.
+                if breach.update(
+                    used > disk_max_percent,
+                    used < disk_max_percent - HYSTERESIS_PERCENT,
+                    Duration::ZERO,
+                ) {
+                    info!("{} is {:.0}% full", disk.mount_point.display(), used);
+                    let message = format!(
+                        "The disk at {} is {:.0}% full, with {} free of {}. Warn the user, and \
+                         suggest emptying the trash or clearing out downloads and large files.",
+                        disk.mount_point.display(),
+                        used,
+                        disks::format_bytes(disk.available),
+                        disks::format_bytes(disk.total)
+                    );
+                    let alert = Alert {
+                        tool: "get_disk_usage",
+                        message,
+                        notice: format!(
+                            "The disk at {} is {:.0}% full.",
+                            disk.mount_point.display(),
+                            used
+                        ),
+                    };
+                    if alert_tx.send(alert).is_err() {
+                        return;
+                    }
+                }
+            }
+
+            thread::sleep(POLL_INTERVAL);
+        }
+    });
+
+    alert_rx
+}
@@ -0,0 +1,216 @@
+//! Current headlines for the get_news_headlines tool, read from RSS and Atom feeds.
+//! The feeds can be changed in the config file. Topics nothing in them covers are looked up on
+//! Google News.
+
+use anyhow::{bail, Context};
+use chrono::{DateTime, Local};
+use regex::Regex;
+use std::{
+    collections::HashSet,
+    sync::{LazyLock, OnceLock},
+    thread,
+    time::Duration,
+};
+use tracing::warn;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// The most headlines given to the AI.
+pub const MAX_HEADLINES: usize = 15;
+const MAX_SUMMARY_CHARS: usize = 200;
+
+const DEFAULT_FEEDS: &[&str] = &[
+    "https://feeds.bbci.co.uk/news/rss.xml",
+    "https://feeds.npr.org/1001/rss.xml",
+];
+
+static FEEDS: OnceLock<Vec<String>> = OnceLock::new();
+
+static HTML_TAG: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"<[^>]*>").unwrap());
+
+/// Sets the feeds headlines are read from, from the config file.
+pub fn configure(feeds: Vec<String>) {
+    let _ = FEEDS.set(feeds);
+}
+
+fn feeds() -> &'static [String] {
+    FEEDS.get_or_init(|| DEFAULT_FEEDS.iter().map(|feed| feed.to_string()).collect())
+}
+
+struct Headline {
+    title: String,
+    source: String,
+    published: Option<DateTime<Local>>,
+    summary: Option<String>,
+}
+
+/// The latest headlines from every feed, or the ones about `topic`, newest first.
+pub fn headlines(topic: Option<&str>, count: usize) -> Result<String, anyhow::Error> {
+    let count = count.clamp(1, MAX_HEADLINES);
+    let mut headlines = fetch_all(feeds())?;
+
+    if let Some(topic) = topic {
+        let words: Vec<String> = topic
+            .to_lowercase()
+            .split_whitespace()
+            .map(str::to_string)
+            .collect();
+        headlines.retain(|headline| {
+            let text = format!(
+                "{} {}",
+                headline.title,
+                headline.summary.as_deref().unwrap_or_default()
+            )
+            .to_lowercase();
+            words.iter().all(|word| text.contains(word.as_str()))
+        });
+        if headlines.is_empty() {
+            let search = reqwest::Url::parse_with_params(
+                "https://news.google.com/rss/search?hl=en-US&gl=US&ceid=US:en",
+                &[("q", topic)],
+            )?;
+            headlines = fetch_all(&[search.to_string()])?;
+        }
+    }
+    if headlines.is_empty() {
+        bail!("No headlines were found");
+    }
+
+    // The same story often comes from more than one feed.
+    let mut seen = HashSet::new();
+    headlines.retain(|headline| seen.insert(headline.title.to_lowercase()));
+    headlines.sort_by_key(|headline| std::cmp::Reverse(headline.published));
+
+    let mut info = match topic {
+        Some(topic) => format!("=== News about {}, newest first ===\n", topic),
+        None => "=== News headlines, newest first ===\n".to_string(),
+    };
+    for headline in headlines.into_iter().take(count) {
+        info.push_str(&format!("- {} ({}", headline.title, headline.source));
+        if let Some(published) = headline.published {
+            info.push_str(&format!(", {}", published.format("%a %H:%M")));
+        }
+        info.push_str(")\n");
+        if let Some(summary) = headline.summary {
+            info.push_str(&format!("  {}\n", summary));
+        }
+    }
+    Ok(info)
+}
+
+/// Reads every feed at once. A feed that fails is skipped, unless they all fail.
+fn fetch_all(feeds: &[String]) -> Result<Vec<Headline>, anyhow::Error> {
+    let results: Vec<Result<Vec<Headline>, anyhow::Error>> = thread::scope(|scope| {
+        let handles: Vec<_> = feeds
+            .iter()
+            .map(|feed| scope.spawn(move || fetch(feed)))
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|_| Err(anyhow::anyhow!("Reading the feed panicked")))
+            })
+            .collect()
+    });
+
+    let mut headlines = Vec::new();
+    let mut last_error = None;
+    for (feed, result) in feeds.iter().zip(results) {
+        match result {
+            Ok(feed_headlines) => headlines.extend(feed_headlines),
+            Err(err) => {
+                warn!("Failed to read news feed {}: {:#}", feed, err);
+                last_error = Some(err);
+            }
+        }
+    }
+    match (headlines.is_empty(), last_error) {
+        (true, Some(err)) => Err(err.context("Failed to read the news feeds")),
+        _ => Ok(headlines),
+    }
+}
+
+fn fetch(url: &str) -> Result<Vec<Headline>, anyhow::Error> {
+    let text = reqwest::blocking::Client::new()
+        .get(url)
+        .timeout(REQUEST_TIMEOUT)
+        .send()?
+        .error_for_status()?
+        .text()?;
+    parse(&text).with_context(|| format!("{} isn't an RSS or Atom feed", url))
+}
+
+fn child_text<'a>(node: roxmltree::Node<'a, 'a>, name: &str) -> Option<&'a str> {
+    node.children()
+        .find(|child| child.tag_name().name() == name)
+        .and_then(|child| child.text())
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+}
+
+/// Turns a feed's HTML summary into a short line of plain text.
+fn clean_summary(summary: &str) -> Option<String> {
+    let text = HTML_TAG.replace_all(summary, " ");
+    let text = text
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'");
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!text.is_empty()).then(|| crate::truncate(&text, MAX_SUMMARY_CHARS))
+}
+
+/// Reads the items of an RSS feed or the entries of an Atom feed.
+fn parse(text: &str) -> Result<Vec<Headline>, anyhow::Error> {
+    // Some feeds start with a DOCTYPE, which is refused unless it's allowed.
+    let options = roxmltree::ParsingOptions {
+        allow_dtd: true,
+        ..Default::default()
+    };
+    let document = roxmltree::Document::parse_with_options(text, options)?;
+    let root = document.root_element();
+    let headlines = match root.tag_name().name() {
+        "rss" | "RDF" => {
+            let source = root
+                .descendants()
+                .find(|node| node.tag_name().name() == "channel")
+                .and_then(|channel| child_text(channel, "title"))
+                .unwrap_or("News")
+                .to_string();
+            root.descendants()
+                .filter(|node| node.tag_name().name() == "item")
+                .filter_map(|item| {
+                    Some(Headline {
+                        title: child_text(item, "title")?.to_string(),
+                        // Google News gives each story's own source.
+                        source: child_text(item, "source").unwrap_or(&source).to_string(),
+                        published: child_text(item, "pubDate")
+                            .and_then(|date| DateTime::parse_from_rfc2822(date).ok())
+                            .map(|date| date.with_timezone(&Local)),
+                        summary: child_text(item, "description").and_then(clean_summary),
+                    })
+                })
+                .collect()
+        }
+        "feed" => {
+            let source = child_text(root, "title").unwrap_or("News").to_string();
+            root.children()
+                .filter(|node| node.tag_name().name() == "entry")
+                .filter_map(|entry| {
+                    Some(Headline {
+                        title: child_text(entry, "title")?.to_string(),
+                        source: source.clone(),
+                        published: child_text(entry, "updated")
+                            .or_else(|| child_text(entry, "published"))
+                            .and_then(|date| DateTime::parse_from_rfc3339(date).ok())
+                            .map(|date| date.with_timezone(&Local)),
+                        summary: child_text(entry, "summary").and_then(clean_summary),
+                    })
+                })
+                .collect()
+        }
+        other => bail!("Unexpected <{}> element", other),
+    };
+    Ok(headlines)
+}
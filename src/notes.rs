@@ -0,0 +1,78 @@
+//! Daily notes for the append_note and read_todays_notes tools. Each day's notes are a markdown
+//! file named like 2024-05-01.md, the way Obsidian's daily notes are, so the folder can be a vault's.
+
+use anyhow::{bail, Context};
+use chrono::{Local, NaiveDate};
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+static FOLDER: OnceLock<PathBuf> = OnceLock::new();
+
+/// Sets the folder notes are kept in, from the config file.
+pub fn configure(folder: PathBuf) {
+    let folder = match folder.strip_prefix("~") {
+        Ok(rest) => dirs::home_dir().unwrap_or_default().join(rest),
+        Err(_) => folder,
+    };
+    let _ = FOLDER.set(folder);
+}
+
+fn folder() -> &'static Path {
+    FOLDER.get_or_init(|| {
+        dirs::document_dir()
+            .or_else(dirs::home_dir)
+            .unwrap_or_default()
+            .join("quick-assistant")
+            .join("notes")
+    })
+}
+
+fn path_for(date: NaiveDate) -> PathBuf {
+    folder().join(format!("{}.md", date.format("%Y-%m-%d")))
+}
+
+/// Adds a note to today's file as a bullet with the time, and returns the file's path.
+pub fn append(text: &str) -> Result<PathBuf, anyhow::Error> {
+    let text = text.trim();
+    if text.is_empty() {
+        bail!("The note is empty");
+    }
+    let folder = folder();
+    fs::create_dir_all(folder).with_context(|| format!("Failed to create {}", folder.display()))?;
+
+    let now = Local::now();
+    let path = path_for(now.date_naive());
+    let is_new = !path.exists();
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut entry = String::new();
+    if is_new {
+        entry.push_str(&format!("# {}\n\n", now.format("%A, %B %-d, %Y")));
+    }
+    // Lines after the first are indented, so they stay part of the bullet.
+    entry.push_str(&format!(
+        "- {} {}\n",
+        now.format("%H:%M"),
+        text.lines().collect::<Vec<_>>().join("\n  ")
+    ));
+    file.write_all(entry.as_bytes())
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+/// The notes for `date`, or None if none were taken that day.
+pub fn read(date: NaiveDate) -> Result<Option<String>, anyhow::Error> {
+    let path = path_for(date);
+    match fs::read_to_string(&path) {
+        Ok(text) => Ok(Some(text)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err).with_context(|| format!("Failed to read {}", path.display())),
+    }
+}
@@ -0,0 +1,127 @@
+use clap::ValueEnum;
+use notify_rust::Notification;
+use std::{thread, time::Duration};
+use tracing::warn;
+
+use crate::{
+    conversation::{self, ConversationEvent},
+    focus,
+    timers::{AudibleTimers, Timer},
+};
+
+/// The most characters of a response shown in a notification.
+const MAX_RESPONSE_CHARS: usize = 300;
+
+/// When the AI's responses are shown as desktop notifications.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum ResponseNotifications {
+    Never,
+    /// Only while the terminal isn't the focused window. Where that can't be told, always.
+    Unfocused,
+    Always,
+}
+
+/// Shows a desktop notification for a timer that went off, so it's seen even if the computer is muted.
+/// Where the platform supports it, the notification has buttons to snooze or dismiss the alarm.
+pub fn notify_timer_expired(
+    timer: &Timer,
+    audible_timers: &AudibleTimers,
+    snooze_duration: Duration,
+) {
+    let mut notification = Notification::new();
+    notification
+        .summary("Timer went off")
+        .body(if timer.description.is_empty() {
+            "Your timer went off."
+        } else {
+            &timer.description
+        })
+        .appname("quick-assistant");
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        notification
+            .action(
+                "snooze",
+                &format!("Snooze {}", humantime::format_duration(snooze_duration)),
+            )
+            .action("dismiss", "Dismiss");
+
+        // Waiting for a button to be pressed blocks, so it's done on its own thread.
+        let timer_id = timer.id;
+        let audible_timers = audible_timers.clone();
+        thread::spawn(move || match notification.show() {
+            Ok(handle) => handle.wait_for_action(|action| match action {
+                "snooze" => {
+                    if let Err(e) = audible_timers.snooze(Some(timer_id), snooze_duration) {
+                        warn!("Failed to snooze timer from notification: {}", e);
+                    }
+                }
+                "dismiss" => {
+                    audible_timers.dismiss(Some(timer_id));
+                }
+                _ => {}
+            }),
+            Err(e) => warn!("Failed to show timer notification: {}", e),
+        });
+    }
+
+    #[cfg(not(all(unix, not(target_os = "macos"))))]
+    {
+        let _ = (audible_timers, snooze_duration);
+        thread::spawn(move || {
+            if let Err(e) = notification.show() {
+                warn!("Failed to show timer notification: {}", e);
+            }
+        });
+    }
+}
+
+/// Shows each of the AI's finished responses as a desktop notification, so muted or unattended use still
+/// gets seen.
+pub fn notify_responses(when: ResponseNotifications) {
+    if when == ResponseNotifications::Never {
+        return;
+    }
+
+    let events = conversation::subscribe();
+    thread::spawn(move || {
+        let mut response = String::new();
+        for event in events.iter() {
+            match event {
+                ConversationEvent::User { .. } => response.clear(),
+                ConversationEvent::Token { text } => response.push_str(&text),
+                ConversationEvent::ResponseDone => {
+                    let text = std::mem::take(&mut response);
+                    let text = text.trim();
+                    let focused = focus::terminal_is_focused().unwrap_or(false);
+                    if text.is_empty() || (when == ResponseNotifications::Unfocused && focused) {
+                        continue;
+                    }
+                    notify_response(text);
+                }
+                _ => {}
+            }
+        }
+    });
+}
+
+fn notify_response(text: &str) {
+    let body = match text.char_indices().nth(MAX_RESPONSE_CHARS) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text.to_string(),
+    };
+    notify("quick-assistant", &body);
+}
+
+/// Shows a desktop notification, for something the assistant can't say out loud.
+pub fn notify(summary: &str, body: &str) {
+    if let Err(e) = Notification::new()
+        .summary(summary)
+        .body(body)
+        .appname("quick-assistant")
+        .show()
+    {
+        warn!("Failed to show notification: {}", e);
+    }
+}
@@ -0,0 +1,153 @@
+//! Opening web pages and folders for the open_url and open_path tools.
+//! Sites on the allowed domains and paths in the folders that can be searched open straight away.
+//! Anything else needs the user to confirm it first, unless that's been turned off.
+
+use anyhow::{bail, Context};
+use reqwest::Url;
+use std::{path::Path, sync::OnceLock};
+
+use crate::file_search;
+
+/// The kinds of files that can be opened: documents, pictures, music and videos, which are shown
+/// or played rather than run. Anything else could be a program, a script, a shortcut or a
+/// registry file that Windows or the desktop would run when opened, so it isn't opened.
+#[rustfmt::skip]
+const DOCUMENT_EXTENSIONS: &[&str] = &[
+    // Documents
+    "pdf", "txt", "md", "log", "csv", "rtf", "odt", "ods", "odp", "doc", "docx", "xls", "xlsx", "ppt", "pptx", "epub",
+    // Pictures
+    "png", "jpg", "jpeg", "gif", "webp", "bmp", "tif", "tiff", "heic",
+    // Music
+    "mp3", "wav", "flac", "ogg", "oga", "opus", "m4a", "aac",
+    // Videos
+    "mp4", "m4v", "mkv", "webm", "mov", "avi",
+];
+
+struct Settings {
+    allowed_domains: Vec<String>,
+    confirm_others: bool,
+}
+
+static SETTINGS: OnceLock<Settings> = OnceLock::new();
+
+/// Sets which domains open without asking, and whether anything else asks first, from the config file.
+pub fn configure(allowed_domains: Vec<String>, confirm_others: bool) {
+    let _ = SETTINGS.set(Settings {
+        allowed_domains: allowed_domains
+            .iter()
+            .map(|domain| domain.trim().trim_start_matches("www.").to_lowercase())
+            .collect(),
+        confirm_others,
+    });
+}
+
+fn settings() -> &'static Settings {
+    SETTINGS.get_or_init(|| Settings {
+        allowed_domains: Vec::new(),
+        confirm_others: true,
+    })
+}
+
+/// What happened when something was asked to be opened.
+pub enum Opening {
+    /// It was opened. Holds what was opened.
+    Opened(String),
+    /// It wasn't opened because the user has to agree to it first. Holds what would be opened.
+    NeedsConfirmation(String),
+}
+
+/// Opens a web page in the default browser. `url` can leave out the `https://`.
+pub fn open_url(url: &str, confirmed: bool) -> Result<Opening, anyhow::Error> {
+    let url = url.trim();
+    let url = match url.contains("://") {
+        true => Url::parse(url),
+        false => Url::parse(&format!("https://{}", url)),
+    }
+    .with_context(|| format!("\"{}\" isn't a valid URL", url))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        bail!("Only http and https links can be opened");
+    }
+    let Some(host) = url.host_str().map(str::to_lowercase) else {
+        bail!("\"{}\" has no website in it", url);
+    };
+
+    let allowed = settings()
+        .allowed_domains
+        .iter()
+        .any(|domain| host == *domain || host.ends_with(&format!(".{}", domain)));
+    if !allowed && !confirmed && settings().confirm_others {
+        return Ok(Opening::NeedsConfirmation(url.to_string()));
+    }
+    open::that_detached(url.as_str()).with_context(|| format!("Failed to open {}", url))?;
+    Ok(Opening::Opened(url.to_string()))
+}
+
+/// Opens a folder or file the way the file browser would. `path` can be a folder name like
+/// "downloads", or start with `~`. Programs and scripts aren't run.
+pub fn open_path(path: &str, confirmed: bool) -> Result<Opening, anyhow::Error> {
+    let path = file_search::resolve_root(path);
+    let path = path
+        .canonicalize()
+        .with_context(|| format!("{} wasn't found", path.display()))?;
+    if !path.is_dir() && !is_document(&path) {
+        bail!(
+            "{} isn't a document, picture, song or video, so it can't be opened this way. Use open_application for apps.",
+            path.display()
+        );
+    }
+
+    let allowed = file_search::is_allowed(&path);
+    if !allowed && !confirmed && settings().confirm_others {
+        return Ok(Opening::NeedsConfirmation(path.display().to_string()));
+    }
+    open::that_detached(&path).with_context(|| format!("Failed to open {}", path.display()))?;
+    Ok(Opening::Opened(path.display().to_string()))
+}
+
+/// Whether a file is a kind that's shown or played when opened, rather than run.
+fn is_document(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| {
+        let extension = extension.to_string_lossy().to_lowercase();
+        DOCUMENT_EXTENSIONS.contains(&extension.as_str())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_documents_open() {
+        for file in [
+            "report.PDF",
+            "notes.txt",
+            "photo.jpeg",
+            "song.flac",
+            "clip.mkv",
+        ] {
+            assert!(is_document(Path::new(file)), "{}", file);
+        }
+        for file in [
+            "setup.exe",
+            "run.bat",
+            "page.hta",
+            "script.wsf",
+            "panel.cpl",
+            "console.msc",
+            "settings.reg",
+            "old.pif",
+            "explorer.scf",
+            "link.url",
+            "encoded.vbe",
+            "encoded.jse",
+            "shortcut.lnk",
+            "app.desktop",
+            "tool.sh",
+            "page.html",
+            "archive.tar.gz",
+            "README",
+        ] {
+            assert!(!is_document(Path::new(file)), "{}", file);
+        }
+    }
+}
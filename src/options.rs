@@ -1,6 +1,10 @@
 use clap::Parser;
+use std::path::PathBuf;
 
-use crate::{easy_rdev_key, SubCommands, VoiceEnum};
+use crate::{
+    easy_rdev_key, notifications::ResponseNotifications, tick::TickSound, SubCommands,
+    TtsModelEnum, VoiceEnum,
+};
 
 #[derive(Parser, Debug)]
 #[command(version)]
@@ -24,16 +28,131 @@ pub struct Opt {
     #[arg(long, conflicts_with("ptt_key"))]
     pub special_ptt_key: Option<u32>,
 
+    /// A key that skips the sentence the AI is currently speaking.
+    #[arg(long)]
+    pub skip_sentence_key: Option<easy_rdev_key::PTTKey>,
+
+    /// A key that pauses the AI's speech, or resumes it if it's paused.
+    #[arg(long)]
+    pub pause_speech_key: Option<easy_rdev_key::PTTKey>,
+
+    /// A key that makes the AI repeat its last response.
+    #[arg(long)]
+    pub repeat_response_key: Option<easy_rdev_key::PTTKey>,
+
+    /// A key that snoozes a ringing alarm.
+    #[arg(long)]
+    pub snooze_key: Option<easy_rdev_key::PTTKey>,
+
+    /// A key that dismisses a ringing alarm.
+    #[arg(long)]
+    pub dismiss_key: Option<easy_rdev_key::PTTKey>,
+
+    /// A key that pauses listening, or resumes it if it's paused. While paused the push to talk key
+    /// is ignored, and timers and reminders aren't announced.
+    #[arg(long)]
+    pub pause_listening_key: Option<easy_rdev_key::PTTKey>,
+
+    /// How many minutes the snooze key snoozes an alarm for.
+    #[arg(long, default_value_t = 10)]
+    pub snooze_minutes: u64,
+
+    /// Don't show a desktop notification when a timer goes off.
+    #[arg(long)]
+    pub no_timer_notifications: bool,
+
+    /// When to show the AI's responses as desktop notifications, so they're seen while muted or away
+    /// from the terminal. "unfocused" only shows them while the terminal isn't the focused window.
+    #[arg(long, value_enum, default_value_t = ResponseNotifications::Never)]
+    pub response_notifications: ResponseNotifications,
+
     /// How fast the AI speaks, with 1.0 as normal speed.
     /// The value must be between 0.5 (slowest) and 100.0 (fastest).
     #[arg(long, default_value_t = 1.0)]
     pub speech_speed: f32,
 
+    /// How many sentences can be converted to speech ahead of the one being spoken.
+    /// Lower values waste less text to speech on responses that get interrupted.
+    #[arg(long, default_value_t = 10)]
+    pub speech_buffer_size: usize,
+
+    /// The most characters of a single response that will be spoken. The rest is only shown as text.
+    /// Useful for keeping text to speech costs down with verbose models.
+    #[arg(long)]
+    pub max_speech_chars: Option<usize>,
+
+    /// Start with the AI voice muted. Responses are only shown as text,
+    /// and nothing is sent to be turned into speech.
+    #[arg(long)]
+    pub mute: bool,
+
+    /// How many milliseconds consecutive sentences overlap, with each one fading in over the end of the last.
+    /// 0 plays sentences back to back.
+    #[arg(long, default_value_t = 0)]
+    pub speech_crossfade_ms: u64,
+
+    /// The volume of the AI voice as a percentage, independent of the system volume.
+    /// The value must be between 0 and 100.
+    #[arg(long, default_value_t = 100)]
+    pub ai_volume: u32,
+
+    /// Lower other audio while the AI is speaking.
+    #[arg(long)]
+    pub duck: bool,
+
+    /// Lower other audio while the push-to-talk key is held.
+    #[arg(long)]
+    pub duck_ptt: bool,
+
+    /// How loud ducked audio plays, as a percentage of its normal volume.
+    #[arg(long, default_value_t = 30)]
+    pub duck_volume: u32,
+
+    /// The sound played while the AI is thinking.
+    #[arg(long, value_enum, default_value_t = TickSound::Tick)]
+    pub tick_sound: TickSound,
+
+    /// The volume of the thinking sound as a percentage. The value must be between 0 and 100.
+    #[arg(long, default_value_t = 25)]
+    pub tick_volume: u32,
+
+    /// How many milliseconds apart thinking sounds are played.
+    #[arg(long, default_value_t = 1000)]
+    pub tick_interval_ms: u64,
+
     /// The voice that the AI will use to speak.
     /// Choose from a list of available voices to customize the output.
     #[arg(long)]
     pub ai_voice: Option<VoiceEnum>,
 
+    /// The text to speech model used for the AI's voice.
+    #[arg(long, value_enum, default_value_t = TtsModelEnum::Tts1)]
+    pub tts_model: TtsModelEnum,
+
+    /// Instructions for how the AI's voice should speak. For example: "speak cheerfully".
+    /// Only supported by the gpt-4o-mini-tts model.
+    #[arg(long)]
+    pub tts_instructions: Option<String>,
+
+    /// A file that always contains the sentence the AI is currently speaking, and is empty otherwise.
+    /// Useful as a live caption source for streaming software or an on-screen overlay.
+    #[arg(long)]
+    pub captions_file: Option<PathBuf>,
+
+    /// An ICS calendar to read upcoming events from. Either a file path or an http(s) or webcal URL.
+    #[arg(long)]
+    pub calendar: Option<String>,
+
+    /// Remind you of calendar events this many minutes before they start.
+    /// Requires `--calendar`.
+    #[arg(long, requires("calendar"))]
+    pub calendar_reminder_minutes: Option<u64>,
+
+    /// A profile from the config file to start with, like "work" for `[profile.work]`.
+    /// Its settings replace the matching command line options.
+    #[arg(long)]
+    pub profile: Option<String>,
+
     /// The language model used to generate responses.
     /// Specify the name of the language model. For a list of available models, visit:
     /// https://platform.openai.com/docs/models/.
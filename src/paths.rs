@@ -0,0 +1,17 @@
+//! Where the assistant keeps its files. The other binaries include this too, so they find the same
+//! files as the assistant.
+
+use std::{env, path::PathBuf, sync::LazyLock};
+
+/// Where caches, logs, settings and saved conversations go. Systems without a cache folder get
+/// one in the temporary folder instead, and so do tests, so they never touch the user's files.
+pub static CACHE_DIR: LazyLock<PathBuf> = LazyLock::new(|| {
+    if cfg!(test) {
+        let dir = env::temp_dir().join(format!("quick-assistant-test-{}", std::process::id()));
+        let _ = std::fs::create_dir_all(&dir);
+        return dir;
+    }
+    dirs::cache_dir()
+        .unwrap_or_else(env::temp_dir)
+        .join("quick-assistant")
+});
@@ -0,0 +1,221 @@
+use anyhow::bail;
+use chrono::{DateTime, Local};
+use std::{
+    sync::{LazyLock, Mutex},
+    time::Duration,
+};
+use tracing::info;
+
+use crate::timers::{delete_timer, set_timer, AlarmSettings, Timer};
+
+// How long the alarm rings at the end of each pomodoro phase.
+const PHASE_ALARM_DURATION: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Copy, PartialEq)]
+enum Phase {
+    Work,
+    Break,
+}
+
+impl Phase {
+    fn name(self) -> &'static str {
+        match self {
+            Phase::Work => "work",
+            Phase::Break => "break",
+        }
+    }
+
+    /// The sound played when this phase ends, so work and breaks ending sound different.
+    fn end_sound(self) -> &'static str {
+        match self {
+            Phase::Work => "recording-stopped",
+            Phase::Break => "recording-started",
+        }
+    }
+}
+
+/// A running pomodoro. Each phase is a timer that starts the next phase when it goes off.
+struct Pomodoro {
+    work: Duration,
+    break_: Duration,
+    cycles: u32,
+    // The current cycle, starting at 1.
+    cycle: u32,
+    phase: Phase,
+    // The timer ending the current phase. None while paused.
+    timer_id: Option<u64>,
+    phase_end: DateTime<Local>,
+    // How much of the current phase was left when it was paused.
+    paused_remaining: Option<Duration>,
+}
+
+static POMODORO: LazyLock<Mutex<Option<Pomodoro>>> = LazyLock::new(|| Mutex::new(None));
+
+impl Pomodoro {
+    fn phase_duration(&self) -> Duration {
+        match self.phase {
+            Phase::Work => self.work,
+            Phase::Break => self.break_,
+        }
+    }
+
+    /// Sets the timer that ends the current phase after `duration`.
+    fn schedule(&mut self, duration: Duration) -> Result<(), anyhow::Error> {
+        let Some(phase_end) =
+            Local::now().checked_add_signed(chrono::Duration::from_std(duration)?)
+        else {
+            bail!("A {} phase can't last that long", self.phase.name());
+        };
+        self.phase_end = phase_end;
+        let alarm = AlarmSettings {
+            sound: Some(self.phase.end_sound().to_string()),
+            ring_duration: PHASE_ALARM_DURATION,
+            re_rings: 0,
+            ..Default::default()
+        };
+        let description = format!(
+            "Pomodoro: {} session {} of {} is over",
+            self.phase.name(),
+            self.cycle,
+            self.cycles
+        );
+        self.timer_id = Some(set_timer(description, self.phase_end, alarm)?);
+        Ok(())
+    }
+
+    fn status(&self) -> String {
+        let remaining = match self.paused_remaining {
+            Some(remaining) => remaining,
+            None => (self.phase_end - Local::now()).to_std().unwrap_or_default(),
+        };
+        format!(
+            "Pomodoro is {} in {} session {} of {}, with {} left{}.",
+            if self.paused_remaining.is_some() {
+                "paused"
+            } else {
+                "running"
+            },
+            self.phase.name(),
+            self.cycle,
+            self.cycles,
+            humantime::format_duration(Duration::from_secs(remaining.as_secs())),
+            if self.paused_remaining.is_some() {
+                ""
+            } else {
+                " in this session"
+            },
+        )
+    }
+}
+
+/// Starts a pomodoro of `cycles` work sessions with breaks between them, replacing any running pomodoro.
+pub fn start(work: Duration, break_: Duration, cycles: u32) -> Result<(), anyhow::Error> {
+    if work.is_zero() || cycles == 0 {
+        bail!("A pomodoro needs a work session length and at least one cycle");
+    }
+    stop();
+
+    let mut pomodoro = Pomodoro {
+        work,
+        break_,
+        cycles,
+        cycle: 1,
+        phase: Phase::Work,
+        timer_id: None,
+        phase_end: Local::now(),
+        paused_remaining: None,
+    };
+    pomodoro.schedule(work)?;
+    info!("Pomodoro started with {} cycles", cycles);
+    *POMODORO.lock().unwrap() = Some(pomodoro);
+    Ok(())
+}
+
+/// Stops the running pomodoro. Returns whether there was one.
+pub fn stop() -> bool {
+    match POMODORO.lock().unwrap().take() {
+        Some(pomodoro) => {
+            if let Some(id) = pomodoro.timer_id {
+                let _ = delete_timer(id);
+            }
+            info!("Pomodoro stopped");
+            true
+        }
+        None => false,
+    }
+}
+
+/// Pauses the running pomodoro, keeping how much of the current session is left.
+pub fn pause() -> Result<(), anyhow::Error> {
+    let mut state = POMODORO.lock().unwrap();
+    let Some(pomodoro) = state.as_mut() else {
+        bail!("No pomodoro is running");
+    };
+    let Some(id) = pomodoro.timer_id.take() else {
+        bail!("The pomodoro is already paused");
+    };
+    delete_timer(id)?;
+    pomodoro.paused_remaining = Some(
+        (pomodoro.phase_end - Local::now())
+            .to_std()
+            .unwrap_or_default(),
+    );
+    Ok(())
+}
+
+/// Resumes a paused pomodoro where it left off.
+pub fn resume() -> Result<(), anyhow::Error> {
+    let mut state = POMODORO.lock().unwrap();
+    let Some(pomodoro) = state.as_mut() else {
+        bail!("No pomodoro is running");
+    };
+    let Some(remaining) = pomodoro.paused_remaining.take() else {
+        bail!("The pomodoro isn't paused");
+    };
+    pomodoro.schedule(remaining)
+}
+
+/// Describes the running pomodoro, if there is one.
+pub fn status() -> Option<String> {
+    POMODORO.lock().unwrap().as_ref().map(Pomodoro::status)
+}
+
+/// Moves the pomodoro on to its next phase if `timer` ended the current one.
+/// Returns a message for the AI about the change, or None if the timer isn't part of the pomodoro.
+pub fn on_timer_expired(timer: &Timer) -> Option<String> {
+    let mut state = POMODORO.lock().unwrap();
+    let pomodoro = state.as_mut()?;
+    if pomodoro.timer_id != Some(timer.id) {
+        return None;
+    }
+    pomodoro.timer_id = None;
+
+    let message = match pomodoro.phase {
+        Phase::Work if pomodoro.cycle == pomodoro.cycles => {
+            let cycles = pomodoro.cycles;
+            *state = None;
+            return Some(format!(
+                "The last pomodoro work session is over. The pomodoro is finished after {} cycles. Congratulate the user.",
+                cycles
+            ));
+        }
+        Phase::Work => {
+            pomodoro.phase = Phase::Break;
+            "A pomodoro work session is over and a break has started. Tell the user to take a break."
+        }
+        Phase::Break => {
+            pomodoro.cycle += 1;
+            pomodoro.phase = Phase::Work;
+            "A pomodoro break is over and the next work session has started. Tell the user to get back to work."
+        }
+    };
+
+    if let Err(err) = pomodoro.schedule(pomodoro.phase_duration()) {
+        *state = None;
+        return Some(format!(
+            "The pomodoro stopped because its next session couldn't be started: {}",
+            err
+        ));
+    }
+    Some(format!("{}\n{}", message, pomodoro.status()))
+}
@@ -0,0 +1,118 @@
+//! Locking the screen, sleeping, and shutting down or restarting the computer.
+//! Each system has its own commands for these, which are run here.
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+use anyhow::Context;
+
+use crate::system_command::run;
+
+/// The longest a shutdown or restart can be put off for.
+pub const MAX_DELAY_MINUTES: u32 = 24 * 60;
+
+/// Locks the screen, so the password is needed to get back in.
+pub fn lock_screen() -> Result<(), anyhow::Error> {
+    #[cfg(target_os = "linux")]
+    return run("loginctl", &["lock-session"])
+        .or_else(|_| run("xdg-screensaver", &["lock"]))
+        .context("Failed to lock the screen");
+
+    #[cfg(windows)]
+    return run("rundll32.exe", &["user32.dll,LockWorkStation"]);
+
+    // Turning the display off only locks it if the password is asked for straight away, so the
+    // lock screen shortcut is pressed instead, which needs the Accessibility permission. Older
+    // versions have CGSession, which doesn't.
+    #[cfg(target_os = "macos")]
+    return run(
+        "/System/Library/CoreServices/Menu Extras/User.menu/Contents/Resources/CGSession",
+        &["-suspend"],
+    )
+    .or_else(|_| {
+        run(
+            "osascript",
+            &[
+                "-e",
+                "tell application \"System Events\" to keystroke \"q\" using {control down, command down}",
+            ],
+        )
+    })
+    .context("Failed to lock the screen. Give quick-assistant the Accessibility permission in System Settings.");
+
+    #[allow(unreachable_code)]
+    Err(anyhow::anyhow!(
+        "Locking the screen isn't supported on this system"
+    ))
+}
+
+/// Puts the computer to sleep.
+pub fn sleep() -> Result<(), anyhow::Error> {
+    #[cfg(target_os = "linux")]
+    return run("systemctl", &["suspend"]);
+
+    // This hibernates instead where hibernation is turned on.
+    #[cfg(windows)]
+    return run("rundll32.exe", &["powrprof.dll,SetSuspendState", "0,1,0"]);
+
+    #[cfg(target_os = "macos")]
+    return run("pmset", &["sleepnow"]);
+
+    #[allow(unreachable_code)]
+    Err(anyhow::anyhow!(
+        "Putting the computer to sleep isn't supported on this system"
+    ))
+}
+
+/// Shuts down or restarts the computer in `delay_minutes`, or straight away if it's 0.
+pub fn shutdown(delay_minutes: u32, restart: bool) -> Result<(), anyhow::Error> {
+    #[cfg(target_os = "linux")]
+    {
+        let when = match delay_minutes {
+            0 => "now".to_string(),
+            minutes => format!("+{}", minutes),
+        };
+        let mode = if restart { "-r" } else { "-h" };
+        return run("shutdown", &[mode, &when]);
+    }
+
+    #[cfg(windows)]
+    {
+        let mode = if restart { "/r" } else { "/s" };
+        let seconds = delay_minutes.saturating_mul(60).to_string();
+        return run("shutdown", &[mode, "/t", &seconds]);
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        // Scheduling a shutdown needs root on macOS, but asking System Events to do it now doesn't.
+        if delay_minutes > 0 {
+            anyhow::bail!("Shutting down later isn't supported on macOS, only straight away");
+        }
+        let action = if restart { "restart" } else { "shut down" };
+        return run(
+            "osascript",
+            &[
+                "-e",
+                &format!("tell application \"System Events\" to {}", action),
+            ],
+        );
+    }
+
+    #[allow(unreachable_code)]
+    Err(anyhow::anyhow!(
+        "Shutting down the computer isn't supported on this system"
+    ))
+}
+
+/// Cancels a shutdown or restart that was scheduled for later.
+pub fn cancel_shutdown() -> Result<(), anyhow::Error> {
+    #[cfg(target_os = "linux")]
+    return run("shutdown", &["-c"]);
+
+    #[cfg(windows)]
+    return run("shutdown", &["/a"]);
+
+    #[allow(unreachable_code)]
+    Err(anyhow::anyhow!(
+        "Cancelling a shutdown isn't supported on this system"
+    ))
+}
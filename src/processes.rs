@@ -0,0 +1,272 @@
+//! Summaries of the running processes for the top_processes and get_process_details tools,
+//! kept short enough to be useful to the AI rather than listing every process on the system.
+
+use anyhow::bail;
+use chrono::{DateTime, Local};
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+use sysinfo::{Pid, Process, ProcessStatus, ProcessesToUpdate, System};
+
+/// The most processes top_processes lists.
+pub const MAX_TOP: usize = 25;
+/// The most processes get_process_details describes when several match.
+const MAX_DETAILS: usize = 10;
+/// How often watched processes are checked on.
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// What to rank processes by.
+#[derive(Debug, Clone, Copy)]
+pub enum SortBy {
+    Cpu,
+    Memory,
+}
+
+impl SortBy {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "cpu" | "processor" => Some(Self::Cpu),
+            "memory" | "ram" | "mem" => Some(Self::Memory),
+            _ => None,
+        }
+    }
+}
+
+/// Reads the processes twice, a moment apart, since CPU usage is measured between two readings.
+fn snapshot() -> System {
+    let mut system = System::new();
+    system.refresh_memory();
+    system.refresh_cpu_usage();
+    system.refresh_processes(ProcessesToUpdate::All, true);
+    thread::sleep(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL.max(Duration::from_millis(300)));
+    system.refresh_processes(ProcessesToUpdate::All, true);
+    system
+}
+
+/// A process's share of all the CPUs, so a busy process on an 8 core machine is at most 100%.
+fn cpu_percent(system: &System, process: &Process) -> f32 {
+    process.cpu_usage() / system.cpus().len().max(1) as f32
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const MB: f64 = 1024.0 * 1024.0;
+    let megabytes = bytes as f64 / MB;
+    match megabytes >= 1024.0 {
+        true => format!("{:.1} GB", megabytes / 1024.0),
+        false => format!("{:.0} MB", megabytes),
+    }
+}
+
+fn format_duration(seconds: u64) -> String {
+    let (days, hours, minutes) = (seconds / 86400, seconds / 3600 % 24, seconds / 60 % 60);
+    match (days, hours) {
+        (0, 0) => format!("{}m", minutes),
+        (0, _) => format!("{}h {}m", hours, minutes),
+        _ => format!("{}d {}h", days, hours),
+    }
+}
+
+/// The `count` processes using the most CPU or memory, one per line.
+pub fn top(sort_by: SortBy, count: usize) -> String {
+    let system = snapshot();
+    let mut processes: Vec<&Process> = system
+        .processes()
+        .values()
+        // Threads show up as processes on Linux.
+        .filter(|process| process.thread_kind().is_none())
+        .collect();
+    match sort_by {
+        SortBy::Cpu => processes.sort_by(|a, b| b.cpu_usage().total_cmp(&a.cpu_usage())),
+        SortBy::Memory => processes.sort_by_key(|process| std::cmp::Reverse(process.memory())),
+    }
+    let count = count.clamp(1, MAX_TOP);
+
+    let mut info = format!(
+        "=== Top {} of {} processes by {} ===\nCPU is a share of all {} cores. Memory used: {} of {}\n",
+        count.min(processes.len()),
+        processes.len(),
+        match sort_by {
+            SortBy::Cpu => "CPU",
+            SortBy::Memory => "memory",
+        },
+        system.cpus().len(),
+        format_bytes(system.used_memory()),
+        format_bytes(system.total_memory()),
+    );
+    for process in processes.into_iter().take(count) {
+        info.push_str(&format!(
+            "[{}] {} cpu: {:.1}% memory: {}\n",
+            process.pid(),
+            process.name().to_string_lossy(),
+            cpu_percent(&system, process),
+            format_bytes(process.memory()),
+        ));
+    }
+    info
+}
+
+/// The process with the pid `query`, or the processes whose names contain it, using the most
+/// memory first.
+fn find<'a>(system: &'a System, query: &str) -> Vec<&'a Process> {
+    match query.parse::<usize>() {
+        Ok(pid) => system.process(Pid::from(pid)).into_iter().collect(),
+        Err(_) => {
+            let query = query.to_lowercase();
+            let query = query.strip_suffix(".exe").unwrap_or(&query);
+            let mut matches: Vec<&Process> = system
+                .processes()
+                .values()
+                .filter(|process| process.thread_kind().is_none())
+                .filter(|process| {
+                    process
+                        .name()
+                        .to_string_lossy()
+                        .to_lowercase()
+                        .contains(query)
+                })
+                .collect();
+            matches.sort_by_key(|process| std::cmp::Reverse(process.memory()));
+            matches
+        }
+    }
+}
+
+/// Describes the process with the pid `name_or_pid`, or the processes whose names contain it.
+pub fn details(name_or_pid: &str) -> Result<String, anyhow::Error> {
+    let query = name_or_pid.trim();
+    if query.is_empty() {
+        bail!("No process name or pid was given");
+    }
+    let system = snapshot();
+    let matches = find(&system, query);
+    if matches.is_empty() {
+        bail!("No running process matches \"{}\"", query);
+    }
+
+    let mut info = String::new();
+    if matches.len() > 1 {
+        let cpu: f32 = matches
+            .iter()
+            .map(|process| cpu_percent(&system, process))
+            .sum();
+        let memory: u64 = matches.iter().map(|process| process.memory()).sum();
+        info.push_str(&format!(
+            "=== {} processes match \"{}\", using {:.1}% CPU and {} in total ===\n",
+            matches.len(),
+            query,
+            cpu,
+            format_bytes(memory)
+        ));
+        if matches.len() > MAX_DETAILS {
+            info.push_str(&format!(
+                "Only the {} using the most memory are shown.\n",
+                MAX_DETAILS
+            ));
+        }
+    }
+    for process in matches.into_iter().take(MAX_DETAILS) {
+        info.push_str(&describe(&system, process));
+    }
+    Ok(info)
+}
+
+fn describe(system: &System, process: &Process) -> String {
+    let started = DateTime::from_timestamp(process.start_time() as i64, 0)
+        .map(|started| {
+            started
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+        })
+        .unwrap_or_else(|| "unknown".to_string());
+    let parent = process.parent().map(|pid| match system.process(pid) {
+        Some(parent) => format!("[{}] {}", pid, parent.name().to_string_lossy()),
+        None => format!("[{}]", pid),
+    });
+    let command_line = process
+        .cmd()
+        .iter()
+        .map(|arg| arg.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let disk = process.disk_usage();
+
+    let mut info = format!(
+        "[{}] {}\n  status: {}, started: {}, running for: {}\n  cpu: {:.1}%, memory: {}\n  disk read: {}, written: {} since it started\n",
+        process.pid(),
+        process.name().to_string_lossy(),
+        process.status(),
+        started,
+        format_duration(process.run_time()),
+        cpu_percent(system, process),
+        format_bytes(process.memory()),
+        format_bytes(disk.total_read_bytes),
+        format_bytes(disk.total_written_bytes),
+    );
+    if let Some(exe) = process.exe() {
+        info.push_str(&format!("  executable: {}\n", exe.display()));
+    }
+    if !command_line.is_empty() {
+        info.push_str(&format!(
+            "  command line: {}\n",
+            crate::truncate(&command_line, 300)
+        ));
+    }
+    if let Some(parent) = parent {
+        info.push_str(&format!("  parent: {}\n", parent));
+    }
+    info
+}
+
+/// A process being waited on. A later process given the same pid is told apart by its start time.
+pub struct Watched {
+    pid: Pid,
+    start_time: u64,
+    pub name: String,
+}
+
+/// The running processes matching `name_or_pid`, to wait for with `wait_for_exit`.
+pub fn watch(name_or_pid: &str) -> Result<Vec<Watched>, anyhow::Error> {
+    let query = name_or_pid.trim();
+    if query.is_empty() {
+        bail!("No process name or pid was given");
+    }
+    let mut system = System::new();
+    system.refresh_processes(ProcessesToUpdate::All, true);
+    let watched: Vec<Watched> = find(&system, query)
+        .into_iter()
+        // The assistant would be waiting on itself.
+        .filter(|process| process.pid().as_u32() != std::process::id())
+        .map(|process| Watched {
+            pid: process.pid(),
+            start_time: process.start_time(),
+            name: process.name().to_string_lossy().to_string(),
+        })
+        .collect();
+    if watched.is_empty() {
+        bail!("No running process matches \"{}\"", query);
+    }
+    Ok(watched)
+}
+
+/// Waits until every watched process has exited, and returns how long that took.
+pub fn wait_for_exit(watched: &[Watched]) -> Duration {
+    let started = Instant::now();
+    let pids: Vec<Pid> = watched.iter().map(|watched| watched.pid).collect();
+    let mut system = System::new();
+    loop {
+        system.refresh_processes(ProcessesToUpdate::Some(&pids), true);
+        let running = watched.iter().any(|watched| {
+            // A process that has exited stays a zombie until its parent notices.
+            system.process(watched.pid).is_some_and(|process| {
+                process.start_time() == watched.start_time
+                    && process.status() != ProcessStatus::Zombie
+            })
+        });
+        if !running {
+            return started.elapsed();
+        }
+        thread::sleep(WATCH_INTERVAL);
+    }
+}
@@ -0,0 +1,126 @@
+//! Named profiles from the config file, which switch the model, voice, push-to-talk key, and tools.
+
+// The chat request still takes tools as functions.
+#[allow(deprecated)]
+use async_openai::types::ChatCompletionFunctions;
+use clap::ValueEnum;
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, OnceLock, RwLock},
+};
+
+use crate::{config::Profile, easy_rdev_key::PTTKey, VoiceEnum};
+
+/// The profile in use, with its settings parsed.
+#[derive(Clone, Debug)]
+pub struct ActiveProfile {
+    pub name: String,
+    pub model: Option<String>,
+    pub voice: Option<VoiceEnum>,
+    pub ptt_key: Option<rdev::Key>,
+    pub tools: Option<Vec<String>>,
+}
+
+static PROFILES: OnceLock<HashMap<String, Profile>> = OnceLock::new();
+
+static ACTIVE: LazyLock<RwLock<Option<ActiveProfile>>> = LazyLock::new(|| RwLock::new(None));
+
+/// Sets the profiles that can be switched to.
+pub fn configure(profiles: HashMap<String, Profile>) {
+    let _ = PROFILES.set(profiles);
+}
+
+/// Returns the names of the profiles, sorted.
+pub fn names() -> Vec<String> {
+    let mut names: Vec<String> = PROFILES
+        .get()
+        .map(|profiles| profiles.keys().cloned().collect())
+        .unwrap_or_default();
+    names.sort();
+    names
+}
+
+/// Switches to the profile named `name` and returns its settings.
+pub fn switch(name: &str) -> Result<ActiveProfile, anyhow::Error> {
+    let Some(profile) = PROFILES.get().and_then(|profiles| profiles.get(name)) else {
+        let names = names();
+        if names.is_empty() {
+            anyhow::bail!("There are no profiles in the config file");
+        }
+        anyhow::bail!(
+            "No profile is named \"{}\". The profiles are: {}",
+            name,
+            names.join(", ")
+        );
+    };
+
+    let voice = match &profile.voice {
+        Some(voice) => Some(
+            VoiceEnum::from_str(voice, true)
+                .map_err(|err| anyhow::anyhow!("Invalid voice in profile {}: {}", name, err))?,
+        ),
+        None => None,
+    };
+    let ptt_key = match &profile.ptt_key {
+        Some(key) => Some(
+            PTTKey::from_str(key, true)
+                .map_err(|err| anyhow::anyhow!("Invalid ptt-key in profile {}: {}", name, err))?
+                .into(),
+        ),
+        None => None,
+    };
+
+    let active = ActiveProfile {
+        name: name.to_string(),
+        model: profile.model.clone(),
+        voice,
+        ptt_key,
+        tools: profile.tools.clone(),
+    };
+    *ACTIVE.write().unwrap() = Some(active.clone());
+    Ok(active)
+}
+
+pub fn active() -> Option<ActiveProfile> {
+    ACTIVE.read().unwrap().clone()
+}
+
+/// Returns the active profile's model, or `default` if it doesn't set one.
+pub fn model(default: &str) -> String {
+    ACTIVE
+        .read()
+        .unwrap()
+        .as_ref()
+        .and_then(|profile| profile.model.clone())
+        .unwrap_or_else(|| default.to_string())
+}
+
+/// Returns the active profile's push-to-talk key, or `default` if it doesn't set one.
+pub fn ptt_key(default: rdev::Key) -> rdev::Key {
+    ACTIVE
+        .read()
+        .unwrap()
+        .as_ref()
+        .and_then(|profile| profile.ptt_key)
+        .unwrap_or(default)
+}
+
+/// Whether the active profile enables the tool named `name`. switch_profile is always enabled while
+/// there are profiles, so a profile without it can still be switched away from.
+pub fn is_enabled(name: &str) -> bool {
+    if name == "switch_profile" {
+        return PROFILES.get().is_some_and(|profiles| !profiles.is_empty());
+    }
+    let active = ACTIVE.read().unwrap();
+    let tools = active.as_ref().and_then(|profile| profile.tools.as_ref());
+    tools.is_none_or(|tools| tools.iter().any(|tool| tool == name))
+}
+
+/// Removes the tools the active profile doesn't enable.
+#[allow(deprecated)]
+pub fn enabled_tools(functions: Vec<ChatCompletionFunctions>) -> Vec<ChatCompletionFunctions> {
+    functions
+        .into_iter()
+        .filter(|function| is_enabled(&function.name))
+        .collect()
+}
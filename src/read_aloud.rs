@@ -0,0 +1,50 @@
+//! Loading text files for the read_file_aloud tool. Only files in the folders that can be searched
+//! are read, so the AI can't read out the rest of the system.
+
+use anyhow::{bail, Context};
+use std::{fs, path::PathBuf};
+
+use crate::file_search;
+
+/// The largest file that's read. Reading this much aloud already takes well over an hour.
+const MAX_FILE_BYTES: u64 = 100 * 1024;
+/// The most text sent to the AI to summarize, so a long file doesn't fill the conversation.
+pub const MAX_SUMMARY_CHARS: usize = 20_000;
+
+/// Reads the text file at `path`, which can start with `~`. Returns its full path and its text.
+pub fn load(path: &str) -> Result<(PathBuf, String), anyhow::Error> {
+    let path = path.trim();
+    let path = match path.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir().unwrap_or_default().join(rest),
+        None => PathBuf::from(path),
+    };
+    let path = path
+        .canonicalize()
+        .with_context(|| format!("{} wasn't found", path.display()))?;
+    if !file_search::is_allowed(&path) {
+        bail!(
+            "{} isn't in a folder that can be read. Only the folders search_files looks in can be.",
+            path.display()
+        );
+    }
+
+    let size = fs::metadata(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?
+        .len();
+    if size > MAX_FILE_BYTES {
+        bail!(
+            "{} is {} KB, which is too long to read aloud. The limit is {} KB.",
+            path.display(),
+            size.div_ceil(1024),
+            MAX_FILE_BYTES / 1024
+        );
+    }
+    let bytes = fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let Ok(text) = String::from_utf8(bytes) else {
+        bail!("{} isn't a text file", path.display());
+    };
+    if text.trim().is_empty() {
+        bail!("{} is empty", path.display());
+    }
+    Ok((path, text))
+}
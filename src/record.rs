@@ -9,16 +9,40 @@ pub mod rec {
     use std::fs::File;
     use std::io::BufWriter;
     use std::path::Path;
+    use std::sync::atomic::{AtomicU32, Ordering};
     use std::sync::{Arc, Mutex};
 
+    /// How loud the recording is, readable from other threads while it's going.
+    #[derive(Clone, Default)]
+    pub struct InputLevel(Arc<AtomicU32>);
+
+    impl InputLevel {
+        /// The RMS loudness of the latest chunk of samples, from 0 to 1.
+        pub fn get(&self) -> f32 {
+            f32::from_bits(self.0.load(Ordering::Relaxed))
+        }
+
+        fn set(&self, level: f32) {
+            self.0.store(level.to_bits(), Ordering::Relaxed);
+        }
+    }
+
     pub struct Recorder {
         #[allow(clippy::type_complexity)]
         utils: Option<(Arc<Mutex<Option<WavWriter<BufWriter<File>>>>>, cpal::Stream)>,
+        level: InputLevel,
     }
 
     impl Recorder {
         pub fn new() -> Self {
-            Recorder { utils: None }
+            Recorder {
+                utils: None,
+                level: InputLevel::default(),
+            }
+        }
+
+        pub fn level(&self) -> InputLevel {
+            self.level.clone()
         }
 
         pub fn start_recording(
@@ -65,6 +89,8 @@ pub mod rec {
 
             // Run the input stream on a separate thread.
             let writer_2 = writer.clone();
+            let level = self.level.clone();
+            level.set(0.0);
 
             let err_fn = move |err| {
                 eprintln!("an error occurred on stream: {}", err);
@@ -74,7 +100,7 @@ pub mod rec {
                 cpal::SampleFormat::I8 => device
                     .build_input_stream(
                         &config.into(),
-                        move |data, _: &_| write_input_data::<i8, i8>(data, &writer_2),
+                        move |data, _: &_| write_input_data::<i8, i8>(data, &writer_2, &level),
                         err_fn,
                         None,
                     )
@@ -82,7 +108,7 @@ pub mod rec {
                 cpal::SampleFormat::I16 => device
                     .build_input_stream(
                         &config.into(),
-                        move |data, _: &_| write_input_data::<i16, i16>(data, &writer_2),
+                        move |data, _: &_| write_input_data::<i16, i16>(data, &writer_2, &level),
                         err_fn,
                         None,
                     )
@@ -90,7 +116,7 @@ pub mod rec {
                 cpal::SampleFormat::I32 => device
                     .build_input_stream(
                         &config.into(),
-                        move |data, _: &_| write_input_data::<i32, i32>(data, &writer_2),
+                        move |data, _: &_| write_input_data::<i32, i32>(data, &writer_2, &level),
                         err_fn,
                         None,
                     )
@@ -98,7 +124,7 @@ pub mod rec {
                 cpal::SampleFormat::F32 => device
                     .build_input_stream(
                         &config.into(),
-                        move |data, _: &_| write_input_data::<f32, f32>(data, &writer_2),
+                        move |data, _: &_| write_input_data::<f32, f32>(data, &writer_2, &level),
                         err_fn,
                         None,
                     )
@@ -160,11 +186,18 @@ pub mod rec {
 
     type WavWriterHandle = Arc<Mutex<Option<hound::WavWriter<BufWriter<File>>>>>;
 
-    fn write_input_data<T, U>(input: &[T], writer: &WavWriterHandle)
+    fn write_input_data<T, U>(input: &[T], writer: &WavWriterHandle, level: &InputLevel)
     where
         T: Sample,
         U: Sample + hound::Sample + FromSample<T>,
+        f32: FromSample<T>,
     {
+        let sum: f32 = input
+            .iter()
+            .map(|&sample| f32::from_sample(sample).powi(2))
+            .sum();
+        level.set((sum / input.len().max(1) as f32).sqrt());
+
         if let Ok(mut guard) = writer.try_lock() {
             if let Some(writer) = guard.as_mut() {
                 for &sample in input.iter() {
@@ -0,0 +1,36 @@
+//! A to-do list of things to remind the user about when they ask, unlike timers which ring.
+
+use anyhow::bail;
+use chrono::{DateTime, Local};
+
+use crate::timer_store;
+
+#[derive(Clone, Debug)]
+pub struct Reminder {
+    pub id: u64,
+    pub text: String,
+    pub created: DateTime<Local>,
+    /// When the reminder was marked done, if it has been.
+    pub completed: Option<DateTime<Local>>,
+}
+
+/// Adds a reminder and returns its ID.
+pub fn add_reminder(text: &str) -> Result<u64, anyhow::Error> {
+    if text.trim().is_empty() {
+        bail!("A reminder needs text");
+    }
+    timer_store::insert_reminder(text.trim(), Local::now())
+}
+
+/// Returns the reminders that aren't done yet, oldest first, along with completed ones if `include_completed` is true.
+pub fn list_reminders(include_completed: bool) -> Result<Vec<Reminder>, anyhow::Error> {
+    timer_store::load_reminders(include_completed)
+}
+
+/// Marks a reminder as done.
+pub fn complete_reminder(id: u64) -> Result<(), anyhow::Error> {
+    if !timer_store::complete_reminder(id, Local::now())? {
+        bail!("No reminder with ID {} is waiting to be done", id);
+    }
+    Ok(())
+}
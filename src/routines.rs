@@ -0,0 +1,461 @@
+//! Routines: fixed lists of tool calls run one after another, so a sequence like the morning
+//! briefing happens the same way every time instead of relying on the AI to remember it. Besides
+//! the morning briefing, the user can have named routines like "goodnight", set up in the config
+//! file or taught to the AI, which saves them there.
+
+use anyhow::bail;
+use serde_json::Value;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{LazyLock, Mutex, OnceLock, RwLock},
+    time::Duration,
+};
+
+use crate::{
+    config::{self, MorningConfig, RoutineConfig, RoutineStep},
+    confirmation,
+    timers::Timer,
+};
+
+/// The description of the wake-up alarm's timer, which is how it's recognized when it goes off.
+pub const WAKE_ALARM_DESCRIPTION: &str = "Wake up";
+
+/// How long the morning briefing waits for the wake-up alarm to be dismissed. If nobody has by then,
+/// there's nobody to give it to.
+pub const BRIEFING_WAIT: Duration = Duration::from_secs(2 * 60 * 60);
+
+/// Tools a routine can't call, so a routine can't run itself.
+const NOT_IN_ROUTINES: [&str; 2] = ["run_routine", "save_routine"];
+
+static MORNING: OnceLock<MorningConfig> = OnceLock::new();
+static ROUTINES: RwLock<Option<HashMap<String, RoutineConfig>>> = RwLock::new(None);
+/// The wake-up alarms whose briefing is waiting for them to be dismissed.
+static BRIEFINGS: LazyLock<Mutex<HashSet<u64>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
+
+/// Sets the morning briefing, from the config file. `parameters` gives the parameters of the tool
+/// with a name, or `None` if there's no such tool. If a step can't be run, the briefing is turned
+/// off and why is returned.
+pub fn configure_morning<'a>(
+    mut morning: MorningConfig,
+    parameters: impl Fn(&str) -> Option<&'a Value>,
+) -> Result<(), anyhow::Error> {
+    let checked = match morning.enabled {
+        true => checked_steps(std::mem::take(&mut morning.steps), parameters),
+        false => Ok(Vec::new()),
+    };
+    let result = match checked {
+        Ok(steps) => {
+            morning.steps = steps;
+            Ok(())
+        }
+        Err(err) => {
+            morning.enabled = false;
+            Err(err.context("The morning briefing is turned off"))
+        }
+    };
+    let _ = MORNING.set(morning);
+    result
+}
+
+/// The steps of the morning briefing, or `None` if it's turned off.
+pub fn morning_steps() -> Option<&'static [RoutineStep]> {
+    let morning = MORNING.get_or_init(MorningConfig::default);
+    (morning.enabled && !morning.steps.is_empty()).then_some(morning.steps.as_slice())
+}
+
+/// Whether `timer` is the wake-up alarm.
+pub fn is_wake_alarm(timer: &Timer) -> bool {
+    timer
+        .description
+        .eq_ignore_ascii_case(WAKE_ALARM_DESCRIPTION)
+}
+
+/// Notes that the wake-up alarm `id` has a briefing waiting for it. Returns false if it already
+/// has one, because it rang again or was snoozed, so it only gets one.
+pub fn start_briefing(id: u64) -> bool {
+    BRIEFINGS.lock().unwrap().insert(id)
+}
+
+/// Notes that the wake-up alarm `id` no longer has a briefing waiting.
+pub fn end_briefing(id: u64) {
+    BRIEFINGS.lock().unwrap().remove(&id);
+}
+
+/// Sets the named routines, from the config file. `parameters` gives the parameters of the tool
+/// with a name, or `None` if there's no such tool. Routines with a step that can't be run are left
+/// out, and why is returned for each.
+pub fn configure<'a>(
+    routines: HashMap<String, RoutineConfig>,
+    parameters: impl Fn(&str) -> Option<&'a Value>,
+) -> Vec<anyhow::Error> {
+    let mut problems = Vec::new();
+    let mut checked = HashMap::new();
+    for (name, routine) in routines {
+        match checked_steps(routine.steps, &parameters) {
+            Ok(steps) => {
+                checked.insert(
+                    name,
+                    RoutineConfig {
+                        description: routine.description,
+                        steps,
+                    },
+                );
+            }
+            Err(err) => {
+                problems.push(err.context(format!("The {} routine was left out", name)));
+            }
+        }
+    }
+    *ROUTINES.write().unwrap() = Some(checked);
+    problems
+}
+
+/// Finds a routine by name, ignoring case. Returns its name as saved too.
+fn find(name: &str) -> Option<(String, RoutineConfig)> {
+    let routines = ROUTINES.read().unwrap();
+    routines
+        .iter()
+        .flatten()
+        .find(|(routine, _)| routine.eq_ignore_ascii_case(name.trim()))
+        .map(|(name, routine)| (name.clone(), routine.clone()))
+}
+
+/// Describes the routines, for the run_routine tool's description.
+pub fn describe() -> String {
+    let routines = ROUTINES.read().unwrap();
+    let mut names: Vec<(&String, &RoutineConfig)> = routines.iter().flatten().collect();
+    if names.is_empty() {
+        return "The user has no routines yet.".to_string();
+    }
+    names.sort_by_key(|(name, _)| name.to_lowercase());
+    let mut info = "The routines are:".to_string();
+    for (name, routine) in names {
+        let tools: Vec<&str> = routine
+            .steps
+            .iter()
+            .map(|step| step.tool.as_str())
+            .collect();
+        info.push_str(&format!("\n- {}", name));
+        if let Some(description) = &routine.description {
+            info.push_str(&format!(": {}", description));
+        }
+        info.push_str(&format!(" (calls {})", tools.join(", ")));
+    }
+    info
+}
+
+/// The steps of the routine named `name`, and its name as saved.
+pub fn steps(name: &str) -> Result<(String, Vec<RoutineStep>), anyhow::Error> {
+    match find(name) {
+        Some((name, routine)) => Ok((name, routine.steps)),
+        None => bail!(
+            "There's no routine named \"{}\". {}",
+            name.trim(),
+            describe()
+        ),
+    }
+}
+
+/// A step's arguments without a confirmation code, which would otherwise be saved and used every
+/// time the routine runs. Guarded tools still ask the user when they're run.
+fn without_confirmation(args: &Value) -> Value {
+    let mut args = args.clone();
+    if let Some(object) = args.as_object_mut() {
+        object.remove(confirmation::ARG);
+    }
+    args
+}
+
+/// Whether `value` is of the JSON schema type `kind`. Unknown types match anything.
+fn is_type(value: &Value, kind: &str) -> bool {
+    match kind {
+        "string" => value.is_string(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        "boolean" => value.is_boolean(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        _ => true,
+    }
+}
+
+/// Checks that `args` has every argument `parameters` requires, and that each argument has the
+/// type and one of the values it allows. Tools expect the AI to have followed their parameters,
+/// so a step that doesn't would make them fail or panic.
+fn check_args(tool: &str, args: &Value, parameters: &Value) -> Result<(), anyhow::Error> {
+    let no_args = serde_json::Map::new();
+    let args = match args {
+        Value::Null => &no_args,
+        Value::Object(args) => args,
+        _ => bail!("{}'s args have to be a table", tool),
+    };
+    let required = parameters["required"].as_array().into_iter().flatten();
+    for name in required.filter_map(Value::as_str) {
+        if !args.contains_key(name) {
+            bail!("{} needs a {} argument", tool, name);
+        }
+    }
+    for (name, value) in args {
+        let property = &parameters["properties"][name];
+        if let Some(kind) = property["type"].as_str() {
+            if !is_type(value, kind) {
+                bail!("{}'s {} argument has to be a {}", tool, name, kind);
+            }
+        }
+        if let Some(allowed) = property["enum"].as_array() {
+            if !allowed.contains(value) {
+                bail!("{}'s {} argument can't be {}", tool, name, value);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Checks that every step calls a tool that exists and can be run without asking the user, with
+/// the arguments it needs. `parameters` gives the parameters of the tool with a name, or `None`
+/// if there's no such tool. Returns the steps as they're saved.
+fn checked_steps<'a>(
+    steps: Vec<RoutineStep>,
+    parameters: impl Fn(&str) -> Option<&'a Value>,
+) -> Result<Vec<RoutineStep>, anyhow::Error> {
+    if steps.is_empty() {
+        bail!("A routine needs at least one step");
+    }
+    let steps: Vec<RoutineStep> = steps
+        .into_iter()
+        .map(|step| RoutineStep {
+            args: without_confirmation(&step.args),
+            tool: step.tool,
+        })
+        .collect();
+    for step in &steps {
+        if NOT_IN_ROUTINES.contains(&step.tool.as_str()) {
+            bail!("A routine can't call {}", step.tool);
+        }
+        if confirmation::is_guarded(&step.tool) {
+            bail!(
+                "{} asks the user before doing anything, so it can't be part of a routine",
+                step.tool
+            );
+        }
+        let Some(tool_parameters) = parameters(&step.tool) else {
+            bail!("There's no tool named {}", step.tool);
+        };
+        check_args(&step.tool, &step.args, tool_parameters)?;
+    }
+    Ok(steps)
+}
+
+/// Saves a routine to the config file, replacing any routine with the same name. `parameters` gives
+/// the parameters of the tool with a name, or `None` if there's no such tool. Returns whether a
+/// routine was replaced.
+pub fn save<'a>(
+    name: &str,
+    description: Option<&str>,
+    steps: Vec<RoutineStep>,
+    parameters: impl Fn(&str) -> Option<&'a Value>,
+) -> Result<bool, anyhow::Error> {
+    let name = match find(name) {
+        Some((existing, _)) => existing,
+        None => name.trim().to_string(),
+    };
+    if name.is_empty() {
+        bail!("A routine needs a name");
+    }
+    let steps = checked_steps(steps, parameters)?;
+    let description = description
+        .map(str::trim)
+        .filter(|description| !description.is_empty());
+
+    let mut saved_steps = toml::value::Array::new();
+    for step in &steps {
+        let mut saved = toml::Table::new();
+        saved.insert("tool".into(), step.tool.clone().into());
+        if !step.args.is_null() {
+            saved.insert("args".into(), toml::Value::try_from(&step.args)?);
+        }
+        saved_steps.push(saved.into());
+    }
+    let mut routine = toml::Table::new();
+    if let Some(description) = description {
+        routine.insert("description".into(), description.into());
+    }
+    routine.insert("steps".into(), saved_steps.into());
+    config::update(|table| {
+        let section = table
+            .entry("routines")
+            .or_insert_with(|| toml::Table::new().into());
+        if let Some(section) = section.as_table_mut() {
+            section.insert(name.clone(), routine.into());
+        }
+    })?;
+
+    let mut routines = ROUTINES.write().unwrap();
+    let replaced = routines
+        .get_or_insert_with(HashMap::new)
+        .insert(
+            name,
+            RoutineConfig {
+                description: description.map(str::to_string),
+                steps,
+            },
+        )
+        .is_some();
+    Ok(replaced)
+}
+
+/// Runs each step's tool in order with `call`, which takes a tool's name and its arguments as
+/// JSON. Returns what every tool gave back, under its name.
+pub fn run(steps: &[RoutineStep], mut call: impl FnMut(&str, &str) -> Option<String>) -> String {
+    let mut results = String::new();
+    for step in steps {
+        // Steps are checked when they're loaded, but a routine running itself would never end.
+        if NOT_IN_ROUTINES.contains(&step.tool.as_str()) {
+            results.push_str(&format!(
+                "=== {} ===\nA routine can't call {}.\n",
+                step.tool, step.tool
+            ));
+            continue;
+        }
+        let args = match &step.args {
+            Value::Null => "{}".to_string(),
+            args => without_confirmation(args).to_string(),
+        };
+        let result = call(&step.tool, &args).unwrap_or_else(|| "It gave no result.".to_string());
+        results.push_str(&format!("=== {} ===\n{}\n", step.tool, result.trim()));
+    }
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn step(tool: &str, args: Value) -> RoutineStep {
+        RoutineStep {
+            tool: tool.to_string(),
+            args,
+        }
+    }
+
+    fn parameters(tool: &str) -> Option<&'static Value> {
+        static DO_NOT_DISTURB: LazyLock<Value> = LazyLock::new(|| {
+            json!({
+                "type": "object",
+                "properties": { "on": { "type": "boolean" } },
+                "required": ["on"],
+            })
+        });
+        static MEDIA_CONTROLS: LazyLock<Value> = LazyLock::new(|| {
+            json!({
+                "type": "object",
+                "properties": {
+                    "media_button": { "type": "string", "enum": ["MediaPlayPause", "MediaStop"] },
+                },
+                "required": ["media_button"],
+            })
+        });
+        static NO_ARGS: LazyLock<Value> =
+            LazyLock::new(|| json!({ "type": "object", "properties": {}, "required": [] }));
+        match tool {
+            "set_do_not_disturb" => Some(&DO_NOT_DISTURB),
+            "media_controls" => Some(&MEDIA_CONTROLS),
+            "shutdown_computer" | "run_routine" | "get_news_headlines" => Some(&NO_ARGS),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn steps_are_checked() {
+        assert!(checked_steps(
+            vec![step("set_do_not_disturb", json!({ "on": true }))],
+            parameters
+        )
+        .is_ok());
+        assert!(checked_steps(vec![step("get_news_headlines", Value::Null)], parameters).is_ok());
+        assert!(checked_steps(Vec::new(), parameters).is_err());
+        assert!(checked_steps(vec![step("turn_off_lights", Value::Null)], parameters).is_err());
+        assert!(checked_steps(
+            vec![step("run_routine", json!({ "name": "x" }))],
+            parameters
+        )
+        .is_err());
+        assert!(checked_steps(
+            vec![step(
+                "shutdown_computer",
+                json!({ confirmation::ARG: "1234abcd" })
+            )],
+            parameters
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn step_arguments_are_checked() {
+        let checked = |args| checked_steps(vec![step("media_controls", args)], parameters);
+        assert!(checked(json!({ "media_button": "MediaStop" })).is_ok());
+        assert!(checked(Value::Null).is_err());
+        assert!(checked(json!({})).is_err());
+        assert!(checked(json!({ "media_button": 3 })).is_err());
+        assert!(checked(json!({ "media_button": "Louder" })).is_err());
+        assert!(checked(json!(["MediaStop"])).is_err());
+    }
+
+    #[test]
+    fn confirmations_are_not_saved() {
+        let steps = checked_steps(
+            vec![step(
+                "set_do_not_disturb",
+                json!({ "on": true, confirmation::ARG: "1234abcd" }),
+            )],
+            parameters,
+        )
+        .unwrap();
+        assert_eq!(steps[0].args, json!({ "on": true }));
+    }
+
+    #[test]
+    fn routines_that_cant_run_are_left_out() {
+        let routines = HashMap::from([
+            (
+                "quiet".to_string(),
+                RoutineConfig {
+                    description: None,
+                    steps: vec![step("set_do_not_disturb", json!({ "on": true }))],
+                },
+            ),
+            (
+                "forever".to_string(),
+                RoutineConfig {
+                    description: None,
+                    steps: vec![step("run_routine", json!({ "name": "forever" }))],
+                },
+            ),
+        ]);
+        let problems = configure(routines, parameters);
+        assert_eq!(problems.len(), 1);
+        assert!(find("quiet").is_some());
+        assert!(find("forever").is_none());
+    }
+
+    #[test]
+    fn routines_cant_run_routines() {
+        let mut called = Vec::new();
+        let results = run(
+            &[
+                step("run_routine", json!({ "name": "forever" })),
+                step("get_news_headlines", Value::Null),
+            ],
+            |tool, args| {
+                called.push((tool.to_string(), args.to_string()));
+                Some("Done".to_string())
+            },
+        );
+        assert_eq!(
+            called,
+            [("get_news_headlines".to_string(), "{}".to_string())]
+        );
+        assert!(results.contains("A routine can't call run_routine"));
+    }
+}
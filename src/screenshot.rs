@@ -0,0 +1,444 @@
+//! Taking screenshots for the take_screenshot tool. They're saved as PNG files and put on the clipboard.
+//! Linux reads the screen over X11, or uses grim on Wayland. Windows uses .NET through PowerShell,
+//! and macOS uses screencapture.
+
+use anyhow::{bail, Context};
+use chrono::Local;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::OnceLock,
+};
+
+static FOLDER: OnceLock<PathBuf> = OnceLock::new();
+
+/// Sets the folder screenshots are saved in, from the config file.
+pub fn configure(folder: PathBuf) {
+    let folder = match folder.strip_prefix("~") {
+        Ok(rest) => dirs::home_dir().unwrap_or_default().join(rest),
+        Err(_) => folder,
+    };
+    let _ = FOLDER.set(folder);
+}
+
+fn folder() -> &'static Path {
+    FOLDER.get_or_init(|| {
+        dirs::picture_dir()
+            .or_else(dirs::home_dir)
+            .unwrap_or_default()
+            .join("Screenshots")
+    })
+}
+
+/// The part of the screen to capture.
+#[derive(Debug, Clone, Copy)]
+pub enum Region {
+    /// Every screen.
+    Screen,
+    /// The focused window.
+    Window,
+    /// A rectangle, in pixels from the top left of the screen.
+    Rect {
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+    },
+}
+
+impl Region {
+    /// Reads a region like "screen", "window", or "x,y,width,height".
+    pub fn parse(region: &str) -> Result<Self, anyhow::Error> {
+        let region = region.trim().to_lowercase();
+        match region.as_str() {
+            "" | "screen" | "full" | "fullscreen" => return Ok(Self::Screen),
+            "window" | "active" | "focused" => return Ok(Self::Window),
+            _ => {}
+        }
+        let numbers: Vec<i64> = region
+            .split(',')
+            .map(|number| number.trim().parse())
+            .collect::<Result<_, _>>()
+            .with_context(|| {
+                format!(
+                    "\"{}\" isn't a region. Use \"screen\", \"window\" or \"x,y,width,height\".",
+                    region
+                )
+            })?;
+        let [x, y, width, height] = numbers[..] else {
+            bail!("A region needs four numbers: x, y, width and height");
+        };
+        if width <= 0 || height <= 0 {
+            bail!("A region's width and height must be more than 0");
+        }
+        Ok(Self::Rect {
+            x: x as i32,
+            y: y as i32,
+            width: width as u32,
+            height: height as u32,
+        })
+    }
+}
+
+/// Captures `region` to a new PNG file, and returns its path.
+fn capture(region: Region) -> Result<PathBuf, anyhow::Error> {
+    let folder = folder();
+    fs::create_dir_all(folder).with_context(|| format!("Failed to create {}", folder.display()))?;
+    let path = folder.join(format!(
+        "Screenshot {}.png",
+        Local::now().format("%Y-%m-%d %H-%M-%S")
+    ));
+
+    #[cfg(target_os = "linux")]
+    linux::capture(region, &path)?;
+
+    #[cfg(windows)]
+    windows::capture(region, &path)?;
+
+    #[cfg(target_os = "macos")]
+    macos::capture(region, &path)?;
+
+    #[cfg(not(any(target_os = "linux", windows, target_os = "macos")))]
+    bail!("Taking screenshots isn't supported on this system");
+
+    #[allow(unreachable_code)]
+    Ok(path)
+}
+
+/// Puts the PNG file at `path` on the clipboard as an image.
+fn copy_to_clipboard(path: &Path) -> Result<(), anyhow::Error> {
+    #[cfg(target_os = "linux")]
+    return linux::copy_to_clipboard(path);
+
+    #[cfg(windows)]
+    return windows::copy_to_clipboard(path);
+
+    #[cfg(target_os = "macos")]
+    return macos::copy_to_clipboard(path);
+
+    #[allow(unreachable_code)]
+    Err(anyhow::anyhow!(
+        "Copying images isn't supported on this system"
+    ))
+}
+
+/// A screenshot that was saved.
+pub struct Screenshot {
+    pub path: PathBuf,
+    /// Why the screenshot couldn't be put on the clipboard, if it couldn't.
+    pub clipboard_error: Option<anyhow::Error>,
+}
+
+/// Captures `region`, saves it, and puts it on the clipboard.
+pub fn take(region: Region) -> Result<Screenshot, anyhow::Error> {
+    let path = capture(region)?;
+    let clipboard_error = copy_to_clipboard(&path).err();
+    Ok(Screenshot {
+        path,
+        clipboard_error,
+    })
+}
+
+/// Runs a program, failing with what it printed if it fails.
+fn run(command: &mut Command, name: &str) -> Result<(), anyhow::Error> {
+    let output = command
+        .output()
+        .with_context(|| format!("Failed to run {}", name))?;
+    if !output.status.success() {
+        bail!(
+            "{} failed: {}",
+            name,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use anyhow::{bail, Context};
+    use std::{
+        env,
+        fs::File,
+        io::BufWriter,
+        path::Path,
+        process::{Command, Stdio},
+    };
+    use x11rb::{
+        connection::Connection,
+        protocol::xproto::{AtomEnum, ConnectionExt, ImageFormat, ImageOrder},
+    };
+
+    use super::{run, Region};
+
+    fn is_wayland() -> bool {
+        env::var_os("WAYLAND_DISPLAY").is_some()
+    }
+
+    pub fn capture(region: Region, path: &Path) -> Result<(), anyhow::Error> {
+        if is_wayland() {
+            // Wayland doesn't let apps read the screen themselves, so it takes a compositor's tool.
+            let mut grim = Command::new("grim");
+            match region {
+                Region::Screen => {}
+                Region::Window => bail!(
+                    "Screenshots of just the focused window aren't supported on Wayland. Take one of the whole screen instead."
+                ),
+                Region::Rect {
+                    x,
+                    y,
+                    width,
+                    height,
+                } => {
+                    grim.args(["-g", &format!("{},{} {}x{}", x, y, width, height)]);
+                }
+            }
+            return run(grim.arg(path), "grim")
+                .context("Taking screenshots on Wayland needs grim to be installed");
+        }
+        capture_x11(region, path)
+    }
+
+    fn capture_x11(region: Region, path: &Path) -> Result<(), anyhow::Error> {
+        let (conn, screen_num) =
+            x11rb::connect(None).context("Failed to connect to the X server")?;
+        let setup = conn.setup();
+        let screen = setup
+            .roots
+            .get(screen_num)
+            .context("The X server has no such screen")?;
+        let (screen_width, screen_height) = (
+            screen.width_in_pixels as i32,
+            screen.height_in_pixels as i32,
+        );
+
+        let (x, y, width, height) = match region {
+            Region::Screen => (0, 0, screen_width, screen_height),
+            Region::Window => {
+                let atom = conn
+                    .intern_atom(false, b"_NET_ACTIVE_WINDOW")?
+                    .reply()?
+                    .atom;
+                let active = conn
+                    .get_property(false, screen.root, atom, AtomEnum::WINDOW, 0, 1)?
+                    .reply()?
+                    .value32()
+                    .and_then(|mut values| values.next())
+                    .filter(|&id| id != 0)
+                    .context("No window is focused")?;
+                let geometry = conn.get_geometry(active)?.reply()?;
+                let position = conn
+                    .translate_coordinates(active, screen.root, 0, 0)?
+                    .reply()?;
+                (
+                    position.dst_x as i32,
+                    position.dst_y as i32,
+                    geometry.width as i32,
+                    geometry.height as i32,
+                )
+            }
+            Region::Rect {
+                x,
+                y,
+                width,
+                height,
+            } => (x, y, width as i32, height as i32),
+        };
+        // Only the part that's on the screen can be captured.
+        let (left, top) = (x.clamp(0, screen_width), y.clamp(0, screen_height));
+        let right = (x + width).clamp(0, screen_width);
+        let bottom = (y + height).clamp(0, screen_height);
+        if right <= left || bottom <= top {
+            bail!("That region isn't on the screen");
+        }
+        let (width, height) = ((right - left) as u16, (bottom - top) as u16);
+
+        let image = conn
+            .get_image(
+                ImageFormat::Z_PIXMAP,
+                screen.root,
+                left as i16,
+                top as i16,
+                width,
+                height,
+                !0,
+            )?
+            .reply()
+            .context("Failed to read the screen")?;
+        let bits_per_pixel = setup
+            .pixmap_formats
+            .iter()
+            .find(|format| format.depth == image.depth)
+            .map(|format| format.bits_per_pixel);
+        if bits_per_pixel != Some(32) {
+            bail!(
+                "Screens with a color depth of {} aren't supported",
+                image.depth
+            );
+        }
+
+        // Each pixel is four bytes, blue first unless the server says otherwise.
+        let (red, green, blue) = match setup.image_byte_order {
+            ImageOrder::MSB_FIRST => (1, 2, 3),
+            _ => (2, 1, 0),
+        };
+        let rgb: Vec<u8> = image
+            .data
+            .chunks_exact(4)
+            .flat_map(|pixel| [pixel[red], pixel[green], pixel[blue]])
+            .collect();
+
+        let file =
+            File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+        let mut encoder = png::Encoder::new(BufWriter::new(file), width.into(), height.into());
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .write_header()?
+            .write_image_data(&rgb)
+            .context("Failed to write the screenshot")?;
+        Ok(())
+    }
+
+    pub fn copy_to_clipboard(path: &Path) -> Result<(), anyhow::Error> {
+        let file = File::open(path)?;
+        let (mut command, name) = match is_wayland() {
+            true => {
+                let mut command = Command::new("wl-copy");
+                command.args(["--type", "image/png"]);
+                (command, "wl-copy")
+            }
+            false => {
+                let mut command = Command::new("xclip");
+                command.args(["-selection", "clipboard", "-target", "image/png"]);
+                (command, "xclip")
+            }
+        };
+        // Both stay running in the background to hand the image to whatever pastes it.
+        command
+            .stdin(file)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        run(&mut command, name)
+            .with_context(|| format!("Copying images needs {} to be installed", name))
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use anyhow::bail;
+    use std::{path::Path, process::Command};
+    use windows_sys::Win32::{
+        Foundation::RECT,
+        UI::WindowsAndMessaging::{GetForegroundWindow, GetWindowRect},
+    };
+
+    use super::{run, Region};
+
+    /// Quotes `path` for PowerShell.
+    fn quote(path: &Path) -> String {
+        format!("'{}'", path.display().to_string().replace('\'', "''"))
+    }
+
+    fn powershell(script: &str) -> Result<(), anyhow::Error> {
+        run(
+            Command::new("powershell").args(["-NoProfile", "-NonInteractive", "-Command", script]),
+            "PowerShell",
+        )
+    }
+
+    pub fn capture(region: Region, path: &Path) -> Result<(), anyhow::Error> {
+        let bounds = match region {
+            Region::Screen => {
+                "$b = [System.Windows.Forms.SystemInformation]::VirtualScreen".to_string()
+            }
+            Region::Window => {
+                let mut rect: RECT = unsafe { std::mem::zeroed() };
+                // SAFETY: `rect` is a valid RECT for Windows to fill in.
+                let ok = unsafe {
+                    let hwnd = GetForegroundWindow();
+                    hwnd != 0 && GetWindowRect(hwnd, &mut rect) != 0
+                };
+                if !ok {
+                    bail!("No window is focused");
+                }
+                format!(
+                    "$b = New-Object System.Drawing.Rectangle {}, {}, {}, {}",
+                    rect.left,
+                    rect.top,
+                    rect.right - rect.left,
+                    rect.bottom - rect.top
+                )
+            }
+            Region::Rect {
+                x,
+                y,
+                width,
+                height,
+            } => format!(
+                "$b = New-Object System.Drawing.Rectangle {}, {}, {}, {}",
+                x, y, width, height
+            ),
+        };
+        powershell(&format!(
+            "Add-Type -AssemblyName System.Windows.Forms, System.Drawing; {}; \
+             $bitmap = New-Object System.Drawing.Bitmap $b.Width, $b.Height; \
+             $graphics = [System.Drawing.Graphics]::FromImage($bitmap); \
+             $graphics.CopyFromScreen($b.Left, $b.Top, 0, 0, $bitmap.Size); \
+             $bitmap.Save({}, [System.Drawing.Imaging.ImageFormat]::Png)",
+            bounds,
+            quote(path)
+        ))
+    }
+
+    pub fn copy_to_clipboard(path: &Path) -> Result<(), anyhow::Error> {
+        powershell(&format!(
+            "Add-Type -AssemblyName System.Windows.Forms, System.Drawing; \
+             [System.Windows.Forms.Clipboard]::SetImage([System.Drawing.Image]::FromFile({}))",
+            quote(path)
+        ))
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod macos {
+    use anyhow::bail;
+    use std::{path::Path, process::Command};
+
+    use super::{run, Region};
+
+    pub fn capture(region: Region, path: &Path) -> Result<(), anyhow::Error> {
+        let mut command = Command::new("screencapture");
+        command.arg("-x");
+        match region {
+            Region::Screen => {}
+            Region::Window => bail!(
+                "Screenshots of just the focused window aren't supported on macOS. Take one of the whole screen instead."
+            ),
+            Region::Rect {
+                x,
+                y,
+                width,
+                height,
+            } => {
+                command.arg(format!("-R{},{},{},{}", x, y, width, height));
+            }
+        }
+        run(command.arg(path), "screencapture")
+    }
+
+    pub fn copy_to_clipboard(path: &Path) -> Result<(), anyhow::Error> {
+        let path = path.display().to_string().replace('"', "\\\"");
+        run(
+            Command::new("osascript").args([
+                "-e",
+                &format!(
+                    "set the clipboard to (read (POSIX file \"{}\") as «class PNGf»)",
+                    path
+                ),
+            ]),
+            "osascript",
+        )
+    }
+}
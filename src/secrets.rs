@@ -0,0 +1,88 @@
+//! The OpenAI API key, the Spotify login and the email password, kept in the system keyring instead of plain text files.
+
+use anyhow::Context;
+use keyring::Entry;
+use tracing::warn;
+
+const SERVICE: &str = "quick-assistant";
+const API_KEY_USER: &str = "openai-api-key";
+const SPOTIFY_TOKEN_USER: &str = "spotify-refresh-token";
+const EMAIL_PASSWORD_USER: &str = "email-password";
+
+fn api_key_entry() -> keyring::Result<Entry> {
+    Entry::new(SERVICE, API_KEY_USER)
+}
+
+fn spotify_token_entry() -> keyring::Result<Entry> {
+    Entry::new(SERVICE, SPOTIFY_TOKEN_USER)
+}
+
+fn email_password_entry() -> keyring::Result<Entry> {
+    Entry::new(SERVICE, EMAIL_PASSWORD_USER)
+}
+
+/// Saves the API key to the system keyring, replacing any saved before.
+pub fn save_api_key(api_key: &str) -> Result<(), anyhow::Error> {
+    api_key_entry()
+        .and_then(|entry| entry.set_password(api_key))
+        .context("Failed to save the API key to the system keyring")
+}
+
+/// The API key saved in the system keyring, if there is one.
+pub fn load_api_key() -> Option<String> {
+    match api_key_entry().and_then(|entry| entry.get_password()) {
+        Ok(api_key) => Some(api_key),
+        Err(keyring::Error::NoEntry) => None,
+        Err(err) => {
+            warn!(
+                "Failed to read the API key from the system keyring: {}",
+                err
+            );
+            None
+        }
+    }
+}
+
+/// Saves the token that keeps the assistant logged in to Spotify, replacing any saved before.
+pub fn save_spotify_refresh_token(token: &str) -> Result<(), anyhow::Error> {
+    spotify_token_entry()
+        .and_then(|entry| entry.set_password(token))
+        .context("Failed to save the Spotify login to the system keyring")
+}
+
+/// The Spotify login token saved in the system keyring, if there is one.
+pub fn load_spotify_refresh_token() -> Option<String> {
+    match spotify_token_entry().and_then(|entry| entry.get_password()) {
+        Ok(token) => Some(token),
+        Err(keyring::Error::NoEntry) => None,
+        Err(err) => {
+            warn!(
+                "Failed to read the Spotify login from the system keyring: {}",
+                err
+            );
+            None
+        }
+    }
+}
+
+/// Saves the password of the email account emails are sent from, replacing any saved before.
+pub fn save_email_password(password: &str) -> Result<(), anyhow::Error> {
+    email_password_entry()
+        .and_then(|entry| entry.set_password(password))
+        .context("Failed to save the email password to the system keyring")
+}
+
+/// The email password saved in the system keyring, if there is one.
+pub fn load_email_password() -> Option<String> {
+    match email_password_entry().and_then(|entry| entry.get_password()) {
+        Ok(password) => Some(password),
+        Err(keyring::Error::NoEntry) => None,
+        Err(err) => {
+            warn!(
+                "Failed to read the email password from the system keyring: {}",
+                err
+            );
+            None
+        }
+    }
+}
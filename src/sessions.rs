@@ -0,0 +1,206 @@
+//! Saves each run's conversation as a session, so it can be exported or looked back on later.
+//! A session is a JSON lines file, one entry per line, named after when the run started.
+
+use anyhow::{bail, Context};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::{LazyLock, OnceLock},
+    thread,
+};
+use tracing::warn;
+
+use crate::{
+    conversation::{self, ConversationEvent},
+    paths::CACHE_DIR,
+};
+
+/// The most of an old conversation handed back to the AI, counted in characters from its end.
+const MAX_CONTEXT_CHARS: usize = 12_000;
+
+pub static SESSIONS_DIR: LazyLock<PathBuf> = LazyLock::new(|| CACHE_DIR.join("sessions"));
+
+/// The file this run's conversation is saved to. It isn't created until something is said.
+static CURRENT: OnceLock<PathBuf> = OnceLock::new();
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Entry {
+    pub time: DateTime<Local>,
+    #[serde(flatten)]
+    pub message: EntryMessage,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "role", rename_all = "snake_case")]
+pub enum EntryMessage {
+    User { text: String },
+    Assistant { text: String },
+    ToolCall { name: String, arguments: String },
+    ToolResult { name: String, content: String },
+}
+
+/// Starts saving this run's conversation.
+pub fn start() {
+    let path = SESSIONS_DIR.join(format!(
+        "{}.jsonl",
+        Local::now().format("%Y-%m-%d_%H-%M-%S")
+    ));
+    if CURRENT.set(path.clone()).is_err() {
+        return;
+    }
+
+    let events = conversation::subscribe();
+    thread::spawn(move || {
+        // The response so far, saved as one entry once it's finished.
+        let mut response = String::new();
+        let mut response_started = Local::now();
+        for event in events.iter() {
+            let message = match event {
+                ConversationEvent::Token { text } => {
+                    if response.is_empty() {
+                        response_started = Local::now();
+                    }
+                    response.push_str(&text);
+                    continue;
+                }
+                ConversationEvent::User { text } => Some(EntryMessage::User { text }),
+                ConversationEvent::FunctionCall { name, arguments } => {
+                    Some(EntryMessage::ToolCall { name, arguments })
+                }
+                ConversationEvent::FunctionResult { name, content } => {
+                    Some(EntryMessage::ToolResult { name, content })
+                }
+                ConversationEvent::ResponseDone => None,
+                _ => continue,
+            };
+
+            let mut entries = Vec::new();
+            if !response.trim().is_empty() {
+                entries.push(Entry {
+                    time: response_started,
+                    message: EntryMessage::Assistant {
+                        text: response.trim().to_string(),
+                    },
+                });
+            }
+            response.clear();
+            entries.extend(message.map(|message| Entry {
+                time: Local::now(),
+                message,
+            }));
+            for entry in entries {
+                if let Err(err) = append(&path, &entry) {
+                    warn!(
+                        "Failed to save conversation to {}: {:?}",
+                        path.display(),
+                        err
+                    );
+                }
+            }
+        }
+    });
+}
+
+fn append(path: &Path, entry: &Entry) -> Result<(), anyhow::Error> {
+    fs::create_dir_all(&*SESSIONS_DIR)?;
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(entry)?)?;
+    Ok(())
+}
+
+/// The session this run is saving to, if anything has been said yet.
+pub fn current() -> Option<PathBuf> {
+    CURRENT.get().filter(|path| path.is_file()).cloned()
+}
+
+/// Every saved session, oldest first.
+pub fn list() -> Result<Vec<PathBuf>, anyhow::Error> {
+    if !SESSIONS_DIR.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut sessions: Vec<PathBuf> = fs::read_dir(&*SESSIONS_DIR)
+        .context("Failed to read sessions folder")?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "jsonl")
+        })
+        .collect();
+    // Session names start with when they started, so sorting by name sorts by time.
+    sessions.sort();
+    Ok(sessions)
+}
+
+/// Finds a saved session by name, like "2024-05-01_09-30-00". `None` finds the latest one.
+pub fn find(name: Option<&str>) -> Result<PathBuf, anyhow::Error> {
+    let sessions = list()?;
+    let session = match name {
+        Some(name) => sessions.into_iter().find(|path| name_of(path) == name),
+        None => sessions.into_iter().last(),
+    };
+    match (session, name) {
+        (Some(session), _) => Ok(session),
+        (None, Some(name)) => bail!("No session named '{}'", name),
+        (None, None) => bail!("No conversations have been saved yet"),
+    }
+}
+
+/// A session's name, which is when it started.
+pub fn name_of(session: &Path) -> String {
+    session
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Reads a session's entries, skipping any that fail to parse.
+pub fn load(session: &Path) -> Result<Vec<Entry>, anyhow::Error> {
+    let file = File::open(session)
+        .with_context(|| format!("Failed to open session {}", session.display()))?;
+    Ok(BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| match serde_json::from_str(&line) {
+            Ok(entry) => Some(entry),
+            Err(err) => {
+                warn!("Skipping session entry that failed to parse: {}", err);
+                None
+            }
+        })
+        .collect())
+}
+
+/// A session as plain text, for giving an old conversation back to the AI.
+/// Long conversations are cut down to their most recent part.
+pub fn as_context(entries: &[Entry]) -> String {
+    let lines: Vec<String> = entries
+        .iter()
+        .map(|entry| {
+            let time = entry.time.format("%Y-%m-%d %H:%M");
+            match &entry.message {
+                EntryMessage::User { text } => format!("[{}] User: {}", time, text),
+                EntryMessage::Assistant { text } => format!("[{}] Assistant: {}", time, text),
+                EntryMessage::ToolCall { name, arguments } => {
+                    format!("[{}] Assistant called {} with {}", time, name, arguments)
+                }
+                EntryMessage::ToolResult { name, content } => {
+                    format!("[{}] {} returned: {}", time, name, content)
+                }
+            }
+        })
+        .collect();
+    let context = lines.join("\n");
+
+    let char_count = context.chars().count();
+    if char_count <= MAX_CONTEXT_CHARS {
+        return context;
+    }
+    let end: String = context
+        .chars()
+        .skip(char_count - MAX_CONTEXT_CHARS)
+        .collect();
+    format!("(earlier messages left out)\n{}", end)
+}
@@ -0,0 +1,112 @@
+//! Settings changed by tools while the assistant runs, saved so the next launch starts with them.
+//! Options passed on the command line still take precedence.
+
+use clap::{parser::ValueSource, ArgMatches, ValueEnum};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs, io,
+    path::PathBuf,
+    sync::{LazyLock, Mutex},
+};
+use tracing::warn;
+
+use crate::{options::Opt, tick::TickSound, voice_to_str, VoiceEnum, CACHE_DIR};
+
+static SETTINGS_PATH: LazyLock<PathBuf> = LazyLock::new(|| CACHE_DIR.join("settings.toml"));
+
+// Keeps two changes at once from overwriting each other.
+static SETTINGS_LOCK: Mutex<()> = Mutex::new(());
+
+/// Settings that have been changed at runtime. Anything left out was never changed.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct SavedSettings {
+    pub ai_volume: Option<u32>,
+    pub muted: Option<bool>,
+    pub ai_voice: Option<String>,
+    pub speech_speed: Option<f32>,
+    pub tick_sound: Option<String>,
+    pub tick_volume: Option<u32>,
+    pub tick_interval_ms: Option<u64>,
+}
+
+impl SavedSettings {
+    pub fn set_ai_voice(&mut self, voice: &VoiceEnum) {
+        self.ai_voice = Some(voice_to_str(voice).to_string());
+    }
+
+    pub fn set_tick_sound(&mut self, sound: TickSound) {
+        self.tick_sound = sound
+            .to_possible_value()
+            .map(|value| value.get_name().to_string());
+    }
+}
+
+fn load() -> SavedSettings {
+    match fs::read_to_string(&*SETTINGS_PATH) {
+        Ok(text) => toml::from_str(&text).unwrap_or_else(|err| {
+            warn!("Failed to parse {}: {}", SETTINGS_PATH.display(), err);
+            SavedSettings::default()
+        }),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => SavedSettings::default(),
+        Err(err) => {
+            warn!("Failed to read {}: {}", SETTINGS_PATH.display(), err);
+            SavedSettings::default()
+        }
+    }
+}
+
+/// Changes the saved settings and writes them to disk.
+pub fn update(change: impl FnOnce(&mut SavedSettings)) {
+    let _lock = SETTINGS_LOCK.lock().unwrap();
+    let mut settings = load();
+    change(&mut settings);
+
+    let result = toml::to_string(&settings)
+        .map_err(anyhow::Error::from)
+        .and_then(|text| {
+            fs::create_dir_all(CACHE_DIR.as_path())?;
+            Ok(fs::write(&*SETTINGS_PATH, text)?)
+        });
+    if let Err(err) = result {
+        warn!(
+            "Failed to save settings to {}: {:?}",
+            SETTINGS_PATH.display(),
+            err
+        );
+    }
+}
+
+/// Applies the saved settings to every option that wasn't passed on the command line.
+pub fn apply(opt: &mut Opt, matches: &ArgMatches) {
+    let saved = load();
+    let unset = |id: &str| matches.value_source(id) != Some(ValueSource::CommandLine);
+
+    if let Some(ai_volume) = saved.ai_volume.filter(|_| unset("ai_volume")) {
+        opt.ai_volume = ai_volume;
+    }
+    if let Some(muted) = saved.muted.filter(|_| unset("mute")) {
+        opt.mute = muted;
+    }
+    if let Some(voice) = saved.ai_voice.filter(|_| unset("ai_voice")) {
+        match VoiceEnum::from_str(&voice, true) {
+            Ok(voice) => opt.ai_voice = Some(voice),
+            Err(err) => warn!("Ignoring saved voice: {}", err),
+        }
+    }
+    if let Some(speech_speed) = saved.speech_speed.filter(|_| unset("speech_speed")) {
+        opt.speech_speed = speech_speed;
+    }
+    if let Some(sound) = saved.tick_sound.filter(|_| unset("tick_sound")) {
+        match TickSound::from_str(&sound, true) {
+            Ok(sound) => opt.tick_sound = sound,
+            Err(err) => warn!("Ignoring saved thinking sound: {}", err),
+        }
+    }
+    if let Some(tick_volume) = saved.tick_volume.filter(|_| unset("tick_volume")) {
+        opt.tick_volume = tick_volume;
+    }
+    if let Some(interval_ms) = saved.tick_interval_ms.filter(|_| unset("tick_interval_ms")) {
+        opt.tick_interval_ms = interval_ms;
+    }
+}
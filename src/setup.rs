@@ -0,0 +1,376 @@
+//! The `setup` subcommand, which walks through the choices needed to start talking to the assistant
+//! and saves them, so later launches need no command line options.
+
+use anyhow::{bail, Context};
+use clap::ValueEnum;
+use colored::Colorize;
+use cpal::traits::{DeviceTrait, HostTrait};
+use std::{
+    env,
+    io::{self, Write},
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
+};
+use tracing::warn;
+
+use crate::{
+    config,
+    devices::LevelStream,
+    easy_rdev_key::PTTKey,
+    secrets, settings,
+    speakstream::ss::{self, SpeakStream},
+    voice_to_str, VoiceEnum,
+};
+
+/// How long the level meter shows after choosing a microphone.
+const METER_DURATION: Duration = Duration::from_secs(4);
+const METER_WIDTH: usize = 40;
+/// The quietest level the meter shows, in decibels.
+const METER_FLOOR_DB: f32 = -60.0;
+
+/// How long to wait for a voice sample to finish before giving up on it.
+const SAMPLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The push-to-talk key chosen during setup.
+enum ChosenKey {
+    Named(PTTKey),
+    Special(u32),
+}
+
+pub fn run(speak_stream: &Mutex<SpeakStream>) -> Result<(), anyhow::Error> {
+    println!(
+        "{}",
+        "Welcome to quick-assistant! Let's get you set up.".green()
+    );
+    println!(
+        "Your choices will be saved to {}\n",
+        config::CONFIG_PATH.display()
+    );
+
+    choose_api_key()?;
+    let device = choose_input_device()?;
+    let ptt_key = choose_ptt_key()?;
+    let voice = choose_voice(speak_stream)?;
+
+    config::update(|table| {
+        match device {
+            Some(device) => table.insert("device".into(), device.into()),
+            None => table.remove("device"),
+        };
+        table.remove("ptt-key");
+        table.remove("special-ptt-key");
+        match ptt_key {
+            ChosenKey::Named(key) => {
+                let name = key.to_possible_value().unwrap().get_name().to_string();
+                table.insert("ptt-key".into(), name.into())
+            }
+            ChosenKey::Special(code) => table.insert("special-ptt-key".into(), code.into()),
+        };
+        table.insert("ai-voice".into(), voice_to_str(&voice).into());
+    })?;
+    // A voice saved by a tool would otherwise be used instead of the one chosen here.
+    settings::update(|saved| saved.ai_voice = None);
+
+    println!(
+        "\n{} Run quick-assistant with no arguments to start talking.",
+        "All set!".green()
+    );
+    Ok(())
+}
+
+/// Asks a question and returns the trimmed answer.
+pub fn prompt(question: &str) -> Result<String, anyhow::Error> {
+    print!("{} ", question);
+    io::stdout().flush()?;
+    read_answer()
+}
+
+/// Asks for a secret, like an API key, without showing what's typed or pasted.
+pub fn prompt_hidden(question: &str) -> Result<String, anyhow::Error> {
+    print!("{} ", question);
+    io::stdout().flush()?;
+    without_echo(read_answer)
+}
+
+fn read_answer() -> Result<String, anyhow::Error> {
+    let mut answer = String::new();
+    if io::stdin().read_line(&mut answer)? == 0 {
+        bail!("Setup was cancelled");
+    }
+    Ok(answer.trim().to_string())
+}
+
+/// Runs `read` with the terminal not showing what's typed. If stdin isn't a terminal, like when
+/// the answer is piped in, it's read as it is.
+#[cfg(unix)]
+fn without_echo<T>(read: impl FnOnce() -> T) -> T {
+    use std::os::fd::AsRawFd;
+
+    let fd = io::stdin().as_raw_fd();
+    let mut original = std::mem::MaybeUninit::<libc::termios>::uninit();
+    // SAFETY: tcgetattr fills in `original` when it succeeds, and it's only used then.
+    if unsafe { libc::tcgetattr(fd, original.as_mut_ptr()) } != 0 {
+        return read();
+    }
+    let original = unsafe { original.assume_init() };
+    let mut hidden = original;
+    // The Enter at the end is still shown, so the next line doesn't start on this one.
+    hidden.c_lflag &= !libc::ECHO;
+    hidden.c_lflag |= libc::ECHONL;
+    // SAFETY: Both are valid termios for this terminal, from tcgetattr.
+    unsafe { libc::tcsetattr(fd, libc::TCSANOW, &hidden) };
+    let result = read();
+    unsafe { libc::tcsetattr(fd, libc::TCSANOW, &original) };
+    result
+}
+
+#[cfg(windows)]
+fn without_echo<T>(read: impl FnOnce() -> T) -> T {
+    use windows_sys::Win32::System::Console::{
+        GetConsoleMode, GetStdHandle, SetConsoleMode, ENABLE_ECHO_INPUT, STD_INPUT_HANDLE,
+    };
+
+    // SAFETY: The console mode is only changed if it could be read, and is put back afterwards.
+    unsafe {
+        let handle = GetStdHandle(STD_INPUT_HANDLE);
+        let mut mode = 0;
+        if GetConsoleMode(handle, &mut mode) == 0 {
+            return read();
+        }
+        SetConsoleMode(handle, mode & !ENABLE_ECHO_INPUT);
+        let result = read();
+        SetConsoleMode(handle, mode);
+        // The Enter at the end isn't shown either.
+        println!();
+        result
+    }
+}
+
+/// Asks a yes or no question, where an empty answer means yes.
+fn confirm(question: &str) -> Result<bool, anyhow::Error> {
+    let answer = prompt(&format!("{} [Y/n]", question))?;
+    Ok(!answer.to_lowercase().starts_with('n'))
+}
+
+fn choose_api_key() -> Result<(), anyhow::Error> {
+    println!("{}", "1. OpenAI API key".bold());
+    let existing = env::var("OPENAI_API_KEY")
+        .ok()
+        .or_else(secrets::load_api_key);
+    let question = if existing.is_some() {
+        "Paste your OpenAI API key, or press Enter to keep the one you have:"
+    } else {
+        "Paste your OpenAI API key:"
+    };
+
+    loop {
+        let api_key = prompt_hidden(question)?;
+        match (api_key.is_empty(), &existing) {
+            (true, Some(existing)) => {
+                env::set_var("OPENAI_API_KEY", existing);
+                break;
+            }
+            (true, None) => continue,
+            (false, _) => {
+                secrets::save_api_key(&api_key)?;
+                env::set_var("OPENAI_API_KEY", &api_key);
+                println!("Saved to your system keyring.");
+                break;
+            }
+        }
+    }
+    println!();
+    Ok(())
+}
+
+/// Returns the name of the chosen device, or `None` for the default device.
+fn choose_input_device() -> Result<Option<String>, anyhow::Error> {
+    println!("{}", "2. Microphone".bold());
+    let host = cpal::default_host();
+    let devices: Vec<cpal::Device> = host
+        .input_devices()
+        .context("Failed to get list of input devices")?
+        .collect();
+
+    println!("  0: The system default");
+    for (i, device) in devices.iter().enumerate() {
+        println!("  {}: {}", i + 1, device.name().unwrap_or_default());
+    }
+
+    loop {
+        let answer = prompt("Which microphone should be used? [0]")?;
+        let choice = match answer.as_str() {
+            "" => 0,
+            answer => match answer.parse::<usize>() {
+                Ok(choice) if choice <= devices.len() => choice,
+                _ => {
+                    println!("Please enter a number from the list.");
+                    continue;
+                }
+            },
+        };
+        let default_device;
+        let device = match choice {
+            0 => match host.default_input_device() {
+                Some(device) => {
+                    default_device = device;
+                    &default_device
+                }
+                None => {
+                    println!("There's no default microphone. Please pick one from the list.");
+                    continue;
+                }
+            },
+            choice => &devices[choice - 1],
+        };
+
+        println!("Say something to check the level...");
+        if let Err(err) = show_level_meter(device, METER_DURATION) {
+            println!(
+                "{}",
+                format!("Failed to listen to microphone: {:#}", err).red()
+            );
+            continue;
+        }
+        if confirm("Use this microphone?")? {
+            println!();
+            return Ok(match choice {
+                0 => None,
+                _ => Some(device.name().context("Failed to get device name")?),
+            });
+        }
+    }
+}
+
+/// Shows a bar that follows the microphone's loudness, for `duration`.
+fn show_level_meter(device: &cpal::Device, duration: Duration) -> Result<(), anyhow::Error> {
+    let microphone = LevelStream::open(device)?;
+
+    let start = Instant::now();
+    while start.elapsed() < duration {
+        let db = 20.0 * microphone.level().max(f32::MIN_POSITIVE).log10();
+        let filled = ((1.0 - db / METER_FLOOR_DB).clamp(0.0, 1.0) * METER_WIDTH as f32) as usize;
+        print!(
+            "\r  [{}{}]",
+            "#".repeat(filled).green(),
+            " ".repeat(METER_WIDTH - filled)
+        );
+        io::stdout().flush()?;
+        thread::sleep(Duration::from_millis(50));
+    }
+    println!();
+    Ok(())
+}
+
+fn choose_ptt_key() -> Result<ChosenKey, anyhow::Error> {
+    println!("{}", "3. Push-to-talk key".bold());
+    println!("You'll hold this key while you talk, so pick one you don't otherwise use.");
+
+    // rdev can't stop listening, so one listener serves every attempt.
+    let (key_tx, key_rx) = flume::unbounded();
+    thread::spawn(move || {
+        let result = rdev::listen(move |event| {
+            if let rdev::EventType::KeyPress(key) = event.event_type {
+                let _ = key_tx.send(key);
+            }
+        });
+        if let Err(err) = result {
+            warn!("Failed to listen to key presses: {:?}", err);
+        }
+    });
+
+    loop {
+        println!("Press the key you want to use...");
+        // Ignore keys pressed before the question was asked.
+        key_rx.drain();
+        let key = key_rx.recv().context("Failed to listen to key presses")?;
+
+        let chosen = match PTTKey::value_variants()
+            .iter()
+            .find(|&&variant| rdev::Key::from(variant) == key)
+        {
+            Some(&variant) => ChosenKey::Named(variant),
+            None => match key {
+                rdev::Key::Unknown(code) => ChosenKey::Special(code),
+                key => {
+                    println!(
+                        "{:?} can't be used for push to talk. Please pick another key.",
+                        key
+                    );
+                    continue;
+                }
+            },
+        };
+        if confirm(&format!("Use {:?}?", key))? {
+            println!();
+            return Ok(chosen);
+        }
+    }
+}
+
+fn choose_voice(speak_stream: &Mutex<SpeakStream>) -> Result<VoiceEnum, anyhow::Error> {
+    println!("{}", "4. Voice".bold());
+    let voices: Vec<&str> = VoiceEnum::value_variants()
+        .iter()
+        .map(voice_to_str)
+        .collect();
+    println!("The voices are: {}", voices.join(", "));
+
+    speak_stream.lock().unwrap().set_muted(false);
+    let mut heard = None;
+    loop {
+        let question = match &heard {
+            Some(voice) => format!(
+                "Type a voice to hear it, or press Enter to use {}:",
+                voice_to_str(voice)
+            ),
+            None => "Type a voice to hear it:".to_string(),
+        };
+        let answer = prompt(&question)?;
+        if answer.is_empty() {
+            match heard {
+                Some(voice) => return Ok(voice),
+                None => continue,
+            }
+        }
+
+        match VoiceEnum::from_str(&answer, true) {
+            Ok(voice) => {
+                play_sample(speak_stream, &voice);
+                heard = Some(voice);
+            }
+            Err(_) => println!("There's no voice called {}.", answer),
+        }
+    }
+}
+
+/// Speaks a sentence in `voice` and waits for it to finish.
+fn play_sample(speak_stream: &Mutex<SpeakStream>, voice: &VoiceEnum) {
+    let events = {
+        let mut speak_stream = speak_stream.lock().unwrap();
+        let events = speak_stream.subscribe();
+        speak_stream.set_voice(voice.clone());
+        speak_stream.add_token(&format!(
+            "Hi, I'm {}. This is how I'll sound when I answer you.",
+            voice_to_str(voice)
+        ));
+        speak_stream.complete_sentence();
+        events
+    };
+
+    let deadline = Instant::now() + SAMPLE_TIMEOUT;
+    loop {
+        match events.recv_deadline(deadline) {
+            Ok(ss::SpeechEvent::Started(_)) => continue,
+            Ok(ss::SpeechEvent::Finished(_) | ss::SpeechEvent::Stopped) => break,
+            Err(_) => {
+                println!(
+                    "{}",
+                    "The voice sample didn't play. Check your API key and connection.".red()
+                );
+                break;
+            }
+        }
+    }
+}
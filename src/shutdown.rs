@@ -0,0 +1,34 @@
+//! Shutting the assistant down cleanly, when Ctrl+C is pressed or the AI is asked to.
+
+use std::sync::LazyLock;
+use tracing::{info, warn};
+
+static SHUTDOWN: LazyLock<(flume::Sender<()>, flume::Receiver<()>)> =
+    LazyLock::new(|| flume::bounded(1));
+
+/// Asks the assistant to shut down. Asking again while it's shutting down does nothing.
+pub fn request() {
+    let _ = SHUTDOWN.0.try_send(());
+}
+
+/// Blocks until the assistant is asked to shut down.
+pub fn wait() {
+    let _ = SHUTDOWN.1.recv();
+}
+
+/// Asks the assistant to shut down when Ctrl+C is pressed. Pressing it a second time exits straight away,
+/// in case shutting down gets stuck. Must be called from within the tokio runtime.
+pub fn handle_ctrl_c() {
+    tokio::spawn(async {
+        if let Err(err) = tokio::signal::ctrl_c().await {
+            warn!("Failed to listen for Ctrl+C: {}", err);
+            return;
+        }
+        info!("Ctrl+C pressed");
+        request();
+
+        if tokio::signal::ctrl_c().await.is_ok() {
+            std::process::exit(130);
+        }
+    });
+}
@@ -0,0 +1,214 @@
+//! Identifying the song that's playing for the identify_song tool. A few seconds of the computer's
+//! sound or the microphone are recorded and sent to AudD, which recognizes songs from audio.
+
+use anyhow::{bail, Context};
+use base64::Engine;
+use serde::Deserialize;
+use std::{path::Path, sync::OnceLock, thread, time::Duration};
+
+use crate::{config::SongsConfig, record::rec};
+
+/// How much audio is recorded. AudD needs a few seconds, and works best with around ten.
+pub const LISTEN_DURATION: Duration = Duration::from_secs(10);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+struct Settings {
+    audd_key: Option<String>,
+    /// The input device recorded for the computer's sound, like "Stereo Mix".
+    system_audio_device: Option<String>,
+    /// The input device the user talks into.
+    microphone: String,
+}
+
+static SETTINGS: OnceLock<Settings> = OnceLock::new();
+
+/// Sets the AudD key and devices, from the config file and the `--device` option.
+pub fn configure(config: SongsConfig, microphone: String) {
+    let _ = SETTINGS.set(Settings {
+        audd_key: config.audd_key,
+        system_audio_device: config.system_audio_device,
+        microphone,
+    });
+}
+
+fn settings() -> &'static Settings {
+    SETTINGS.get_or_init(|| Settings {
+        audd_key: None,
+        system_audio_device: None,
+        microphone: "default".to_string(),
+    })
+}
+
+/// Where the song is heard.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Source {
+    /// What the computer is playing.
+    System,
+    /// What the microphone hears, like a song on the radio.
+    Microphone,
+}
+
+impl Source {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "system" | "computer" | "speakers" => Some(Self::System),
+            "microphone" | "mic" | "room" => Some(Self::Microphone),
+            _ => None,
+        }
+    }
+}
+
+/// The AudD key, or an error saying how to get one.
+pub fn audd_key() -> Result<&'static str, anyhow::Error> {
+    settings().audd_key.as_deref().context(
+        "Identifying songs needs an AudD API key. Get one from https://audd.io and add it to the \
+         config file as audd-key under [songs].",
+    )
+}
+
+/// Records `LISTEN_DURATION` of audio from `source` and asks AudD what song it is. Returns `None`
+/// when no song was recognized.
+pub fn identify(source: Source) -> Result<Option<String>, anyhow::Error> {
+    let key = audd_key()?;
+    let recording = tempfile::Builder::new()
+        .suffix(".wav")
+        .tempfile()
+        .context("Failed to create a file to record to")?;
+    record(source, recording.path())?;
+    recognize(key, recording.path())
+}
+
+fn record(source: Source, path: &Path) -> Result<(), anyhow::Error> {
+    let settings = settings();
+    match source {
+        Source::Microphone => record_device(&settings.microphone, path),
+        Source::System => match &settings.system_audio_device {
+            Some(device) => record_device(device, path),
+            None => {
+                #[cfg(target_os = "linux")]
+                return record_monitor(path);
+
+                #[allow(unreachable_code)]
+                Err(anyhow::anyhow!(
+                    "Recording the computer's sound needs a loopback input device, like \"Stereo \
+                     Mix\" on Windows or BlackHole on macOS. Set it as system-audio-device under \
+                     [songs] in the config file."
+                ))
+            }
+        },
+    }
+}
+
+fn record_device(device: &str, path: &Path) -> Result<(), anyhow::Error> {
+    let mut recorder = rec::Recorder::new();
+    recorder.start_recording(path, Some(device))?;
+    thread::sleep(LISTEN_DURATION);
+    recorder.stop_recording()
+}
+
+/// Records what the default output is playing from its PulseAudio or PipeWire monitor source.
+#[cfg(target_os = "linux")]
+fn record_monitor(path: &Path) -> Result<(), anyhow::Error> {
+    use std::{
+        io::Read,
+        process::{Command, Stdio},
+    };
+
+    const SAMPLE_RATE: u32 = 22_050;
+    let mut parec = Command::new("parec")
+        .args([
+            "--device=@DEFAULT_MONITOR@",
+            "--format=s16le",
+            "--channels=1",
+            &format!("--rate={}", SAMPLE_RATE),
+        ])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .context("Failed to run parec. Is PulseAudio or PipeWire installed?")?;
+    let mut stdout = parec.stdout.take().context("Failed to read from parec")?;
+    let reader = thread::spawn(move || {
+        let mut audio = Vec::new();
+        let _ = stdout.read_to_end(&mut audio);
+        audio
+    });
+    thread::sleep(LISTEN_DURATION);
+    let _ = parec.kill();
+    let _ = parec.wait();
+    let audio = reader.join().unwrap_or_default();
+    if audio.is_empty() {
+        bail!("No sound could be recorded from the computer");
+    }
+
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: SAMPLE_RATE,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(path, spec).context("Failed to create WAV writer")?;
+    for sample in audio.chunks_exact(2) {
+        writer.write_sample(i16::from_le_bytes([sample[0], sample[1]]))?;
+    }
+    writer.finalize()?;
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct AuddResponse {
+    status: String,
+    result: Option<Song>,
+    error: Option<AuddError>,
+}
+
+#[derive(Deserialize)]
+struct AuddError {
+    error_message: String,
+}
+
+#[derive(Deserialize)]
+struct Song {
+    artist: String,
+    title: String,
+    album: Option<String>,
+    release_date: Option<String>,
+    song_link: Option<String>,
+}
+
+fn recognize(key: &str, path: &Path) -> Result<Option<String>, anyhow::Error> {
+    let audio = std::fs::read(path).context("Failed to read the recording")?;
+    let audio = base64::engine::general_purpose::STANDARD.encode(audio);
+    let response: AuddResponse = reqwest::blocking::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()?
+        .post("https://api.audd.io/")
+        .form(&[("api_token", key), ("audio", audio.as_str())])
+        .send()
+        .context("Failed to reach AudD")?
+        .json()
+        .context("Failed to read AudD's answer")?;
+    if response.status != "success" {
+        bail!(
+            "AudD said: {}",
+            response
+                .error
+                .map(|error| error.error_message)
+                .unwrap_or(response.status)
+        );
+    }
+    let Some(song) = response.result else {
+        return Ok(None);
+    };
+
+    let mut info = format!("\"{}\" by {}", song.title, song.artist);
+    if let Some(album) = song.album.filter(|album| !album.is_empty()) {
+        info.push_str(&format!(", from the album \"{}\"", album));
+    }
+    if let Some(release_date) = song.release_date.filter(|date| !date.is_empty()) {
+        info.push_str(&format!(", released {}", release_date));
+    }
+    if let Some(link) = song.song_link {
+        info.push_str(&format!("\nLink: {}", link));
+    }
+    Ok(Some(info))
+}
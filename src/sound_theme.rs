@@ -0,0 +1,172 @@
+use std::{
+    collections::HashMap,
+    f32::consts::PI,
+    path::{Path, PathBuf},
+};
+use tempfile::NamedTempFile;
+use tracing::warn;
+
+use crate::{config::SoundOverrides, create_temp_file_from_bytes, println_error};
+
+/// The sounds the assistant plays to let the user know what it's doing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Sound {
+    Failed,
+    Alarm,
+    RecordingStarted,
+    RecordingStopped,
+    Thinking,
+    FunctionInvoked,
+}
+
+impl Sound {
+    const ALL: [Sound; 6] = [
+        Sound::Failed,
+        Sound::Alarm,
+        Sound::RecordingStarted,
+        Sound::RecordingStopped,
+        Sound::Thinking,
+        Sound::FunctionInvoked,
+    ];
+
+    /// Where the sound plays between the left (-1.0) and right (1.0) speakers, so events can be told
+    /// apart by ear. Recording sounds come from the left and failures from the right.
+    pub fn pan(self) -> f32 {
+        match self {
+            Sound::RecordingStarted | Sound::RecordingStopped => -0.4,
+            Sound::Failed => 0.4,
+            Sound::Alarm | Sound::Thinking | Sound::FunctionInvoked => 0.0,
+        }
+    }
+
+    /// Looks up a sound by the name it has in the config file, like "recording-started".
+    pub fn from_name(name: &str) -> Option<Sound> {
+        match name {
+            "failed" => Some(Sound::Failed),
+            "alarm" => Some(Sound::Alarm),
+            "recording-started" => Some(Sound::RecordingStarted),
+            "recording-stopped" => Some(Sound::RecordingStopped),
+            "thinking" => Some(Sound::Thinking),
+            "function-invoked" => Some(Sound::FunctionInvoked),
+            _ => None,
+        }
+    }
+}
+
+/// The audio file played for each sound. Built-in sounds are used unless the config file overrides them.
+pub struct SoundTheme {
+    sounds: HashMap<Sound, PathBuf>,
+    // Keeps the built-in sounds' temporary files from being deleted.
+    _builtin_files: Vec<NamedTempFile>,
+}
+
+impl SoundTheme {
+    pub fn new(overrides: &SoundOverrides) -> Self {
+        let mut sounds = HashMap::new();
+        let mut builtin_files = Vec::new();
+
+        for sound in Sound::ALL {
+            let override_path = match sound {
+                Sound::Failed => &overrides.failed,
+                Sound::Alarm => &overrides.alarm,
+                Sound::RecordingStarted => &overrides.recording_started,
+                Sound::RecordingStopped => &overrides.recording_stopped,
+                Sound::Thinking => &overrides.thinking,
+                Sound::FunctionInvoked => &overrides.function_invoked,
+            };
+
+            match override_path {
+                // An alarm that makes no sound would be easy to miss, so it can't be turned off.
+                Some(path) if path.as_os_str().is_empty() && sound != Sound::Alarm => continue,
+                Some(path) if path.is_file() => {
+                    sounds.insert(sound, path.clone());
+                    continue;
+                }
+                Some(path) => {
+                    println_error(&format!(
+                        "Sound file for {:?} not found: {}. Using the built-in sound.",
+                        sound,
+                        path.display()
+                    ));
+                }
+                None => {}
+            }
+
+            if let Some(file) = builtin_sound(sound) {
+                sounds.insert(sound, file.path().to_path_buf());
+                builtin_files.push(file);
+            }
+        }
+
+        SoundTheme {
+            sounds,
+            _builtin_files: builtin_files,
+        }
+    }
+
+    /// Returns the audio file for a sound, or None if the sound is turned off.
+    pub fn path(&self, sound: Sound) -> Option<&Path> {
+        self.sounds.get(&sound).map(PathBuf::as_path)
+    }
+}
+
+fn builtin_sound(sound: Sound) -> Option<NamedTempFile> {
+    match sound {
+        Sound::Failed => Some(create_temp_file_from_bytes(
+            include_bytes!("../assets/failed.mp3"),
+            ".mp3",
+        )),
+        Sound::Alarm => Some(create_temp_file_from_bytes(
+            include_bytes!("../assets/Dreaming of Victory.mp3"),
+            ".mp3",
+        )),
+        Sound::RecordingStarted => tone_file(&[660.0, 880.0]),
+        Sound::RecordingStopped => tone_file(&[880.0, 660.0]),
+        Sound::FunctionInvoked => tone_file(&[1320.0]),
+        // The built-in thinking sounds are generated by the tick module.
+        Sound::Thinking => None,
+    }
+}
+
+/// Creates a WAV file of short, quiet beeps, one after another at the given frequencies.
+fn tone_file(frequencies: &[f32]) -> Option<NamedTempFile> {
+    const SAMPLE_RATE: u32 = 24000;
+    const NOTE_SECONDS: f32 = 0.06;
+    const FADE_SECONDS: f32 = 0.005;
+
+    let note_len = (SAMPLE_RATE as f32 * NOTE_SECONDS) as usize;
+    let fade_len = (SAMPLE_RATE as f32 * FADE_SECONDS) as usize;
+    let samples = frequencies.iter().flat_map(|&frequency| {
+        (0..note_len).map(move |i| {
+            // Fading in and out avoids clicks at the start and end of each note.
+            let fade = (i.min(note_len - i) as f32 / fade_len as f32).min(1.0);
+            let t = i as f32 / SAMPLE_RATE as f32;
+            ((2.0 * PI * frequency * t).sin() * fade * 0.25 * i16::MAX as f32) as i16
+        })
+    });
+
+    let file = tempfile::Builder::new()
+        .prefix("sound")
+        .suffix(".wav")
+        .rand_bytes(16)
+        .tempfile()
+        .ok()?;
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: SAMPLE_RATE,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let result = hound::WavWriter::create(file.path(), spec).and_then(|mut writer| {
+        for sample in samples {
+            writer.write_sample(sample)?;
+        }
+        writer.finalize()
+    });
+    if let Err(err) = result {
+        warn!("Failed to create built-in sound: {}", err);
+        return None;
+    }
+
+    Some(file)
+}
@@ -1,29 +1,33 @@
 pub mod ss {
 
     use anyhow::Context;
-    use async_openai::{
-        types::{CreateSpeechRequestArgs, SpeechModel, Voice},
-        Client,
-    };
+    use async_openai::config::{Config, OpenAIConfig};
     use async_std::future;
     use colored::Colorize;
-    use futures::{future::FutureExt, select};
-    use rodio::OutputStream;
+    use futures::select;
+    use serde_json::json;
     use std::io::BufReader;
-    use std::path::Path;
-    use std::process::Command;
-    use std::sync::Arc;
+    use rodio::buffer::SamplesBuffer;
+    use rodio::source::{Buffered, EmptyCallback, SkipDuration};
+    use rodio::Source;
+    use std::path::{Path, PathBuf};
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
     use std::thread;
     use std::time::Duration;
     use tempfile::Builder;
     use tempfile::NamedTempFile;
-    use tokio::task;
     
     use tracing::info;
     use tracing::{debug, warn};
 
-    use crate::error_and_panic;
+    use crate::audio::{self, AudioSink, Channel};
+    use crate::ducking;
+    use crate::speech_text::{normalize_for_speech, take_speech_tags, SpeechStyle};
+    use crate::time_stretch::time_stretch;
     use crate::truncate;
+    use crate::{tts_model_to_str, voice_to_str, TtsModelEnum, VoiceEnum};
+    use crate::tts_cache;
 
     fn println_error(err: &str) {
         println!("{}: {}", "Error".truecolor(255, 0, 0), err);
@@ -35,6 +39,10 @@ pub mod ss {
     struct SentenceAccumulator {
         buffer: String,
         sentence_end_chars: Vec<char>,
+        clause_end_chars: Vec<char>,
+        // Whether the next sentence is the first of a response.
+        // Nothing is spoken until it's converted, so it's cut short at the first clause break.
+        first_sentence: bool,
     }
 
     impl SentenceAccumulator {
@@ -42,6 +50,8 @@ pub mod ss {
             SentenceAccumulator {
                 buffer: String::new(),
                 sentence_end_chars: vec!['.', '?', '!'],
+                clause_end_chars: vec![',', ';', ':'],
+                first_sentence: true,
             }
         }
 
@@ -79,8 +89,13 @@ pub mod ss {
                 } else if self.buffer.len() > 15 {
                     if let Some(second_to_last_char) = get_second_to_last_char(&self.buffer) {
                         if
-                        // If the second to last character is a sentence ending character
-                        self.sentence_end_chars.contains(&second_to_last_char)
+                        // If the second to last character is a sentence ending character,
+                        (self.sentence_end_chars.contains(&second_to_last_char)
+                            // or a clause ending character in a long enough first sentence,
+                            || (self.first_sentence
+                                && sentences.is_empty()
+                                && self.buffer.len() > 40
+                                && self.clause_end_chars.contains(&second_to_last_char)))
                         // and the last character is whitespace.
                         && self
                             .buffer
@@ -101,6 +116,10 @@ pub mod ss {
                 }
             }
 
+            if !sentences.is_empty() {
+                self.first_sentence = false;
+            }
+
             sentences
         }
 
@@ -115,205 +134,560 @@ pub mod ss {
                 None
             };
             self.buffer.clear();
+            self.first_sentence = true;
             sentence_option
         }
 
         fn clear_buffer(&mut self) {
             self.buffer.clear();
+            self.first_sentence = true;
         }
     }
 
-    /// Speeds up an audio file by a factor of `speed`.
-    fn adjust_audio_file_speed(input: &Path, output: &Path, speed: f32) {
-        // ffmpeg -y -i input.mp3 -filter:a "atempo={speed}" -vn output.mp3
-        match Command::new("ffmpeg")
-            .args([
-                "-y",
-                "-i",
-                input
-                    .to_str()
-                    .context("Failed to convert input path to string")
-                    .unwrap(),
-                // -codec:a libmp3lame -b:a 160k
-                // audio quality decreases from 160k bitrate to 33k bitrate without these lines.
-                "-codec:a",
-                "libmp3lame",
-                "-b:a",
-                "160k",
-                //
-                "-filter:a",
-                format!("atempo={}", speed).as_str(),
-                "-vn",
-                output
-                    .to_str()
-                    .context("Failed to convert output path to string")
-                    .unwrap(),
-            ])
-            .output()
-        {
-            Ok(x) => {
-                if !x.status.success() {
-                    error_and_panic("ffmpeg failed to adjust audio speed");
-                }
-                x
-            }
-            Err(err) => {
-                if err.kind() == std::io::ErrorKind::NotFound {
-                    error_and_panic(
-                        "ffmpeg not found. Please install ffmpeg and add it to your PATH",
-                    );
-                } else {
-                    error_and_panic("ffmpeg failed to adjust audio speed");
-                }
-            }
+    /// Time stretches an audio file by a factor of `speed`, which changes its tempo but not its pitch,
+    /// and writes the result to `output` as a WAV file.
+    fn time_stretch_to_wav(input: &Path, output: &Path, speed: f32) -> Result<(), anyhow::Error> {
+        let file = std::fs::File::open(input).context("Failed to open speech segment")?;
+        let decoder =
+            rodio::Decoder::new(BufReader::new(file)).context("Failed to decode speech segment")?;
+        let channels = decoder.channels();
+        let sample_rate = decoder.sample_rate();
+        let samples: Vec<i16> = decoder.collect();
+
+        let stretched = time_stretch(&samples, channels, sample_rate, speed);
+
+        let spec = hound::WavSpec {
+            channels,
+            sample_rate,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
         };
+        let mut writer =
+            hound::WavWriter::create(output, spec).context("Failed to create WAV writer")?;
+        for sample in stretched {
+            writer.write_sample(sample)?;
+        }
+        writer.finalize().context("Failed to finalize WAV file")?;
+
+        Ok(())
+    }
+
+    /// Decodes a speech segment, trimming the long silences text to speech leaves at its start and end
+    /// so consecutive sentences flow together. `pause_before` is added back as silence at the start.
+    fn decode_speech_segment(
+        path: &Path,
+        pause_before: Duration,
+    ) -> Result<SamplesBuffer<i16>, anyhow::Error> {
+        // Samples quieter than this are treated as silence.
+        const SILENCE_THRESHOLD: u16 = 300;
+        // A little silence is kept so words aren't clipped and sentences still have a natural pause between them.
+        const LEADING_SILENCE_KEPT: f32 = 0.02;
+        const TRAILING_SILENCE_KEPT: f32 = 0.15;
+
+        let file = std::fs::File::open(path).context("Failed to open speech segment")?;
+        let decoder =
+            rodio::Decoder::new(BufReader::new(file)).context("Failed to decode speech segment")?;
+        let channels = decoder.channels();
+        let sample_rate = decoder.sample_rate();
+        let samples: Vec<i16> = decoder.collect();
+
+        let frame_count = samples.len() / channels as usize;
+        let is_loud = |frame: &[i16]| frame.iter().any(|s| s.unsigned_abs() > SILENCE_THRESHOLD);
+        let first_loud = samples.chunks_exact(channels as usize).position(is_loud);
+        let last_loud = samples.chunks_exact(channels as usize).rposition(is_loud);
+        let (start, end) = match (first_loud, last_loud) {
+            (Some(first), Some(last)) => (
+                first.saturating_sub((LEADING_SILENCE_KEPT * sample_rate as f32) as usize),
+                (last + 1 + (TRAILING_SILENCE_KEPT * sample_rate as f32) as usize).min(frame_count),
+            ),
+            _ => (0, frame_count),
+        };
+
+        let pause_frames = (pause_before.as_secs_f32() * sample_rate as f32) as usize;
+        let mut trimmed = vec![0; pause_frames * channels as usize];
+        trimmed.extend_from_slice(&samples[start * channels as usize..end * channels as usize]);
+
+        Ok(SamplesBuffer::new(channels, sample_rate, trimmed))
+    }
+
+    /// Requests speech audio from OpenAI's speech endpoint.
+    ///
+    /// The request is sent directly instead of through async-openai, whose request type
+    /// doesn't know about the newer voices or the `instructions` field.
+    async fn request_speech(
+        ai_text: &str,
+        voice: &VoiceEnum,
+        model: &TtsModelEnum,
+        instructions: Option<&str>,
+    ) -> Result<Vec<u8>, anyhow::Error> {
+        let config = OpenAIConfig::default();
+
+        let mut body = json!({
+            "model": tts_model_to_str(model),
+            "input": ai_text,
+            "voice": voice_to_str(voice),
+            "response_format": "mp3",
+        });
+        if let Some(instructions) = instructions {
+            body["instructions"] = json!(instructions);
+        }
+
+        let response = reqwest::Client::new()
+            .post(config.url("/audio/speech"))
+            .headers(config.headers())
+            .json(&body)
+            .send()
+            .await
+            .context("Failed to send text to speech request")?
+            .error_for_status()
+            .context("Text to speech request failed")?;
+
+        Ok(response.bytes().await?.to_vec())
     }
 
     /// Turns text into speech using the AI voice.
     async fn turn_text_to_speech(
         ai_text: String,
         speed: f32,
-        voice: Voice,
+        voice: VoiceEnum,
+        model: TtsModelEnum,
+        instructions: Option<String>,
     ) -> Option<(NamedTempFile, String)> {
-        let client = Client::new();
+        let voice_str = voice_to_str(&voice);
+        let model_str = tts_model_to_str(&model);
 
-        // Turn AI's response into speech
-
-        let request = CreateSpeechRequestArgs::default()
-            .input(&ai_text)
-            .voice(voice.clone())
-            .model(SpeechModel::Tts1)
-            .build()
-            .unwrap();
-
-        let response =
-            match future::timeout(Duration::from_secs(15), client.audio().speech(request)).await {
-                Ok(transcription_result) => transcription_result,
-                Err(err) => {
-                    println_error(&format!(
-                        "Failed to turn text to speech due to timeout: {:?}",
-                        err
-                    ));
-
-                    // play_audio(&failed_temp_file.path());
+        // Replay the segment from the cache if this exact text has been spoken before.
+        if let Some(cached_path) =
+            tts_cache::get(&ai_text, voice_str, model_str, instructions.as_deref(), speed)
+        {
+            let extension = cached_path
+                .extension()
+                .map(|ext| format!(".{}", ext.to_string_lossy()))
+                .unwrap_or_default();
+            let cached_segment_tempfile = Builder::new()
+                .prefix("ai-speech-segment")
+                .suffix(&extension)
+                .rand_bytes(16)
+                .tempfile()
+                .unwrap();
 
-                    // continue;
-                    return None;
+            match std::fs::copy(&cached_path, cached_segment_tempfile.path()) {
+                Ok(_) => {
+                    debug!("Using cached speech for: \"{}\"", truncate(&ai_text, 20));
+                    return Some((cached_segment_tempfile, ai_text));
                 }
+                Err(err) => warn!("Failed to copy cached speech segment: {}", err),
             }
-            .unwrap();
-
-        let ai_speech_segment_tempfile = Builder::new()
-            .prefix("ai-speech-segment")
-            .suffix(".mp3")
-            .rand_bytes(16)
-            .tempfile()
-            .unwrap();
+        }
 
-        let _ = match future::timeout(
-            Duration::from_secs(10),
-            response.save(ai_speech_segment_tempfile.path()),
+        // Turn AI's response into speech
+        let speech = match future::timeout(
+            Duration::from_secs(15),
+            request_speech(&ai_text, &voice, &model, instructions.as_deref()),
         )
         .await
         {
-            Ok(transcription_result) => transcription_result,
+            Ok(Ok(speech)) => speech,
+            Ok(Err(err)) => {
+                println_error(&format!("Failed to turn text to speech: {:?}", err));
+                return None;
+            }
             Err(err) => {
                 println_error(&format!(
-                    "Failed to save ai speech to file due to timeout: {:?}",
+                    "Failed to turn text to speech due to timeout: {:?}",
                     err
                 ));
-
                 return None;
             }
         };
 
-        if speed != 1.0 {
+        let ai_speech_segment_tempfile = Builder::new()
+            .prefix("ai-speech-segment")
+            .suffix(".mp3")
+            .rand_bytes(16)
+            .tempfile()
+            .unwrap();
+
+        if let Err(err) = std::fs::write(ai_speech_segment_tempfile.path(), speech) {
+            println_error(&format!("Failed to save ai speech to file: {:?}", err));
+            return None;
+        }
+
+        let speech_tempfile = if speed != 1.0 {
             let sped_up_audio_path = Builder::new()
                 .prefix("quick-assist-ai-voice-sped-up")
-                .suffix(".mp3")
+                .suffix(".wav")
                 .rand_bytes(16)
                 .tempfile()
                 .unwrap();
 
-            adjust_audio_file_speed(
+            match time_stretch_to_wav(
                 ai_speech_segment_tempfile.path(),
                 sped_up_audio_path.path(),
                 speed,
-            );
-
-            Some((sped_up_audio_path, ai_text))
+            ) {
+                Ok(_) => sped_up_audio_path,
+                Err(err) => {
+                    println_error(&format!("Failed to adjust speech speed: {:?}", err));
+                    ai_speech_segment_tempfile
+                }
+            }
         } else {
-            Some((ai_speech_segment_tempfile, ai_text))
+            ai_speech_segment_tempfile
+        };
+
+        if let Err(err) = tts_cache::insert(
+            &ai_text,
+            voice_str,
+            model_str,
+            instructions.as_deref(),
+            speed,
+            speech_tempfile.path(),
+        ) {
+            warn!("Failed to cache speech segment: {}", err);
+        }
+
+        Some((speech_tempfile, ai_text))
+    }
+
+    /// Returns the instructions and speed to convert a sentence with,
+    /// combining the AI voice's settings with the sentence's speech tags.
+    fn speech_settings(
+        tts_model: &TtsModelEnum,
+        tts_instructions: Option<String>,
+        speech_speed: f32,
+        style: &SpeechStyle,
+    ) -> (Option<String>, f32) {
+        // Only some models take instructions. The others would reject the request.
+        let instructions = match (tts_instructions, style.instructions.clone()) {
+            _ if *tts_model != TtsModelEnum::Gpt4oMiniTts => None,
+            (Some(instructions), Some(style_instructions)) => {
+                Some(format!("{} {}", instructions, style_instructions))
+            }
+            (instructions, style_instructions) => instructions.or(style_instructions),
+        };
+        (instructions, speech_speed * style.speed)
+    }
+
+    /// Joins speech segments into one audio file at `output`, with the file extension added.
+    /// MP3 segments are joined as they are. If any segment isn't an MP3, they're all decoded and saved as a WAV.
+    fn concatenate_audio_files(
+        segments: &[NamedTempFile],
+        output: &Path,
+    ) -> Result<PathBuf, anyhow::Error> {
+        let all_mp3 = segments
+            .iter()
+            .all(|segment| segment.path().extension().is_some_and(|ext| ext == "mp3"));
+
+        if all_mp3 {
+            // MP3 files are a series of independent frames, so they can be joined end to end.
+            let output = output.with_extension("mp3");
+            let mut audio = Vec::new();
+            for segment in segments {
+                audio.extend(std::fs::read(segment.path()).context("Failed to read speech segment")?);
+            }
+            std::fs::write(&output, audio).context("Failed to write audio file")?;
+            return Ok(output);
+        }
+
+        let output = output.with_extension("wav");
+        let mut writer: Option<hound::WavWriter<_>> = None;
+        for segment in segments {
+            let file = std::fs::File::open(segment.path()).context("Failed to open speech segment")?;
+            let decoder = rodio::Decoder::new(BufReader::new(file))
+                .context("Failed to decode speech segment")?;
+            let writer = match &mut writer {
+                Some(writer) => writer,
+                None => {
+                    let spec = hound::WavSpec {
+                        channels: decoder.channels(),
+                        sample_rate: decoder.sample_rate(),
+                        bits_per_sample: 16,
+                        sample_format: hound::SampleFormat::Int,
+                    };
+                    writer.insert(
+                        hound::WavWriter::create(&output, spec)
+                            .context("Failed to create WAV writer")?,
+                    )
+                }
+            };
+            for sample in decoder {
+                writer.write_sample(sample)?;
+            }
         }
+        if let Some(writer) = writer {
+            writer.finalize().context("Failed to finalize WAV file")?;
+        }
+
+        Ok(output)
     }
 
     fn get_second_to_last_char(s: &str) -> Option<char> {
         s.chars().rev().nth(1)
     }
 
+    /// What the AI voice is doing, for showing live captions of what's being spoken.
+    #[derive(Clone, Debug)]
+    pub enum SpeechEvent {
+        /// A sentence started playing.
+        Started(String),
+        /// A sentence finished playing or was skipped.
+        Finished(String),
+        /// Speech was stopped and everything queued was discarded.
+        Stopped,
+    }
+
+    /// Sends an event to every subscriber, forgetting subscribers that have hung up.
+    fn emit(subscribers: &Mutex<Vec<flume::Sender<SpeechEvent>>>, event: SpeechEvent) {
+        subscribers
+            .lock()
+            .unwrap()
+            .retain(|tx| tx.send(event.clone()).is_ok());
+    }
+
+    /// Counts a queued sentence as done, whether it was spoken or dropped.
+    /// Saturates, because stop_speech resets the count while sentences are still on their way out.
+    fn sentence_done(unspoken: &AtomicUsize) {
+        let _ = unspoken.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1));
+    }
+
+    /// A sentence waiting to be turned into speech: the speech generation it belongs to,
+    /// its text, and how it should be spoken.
+    type QueuedSentence = (u64, String, SpeechStyle);
+
+    /// A speech segment ready to be played: the speech generation it belongs to, the audio file,
+    /// the text that was spoken, and how long to stay silent before playing it.
+    type SpeechSegment = (u64, NamedTempFile, String, Duration);
+
+    // How often a sentence being spoken checks whether it's been skipped.
+    const SKIP_CHECK_INTERVAL: Duration = Duration::from_millis(5);
+
+    /// Something the audio playing thread has to act on.
+    enum PlayerEvent {
+        /// A speech segment is ready to be played.
+        Segment(SpeechSegment),
+        /// Everything but the end of the segment with this id has played.
+        BodyEnded(u64),
+    }
+
+    /// A silent sound that calls `callback` when the sink gets to it.
+    fn mark(callback: impl Fn() + Send + 'static) -> EmptyCallback<f32> {
+        EmptyCallback::new(Box::new(callback))
+    }
+
+    /// Ends `source` early once `skipped` is set.
+    fn skippable<S>(source: S, skipped: Arc<AtomicBool>) -> impl Source<Item = S::Item> + Send
+    where
+        S: Source + Send,
+        S::Item: rodio::Sample,
+    {
+        source
+            .stoppable()
+            .periodic_access(SKIP_CHECK_INTERVAL, move |source| {
+                if skipped.load(Ordering::SeqCst) {
+                    source.stop();
+                }
+            })
+    }
+
+    /// Reports on a speech segment as the sink gets to it.
+    #[derive(Clone)]
+    struct SegmentMarks {
+        generation: u64,
+        text: String,
+        skipped: Arc<AtomicBool>,
+        speech_generation: Arc<Mutex<u64>>,
+        speaking: Arc<Mutex<Option<Arc<AtomicBool>>>>,
+        event_subscribers: Arc<Mutex<Vec<flume::Sender<SpeechEvent>>>>,
+        unspoken: Arc<AtomicUsize>,
+    }
+
+    impl SegmentMarks {
+        // A stopped sentence is covered by the Stopped event sent by stop_speech.
+        fn is_current(&self) -> bool {
+            self.generation == *self.speech_generation.lock().unwrap()
+        }
+
+        fn started(&self) -> EmptyCallback<f32> {
+            let marks = self.clone();
+            mark(move || {
+                if marks.is_current() {
+                    *marks.speaking.lock().unwrap() = Some(marks.skipped.clone());
+                    info!("Playing AI voice audio: \"{}\"", truncate(&marks.text, 20));
+                    emit(
+                        &marks.event_subscribers,
+                        SpeechEvent::Started(marks.text.clone()),
+                    );
+                }
+            })
+        }
+
+        fn finished(&self) -> EmptyCallback<f32> {
+            let marks = self.clone();
+            mark(move || {
+                sentence_done(&marks.unspoken);
+                if marks.is_current() {
+                    emit(
+                        &marks.event_subscribers,
+                        SpeechEvent::Finished(marks.text.clone()),
+                    );
+                }
+            })
+        }
+    }
+
+    /// The end of a speech segment, held back so the next segment can fade in over it.
+    /// It's played as it is if the rest of the segment finishes before the next one arrives.
+    struct HeldTail {
+        // Sent once the rest of the segment has played.
+        id: u64,
+        generation: u64,
+        source: SkipDuration<Buffered<SamplesBuffer<i16>>>,
+        duration: Duration,
+        skipped: Arc<AtomicBool>,
+        finished: EmptyCallback<f32>,
+    }
+
     /// SpeakStream is a struct that accumulates tokens into sentences
     /// Once a sentence is complete, it speaks the sentence using the AI voice.
     pub struct SpeakStream {
         sentence_accumulator: SentenceAccumulator,
-        ai_tts_tx: flume::Sender<String>,
-        ai_tts_rx: flume::Receiver<String>,
+        ai_tts_tx: flume::Sender<QueuedSentence>,
+        ai_tts_rx: flume::Receiver<QueuedSentence>,
         futures_ordered_kill_tx: flume::Sender<()>,
-        stop_speech_tx: flume::Sender<()>,
-        ai_audio_playing_rx: flume::Receiver<(NamedTempFile, String)>,
+        ai_audio_playing_rx: flume::Receiver<SpeechSegment>,
+        // Incremented every time speech is stopped. Sentences are tagged with the generation
+        // they were queued in, and any from an older generation are thrown away instead of played.
+        speech_generation: Arc<Mutex<u64>>,
+        shutdown_tx: Option<flume::Sender<()>>,
+        playing_thread: Option<thread::JoinHandle<()>>,
+        // Every sentence is spoken through this one sink, so consecutive sentences play without gaps.
+        sink: Arc<Mutex<Arc<dyn AudioSink>>>,
+        // Set to skip the sentence being spoken.
+        speaking: Arc<Mutex<Option<Arc<AtomicBool>>>>,
+        paused: Arc<Mutex<bool>>,
+        crossfade: Arc<Mutex<Duration>>,
+        event_subscribers: Arc<Mutex<Vec<flume::Sender<SpeechEvent>>>>,
+        // How many queued sentences haven't finished playing or been dropped yet.
+        unspoken: Arc<AtomicUsize>,
+        // The sentences of the response currently being spoken, and of the one before it.
+        response_sentences: Vec<(String, SpeechStyle)>,
+        last_response_sentences: Vec<(String, SpeechStyle)>,
+        // The most characters of a single response that will be turned into speech.
+        max_response_chars: Option<usize>,
+        response_chars: usize,
+        // While muted, sentences are never sent to be turned into speech, so nothing is billed.
+        muted: bool,
+        voice: Arc<Mutex<VoiceEnum>>,
+        tts_model: TtsModelEnum,
+        tts_instructions: Option<String>,
+        speech_speed: Arc<Mutex<f32>>,
     }
 
     impl SpeakStream {
-        pub fn new(voice: Voice, speech_speed: f32) -> (Self, OutputStream) {
+        pub fn new(
+            voice: VoiceEnum,
+            tts_model: TtsModelEnum,
+            tts_instructions: Option<String>,
+            speech_speed: f32,
+            volume: f32,
             // The maximum number of audio files that can be queued up to be played by the AI voice audio
             // playing thread Limiting this number prevents converting too much text to speech at once and
             // incurring large API costs for conversions that may not be used if speaking is stopped.
-            const AI_VOICE_SINK_BUFFER_SIZE: usize = 10;
+            buffer_size: usize,
+            max_response_chars: Option<usize>,
+        ) -> Self {
 
             // The sentence accumulator sends sentences to this channel to be turned into speech audio
-            let (ai_tts_tx, ai_tts_rx): (flume::Sender<String>, flume::Receiver<String>) =
-                flume::unbounded();
-
-            let (stop_speech_tx, stop_speech_rx): (flume::Sender<()>, flume::Receiver<()>) =
-                flume::unbounded();
-
-            // Create the AI voice audio sink
-            let (_stream, stream_handle) = rodio::OutputStream::try_default().unwrap();
-            let ai_voice_sink = rodio::Sink::try_new(&stream_handle).unwrap();
-            let _ai_voice_sink = Arc::new(ai_voice_sink);
+            let (ai_tts_tx, ai_tts_rx): (
+                flume::Sender<QueuedSentence>,
+                flume::Receiver<QueuedSentence>,
+            ) = flume::unbounded();
 
             let (ai_audio_playing_tx, ai_audio_playing_rx): (
-                flume::Sender<(NamedTempFile, String)>,
-                flume::Receiver<(NamedTempFile, String)>,
-            ) = flume::bounded(AI_VOICE_SINK_BUFFER_SIZE);
+                flume::Sender<SpeechSegment>,
+                flume::Receiver<SpeechSegment>,
+            ) = flume::bounded(buffer_size);
 
             let (futures_ordered_kill_tx, futures_ordered_kill_rx): (
                 flume::Sender<()>,
                 flume::Receiver<()>,
             ) = flume::unbounded();
 
+            // Nothing is ever sent on this channel. It's disconnected when the SpeakStream is dropped,
+            // which tells the conversion task and the audio playing thread to exit.
+            let (shutdown_tx, shutdown_rx): (flume::Sender<()>, flume::Receiver<()>) =
+                flume::bounded(1);
+
+            let speech_generation = Arc::new(Mutex::new(0));
+
+            // The AI voice's volume is the speech channel's, so volume changes apply
+            // to the sentence being spoken instead of waiting for the next one.
+            audio::set_channel_volume(Channel::Speech, volume);
+            let sink = audio::new_sink(Channel::Speech);
+            // Played through the audio service, so it's held while an alarm preempts the AI voice.
+            sink.pause();
+            audio::play(&sink);
+            let sink = Arc::new(Mutex::new(sink));
+            let speaking: Arc<Mutex<Option<Arc<AtomicBool>>>> = Arc::new(Mutex::new(None));
+            let paused = Arc::new(Mutex::new(false));
+            let unspoken = Arc::new(AtomicUsize::new(0));
+            let crossfade = Arc::new(Mutex::new(Duration::ZERO));
+            let event_subscribers: Arc<Mutex<Vec<flume::Sender<SpeechEvent>>>> =
+                Arc::new(Mutex::new(Vec::new()));
+
             // Create text to speech conversion thread
             // that will convert text to speech and pass the audio file path to
             // the ai voice audio playing thread
             let thread_ai_tts_rx = ai_tts_rx.clone();
+            // The voice can be changed while speaking, so each sentence uses whichever is current.
+            let voice = Arc::new(Mutex::new(voice));
             let thread_voice = voice.clone();
+            let thread_tts_model = tts_model.clone();
+            let thread_tts_instructions = tts_instructions.clone();
+            let speech_speed = Arc::new(Mutex::new(speech_speed));
+            let thread_speech_speed = speech_speed.clone();
+            let thread_speech_generation = speech_generation.clone();
+            let thread_shutdown_rx = shutdown_rx.clone();
+            let thread_unspoken = unspoken.clone();
             tokio::spawn(async move {
                 // Create the futures ordered queue Used to turn text into speech
-                // let (mut converting_tx, mut converting_rx) = tokio::sync::mpsc::unbounded_channel();
-                let (converting_tx, converting_rx) = flume::bounded(AI_VOICE_SINK_BUFFER_SIZE);
+                let (converting_tx, converting_rx) = flume::bounded(buffer_size);
 
                 {
-                    let converting_tx = converting_tx.clone();
+                    let thread_speech_generation = thread_speech_generation.clone();
+                    let thread_unspoken = thread_unspoken.clone();
                     tokio::spawn(async move {
                         // Queue up any text segments to be turned into speech.
-                        while let Ok(ai_text) = thread_ai_tts_rx.recv_async().await {
-                            let thread_voice = thread_voice.clone();
-                            let thread_ai_text = ai_text.clone();
-                            converting_tx
-                                .send_async(tokio::spawn(async move {
-                                    turn_text_to_speech(thread_ai_text, speech_speed, thread_voice)
-                                }))
+                        while let Ok((generation, ai_text, style)) = thread_ai_tts_rx.recv_async().await {
+                            // Don't pay for converting a sentence that was stopped before it was converted.
+                            if generation != *thread_speech_generation.lock().unwrap() {
+                                sentence_done(&thread_unspoken);
+                                continue;
+                            }
+
+                            let (instructions, speed) = speech_settings(
+                                &thread_tts_model,
+                                thread_tts_instructions.clone(),
+                                *thread_speech_speed.lock().unwrap(),
+                                &style,
+                            );
+                            // Spawning starts the conversion right away, so later sentences convert
+                            // while earlier ones are still converting or playing.
+                            let handle = tokio::spawn(turn_text_to_speech(
+                                ai_text.clone(),
+                                speed,
+                                thread_voice.lock().unwrap().clone(),
+                                thread_tts_model.clone(),
+                                instructions,
+                            ));
+                            if converting_tx
+                                .send_async((generation, style.pause_before, handle))
                                 .await
-                                .unwrap();
+                                .is_err()
+                            {
+                                break;
+                            }
 
                             debug!(
                                 "Sent text-to-speech conversion request to the text-to-speech conversion thread with text: \"{}\"", truncate(&ai_text, 20)
@@ -323,127 +697,272 @@ pub mod ss {
                 }
 
                 loop {
-                    // tokio sleep is needed here because otherwise this green thread
-                    // takes up so much compute that other green threads never get to run.
-                    tokio::time::sleep(Duration::from_millis(100)).await;
-
-                    // Empty the futures ordered queue if the kill channel has received a message
-                    for _ in futures_ordered_kill_rx.try_iter() {
-                        while let Ok(handle) = converting_rx.try_recv() {
-                            handle.abort();
+                    // Wait for the oldest conversion instead of polling, so the first sentence
+                    // of a response starts playing the moment its audio is ready.
+                    let (generation, pause_before, handle) = select! {
+                        kill = futures_ordered_kill_rx.recv_async() => {
+                            if kill.is_err() {
+                                break;
+                            }
+                            // Empty the futures ordered queue
+                            while let Ok((_, _, handle)) = converting_rx.try_recv() {
+                                handle.abort();
+                            }
+                            continue;
                         }
-                    }
-
-                    while let Ok(handle) = converting_rx.try_recv() {
-                        let handle = handle.await.unwrap();
-
-                        let tempfile_option = handle.await;
+                        conversion = converting_rx.recv_async() => match conversion {
+                            Ok(conversion) => conversion,
+                            Err(_) => break,
+                        },
+                        _ = thread_shutdown_rx.recv_async() => break,
+                        // Every channel has disconnected, so the SpeakStream is gone.
+                        complete => break,
+                    };
 
-                        match tempfile_option {
-                            Some((tempfile, ai_text)) => {
-                                let mut kill_signal_sent = false;
-                                // Empty the futures ordered queue if the kill channel has received a message
-                                for _ in futures_ordered_kill_rx.try_iter() {
-                                    while let Ok(handle) = converting_rx.try_recv() {
-                                        handle.abort();
-                                    }
-                                    kill_signal_sent = true;
-                                }
-
-                                if !kill_signal_sent {
-                                    // send tempfile to ai voice audio playing thread
-                                    ai_audio_playing_tx.send((tempfile, ai_text)).unwrap();
-                                }
+                    match handle.await.unwrap_or(None) {
+                        Some((tempfile, ai_text)) => {
+                            if generation != *thread_speech_generation.lock().unwrap() {
+                                sentence_done(&thread_unspoken);
+                                continue;
                             }
-                            None => {
-                                // play_audio(&failed_temp_file.path());
-                                println_error("failed to turn text to speech");
+
+                            // send tempfile to ai voice audio playing thread
+                            if ai_audio_playing_tx
+                                .send_async((generation, tempfile, ai_text, pause_before))
+                                .await
+                                .is_err()
+                            {
+                                break;
                             }
                         }
+                        None => {
+                            // play_audio(&failed_temp_file.path());
+                            println_error("failed to turn text to speech");
+                            sentence_done(&thread_unspoken);
+                        }
                     }
                 }
+
+                // Stop paying for conversions that will never be played.
+                while let Ok((_, _, handle)) = converting_rx.try_recv() {
+                    handle.abort();
+                }
             });
 
             // Create the ai voice audio playing thread
-            // let thread_ai_voice_sink = ai_voice_sink.clone();
             let thread_ai_audio_playing_rx = ai_audio_playing_rx.clone();
-            thread::spawn(move || {
-                let runtime = tokio::runtime::Runtime::new()
-                    .context("Failed to create tokio runtime")
-                    .unwrap();
-
-                let (mut _stream, _stream_handle) = rodio::OutputStream::try_default().unwrap();
-                let ai_voice_sink = rodio::Sink::try_new(&stream_handle).unwrap();
-                let mut ai_voice_sink = Arc::new(ai_voice_sink);
-
-                for (ai_speech_segment, ai_text) in thread_ai_audio_playing_rx.iter() {
-                    // create new stream and sink
-                    let (new_stream, stream_handle) = rodio::OutputStream::try_default().unwrap();
-                    let new_ai_voice_sink = rodio::Sink::try_new(&stream_handle).unwrap();
-
-                    // put them in the persistent vars
-                    ai_voice_sink = Arc::new(new_ai_voice_sink);
-                    _stream = new_stream;
+            let thread_speech_generation = speech_generation.clone();
+            let thread_sink = sink.clone();
+            let thread_speaking = speaking.clone();
+            let thread_event_subscribers = event_subscribers.clone();
+            let thread_crossfade = crossfade.clone();
+            let thread_unspoken = unspoken.clone();
+            let playing_thread = thread::spawn(move || {
+                // A segment's end is held back while the rest of it plays. Its id is sent here once
+                // the rest has played, if the next segment hasn't arrived to fade in over it by then.
+                let (body_ended_tx, body_ended_rx) = flume::unbounded();
+                let mut held_tail: Option<HeldTail> = None;
+                let mut next_id: u64 = 0;
 
-                    // play the sound of AI speech
-                    let file = std::fs::File::open(ai_speech_segment.path()).unwrap();
-                    ai_voice_sink.stop();
-                    ai_voice_sink.append(rodio::Decoder::new(BufReader::new(file)).unwrap());
-                    info!("Playing AI voice audio: \"{}\"", truncate(&ai_text, 20));
-
-                    // sink.play();
-
-                    while stop_speech_rx.try_recv().is_ok() {}
-
-                    // ai_voice_sink.stop();
-                    runtime.block_on(async {
-                        let blocking_task = {
-                            let ai_voice_sink = ai_voice_sink.clone();
+                loop {
+                    // Waiting with a timeout keeps other audio ducked through the short gaps between sentences.
+                    let event = flume::Selector::new()
+                        .recv(&thread_ai_audio_playing_rx, |segment| {
+                            segment.ok().map(PlayerEvent::Segment)
+                        })
+                        .recv(&body_ended_rx, |id| id.ok().map(PlayerEvent::BodyEnded))
+                        .recv(&shutdown_rx, |_| None)
+                        .wait_timeout(Duration::from_millis(500));
+                    let (generation, ai_speech_segment, ai_text, pause_before) = match event {
+                        Ok(Some(PlayerEvent::Segment(segment))) => segment,
+                        Ok(Some(PlayerEvent::BodyEnded(id))) => {
+                            // Nothing came to fade in over the end of the segment, so it's played as it is.
+                            if let Some(tail) = held_tail.take_if(|tail| tail.id == id) {
+                                let sink = thread_sink.lock().unwrap();
+                                let current_generation = *thread_speech_generation.lock().unwrap();
+                                if tail.generation == current_generation {
+                                    sink.append(skippable(tail.source, tail.skipped));
+                                    sink.append(tail.finished);
+                                }
+                            }
+                            continue;
+                        }
+                        Ok(None) => break,
+                        Err(_) => {
+                            if thread_sink.lock().unwrap().empty() {
+                                ducking::set_speaking(false);
+                            }
+                            continue;
+                        }
+                    };
 
-                            task::spawn_blocking(move || {
-                                // Your blocking operation here
-                                ai_voice_sink.sleep_until_end()
-                            })
-                        };
+                    let audio = match decode_speech_segment(ai_speech_segment.path(), pause_before) {
+                        // Buffered, so each part of the segment can be played from a clone of it.
+                        Ok(audio) => audio.buffered(),
+                        Err(err) => {
+                            println_error(&format!("Failed to play AI voice audio: {:?}", err));
+                            sentence_done(&thread_unspoken);
+                            continue;
+                        }
+                    };
+                    let audio_duration = audio.total_duration().unwrap_or_default();
+                    let crossfade = (*thread_crossfade.lock().unwrap()).min(audio_duration / 2);
+                    let id = next_id;
+                    next_id += 1;
+                    let marks = SegmentMarks {
+                        generation,
+                        text: ai_text,
+                        skipped: Arc::new(AtomicBool::new(false)),
+                        speech_generation: thread_speech_generation.clone(),
+                        speaking: thread_speaking.clone(),
+                        event_subscribers: thread_event_subscribers.clone(),
+                        unspoken: thread_unspoken.clone(),
+                    };
 
-                        select! {
-                            _ = blocking_task.fuse() => {},
-                            _ = stop_speech_rx.recv_async() => {
-                                // empty the stop_speech_rx channel.
-                                while stop_speech_rx.try_recv().is_ok(){}
+                    // Checking the generation while holding the sink lock means stop_speech either
+                    // runs before this and the segment is skipped, or after this and stops it.
+                    let sink = thread_sink.lock().unwrap();
+                    let current_generation = *thread_speech_generation.lock().unwrap();
+                    let previous_tail = held_tail
+                        .take()
+                        .filter(|tail| tail.generation == current_generation);
+                    if generation != current_generation {
+                        sentence_done(&thread_unspoken);
+                        continue;
+                    }
 
-                                ai_voice_sink.stop();
-                            }
-                        };
-                    });
+                    // The previous segment fades out as this one fades in, unless it was skipped.
+                    let mut fading_tail = None;
+                    if let Some(tail) = previous_tail {
+                        sink.append(tail.finished);
+                        if !tail.skipped.load(Ordering::SeqCst) {
+                            fading_tail = Some((tail.source, tail.duration.min(audio_duration / 2)));
+                        }
+                    }
+                    sink.append(marks.started());
+                    let head_duration = match fading_tail {
+                        Some((tail, duration)) => {
+                            sink.append(skippable(
+                                tail.take_crossfade_with(audio.clone(), duration),
+                                marks.skipped.clone(),
+                            ));
+                            duration
+                        }
+                        None => Duration::ZERO,
+                    };
+                    let body_duration = audio_duration.saturating_sub(head_duration + crossfade);
+                    sink.append(skippable(
+                        audio
+                            .clone()
+                            .skip_duration(head_duration)
+                            .take_duration(body_duration),
+                        marks.skipped.clone(),
+                    ));
+                    if crossfade.is_zero() {
+                        sink.append(marks.finished());
+                    } else {
+                        let body_ended_tx = body_ended_tx.clone();
+                        sink.append(mark(move || {
+                            let _ = body_ended_tx.send(id);
+                        }));
+                        held_tail = Some(HeldTail {
+                            id,
+                            generation,
+                            source: audio.skip_duration(head_duration + body_duration),
+                            duration: crossfade,
+                            skipped: marks.skipped.clone(),
+                            finished: marks.finished(),
+                        });
+                    }
+                    drop(sink);
+                    ducking::set_speaking(true);
                 }
+
+                // Don't leave other applications ducked after the AI voice goes away.
+                ducking::set_speaking(false);
             });
 
-            (
-                SpeakStream {
-                    sentence_accumulator: SentenceAccumulator::new(),
-                    ai_tts_tx,
-                    ai_tts_rx,
-                    futures_ordered_kill_tx,
-                    stop_speech_tx,
-                    ai_audio_playing_rx,
-                },
-                _stream,
-            )
+            SpeakStream {
+                sentence_accumulator: SentenceAccumulator::new(),
+                ai_tts_tx,
+                ai_tts_rx,
+                futures_ordered_kill_tx,
+                ai_audio_playing_rx,
+                speech_generation,
+                shutdown_tx: Some(shutdown_tx),
+                playing_thread: Some(playing_thread),
+                sink,
+                speaking,
+                paused,
+                crossfade,
+                event_subscribers,
+                unspoken,
+                response_sentences: Vec::new(),
+                last_response_sentences: Vec::new(),
+                max_response_chars,
+                response_chars: 0,
+                muted: false,
+                voice,
+                tts_model,
+                tts_instructions,
+                speech_speed,
+            }
         }
 
         pub fn add_token(&mut self, token: &str) {
             // Add the token to the sentence accumulator
             let sentences = self.sentence_accumulator.add_token(token);
             for sentence in sentences {
-                self.ai_tts_tx.send(sentence).unwrap();
+                self.send_sentence_to_tts(&sentence);
             }
         }
 
         pub fn complete_sentence(&mut self) {
             // Process the last sentence
             if let Some(sentence) = self.sentence_accumulator.complete_sentence() {
-                self.ai_tts_tx.send(sentence).unwrap();
+                self.send_sentence_to_tts(&sentence);
+            }
+        }
+
+        /// Queues a sentence to be turned into speech once it's been made speakable.
+        fn send_sentence_to_tts(&mut self, sentence: &str) {
+            let (sentence, style) = take_speech_tags(sentence);
+            let sentence = normalize_for_speech(&sentence);
+            // Sentences made only of markdown, such as a horizontal rule, have nothing to say.
+            if sentence.is_empty() {
+                return;
+            }
+
+            // Still remember muted sentences, so the response can be repeated after unmuting.
+            if self.muted {
+                self.response_sentences.push((sentence, style));
+                return;
+            }
+
+            if let Some(max_response_chars) = self.max_response_chars {
+                if self.response_chars + sentence.len() > max_response_chars {
+                    if self.response_chars <= max_response_chars {
+                        info!("Speech character budget reached. The rest of the response won't be spoken.");
+                        // Push past the budget so this is only logged once per response.
+                        self.response_chars = max_response_chars + 1;
+                    }
+                    return;
+                }
+            }
+            self.response_chars += sentence.len();
+
+            self.response_sentences.push((sentence.clone(), style.clone()));
+            let generation = *self.speech_generation.lock().unwrap();
+            self.unspoken.fetch_add(1, Ordering::SeqCst);
+            self.ai_tts_tx.send((generation, sentence, style)).unwrap();
+        }
+
+        /// Marks the start of a new AI response, so the previous one can be repeated
+        /// and the new one gets a fresh speech character budget.
+        pub fn begin_response(&mut self) {
+            self.response_chars = 0;
+            if !self.response_sentences.is_empty() {
+                self.last_response_sentences = std::mem::take(&mut self.response_sentences);
             }
         }
 
@@ -462,8 +981,356 @@ pub mod ss {
             // clear the channel that passes audio files to the ai voice audio playing thread
             for _ in self.ai_audio_playing_rx.try_iter() {}
 
-            // stop the AI voice from speaking the current sentence
-            self.stop_speech_tx.send(()).unwrap();
+            // Anything queued before now that's still on its way to being spoken is now stale.
+            // The sink lock is held so the audio playing thread can't queue a stale sentence in between.
+            {
+                let sink = self.sink.lock().unwrap();
+                *self.speech_generation.lock().unwrap() += 1;
+
+                // stop the AI voice from speaking the current sentence
+                sink.stop();
+
+                // Speech stopped by the user shouldn't leave the next response paused.
+                *self.paused.lock().unwrap() = false;
+                audio::play(&sink);
+            }
+            self.unspoken.store(0, Ordering::SeqCst);
+
+            emit(&self.event_subscribers, SpeechEvent::Stopped);
+        }
+
+        /// Returns a channel that receives an event whenever a sentence starts or finishes playing.
+        /// Each call returns a new channel that receives every event.
+        pub fn subscribe(&self) -> flume::Receiver<SpeechEvent> {
+            let (tx, rx) = flume::unbounded();
+            self.event_subscribers.lock().unwrap().push(tx);
+            rx
+        }
+
+        /// Skips the sentence currently being spoken and moves on to the next one.
+        pub fn skip_sentence(&mut self) {
+            if let Some(skipped) = self.speaking.lock().unwrap().as_ref() {
+                skipped.store(true, Ordering::SeqCst);
+            }
+        }
+
+        /// Pauses the AI voice. Sentences that are still being converted stay queued.
+        pub fn pause_speech(&mut self) {
+            *self.paused.lock().unwrap() = true;
+            audio::pause(&self.sink.lock().unwrap());
+        }
+
+        /// Resumes the AI voice after `pause_speech`.
+        pub fn resume_speech(&mut self) {
+            *self.paused.lock().unwrap() = false;
+            audio::play(&self.sink.lock().unwrap());
+        }
+
+        pub fn is_paused(&self) -> bool {
+            *self.paused.lock().unwrap()
+        }
+
+        /// Queues the most recent response to be spoken again.
+        /// Returns false if there is no response to repeat.
+        pub fn repeat_last_response(&mut self) -> bool {
+            let sentences = if self.response_sentences.is_empty() {
+                self.last_response_sentences.clone()
+            } else {
+                self.response_sentences.clone()
+            };
+            if sentences.is_empty() {
+                return false;
+            }
+
+            let generation = *self.speech_generation.lock().unwrap();
+            self.unspoken.fetch_add(sentences.len(), Ordering::SeqCst);
+            for (sentence, style) in sentences {
+                self.ai_tts_tx.send((generation, sentence, style)).unwrap();
+            }
+            true
+        }
+
+        /// Speaks text that isn't part of a response, like a file being read out. It isn't held to
+        /// the response's speech budget, and isn't repeated with the response.
+        /// Returns false if speech is muted.
+        pub fn read_aloud(&mut self, text: &str) -> bool {
+            self.queue_text(text, SpeechStyle::default(), true)
+        }
+
+        /// Like `read_aloud`, for text in another language, like "French". The clean up of numbers
+        /// and units only knows English, so it's skipped.
+        pub fn speak_in_language(&mut self, text: &str, language: &str) -> bool {
+            let style = SpeechStyle {
+                instructions: Some(format!("Speak in {} with a native accent.", language)),
+                ..SpeechStyle::default()
+            };
+            self.queue_text(text, style, false)
+        }
+
+        fn queue_text(&mut self, text: &str, style: SpeechStyle, normalize: bool) -> bool {
+            if self.muted {
+                return false;
+            }
+            let mut accumulator = SentenceAccumulator::new();
+            let mut sentences = accumulator.add_token(text);
+            sentences.extend(accumulator.complete_sentence());
+
+            let generation = *self.speech_generation.lock().unwrap();
+            for sentence in sentences {
+                let sentence = match normalize {
+                    true => normalize_for_speech(&sentence),
+                    false => sentence.trim().to_string(),
+                };
+                if sentence.is_empty() {
+                    continue;
+                }
+                self.unspoken.fetch_add(1, Ordering::SeqCst);
+                self.ai_tts_tx
+                    .send((generation, sentence, style.clone()))
+                    .unwrap();
+            }
+            true
+        }
+
+        /// Saves the speech of the most recent response as one audio file in `dir`, and returns its path.
+        /// Sentences are usually in the speech cache, so this rarely needs to convert them again.
+        pub fn save_last_response_audio(&self, dir: &Path) -> Result<PathBuf, anyhow::Error> {
+            let sentences = if self.response_sentences.is_empty() {
+                &self.last_response_sentences
+            } else {
+                &self.response_sentences
+            };
+            if sentences.is_empty() {
+                anyhow::bail!("There is no response to save");
+            }
+
+            let runtime = tokio::runtime::Runtime::new().context("Failed to create tokio runtime")?;
+            let mut segments = Vec::new();
+            for (sentence, style) in sentences {
+                let (instructions, speed) = speech_settings(
+                    &self.tts_model,
+                    self.tts_instructions.clone(),
+                    *self.speech_speed.lock().unwrap(),
+                    style,
+                );
+                let (segment, _) = runtime
+                    .block_on(turn_text_to_speech(
+                        sentence.clone(),
+                        speed,
+                        self.voice.lock().unwrap().clone(),
+                        self.tts_model.clone(),
+                        instructions,
+                    ))
+                    .context("Failed to turn the response into speech")?;
+                segments.push(segment);
+            }
+
+            std::fs::create_dir_all(dir).context("Failed to create the audio folder")?;
+            let file_stem = format!("response {}", chrono::Local::now().format("%Y-%m-%d %H-%M-%S"));
+            concatenate_audio_files(&segments, &dir.join(file_stem))
+        }
+
+        /// Mutes or unmutes the AI voice. Muting stops the current speech,
+        /// and no text is turned into speech until it's unmuted.
+        pub fn set_muted(&mut self, muted: bool) {
+            if muted {
+                self.stop_speech();
+            }
+            self.muted = muted;
+        }
+
+        pub fn is_muted(&self) -> bool {
+            self.muted
+        }
+
+        /// Whether everything given to be spoken has finished playing, or been dropped.
+        /// Text waiting for complete_sentence doesn't count.
+        pub fn is_idle(&self) -> bool {
+            self.unspoken.load(Ordering::SeqCst) == 0
+        }
+
+        /// Sets the volume of the AI voice, where 1.0 is the original volume.
+        /// This only affects the AI voice, not the system volume.
+        pub fn set_volume(&mut self, volume: f32) {
+            audio::set_channel_volume(Channel::Speech, volume);
+        }
+
+        /// Sets how long consecutive sentences overlap, with each one fading in over the end of the one before it.
+        /// Zero plays sentences back to back.
+        pub fn set_crossfade(&mut self, crossfade: Duration) {
+            *self.crossfade.lock().unwrap() = crossfade;
+        }
+
+        /// Changes the voice the AI speaks with, starting with the next sentence converted to speech.
+        pub fn set_voice(&mut self, voice: VoiceEnum) {
+            *self.voice.lock().unwrap() = voice;
+        }
+
+        /// Changes how fast the AI speaks, starting with the next sentence converted to speech.
+        pub fn set_speech_speed(&mut self, speech_speed: f32) {
+            *self.speech_speed.lock().unwrap() = speech_speed;
+        }
+
+        /// Returns the volume of the AI voice, where 1.0 is the original volume.
+        pub fn volume(&self) -> f32 {
+            audio::channel_volume(Channel::Speech)
+        }
+    }
+
+    impl Drop for SpeakStream {
+        fn drop(&mut self) {
+            self.stop_speech();
+            // The conversion task and audio playing thread exit once the shutdown channel disconnects.
+            drop(self.shutdown_tx.take());
+            if let Some(playing_thread) = self.playing_thread.take() {
+                if playing_thread.join().is_err() {
+                    warn!("The AI voice audio playing thread panicked");
+                }
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn voice() -> VoiceEnum {
+            VoiceEnum::Alloy
+        }
+
+        fn model() -> TtsModelEnum {
+            TtsModelEnum::Tts1
+        }
+
+        /// Puts speech for `text` in the speech cache, so speaking it doesn't make a request.
+        fn cache_speech(text: &str) {
+            let audio = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/failed.mp3");
+            tts_cache::insert(
+                text,
+                voice_to_str(&voice()),
+                tts_model_to_str(&model()),
+                None,
+                1.0,
+                &audio,
+            )
+            .unwrap();
+        }
+
+        fn speak_stream() -> SpeakStream {
+            SpeakStream::new(voice(), model(), None, 1.0, 1.0, 4, None)
+        }
+
+        fn next_event(events: &flume::Receiver<SpeechEvent>) -> String {
+            match events.recv_timeout(Duration::from_secs(5)) {
+                Ok(SpeechEvent::Started(text)) => format!("started {}", text),
+                Ok(SpeechEvent::Finished(text)) => format!("finished {}", text),
+                Ok(SpeechEvent::Stopped) => "stopped".to_string(),
+                Err(err) => panic!("No speech event came: {}", err),
+            }
+        }
+
+        #[tokio::test(flavor = "multi_thread")]
+        async fn sentences_are_spoken_in_order() {
+            let _preemptions = audio::TEST_LOCK
+                .lock()
+                .unwrap_or_else(|err| err.into_inner());
+            cache_speech("Hello there, nice to meet you.");
+            cache_speech("How are you?");
+            let mut speak_stream = speak_stream();
+            let events = speak_stream.subscribe();
+
+            speak_stream.add_token("Hello there, nice to meet you. How are");
+            speak_stream.add_token(" you?");
+            speak_stream.complete_sentence();
+            let spoken: Vec<String> = (0..4).map(|_| next_event(&events)).collect();
+            assert_eq!(
+                spoken,
+                [
+                    "started Hello there, nice to meet you.",
+                    "finished Hello there, nice to meet you.",
+                    "started How are you?",
+                    "finished How are you?",
+                ]
+            );
+            assert!(speak_stream.is_idle());
+        }
+
+        #[tokio::test(flavor = "multi_thread")]
+        async fn crossfaded_sentences_are_spoken_in_order() {
+            let _preemptions = audio::TEST_LOCK
+                .lock()
+                .unwrap_or_else(|err| err.into_inner());
+            cache_speech("First of all, thanks for asking.");
+            cache_speech("Second, here it is.");
+            let mut speak_stream = speak_stream();
+            speak_stream.set_crossfade(Duration::from_millis(100));
+            let events = speak_stream.subscribe();
+
+            speak_stream.read_aloud("First of all, thanks for asking. Second, here it is.");
+            let spoken: Vec<String> = (0..4).map(|_| next_event(&events)).collect();
+            assert_eq!(
+                spoken,
+                [
+                    "started First of all, thanks for asking.",
+                    "finished First of all, thanks for asking.",
+                    "started Second, here it is.",
+                    "finished Second, here it is.",
+                ]
+            );
+            assert!(speak_stream.is_idle());
+        }
+
+        #[tokio::test(flavor = "multi_thread")]
+        async fn stopping_paused_speech_lets_the_next_response_play() {
+            let _preemptions = audio::TEST_LOCK
+                .lock()
+                .unwrap_or_else(|err| err.into_inner());
+            cache_speech("This is never heard.");
+            cache_speech("This one is heard.");
+            let mut speak_stream = speak_stream();
+            let events = speak_stream.subscribe();
+
+            speak_stream.pause_speech();
+            speak_stream.read_aloud("This is never heard.");
+            thread::sleep(Duration::from_millis(200));
+            speak_stream.stop_speech();
+            assert_eq!(next_event(&events), "stopped");
+
+            speak_stream.read_aloud("This one is heard.");
+            let spoken: Vec<String> = (0..2).map(|_| next_event(&events)).collect();
+            assert_eq!(
+                spoken,
+                ["started This one is heard.", "finished This one is heard."]
+            );
+        }
+
+        #[tokio::test(flavor = "multi_thread")]
+        async fn paused_speech_waits_to_be_resumed() {
+            let _preemptions = audio::TEST_LOCK
+                .lock()
+                .unwrap_or_else(|err| err.into_inner());
+            cache_speech("Wait for it.");
+            let mut speak_stream = speak_stream();
+            let events = speak_stream.subscribe();
+
+            speak_stream.pause_speech();
+            speak_stream.add_token("Wait for it.");
+            speak_stream.complete_sentence();
+            let while_paused: Vec<SpeechEvent> = events
+                .recv_timeout(Duration::from_millis(500))
+                .into_iter()
+                .collect();
+            assert!(!while_paused
+                .iter()
+                .any(|event| matches!(event, SpeechEvent::Finished(_))));
+            assert!(!speak_stream.is_idle());
+
+            speak_stream.resume_speech();
+            loop {
+                if next_event(&events) == "finished Wait for it." {
+                    break;
+                }
+            }
         }
     }
 }
@@ -0,0 +1,249 @@
+use regex::{Captures, Regex};
+use std::sync::LazyLock;
+use std::time::Duration;
+
+static LINK: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"!?\[([^\]]*)\]\([^)]*\)").unwrap());
+static URL: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\bhttps?://(?:www\.)?([^\s/?#]+)[^\s]*").unwrap());
+static EMPHASIS: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(\*\*|__|\*)([^*\n]+?)(\*\*|__|\*)").unwrap());
+static INLINE_CODE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"`+([^`]*)`+").unwrap());
+static HEADER: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?m)^\s{0,3}#{1,6}\s+").unwrap());
+static BULLET: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?m)^\s*[-*+]\s+").unwrap());
+static BLOCKQUOTE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?m)^\s*>\s?").unwrap());
+static HORIZONTAL_RULE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?m)^\s*(?:[-*_]\s*){3,}$").unwrap());
+static ORDINAL: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b(\d{1,9})(?:st|nd|rd|th)\b").unwrap());
+static UNIT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\b(\d+(?:\.\d+)?)\s?(KB|MB|GB|TB|Kbps|Mbps|Gbps|kHz|MHz|GHz|ms|km|kg|cm|mm|mph)\b")
+        .unwrap()
+});
+static TEMPERATURE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(\d+(?:\.\d+)?)\s?°\s?([CF])\b").unwrap());
+static SPEECH_TAG: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\[(whisper|excited|calm|sad|serious|cheerful|fast|slow|pause=(\d+)(ms|s))\] ?")
+        .unwrap()
+});
+
+// The longest speech tag is "[pause=99999ms]". Anything longer after a "[" can't be a tag.
+const MAX_SPEECH_TAG_LEN: usize = 16;
+
+/// Tells the AI which inline speech tags it can use. Appended to the system prompt.
+pub const SPEECH_TAGS_PROMPT: &str = "You can start a sentence with one of these tags to change how it's spoken: [whisper], [excited], [calm], [sad], [serious], [cheerful], [fast], [slow], or [pause=500ms] to pause before it. Tags are never shown to the user. Use them sparingly.";
+
+/// How a sentence should be spoken, based on the speech tags the AI put in it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpeechStyle {
+    /// Extra instructions for text to speech models that support them.
+    pub instructions: Option<String>,
+    /// Multiplied with the normal speech speed.
+    pub speed: f32,
+    /// Silence to play before the sentence.
+    pub pause_before: Duration,
+}
+
+impl Default for SpeechStyle {
+    fn default() -> Self {
+        SpeechStyle {
+            instructions: None,
+            speed: 1.0,
+            pause_before: Duration::ZERO,
+        }
+    }
+}
+
+/// Removes speech tags such as "[whisper]" or "[pause=500ms]" from a sentence,
+/// and returns the sentence along with how the tags say it should be spoken.
+pub fn take_speech_tags(sentence: &str) -> (String, SpeechStyle) {
+    let mut style = SpeechStyle::default();
+    for caps in SPEECH_TAG.captures_iter(sentence) {
+        match &caps[1] {
+            "fast" => style.speed = 1.25,
+            "slow" => style.speed = 0.8,
+            "whisper" => style.instructions = Some("Whisper.".to_string()),
+            tag if tag.starts_with("pause=") => {
+                let amount: u64 = caps[2].parse().unwrap_or(0);
+                style.pause_before += match &caps[3] {
+                    "s" => Duration::from_secs(amount),
+                    _ => Duration::from_millis(amount),
+                };
+            }
+            mood => style.instructions = Some(format!("Speak in a {} tone.", mood)),
+        }
+    }
+
+    (SPEECH_TAG.replace_all(sentence, "").into_owned(), style)
+}
+
+/// Removes speech tags from AI text as it's streamed in, so they aren't displayed.
+///
+/// Text after an unclosed "[" is held back until it's clear whether it's a tag.
+#[derive(Default)]
+pub struct SpeechTagFilter {
+    pending: String,
+}
+
+impl SpeechTagFilter {
+    /// Adds streamed text and returns the part of it that's ready to be displayed.
+    pub fn filter(&mut self, token: &str) -> String {
+        self.pending.push_str(token);
+
+        let hold_from = match self.pending.rfind('[') {
+            Some(i) if !self.pending[i..].contains(']') && self.pending.len() - i < MAX_SPEECH_TAG_LEN => i,
+            _ => self.pending.len(),
+        };
+
+        let ready = SPEECH_TAG.replace_all(&self.pending[..hold_from], "").into_owned();
+        self.pending.drain(..hold_from);
+        ready
+    }
+
+    /// Returns any text still being held back. Called once the AI finishes responding.
+    pub fn flush(&mut self) -> String {
+        let pending = std::mem::take(&mut self.pending);
+        SPEECH_TAG.replace_all(&pending, "").into_owned()
+    }
+}
+
+/// Rewrites text the AI wrote for the terminal into text that sounds natural when spoken.
+///
+/// Markdown formatting is removed, and ordinals, units, and URLs are expanded into words
+/// so the text to speech engine doesn't read out symbols or spell abbreviations.
+pub fn normalize_for_speech(text: &str) -> String {
+    let text = LINK.replace_all(text, "$1");
+    let text = URL.replace_all(&text, |caps: &Captures| {
+        format!("a link to {}", caps[1].replace('.', " dot "))
+    });
+    let text = HORIZONTAL_RULE.replace_all(&text, "");
+    let text = HEADER.replace_all(&text, "");
+    let text = BULLET.replace_all(&text, "");
+    let text = BLOCKQUOTE.replace_all(&text, "");
+    let text = INLINE_CODE.replace_all(&text, "$1");
+    let text = EMPHASIS.replace_all(&text, "$2");
+    // Emphasis markers whose other half ended up in a different sentence.
+    let text = text.replace("**", "").replace("__", "");
+
+    let text = ORDINAL.replace_all(&text, |caps: &Captures| match caps[1].parse::<u64>() {
+        Ok(n) => ordinal_to_words(n),
+        Err(_) => caps[0].to_string(),
+    });
+    let text = UNIT.replace_all(&text, |caps: &Captures| {
+        let singular = &caps[1] == "1";
+        format!("{} {}", &caps[1], unit_to_words(&caps[2], singular))
+    });
+    let text = TEMPERATURE.replace_all(&text, |caps: &Captures| {
+        let scale = if &caps[2] == "C" { "Celsius" } else { "Fahrenheit" };
+        format!("{} degrees {}", &caps[1], scale)
+    });
+
+    text.trim().to_string()
+}
+
+fn unit_to_words(unit: &str, singular: bool) -> String {
+    let word = match unit {
+        "KB" => "kilobyte",
+        "MB" => "megabyte",
+        "GB" => "gigabyte",
+        "TB" => "terabyte",
+        "Kbps" => "kilobit per second",
+        "Mbps" => "megabit per second",
+        "Gbps" => "gigabit per second",
+        "kHz" => "kilohertz",
+        "MHz" => "megahertz",
+        "GHz" => "gigahertz",
+        "ms" => "millisecond",
+        "km" => "kilometer",
+        "kg" => "kilogram",
+        "cm" => "centimeter",
+        "mm" => "millimeter",
+        "mph" => "mile per hour",
+        _ => return unit.to_string(),
+    };
+
+    if singular || word.ends_with("hertz") {
+        return word.to_string();
+    }
+
+    // Pluralize the first word, so "megabit per second" becomes "megabits per second".
+    match word.split_once(' ') {
+        Some((first, rest)) => format!("{}s {}", first, rest),
+        None => format!("{}s", word),
+    }
+}
+
+const ONES: [&str; 20] = [
+    "zero",
+    "one",
+    "two",
+    "three",
+    "four",
+    "five",
+    "six",
+    "seven",
+    "eight",
+    "nine",
+    "ten",
+    "eleven",
+    "twelve",
+    "thirteen",
+    "fourteen",
+    "fifteen",
+    "sixteen",
+    "seventeen",
+    "eighteen",
+    "nineteen",
+];
+
+const TENS: [&str; 10] = [
+    "", "", "twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety",
+];
+
+/// Spells out a number in English words. For example 342 becomes "three hundred forty-two".
+fn number_to_words(n: u64) -> String {
+    match n {
+        0..=19 => ONES[n as usize].to_string(),
+        20..=99 => match n % 10 {
+            0 => TENS[(n / 10) as usize].to_string(),
+            ones => format!("{}-{}", TENS[(n / 10) as usize], ONES[ones as usize]),
+        },
+        100..=999 => join_scale(n / 100, "hundred", n % 100),
+        1_000..=999_999 => join_scale(n / 1_000, "thousand", n % 1_000),
+        1_000_000..=999_999_999 => join_scale(n / 1_000_000, "million", n % 1_000_000),
+        _ => join_scale(n / 1_000_000_000, "billion", n % 1_000_000_000),
+    }
+}
+
+fn join_scale(count: u64, scale: &str, remainder: u64) -> String {
+    if remainder == 0 {
+        format!("{} {}", number_to_words(count), scale)
+    } else {
+        format!(
+            "{} {} {}",
+            number_to_words(count),
+            scale,
+            number_to_words(remainder)
+        )
+    }
+}
+
+/// Spells out an ordinal number in English words. For example 21 becomes "twenty-first".
+fn ordinal_to_words(n: u64) -> String {
+    let words = number_to_words(n);
+    let split_at = words.rfind([' ', '-']).map_or(0, |i| i + 1);
+    let (head, last) = words.split_at(split_at);
+
+    let last = match last {
+        "one" => "first".to_string(),
+        "two" => "second".to_string(),
+        "three" => "third".to_string(),
+        "five" => "fifth".to_string(),
+        "eight" => "eighth".to_string(),
+        "nine" => "ninth".to_string(),
+        "twelve" => "twelfth".to_string(),
+        word if word.ends_with('y') => format!("{}ieth", &word[..word.len() - 1]),
+        word => format!("{}th", word),
+    };
+
+    format!("{}{}", head, last)
+}
@@ -0,0 +1,447 @@
+//! Plays music on the user's Spotify account through the Spotify Web API.
+//! Logging in is done once with the `spotify-login` subcommand, which keeps a refresh token in the
+//! system keyring. Access tokens are kept in memory and refreshed when they run out.
+
+use anyhow::{anyhow, bail, Context};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use reqwest::{
+    blocking::{Client, RequestBuilder, Response},
+    header::CONTENT_LENGTH,
+    Method, StatusCode, Url,
+};
+use serde::Deserialize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::TcpListener,
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+use uuid::Uuid;
+
+use crate::secrets;
+
+/// Has to match a redirect URI of the user's Spotify app.
+const REDIRECT_PORT: u16 = 47_614;
+const REDIRECT_URI: &str = "http://127.0.0.1:47614/callback";
+const SCOPES: &str = "user-read-playback-state user-modify-playback-state user-read-currently-playing playlist-read-private";
+const AUTHORIZE_URL: &str = "https://accounts.spotify.com/authorize";
+const TOKEN_URL: &str = "https://accounts.spotify.com/api/token";
+const API_URL: &str = "https://api.spotify.com/v1";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Access tokens are refreshed this long before they run out, so one doesn't run out mid-request.
+const EXPIRY_MARGIN: Duration = Duration::from_secs(60);
+
+static CLIENT_ID: OnceLock<String> = OnceLock::new();
+
+struct AccessToken {
+    token: String,
+    expires: Instant,
+}
+
+static ACCESS_TOKEN: Mutex<Option<AccessToken>> = Mutex::new(None);
+
+/// Sets the client ID of the user's Spotify app, from the config file.
+pub fn configure(client_id: String) {
+    let _ = CLIENT_ID.set(client_id);
+}
+
+fn client_id() -> Result<&'static str, anyhow::Error> {
+    CLIENT_ID.get().map(String::as_str).context(
+        "Spotify isn't set up. Add client-id to the [spotify] section of the config file, then run `quick-assistant spotify-login`.",
+    )
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: u64,
+    /// Spotify can hand out a new refresh token when one is used.
+    refresh_token: Option<String>,
+}
+
+fn request_token(form: &[(&str, &str)]) -> Result<TokenResponse, anyhow::Error> {
+    let response = Client::new()
+        .post(TOKEN_URL)
+        .form(form)
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .context("Failed to reach Spotify")?;
+    if !response.status().is_success() {
+        bail!(
+            "Spotify refused the login: {}",
+            response.text().unwrap_or_default()
+        );
+    }
+    Ok(response.json()?)
+}
+
+fn keep_token(token: TokenResponse) -> Result<String, anyhow::Error> {
+    if let Some(refresh_token) = &token.refresh_token {
+        secrets::save_spotify_refresh_token(refresh_token)?;
+    }
+    *ACCESS_TOKEN.lock().unwrap() = Some(AccessToken {
+        token: token.access_token.clone(),
+        expires: Instant::now()
+            + Duration::from_secs(token.expires_in).saturating_sub(EXPIRY_MARGIN),
+    });
+    Ok(token.access_token)
+}
+
+fn access_token() -> Result<String, anyhow::Error> {
+    if let Some(token) = ACCESS_TOKEN
+        .lock()
+        .unwrap()
+        .as_ref()
+        .filter(|token| Instant::now() < token.expires)
+    {
+        return Ok(token.token.clone());
+    }
+    let refresh_token = secrets::load_spotify_refresh_token()
+        .context("Not logged in to Spotify. Run `quick-assistant spotify-login` first.")?;
+    let token = request_token(&[
+        ("grant_type", "refresh_token"),
+        ("refresh_token", &refresh_token),
+        ("client_id", client_id()?),
+    ])?;
+    keep_token(token)
+}
+
+/// Logs in to Spotify in the browser, and keeps the login in the system keyring.
+/// Uses the authorization code flow with PKCE, so the Spotify app's secret isn't needed.
+pub fn login(client_id: &str) -> Result<(), anyhow::Error> {
+    let verifier = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+    let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()));
+    let state = Uuid::new_v4().simple().to_string();
+    let url = Url::parse_with_params(
+        AUTHORIZE_URL,
+        &[
+            ("client_id", client_id),
+            ("response_type", "code"),
+            ("redirect_uri", REDIRECT_URI),
+            ("code_challenge_method", "S256"),
+            ("code_challenge", &challenge),
+            ("scope", SCOPES),
+            ("state", &state),
+        ],
+    )?;
+
+    let listener = TcpListener::bind(("127.0.0.1", REDIRECT_PORT))
+        .with_context(|| format!("Failed to listen on port {}", REDIRECT_PORT))?;
+    println!(
+        "Log in to Spotify in your browser. If it doesn't open, go to:\n{}",
+        url
+    );
+    let _ = open::that(url.as_str());
+
+    let code = wait_for_code(&listener, &state)?;
+    let token = request_token(&[
+        ("grant_type", "authorization_code"),
+        ("code", &code),
+        ("redirect_uri", REDIRECT_URI),
+        ("client_id", client_id),
+        ("code_verifier", &verifier),
+    ])?;
+    keep_token(token)?;
+    Ok(())
+}
+
+/// Waits for the browser to be sent back from Spotify's login page, and returns the code it brings.
+fn wait_for_code(listener: &TcpListener, state: &str) -> Result<String, anyhow::Error> {
+    for stream in listener.incoming() {
+        let mut stream = stream?;
+        let mut request_line = String::new();
+        BufReader::new(&stream).read_line(&mut request_line)?;
+        let Some(path) = request_line.split_whitespace().nth(1) else {
+            continue;
+        };
+        let url = Url::parse(&format!("http://127.0.0.1{}", path))?;
+        // Browsers also ask for things like the favicon.
+        if url.path() != "/callback" {
+            let _ = stream.write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n");
+            continue;
+        }
+
+        let param = |name: &str| {
+            url.query_pairs()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.into_owned())
+        };
+        let result = if param("state").as_deref() != Some(state) {
+            Err(anyhow!("The login didn't come from this assistant"))
+        } else if let Some(error) = param("error") {
+            Err(anyhow!("Spotify login failed: {}", error))
+        } else {
+            param("code").context("Spotify didn't send a login code")
+        };
+
+        let message = match &result {
+            Ok(_) => "Logged in to Spotify. You can close this tab.",
+            Err(_) => "Logging in to Spotify failed. Check the assistant for details.",
+        };
+        let _ = write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\n\r\n{}",
+            message.len(),
+            message
+        );
+        return result;
+    }
+    bail!("Stopped waiting for the Spotify login")
+}
+
+#[derive(Deserialize)]
+struct ErrorResponse {
+    error: ApiError,
+}
+
+#[derive(Deserialize)]
+struct ApiError {
+    message: String,
+}
+
+fn api(method: Method, path: &str) -> Result<RequestBuilder, anyhow::Error> {
+    Ok(Client::new()
+        .request(method, format!("{}{}", API_URL, path))
+        .bearer_auth(access_token()?)
+        .timeout(REQUEST_TIMEOUT))
+}
+
+/// Sends a request to the Web API, turning error responses into errors.
+fn send(request: RequestBuilder) -> Result<Response, anyhow::Error> {
+    check(request.send().context("Failed to reach Spotify")?)
+}
+
+fn check(response: Response) -> Result<Response, anyhow::Error> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let message = response
+        .json::<ErrorResponse>()
+        .map(|response| response.error.message)
+        .unwrap_or_else(|_| status.to_string());
+    if status == StatusCode::FORBIDDEN {
+        bail!(
+            "Spotify refused: {}. Controlling playback needs Spotify Premium.",
+            message
+        );
+    }
+    bail!("Spotify request failed: {}", message)
+}
+
+#[derive(Deserialize)]
+struct Devices {
+    devices: Vec<Device>,
+}
+
+#[derive(Deserialize)]
+struct Device {
+    id: Option<String>,
+    #[serde(default)]
+    is_restricted: bool,
+}
+
+/// Sends a playback command. Spotify only takes them while one of the user's devices is active,
+/// so when none is, the command goes to the first device that has Spotify open.
+fn player_command(
+    method: Method,
+    path: &str,
+    body: Option<serde_json::Value>,
+) -> Result<(), anyhow::Error> {
+    let request = |device: Option<&str>| -> Result<RequestBuilder, anyhow::Error> {
+        let mut request = api(method.clone(), path)?;
+        if let Some(device) = device {
+            request = request.query(&[("device_id", device)]);
+        }
+        Ok(match &body {
+            Some(body) => request.json(body),
+            // Spotify wants a length even when there's no body.
+            None => request.header(CONTENT_LENGTH, 0),
+        })
+    };
+
+    let response = request(None)?.send().context("Failed to reach Spotify")?;
+    if response.status() != StatusCode::NOT_FOUND {
+        check(response)?;
+        return Ok(());
+    }
+    let devices: Devices = send(api(Method::GET, "/me/player/devices")?)?.json()?;
+    let device = devices
+        .devices
+        .into_iter()
+        .filter(|device| !device.is_restricted)
+        .find_map(|device| device.id)
+        .context(
+            "Spotify isn't open on any device. Open it on this computer or your phone first.",
+        )?;
+    send(request(Some(&device))?)?;
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct Item {
+    name: String,
+    uri: String,
+    #[serde(default)]
+    artists: Vec<Artist>,
+    duration_ms: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct Artist {
+    name: String,
+}
+
+impl Item {
+    fn describe(&self) -> String {
+        match self.artists.is_empty() {
+            true => format!("\"{}\"", self.name),
+            false => format!(
+                "\"{}\" by {}",
+                self.name,
+                self.artists
+                    .iter()
+                    .map(|artist| artist.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Page {
+    /// Spotify sometimes leaves gaps in search results.
+    items: Vec<Option<Item>>,
+}
+
+/// What to search Spotify for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    Track,
+    Album,
+    Artist,
+    Playlist,
+}
+
+impl Kind {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "track" | "song" => Some(Kind::Track),
+            "album" => Some(Kind::Album),
+            "artist" => Some(Kind::Artist),
+            "playlist" => Some(Kind::Playlist),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Kind::Track => "track",
+            Kind::Album => "album",
+            Kind::Artist => "artist",
+            Kind::Playlist => "playlist",
+        }
+    }
+}
+
+fn search(query: &str, kind: Kind) -> Result<Item, anyhow::Error> {
+    let mut response: serde_json::Value = send(api(Method::GET, "/search")?.query(&[
+        ("q", query),
+        ("type", kind.name()),
+        ("limit", "5"),
+    ]))?
+    .json()?;
+    let page: Page = serde_json::from_value(response[format!("{}s", kind.name())].take())?;
+    page.items
+        .into_iter()
+        .flatten()
+        .next()
+        .with_context(|| format!("Spotify found no {} for \"{}\"", kind.name(), query))
+}
+
+fn play_item(item: &Item) -> Result<(), anyhow::Error> {
+    let body = match item.uri.starts_with("spotify:track:") {
+        true => json!({ "uris": [item.uri] }),
+        false => json!({ "context_uri": item.uri }),
+    };
+    player_command(Method::PUT, "/me/player/play", Some(body))
+}
+
+/// Plays the best match for `query`, or resumes playback if there's no query. Returns what's playing.
+pub fn play(query: Option<&str>, kind: Kind) -> Result<String, anyhow::Error> {
+    let Some(query) = query else {
+        player_command(Method::PUT, "/me/player/play", None)?;
+        return Ok("Resumed playback on Spotify.".to_string());
+    };
+    let item = search(query, kind)?;
+    play_item(&item)?;
+    Ok(format!(
+        "Playing the {} {} on Spotify.",
+        kind.name(),
+        item.describe()
+    ))
+}
+
+/// Plays one of the user's own playlists, or the best matching public playlist if none of theirs match.
+pub fn play_playlist(name: &str) -> Result<String, anyhow::Error> {
+    let own: Page = send(api(Method::GET, "/me/playlists")?.query(&[("limit", "50")]))?.json()?;
+    let wanted = name.to_lowercase();
+    let item = match own
+        .items
+        .into_iter()
+        .flatten()
+        .find(|playlist| playlist.name.to_lowercase().contains(&wanted))
+    {
+        Some(playlist) => playlist,
+        None => search(name, Kind::Playlist)?,
+    };
+    play_item(&item)?;
+    Ok(format!(
+        "Playing the playlist \"{}\" on Spotify.",
+        item.name
+    ))
+}
+
+pub fn pause() -> Result<(), anyhow::Error> {
+    player_command(Method::PUT, "/me/player/pause", None)
+}
+
+pub fn next() -> Result<(), anyhow::Error> {
+    player_command(Method::POST, "/me/player/next", None)
+}
+
+#[derive(Deserialize)]
+struct CurrentlyPlaying {
+    is_playing: bool,
+    progress_ms: Option<u64>,
+    item: Option<Item>,
+}
+
+/// Describes what's playing on Spotify, for the AI.
+pub fn current_track() -> Result<String, anyhow::Error> {
+    let response = send(api(Method::GET, "/me/player/currently-playing")?)?;
+    // Nothing is playing.
+    if response.status() == StatusCode::NO_CONTENT {
+        return Ok("Nothing is playing on Spotify.".to_string());
+    }
+    let playing: CurrentlyPlaying = response.json()?;
+    let Some(item) = playing.item else {
+        return Ok("Nothing is playing on Spotify.".to_string());
+    };
+    let mut info = format!(
+        "{} {}",
+        match playing.is_playing {
+            true => "Playing",
+            false => "Paused on",
+        },
+        item.describe()
+    );
+    if let (Some(progress), Some(duration)) = (playing.progress_ms, item.duration_ms) {
+        let seconds = |ms: u64| humantime::format_duration(Duration::from_secs(ms / 1000));
+        info.push_str(&format!(", {} of {}", seconds(progress), seconds(duration)));
+    }
+    Ok(info)
+}
@@ -0,0 +1,28 @@
+//! Running the system's own programs, like `shutdown` or `bluetoothctl`, for the tools that need
+//! them.
+
+use anyhow::{bail, Context};
+use std::process::Command;
+
+/// Runs a command, failing with what it printed if it fails.
+pub fn run(program: &str, args: &[&str]) -> Result<(), anyhow::Error> {
+    output(program, args).map(drop)
+}
+
+/// Runs a command and returns what it printed, failing with its error output if it fails.
+pub fn output(program: &str, args: &[&str]) -> Result<String, anyhow::Error> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .with_context(|| format!("Failed to run {}. Is it installed?", program))?;
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let message = match stderr.trim().is_empty() {
+            true => stdout.trim(),
+            false => stderr.trim(),
+        };
+        bail!("{} failed: {}", program, message);
+    }
+    Ok(stdout)
+}
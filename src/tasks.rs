@@ -0,0 +1,165 @@
+//! Keeps track of tools that carry on running after they return, like the speed test,
+//! showing a spinner while they run and letting the AI say how they're doing.
+
+use std::{
+    sync::{Mutex, Once},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::terminal;
+
+const SPINNER_FRAMES: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+const SPINNER_INTERVAL: Duration = Duration::from_millis(100);
+/// How many finished tasks are remembered, so the AI can say when they finished.
+const MAX_FINISHED: usize = 10;
+
+struct Task {
+    id: u64,
+    description: String,
+    started: Instant,
+    finished: Option<Instant>,
+    /// How far along the task is, like "40 MB of 100 MB", if it can tell.
+    progress: Option<String>,
+}
+
+struct Tasks {
+    next_id: u64,
+    tasks: Vec<Task>,
+}
+
+static TASKS: Mutex<Tasks> = Mutex::new(Tasks {
+    next_id: 0,
+    tasks: Vec::new(),
+});
+
+static START_SPINNER_THREAD: Once = Once::new();
+
+/// Marks a task as finished when dropped.
+pub struct RunningTask {
+    id: u64,
+}
+
+impl Drop for RunningTask {
+    fn drop(&mut self) {
+        let mut tasks = TASKS.lock().unwrap();
+        if let Some(task) = tasks.tasks.iter_mut().find(|task| task.id == self.id) {
+            task.finished = Some(Instant::now());
+        }
+        // Forget the oldest finished tasks.
+        let finished = tasks
+            .tasks
+            .iter()
+            .filter(|task| task.finished.is_some())
+            .count();
+        let mut to_forget = finished.saturating_sub(MAX_FINISHED);
+        tasks.tasks.retain(|task| {
+            let forget = to_forget > 0 && task.finished.is_some();
+            to_forget -= usize::from(forget);
+            !forget
+        });
+    }
+}
+
+impl RunningTask {
+    /// Sets how far along the task is, shown after its description.
+    pub fn set_progress(&self, progress: &str) {
+        let mut tasks = TASKS.lock().unwrap();
+        if let Some(task) = tasks.tasks.iter_mut().find(|task| task.id == self.id) {
+            task.progress = Some(progress.to_string());
+        }
+    }
+}
+
+/// Starts tracking a task, described like "Running a speed test". It's running until the returned value is dropped.
+pub fn start(description: &str) -> RunningTask {
+    START_SPINNER_THREAD.call_once(|| {
+        thread::spawn(spin);
+    });
+
+    let mut tasks = TASKS.lock().unwrap();
+    let id = tasks.next_id;
+    tasks.next_id += 1;
+    tasks.tasks.push(Task {
+        id,
+        description: description.to_string(),
+        started: Instant::now(),
+        finished: None,
+        progress: None,
+    });
+    RunningTask { id }
+}
+
+/// Shows a spinner line in the terminal for as long as any task is running.
+fn spin() {
+    let mut showing = false;
+    for frame in SPINNER_FRAMES.iter().cycle() {
+        let running: Vec<String> = TASKS
+            .lock()
+            .unwrap()
+            .tasks
+            .iter()
+            .filter(|task| task.finished.is_none())
+            .map(|task| match &task.progress {
+                Some(progress) => format!(
+                    "{}, {} ({}s)",
+                    task.description,
+                    progress,
+                    task.started.elapsed().as_secs()
+                ),
+                None => format!(
+                    "{} ({}s)",
+                    task.description,
+                    task.started.elapsed().as_secs()
+                ),
+            })
+            .collect();
+
+        if running.is_empty() {
+            if showing {
+                terminal::clear_status();
+                showing = false;
+            }
+        } else {
+            terminal::show_status(&format!("{} {}", frame, running.join(", ")));
+            showing = true;
+        }
+        thread::sleep(SPINNER_INTERVAL);
+    }
+}
+
+/// Describes the running and recently finished tasks, for the AI.
+pub fn describe() -> String {
+    let tasks = TASKS.lock().unwrap();
+    if tasks.tasks.is_empty() {
+        return "No tasks are running, and none have run recently.".to_string();
+    }
+    tasks
+        .tasks
+        .iter()
+        .map(|task| match task.finished {
+            None => {
+                let mut line = format!(
+                    "{}: running for {}",
+                    task.description,
+                    humantime::format_duration(Duration::from_secs(
+                        task.started.elapsed().as_secs()
+                    ))
+                );
+                if let Some(progress) = &task.progress {
+                    line.push_str(&format!(", {}", progress));
+                }
+                line
+            }
+            Some(finished) => format!(
+                "{}: finished {} ago, after {}",
+                task.description,
+                humantime::format_duration(Duration::from_secs(finished.elapsed().as_secs())),
+                humantime::format_duration(Duration::from_secs(
+                    (finished - task.started).as_secs()
+                ))
+            ),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
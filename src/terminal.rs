@@ -0,0 +1,488 @@
+//! Prints the conversation to the terminal: colored labels, optional timestamps, and the AI's
+//! responses word-wrapped with their markdown shown as formatting while they stream in.
+
+use chrono::Local;
+use colored::{Color, ColoredString, Colorize};
+use std::{
+    env,
+    fmt::Write as _,
+    io::{stdout, Write},
+    str::FromStr,
+    sync::{Mutex, OnceLock},
+};
+use tracing::warn;
+
+use crate::config::TerminalConfig;
+
+struct Theme {
+    user: Color,
+    ai: Color,
+    error: Color,
+    code: Color,
+    timestamps: bool,
+    wrap_width: Option<usize>,
+    markdown: bool,
+}
+
+static THEME: OnceLock<Theme> = OnceLock::new();
+
+/// Parses a color name like "bright blue", or hex like "#0000ff".
+fn parse_color(color: &str) -> Option<Color> {
+    match color.strip_prefix('#') {
+        Some(hex) if hex.len() == 6 => {
+            let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+            Some(Color::TrueColor {
+                r: channel(0)?,
+                g: channel(2)?,
+                b: channel(4)?,
+            })
+        }
+        Some(_) => None,
+        None => Color::from_str(color).ok(),
+    }
+}
+
+impl Theme {
+    fn new(config: &TerminalConfig) -> Self {
+        let defaults = TerminalConfig::default();
+        let color = |color: &str, default: &str| {
+            parse_color(color).unwrap_or_else(|| {
+                warn!("Ignoring unknown terminal color \"{}\"", color);
+                parse_color(default).unwrap()
+            })
+        };
+        // Most shells keep COLUMNS to themselves, so this usually needs setting in the config file.
+        let wrap_width = config
+            .wrap_width
+            .or_else(|| env::var("COLUMNS").ok()?.parse().ok())
+            .filter(|&width| width > 0);
+
+        Self {
+            user: color(&config.user_color, &defaults.user_color),
+            ai: color(&config.ai_color, &defaults.ai_color),
+            error: color(&config.error_color, &defaults.error_color),
+            code: color(&config.code_color, &defaults.code_color),
+            timestamps: config.timestamps,
+            wrap_width,
+            markdown: config.markdown,
+        }
+    }
+}
+
+/// Sets how the conversation is printed. Anything printed before this uses the defaults.
+pub fn configure(config: &TerminalConfig) {
+    let _ = THEME.set(Theme::new(config));
+}
+
+fn theme() -> &'static Theme {
+    THEME.get_or_init(|| Theme::new(&TerminalConfig::default()))
+}
+
+fn label(name: &str, color: Color) -> String {
+    let label = format!("{}: ", name).color(color);
+    match theme().timestamps {
+        true => format!(
+            "{} {}",
+            Local::now().format("[%H:%M:%S]").to_string().dimmed(),
+            label
+        ),
+        false => label.to_string(),
+    }
+}
+
+/// A line at the bottom of the terminal showing what's going on, like a spinner for a running task.
+/// It's only shown between responses, and is cleared before anything else is printed.
+struct StatusLine {
+    shown: bool,
+    responding: bool,
+}
+
+static STATUS_LINE: Mutex<StatusLine> = Mutex::new(StatusLine {
+    shown: false,
+    responding: false,
+});
+
+/// Replaces the status line. Nothing is shown while the AI is responding.
+pub fn show_status(status: &str) {
+    let mut status_line = STATUS_LINE.lock().unwrap();
+    if status_line.responding {
+        return;
+    }
+    print!("\r\x1b[2K{}", status.dimmed());
+    let _ = stdout().flush();
+    status_line.shown = true;
+}
+
+pub fn clear_status() {
+    let mut status_line = STATUS_LINE.lock().unwrap();
+    if status_line.shown {
+        print!("\r\x1b[2K");
+        let _ = stdout().flush();
+        status_line.shown = false;
+    }
+}
+
+/// Hides the status line while the AI handles a message, so it doesn't get mixed into what's printed.
+pub fn set_responding(responding: bool) {
+    let mut status_line = STATUS_LINE.lock().unwrap();
+    if responding && status_line.shown {
+        print!("\r\x1b[2K");
+        let _ = stdout().flush();
+        status_line.shown = false;
+    }
+    status_line.responding = responding;
+}
+
+/// Prints what the user said or typed.
+pub fn print_user(text: &str) {
+    clear_status();
+    println!("{}", label("You", theme().user));
+    let mut renderer = Renderer::default();
+    renderer.push(text);
+    renderer.finish();
+}
+
+pub fn print_error(err: &str) {
+    clear_status();
+    println!("{}{}", label("Error", theme().error), err);
+}
+
+/// Prints the AI's response as it streams in, one word at a time so it can be wrapped and formatted.
+#[derive(Default)]
+pub struct ResponseRenderer {
+    started: bool,
+    renderer: Renderer,
+}
+
+impl ResponseRenderer {
+    /// Prints the next part of the response. The first part is preceded by the AI's label.
+    pub fn push(&mut self, text: &str) {
+        if !self.started {
+            clear_status();
+            println!("{}", label("AI", theme().ai));
+            self.started = true;
+        }
+        self.renderer.push(text);
+    }
+
+    /// Prints whatever is left and ends the line.
+    pub fn finish(&mut self) {
+        self.renderer.finish();
+    }
+}
+
+/// Turns streamed markdown into formatted terminal output.
+#[derive(Default)]
+struct Renderer {
+    /// What's been rendered but not printed yet.
+    out: String,
+    /// The word being streamed in, printed once it's complete.
+    word: String,
+    /// The line being streamed in, while inside a code block.
+    code_line: String,
+    column: usize,
+    /// Where wrapped lines start, so list items line up.
+    indent: usize,
+    /// The spaces before the line's first word, which nest list items.
+    leading: usize,
+    /// Something has been printed on this line.
+    mid_line: bool,
+    space_pending: bool,
+    /// The rest of the line is skipped, like the language after a code fence.
+    skip_line: bool,
+    in_code_block: bool,
+    heading: bool,
+    bold: bool,
+    italic: bool,
+    code: bool,
+}
+
+impl Renderer {
+    fn push(&mut self, text: &str) {
+        self.render(text);
+        print!("{}", std::mem::take(&mut self.out));
+        let _ = stdout().flush();
+    }
+
+    fn finish(&mut self) {
+        print!("{}", self.render_end());
+    }
+
+    fn render(&mut self, text: &str) {
+        for c in text.chars() {
+            if self.in_code_block {
+                self.push_code_char(c);
+            } else if c == '\n' {
+                self.end_word();
+                self.end_line();
+            } else if c.is_whitespace() && self.at_line_start() {
+                self.leading += if c == '\t' { 4 } else { 1 };
+            } else if c.is_whitespace() {
+                self.end_word();
+                if self.mid_line {
+                    self.space_pending = true;
+                }
+            } else {
+                self.word.push(c);
+            }
+        }
+    }
+
+    /// Nothing on this line has been read yet, apart from spaces.
+    fn at_line_start(&self) -> bool {
+        !self.mid_line && !self.heading && self.word.is_empty()
+    }
+
+    /// Renders whatever is left and ends the line, returning everything not yet printed.
+    fn render_end(&mut self) -> String {
+        self.end_word();
+        if self.in_code_block && !self.code_line.is_empty() {
+            let _ = write!(self.out, "{}", self.code_line.color(theme().code));
+        }
+        self.out.push('\n');
+        std::mem::take(self).out
+    }
+
+    fn end_line(&mut self) {
+        // The newline after a code fence starts the code block, and isn't shown.
+        if !self.in_code_block {
+            self.out.push('\n');
+        }
+        self.start_line();
+    }
+
+    /// Forgets the last line's formatting. Markdown styles don't carry over between lines.
+    fn start_line(&mut self) {
+        *self = Self {
+            out: std::mem::take(&mut self.out),
+            in_code_block: self.in_code_block,
+            ..Self::default()
+        };
+    }
+
+    fn push_code_char(&mut self, c: char) {
+        if self.skip_line {
+            if c == '\n' {
+                self.skip_line = false;
+            }
+            return;
+        }
+        if c != '\n' {
+            self.code_line.push(c);
+            return;
+        }
+        let line = std::mem::take(&mut self.code_line);
+        if line.trim_start().starts_with("```") {
+            self.in_code_block = false;
+            self.start_line();
+        } else {
+            let _ = writeln!(self.out, "{}", line.color(theme().code));
+        }
+    }
+
+    fn end_word(&mut self) {
+        if self.word.is_empty() {
+            return;
+        }
+        let word = std::mem::take(&mut self.word);
+        if !theme().markdown {
+            self.print_word(vec![word.normal()]);
+            return;
+        }
+
+        if !self.mid_line {
+            self.indent = self.leading;
+            if word.starts_with("```") {
+                self.in_code_block = true;
+                self.skip_line = true;
+                return;
+            }
+            if word.len() <= 6 && word.chars().all(|c| c == '#') {
+                self.heading = true;
+                return;
+            }
+            if matches!(word.as_str(), "-" | "*" | "+") {
+                // Nested items get a hollow bullet, so they stand out from the ones they're under.
+                let bullet = match self.leading {
+                    0 => "•",
+                    _ => "◦",
+                };
+                self.print_word(vec![bullet.normal()]);
+                self.indent += 2;
+                return;
+            }
+            let number = word.strip_suffix('.').unwrap_or_default();
+            if !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()) {
+                self.indent += word.len() + 1;
+            }
+        }
+
+        let segments = self.style_word(&word);
+        self.print_word(segments);
+    }
+
+    /// Splits a word at its markdown markers, styling each part and leaving the markers out.
+    /// Underscores only count at the edges of a word, so names like snake_case are left alone.
+    fn style_word(&mut self, word: &str) -> Vec<ColoredString> {
+        let mut segments = Vec::new();
+        let mut segment = String::new();
+        let chars: Vec<char> = word.chars().collect();
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            let doubled = chars.get(i + 1) == Some(&c);
+            let marker_end = i + if doubled { 2 } else { 1 };
+            let toggles = match c {
+                '`' => true,
+                '*' => !self.code,
+                '_' => {
+                    let at_edge = |c: Option<&char>| !c.is_some_and(|c| c.is_alphanumeric());
+                    !self.code
+                        && (at_edge(i.checked_sub(1).and_then(|i| chars.get(i)))
+                            || at_edge(chars.get(marker_end)))
+                }
+                _ => false,
+            };
+            if !toggles {
+                segment.push(c);
+                i += 1;
+                continue;
+            }
+            if !segment.is_empty() {
+                segments.push(self.style(&std::mem::take(&mut segment)));
+            }
+            match c {
+                '`' => {
+                    self.code = !self.code;
+                    i += 1;
+                    continue;
+                }
+                _ if doubled => self.bold = !self.bold,
+                _ => self.italic = !self.italic,
+            }
+            i = marker_end;
+        }
+        if !segment.is_empty() {
+            segments.push(self.style(&segment));
+        }
+        segments
+    }
+
+    fn style(&self, text: &str) -> ColoredString {
+        let mut styled = match self.code {
+            true => text.color(theme().code),
+            false => text.normal(),
+        };
+        if self.bold || self.heading {
+            styled = styled.bold();
+        }
+        if self.italic {
+            styled = styled.italic();
+        }
+        if self.heading {
+            styled = styled.underline();
+        }
+        styled
+    }
+
+    fn print_word(&mut self, segments: Vec<ColoredString>) {
+        let width: usize = segments.iter().map(|s| s.chars().count()).sum();
+        let space = usize::from(self.space_pending);
+        if !self.mid_line && self.leading > 0 {
+            self.out.push_str(&" ".repeat(self.leading));
+            self.column = self.leading;
+        }
+        if let Some(wrap_width) = theme().wrap_width {
+            if self.mid_line && self.column + space + width > wrap_width {
+                let _ = write!(self.out, "\n{}", " ".repeat(self.indent));
+                self.column = self.indent;
+                self.space_pending = false;
+            }
+        }
+        if self.space_pending {
+            self.out.push(' ');
+            self.column += 1;
+            self.space_pending = false;
+        }
+        for segment in segments {
+            let _ = write!(self.out, "{}", segment);
+        }
+        self.column += width;
+        self.mid_line = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Renders markdown streamed in as `chunks`, the way it's printed.
+    fn render(chunks: &[&str]) -> String {
+        colored::control::set_override(true);
+        let mut renderer = Renderer::default();
+        for chunk in chunks {
+            renderer.render(chunk);
+        }
+        renderer.render_end()
+    }
+
+    /// Leaves out the colors and styles, to check what's printed where.
+    fn plain(rendered: &str) -> String {
+        let mut plain = String::new();
+        let mut chars = rendered.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\x1b' => {
+                    chars.find(|&c| c == 'm');
+                }
+                c => plain.push(c),
+            }
+        }
+        plain
+    }
+
+    #[test]
+    fn markers_split_between_chunks_still_format() {
+        let whole = render(&["Some **bold** and *italic* text\n"]);
+        assert_eq!(
+            render(&["Some *", "*bo", "ld*", "* and *ital", "ic* text\n"]),
+            whole
+        );
+        assert!(whole.contains(&"bold".bold().to_string()));
+        assert!(whole.contains(&"italic".italic().to_string()));
+        assert_eq!(plain(&whole), "Some bold and italic text\n\n");
+    }
+
+    #[test]
+    fn underscores_format_only_at_the_edges_of_words() {
+        let rendered = render(&["_italic_ and __bold__ but not snake_case\n"]);
+        assert!(rendered.contains(&"italic".italic().to_string()));
+        assert!(rendered.contains(&"bold".bold().to_string()));
+        assert_eq!(plain(&rendered), "italic and bold but not snake_case\n\n");
+    }
+
+    #[test]
+    fn code_fences_show_only_the_code() {
+        let whole = render(&["Run:\n```rust\nlet a = *b;\n```\nDone"]);
+        assert_eq!(
+            render(&["Run:\n``", "`ru", "st\nlet a = *", "b;\n``", "`\nDone"]),
+            whole
+        );
+        assert_eq!(plain(&whole), "Run:\nlet a = *b;\nDone\n");
+        assert!(whole.contains(&"let a = *b;".color(theme().code).to_string()));
+    }
+
+    #[test]
+    fn nested_list_items_stay_indented() {
+        let rendered = render(&["- one\n  - two\n    - three\n1. first\n   2. second\n"]);
+        assert_eq!(
+            plain(&rendered),
+            "• one\n  ◦ two\n    ◦ three\n1. first\n   2. second\n\n"
+        );
+    }
+
+    #[test]
+    fn headings_dont_keep_their_markers() {
+        assert_eq!(plain(&render(&["## Title\n"])), "Title\n\n");
+    }
+}
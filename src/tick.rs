@@ -0,0 +1,179 @@
+use rodio::{buffer::SamplesBuffer, source::Buffered, Source};
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, Condvar, LazyLock, Mutex, Once},
+    time::Duration,
+};
+
+use crate::audio::{self, AudioSink, Channel};
+
+/// The sound played while the AI is thinking.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum TickSound {
+    /// A short, soft click.
+    Tick,
+    /// A few quiet keystrokes, like someone typing.
+    Typing,
+    /// No sound.
+    Off,
+}
+
+#[derive(Clone, Debug)]
+pub struct TickSettings {
+    pub sound: TickSound,
+    /// How loud the tick plays, where 1.0 is full volume.
+    pub volume: f32,
+    /// The time from the start of one tick to the start of the next.
+    pub interval: Duration,
+    /// An audio file from the sound theme to play instead of the built-in sounds.
+    pub custom_sound: Option<PathBuf>,
+}
+
+struct TickState {
+    settings: TickSettings,
+    thinking: bool,
+}
+
+static TICK_STATE: LazyLock<(Mutex<TickState>, Condvar)> = LazyLock::new(|| {
+    (
+        Mutex::new(TickState {
+            settings: TickSettings {
+                sound: TickSound::Off,
+                volume: 0.25,
+                interval: Duration::from_millis(1000),
+                custom_sound: None,
+            },
+            thinking: false,
+        }),
+        Condvar::new(),
+    )
+});
+
+static START_TICK_THREAD: Once = Once::new();
+
+// The most ticks queued to play at once. A custom sound longer than the interval would otherwise
+// pile up in memory for as long as the AI thinks.
+const MAX_QUEUED_TICKS: usize = 2;
+
+/// Sets how the thinking tick sounds.
+pub fn configure(settings: TickSettings) {
+    let (state, changed) = &*TICK_STATE;
+    state.lock().unwrap().settings = settings;
+    changed.notify_all();
+    START_TICK_THREAD.call_once(|| {
+        std::thread::spawn(tick_loop);
+    });
+}
+
+pub fn settings() -> TickSettings {
+    TICK_STATE.0.lock().unwrap().settings.clone()
+}
+
+/// Called when the AI starts or stops thinking. The tick plays while it's thinking.
+pub fn set_thinking(thinking: bool) {
+    let (state, changed) = &*TICK_STATE;
+    state.lock().unwrap().thinking = thinking;
+    changed.notify_all();
+}
+
+fn tick_loop() {
+    // The sink of the last tick, so a long custom sound can be cut off.
+    let mut sink: Option<Arc<dyn AudioSink>> = None;
+    // The decoded custom sound, so it isn't read from disk for every tick.
+    let mut custom_samples: Option<(PathBuf, Buffered<SamplesBuffer<i16>>)> = None;
+
+    let (state, changed) = &*TICK_STATE;
+    let mut state = state.lock().unwrap();
+    loop {
+        let settings = state.settings.clone();
+        if !state.thinking || settings.sound == TickSound::Off {
+            // Cut off a long custom sound as soon as the AI stops thinking.
+            if let Some(sink) = sink.take() {
+                sink.stop();
+            }
+            state = changed.wait(state).unwrap();
+            continue;
+        }
+
+        let samples = match &settings.custom_sound {
+            Some(path) => {
+                if custom_samples
+                    .as_ref()
+                    .is_none_or(|(loaded, _)| loaded != path)
+                {
+                    custom_samples =
+                        load_sound(path).map(|samples| (path.clone(), samples.buffered()));
+                }
+                match &custom_samples {
+                    Some((_, samples)) => samples.clone(),
+                    None => builtin_samples(settings.sound).buffered(),
+                }
+            }
+            None => builtin_samples(settings.sound).buffered(),
+        };
+        // A new sink is made once the last one is done, so ticks follow the ui channel's output device
+        // while a long custom sound still queues up behind itself.
+        if sink.as_ref().is_some_and(|sink| sink.empty()) {
+            sink = None;
+        }
+        let sink = sink.get_or_insert_with(|| audio::new_sink(Channel::Ui));
+        if sink.len() < MAX_QUEUED_TICKS {
+            // The tick's own volume is applied to the sound itself, since the audio service controls the sink's volume.
+            sink.append(samples.amplify(settings.volume));
+        }
+
+        // Waiting on the condvar means turning the tick off takes effect right away.
+        state = changed
+            .wait_timeout_while(state, settings.interval, |state| state.thinking)
+            .unwrap()
+            .0;
+    }
+}
+
+const SAMPLE_RATE: u32 = 24000;
+
+fn load_sound(path: &Path) -> Option<rodio::buffer::SamplesBuffer<i16>> {
+    let file = std::fs::File::open(path).ok()?;
+    let decoder = rodio::Decoder::new(std::io::BufReader::new(file)).ok()?;
+    let channels = decoder.channels();
+    let sample_rate = decoder.sample_rate();
+    Some(rodio::buffer::SamplesBuffer::new(
+        channels,
+        sample_rate,
+        decoder.collect::<Vec<i16>>(),
+    ))
+}
+
+fn builtin_samples(sound: TickSound) -> rodio::buffer::SamplesBuffer<i16> {
+    let samples = match sound {
+        TickSound::Tick => click(2000.0, 0.008, 0.5),
+        TickSound::Typing => {
+            // Keystrokes at uneven gaps and strengths sound more like typing than a steady pattern.
+            let mut samples = Vec::new();
+            for (gap, pitch, strength) in [
+                (0.0, 3100.0, 0.5),
+                (0.11, 2600.0, 0.35),
+                (0.07, 2900.0, 0.45),
+            ] {
+                samples.extend(std::iter::repeat_n(0, (gap * SAMPLE_RATE as f32) as usize));
+                samples.extend(click(pitch, 0.012, strength));
+            }
+            samples
+        }
+        TickSound::Off => Vec::new(),
+    };
+    rodio::buffer::SamplesBuffer::new(1, SAMPLE_RATE, samples)
+}
+
+/// A short tone that dies away quickly, like a click.
+fn click(frequency: f32, seconds: f32, strength: f32) -> Vec<i16> {
+    let len = (SAMPLE_RATE as f32 * seconds) as usize;
+    (0..len)
+        .map(|i| {
+            let t = i as f32 / SAMPLE_RATE as f32;
+            let decay = (-t / (seconds / 5.0)).exp();
+            let sample = (2.0 * std::f32::consts::PI * frequency * t).sin() * decay * strength;
+            (sample * i16::MAX as f32) as i16
+        })
+        .collect()
+}
@@ -0,0 +1,158 @@
+use std::f32::consts::PI;
+
+// The length of each overlapping frame. 30ms is short enough to follow speech
+// but long enough to contain a couple of pitch periods of a low voice.
+const FRAME_SECONDS: f32 = 0.03;
+
+/// Changes the tempo of interleaved audio samples by `speed` without changing their pitch.
+///
+/// Uses WSOLA (waveform similarity overlap-add). The audio is cut into overlapping windowed
+/// frames that are read `speed` times faster than they are written back out. Each frame's read
+/// position is nudged to where it best lines up with the previous frame, so the voice doesn't warble.
+/// A `speed` that isn't a positive number leaves the audio unchanged.
+pub fn time_stretch(samples: &[i16], channels: u16, sample_rate: u32, speed: f32) -> Vec<i16> {
+    let channels = channels.max(1) as usize;
+    let input_frames = samples.len() / channels;
+    if speed == 1.0 || !(speed.is_finite() && speed > 0.0) || input_frames == 0 {
+        return samples.to_vec();
+    }
+
+    let frame_len = ((sample_rate as f32 * FRAME_SECONDS) as usize).max(4);
+    let synthesis_hop = frame_len / 2;
+    let analysis_hop = synthesis_hop as f32 * speed;
+    let tolerance = frame_len / 4;
+
+    // Frames are aligned using a mono mix so every channel gets the same offsets.
+    let mono: Vec<f32> = samples
+        .chunks_exact(channels)
+        .map(|frame| frame.iter().map(|&s| s as f32).sum::<f32>() / channels as f32)
+        .collect();
+
+    // A periodic Hann window sums to a constant at 50% overlap.
+    let window: Vec<f32> = (0..frame_len)
+        .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / frame_len as f32).cos())
+        .collect();
+
+    let output_frames = (input_frames as f32 / speed) as usize;
+    let mut output = vec![0.0f32; (output_frames + frame_len) * channels];
+    let mut window_sum = vec![0.0f32; output_frames + frame_len];
+
+    let mut previous_input_pos = 0;
+    for k in 0.. {
+        let nominal_input_pos = (k as f32 * analysis_hop) as usize;
+        if nominal_input_pos >= input_frames {
+            break;
+        }
+
+        let input_pos = if k == 0 {
+            0
+        } else {
+            best_alignment(
+                &mono,
+                previous_input_pos + synthesis_hop,
+                nominal_input_pos,
+                tolerance,
+                frame_len,
+            )
+        };
+
+        let output_pos = k * synthesis_hop;
+        for (i, &weight) in window.iter().enumerate() {
+            let src = input_pos + i;
+            let dst = output_pos + i;
+            if src >= input_frames || dst >= window_sum.len() {
+                break;
+            }
+            for c in 0..channels {
+                output[dst * channels + c] += samples[src * channels + c] as f32 * weight;
+            }
+            window_sum[dst] += weight;
+        }
+
+        previous_input_pos = input_pos;
+    }
+
+    output.truncate(output_frames * channels);
+    output
+        .chunks_exact(channels)
+        .zip(window_sum)
+        .flat_map(|(frame, weight)| {
+            frame.iter().map(move |&s| {
+                let s = if weight > 1e-3 { s / weight } else { 0.0 };
+                s.clamp(i16::MIN as f32, i16::MAX as f32) as i16
+            })
+        })
+        .collect()
+}
+
+/// Searches around `nominal` for the frame start whose waveform best matches
+/// the frame starting at `target`, the natural continuation of the previous frame.
+fn best_alignment(
+    mono: &[f32],
+    target: usize,
+    nominal: usize,
+    tolerance: usize,
+    frame_len: usize,
+) -> usize {
+    let start = nominal.saturating_sub(tolerance);
+    let end = (nominal + tolerance).min(mono.len() - 1);
+
+    let mut best_pos = nominal;
+    let mut best_score = f32::MIN;
+    for candidate in start..=end {
+        // Only every 4th sample is compared. It's plenty for alignment and 4x cheaper.
+        let score: f32 = (0..frame_len)
+            .step_by(4)
+            .take_while(|i| target + i < mono.len() && candidate + i < mono.len())
+            .map(|i| mono[target + i] * mono[candidate + i])
+            .sum();
+        if score > best_score {
+            best_score = score;
+            best_pos = candidate;
+        }
+    }
+
+    best_pos
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: u32 = 16_000;
+
+    /// One second of a 220Hz tone, in stereo.
+    fn tone() -> Vec<i16> {
+        (0..SAMPLE_RATE)
+            .flat_map(|i| {
+                let sample =
+                    (8000.0 * (2.0 * PI * 220.0 * i as f32 / SAMPLE_RATE as f32).sin()) as i16;
+                [sample, sample]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn speed_changes_the_length() {
+        let samples = tone();
+        for speed in [0.5, 1.5, 2.0] {
+            let stretched = time_stretch(&samples, 2, SAMPLE_RATE, speed);
+            let expected = samples.len() as f32 / speed;
+            assert_eq!(stretched.len() % 2, 0);
+            assert!(
+                (stretched.len() as f32 - expected).abs() < expected * 0.05,
+                "{} samples at speed {}",
+                stretched.len(),
+                speed
+            );
+        }
+    }
+
+    #[test]
+    fn bad_speeds_leave_the_audio_unchanged() {
+        let samples = tone();
+        for speed in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+            assert_eq!(time_stretch(&samples, 2, SAMPLE_RATE, speed), samples);
+        }
+    }
+}
@@ -0,0 +1,446 @@
+//! Saves timers and how often they repeat, stopwatches, and reminders in a SQLite database, so
+//! they survive restarts.
+
+use anyhow::{bail, Context};
+use chrono::{DateTime, Local};
+use csv::{ReaderBuilder, StringRecord};
+use rusqlite::{params, Connection};
+use std::{
+    ops::{Deref, DerefMut},
+    path::Path,
+    str::FromStr,
+    sync::{Mutex, MutexGuard},
+    time::Duration,
+};
+use tracing::{info, warn};
+
+use crate::{
+    reminders::Reminder,
+    timers::{AlarmSettings, Timer},
+    CACHE_DIR,
+};
+
+// Each migration upgrades the database schema by one version. Only ever add to the end of this list.
+const MIGRATIONS: [&str; 6] = [
+    "CREATE TABLE timers (
+        id INTEGER PRIMARY KEY,
+        description TEXT NOT NULL,
+        timestamp TEXT NOT NULL,
+        sound TEXT,
+        volume REAL NOT NULL,
+        escalate INTEGER NOT NULL,
+        ring_secs INTEGER NOT NULL,
+        re_ring_interval_secs INTEGER NOT NULL,
+        re_rings INTEGER NOT NULL
+    );",
+    "CREATE TABLE stopwatches (
+        name TEXT PRIMARY KEY,
+        elapsed_ms INTEGER NOT NULL,
+        running_since TEXT
+    );",
+    "CREATE TABLE reminders (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        text TEXT NOT NULL,
+        created TEXT NOT NULL,
+        completed TEXT
+    );",
+    "ALTER TABLE timers ADD COLUMN announce_before TEXT NOT NULL DEFAULT '';",
+    // IDs are given out by the database, and never reused, so two launches can't give out the same one.
+    "CREATE TABLE timers_new (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        description TEXT NOT NULL,
+        timestamp TEXT NOT NULL,
+        sound TEXT,
+        volume REAL NOT NULL,
+        escalate INTEGER NOT NULL,
+        ring_secs INTEGER NOT NULL,
+        re_ring_interval_secs INTEGER NOT NULL,
+        re_rings INTEGER NOT NULL,
+        announce_before TEXT NOT NULL DEFAULT ''
+    );
+    INSERT INTO timers_new SELECT id, description, timestamp, sound, volume, escalate, ring_secs,
+        re_ring_interval_secs, re_rings, announce_before FROM timers;
+    DROP TABLE timers;
+    ALTER TABLE timers_new RENAME TO timers;",
+    // How long after going off a repeating timer goes off again. Timers without a row don't repeat.
+    "CREATE TABLE recurrences (
+        timer_id INTEGER PRIMARY KEY REFERENCES timers(id),
+        interval_secs INTEGER NOT NULL
+    );",
+];
+
+// Opened when it's first used. If it can't be opened, it's tried again the next time it's used.
+static DB: Mutex<Option<Connection>> = Mutex::new(None);
+
+/// The open timer database, locked so only one thread uses it at a time.
+struct Db(MutexGuard<'static, Option<Connection>>);
+
+impl Deref for Db {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.0
+            .as_ref()
+            .expect("The database is opened before a Db is made")
+    }
+}
+
+impl DerefMut for Db {
+    fn deref_mut(&mut self) -> &mut Connection {
+        self.0
+            .as_mut()
+            .expect("The database is opened before a Db is made")
+    }
+}
+
+/// Locks the timer database, opening it if it isn't open yet.
+fn db() -> Result<Db, anyhow::Error> {
+    let mut db = DB.lock().unwrap();
+    if db.is_none() {
+        *db = Some(open().context("Failed to open the timer database")?);
+    }
+    Ok(Db(db))
+}
+
+fn open() -> Result<Connection, anyhow::Error> {
+    let mut conn = Connection::open(CACHE_DIR.join("timers.db"))?;
+    migrate(&mut conn)?;
+    // Old timers that can't be imported aren't worth losing the rest over.
+    let csv_path = CACHE_DIR.join("timers.csv");
+    if let Err(err) = import_csv(&mut conn, &csv_path) {
+        let bad_path = csv_path.with_extension("csv.bad");
+        warn!(
+            "Failed to import timers from {}, so it was renamed to {}: {:?}",
+            csv_path.display(),
+            bad_path.display(),
+            err
+        );
+        let _ = std::fs::rename(&csv_path, bad_path);
+    }
+    Ok(conn)
+}
+
+fn migrate(conn: &mut Connection) -> Result<(), anyhow::Error> {
+    let version: usize = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration)?;
+        tx.pragma_update(None, "user_version", i + 1)?;
+        tx.commit()?;
+        info!("Migrated the timer database to version {}", i + 1);
+    }
+    Ok(())
+}
+
+/// Parses an optional column of a timer record, using `default` if the column is missing.
+fn column<T: FromStr>(record: &StringRecord, index: usize, default: T) -> Result<T, anyhow::Error>
+where
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    match record.get(index) {
+        Some(value) => Ok(value.parse()?),
+        None => Ok(default),
+    }
+}
+
+/// Reads a timer from a record of the CSV file older versions saved timers in.
+fn parse_record(record: &StringRecord) -> Result<Timer, anyhow::Error> {
+    let (Some(id), Some(description), Some(timestamp)) =
+        (record.get(0), record.get(1), record.get(2))
+    else {
+        bail!("The timer is missing columns");
+    };
+    let default_alarm = AlarmSettings::default();
+    Ok(Timer {
+        id: id.parse()?,
+        description: description.to_string(),
+        timestamp: timestamp.parse()?,
+        alarm: AlarmSettings {
+            sound: record.get(3).filter(|s| !s.is_empty()).map(str::to_string),
+            volume: column(record, 4, default_alarm.volume)?,
+            escalate: column(record, 5, default_alarm.escalate)?,
+            ring_duration: Duration::from_secs(column(
+                record,
+                6,
+                default_alarm.ring_duration.as_secs(),
+            )?),
+            re_ring_interval: Duration::from_secs(column(
+                record,
+                7,
+                default_alarm.re_ring_interval.as_secs(),
+            )?),
+            re_rings: column(record, 8, default_alarm.re_rings)?,
+            announce_before: Vec::new(),
+        },
+    })
+}
+
+/// Moves timers from the CSV file older versions saved them in into the database.
+/// The file is renamed afterwards so it's only imported once.
+fn import_csv(conn: &mut Connection, path: &Path) -> Result<(), anyhow::Error> {
+    if !path.is_file() {
+        return Ok(());
+    }
+
+    // Files saved by older versions don't have the alarm settings columns.
+    let mut rdr = ReaderBuilder::new().flexible(true).from_path(path)?;
+    let tx = conn.transaction()?;
+    let mut count = 0;
+    for (line, result) in rdr.records().enumerate() {
+        // Records that can't be read are skipped, so one bad line doesn't lose every timer.
+        let timer = match result
+            .map_err(anyhow::Error::from)
+            .and_then(|record| parse_record(&record))
+        {
+            Ok(timer) => timer,
+            Err(err) => {
+                warn!(
+                    "Skipped timer {} in {}: {:?}",
+                    line + 1,
+                    path.display(),
+                    err
+                );
+                continue;
+            }
+        };
+        insert_timer_with(&tx, Some(timer.id), &timer)?;
+        count += 1;
+    }
+    tx.commit()?;
+
+    std::fs::rename(path, path.with_extension("csv.imported"))?;
+    info!("Imported {} timers from {}", count, path.display());
+    Ok(())
+}
+
+pub fn load_timers() -> Result<Vec<Timer>, anyhow::Error> {
+    let db = db()?;
+    let mut stmt = db.prepare(
+        "SELECT id, description, timestamp, sound, volume, escalate, ring_secs, re_ring_interval_secs, re_rings, announce_before
+        FROM timers ORDER BY timestamp",
+    )?;
+    let timers = stmt.query_map([], |row| {
+        Ok(Timer {
+            id: row.get(0)?,
+            description: row.get(1)?,
+            timestamp: row.get(2)?,
+            alarm: AlarmSettings {
+                sound: row.get(3)?,
+                volume: row.get(4)?,
+                escalate: row.get(5)?,
+                ring_duration: Duration::from_secs(row.get(6)?),
+                re_ring_interval: Duration::from_secs(row.get(7)?),
+                re_rings: row.get(8)?,
+                announce_before: row
+                    .get::<_, String>(9)?
+                    .split(',')
+                    .filter_map(|secs| secs.parse().ok())
+                    .map(Duration::from_secs)
+                    .collect(),
+            },
+        })
+    })?;
+    Ok(timers.collect::<Result<_, _>>()?)
+}
+
+/// A timer's countdown announcements, as they're stored: a comma separated list of seconds.
+fn announce_before(timer: &Timer) -> String {
+    timer
+        .alarm
+        .announce_before
+        .iter()
+        .map(|lead| lead.as_secs().to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Saves a timer with `id`, or with the next free ID if it's `None`. Returns the timer's ID.
+fn insert_timer_with(
+    conn: &Connection,
+    id: Option<u64>,
+    timer: &Timer,
+) -> Result<u64, anyhow::Error> {
+    conn.execute(
+        "INSERT INTO timers
+        (id, description, timestamp, sound, volume, escalate, ring_secs, re_ring_interval_secs, re_rings, announce_before)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![
+            id,
+            timer.description,
+            timer.timestamp,
+            timer.alarm.sound,
+            timer.alarm.volume,
+            timer.alarm.escalate,
+            timer.alarm.ring_duration.as_secs(),
+            timer.alarm.re_ring_interval.as_secs(),
+            timer.alarm.re_rings,
+            announce_before(timer),
+        ],
+    )?;
+    Ok(conn.last_insert_rowid() as u64)
+}
+
+/// Saves a new timer, ignoring its ID. Returns the ID the database gave it.
+pub fn insert_timer(timer: &Timer) -> Result<u64, anyhow::Error> {
+    insert_timer_with(&*db()?, None, timer)
+}
+
+/// Saves a timer that went off back with its own ID, to go off again.
+pub fn restore_timer(timer: &Timer) -> Result<(), anyhow::Error> {
+    insert_timer_with(&*db()?, Some(timer.id), timer)?;
+    Ok(())
+}
+
+/// Saves changes to when a saved timer goes off, what it's for, and its announcements.
+pub fn update_timer(timer: &Timer) -> Result<(), anyhow::Error> {
+    let changed = db()?.execute(
+        "UPDATE timers SET description = ?2, timestamp = ?3, announce_before = ?4 WHERE id = ?1",
+        params![
+            timer.id,
+            timer.description,
+            timer.timestamp,
+            announce_before(timer),
+        ],
+    )?;
+    if changed == 0 {
+        bail!("Timer with ID {} isn't saved", timer.id);
+    }
+    Ok(())
+}
+
+pub fn delete_timers(ids: &[u64]) -> Result<(), anyhow::Error> {
+    let mut db = db()?;
+    let tx = db.transaction()?;
+    for id in ids {
+        tx.execute("DELETE FROM timers WHERE id = ?1", [id])?;
+        // Foreign keys aren't enforced, so a deleted timer's recurrence is deleted here.
+        tx.execute("DELETE FROM recurrences WHERE timer_id = ?1", [id])?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// A saved stopwatch: its name, the time it counted before it was last started,
+/// and when it was last started if it's running.
+pub type SavedStopwatch = (String, Duration, Option<DateTime<Local>>);
+
+pub fn load_stopwatches() -> Result<Vec<SavedStopwatch>, anyhow::Error> {
+    let db = db()?;
+    let mut stmt = db.prepare("SELECT name, elapsed_ms, running_since FROM stopwatches")?;
+    let stopwatches = stmt.query_map([], |row| {
+        Ok((row.get(0)?, Duration::from_millis(row.get(1)?), row.get(2)?))
+    })?;
+    Ok(stopwatches.collect::<Result<_, _>>()?)
+}
+
+pub fn save_stopwatch(
+    name: &str,
+    elapsed: Duration,
+    running_since: Option<DateTime<Local>>,
+) -> Result<(), anyhow::Error> {
+    db()?.execute(
+        "INSERT OR REPLACE INTO stopwatches (name, elapsed_ms, running_since) VALUES (?1, ?2, ?3)",
+        params![name, elapsed.as_millis() as u64, running_since,],
+    )?;
+    Ok(())
+}
+
+pub fn delete_stopwatch(name: &str) -> Result<(), anyhow::Error> {
+    db()?.execute("DELETE FROM stopwatches WHERE name = ?1", [name])?;
+    Ok(())
+}
+
+/// Saves a new reminder and returns its ID.
+pub fn insert_reminder(text: &str, created: DateTime<Local>) -> Result<u64, anyhow::Error> {
+    let db = db()?;
+    db.execute(
+        "INSERT INTO reminders (text, created) VALUES (?1, ?2)",
+        params![text, created],
+    )?;
+    Ok(db.last_insert_rowid() as u64)
+}
+
+/// Loads reminders, oldest first. Completed reminders are only included if `include_completed` is true.
+pub fn load_reminders(include_completed: bool) -> Result<Vec<Reminder>, anyhow::Error> {
+    let db = db()?;
+    let mut stmt = db.prepare(
+        "SELECT id, text, created, completed FROM reminders
+        WHERE ?1 OR completed IS NULL ORDER BY id",
+    )?;
+    let reminders = stmt.query_map([include_completed], |row| {
+        Ok(Reminder {
+            id: row.get(0)?,
+            text: row.get(1)?,
+            created: row.get(2)?,
+            completed: row.get(3)?,
+        })
+    })?;
+    Ok(reminders.collect::<Result<_, _>>()?)
+}
+
+/// Marks a reminder as completed. Returns false if there's no uncompleted reminder with that ID.
+pub fn complete_reminder(id: u64, completed: DateTime<Local>) -> Result<bool, anyhow::Error> {
+    let changed = db()?.execute(
+        "UPDATE reminders SET completed = ?2 WHERE id = ?1 AND completed IS NULL",
+        params![id, completed],
+    )?;
+    Ok(changed > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timer_ids_are_not_reused() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+        let timer = Timer {
+            id: 0,
+            description: "Tea".to_string(),
+            timestamp: Local::now(),
+            alarm: AlarmSettings::default(),
+        };
+
+        let first = insert_timer_with(&conn, None, &timer).unwrap();
+        let second = insert_timer_with(&conn, None, &timer).unwrap();
+        assert_ne!(first, second);
+
+        // A timer that's gone doesn't give its ID to the next one.
+        conn.execute("DELETE FROM timers WHERE id = ?1", [second])
+            .unwrap();
+        assert!(insert_timer_with(&conn, None, &timer).unwrap() > second);
+
+        // Saving a timer with an ID that's taken fails, instead of replacing it.
+        assert!(insert_timer_with(&conn, Some(first), &timer).is_err());
+    }
+
+    #[test]
+    fn bad_csv_timers_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("timers.csv");
+        let timestamp = Local::now().to_rfc3339();
+        std::fs::write(
+            &path,
+            format!(
+                "id,description,timestamp\n1,Tea,{0}\nnot a number,Eggs,{0}\n3,Pasta,yesterday\n4\n5,Bread,{0},,0.5\n",
+                timestamp
+            ),
+        )
+        .unwrap();
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+
+        import_csv(&mut conn, &path).unwrap();
+        let descriptions: Vec<String> = conn
+            .prepare("SELECT description FROM timers ORDER BY id")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(descriptions, ["Tea", "Bread"]);
+        assert!(!path.exists());
+        assert!(path.with_extension("csv.imported").exists());
+    }
+}
@@ -1,86 +1,100 @@
 use anyhow::bail;
 use chrono::{DateTime, Local};
-use csv::Reader;
+use rodio::Source;
 use std::{
+    collections::HashMap,
     io::BufReader,
     path::{Path, PathBuf},
-    sync::{
-        atomic::{AtomicU64, Ordering},
-        LazyLock, RwLock,
-    }, // Use LazyLock from std
+    sync::{Arc, Condvar, LazyLock, Mutex, RwLock}, // Use LazyLock from std
     thread,
+    time::Duration,
 };
 use tracing::{info, warn};
 
-use crate::CACHE_DIR;
+use crate::{
+    audio::{self, Channel},
+    sound_theme::Sound,
+    timer_store, SOUND_THEME,
+};
+
+// How quiet an escalating alarm starts, relative to its full volume.
+const ESCALATION_START_VOLUME: f32 = 0.2;
+// How long an escalating alarm takes to reach its full volume.
+const ESCALATION_TIME: Duration = Duration::from_secs(60);
 
-// Global atomic ID counter for timers
-static NEXT_ID: LazyLock<AtomicU64> = LazyLock::new(|| AtomicU64::new(1));
+// The longest the alarm thread waits between checking the timers,
+// in case the system clock changes or the computer sleeps.
+const MAX_TIMER_WAIT: Duration = Duration::from_secs(60);
 
-// Global lazy-initialized in-memory timers storage.
-// Now we store (id, description, timestamp)
-static TIMERS: LazyLock<RwLock<Vec<(u64, String, DateTime<Local>)>>> = LazyLock::new(|| {
-    let path = CACHE_DIR.join("timers.csv");
-    let timers = load_timers_from_disk(&path).expect("Failed to load timers");
-    RwLock::new(timers)
+// Global lazy-initialized in-memory timers storage. Changes are saved to the timer store as they're made.
+// If they can't be loaded, it starts with none, and adding timers says why they can't be saved.
+static TIMERS: LazyLock<RwLock<Vec<Timer>>> = LazyLock::new(|| {
+    RwLock::new(timer_store::load_timers().unwrap_or_else(|err| {
+        warn!("Failed to load timers: {:?}", err);
+        Vec::new()
+    }))
 });
 
-fn load_timers_from_disk(
-    path: &Path,
-) -> Result<Vec<(u64, String, DateTime<Local>)>, anyhow::Error> {
-    if !path.is_file() {
-        let mut wtr = csv::Writer::from_path(path)?;
-        wtr.write_record(["id", "description", "timestamp"])?;
-        wtr.flush()?;
-        return Ok(vec![]);
-    }
-
-    let mut rdr = Reader::from_path(path)?;
-    let mut records = Vec::new();
-    let mut max_id = 0;
-    for result in rdr.records() {
-        let record = result?;
-        let id: u64 = record[0].parse()?;
-        let description = &record[1];
-        let timestamp: DateTime<Local> = record[2].parse()?;
-        if id > max_id {
-            max_id = id;
-        }
-        records.push((id, description.to_string(), timestamp));
-    }
+// Wakes the alarm thread when timers are added or changed, so it can recompute when the next one goes off.
+static TIMERS_CHANGED: LazyLock<(flume::Sender<()>, flume::Receiver<()>)> =
+    LazyLock::new(flume::unbounded);
 
-    // Set NEXT_ID to one more than the max ID found
-    NEXT_ID.store(max_id + 1, Ordering::Relaxed);
-    Ok(records)
+fn notify_timers_changed() {
+    let _ = TIMERS_CHANGED.0.send(());
 }
 
-fn save_timers_to_disk(path: &Path) -> Result<(), anyhow::Error> {
+// How long until the next timer goes off or has a countdown announcement, up to MAX_TIMER_WAIT
+fn time_until_next_timer() -> Duration {
     let timers = TIMERS.read().unwrap();
-    let mut wtr = csv::Writer::from_path(path)?;
-    wtr.write_record(["id", "description", "timestamp"])?;
-    for (id, description, timestamp) in timers.iter() {
-        wtr.write_record([&id.to_string(), description, &timestamp.to_rfc3339()])?;
+    let next = timers
+        .iter()
+        .flat_map(|timer| {
+            let announcements =
+                timer.alarm.announce_before.iter().filter_map(|lead| {
+                    Some(timer.timestamp - chrono::Duration::from_std(*lead).ok()?)
+                });
+            std::iter::once(timer.timestamp).chain(announcements)
+        })
+        .min();
+    match next {
+        Some(next) => (next - Local::now())
+            .to_std()
+            .unwrap_or_default()
+            .min(MAX_TIMER_WAIT),
+        None => MAX_TIMER_WAIT,
     }
-    wtr.flush()?;
-    Ok(())
 }
 
 // Public API for reading timers from memory
-pub fn get_timers() -> Vec<(u64, String, DateTime<Local>)> {
+pub fn get_timers() -> Vec<Timer> {
     let timers = TIMERS.read().unwrap();
     timers.clone()
 }
 
-// Public API for adding a timer with a description
-pub fn set_timer(description: String, timer_time: DateTime<Local>) -> Result<(), anyhow::Error> {
-    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
-    {
-        let mut timers = TIMERS.write().unwrap();
-        timers.push((id, description, timer_time));
-    }
-    // Save to disk after modification if desired
-    save_timers_to_disk(&CACHE_DIR.join("timers.csv"))?;
-    Ok(())
+// Public API for adding a timer with a description and how its alarm should ring.
+// Returns the new timer's ID.
+pub fn set_timer(
+    description: String,
+    timer_time: DateTime<Local>,
+    mut alarm: AlarmSettings,
+) -> Result<u64, anyhow::Error> {
+    // Announcements that would already be due are dropped, so a 3 minute timer isn't announced as having 5 minutes left.
+    let time_left = (timer_time - Local::now()).to_std().unwrap_or_default();
+    alarm.announce_before.retain(|lead| *lead < time_left);
+
+    let mut timers = TIMERS.write().unwrap();
+    let mut timer = Timer {
+        id: 0,
+        description,
+        timestamp: timer_time,
+        alarm,
+    };
+    // The timer store gives out IDs, so a timer set by another launch can't share one.
+    timer.id = timer_store::insert_timer(&timer)?;
+    let id = timer.id;
+    timers.push(timer);
+    notify_timers_changed();
+    Ok(id)
 }
 
 // Public API for deleting a timer by ID
@@ -90,12 +104,13 @@ pub fn delete_timer(id: u64) -> Result<(), anyhow::Error> {
     {
         let mut timers = TIMERS.write().unwrap();
         original_count = timers.len();
-        timers.retain(|(t_id, _, _)| *t_id != id);
+        timers.retain(|timer| timer.id != id);
         new_count = timers.len();
     }
 
     if new_count != original_count {
-        save_timers_to_disk(&CACHE_DIR.join("timers.csv"))?;
+        timer_store::delete_timers(&[id])?;
+        notify_timers_changed();
     } else {
         bail!("Timer with ID {} not found", id);
     }
@@ -103,14 +118,53 @@ pub fn delete_timer(id: u64) -> Result<(), anyhow::Error> {
     Ok(())
 }
 
-fn check_timers() -> Result<Vec<(u64, String, DateTime<Local>)>, anyhow::Error> {
+// Public API for changing when a timer goes off and what it's for. Returns the updated timer.
+pub fn update_timer(
+    id: u64,
+    new_time: Option<DateTime<Local>>,
+    new_description: Option<String>,
+) -> Result<Timer, anyhow::Error> {
+    let mut timers = TIMERS.write().unwrap();
+    let Some(timer) = timers.iter_mut().find(|timer| timer.id == id) else {
+        bail!("Timer with ID {} not found", id);
+    };
+
+    let mut updated = timer.clone();
+    if let Some(new_time) = new_time {
+        updated.timestamp = new_time;
+    }
+    if let Some(new_description) = new_description {
+        updated.description = new_description;
+    }
+    timer_store::update_timer(&updated)?;
+    *timer = updated.clone();
+    notify_timers_changed();
+    Ok(updated)
+}
+
+// Puts an expired timer back with its original ID, to go off again at `timer_time`
+fn reschedule_timer(mut timer: Timer, timer_time: DateTime<Local>) -> Result<(), anyhow::Error> {
+    timer.timestamp = timer_time;
+    timer_store::restore_timer(&timer)?;
+    TIMERS.write().unwrap().push(timer);
+    notify_timers_changed();
+    Ok(())
+}
+
+// Public API for removing the timers that went off while the assistant wasn't running.
+// Call before creating AudibleTimers, so they're announced instead of all ringing at once.
+pub fn take_missed_timers() -> Result<Vec<Timer>, anyhow::Error> {
+    check_timers()
+}
+
+fn check_timers() -> Result<Vec<Timer>, anyhow::Error> {
     let mut expired_timers = Vec::new();
     {
         let mut timers = TIMERS.write().unwrap();
-        timers.retain(|(id, description, timestamp)| {
+        timers.retain(|timer| {
             let now_local = Local::now();
-            if *timestamp <= now_local {
-                expired_timers.push((*id, description.clone(), *timestamp));
+            if timer.timestamp <= now_local {
+                expired_timers.push(timer.clone());
                 false
             } else {
                 true
@@ -118,37 +172,277 @@ fn check_timers() -> Result<Vec<(u64, String, DateTime<Local>)>, anyhow::Error>
         });
     }
     if !expired_timers.is_empty() {
-        save_timers_to_disk(&CACHE_DIR.join("timers.csv"))?;
+        let ids: Vec<u64> = expired_timers.iter().map(|timer| timer.id).collect();
+        timer_store::delete_timers(&ids)?;
     }
     Ok(expired_timers)
 }
 
+// Finds the timers with a countdown announcement that's due, and removes those announcements.
+// Returns each timer along with how much time it has left.
+fn take_due_countdowns() -> Result<Vec<(Timer, Duration)>, anyhow::Error> {
+    let now = Local::now();
+    let mut due = Vec::new();
+    {
+        let mut timers = TIMERS.write().unwrap();
+        for timer in timers.iter_mut() {
+            let time_left = (timer.timestamp - now).to_std().unwrap_or_default();
+            let announcements = timer.alarm.announce_before.len();
+            // If several are due at once, only the one closest to now is announced.
+            timer.alarm.announce_before.retain(|lead| *lead < time_left);
+            if timer.alarm.announce_before.len() != announcements {
+                due.push((timer.clone(), time_left));
+            }
+        }
+    }
+    for (timer, _) in &due {
+        timer_store::update_timer(timer)?;
+    }
+    Ok(due)
+}
+
+/// A stopwatch that can be stopped and started again without losing its time.
+#[derive(Default)]
+struct Stopwatch {
+    // Time counted before the stopwatch was last started.
+    elapsed: Duration,
+    // When the stopwatch was last started, if it's running.
+    // Wall clock time, so running stopwatches keep counting across restarts.
+    running_since: Option<DateTime<Local>>,
+}
+
+impl Stopwatch {
+    fn elapsed(&self) -> Duration {
+        self.elapsed
+            + self.running_since.map_or(Duration::ZERO, |since| {
+                (Local::now() - since).to_std().unwrap_or_default()
+            })
+    }
+
+    fn save(&self, name: &str) -> Result<(), anyhow::Error> {
+        timer_store::save_stopwatch(name, self.elapsed, self.running_since)
+    }
+}
+
+// Named stopwatches, like "pasta".
+static STOPWATCHES: LazyLock<Mutex<HashMap<String, Stopwatch>>> = LazyLock::new(|| {
+    let stopwatches = timer_store::load_stopwatches().unwrap_or_else(|err| {
+        warn!("Failed to load stopwatches: {:?}", err);
+        Vec::new()
+    });
+    let stopwatches = stopwatches
+        .into_iter()
+        .map(|(name, elapsed, running_since)| {
+            (
+                name,
+                Stopwatch {
+                    elapsed,
+                    running_since,
+                },
+            )
+        })
+        .collect();
+    Mutex::new(stopwatches)
+});
+
+// Public API for starting a stopwatch, or resuming it if it was stopped
+pub fn start_stopwatch(name: &str) -> Result<(), anyhow::Error> {
+    let mut stopwatches = STOPWATCHES.lock().unwrap();
+    let stopwatch = stopwatches.entry(name.to_string()).or_default();
+    if stopwatch.running_since.is_some() {
+        bail!("Stopwatch \"{}\" is already running", name);
+    }
+    stopwatch.running_since = Some(Local::now());
+    stopwatch.save(name)
+}
+
+// Public API for stopping a stopwatch. Returns the time it counted.
+pub fn stop_stopwatch(name: &str) -> Result<Duration, anyhow::Error> {
+    let mut stopwatches = STOPWATCHES.lock().unwrap();
+    let Some(stopwatch) = stopwatches.get_mut(name) else {
+        bail!("Stopwatch \"{}\" not found", name);
+    };
+    stopwatch.elapsed = stopwatch.elapsed();
+    if stopwatch.running_since.take().is_none() {
+        bail!("Stopwatch \"{}\" isn't running", name);
+    }
+    stopwatch.save(name)?;
+    Ok(stopwatch.elapsed)
+}
+
+// Public API for reading stopwatches as (name, time counted, is running), sorted by name
+pub fn get_stopwatches() -> Vec<(String, Duration, bool)> {
+    let stopwatches = STOPWATCHES.lock().unwrap();
+    let mut stopwatches: Vec<(String, Duration, bool)> = stopwatches
+        .iter()
+        .map(|(name, stopwatch)| {
+            (
+                name.clone(),
+                stopwatch.elapsed(),
+                stopwatch.running_since.is_some(),
+            )
+        })
+        .collect();
+    stopwatches.sort_by(|a, b| a.0.cmp(&b.0));
+    stopwatches
+}
+
+// Public API for removing a stopwatch
+pub fn reset_stopwatch(name: &str) -> Result<(), anyhow::Error> {
+    if STOPWATCHES.lock().unwrap().remove(name).is_none() {
+        bail!("Stopwatch \"{}\" not found", name);
+    }
+    timer_store::delete_stopwatch(name)
+}
+
+/// How a timer's alarm rings when it goes off.
+#[derive(Clone, Debug)]
+pub struct AlarmSettings {
+    /// The name of a sound in the sound theme, like "failed", or a path to an audio file.
+    /// The alarm sound is used if this is None.
+    pub sound: Option<String>,
+    /// How loud the alarm rings, from 0.0 to 1.0.
+    pub volume: f32,
+    /// Whether the alarm starts quiet and gets louder the longer it rings.
+    pub escalate: bool,
+    /// How long the alarm rings before it stops by itself. Zero rings until it's stopped.
+    pub ring_duration: Duration,
+    /// How long the alarm waits to ring again after it stops by itself.
+    pub re_ring_interval: Duration,
+    /// How many more times the alarm rings again after it stops by itself.
+    pub re_rings: u32,
+    /// How long before the timer goes off to announce how much time is left, like 5 minutes before.
+    /// Each one is removed once it's announced.
+    pub announce_before: Vec<Duration>,
+}
+
+impl Default for AlarmSettings {
+    fn default() -> Self {
+        AlarmSettings {
+            sound: None,
+            volume: 1.0,
+            escalate: false,
+            ring_duration: Duration::from_secs(2 * 60),
+            re_ring_interval: Duration::from_secs(5 * 60),
+            re_rings: 3,
+            announce_before: Vec::new(),
+        }
+    }
+}
+
+impl AlarmSettings {
+    /// The audio file the alarm plays. Falls back to `default` if the sound can't be found.
+    fn audio_file(&self, default: &Path) -> PathBuf {
+        let Some(sound) = &self.sound else {
+            return default.to_path_buf();
+        };
+
+        let path = match Sound::from_name(sound) {
+            Some(sound) => SOUND_THEME
+                .get()
+                .and_then(|theme| theme.path(sound))
+                .map(Path::to_path_buf),
+            None => Some(PathBuf::from(sound)),
+        };
+        match path {
+            Some(path) if path.is_file() => path,
+            _ => {
+                warn!(
+                    "Alarm sound \"{}\" not found. Using the default alarm sound.",
+                    sound
+                );
+                default.to_path_buf()
+            }
+        }
+    }
+
+    /// How loud the alarm should be after ringing for `elapsed`.
+    fn volume_after(&self, elapsed: Duration) -> f32 {
+        if !self.escalate {
+            return self.volume;
+        }
+        let progress = (elapsed.as_secs_f32() / ESCALATION_TIME.as_secs_f32()).min(1.0);
+        self.volume * (ESCALATION_START_VOLUME + (1.0 - ESCALATION_START_VOLUME) * progress)
+    }
+}
+
+/// Something that happened to a timer, sent from AudibleTimers.
+#[derive(Clone, Debug)]
+pub enum TimerEvent {
+    /// The timer went off and its alarm is ringing.
+    Expired(Timer),
+    /// The timer goes off in `time_left`, and asked for that to be announced.
+    Countdown { timer: Timer, time_left: Duration },
+}
+
+#[derive(Clone, Debug)]
 pub struct Timer {
     pub id: u64,
     pub description: String,
     pub timestamp: DateTime<Local>,
+    pub alarm: AlarmSettings,
 }
 
+#[derive(Clone)]
 pub struct AudibleTimers {
     audio_stop_tx: flume::Sender<()>,
+    // The expired timers whose alarm is currently ringing.
+    ringing: Arc<Mutex<Vec<Timer>>>,
+    // Notified whenever alarms stop ringing.
+    silenced: Arc<Condvar>,
+    // Told whenever the alarm starts or stops ringing.
+    state_subscribers: Arc<Mutex<Vec<flume::Sender<bool>>>>,
 }
 
 impl AudibleTimers {
-    pub fn new(audio_file: PathBuf) -> Result<(Self, flume::Receiver<Timer>), anyhow::Error> {
+    pub fn new(audio_file: PathBuf) -> Result<(Self, flume::Receiver<TimerEvent>), anyhow::Error> {
         let (audio_stop_tx, audio_stop_rx) = flume::unbounded();
-        let (expired_timers_tx, expired_timers_rx): (flume::Sender<Timer>, flume::Receiver<Timer>) =
-            flume::unbounded();
+        let (timer_events_tx, timer_events_rx): (
+            flume::Sender<TimerEvent>,
+            flume::Receiver<TimerEvent>,
+        ) = flume::unbounded();
+        let ringing: Arc<Mutex<Vec<Timer>>> = Arc::new(Mutex::new(Vec::new()));
+        let silenced = Arc::new(Condvar::new());
 
-        thread::spawn(move || {
-            let (_stream, stream_handle) = rodio::OutputStream::try_default().unwrap();
-            let sink = rodio::Sink::try_new(&stream_handle).unwrap();
+        let state_subscribers: Arc<Mutex<Vec<flume::Sender<bool>>>> =
+            Arc::new(Mutex::new(Vec::new()));
 
+        let thread_ringing = ringing.clone();
+        let thread_silenced = silenced.clone();
+        let thread_state_subscribers = state_subscribers.clone();
+        let emit_state = move |ringing: bool| {
+            thread_state_subscribers
+                .lock()
+                .unwrap()
+                .retain(|tx| tx.send(ringing).is_ok());
+        };
+        thread::spawn(move || {
             let mut timer_error_was_logged = false;
 
             loop {
                 // Clear any pending stop signals
                 while audio_stop_rx.try_recv().is_ok() {}
 
+                match take_due_countdowns() {
+                    Ok(countdowns) => {
+                        for (timer, time_left) in countdowns {
+                            info!(
+                                "Timer {} goes off in {}",
+                                timer.id,
+                                humantime::format_duration(Duration::from_secs(
+                                    time_left.as_secs()
+                                ))
+                            );
+                            if let Err(e) =
+                                timer_events_tx.send(TimerEvent::Countdown { timer, time_left })
+                            {
+                                warn!("Failed to send timer countdown to main thread: {}", e);
+                            }
+                        }
+                    }
+                    Err(e) => warn!("Error checking timer countdowns: {}", e),
+                }
+
                 let expired_timers = match check_timers() {
                     Ok(timers) => {
                         timer_error_was_logged = false;
@@ -164,44 +458,59 @@ impl AudibleTimers {
                 };
 
                 if !expired_timers.is_empty() {
-                    for (id, description, timestamp) in &expired_timers {
+                    for timer in &expired_timers {
                         info!(
                             "Timer expired (ID: {}): description: \"{}\", time: {}",
-                            id,
-                            description,
-                            &timestamp.to_rfc3339()
+                            timer.id,
+                            timer.description,
+                            &timer.timestamp.to_rfc3339()
                         );
                     }
 
                     // send expired timers to the main thread
-                    for (id, description, timestamp) in expired_timers {
-                        let timer = Timer {
-                            id,
-                            description,
-                            timestamp,
-                        };
-                        if let Err(e) = expired_timers_tx.send(timer) {
+                    for timer in expired_timers {
+                        thread_ringing.lock().unwrap().push(timer.clone());
+                        if let Err(e) = timer_events_tx.send(TimerEvent::Expired(timer)) {
                             warn!("Failed to send expired timer to main thread: {}", e);
                         }
                     }
 
+                    let ring_start = std::time::Instant::now();
+                    // The AI voice is paused until the alarm stops ringing.
+                    let preemption = audio::preempt(Channel::Alerts);
+                    emit_state(true);
                     'alarm_loop: loop {
-                        sink.stop(); // Clear any previous sound
-                        let file = match std::fs::File::open(&audio_file) {
+                        // Ring the way the first timer that's still ringing wants to.
+                        let alarm = match thread_ringing.lock().unwrap().first() {
+                            Some(timer) => timer.alarm.clone(),
+                            None => AlarmSettings::default(),
+                        };
+                        let file = match std::fs::File::open(alarm.audio_file(&audio_file)) {
                             Ok(f) => f,
                             Err(e) => {
                                 warn!("Failed to open audio file: {}", e);
                                 break 'alarm_loop;
                             }
                         };
-                        let source = rodio::Decoder::new(BufReader::new(file)).unwrap();
-                        sink.append(source);
+                        let source = match rodio::Decoder::new(BufReader::new(file)) {
+                            Ok(source) => source,
+                            Err(e) => {
+                                warn!("Failed to decode audio file: {}", e);
+                                break 'alarm_loop;
+                            }
+                        };
+                        // Each ring gets a new sink, so it plays on the alerts channel's current output device.
+                        // The previous ring's sink is stopped when it's dropped.
+                        let sink = audio::new_sink(Channel::Alerts);
+                        sink.append(source.amplify(alarm.volume_after(ring_start.elapsed())));
 
                         // Poll for stop signal or end of sound
                         loop {
                             if audio_stop_rx.try_recv().is_ok() {
                                 // Stop immediately and break out of the entire alarm loop
                                 sink.stop();
+                                thread_ringing.lock().unwrap().clear();
+                                thread_silenced.notify_all();
                                 break 'alarm_loop;
                             }
 
@@ -210,20 +519,221 @@ impl AudibleTimers {
                                 break;
                             }
 
+                            if !alarm.ring_duration.is_zero()
+                                && ring_start.elapsed() >= alarm.ring_duration
+                            {
+                                sink.stop();
+                                // They're rescheduled before they stop ringing, so they're never
+                                // seen as neither ringing nor set.
+                                let timed_out: Vec<Timer> = thread_ringing.lock().unwrap().clone();
+                                for mut timer in timed_out {
+                                    if timer.alarm.re_rings == 0 {
+                                        info!(
+                                            "Alarm for timer {} stopped ringing for good",
+                                            timer.id
+                                        );
+                                        continue;
+                                    }
+                                    info!(
+                                        "Alarm for timer {} stopped ringing. It rings again in {}",
+                                        timer.id,
+                                        humantime::format_duration(timer.alarm.re_ring_interval)
+                                    );
+                                    timer.alarm.re_rings -= 1;
+                                    let Some(re_ring_time) =
+                                        chrono::Duration::from_std(timer.alarm.re_ring_interval)
+                                            .ok()
+                                            .and_then(|interval| {
+                                                Local::now().checked_add_signed(interval)
+                                            })
+                                    else {
+                                        warn!(
+                                            "Alarm for timer {} can't ring again in {}",
+                                            timer.id,
+                                            humantime::format_duration(
+                                                timer.alarm.re_ring_interval
+                                            )
+                                        );
+                                        continue;
+                                    };
+                                    if let Err(e) = reschedule_timer(timer, re_ring_time) {
+                                        warn!("Failed to reschedule timer: {}", e);
+                                    }
+                                }
+                                thread_ringing.lock().unwrap().clear();
+                                thread_silenced.notify_all();
+                                break 'alarm_loop;
+                            }
+
                             thread::sleep(std::time::Duration::from_millis(100));
                         }
                     }
+                    // The alarm can also stop because its sound failed to play.
+                    thread_ringing.lock().unwrap().clear();
+                    thread_silenced.notify_all();
+                    drop(preemption);
+                    emit_state(false);
                 }
 
-                // Sleep a bit before re-checking
-                thread::sleep(std::time::Duration::from_secs(1));
+                // Sleep until the next timer goes off, or until the timers change
+                if TIMERS_CHANGED
+                    .1
+                    .recv_timeout(time_until_next_timer())
+                    .is_ok()
+                {
+                    while TIMERS_CHANGED.1.try_recv().is_ok() {}
+                }
             }
         });
 
-        Ok((AudibleTimers { audio_stop_tx }, expired_timers_rx))
+        Ok((
+            AudibleTimers {
+                audio_stop_tx,
+                ringing,
+                silenced,
+                state_subscribers,
+            },
+            timer_events_rx,
+        ))
+    }
+
+    /// Timers whose alarms never ring, for answering a single question. Timers that go off are left
+    /// for the assistant that's running, or the next launch, to ring.
+    pub fn silent() -> (Self, flume::Receiver<TimerEvent>) {
+        let (audio_stop_tx, _) = flume::unbounded();
+        let (_, timer_events_rx) = flume::unbounded();
+        (
+            AudibleTimers {
+                audio_stop_tx,
+                ringing: Arc::new(Mutex::new(Vec::new())),
+                silenced: Arc::new(Condvar::new()),
+                state_subscribers: Arc::new(Mutex::new(Vec::new())),
+            },
+            timer_events_rx,
+        )
     }
 
     pub fn stop_alarm(&self) {
-        self.audio_stop_tx.send(()).unwrap();
+        // There's nothing to stop if the alarms don't ring.
+        let _ = self.audio_stop_tx.send(());
+    }
+
+    /// Whether an alarm is ringing right now.
+    pub fn is_alarm_ringing(&self) -> bool {
+        !self.ringing.lock().unwrap().is_empty()
+    }
+
+    /// Returns a channel that receives true when the alarm starts ringing, and false when it stops.
+    pub fn subscribe(&self) -> flume::Receiver<bool> {
+        let (tx, rx) = flume::unbounded();
+        self.state_subscribers.lock().unwrap().push(tx);
+        rx
+    }
+
+    /// Silences the alarm of one ringing timer, or of all of them if `id` is None.
+    /// The alarm keeps ringing for any other timers. Returns the dismissed timers.
+    pub fn dismiss(&self, id: Option<u64>) -> Vec<Timer> {
+        let mut ringing = self.ringing.lock().unwrap();
+        let (dismissed, still_ringing): (Vec<Timer>, Vec<Timer>) = ringing
+            .drain(..)
+            .partition(|timer| id.is_none_or(|id| timer.id == id));
+        *ringing = still_ringing;
+
+        if ringing.is_empty() && !dismissed.is_empty() {
+            self.stop_alarm();
+        }
+        self.silenced.notify_all();
+        dismissed
+    }
+
+    /// Waits for up to `timeout` for the timer `id` to be dismissed, so that its alarm isn't ringing
+    /// and it isn't snoozed or set to ring again. Returns whether it was.
+    pub fn wait_until_dismissed(&self, id: u64, timeout: Duration) -> bool {
+        let ringing = self.ringing.lock().unwrap();
+        let (_ringing, wait) = self
+            .silenced
+            .wait_timeout_while(ringing, timeout, |ringing| {
+                ringing.iter().any(|timer| timer.id == id)
+                    || TIMERS.read().unwrap().iter().any(|timer| timer.id == id)
+            })
+            .unwrap();
+        !wait.timed_out()
+    }
+
+    /// Silences the alarm of one ringing timer, or of all of them if `id` is None,
+    /// and sets each of them to go off again after `duration`, keeping their IDs.
+    /// Returns the snoozed timers.
+    pub fn snooze(&self, id: Option<u64>, duration: Duration) -> Result<Vec<Timer>, anyhow::Error> {
+        let Some(timestamp) =
+            Local::now().checked_add_signed(chrono::Duration::from_std(duration)?)
+        else {
+            bail!("Can't snooze for {}", humantime::format_duration(duration));
+        };
+        // Set again before they're dismissed, so they're never seen as neither ringing nor set.
+        let snoozing: Vec<Timer> = self
+            .ringing
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|timer| id.is_none_or(|id| timer.id == id))
+            .cloned()
+            .collect();
+        for timer in &snoozing {
+            reschedule_timer(timer.clone(), timestamp)?;
+        }
+        Ok(self.dismiss(id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    /// Waits up to a few seconds for `done`, for what other threads do soon after an event.
+    fn wait_for(done: impl Fn() -> bool) -> bool {
+        let start = Instant::now();
+        while !done() {
+            if start.elapsed() > Duration::from_secs(5) {
+                return false;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        true
+    }
+
+    #[test]
+    fn an_alarm_rings_over_speech_until_its_stopped() {
+        let _preemptions = audio::TEST_LOCK
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        let alarm_file = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/failed.mp3");
+        let (timers, events) = AudibleTimers::new(alarm_file).unwrap();
+        let speech = audio::new_sink(Channel::Speech);
+
+        let id = set_timer(
+            "tea".to_string(),
+            Local::now() + chrono::Duration::milliseconds(200),
+            AlarmSettings {
+                announce_before: vec![Duration::from_millis(100)],
+                ..AlarmSettings::default()
+            },
+        )
+        .unwrap();
+        match events.recv_timeout(Duration::from_secs(5)).unwrap() {
+            TimerEvent::Countdown { timer, .. } => assert_eq!(timer.id, id),
+            event => panic!("Expected a countdown, got {:?}", event),
+        }
+        match events.recv_timeout(Duration::from_secs(5)).unwrap() {
+            TimerEvent::Expired(timer) => assert_eq!(timer.id, id),
+            event => panic!("Expected the timer to go off, got {:?}", event),
+        }
+        assert!(timers.is_alarm_ringing());
+        assert!(wait_for(|| speech.is_paused()));
+
+        timers.stop_alarm();
+        assert!(timers.wait_until_dismissed(id, Duration::from_secs(5)));
+        assert!(!timers.is_alarm_ringing());
+        assert!(wait_for(|| !speech.is_paused()));
     }
 }
@@ -0,0 +1,105 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::LazyLock,
+    time::SystemTime,
+};
+use tracing::debug;
+
+use crate::CACHE_DIR;
+
+// The maximum number of speech segments kept on disk.
+// When this is exceeded, the least recently played segments are deleted.
+const MAX_CACHED_SEGMENTS: usize = 500;
+
+// Segments are stored as mp3 straight from the API, or wav once their speed has been adjusted.
+const CACHED_EXTENSIONS: [&str; 2] = ["mp3", "wav"];
+
+static TTS_CACHE_DIR: LazyLock<PathBuf> = LazyLock::new(|| CACHE_DIR.join("tts_cache"));
+
+/// Returns a key that identifies a speech segment by its text and everything that affects how it sounds.
+///
+/// FNV-1a is used instead of std's hasher because the key must stay the same
+/// between builds for cached files to be found again.
+fn cache_key(text: &str, voice: &str, model: &str, instructions: Option<&str>, speed: f32) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    let key_source = format!(
+        "{}|{}|{}|{}|{}",
+        voice,
+        model,
+        instructions.unwrap_or_default(),
+        speed,
+        text
+    );
+    for byte in key_source.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{:016x}", hash)
+}
+
+/// Returns the path of a previously generated speech segment, if there is one.
+/// Marks the segment as recently used.
+pub fn get(
+    text: &str,
+    voice: &str,
+    model: &str,
+    instructions: Option<&str>,
+    speed: f32,
+) -> Option<PathBuf> {
+    let key = cache_key(text, voice, model, instructions, speed);
+    let path = CACHED_EXTENSIONS
+        .iter()
+        .map(|ext| TTS_CACHE_DIR.join(format!("{}.{}", key, ext)))
+        .find(|path| path.is_file())?;
+
+    // Bump the modified time so this segment is evicted last.
+    if let Ok(file) = fs::File::options().write(true).open(&path) {
+        let _ = file.set_modified(SystemTime::now());
+    }
+
+    Some(path)
+}
+
+/// Copies a generated speech segment into the cache,
+/// evicting the least recently used segments if the cache is full.
+pub fn insert(
+    text: &str,
+    voice: &str,
+    model: &str,
+    instructions: Option<&str>,
+    speed: f32,
+    audio: &Path,
+) -> Result<(), anyhow::Error> {
+    let key = cache_key(text, voice, model, instructions, speed);
+    let extension = audio.extension().unwrap_or_default().to_string_lossy();
+    let path = TTS_CACHE_DIR.join(format!("{}.{}", key, extension));
+
+    fs::create_dir_all(&*TTS_CACHE_DIR)?;
+    fs::copy(audio, path)?;
+    evict_least_recently_used()?;
+    Ok(())
+}
+
+fn evict_least_recently_used() -> Result<(), anyhow::Error> {
+    let mut entries: Vec<(SystemTime, PathBuf)> = fs::read_dir(&*TTS_CACHE_DIR)?
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let modified = entry.metadata().ok()?.modified().ok()?;
+            Some((modified, entry.path()))
+        })
+        .collect();
+
+    if entries.len() <= MAX_CACHED_SEGMENTS {
+        return Ok(());
+    }
+
+    entries.sort_by_key(|(modified, _)| *modified);
+    let excess = entries.len() - MAX_CACHED_SEGMENTS;
+    for (_, path) in entries.into_iter().take(excess) {
+        debug!("Evicting cached speech segment: {}", path.display());
+        fs::remove_file(path)?;
+    }
+
+    Ok(())
+}
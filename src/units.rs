@@ -0,0 +1,300 @@
+//! Converting between units for the convert_units tool. Units are found by their symbol or name,
+//! like "km", "kilometers" or "°F", and only convert to units that measure the same thing.
+
+use anyhow::bail;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Length,
+    Mass,
+    Volume,
+    Area,
+    Time,
+    Speed,
+    Data,
+    Energy,
+    Power,
+    Pressure,
+    Temperature,
+}
+
+impl Kind {
+    fn name(self) -> &'static str {
+        match self {
+            Kind::Length => "length",
+            Kind::Mass => "mass",
+            Kind::Volume => "volume",
+            Kind::Area => "area",
+            Kind::Time => "time",
+            Kind::Speed => "speed",
+            Kind::Data => "data",
+            Kind::Energy => "energy",
+            Kind::Power => "power",
+            Kind::Pressure => "pressure",
+            Kind::Temperature => "temperature",
+        }
+    }
+}
+
+struct Unit {
+    kind: Kind,
+    /// How many of the kind's base unit one of this is, like 1000 for kilometers.
+    /// Temperatures are converted by `to_kelvin` and `from_kelvin` instead.
+    factor: f64,
+    /// The symbol first, then other ways of writing it.
+    names: &'static [&'static str],
+}
+
+const fn unit(kind: Kind, factor: f64, names: &'static [&'static str]) -> Unit {
+    Unit {
+        kind,
+        factor,
+        names,
+    }
+}
+
+/// Every unit, with meters, kilograms, liters, square meters, seconds, meters per second, bytes,
+/// joules, watts, pascals and kelvin as the base units.
+#[rustfmt::skip]
+const UNITS: &[Unit] = &[
+    unit(Kind::Length, 1.0, &["m", "meter", "meters", "metre", "metres"]),
+    unit(Kind::Length, 1000.0, &["km", "kilometer", "kilometers", "kilometre", "kilometres"]),
+    unit(Kind::Length, 0.01, &["cm", "centimeter", "centimeters", "centimetre", "centimetres"]),
+    unit(Kind::Length, 0.001, &["mm", "millimeter", "millimeters", "millimetre", "millimetres"]),
+    unit(Kind::Length, 1e-6, &["µm", "um", "micrometer", "micrometers", "micron", "microns"]),
+    unit(Kind::Length, 1e-9, &["nm", "nanometer", "nanometers"]),
+    unit(Kind::Length, 0.0254, &["in", "inch", "inches", "\""]),
+    unit(Kind::Length, 0.3048, &["ft", "foot", "feet", "'"]),
+    unit(Kind::Length, 0.9144, &["yd", "yard", "yards"]),
+    unit(Kind::Length, 1609.344, &["mi", "mile", "miles"]),
+    unit(Kind::Length, 1852.0, &["nmi", "nautical mile", "nautical miles"]),
+    unit(Kind::Length, 9.4607e15, &["ly", "light year", "light years", "lightyear", "lightyears"]),
+    unit(Kind::Length, 1.495978707e11, &["au", "astronomical unit", "astronomical units"]),
+
+    unit(Kind::Mass, 1.0, &["kg", "kilogram", "kilograms", "kilo", "kilos"]),
+    unit(Kind::Mass, 0.001, &["g", "gram", "grams"]),
+    unit(Kind::Mass, 1e-6, &["mg", "milligram", "milligrams"]),
+    unit(Kind::Mass, 1e-9, &["µg", "ug", "mcg", "microgram", "micrograms"]),
+    unit(Kind::Mass, 1000.0, &["t", "tonne", "tonnes", "metric ton", "metric tons"]),
+    unit(Kind::Mass, 0.45359237, &["lb", "lbs", "pound", "pounds"]),
+    unit(Kind::Mass, 0.028349523125, &["oz", "ounce", "ounces"]),
+    unit(Kind::Mass, 6.35029318, &["st", "stone", "stones"]),
+    unit(Kind::Mass, 907.18474, &["ton", "tons", "short ton", "short tons"]),
+    unit(Kind::Mass, 1016.0469088, &["long ton", "long tons"]),
+
+    unit(Kind::Volume, 1.0, &["l", "liter", "liters", "litre", "litres"]),
+    unit(Kind::Volume, 0.001, &["ml", "milliliter", "milliliters", "millilitre", "millilitres"]),
+    unit(Kind::Volume, 0.01, &["cl", "centiliter", "centiliters", "centilitre", "centilitres"]),
+    unit(Kind::Volume, 0.1, &["dl", "deciliter", "deciliters", "decilitre", "decilitres"]),
+    unit(Kind::Volume, 1000.0, &["m3", "m³", "cubic meter", "cubic meters", "cubic metre", "cubic metres"]),
+    unit(Kind::Volume, 0.001, &["cm3", "cm³", "cc", "cubic centimeter", "cubic centimeters"]),
+    unit(Kind::Volume, 0.016387064, &["in3", "in³", "cubic inch", "cubic inches"]),
+    unit(Kind::Volume, 28.316846592, &["ft3", "ft³", "cubic foot", "cubic feet"]),
+    unit(Kind::Volume, 3.785411784, &["gal", "gallon", "gallons", "us gallon", "us gallons"]),
+    unit(Kind::Volume, 4.54609, &["imperial gallon", "imperial gallons", "uk gallon", "uk gallons"]),
+    unit(Kind::Volume, 0.946352946, &["qt", "quart", "quarts"]),
+    unit(Kind::Volume, 0.473176473, &["pt", "pint", "pints", "us pint", "us pints"]),
+    unit(Kind::Volume, 0.56826125, &["imperial pint", "imperial pints", "uk pint", "uk pints"]),
+    unit(Kind::Volume, 0.2365882365, &["cup", "cups"]),
+    unit(Kind::Volume, 0.0295735295625, &["fl oz", "floz", "fluid ounce", "fluid ounces"]),
+    unit(Kind::Volume, 0.01478676478125, &["tbsp", "tablespoon", "tablespoons"]),
+    unit(Kind::Volume, 0.00492892159375, &["tsp", "teaspoon", "teaspoons"]),
+
+    unit(Kind::Area, 1.0, &["m2", "m²", "square meter", "square meters", "square metre", "square metres", "sq m"]),
+    unit(Kind::Area, 1e6, &["km2", "km²", "square kilometer", "square kilometers", "square kilometre", "square kilometres", "sq km"]),
+    unit(Kind::Area, 1e-4, &["cm2", "cm²", "square centimeter", "square centimeters", "sq cm"]),
+    unit(Kind::Area, 1e-6, &["mm2", "mm²", "square millimeter", "square millimeters", "sq mm"]),
+    unit(Kind::Area, 0.09290304, &["ft2", "ft²", "sq ft", "square foot", "square feet"]),
+    unit(Kind::Area, 0.00064516, &["in2", "in²", "sq in", "square inch", "square inches"]),
+    unit(Kind::Area, 0.83612736, &["yd2", "yd²", "sq yd", "square yard", "square yards"]),
+    unit(Kind::Area, 2589988.110336, &["mi2", "mi²", "sq mi", "square mile", "square miles"]),
+    unit(Kind::Area, 4046.8564224, &["ac", "acre", "acres"]),
+    unit(Kind::Area, 10000.0, &["ha", "hectare", "hectares"]),
+
+    unit(Kind::Time, 1.0, &["s", "sec", "secs", "second", "seconds"]),
+    unit(Kind::Time, 0.001, &["ms", "millisecond", "milliseconds"]),
+    unit(Kind::Time, 1e-6, &["µs", "us", "microsecond", "microseconds"]),
+    unit(Kind::Time, 1e-9, &["ns", "nanosecond", "nanoseconds"]),
+    unit(Kind::Time, 60.0, &["min", "mins", "minute", "minutes"]),
+    unit(Kind::Time, 3600.0, &["h", "hr", "hrs", "hour", "hours"]),
+    unit(Kind::Time, 86400.0, &["d", "day", "days"]),
+    unit(Kind::Time, 604800.0, &["wk", "week", "weeks"]),
+    unit(Kind::Time, 2629746.0, &["mo", "month", "months"]),
+    unit(Kind::Time, 31556952.0, &["yr", "year", "years"]),
+
+    unit(Kind::Speed, 1.0, &["m/s", "mps", "meters per second", "metres per second"]),
+    unit(Kind::Speed, 1.0 / 3.6, &["km/h", "kph", "kmh", "kilometers per hour", "kilometres per hour"]),
+    unit(Kind::Speed, 0.44704, &["mph", "mi/h", "miles per hour"]),
+    unit(Kind::Speed, 0.3048, &["ft/s", "fps", "feet per second"]),
+    unit(Kind::Speed, 1852.0 / 3600.0, &["kn", "kt", "knot", "knots"]),
+
+    unit(Kind::Data, 1.0, &["B", "byte", "bytes"]),
+    unit(Kind::Data, 0.125, &["bit", "bits"]),
+    unit(Kind::Data, 1e3, &["KB", "kB", "kilobyte", "kilobytes"]),
+    unit(Kind::Data, 1e6, &["MB", "megabyte", "megabytes"]),
+    unit(Kind::Data, 1e9, &["GB", "gigabyte", "gigabytes"]),
+    unit(Kind::Data, 1e12, &["TB", "terabyte", "terabytes"]),
+    unit(Kind::Data, 1e15, &["PB", "petabyte", "petabytes"]),
+    unit(Kind::Data, 1024.0, &["KiB", "kibibyte", "kibibytes"]),
+    unit(Kind::Data, 1048576.0, &["MiB", "mebibyte", "mebibytes"]),
+    unit(Kind::Data, 1073741824.0, &["GiB", "gibibyte", "gibibytes"]),
+    unit(Kind::Data, 1099511627776.0, &["TiB", "tebibyte", "tebibytes"]),
+    unit(Kind::Data, 125.0, &["Kb", "kbit", "kilobit", "kilobits"]),
+    unit(Kind::Data, 125000.0, &["Mb", "mbit", "megabit", "megabits"]),
+    unit(Kind::Data, 125000000.0, &["Gb", "gbit", "gigabit", "gigabits"]),
+
+    unit(Kind::Energy, 1.0, &["J", "joule", "joules"]),
+    unit(Kind::Energy, 1000.0, &["kJ", "kilojoule", "kilojoules"]),
+    unit(Kind::Energy, 1e6, &["MJ", "megajoule", "megajoules"]),
+    unit(Kind::Energy, 4.184, &["cal", "calorie", "calories"]),
+    unit(Kind::Energy, 4184.0, &["kcal", "kilocalorie", "kilocalories", "Cal", "food calorie", "food calories"]),
+    unit(Kind::Energy, 3600.0, &["Wh", "watt hour", "watt hours"]),
+    unit(Kind::Energy, 3.6e6, &["kWh", "kilowatt hour", "kilowatt hours"]),
+    unit(Kind::Energy, 1055.05585262, &["BTU", "btu", "british thermal unit", "british thermal units"]),
+    unit(Kind::Energy, 1.602176634e-19, &["eV", "electronvolt", "electronvolts"]),
+
+    unit(Kind::Power, 1.0, &["W", "watt", "watts"]),
+    unit(Kind::Power, 1000.0, &["kW", "kilowatt", "kilowatts"]),
+    unit(Kind::Power, 1e6, &["MW", "megawatt", "megawatts"]),
+    unit(Kind::Power, 745.69987158227, &["hp", "horsepower"]),
+
+    unit(Kind::Pressure, 1.0, &["Pa", "pascal", "pascals"]),
+    unit(Kind::Pressure, 1000.0, &["kPa", "kilopascal", "kilopascals"]),
+    unit(Kind::Pressure, 100.0, &["hPa", "hectopascal", "hectopascals", "mbar", "millibar", "millibars"]),
+    unit(Kind::Pressure, 100000.0, &["bar", "bars"]),
+    unit(Kind::Pressure, 6894.757293168, &["psi", "pounds per square inch"]),
+    unit(Kind::Pressure, 101325.0, &["atm", "atmosphere", "atmospheres"]),
+    unit(Kind::Pressure, 133.322387415, &["mmHg", "millimeters of mercury"]),
+    unit(Kind::Pressure, 3386.389, &["inHg", "inches of mercury"]),
+
+    unit(Kind::Temperature, 1.0, &["K", "kelvin", "kelvins"]),
+    unit(Kind::Temperature, 1.0, &["°C", "C", "celsius", "centigrade", "degrees celsius"]),
+    unit(Kind::Temperature, 1.0, &["°F", "F", "fahrenheit", "degrees fahrenheit"]),
+];
+
+/// Finds a unit by how it's written. Symbols like "Mb" and "MB" are told apart by case
+/// where that matters, and everything else matches whatever the case.
+fn find(name: &str) -> Option<&'static Unit> {
+    let name = name.trim();
+    let name = name.strip_prefix("degrees ").unwrap_or(name);
+    UNITS
+        .iter()
+        .find(|unit| unit.names.contains(&name))
+        .or_else(|| {
+            let lowercase = name.to_lowercase();
+            UNITS.iter().find(|unit| {
+                unit.names
+                    .iter()
+                    .any(|unit_name| unit_name.to_lowercase() == lowercase)
+            })
+        })
+        .or_else(|| {
+            // Plurals that aren't listed, like "kgs".
+            let singular = name.strip_suffix('s')?;
+            find(singular)
+        })
+}
+
+fn to_kelvin(unit: &Unit, value: f64) -> f64 {
+    match unit.names[0] {
+        "°C" => value + 273.15,
+        "°F" => (value - 32.0) * 5.0 / 9.0 + 273.15,
+        _ => value,
+    }
+}
+
+fn from_kelvin(unit: &Unit, kelvin: f64) -> f64 {
+    match unit.names[0] {
+        "°C" => kelvin - 273.15,
+        "°F" => (kelvin - 273.15) * 9.0 / 5.0 + 32.0,
+        _ => kelvin,
+    }
+}
+
+/// Converts `value` from one unit to another, and returns the result and the units' symbols.
+pub fn convert(
+    value: f64,
+    from: &str,
+    to: &str,
+) -> Result<(f64, &'static str, &'static str), anyhow::Error> {
+    let Some(from_unit) = find(from) else {
+        bail!("\"{}\" isn't a unit that can be converted", from);
+    };
+    let Some(to_unit) = find(to) else {
+        bail!("\"{}\" isn't a unit that can be converted", to);
+    };
+    if from_unit.kind != to_unit.kind {
+        bail!(
+            "Can't convert {} to {}, since one is a {} and the other is a {}",
+            from,
+            to,
+            from_unit.kind.name(),
+            to_unit.kind.name()
+        );
+    }
+    let result = match from_unit.kind {
+        Kind::Temperature => from_kelvin(to_unit, to_kelvin(from_unit, value)),
+        _ => value * from_unit.factor / to_unit.factor,
+    };
+    Ok((result, from_unit.names[0], to_unit.names[0]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(value: f64, from: &str, to: &str, expected: f64) {
+        let (result, _, _) = convert(value, from, to).unwrap();
+        assert!(
+            (result - expected).abs() <= expected.abs() * 1e-9,
+            "{} {} gave {} {}, not {}",
+            value,
+            from,
+            result,
+            to,
+            expected
+        );
+    }
+
+    #[test]
+    fn conversions_are_right() {
+        check(1.0, "mi", "km", 1.609344);
+        check(100.0, "°C", "°F", 212.0);
+        check(-40.0, "fahrenheit", "celsius", -40.0);
+        check(0.0, "C", "K", 273.15);
+        check(1.0, "GiB", "MiB", 1024.0);
+        check(8.0, "bits", "bytes", 1.0);
+        check(1.0, "kWh", "J", 3.6e6);
+    }
+
+    #[test]
+    fn every_unit_round_trips() {
+        for unit in UNITS {
+            let symbol = unit.names[0];
+            let base = UNITS
+                .iter()
+                .find(|base| base.kind == unit.kind && base.factor == 1.0)
+                .unwrap()
+                .names[0];
+            let (there, _, _) = convert(42.5, symbol, base).unwrap();
+            check(there, base, symbol, 42.5);
+            for name in unit.names {
+                let (_, found, _) = convert(1.0, name, symbol).unwrap();
+                assert_eq!(found, symbol, "\"{}\" found {}", name, found);
+            }
+        }
+    }
+
+    #[test]
+    fn symbols_are_told_apart_by_case() {
+        assert_eq!(convert(1.0, "Mb", "B").unwrap().1, "Mb");
+        assert_eq!(convert(1.0, "MB", "B").unwrap().1, "MB");
+        assert_eq!(convert(1.0, "kgs", "g").unwrap().1, "kg");
+    }
+
+    #[test]
+    fn different_kinds_dont_convert() {
+        assert!(convert(1.0, "kg", "m").is_err());
+        assert!(convert(1.0, "furlong", "m").is_err());
+    }
+}
@@ -0,0 +1,164 @@
+//! Current weather and a short forecast from Open-Meteo, which doesn't need an API key.
+
+use anyhow::Context;
+use chrono::NaiveDate;
+use serde::Deserialize;
+use std::time::Duration;
+
+use crate::location::Location;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const FORECAST_DAYS: usize = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Units {
+    Metric,
+    Imperial,
+}
+
+impl Units {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "metric" | "celsius" => Some(Units::Metric),
+            "imperial" | "fahrenheit" => Some(Units::Imperial),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Forecast {
+    current: Current,
+    daily: Daily,
+}
+
+#[derive(Deserialize)]
+struct Current {
+    temperature_2m: f64,
+    apparent_temperature: f64,
+    relative_humidity_2m: f64,
+    precipitation: f64,
+    weather_code: u8,
+    wind_speed_10m: f64,
+}
+
+#[derive(Deserialize)]
+struct Daily {
+    time: Vec<NaiveDate>,
+    weather_code: Vec<Option<u8>>,
+    temperature_2m_max: Vec<Option<f64>>,
+    temperature_2m_min: Vec<Option<f64>>,
+    precipitation_probability_max: Vec<Option<u8>>,
+}
+
+/// Describes the weather now and over the next few days at `location`, for the AI.
+pub fn describe(location: &Location, units: Units) -> Result<String, anyhow::Error> {
+    let (temperature_unit, wind_speed_unit, precipitation_unit) = match units {
+        Units::Metric => ("celsius", "kmh", "mm"),
+        Units::Imperial => ("fahrenheit", "mph", "inch"),
+    };
+    let forecast: Forecast = reqwest::blocking::Client::new()
+        .get("https://api.open-meteo.com/v1/forecast")
+        .query(&[
+            ("latitude", location.latitude.to_string()),
+            ("longitude", location.longitude.to_string()),
+            (
+                "current",
+                "temperature_2m,apparent_temperature,relative_humidity_2m,precipitation,weather_code,wind_speed_10m".to_string(),
+            ),
+            (
+                "daily",
+                "weather_code,temperature_2m_max,temperature_2m_min,precipitation_probability_max".to_string(),
+            ),
+            ("temperature_unit", temperature_unit.to_string()),
+            ("wind_speed_unit", wind_speed_unit.to_string()),
+            ("precipitation_unit", precipitation_unit.to_string()),
+            ("forecast_days", FORECAST_DAYS.to_string()),
+            ("timezone", "auto".to_string()),
+        ])
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .context("Failed to get the weather")?
+        .error_for_status()?
+        .json()?;
+
+    let degrees = match units {
+        Units::Metric => "°C",
+        Units::Imperial => "°F",
+    };
+    let current = &forecast.current;
+    let mut info = format!(
+        "Weather in {}:\nNow: {}, {:.0}{} (feels like {:.0}{}), humidity {:.0}%, wind {:.0} {}, precipitation {} {}\n",
+        location.name,
+        condition(current.weather_code),
+        current.temperature_2m,
+        degrees,
+        current.apparent_temperature,
+        degrees,
+        current.relative_humidity_2m,
+        current.wind_speed_10m,
+        wind_speed_unit.replace("kmh", "km/h"),
+        current.precipitation,
+        precipitation_unit,
+    );
+
+    let daily = &forecast.daily;
+    for (i, date) in daily.time.iter().enumerate() {
+        let day = match i {
+            0 => "Today".to_string(),
+            1 => "Tomorrow".to_string(),
+            _ => date.format("%A").to_string(),
+        };
+        let code = daily.weather_code.get(i).copied().flatten();
+        let high = daily.temperature_2m_max.get(i).copied().flatten();
+        let low = daily.temperature_2m_min.get(i).copied().flatten();
+        let rain = daily
+            .precipitation_probability_max
+            .get(i)
+            .copied()
+            .flatten();
+
+        info.push_str(&format!(
+            "{}: {}",
+            day,
+            code.map_or("unknown conditions", condition)
+        ));
+        if let (Some(high), Some(low)) = (high, low) {
+            info.push_str(&format!(
+                ", high {:.0}{}, low {:.0}{}",
+                high, degrees, low, degrees
+            ));
+        }
+        if let Some(rain) = rain {
+            info.push_str(&format!(", {}% chance of precipitation", rain));
+        }
+        info.push('\n');
+    }
+    Ok(info)
+}
+
+/// Describes a WMO weather code, as used by Open-Meteo.
+fn condition(code: u8) -> &'static str {
+    match code {
+        0 => "clear sky",
+        1 => "mainly clear",
+        2 => "partly cloudy",
+        3 => "overcast",
+        45 | 48 => "fog",
+        51 | 53 | 55 => "drizzle",
+        56 | 57 => "freezing drizzle",
+        61 => "light rain",
+        63 => "rain",
+        65 => "heavy rain",
+        66 | 67 => "freezing rain",
+        71 => "light snow",
+        73 => "snow",
+        75 => "heavy snow",
+        77 => "snow grains",
+        80..=82 => "rain showers",
+        85 | 86 => "snow showers",
+        95 => "thunderstorm",
+        96 | 99 => "thunderstorm with hail",
+        _ => "unknown conditions",
+    }
+}
@@ -0,0 +1,376 @@
+//! Listing, focusing, minimizing and closing the desktop's windows.
+//! Windows uses the Win32 API. Linux asks an EWMH window manager over X11, so on Wayland only
+//! XWayland windows can be seen, and macOS isn't supported.
+
+use anyhow::{bail, Context};
+
+use crate::apps;
+
+/// A top level window.
+#[derive(Debug, Clone)]
+pub struct Window {
+    id: u64,
+    pub title: String,
+    /// The application the window belongs to, where it's known.
+    pub app: Option<String>,
+}
+
+impl Window {
+    /// The window's HWND.
+    #[cfg(windows)]
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+/// The windows on the desktop, as shown in the taskbar.
+pub fn list() -> Result<Vec<Window>, anyhow::Error> {
+    #[cfg(target_os = "linux")]
+    return x11::list();
+
+    #[cfg(windows)]
+    return win32::list();
+
+    #[allow(unreachable_code)]
+    Err(anyhow::anyhow!(
+        "Managing windows isn't supported on this system"
+    ))
+}
+
+/// Finds the window whose title or application best matches `query`, out of those that score at
+/// least `min_score`.
+fn find(query: &str, min_score: u32) -> Result<Window, anyhow::Error> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        bail!("Say which window");
+    }
+    let windows = list()?;
+    let found = windows
+        .iter()
+        .filter_map(|window| {
+            let by_title = apps::score(&window.title, &query);
+            let by_app = window
+                .app
+                .as_deref()
+                .and_then(|app| apps::score(app, &query));
+            Some((by_title.max(by_app)?, window))
+        })
+        .filter(|(score, _)| *score >= min_score)
+        // Windows are listed oldest first, so the newest of equally good matches wins.
+        .max_by_key(|(score, _)| *score)
+        .map(|(_, window)| window.clone());
+    if let Some(window) = found {
+        return Ok(window);
+    }
+    let closest = apps::closest(windows.iter().map(|window| window.title.as_str()), &query);
+    if closest.is_empty() {
+        bail!("No window matching \"{}\" is open", query);
+    }
+    bail!(
+        "No window matching \"{}\" is open. The closest are: {}",
+        query,
+        closest.join(", ")
+    )
+}
+
+/// The window matching `query` with at least `min_score`, or the focused window if there's no query.
+fn target(query: Option<&str>, min_score: u32) -> Result<Window, anyhow::Error> {
+    match query.filter(|query| !query.trim().is_empty()) {
+        Some(query) => find(query, min_score),
+        None => {
+            #[cfg(target_os = "linux")]
+            let active = x11::active()?;
+            #[cfg(windows)]
+            let active = win32::active()?;
+            #[cfg(not(any(target_os = "linux", windows)))]
+            let active: Option<u64> = bail!("Managing windows isn't supported on this system");
+
+            let Some(id) = active else {
+                bail!("No window is focused");
+            };
+            list()?
+                .into_iter()
+                .find(|window| window.id == id)
+                .context("The focused window isn't one that can be managed")
+        }
+    }
+}
+
+/// The focused window.
+pub fn active() -> Result<Window, anyhow::Error> {
+    target(None, 0)
+}
+
+/// Brings the window matching `query` to the front, and returns its title.
+pub fn focus(query: &str) -> Result<String, anyhow::Error> {
+    let window = find(query, 0)?;
+    #[cfg(target_os = "linux")]
+    x11::focus(window.id)?;
+    #[cfg(windows)]
+    win32::focus(window.id)?;
+    Ok(window.title)
+}
+
+/// Minimizes the window matching `query`, or the focused window. Returns its title.
+pub fn minimize(query: Option<&str>) -> Result<String, anyhow::Error> {
+    let window = target(query, 0)?;
+    #[cfg(target_os = "linux")]
+    x11::minimize(window.id)?;
+    #[cfg(windows)]
+    win32::minimize(window.id)?;
+    Ok(window.title)
+}
+
+/// Asks the window matching `query`, or the focused window, to close. Returns its title.
+/// The application can still ask to save first. The query has to be part of the window's title or
+/// application, since closing the wrong window is worse than not finding one.
+pub fn close(query: Option<&str>) -> Result<String, anyhow::Error> {
+    let window = target(query, apps::CONTAINS_SCORE)?;
+    #[cfg(target_os = "linux")]
+    x11::close(window.id)?;
+    #[cfg(windows)]
+    win32::close(window.id)?;
+    Ok(window.title)
+}
+
+#[cfg(target_os = "linux")]
+mod x11 {
+    use anyhow::{bail, Context};
+    use x11rb::{
+        connection::Connection,
+        protocol::xproto::{Atom, AtomEnum, ClientMessageEvent, ConnectionExt, EventMask},
+        rust_connection::RustConnection,
+        CURRENT_TIME,
+    };
+
+    use super::Window;
+
+    /// Says where a request comes from, so the window manager treats it like a taskbar click.
+    const SOURCE_PAGER: u32 = 2;
+    const ICONIC_STATE: u32 = 3;
+
+    struct Display {
+        conn: RustConnection,
+        root: u32,
+    }
+
+    impl Display {
+        fn connect() -> Result<Self, anyhow::Error> {
+            let (conn, screen_num) = x11rb::connect(None).context(
+                "Failed to connect to the X server. On Wayland, only apps running under XWayland can be managed.",
+            )?;
+            let root = conn
+                .setup()
+                .roots
+                .get(screen_num)
+                .context("The X server has no such screen")?
+                .root;
+            Ok(Self { conn, root })
+        }
+
+        fn atom(&self, name: &str) -> Result<Atom, anyhow::Error> {
+            Ok(self.conn.intern_atom(false, name.as_bytes())?.reply()?.atom)
+        }
+
+        fn windows_property(&self, window: u32, name: &str) -> Result<Vec<u32>, anyhow::Error> {
+            let reply = self
+                .conn
+                .get_property(
+                    false,
+                    window,
+                    self.atom(name)?,
+                    AtomEnum::WINDOW,
+                    0,
+                    u32::MAX,
+                )?
+                .reply()?;
+            Ok(reply
+                .value32()
+                .map(|values| values.collect())
+                .unwrap_or_default())
+        }
+
+        fn text_property(&self, window: u32, name: &str, kind: Atom) -> Option<String> {
+            let reply = self
+                .conn
+                .get_property(false, window, self.atom(name).ok()?, kind, 0, u32::MAX)
+                .ok()?
+                .reply()
+                .ok()?;
+            Some(String::from_utf8_lossy(&reply.value).into_owned()).filter(|text| !text.is_empty())
+        }
+
+        /// Sends a request about `window` to the window manager.
+        fn send(&self, window: u32, message: &str, data: [u32; 5]) -> Result<(), anyhow::Error> {
+            let event = ClientMessageEvent::new(32, window, self.atom(message)?, data);
+            self.conn.send_event(
+                false,
+                self.root,
+                EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY,
+                event,
+            )?;
+            self.conn.flush()?;
+            Ok(())
+        }
+    }
+
+    pub fn list() -> Result<Vec<Window>, anyhow::Error> {
+        let display = Display::connect()?;
+        let utf8_string = display.atom("UTF8_STRING")?;
+        let clients = display.windows_property(display.root, "_NET_CLIENT_LIST")?;
+        if clients.is_empty() {
+            bail!("The window manager doesn't list its windows");
+        }
+        Ok(clients
+            .into_iter()
+            .filter_map(|id| {
+                let title = display
+                    .text_property(id, "_NET_WM_NAME", utf8_string)
+                    .or_else(|| display.text_property(id, "WM_NAME", AtomEnum::STRING.into()))?;
+                // WM_CLASS is the instance name then the class name, each ending in a null byte.
+                let app = display
+                    .text_property(id, "WM_CLASS", AtomEnum::STRING.into())
+                    .and_then(|class| {
+                        class
+                            .split('\0')
+                            .rfind(|part| !part.is_empty())
+                            .map(str::to_string)
+                    });
+                Some(Window {
+                    id: id.into(),
+                    title,
+                    app,
+                })
+            })
+            .collect())
+    }
+
+    pub fn active() -> Result<Option<u64>, anyhow::Error> {
+        let display = Display::connect()?;
+        let active = display.windows_property(display.root, "_NET_ACTIVE_WINDOW")?;
+        Ok(active.first().filter(|&&id| id != 0).map(|&id| id.into()))
+    }
+
+    pub fn focus(id: u64) -> Result<(), anyhow::Error> {
+        Display::connect()?.send(
+            id as u32,
+            "_NET_ACTIVE_WINDOW",
+            [SOURCE_PAGER, CURRENT_TIME, 0, 0, 0],
+        )
+    }
+
+    pub fn minimize(id: u64) -> Result<(), anyhow::Error> {
+        Display::connect()?.send(id as u32, "WM_CHANGE_STATE", [ICONIC_STATE, 0, 0, 0, 0])
+    }
+
+    pub fn close(id: u64) -> Result<(), anyhow::Error> {
+        Display::connect()?.send(
+            id as u32,
+            "_NET_CLOSE_WINDOW",
+            [CURRENT_TIME, SOURCE_PAGER, 0, 0, 0],
+        )
+    }
+}
+
+#[cfg(windows)]
+mod win32 {
+    use anyhow::bail;
+    use windows_sys::Win32::{
+        Foundation::{BOOL, HWND, LPARAM, TRUE},
+        UI::WindowsAndMessaging::{
+            EnumWindows, GetForegroundWindow, GetWindow, GetWindowTextLengthW, GetWindowTextW,
+            IsIconic, IsWindowVisible, PostMessageW, SetForegroundWindow, ShowWindow, GW_OWNER,
+            SW_MINIMIZE, SW_RESTORE, WM_CLOSE,
+        },
+    };
+
+    use super::Window;
+
+    unsafe extern "system" fn collect_window(hwnd: HWND, windows: LPARAM) -> BOOL {
+        (*(windows as *mut Vec<HWND>)).push(hwnd);
+        TRUE
+    }
+
+    fn title(hwnd: HWND) -> Option<String> {
+        // SAFETY: The buffer has room for the title and its null terminator.
+        unsafe {
+            let length = GetWindowTextLengthW(hwnd);
+            if length <= 0 {
+                return None;
+            }
+            let mut buffer = vec![0u16; length as usize + 1];
+            let copied = GetWindowTextW(hwnd, buffer.as_mut_ptr(), buffer.len() as i32);
+            Some(String::from_utf16_lossy(&buffer[..copied.max(0) as usize]))
+        }
+    }
+
+    pub fn list() -> Result<Vec<Window>, anyhow::Error> {
+        let mut handles: Vec<HWND> = Vec::new();
+        // SAFETY: The callback only pushes onto `handles`, which outlives the call.
+        unsafe {
+            EnumWindows(
+                Some(collect_window),
+                &mut handles as *mut Vec<HWND> as LPARAM,
+            );
+        }
+        Ok(handles
+            .into_iter()
+            // Taskbar windows are visible and have no owner. Owned windows are dialogs and the like.
+            .filter(|&hwnd| unsafe { IsWindowVisible(hwnd) != 0 && GetWindow(hwnd, GW_OWNER) == 0 })
+            .filter_map(|hwnd| {
+                Some(Window {
+                    id: hwnd as u64,
+                    title: title(hwnd)?,
+                    app: None,
+                })
+            })
+            .collect())
+    }
+
+    pub fn active() -> Result<Option<u64>, anyhow::Error> {
+        // SAFETY: Takes no arguments and only returns a window handle.
+        let hwnd = unsafe { GetForegroundWindow() };
+        Ok((hwnd != 0).then_some(hwnd as u64))
+    }
+
+    pub fn focus(id: u64) -> Result<(), anyhow::Error> {
+        let hwnd = id as HWND;
+        // SAFETY: Windows checks the handle, and these fail harmlessly if the window has closed.
+        unsafe {
+            if IsIconic(hwnd) != 0 {
+                ShowWindow(hwnd, SW_RESTORE);
+            }
+            if SetForegroundWindow(hwnd) == 0 {
+                bail!("Windows didn't let the window be brought to the front");
+            }
+        }
+        Ok(())
+    }
+
+    pub fn minimize(id: u64) -> Result<(), anyhow::Error> {
+        // SAFETY: Windows checks the handle.
+        unsafe {
+            ShowWindow(id as HWND, SW_MINIMIZE);
+        }
+        Ok(())
+    }
+
+    pub fn close(id: u64) -> Result<(), anyhow::Error> {
+        // SAFETY: Windows checks the handle.
+        if unsafe { PostMessageW(id as HWND, WM_CLOSE, 0, 0) } == 0 {
+            bail!("Failed to ask the window to close");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_blank_query_finds_nothing() {
+        assert!(find("", 0).is_err());
+        assert!(find("  \t", apps::CONTAINS_SCORE).is_err());
+    }
+}
@@ -0,0 +1,221 @@
+//! Reading the text shown in the focused window for the summarize_active_window tool.
+//! Linux asks applications over AT-SPI and Windows uses UI Automation, the same ways screen
+//! readers get at it. Applications that don't support these only give their window's title.
+
+use tracing::warn;
+
+use crate::window_control::{self, Window};
+
+/// The most characters of a window's text given to the AI.
+pub const MAX_TEXT_CHARS: usize = 20_000;
+
+/// The focused window, and the text in it if any could be read.
+pub fn read_active() -> Result<(Window, Option<String>), anyhow::Error> {
+    let window = window_control::active()?;
+    let text = match read_text(&window) {
+        Ok(text) => {
+            // Lines of only whitespace are left behind by layout and images.
+            let text = text
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .collect::<Vec<_>>()
+                .join("\n");
+            (!text.is_empty()).then_some(text)
+        }
+        Err(err) => {
+            warn!("Failed to read the text of \"{}\": {:#}", window.title, err);
+            None
+        }
+    };
+    Ok((window, text))
+}
+
+fn read_text(window: &Window) -> Result<String, anyhow::Error> {
+    #[cfg(target_os = "linux")]
+    {
+        let _ = window;
+        return atspi::active_window_text();
+    }
+
+    #[cfg(windows)]
+    return uia::window_text(window.id());
+
+    #[allow(unreachable_code)]
+    {
+        let _ = window;
+        Err(anyhow::anyhow!(
+            "Reading window text isn't supported on this system"
+        ))
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod atspi {
+    use anyhow::{bail, Context};
+    use std::time::{Duration, Instant};
+    use zbus::{
+        blocking::{connection, Connection},
+        zvariant::OwnedObjectPath,
+    };
+
+    use super::MAX_TEXT_CHARS;
+
+    const ACCESSIBLE: &str = "org.a11y.atspi.Accessible";
+    const TEXT: &str = "org.a11y.atspi.Text";
+    /// The bit for the active state, which the focused window has.
+    const STATE_ACTIVE: u32 = 1 << 1;
+    /// Web pages can have a huge number of elements, so reading stops after this many, or this long.
+    const MAX_NODES: usize = 5_000;
+    const MAX_READ_TIME: Duration = Duration::from_secs(5);
+    /// Stands in for a child element, like a link, whose text is read separately.
+    const EMBEDDED_OBJECT: char = '\u{fffc}';
+
+    type Node = (String, OwnedObjectPath);
+
+    /// Accessibility has its own bus, which the session bus gives the address of.
+    fn connect() -> Result<Connection, anyhow::Error> {
+        let session = Connection::session().context("Failed to connect to D-Bus")?;
+        let address: String = session
+            .call_method(
+                Some("org.a11y.Bus"),
+                "/org/a11y/bus",
+                Some("org.a11y.Bus"),
+                "GetAddress",
+                &(),
+            )
+            .context("Accessibility isn't running")?
+            .body()
+            .deserialize()?;
+        Ok(connection::Builder::address(address.as_str())?.build()?)
+    }
+
+    fn call<R>(
+        connection: &Connection,
+        (destination, path): &Node,
+        interface: &str,
+        method: &str,
+        args: &(impl serde::Serialize + zbus::zvariant::DynamicType),
+    ) -> Result<R, anyhow::Error>
+    where
+        R: for<'d> serde::Deserialize<'d> + zbus::zvariant::Type,
+    {
+        Ok(connection
+            .call_method(
+                Some(destination.as_str()),
+                path.as_str(),
+                Some(interface),
+                method,
+                args,
+            )?
+            .body()
+            .deserialize()?)
+    }
+
+    fn children(connection: &Connection, node: &Node) -> Result<Vec<Node>, anyhow::Error> {
+        call(connection, node, ACCESSIBLE, "GetChildren", &())
+    }
+
+    /// Finds the active window among every application's windows.
+    fn active_window(connection: &Connection) -> Result<Node, anyhow::Error> {
+        let registry = (
+            "org.a11y.atspi.Registry".to_string(),
+            OwnedObjectPath::try_from("/org/a11y/atspi/accessible/root")?,
+        );
+        for application in children(connection, &registry)? {
+            // Applications that are busy or gone are skipped.
+            let Ok(windows) = children(connection, &application) else {
+                continue;
+            };
+            for window in windows {
+                let state: Vec<u32> =
+                    call(connection, &window, ACCESSIBLE, "GetState", &()).unwrap_or_default();
+                if state.first().is_some_and(|bits| bits & STATE_ACTIVE != 0) {
+                    return Ok(window);
+                }
+            }
+        }
+        bail!("The focused window's application doesn't support accessibility")
+    }
+
+    /// Reads the text of every element in the active window, in order.
+    pub fn active_window_text() -> Result<String, anyhow::Error> {
+        let connection = connect()?;
+        let window = active_window(&connection)?;
+
+        let start = Instant::now();
+        let mut text = String::new();
+        let mut nodes = 0;
+        // Depth first, so text comes out in the order it's shown.
+        let mut stack: Vec<Node> = vec![window];
+        while let Some(node) = stack.pop() {
+            nodes += 1;
+            if nodes > MAX_NODES
+                || start.elapsed() > MAX_READ_TIME
+                || text.len() > MAX_TEXT_CHARS * 4
+            {
+                break;
+            }
+            let interfaces: Vec<String> =
+                call(&connection, &node, ACCESSIBLE, "GetInterfaces", &()).unwrap_or_default();
+            if interfaces.iter().any(|interface| interface == TEXT) {
+                if let Ok(node_text) =
+                    call::<String>(&connection, &node, TEXT, "GetText", &(0i32, -1i32))
+                {
+                    let node_text: String = node_text
+                        .chars()
+                        .filter(|&c| c != EMBEDDED_OBJECT)
+                        .collect();
+                    if !node_text.trim().is_empty() {
+                        text.push_str(node_text.trim());
+                        text.push('\n');
+                    }
+                }
+            }
+            if let Ok(children) = children(&connection, &node) {
+                stack.extend(children.into_iter().rev());
+            }
+        }
+        Ok(text)
+    }
+}
+
+#[cfg(windows)]
+mod uia {
+    use anyhow::{bail, Context};
+    use std::process::Command;
+
+    /// Reads the window's text with .NET's UI Automation client. Documents give their whole text,
+    /// and other elements their value or name.
+    pub fn window_text(hwnd: u64) -> Result<String, anyhow::Error> {
+        let script = format!(
+            "[Console]::OutputEncoding = [Text.Encoding]::UTF8; \
+             Add-Type -AssemblyName UIAutomationClient, UIAutomationTypes; \
+             $window = [Windows.Automation.AutomationElement]::FromHandle([IntPtr]{}); \
+             $elements = $window.FindAll([Windows.Automation.TreeScope]::Descendants, [Windows.Automation.Condition]::TrueCondition); \
+             $documents = 0; \
+             foreach ($element in $elements) {{ \
+               $pattern = $null; \
+               if ($element.TryGetCurrentPattern([Windows.Automation.TextPattern]::Pattern, [ref]$pattern)) {{ \
+                 $pattern.DocumentRange.GetText(-1); $documents++; continue \
+               }} \
+               if ($documents -eq 0) {{ \
+                 if ($element.TryGetCurrentPattern([Windows.Automation.ValuePattern]::Pattern, [ref]$pattern)) {{ $pattern.Current.Value; continue }} \
+                 if ($element.Current.ControlType -eq [Windows.Automation.ControlType]::Text) {{ $element.Current.Name }} \
+               }} \
+             }}",
+            hwnd
+        );
+        let output = Command::new("powershell")
+            .args(["-NoProfile", "-NonInteractive", "-Command", &script])
+            .output()
+            .context("Failed to run PowerShell")?;
+        if !output.status.success() {
+            bail!(
+                "PowerShell failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+}